@@ -17,6 +17,18 @@ pub struct Account {
     pub is_active: bool,
     pub created_at: i64,
     pub last_synced_at: Option<i64>,
+    /// Pinned server certificate fingerprint, for servers with self-signed certificates
+    #[serde(default)]
+    pub cert_fingerprint: Option<String>,
+    /// Color shown next to the account, as `#rrggbb`
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Position in the account switcher and unified inbox, lowest first
+    #[serde(default)]
+    pub sort_order: i64,
+    /// Short custom label, e.g. "Work", shown instead of the address where space is tight
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 impl Account {
@@ -46,6 +58,10 @@ impl Account {
             is_active: true,
             created_at: chrono::Utc::now().timestamp(),
             last_synced_at: None,
+            cert_fingerprint: None,
+            color: None,
+            sort_order: 0,
+            label: None,
         }
     }
 
@@ -60,3 +76,92 @@ impl Account {
         }
     }
 }
+
+/// Changes to an account's display metadata. Fields left out are unchanged; an empty
+/// color or label clears it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountPatch {
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub sort_order: Option<i64>,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl AccountPatch {
+    /// Validate the patch and apply it to `account`
+    pub fn apply(self, account: &mut Account) -> Result<(), String> {
+        if let Some(display_name) = self.display_name {
+            let display_name = display_name.trim();
+            if display_name.is_empty() {
+                return Err("Account name can't be empty".to_string());
+            }
+            account.display_name = display_name.to_string();
+        }
+        if let Some(color) = self.color {
+            let color = color.trim();
+            account.color = if color.is_empty() {
+                None
+            } else if is_hex_color(color) {
+                Some(color.to_ascii_lowercase())
+            } else {
+                return Err(format!("Invalid color '{}', expected #rrggbb", color));
+            };
+        }
+        if let Some(sort_order) = self.sort_order {
+            account.sort_order = sort_order;
+        }
+        if let Some(label) = self.label {
+            let label = label.trim();
+            account.label = (!label.is_empty()).then(|| label.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Whether `color` is a `#rrggbb` hex color
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// An address an account can send as: an alias, plus-address or custom domain that
+/// delivers to the same mailbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    pub id: String,
+    pub account_id: String,
+    pub address: String,
+    pub display_name: Option<String>,
+    /// HTML appended to messages sent as this identity
+    pub signature: Option<String>,
+    pub created_at: i64,
+}
+
+impl Identity {
+    pub fn new(
+        account_id: String,
+        address: String,
+        display_name: Option<String>,
+        signature: Option<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            account_id,
+            address,
+            display_name,
+            signature,
+            created_at: chrono::Utc::now().timestamp(),
+        }
+    }
+
+    /// `From` header value, e.g. `"Ada Lovelace" <ada+work@example.com>`
+    pub fn mailbox(&self) -> String {
+        match self.display_name.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => format!("\"{}\" <{}>", name.replace('"', ""), self.address),
+            None => self.address.clone(),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Loopback server that receives the OAuth redirect
+//!
+//! It listens on 127.0.0.1, on a free port unless the settings fix one, for as long as the
+//! user might take to sign in. Only a request to `/callback` carrying the flow's `state`
+//! ends the wait; stray requests (a favicon, another tab) and forged redirects are
+//! answered with a 404 and ignored. Providers accept any port on a loopback redirect URI
+//! for desktop apps, so nothing needs to be registered per port.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tracing::{debug, info};
+
+/// How long the user has to finish signing in
+pub const CALLBACK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How long a connection may take to send its request line
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CALLBACK_PATH: &str = "/callback";
+
+const SUCCESS_PAGE: &str = "<html><body>\
+    <h1>Authentication Successful!</h1>\
+    <p>You can close this window and return to Inboxed.</p>\
+    <script>window.close();</script>\
+    </body></html>";
+
+const FAILURE_PAGE: &str = "<html><body>\
+    <h1>Authentication Failed</h1>\
+    <p>You can close this window and try again from Inboxed.</p>\
+    </body></html>";
+
+/// What a request to the callback server carried
+#[derive(Debug, PartialEq)]
+pub enum Callback {
+    /// The provider redirected with an authorization code
+    Code(String),
+    /// The provider redirected with an error, e.g. because the user declined
+    Error(String),
+    /// Not the redirect for this flow
+    Ignored,
+}
+
+/// Parse the request line of a request to the server, e.g.
+/// `GET /callback?code=...&state=... HTTP/1.1`, for the flow started with `state`
+pub fn parse_request_line(request_line: &str, state: &str) -> Callback {
+    let mut parts = request_line.split_whitespace();
+    let (Some("GET"), Some(target)) = (parts.next(), parts.next()) else {
+        return Callback::Ignored;
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != CALLBACK_PATH {
+        return Callback::Ignored;
+    }
+
+    let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    if params.get("state").map(String::as_str) != Some(state) {
+        return Callback::Ignored;
+    }
+    if let Some(error) = params.get("error") {
+        return Callback::Error(match (error.as_str(), params.get("error_description")) {
+            ("access_denied", _) => "Sign-in was cancelled".to_string(),
+            (_, Some(description)) => format!("Sign-in failed: {}", description),
+            (error, None) => format!("Sign-in failed: {}", error),
+        });
+    }
+    match params.get("code") {
+        Some(code) if !code.is_empty() => Callback::Code(code.clone()),
+        _ => Callback::Error("No authorization code in callback".to_string()),
+    }
+}
+
+/// Callback server bound to its port, ready to be spawned
+pub struct CallbackServer {
+    listener: std::net::TcpListener,
+    port: u16,
+}
+
+impl CallbackServer {
+    /// Bind to `port` on the loopback interface, or to a free port when it's 0
+    pub fn bind(port: u16) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port)).with_context(|| {
+            format!(
+                "Port {} is in use; choose another OAuth callback port in the settings",
+                port
+            )
+        })?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        Ok(Self { listener, port })
+    }
+
+    /// Redirect URI the provider is given for this server
+    pub fn redirect_uri(&self) -> String {
+        format!("http://localhost:{}{}", self.port, CALLBACK_PATH)
+    }
+
+    /// Serve until the redirect for `state` arrives, then send its authorization code to
+    /// `tx`. Gives up after `CALLBACK_TIMEOUT`, or as soon as `tx`'s receiver is dropped
+    /// because another sign-in started.
+    pub fn spawn(self, state: String, mut tx: oneshot::Sender<Result<String>>) {
+        tokio::spawn(async move {
+            let port = self.port;
+            let result = tokio::select! {
+                result = tokio::time::timeout(CALLBACK_TIMEOUT, self.serve(&state)) => {
+                    result.unwrap_or_else(|_| Err(anyhow!("Timed out waiting for sign-in")))
+                }
+                _ = tx.closed() => {
+                    info!("OAuth callback server on port {} replaced", port);
+                    return;
+                }
+            };
+            let _ = tx.send(result);
+        });
+    }
+
+    async fn serve(self, state: &str) -> Result<String> {
+        let listener = TcpListener::from_std(self.listener)?;
+        info!("Waiting for OAuth callback on port {}", self.port);
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            let mut request_line = String::new();
+            let read = {
+                let mut reader = BufReader::new(&mut stream);
+                tokio::time::timeout(REQUEST_TIMEOUT, reader.read_line(&mut request_line)).await
+            };
+            if !matches!(read, Ok(Ok(_))) {
+                continue;
+            }
+
+            let callback = parse_request_line(&request_line, state);
+            let (status, body) = match callback {
+                Callback::Code(_) => ("200 OK", SUCCESS_PAGE),
+                Callback::Error(_) => ("200 OK", FAILURE_PAGE),
+                Callback::Ignored => ("404 Not Found", ""),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+
+            match callback {
+                Callback::Code(code) => return Ok(code),
+                Callback::Error(message) => return Err(anyhow!(message)),
+                Callback::Ignored => debug!("Ignored request to the OAuth callback server"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line() {
+        let state = "s3cr3t";
+        assert_eq!(
+            parse_request_line("GET /callback?code=4%2F0Ab&state=s3cr3t HTTP/1.1", state),
+            Callback::Code("4/0Ab".to_string())
+        );
+        assert_eq!(
+            parse_request_line(
+                "GET /callback?error=access_denied&state=s3cr3t HTTP/1.1",
+                state
+            ),
+            Callback::Error("Sign-in was cancelled".to_string())
+        );
+        assert_eq!(
+            parse_request_line(
+                "GET /callback?error=invalid_scope&error_description=Bad+scope&state=s3cr3t HTTP/1.1",
+                state
+            ),
+            Callback::Error("Sign-in failed: Bad scope".to_string())
+        );
+
+        // Forged or stale redirects and unrelated requests don't end the wait
+        assert_eq!(
+            parse_request_line("GET /callback?code=abc&state=other HTTP/1.1", state),
+            Callback::Ignored
+        );
+        assert_eq!(
+            parse_request_line("GET /callback?code=abc HTTP/1.1", state),
+            Callback::Ignored
+        );
+        assert_eq!(
+            parse_request_line("GET /favicon.ico HTTP/1.1", state),
+            Callback::Ignored
+        );
+        assert_eq!(parse_request_line("", state), Callback::Ignored);
+    }
+}
@@ -1,10 +1,12 @@
 pub mod account;
+pub mod callback;
 pub mod oauth;
 pub mod storage;
 
-pub use account::Account;
+pub use account::{Account, Identity};
 pub use oauth::{
-    handle_oauth_callback, refresh_access_token, refresh_access_token_for_provider,
-    start_oauth_flow, start_oauth_flow_for_provider,
+    complete_device_flow, handle_oauth_callback, refresh_access_token,
+    refresh_access_token_for_provider, start_device_flow, start_oauth_flow,
+    start_oauth_flow_for_provider, start_reauth_flow, DeviceCodePrompt,
 };
 pub use storage::{clear_tokens, get_tokens, has_valid_tokens, TokenData};
@@ -2,8 +2,10 @@ use anyhow::{Context, Result};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
 use oauth2::{
-    basic::BasicClient, reqwest::async_http_client, AuthUrl, AuthorizationCode, ClientId,
-    ClientSecret, CsrfToken, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    basic::{BasicClient, BasicTokenResponse},
+    reqwest::async_http_client,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, DeviceAuthorizationUrl,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, StandardDeviceAuthorizationResponse,
     TokenResponse, TokenUrl,
 };
 use rand::Rng;
@@ -11,18 +13,20 @@ use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 use tokio::sync::oneshot;
 
-use super::storage::{store_account_tokens, store_tokens, TokenData};
+use super::callback::CallbackServer;
+use super::storage::{get_account_tokens, store_account_tokens, store_tokens, TokenData};
+use crate::settings;
 
 // ========== OAuth Provider Configurations ==========
 
-const REDIRECT_URI: &str = "http://localhost:3000/callback";
-
 /// Provider-specific OAuth configuration
 #[derive(Debug, Clone)]
 pub struct OAuthProviderConfig {
     pub auth_url: String,
     pub token_url: String,
     pub scopes: Vec<String>,
+    /// Endpoint of the device authorization grant, if the provider allows it for mail
+    pub device_auth_url: Option<String>,
     pub client_id_env: &'static str,
     pub client_secret_env: &'static str,
 }
@@ -34,6 +38,8 @@ pub fn google_oauth_config() -> OAuthProviderConfig {
         token_url: "https://oauth2.googleapis.com/token".to_string(),
         // Use the full mail scope for IMAP access (not gmail.modify)
         scopes: vec!["https://mail.google.com/".to_string()],
+        // Google's device flow only allows a few scopes, and mail isn't one of them
+        device_auth_url: None,
         client_id_env: "GOOGLE_CLIENT_ID",
         client_secret_env: "GOOGLE_CLIENT_SECRET",
     }
@@ -49,6 +55,9 @@ pub fn microsoft_oauth_config() -> OAuthProviderConfig {
             "https://outlook.office365.com/SMTP.Send".to_string(),
             "offline_access".to_string(),
         ],
+        device_auth_url: Some(
+            "https://login.microsoftonline.com/common/oauth2/v2.0/devicecode".to_string(),
+        ),
         client_id_env: "MICROSOFT_CLIENT_ID",
         client_secret_env: "MICROSOFT_CLIENT_SECRET",
     }
@@ -99,7 +108,10 @@ fn get_client_secret() -> Option<String> {
 pub struct OAuthState {
     pub pkce_verifier: PkceCodeVerifier,
     pub csrf_token: CsrfToken,
+    /// Receives the authorization code from the callback server
     pub callback_receiver: Option<oneshot::Receiver<Result<String>>>,
+    /// Redirect URI the flow was started with, which the code exchange must repeat
+    pub redirect_uri: String,
     pub account_id: Option<String>,
     pub provider: String,
 }
@@ -130,14 +142,21 @@ fn create_oauth_client_for_provider(config: &OAuthProviderConfig) -> Result<Basi
         client_secret.map(ClientSecret::new),
         AuthUrl::new(config.auth_url.clone()).context("Failed to create auth URL")?,
         Some(TokenUrl::new(config.token_url.clone()).context("Failed to create token URL")?),
-    )
-    .set_redirect_uri(
-        RedirectUrl::new(REDIRECT_URI.to_string()).context("Failed to create redirect URL")?,
     );
 
     Ok(client)
 }
 
+/// Client for the sign-in flow whose callback server listens at `redirect_uri`
+fn create_oauth_client_with_redirect(
+    config: &OAuthProviderConfig,
+    redirect_uri: &str,
+) -> Result<BasicClient> {
+    Ok(create_oauth_client_for_provider(config)?.set_redirect_uri(
+        RedirectUrl::new(redirect_uri.to_string()).context("Failed to create redirect URL")?,
+    ))
+}
+
 fn create_oauth_client() -> Result<BasicClient> {
     create_oauth_client_for_provider(&google_oauth_config())
 }
@@ -146,8 +165,24 @@ fn create_oauth_client() -> Result<BasicClient> {
 
 /// Start OAuth flow for a specific provider and optional account
 pub fn start_oauth_flow_for_provider(provider: &str, account_id: Option<&str>) -> Result<String> {
+    start_oauth_flow_with_hint(provider, account_id, None)
+}
+
+/// Start OAuth flow to sign an existing account in again. The provider's sign-in page
+/// has `email` preselected and asks for consent, so a fresh refresh token is issued.
+pub fn start_reauth_flow(provider: &str, account_id: &str, email: &str) -> Result<String> {
+    start_oauth_flow_with_hint(provider, Some(account_id), Some(email))
+}
+
+fn start_oauth_flow_with_hint(
+    provider: &str,
+    account_id: Option<&str>,
+    login_hint: Option<&str>,
+) -> Result<String> {
     let config = get_provider_config(provider);
-    let client = create_oauth_client_for_provider(&config)?;
+    let server = CallbackServer::bind(settings::current().auth.oauth_callback_port)?;
+    let redirect_uri = server.redirect_uri();
+    let client = create_oauth_client_with_redirect(&config, &redirect_uri)?;
 
     let (pkce_verifier, pkce_challenge) = generate_pkce();
 
@@ -158,22 +193,28 @@ pub fn start_oauth_flow_for_provider(provider: &str, account_id: Option<&str>) -
     for scope in &config.scopes {
         auth_request = auth_request.add_scope(Scope::new(scope.clone()));
     }
+    if let Some(login_hint) = login_hint {
+        auth_request = auth_request
+            .add_extra_param("login_hint", login_hint)
+            .add_extra_param("prompt", "consent");
+    }
 
     let (authorize_url, csrf_token) = auth_request.url();
 
     let (tx, rx) = oneshot::channel();
+    server.spawn(csrf_token.secret().clone(), tx);
 
+    // Replacing an unfinished flow drops its receiver, which stops its server
     let mut state = OAUTH_STATE.lock().unwrap();
     *state = Some(OAuthState {
         pkce_verifier,
         csrf_token,
         callback_receiver: Some(rx),
+        redirect_uri,
         account_id: account_id.map(|s| s.to_string()),
         provider: provider.to_string(),
     });
 
-    start_callback_server(tx);
-
     Ok(authorize_url.to_string())
 }
 
@@ -182,87 +223,48 @@ pub fn start_oauth_flow() -> Result<String> {
     start_oauth_flow_for_provider("gmail", None)
 }
 
-// ========== Callback Server ==========
-
-fn start_callback_server(tx: oneshot::Sender<Result<String>>) {
-    use std::io::{BufRead, BufReader, Write};
-    use std::net::TcpListener;
-
-    tokio::spawn(async move {
-        let listener = match TcpListener::bind("127.0.0.1:3000") {
-            Ok(l) => l,
-            Err(e) => {
-                let _ = tx.send(Err(anyhow::anyhow!("Failed to bind to port 3000: {}", e)));
-                return;
-            }
-        };
-
-        if let Ok((mut stream, _)) = listener.accept() {
-            let mut reader = BufReader::new(&stream);
-            let mut request_line = String::new();
-            if reader.read_line(&mut request_line).is_ok() {
-                if let Some(query_start) = request_line.find("?") {
-                    if let Some(query_end) = request_line.find(" HTTP/") {
-                        let query = &request_line[query_start + 1..query_end];
-
-                        let response = "HTTP/1.1 200 OK\r\n\r\n\
-                            <html><body>\
-                            <h1>Authentication Successful!</h1>\
-                            <p>You can close this window and return to Inboxed.</p>\
-                            <script>window.close();</script>\
-                            </body></html>";
-                        let _ = stream.write_all(response.as_bytes());
-
-                        let _ = tx.send(Ok(query.to_string()));
-                        return;
-                    }
-                }
-            }
-            let _ = tx.send(Err(anyhow::anyhow!("Failed to parse callback request")));
-        }
-    });
-}
-
 // ========== Token Exchange ==========
 
 /// Handle OAuth callback — exchanges code for tokens, stores them
 pub async fn handle_oauth_callback() -> Result<TokenData> {
-    let (pkce_verifier, callback_receiver, account_id, provider) = {
+    let (pkce_verifier, callback_receiver, redirect_uri, account_id, provider) = {
         let mut state_lock = OAUTH_STATE.lock().unwrap();
         let state = state_lock.take().context("No OAuth flow in progress")?;
 
         (
             state.pkce_verifier,
             state.callback_receiver,
+            state.redirect_uri,
             state.account_id,
             state.provider,
         )
     };
 
-    let query_string = callback_receiver
+    // The callback server has checked the state and turned error redirects into errors
+    let code = callback_receiver
         .context("No callback receiver")?
         .await
-        .context("Failed to receive callback")??;
-
-    let params: std::collections::HashMap<_, _> =
-        url::form_urlencoded::parse(query_string.as_bytes())
-            .into_owned()
-            .collect();
-
-    let code = params
-        .get("code")
-        .context("No authorization code in callback")?;
+        .context("Sign-in was abandoned")??;
 
     let config = get_provider_config(&provider);
-    let client = create_oauth_client_for_provider(&config)?;
+    let client = create_oauth_client_with_redirect(&config, &redirect_uri)?;
 
     let token_response = client
-        .exchange_code(AuthorizationCode::new(code.clone()))
+        .exchange_code(AuthorizationCode::new(code))
         .set_pkce_verifier(pkce_verifier)
         .request_async(async_http_client)
         .await
         .context("Failed to exchange authorization code for tokens")?;
 
+    store_sign_in_tokens(account_id.as_deref(), &token_response)
+}
+
+/// Store the tokens a completed sign-in returned: per-account if `account_id` is set,
+/// otherwise legacy
+fn store_sign_in_tokens(
+    account_id: Option<&str>,
+    token_response: &BasicTokenResponse,
+) -> Result<TokenData> {
     let expires_at = Utc::now()
         + Duration::seconds(
             token_response
@@ -271,7 +273,7 @@ pub async fn handle_oauth_callback() -> Result<TokenData> {
                 .unwrap_or(3600),
         );
 
-    let token_data = TokenData {
+    let mut token_data = TokenData {
         access_token: token_response.access_token().secret().clone(),
         refresh_token: token_response
             .refresh_token()
@@ -279,8 +281,14 @@ pub async fn handle_oauth_callback() -> Result<TokenData> {
         expires_at,
     };
 
-    // Store tokens: per-account if account_id is set, otherwise legacy
-    if let Some(ref aid) = account_id {
+    if let Some(aid) = account_id {
+        // Providers may not issue a new refresh token when an account signs in again;
+        // keep the stored one
+        if token_data.refresh_token.is_none() {
+            token_data.refresh_token = get_account_tokens(aid)
+                .ok()
+                .and_then(|tokens| tokens.refresh_token);
+        }
         store_account_tokens(aid, &token_data)?;
     } else {
         store_tokens(&token_data)?;
@@ -289,6 +297,94 @@ pub async fn handle_oauth_callback() -> Result<TokenData> {
     Ok(token_data)
 }
 
+// ========== Device Code Flow ==========
+
+/// What the user needs to sign in on another page or device when no local redirect
+/// can be received
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodePrompt {
+    /// Code to enter on the verification page
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Verification page with the code filled in, if the provider offers one
+    pub verification_uri_complete: Option<String>,
+    pub expires_in_secs: u64,
+}
+
+struct DeviceFlowState {
+    details: StandardDeviceAuthorizationResponse,
+    account_id: Option<String>,
+    provider: String,
+}
+
+lazy_static::lazy_static! {
+    static ref DEVICE_FLOW_STATE: Mutex<Option<DeviceFlowState>> = Mutex::new(None);
+}
+
+/// Start the device authorization grant: the provider hands out a code for the user to
+/// enter on its verification page, while `complete_device_flow` polls for the tokens.
+/// Needs no localhost listener, for systems where one is blocked.
+pub async fn start_device_flow(
+    provider: &str,
+    account_id: Option<&str>,
+) -> Result<DeviceCodePrompt> {
+    let config = get_provider_config(provider);
+    let device_auth_url = config
+        .device_auth_url
+        .clone()
+        .context("This provider doesn't support signing in with a code")?;
+    let client = create_oauth_client_for_provider(&config)?.set_device_authorization_url(
+        DeviceAuthorizationUrl::new(device_auth_url)
+            .context("Failed to create device authorization URL")?,
+    );
+
+    let mut request = client.exchange_device_code()?;
+    for scope in &config.scopes {
+        request = request.add_scope(Scope::new(scope.clone()));
+    }
+    let details: StandardDeviceAuthorizationResponse = request
+        .request_async(async_http_client)
+        .await
+        .context("Failed to request a device code")?;
+
+    let prompt = DeviceCodePrompt {
+        user_code: details.user_code().secret().clone(),
+        verification_uri: details.verification_uri().url().to_string(),
+        verification_uri_complete: details
+            .verification_uri_complete()
+            .map(|uri| uri.secret().clone()),
+        expires_in_secs: details.expires_in().as_secs(),
+    };
+
+    *DEVICE_FLOW_STATE.lock().unwrap() = Some(DeviceFlowState {
+        details,
+        account_id: account_id.map(|s| s.to_string()),
+        provider: provider.to_string(),
+    });
+
+    Ok(prompt)
+}
+
+/// Poll until the user has entered the code from `start_device_flow`, then store the
+/// tokens. Fails when the code expires or the user declines.
+pub async fn complete_device_flow() -> Result<TokenData> {
+    let state = DEVICE_FLOW_STATE
+        .lock()
+        .unwrap()
+        .take()
+        .context("No device sign-in in progress")?;
+
+    let config = get_provider_config(&state.provider);
+    let client = create_oauth_client_for_provider(&config)?;
+    let token_response = client
+        .exchange_device_access_token(&state.details)
+        .request_async(async_http_client, tokio::time::sleep, None)
+        .await
+        .context("Device sign-in failed")?;
+
+    store_sign_in_tokens(state.account_id.as_deref(), &token_response)
+}
+
 /// Refresh access token (parameterized by provider and optional account)
 pub async fn refresh_access_token(refresh_token: &str) -> Result<TokenData> {
     refresh_access_token_for_provider(refresh_token, "gmail", None).await
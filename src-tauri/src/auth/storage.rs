@@ -13,11 +13,24 @@ const EXPIRY_KEY: &str = "gmail_token_expiry";
 // Dev mode: use file storage to avoid keychain prompts
 const USE_FILE_STORAGE: bool = cfg!(debug_assertions);
 
+/// Keychain service of the active profile
+fn service_name() -> String {
+    crate::paths::keychain_service(SERVICE_NAME)
+}
+
+/// Directory of the token files: the data directory for a named profile or a portable
+/// install, otherwise a stable location in the user's home directory
+fn token_dir() -> Option<PathBuf> {
+    if crate::paths::profile().is_some() || crate::paths::is_portable() {
+        return crate::paths::data_dir().ok();
+    }
+    std::env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(".inboxed"))
+}
+
 fn get_token_file_path() -> PathBuf {
-    // Use a stable location in the user's home directory instead of temp
-    if let Ok(home) = std::env::var("HOME") {
-        let mut path = PathBuf::from(home);
-        path.push(".inboxed");
+    if let Some(mut path) = token_dir() {
         // Create directory if it doesn't exist
         let _ = std::fs::create_dir_all(&path);
         path.push("tokens.json");
@@ -46,7 +59,7 @@ pub struct TokenData {
 
 /// Store access token in system keychain
 pub fn store_access_token(token: &str) -> Result<()> {
-    let entry = Entry::new(SERVICE_NAME, ACCESS_TOKEN_KEY)
+    let entry = Entry::new(&service_name(), ACCESS_TOKEN_KEY)
         .context("Failed to create keychain entry for access token")?;
     entry
         .set_password(token)
@@ -56,7 +69,7 @@ pub fn store_access_token(token: &str) -> Result<()> {
 
 /// Store refresh token in system keychain
 pub fn store_refresh_token(token: &str) -> Result<()> {
-    let entry = Entry::new(SERVICE_NAME, REFRESH_TOKEN_KEY)
+    let entry = Entry::new(&service_name(), REFRESH_TOKEN_KEY)
         .context("Failed to create keychain entry for refresh token")?;
     entry
         .set_password(token)
@@ -66,7 +79,7 @@ pub fn store_refresh_token(token: &str) -> Result<()> {
 
 /// Store token expiry time
 pub fn store_token_expiry(expires_at: DateTime<Utc>) -> Result<()> {
-    let entry = Entry::new(SERVICE_NAME, EXPIRY_KEY)
+    let entry = Entry::new(&service_name(), EXPIRY_KEY)
         .context("Failed to create keychain entry for token expiry")?;
     let expiry_str = expires_at.to_rfc3339();
     entry
@@ -100,7 +113,7 @@ pub fn store_tokens(token_data: &TokenData) -> Result<()> {
 
 /// Retrieve access token from keychain
 pub fn get_access_token() -> Result<String> {
-    let entry = Entry::new(SERVICE_NAME, ACCESS_TOKEN_KEY)
+    let entry = Entry::new(&service_name(), ACCESS_TOKEN_KEY)
         .context("Failed to create keychain entry for access token")?;
     entry
         .get_password()
@@ -109,7 +122,7 @@ pub fn get_access_token() -> Result<String> {
 
 /// Retrieve refresh token from keychain
 pub fn get_refresh_token() -> Result<String> {
-    let entry = Entry::new(SERVICE_NAME, REFRESH_TOKEN_KEY)
+    let entry = Entry::new(&service_name(), REFRESH_TOKEN_KEY)
         .context("Failed to create keychain entry for refresh token")?;
     entry
         .get_password()
@@ -118,7 +131,7 @@ pub fn get_refresh_token() -> Result<String> {
 
 /// Retrieve token expiry time
 pub fn get_token_expiry() -> Result<DateTime<Utc>> {
-    let entry = Entry::new(SERVICE_NAME, EXPIRY_KEY)
+    let entry = Entry::new(&service_name(), EXPIRY_KEY)
         .context("Failed to create keychain entry for token expiry")?;
     let expiry_str = entry
         .get_password()
@@ -179,11 +192,11 @@ pub fn clear_tokens() -> Result<()> {
         let _ = fs::remove_file(get_token_file_path());
     } else {
         // Production: clear keychain
-        let _ = Entry::new(SERVICE_NAME, ACCESS_TOKEN_KEY)
+        let _ = Entry::new(&service_name(), ACCESS_TOKEN_KEY)
             .and_then(|e| e.delete_credential());
-        let _ = Entry::new(SERVICE_NAME, REFRESH_TOKEN_KEY)
+        let _ = Entry::new(&service_name(), REFRESH_TOKEN_KEY)
             .and_then(|e| e.delete_credential());
-        let _ = Entry::new(SERVICE_NAME, EXPIRY_KEY)
+        let _ = Entry::new(&service_name(), EXPIRY_KEY)
             .and_then(|e| e.delete_credential());
     }
     Ok(())
@@ -205,9 +218,7 @@ struct AccountTokens {
 }
 
 fn get_multi_account_file_path() -> PathBuf {
-    if let Ok(home) = std::env::var("HOME") {
-        let mut path = PathBuf::from(home);
-        path.push(".inboxed");
+    if let Some(mut path) = token_dir() {
         let _ = std::fs::create_dir_all(&path);
         path.push("account_tokens.json");
         path
@@ -1,10 +1,20 @@
-use crate::auth::account::Account;
+use crate::auth::account::{Account, AccountPatch, Identity};
+use crate::commands::email::get_account_client;
 use crate::db::EmailDatabase;
+use crate::email::capabilities::ServerCapabilities;
 use crate::email::imap_client::{ImapClient, ImapCredentials};
-use crate::email::server_presets::{get_server_preset, AuthType, ProviderType, ServerConfig};
+use crate::email::pop3_client::Pop3Client;
+use crate::email::server_presets::{
+    get_server_preset, imap_uses_starttls, AuthType, ProviderType, ServerConfig, POP3_TLS_PORT,
+};
+use crate::email::tls::{self, normalize_fingerprint, Starttls};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tauri::State;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+/// How often the keepalive task looks for sessions that have gone unused
+const KEEPALIVE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Holds active IMAP clients for all connected accounts
 pub struct AccountManager {
@@ -37,6 +47,27 @@ impl AccountManager {
     }
 }
 
+/// Keep the sessions of connected accounts from being logged out for inactivity, with a
+/// NOOP on each one left unused for a while. Clients busy with a command are skipped.
+/// Runs for the lifetime of the app.
+pub fn spawn_imap_keepalive<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(KEEPALIVE_CHECK_INTERVAL).await;
+            let clients: Vec<_> = {
+                let account_manager = app.state::<AccountManager>();
+                let clients = account_manager.clients.lock().unwrap();
+                clients.values().cloned().collect()
+            };
+            for client in clients {
+                if let Ok(client) = client.try_lock() {
+                    client.keep_alive().await;
+                }
+            }
+        }
+    });
+}
+
 type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
 /// Add a new email account (OAuth — tokens already obtained)
@@ -52,8 +83,13 @@ pub async fn add_account(
     smtp_host: Option<String>,
     smtp_port: Option<u16>,
     auth_type: String,
+    cert_fingerprint: Option<String>,
 ) -> Result<Account, String> {
     let provider_type = ProviderType::from_str(&provider);
+    let cert_fingerprint = cert_fingerprint
+        .filter(|fingerprint| !fingerprint.trim().is_empty())
+        .map(|fingerprint| normalize_fingerprint(&fingerprint))
+        .transpose()?;
     let auth = if auth_type == "oauth2" {
         AuthType::OAuth2
     } else {
@@ -68,6 +104,17 @@ pub async fn add_account(
             smtp_host: smtp_host.unwrap_or(preset.smtp_host),
             smtp_port: smtp_port.unwrap_or(preset.smtp_port),
             use_tls: preset.use_tls,
+            cert_fingerprint,
+        }
+    } else if provider_type == ProviderType::Pop3 {
+        // The IMAP fields hold the POP3 server
+        ServerConfig {
+            imap_host: imap_host.ok_or("POP3 host required")?,
+            imap_port: imap_port.unwrap_or(POP3_TLS_PORT),
+            smtp_host: smtp_host.unwrap_or_default(),
+            smtp_port: smtp_port.unwrap_or(465),
+            use_tls: true,
+            cert_fingerprint,
         }
     } else {
         ServerConfig {
@@ -76,10 +123,11 @@ pub async fn add_account(
             smtp_host: smtp_host.ok_or("SMTP host required for custom provider")?,
             smtp_port: smtp_port.unwrap_or(465),
             use_tls: true,
+            cert_fingerprint,
         }
     };
 
-    let account = Account::new(
+    let mut account = Account::new(
         email,
         display_name,
         provider_type,
@@ -89,11 +137,15 @@ pub async fn add_account(
         server_config.smtp_port,
         auth,
     );
+    account.cert_fingerprint = server_config.cert_fingerprint;
 
     // Store in database
     {
         let db_lock = db.lock().unwrap();
         let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        account.sort_order = database
+            .next_account_sort_order()
+            .map_err(|e| e.to_string())?;
         database
             .store_account(&account)
             .map_err(|e| e.to_string())?;
@@ -138,6 +190,28 @@ pub async fn list_accounts(db: State<'_, DbState>) -> Result<Vec<Account>, Strin
         .map_err(|e| e.to_string())
 }
 
+/// Rename an account or change its color, position or label. Returns the updated
+/// account.
+#[tauri::command]
+pub async fn update_account(
+    db: State<'_, DbState>,
+    account_id: String,
+    patch: AccountPatch,
+) -> Result<Account, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let mut account = database
+        .get_account(&account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Account not found")?;
+
+    patch.apply(&mut account)?;
+    database
+        .update_account_metadata(&account)
+        .map_err(|e| e.to_string())?;
+    Ok(account)
+}
+
 /// Set active account
 #[tauri::command]
 pub async fn set_active_account(
@@ -192,8 +266,18 @@ pub async fn connect_account(
         smtp_host: account.smtp_host.clone(),
         smtp_port: account.smtp_port,
         use_tls: true,
+        cert_fingerprint: account.cert_fingerprint.clone(),
     };
 
+    // POP3 accounts have no long-lived client; they are only downloaded from
+    if account.provider_type() == ProviderType::Pop3 {
+        let client = Pop3Client::new(account.id, account.email, server_config, credentials);
+        return client
+            .check()
+            .await
+            .map_err(|e| format!("Connection failed: {}", e));
+    }
+
     let client = ImapClient::new(
         account.id.clone(),
         account.email.clone(),
@@ -209,3 +293,140 @@ pub async fn connect_account(
 
     Ok(())
 }
+
+/// Fingerprint of the certificate a server presents, trusted or not, so the user can
+/// compare it with the one their server shows (e.g. in ProtonMail Bridge) and pin it
+#[tauri::command]
+pub async fn get_server_certificate_fingerprint(host: String, port: u16) -> Result<String, String> {
+    let tcp = tokio::net::TcpStream::connect((host.as_str(), port))
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+    let tcp = if imap_uses_starttls(port) {
+        tls::starttls(tcp, Starttls::Imap)
+            .await
+            .map_err(|e| e.to_string())?
+    } else {
+        tcp
+    };
+    let der = tls::fetch_certificate(tcp, &host)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(tls::fingerprint_of(&der))
+}
+
+/// Pin an account to a server certificate fingerprint, or clear the pin with `None`
+#[tauri::command]
+pub async fn set_account_cert_fingerprint(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    account_id: String,
+    cert_fingerprint: Option<String>,
+) -> Result<(), String> {
+    let cert_fingerprint = cert_fingerprint
+        .filter(|fingerprint| !fingerprint.trim().is_empty())
+        .map(|fingerprint| normalize_fingerprint(&fingerprint))
+        .transpose()?;
+
+    {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .set_account_cert_fingerprint(&account_id, cert_fingerprint.as_deref())
+            .map_err(|e| e.to_string())?;
+    }
+
+    // Reconnect with the new pin on next use
+    account_manager.remove_client(&account_id);
+    Ok(())
+}
+
+/// Extensions the account's IMAP server advertises and its reported software
+#[tauri::command]
+pub async fn get_account_capabilities(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    account_id: String,
+) -> Result<ServerCapabilities, String> {
+    let account = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_account(&account_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Account not found")?
+    };
+
+    let client = get_account_client(&account, &account_manager).await?;
+    let client = client.lock().await;
+    client.capabilities().await.map_err(|e| e.to_string())
+}
+
+/// Addresses an account can send as besides its own (the active account by default)
+#[tauri::command]
+pub async fn list_identities(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+) -> Result<Vec<Identity>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let account_id = match account_id {
+        Some(id) => id,
+        None => {
+            database
+                .get_active_account()
+                .map_err(|e| e.to_string())?
+                .ok_or("No active account")?
+                .id
+        }
+    };
+    database
+        .list_identities(&account_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Add an address the account can send as, e.g. a plus-address or an alias on a custom
+/// domain routed to the same mailbox
+#[tauri::command]
+pub async fn add_identity(
+    db: State<'_, DbState>,
+    account_id: String,
+    address: String,
+    display_name: Option<String>,
+    signature: Option<String>,
+) -> Result<Identity, String> {
+    let address = address.trim().to_string();
+    address
+        .parse::<lettre::Address>()
+        .map_err(|_| format!("Invalid email address: {}", address))?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let account = database
+        .get_account(&account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Account not found")?;
+    if account.email.eq_ignore_ascii_case(&address) {
+        return Err("That is the account's own address".to_string());
+    }
+    if database
+        .get_identity(&account_id, &address)
+        .map_err(|e| e.to_string())?
+        .is_some()
+    {
+        return Err(format!(
+            "{} is already an identity of this account",
+            address
+        ));
+    }
+
+    let identity = Identity::new(
+        account_id,
+        address,
+        display_name.filter(|name| !name.trim().is_empty()),
+        signature.filter(|signature| !signature.trim().is_empty()),
+    );
+    database
+        .add_identity(&identity)
+        .map_err(|e| e.to_string())?;
+    Ok(identity)
+}
@@ -1,16 +1,112 @@
+use crate::commands::jobs::TrackedJob;
+use crate::commands::settings::settings_changed;
+use crate::jobs::JobKind;
+use crate::llm::compute::{self, ComputeCapabilities, ComputeSettings};
+use crate::security::ensure_unlocked;
+use crate::settings;
+use crate::db::EmailDatabase;
+use crate::email::language;
+use crate::llm::benchmark::run_benchmark;
 use crate::llm::{
-    get_available_models, ModelManager, ModelOption, ModelStatus, Summarizer, DEFAULT_MODEL_FILE,
-    DEFAULT_MODEL_REPO,
+    CancellationToken, LlmSettings, LlmWorker, ModelBenchmark, ModelManager, ModelOption,
+    ModelStatus, Priority, PromptFormat, PromptTemplate, Summarizer, DEFAULT_MODEL_FILE, DEFAULT_MODEL_REPO,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
-use tauri::{AppHandle, Emitter};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tracing::{debug, info, warn};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
 lazy_static::lazy_static! {
-    pub static ref SUMMARIZER: Mutex<Option<Summarizer>> = Mutex::new(None);
+    /// Owns the summarizer; all generation goes through its queues
+    pub static ref LLM_WORKER: LlmWorker = LlmWorker::spawn().expect("Failed to start LLM worker");
     static ref MODEL_MANAGER: Mutex<Option<ModelManager>> = Mutex::new(None);
     static ref CURRENT_MODEL_ID: Mutex<Option<String>> = Mutex::new(None);
     static ref MODEL_LOADING: Mutex<bool> = Mutex::new(false);
+    /// In-flight cancellable generations by task id
+    static ref AI_TASKS: Mutex<HashMap<String, CancellationToken>> = Mutex::new(HashMap::new());
+    /// File of the model most recently loaded into the worker
+    static ref LOADED_MODEL_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+    /// Serializes unloading and reloading so concurrent callers reload only once
+    static ref MODEL_RELOAD: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    /// Set when the model chosen last was missing at startup, until another is activated
+    static ref MODEL_FALLBACK: Mutex<Option<ModelFallback>> = Mutex::new(None);
+}
+
+/// Set when the model was freed by `unload_model` or the idle timeout; the next use reloads it
+static MODEL_UNLOADED: AtomicBool = AtomicBool::new(false);
+
+/// How often the idle timeout is checked
+const AUTO_UNLOAD_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Run `f` with the installed summarizer on the LLM worker
+pub(crate) async fn with_summarizer<T, F>(priority: Priority, f: F) -> Result<T, String>
+where
+    F: FnOnce(&Summarizer) -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    ensure_model_loaded().await;
+    LLM_WORKER
+        .run(priority, move |slot| match slot.as_ref() {
+            Some(summarizer) => f(summarizer),
+            None => Err("AI not initialized. Call init_ai first.".to_string()),
+        })
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// A registered cancellable generation; unregisters itself when dropped
+pub(crate) struct AiTask {
+    id: String,
+    token: CancellationToken,
+}
+
+impl AiTask {
+    pub(crate) fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for AiTask {
+    fn drop(&mut self) {
+        let mut tasks = AI_TASKS.lock().unwrap();
+        // A newer task may have reused the id; only remove our own entry
+        if tasks.get(&self.id).is_some_and(|t| t.same_as(&self.token)) {
+            tasks.remove(&self.id);
+        }
+    }
+}
+
+/// Register a cancellable generation under `task_id` (a random id when None)
+pub(crate) fn begin_ai_task(task_id: Option<String>) -> AiTask {
+    let id = task_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let token = CancellationToken::new();
+    AI_TASKS.lock().unwrap().insert(id.clone(), token.clone());
+    AiTask { id, token }
+}
+
+/// Cancel the generation registered as `task_id`, or every in-flight generation when None.
+/// Returns how many tasks were cancelled.
+pub(crate) fn cancel_ai_tasks(task_id: Option<&str>) -> usize {
+    let tasks = AI_TASKS.lock().unwrap();
+    match task_id {
+        Some(id) => match tasks.get(id) {
+            Some(token) => {
+                token.cancel();
+                1
+            }
+            None => 0,
+        },
+        None => {
+            tasks.values().for_each(CancellationToken::cancel);
+            tasks.len()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -60,10 +156,147 @@ fn ensure_model_manager() -> Result<(), String> {
     Ok(())
 }
 
-/// Get list of available models
+/// A handle to the model manager that can be used without holding its lock across awaits
+fn current_model_manager() -> Result<ModelManager, String> {
+    MODEL_MANAGER
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| "Model manager not initialized".to_string())
+}
+
+/// The model manager, created on first use
+pub(crate) fn model_manager() -> Result<ModelManager, String> {
+    ensure_model_manager()?;
+    current_model_manager()
+}
+
+/// Create a summarizer and load the model at `model_path` off the async runtime
+async fn load_summarizer(model_path: std::path::PathBuf) -> Result<Summarizer, String> {
+    tokio::task::spawn_blocking(move || {
+        let mut summarizer = Summarizer::new().map_err(|e| e.to_string())?;
+        summarizer
+            .load_model(&model_path, settings::current().llm)
+            .map_err(|e| e.to_string())?;
+        *LOADED_MODEL_PATH.lock().unwrap() = Some(model_path);
+        Ok(summarizer)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Reload a model freed by `unload_model` or the idle timeout. Called before LLM work;
+/// does nothing if the model wasn't unloaded.
+pub(crate) async fn ensure_model_loaded() {
+    let _reload = MODEL_RELOAD.lock().await;
+    if !MODEL_UNLOADED.swap(false, Ordering::SeqCst) || LLM_WORKER.is_model_loaded() {
+        return;
+    }
+
+    let model_id = CURRENT_MODEL_ID.lock().unwrap().clone();
+    info!("Reloading model after unload");
+    let result = match model_id {
+        Some(model_id) => activate_model(model_id).await,
+        None => load_startup_model(None).await,
+    };
+    if let Err(e) = result {
+        warn!("Failed to reload model: {}", e);
+    }
+}
+
+/// Free the LLM and embedding model once they have gone unused for the idle timeout in
+/// `ComputeSettings`. Runs for the lifetime of the app.
+pub fn spawn_model_auto_unload() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(AUTO_UNLOAD_CHECK_INTERVAL).await;
+            let Some(timeout) = settings::current().compute.idle_unload_after() else {
+                continue;
+            };
+
+            let loading = *MODEL_LOADING.lock().unwrap();
+            if !loading && LLM_WORKER.is_model_loaded() && LLM_WORKER.idle_for() >= timeout {
+                let _reload = MODEL_RELOAD.lock().await;
+                match LLM_WORKER.unload_if_idle(timeout).await {
+                    Ok(true) => {
+                        MODEL_UNLOADED.store(true, Ordering::SeqCst);
+                        info!(
+                            "Unloaded model after {} idle minutes",
+                            timeout.as_secs() / 60
+                        );
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("Failed to unload idle model: {}", e),
+                }
+            }
+
+            crate::commands::rag::unload_embedding_if_idle(timeout).await;
+            crate::commands::dictation::unload_speech_if_idle(timeout);
+        }
+    });
+}
+
+/// Emitted as `ai:warmup` for the LLM and then the embedding engine once the startup
+/// load has finished with each
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupStatus {
+    /// `llm` or `rag`
+    pub component: &'static str,
+    /// False when nothing is downloaded yet or loading failed
+    pub ready: bool,
+    pub error: Option<String>,
+}
+
+/// Load the model chosen last and the embedding engine in the background when the app
+/// starts, unless turned off in the AI settings. Models that aren't downloaded are left
+/// for `init_ai` and `init_rag`.
+pub fn spawn_ai_warmup(app: AppHandle) {
+    if !settings::current().ai.load_on_startup {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        let llm = warm_up_llm(&app).await;
+        emit_warmup(&app, "llm", llm);
+        let rag = crate::commands::rag::start_rag(&app, false).await;
+        emit_warmup(&app, "rag", rag);
+    });
+}
+
+async fn warm_up_llm(app: &AppHandle) -> Result<bool, String> {
+    ensure_model_manager()?;
+    let manager = current_model_manager()?;
+    if manager.find_any_downloaded_model().is_none() {
+        info!("No model downloaded, not loading one at startup");
+        return Ok(false);
+    }
+    load_startup_model(Some(app)).await?;
+    Ok(true)
+}
+
+fn emit_warmup(app: &AppHandle, component: &'static str, result: Result<bool, String>) {
+    let status = match result {
+        Ok(ready) => WarmupStatus {
+            component,
+            ready,
+            error: None,
+        },
+        Err(e) => {
+            warn!("Failed to load {} at startup: {}", component, e);
+            WarmupStatus {
+                component,
+                ready: false,
+                error: Some(e),
+            }
+        }
+    };
+    let _ = app.emit("ai:warmup", status);
+}
+
+/// Get list of available models (built-in catalog plus imported custom models)
 #[tauri::command]
 pub async fn get_available_ai_models() -> Result<Vec<ModelOption>, String> {
-    Ok(get_available_models())
+    ensure_model_manager()?;
+    Ok(current_model_manager()?.models())
 }
 
 /// Check if the AI model is downloaded and ready
@@ -75,7 +308,7 @@ pub async fn check_model_status() -> Result<ModelStatusResponse, String> {
     {
         let loading_guard = MODEL_LOADING.lock().unwrap();
         if *loading_guard {
-            println!("[AI] check_model_status: Loading in progress");
+            debug!("check_model_status: Loading in progress");
             return Ok(ModelStatusResponse::Loading);
         }
     }
@@ -86,17 +319,14 @@ pub async fn check_model_status() -> Result<ModelStatusResponse, String> {
     // Check if any model is downloaded
     if manager.find_any_downloaded_model().is_some() {
         // Check if model is loaded
-        let summarizer_guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = summarizer_guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[AI] check_model_status: Ready");
-                return Ok(ModelStatusResponse::Ready);
-            }
+        if LLM_WORKER.is_model_loaded() {
+            debug!("check_model_status: Ready");
+            return Ok(ModelStatusResponse::Ready);
         }
-        println!("[AI] check_model_status: Downloaded but not loaded");
+        debug!("check_model_status: Downloaded but not loaded");
         Ok(ModelStatusResponse::Downloaded)
     } else {
-        println!("[AI] check_model_status: Not downloaded");
+        debug!("check_model_status: Not downloaded");
         Ok(ModelStatusResponse::NotDownloaded)
     }
 }
@@ -119,22 +349,18 @@ pub async fn download_model(app: AppHandle) -> Result<(), String> {
 
     // Clone app handle for the closure
     let app_clone = app.clone();
-
-    // Run download in blocking task
-    let result = tokio::task::spawn_blocking(move || {
-        let guard = MODEL_MANAGER.lock().unwrap();
-        let manager = guard
-            .as_ref()
-            .ok_or_else(|| "Model manager not initialized".to_string())?;
-
-        manager
-            .download_default_model(move |progress| {
-                let _ = app_clone.emit("model:progress", progress);
-            })
-            .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| e.to_string())?;
+    let job = TrackedJob::start(&app, JobKind::ModelDownload, None);
+    let job_clone = job.clone();
+
+    let manager = current_model_manager()?;
+    let result = manager
+        .download_default_model(move |progress| {
+            let _ = app_clone.emit("model:progress", progress);
+            job_clone.percent(progress);
+        })
+        .await
+        .map_err(|e| e.to_string());
+    job.finish_with(&result);
 
     match result {
         Ok(_) => {
@@ -163,23 +389,19 @@ pub async fn download_model_by_id(app: AppHandle, model_id: String) -> Result<()
         .map_err(|e| e.to_string())?;
 
     let app_clone = app.clone();
-    let model_id_clone = model_id.clone();
-
-    // Run download in blocking task
-    let result = tokio::task::spawn_blocking(move || {
-        let guard = MODEL_MANAGER.lock().unwrap();
-        let manager = guard
-            .as_ref()
-            .ok_or_else(|| "Model manager not initialized".to_string())?;
-
-        manager
-            .download_model_by_id(&model_id_clone, move |progress| {
-                let _ = app_clone.emit("model:progress", progress);
-            })
-            .map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| e.to_string())?;
+    let job = TrackedJob::start(&app, JobKind::ModelDownload, None);
+    job.message(model_id.clone());
+    let job_clone = job.clone();
+
+    let manager = current_model_manager()?;
+    let result = manager
+        .download_model_by_id(&model_id, move |progress| {
+            let _ = app_clone.emit("model:progress", progress);
+            job_clone.percent(progress);
+        })
+        .await
+        .map_err(|e| e.to_string());
+    job.finish_with(&result);
 
     match result {
         Ok(_) => {
@@ -198,18 +420,69 @@ pub async fn download_model_by_id(app: AppHandle, model_id: String) -> Result<()
     }
 }
 
+/// Emitted as `model:fallback` when the model chosen last is no longer downloaded
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelFallback {
+    /// The model chosen last
+    pub requested: String,
+    /// The downloaded model loaded instead, if there is one
+    pub loaded: Option<String>,
+}
+
+/// The model to load when none is active: the one chosen last if it's still downloaded,
+/// otherwise any downloaded model
+fn startup_model(
+    manager: &ModelManager,
+    app: Option<&AppHandle>,
+) -> Option<(ModelOption, PathBuf)> {
+    let requested = settings::current().ai.model_id;
+    let chosen = requested
+        .as_deref()
+        .and_then(|id| manager.get_model_by_id(id))
+        .filter(|model| manager.is_model_downloaded(&model.filename));
+    if let Some(model) = chosen {
+        let path = manager.get_model_path(&model.filename);
+        return Some((model, path));
+    }
+
+    let fallback = manager.find_any_downloaded_model();
+    if let Some(requested) = requested {
+        let loaded = fallback.as_ref().map(|(model, _)| model.id.clone());
+        warn!(
+            "Model {} is no longer downloaded, loading {} instead",
+            requested,
+            loaded.as_deref().unwrap_or("no model")
+        );
+        let notice = ModelFallback { requested, loaded };
+        *MODEL_FALLBACK.lock().unwrap() = Some(notice.clone());
+        if let Some(app) = app {
+            let _ = app.emit("model:fallback", notice);
+        }
+    }
+    fallback
+}
+
+/// Record the loaded model as the active one and the one to load next time
+fn set_active_model(model_id: String) {
+    if settings::current().ai.model_id.as_deref() != Some(model_id.as_str()) {
+        remember_model(Some(model_id.clone()));
+    }
+    *CURRENT_MODEL_ID.lock().unwrap() = Some(model_id);
+}
+
 /// Initialize the AI system (load model into memory)
 #[tauri::command]
-pub async fn init_ai() -> Result<(), String> {
+pub async fn init_ai(app: AppHandle) -> Result<(), String> {
+    load_startup_model(Some(&app)).await
+}
+
+/// Load the model chosen last, or any downloaded model. `model:fallback` is emitted through
+/// `app` when the chosen one is missing.
+async fn load_startup_model(app: Option<&AppHandle>) -> Result<(), String> {
     // Check if model is already loaded - skip reloading
-    {
-        let guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[AI] Model already loaded, skipping init");
-                return Ok(());
-            }
-        }
+    if LLM_WORKER.is_model_loaded() {
+        info!("Model already loaded, skipping init");
+        return Ok(());
     }
 
     // Check if loading is already in progress
@@ -219,7 +492,7 @@ pub async fn init_ai() -> Result<(), String> {
     };
 
     if is_loading {
-        println!("[AI] Model loading already in progress, waiting...");
+        info!("Model loading already in progress, waiting...");
         // Wait for loading to complete
         loop {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -232,12 +505,9 @@ pub async fn init_ai() -> Result<(), String> {
             }
         }
         // Check if model is now loaded
-        let guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[AI] Model loaded by another call");
-                return Ok(());
-            }
+        if LLM_WORKER.is_model_loaded() {
+            info!("Model loaded by another call");
+            return Ok(());
         }
         return Err("Model loading failed in another call".to_string());
     }
@@ -250,15 +520,15 @@ pub async fn init_ai() -> Result<(), String> {
 
     ensure_model_manager()?;
 
-    // Get model path (try any downloaded model)
-    let model_path = {
+    // Get model path (the model chosen last, or any downloaded model)
+    let (model_id, model_path) = {
         let guard = MODEL_MANAGER.lock().unwrap();
         let manager = guard.as_ref().ok_or("Model manager not initialized")?;
 
-        match manager.find_any_downloaded_model() {
+        match startup_model(manager, app) {
             Some((model, path)) => {
-                println!("[AI] Found downloaded model: {}", model.id);
-                path
+                info!("Found downloaded model: {}", model.id);
+                (model.id, path)
             }
             None => {
                 let mut loading_guard = MODEL_LOADING.lock().unwrap();
@@ -268,22 +538,20 @@ pub async fn init_ai() -> Result<(), String> {
         }
     };
 
-    println!("[AI] Loading model from: {:?}", model_path);
-
-    // Load model in blocking task
-    let result = tokio::task::spawn_blocking(move || {
-        let mut summarizer = Summarizer::new().map_err(|e| e.to_string())?;
-        summarizer
-            .load_model(&model_path)
-            .map_err(|e| e.to_string())?;
-
-        let mut guard = SUMMARIZER.lock().unwrap();
-        *guard = Some(summarizer);
-        println!("[AI] Model loaded successfully");
-        Ok::<(), String>(())
-    })
-    .await
-    .map_err(|e| e.to_string())?;
+    info!("Loading model from: {:?}", model_path);
+
+    // Load model in blocking task, then hand it to the LLM worker
+    let result = match load_summarizer(model_path).await {
+        Ok(summarizer) => {
+            info!("Model loaded successfully");
+            LLM_WORKER
+                .set_summarizer(Some(summarizer))
+                .await
+                .map(|_| set_active_model(model_id))
+                .map_err(|e| e.to_string())
+        }
+        Err(e) => Err(e),
+    };
 
     // Clear loading flag
     {
@@ -296,16 +564,11 @@ pub async fn init_ai() -> Result<(), String> {
 
 /// Initialize AI with fallback (works even without model downloaded)
 #[tauri::command]
-pub async fn init_ai_fallback() -> Result<bool, String> {
+pub async fn init_ai_fallback(app: AppHandle) -> Result<bool, String> {
     // Check if model is already loaded - skip reloading
-    {
-        let guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[AI] Model already loaded (fallback check)");
-                return Ok(true); // Model already loaded
-            }
-        }
+    if LLM_WORKER.is_model_loaded() {
+        info!("Model already loaded (fallback check)");
+        return Ok(true); // Model already loaded
     }
 
     // Check if loading is already in progress
@@ -315,7 +578,7 @@ pub async fn init_ai_fallback() -> Result<bool, String> {
     };
 
     if is_loading {
-        println!("[AI] Model loading already in progress (fallback), waiting...");
+        info!("Model loading already in progress (fallback), waiting...");
         // Wait for loading to complete
         loop {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -328,12 +591,9 @@ pub async fn init_ai_fallback() -> Result<bool, String> {
             }
         }
         // Check if model is now loaded
-        let guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[AI] Model loaded by another call (fallback)");
-                return Ok(true);
-            }
+        if LLM_WORKER.is_model_loaded() {
+            info!("Model loaded by another call (fallback)");
+            return Ok(true);
         }
         // Return false to indicate fallback mode
         return Ok(false);
@@ -347,30 +607,33 @@ pub async fn init_ai_fallback() -> Result<bool, String> {
 
     ensure_model_manager()?;
 
-    // Try to find any downloaded model
-    let model_path = {
+    // Try the model chosen last, then any downloaded model
+    let model = {
         let guard = MODEL_MANAGER.lock().unwrap();
         let manager = guard.as_ref().ok_or("Model manager not initialized")?;
-        manager.find_any_downloaded_model().map(|(model, path)| {
-            println!("[AI] Found downloaded model for fallback init: {}", model.id);
-            path
+        startup_model(manager, Some(&app)).map(|(model, path)| {
+            info!("Found downloaded model for fallback init: {}", model.id);
+            (model.id, path)
         })
     };
 
-    if let Some(path) = model_path {
-        println!("[AI] Loading model in fallback mode from: {:?}", path);
-        // Load model in blocking task
-        let result = tokio::task::spawn_blocking(move || {
-            let mut summarizer = Summarizer::new().map_err(|e| e.to_string())?;
-            summarizer.load_model(&path).map_err(|e| e.to_string())?;
-
-            let mut guard = SUMMARIZER.lock().unwrap();
-            *guard = Some(summarizer);
-            println!("[AI] Model loaded successfully in fallback mode");
-            Ok::<bool, String>(true)
-        })
-        .await
-        .map_err(|e| e.to_string())?;
+    if let Some((model_id, path)) = model {
+        info!("Loading model in fallback mode from: {:?}", path);
+        // Load model in blocking task, then hand it to the LLM worker
+        let result = match load_summarizer(path).await {
+            Ok(summarizer) => {
+                info!("Model loaded successfully in fallback mode");
+                LLM_WORKER
+                    .set_summarizer(Some(summarizer))
+                    .await
+                    .map(|_| {
+                        set_active_model(model_id);
+                        true
+                    })
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => Err(e),
+        };
 
         // Clear loading flag
         {
@@ -381,10 +644,12 @@ pub async fn init_ai_fallback() -> Result<bool, String> {
         result
     } else {
         // No model downloaded, use fallback summarizer (no LLM)
-        println!("[AI] No model downloaded, using keyword-based fallback");
+        info!("No model downloaded, using keyword-based fallback");
         let summarizer = Summarizer::new().map_err(|e| e.to_string())?;
-        let mut guard = SUMMARIZER.lock().unwrap();
-        *guard = Some(summarizer);
+        LLM_WORKER
+            .set_summarizer(Some(summarizer))
+            .await
+            .map_err(|e| e.to_string())?;
 
         // Clear loading flag
         {
@@ -396,6 +661,17 @@ pub async fn init_ai_fallback() -> Result<bool, String> {
     }
 }
 
+/// English name of the language to write a summary in: the one chosen in the settings,
+/// or else `detected`, the ISO 639-3 code of the email's own language
+pub(crate) fn summary_language(detected: Option<&str>) -> Option<&'static str> {
+    let preferred = settings::current().ai.summary_language;
+    preferred
+        .as_deref()
+        .and_then(language::parse)
+        .or_else(|| detected.and_then(language::parse))
+        .and_then(language::english_name)
+}
+
 /// Summarize an email
 #[tauri::command]
 pub async fn summarize_email(
@@ -403,28 +679,28 @@ pub async fn summarize_email(
     from: String,
     body: String,
 ) -> Result<EmailSummary, String> {
-    let guard = SUMMARIZER.lock().unwrap();
-    let summarizer = guard
-        .as_ref()
-        .ok_or("AI not initialized. Call init_ai first.")?;
-
-    let summary = summarizer
-        .summarize_email(&subject, &from, &body)
-        .map_err(|e| e.to_string())?;
+    ensure_unlocked()?;
+    let language = summary_language(language::detect(&Summarizer::strip_html(&body)));
+    with_summarizer(Priority::Interactive, move |summarizer| {
+        let summary = summarizer
+            .summarize_email(&subject, &from, &body, language)
+            .map_err(|e| e.to_string())?;
 
-    let insights = summarizer
-        .generate_insights(&subject, &body)
-        .map_err(|e| e.to_string())?;
+        let insights = summarizer
+            .generate_insights(&subject, &body)
+            .map_err(|e| e.to_string())?;
 
-    let priority = summarizer
-        .classify_priority(&subject, &from, &body)
-        .map_err(|e| e.to_string())?;
+        let priority = summarizer
+            .classify_priority(&subject, &from, &body)
+            .map_err(|e| e.to_string())?;
 
-    Ok(EmailSummary {
-        summary,
-        insights,
-        priority,
+        Ok(EmailSummary {
+            summary,
+            insights,
+            priority,
+        })
     })
+    .await
 }
 
 /// Summarize an email with streaming output
@@ -434,37 +710,41 @@ pub async fn summarize_email_stream(
     subject: String,
     from: String,
     body: String,
+    task_id: Option<String>,
 ) -> Result<EmailSummary, String> {
-    // Clone data for the blocking task
+    ensure_unlocked()?;
+    // Clone data for the worker job
     let subject_clone = subject.clone();
     let from_clone = from.clone();
     let body_clone = body.clone();
     let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+    let language = summary_language(language::detect(&Summarizer::strip_html(&body)));
 
-    // Run summarization in blocking task for streaming
-    let summary = tokio::task::spawn_blocking(move || {
-        let guard = SUMMARIZER.lock().unwrap();
-        let summarizer = guard
-            .as_ref()
-            .ok_or_else(|| "AI not initialized".to_string())?;
-
+    // Run summarization on the LLM worker for streaming
+    let summary = with_summarizer(Priority::Interactive, move |summarizer| {
         summarizer
-            .summarize_email_stream(&subject_clone, &from_clone, &body_clone, |token| {
+            .summarize_email_stream(&subject_clone, &from_clone, &body_clone, language, Some(cancel), |token| {
                 let _ = app_clone.emit("ai:token", token);
             })
             .map_err(|e| e.to_string())
     })
-    .await
-    .map_err(|e| e.to_string())??;
+    .await?;
 
     // Emit completion
     app.emit("ai:complete", ()).map_err(|e| e.to_string())?;
 
-    // Get insights and priority (non-streaming)
-    let (insights, priority) = {
-        let guard = SUMMARIZER.lock().unwrap();
-        let summarizer = guard.as_ref().ok_or("AI not initialized")?;
+    if task.token().is_cancelled() {
+        return Ok(EmailSummary {
+            summary,
+            insights: Vec::new(),
+            priority: "MEDIUM".to_string(),
+        });
+    }
 
+    // Get insights and priority (non-streaming)
+    let (insights, priority) = with_summarizer(Priority::Interactive, move |summarizer| {
         let insights = summarizer
             .generate_insights(&subject, &body)
             .map_err(|e| e.to_string())?;
@@ -473,8 +753,9 @@ pub async fn summarize_email_stream(
             .classify_priority(&subject, &from, &body)
             .map_err(|e| e.to_string())?;
 
-        (insights, priority)
-    };
+        Ok((insights, priority))
+    })
+    .await?;
 
     Ok(EmailSummary {
         summary,
@@ -483,28 +764,202 @@ pub async fn summarize_email_stream(
     })
 }
 
+/// Draft an email from instructions (bullet points or a short description), streaming
+/// tokens as `compose:token` and finishing with `compose:complete`
+#[tauri::command]
+pub async fn compose_with_ai(
+    app: AppHandle,
+    instructions: String,
+    context: Option<String>,
+    task_id: Option<String>,
+) -> Result<String, String> {
+    let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+
+    let draft = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .compose_email_stream(&instructions, context.as_deref(), Some(cancel), |token| {
+                let _ = app_clone.emit("compose:token", token);
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    app.emit("compose:complete", ()).map_err(|e| e.to_string())?;
+
+    Ok(draft.trim().to_string())
+}
+
+/// Rewrite text in the composer: `tone` is a mode such as "grammar", "shorter", "formal"
+/// or "friendly" (any other value is used as a tone name). Streams like `compose_with_ai`.
+#[tauri::command]
+pub async fn rewrite_text(
+    app: AppHandle,
+    text: String,
+    tone: String,
+    task_id: Option<String>,
+) -> Result<String, String> {
+    let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+
+    let rewritten = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .rewrite_text_stream(&text, &tone, Some(cancel), |token| {
+                let _ = app_clone.emit("compose:token", token);
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    app.emit("compose:complete", ()).map_err(|e| e.to_string())?;
+
+    Ok(rewritten.trim().to_string())
+}
+
+/// Stop an in-flight AI generation (summary, compose, rewrite or chat) by the `task_id`
+/// it was started with, or all of them when no id is given. The partial output is kept.
+#[tauri::command]
+pub async fn cancel_ai_task(task_id: Option<String>) -> Result<usize, String> {
+    let cancelled = cancel_ai_tasks(task_id.as_deref());
+    info!("Cancelled {} task(s)", cancelled);
+    Ok(cancelled)
+}
+
 /// Get quick insights about an email
 #[tauri::command]
 pub async fn get_email_insights(subject: String, body: String) -> Result<Vec<String>, String> {
-    let guard = SUMMARIZER.lock().unwrap();
-    let summarizer = guard.as_ref().ok_or("AI not initialized")?;
-
-    summarizer
-        .generate_insights(&subject, &body)
-        .map_err(|e| e.to_string())
+    with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .generate_insights(&subject, &body)
+            .map_err(|e| e.to_string())
+    })
+    .await
 }
 
 /// Classify email priority
 #[tauri::command]
 pub async fn classify_priority(subject: String, from: String, body: String) -> Result<String, String> {
-    let guard = SUMMARIZER.lock().unwrap();
-    let summarizer = guard.as_ref().ok_or("AI not initialized")?;
+    with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .classify_priority(&subject, &from, &body)
+            .map_err(|e| e.to_string())
+    })
+    .await
+}
 
-    summarizer
-        .classify_priority(&subject, &from, &body)
+/// GPU backends, memory and cores available for local inference
+#[tauri::command]
+pub async fn get_compute_capabilities() -> Result<ComputeCapabilities, String> {
+    tokio::task::spawn_blocking(ComputeCapabilities::detect)
+        .await
         .map_err(|e| e.to_string())
 }
 
+/// Get the device preference used when loading models
+#[tauri::command]
+pub async fn get_compute_settings() -> Result<ComputeSettings, String> {
+    Ok(settings::current().compute)
+}
+
+/// Save the device preference; it applies the next time a model is loaded
+#[tauri::command]
+pub async fn save_compute_settings(
+    app: AppHandle,
+    settings: ComputeSettings,
+) -> Result<(), String> {
+    let updated = settings::update(|current| current.compute = settings)
+        .map_err(|e| format!("Failed to save compute settings: {}", e))?;
+    settings_changed(&app, &updated);
+    Ok(())
+}
+
+/// Get the LLM runtime parameters (context size, batch size, threads, GPU layers, sampling)
+#[tauri::command]
+pub async fn get_llm_settings() -> Result<LlmSettings, String> {
+    Ok(settings::current().llm)
+}
+
+/// Save LLM runtime parameters and reload the active model so they take effect
+#[tauri::command]
+pub async fn set_llm_settings(app: AppHandle, settings: LlmSettings) -> Result<(), String> {
+    let updated = settings::update(|current| current.llm = settings).map_err(|e| e.to_string())?;
+    settings_changed(&app, &updated);
+    apply_llm_settings().await
+}
+
+/// Reload the active model with the current LLM parameters
+pub(crate) async fn apply_llm_settings() -> Result<(), String> {
+    let active_model = CURRENT_MODEL_ID.lock().unwrap().clone();
+    match active_model {
+        Some(model_id) if LLM_WORKER.is_model_loaded() => {
+            info!("LLM settings changed, reloading {}", model_id);
+            activate_model(model_id).await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// How prompts are laid out for a model
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModelPromptTemplate {
+    /// Format detected from the model's chat template, architecture or file name
+    pub detected_format: PromptFormat,
+    /// The detected format as an editable template
+    pub default_template: PromptTemplate,
+    /// The user's override, used instead of the detected format when set
+    pub custom_template: Option<PromptTemplate>,
+}
+
+/// Get the detected prompt format of a model and the user's template override, if any
+#[tauri::command]
+pub async fn get_prompt_template(model_id: String) -> Result<ModelPromptTemplate, String> {
+    ensure_model_manager()?;
+    let manager = current_model_manager()?;
+    let model = manager
+        .get_model_by_id(&model_id)
+        .ok_or_else(|| format!("Unknown model: {}", model_id))?;
+
+    let detected_format = tokio::task::spawn_blocking({
+        let manager = manager.clone();
+        let model = model.clone();
+        move || manager.prompt_format(&model)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(ModelPromptTemplate {
+        detected_format,
+        default_template: detected_format.template(),
+        custom_template: manager.prompt_template_override(&model),
+    })
+}
+
+/// Set a model's prompt template, or clear it with `null` to go back to the detected
+/// format. Reloads the model if it's the active one so the template takes effect.
+#[tauri::command]
+pub async fn set_prompt_template(
+    model_id: String,
+    template: Option<PromptTemplate>,
+) -> Result<(), String> {
+    ensure_model_manager()?;
+    let manager = current_model_manager()?;
+    let model = manager
+        .get_model_by_id(&model_id)
+        .ok_or_else(|| format!("Unknown model: {}", model_id))?;
+    manager
+        .set_prompt_template_override(&model, template)
+        .map_err(|e| e.to_string())?;
+
+    let is_active = CURRENT_MODEL_ID.lock().unwrap().as_deref() == Some(model_id.as_str());
+    if is_active && LLM_WORKER.is_model_loaded() {
+        info!("Prompt template changed, reloading {}", model_id);
+        activate_model(model_id).await?;
+    }
+    Ok(())
+}
+
 /// Get model information (for the default/recommended model)
 #[tauri::command]
 pub async fn get_model_info() -> Result<ModelInfo, String> {
@@ -540,21 +995,160 @@ pub async fn get_downloaded_models() -> Result<Vec<ModelOption>, String> {
     Ok(manager.get_downloaded_models())
 }
 
+/// Import a GGUF model from a local path or URL; select it afterwards with `activate_model`
+#[tauri::command]
+pub async fn add_custom_model(
+    app: AppHandle,
+    path_or_url: String,
+    name: Option<String>,
+) -> Result<ModelOption, String> {
+    ensure_model_manager()?;
+
+    let app_clone = app.clone();
+    let job = TrackedJob::start(&app, JobKind::ModelDownload, None);
+    job.message(path_or_url.clone());
+    let job_clone = job.clone();
+
+    let manager = current_model_manager()?;
+    let result = manager
+        .add_custom_model(&path_or_url, name.as_deref(), move |progress| {
+            let _ = app_clone.emit("model:progress", progress);
+            job_clone.percent(progress);
+        })
+        .await
+        .map_err(|e| e.to_string());
+    job.finish_with(&result);
+
+    match &result {
+        Ok(model) => info!("Imported custom model {} ({})", model.id, model.filename),
+        Err(e) => {
+            let _ = app.emit("model:error", e.clone());
+        }
+    }
+    result
+}
+
+/// Free the LLM's memory. The model is reloaded the next time a summary, chat or other
+/// AI feature needs it. Returns whether a model was loaded.
+#[tauri::command]
+pub async fn unload_model() -> Result<bool, String> {
+    let _reload = MODEL_RELOAD.lock().await;
+    let unloaded = LLM_WORKER.unload().await.map_err(|e| e.to_string())?;
+    if unloaded {
+        MODEL_UNLOADED.store(true, Ordering::SeqCst);
+        info!("Model unloaded");
+    }
+    Ok(unloaded)
+}
+
+/// Approximate memory held by the app and its models
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// Resident memory of the whole app process, when the platform reports it
+    pub process_rss_bytes: Option<u64>,
+    /// The loaded LLM's file name and size; weights are memory-mapped, so this is roughly
+    /// what the model keeps resident
+    pub llm_model: Option<String>,
+    pub llm_model_bytes: Option<u64>,
+    pub embedding_model: Option<String>,
+    pub embedding_model_bytes: Option<u64>,
+    /// Minutes of inactivity before models are freed, if enabled
+    pub idle_unload_minutes: Option<u32>,
+}
+
+/// Report process memory and the approximate size of each loaded model
+#[tauri::command]
+pub async fn get_memory_usage() -> Result<MemoryUsage, String> {
+    let llm_path = LLM_WORKER
+        .is_model_loaded()
+        .then(|| LOADED_MODEL_PATH.lock().unwrap().clone())
+        .flatten();
+    let embedding = crate::commands::rag::embedding_memory();
+
+    Ok(MemoryUsage {
+        process_rss_bytes: compute::process_rss_bytes(),
+        llm_model: llm_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned()),
+        llm_model_bytes: llm_path
+            .as_ref()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len()),
+        embedding_model: embedding.as_ref().map(|(model, _)| model.clone()),
+        embedding_model_bytes: embedding.map(|(_, bytes)| bytes),
+        idle_unload_minutes: settings::current().compute.idle_unload_minutes,
+    })
+}
+
+/// Load a downloaded model on its own and measure load time, generation speed and memory
+/// on this machine. Runs on the LLM worker so it isn't slowed by other generations.
+#[tauri::command]
+pub async fn benchmark_model(
+    db: State<'_, DbState>,
+    model_id: String,
+) -> Result<ModelBenchmark, String> {
+    ensure_model_manager()?;
+    let manager = current_model_manager()?;
+
+    let model = manager
+        .models()
+        .into_iter()
+        .find(|m| m.id == model_id)
+        .ok_or_else(|| format!("Unknown model: {}", model_id))?;
+    if !manager.is_model_downloaded(&model.filename) {
+        return Err(format!("{} is not downloaded", model.name));
+    }
+    let model_path = manager.get_model_path(&model.filename);
+
+    info!("Benchmarking {}", model_id);
+    let settings = settings::current().llm;
+    let benchmark = LLM_WORKER
+        .run(Priority::Interactive, move |_| {
+            run_benchmark(&model_id, &model_path, settings)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Benchmark failed: {}", e))?;
+    info!(
+        "Benchmark of {}: loaded in {}ms, {:.1} tok/s",
+        benchmark.model_id, benchmark.load_time_ms, benchmark.tokens_per_sec
+    );
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .save_model_benchmark(&benchmark)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+
+    Ok(benchmark)
+}
+
+/// Get the latest saved benchmark of each model
+#[tauri::command]
+pub async fn get_model_benchmarks(db: State<'_, DbState>) -> Result<Vec<ModelBenchmark>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_model_benchmarks()
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
 /// Delete a model by ID
 #[tauri::command]
 pub async fn delete_model(model_id: String) -> Result<(), String> {
     ensure_model_manager()?;
 
     // Check if this is the currently active model
-    {
-        let current_guard = CURRENT_MODEL_ID.lock().unwrap();
-        if let Some(ref current_id) = *current_guard {
-            if current_id == &model_id {
-                // Unload the model first
-                let mut summarizer_guard = SUMMARIZER.lock().unwrap();
-                *summarizer_guard = None;
-            }
-        }
+    let is_active = CURRENT_MODEL_ID.lock().unwrap().as_deref() == Some(model_id.as_str());
+    if is_active {
+        // Unload the model first, and don't reload it on next use
+        LLM_WORKER
+            .set_summarizer(None)
+            .await
+            .map_err(|e| e.to_string())?;
+        MODEL_UNLOADED.store(false, Ordering::SeqCst);
     }
 
     // Delete the model file
@@ -571,14 +1165,25 @@ pub async fn delete_model(model_id: String) -> Result<(), String> {
             }
         }
     }
+    if settings::current().ai.model_id.as_deref() == Some(model_id.as_str()) {
+        remember_model(None);
+    }
 
     Ok(())
 }
 
+/// Save the model `init_ai` should load
+fn remember_model(model_id: Option<String>) {
+    if let Err(e) = settings::update(|settings| settings.ai.model_id = model_id) {
+        warn!("Failed to save the model choice: {}", e);
+    }
+}
+
 /// Activate a specific model by ID (load it into memory)
 #[tauri::command]
 pub async fn activate_model(model_id: String) -> Result<(), String> {
-    println!("[AI] Activating model: {}", model_id);
+    info!("Activating model: {}", model_id);
+    *MODEL_FALLBACK.lock().unwrap() = None;
 
     // Check if loading is already in progress
     let is_loading = {
@@ -587,7 +1192,7 @@ pub async fn activate_model(model_id: String) -> Result<(), String> {
     };
 
     if is_loading {
-        println!("[AI] Model loading already in progress, waiting before activating...");
+        info!("Model loading already in progress, waiting before activating...");
         // Wait for loading to complete
         loop {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -625,32 +1230,26 @@ pub async fn activate_model(model_id: String) -> Result<(), String> {
             return Err(format!("Model not downloaded: {}", model_id));
         }
 
-        println!("[AI] Model path: {:?}", path);
+        debug!("Model path: {:?}", path);
         path
     };
 
     let model_id_clone = model_id.clone();
 
-    // Load model in blocking task
-    let result = tokio::task::spawn_blocking(move || {
-        println!("[AI] Starting model load in blocking task...");
-        let mut summarizer = Summarizer::new().map_err(|e| e.to_string())?;
-        summarizer
-            .load_model(&model_path)
-            .map_err(|e| e.to_string())?;
-
-        let mut guard = SUMMARIZER.lock().unwrap();
-        *guard = Some(summarizer);
-
-        // Update current model ID
-        let mut model_id_guard = CURRENT_MODEL_ID.lock().unwrap();
-        *model_id_guard = Some(model_id_clone);
-
-        println!("[AI] Model activated successfully");
-        Ok::<(), String>(())
-    })
-    .await
-    .map_err(|e| e.to_string())?;
+    // Load model in blocking task, then hand it to the LLM worker
+    debug!("Starting model load in blocking task...");
+    let result = match load_summarizer(model_path).await {
+        Ok(summarizer) => LLM_WORKER
+            .set_summarizer(Some(summarizer))
+            .await
+            .map(|_| {
+                // Update current model ID, and load it again next time
+                set_active_model(model_id_clone);
+                info!("Model activated successfully");
+            })
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e),
+    };
 
     // Clear loading flag
     {
@@ -667,3 +1266,10 @@ pub async fn get_active_model_id() -> Result<Option<String>, String> {
     let guard = CURRENT_MODEL_ID.lock().unwrap();
     Ok(guard.clone())
 }
+
+/// The fallback made at startup when the model chosen last was missing, for frontends
+/// that weren't listening for `model:fallback` yet
+#[tauri::command]
+pub async fn get_model_fallback() -> Result<Option<ModelFallback>, String> {
+    Ok(MODEL_FALLBACK.lock().unwrap().clone())
+}
@@ -0,0 +1,286 @@
+//! Attachment gallery and downloads
+//!
+//! Attachments are recorded when emails are cached, so the gallery lists files from
+//! every folder without going back to the server. Emails cached before attachments were
+//! recorded show up once they're synced again. Attachments under the size set in the
+//! cache settings are saved while syncing; `download_attachment` fetches the others when
+//! they're opened, reporting progress as `attachment:progress` events.
+//!
+//! With OCR turned on in the AI settings, downloaded images and scanned PDFs are read
+//! with Tesseract (see `email::ocr`): indexing reads an email's attachments before
+//! summarizing it, and a background task reads the ones downloaded later. The text is
+//! searched along with the email.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::commands::account::AccountManager;
+use crate::commands::cache::save_attachment;
+use crate::commands::email::{get_account_client, parse_email_id};
+use crate::db::email_db::{AttachmentFilter, AttachmentItem};
+use crate::db::EmailDatabase;
+use crate::email::ocr::{self, MAX_OCR_BYTES};
+use crate::email::server_presets::ProviderType;
+use crate::email::types::Attachment;
+use crate::security::ensure_unlocked;
+use crate::settings::{self, AiSettings};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// How often the OCR task looks for newly downloaded attachments
+const OCR_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Attachments read per round of the OCR task
+const OCR_BATCH: i64 = 10;
+
+/// Payload of `attachment:progress`. Attachments are fetched with their message, so the
+/// byte counts are of the whole message.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttachmentProgress {
+    pub email_id: String,
+    pub position: u32,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Attachments across the mailbox, newest first, narrowed by kind, sender, date and size
+#[tauri::command]
+pub async fn list_all_attachments(
+    db: State<'_, DbState>,
+    filter: Option<AttachmentFilter>,
+) -> Result<Vec<AttachmentItem>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .list_attachments(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Local path of an attachment, downloading it into the media cache first if needed
+#[tauri::command]
+pub async fn download_attachment(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    email_id: String,
+    position: u32,
+) -> Result<String, String> {
+    ensure_unlocked()?;
+    let (account_id, folder, uid) = parse_email_id(&email_id).ok_or("Invalid email id")?;
+    let (attachment, account) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let attachment = database
+            .get_attachment(&email_id, position)
+            .map_err(|e| e.to_string())?
+            .ok_or("Attachment not found")?;
+        let account = database
+            .get_account(&account_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Account not found")?;
+        (attachment, account)
+    };
+
+    if let Some(path) = &attachment.local_path {
+        if Path::new(path).exists() {
+            return Ok(path.clone());
+        }
+    }
+    if account.provider_type() == ProviderType::Pop3 {
+        return Err("This attachment is no longer cached and POP3 can't fetch it again".into());
+    }
+
+    let client_arc = get_account_client(&account, &account_manager).await?;
+    let client = client_arc.lock().await;
+    let raw = client
+        .fetch_raw_message(&folder, uid, |downloaded_bytes, total_bytes| {
+            let _ = app.emit(
+                "attachment:progress",
+                AttachmentProgress {
+                    email_id: email_id.clone(),
+                    position,
+                    downloaded_bytes,
+                    total_bytes,
+                },
+            );
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let email = client
+        .parse_raw_email(uid, &folder, &raw, &[])
+        .map_err(|e| e.to_string())?;
+    drop(client);
+
+    let data = email
+        .attachments
+        .into_iter()
+        .find(|part| part.position == position)
+        .map(|part| part.data)
+        .ok_or("Attachment not found in the message")?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    save_attachment(database, &email_id, position, &attachment.filename, &data)
+}
+
+/// Text read from an attachment with OCR, reading it now if it hasn't been. None when
+/// OCR is off or found no text.
+#[tauri::command]
+pub async fn get_attachment_text(
+    db: State<'_, DbState>,
+    email_id: String,
+    position: u32,
+) -> Result<Option<String>, String> {
+    ensure_unlocked()?;
+    let pending = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_ocr_pending_attachments(Some(&email_id), MAX_OCR_BYTES, i64::MAX)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|(_, attachment)| attachment.position == position)
+    };
+
+    let ai = settings::current().ai;
+    if let Some((_, attachment)) = pending.filter(|_| ai.ocr_enabled) {
+        if !ocr::is_available(ai.tesseract_path.as_deref()) {
+            return Err("Tesseract isn't installed or can't be run".to_string());
+        }
+        let text = tokio::task::spawn_blocking(move || ocr_attachment(&attachment, &ai))
+            .await
+            .map_err(|e| e.to_string())?;
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .set_attachment_ocr_text(&email_id, position, text.as_deref())
+            .map_err(|e| e.to_string())?;
+        return Ok(text);
+    }
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .get_attachment_ocr_text(&email_id, position)
+        .map_err(|e| e.to_string())
+}
+
+/// Read the attachments of `email_id` that OCR hasn't read yet, when OCR is on
+pub(crate) async fn read_email_attachments(database: &EmailDatabase, email_id: &str) {
+    let ai = settings::current().ai;
+    if !ai.ocr_enabled {
+        return;
+    }
+    let pending =
+        match database.get_ocr_pending_attachments(Some(email_id), MAX_OCR_BYTES, i64::MAX) {
+            Ok(pending) => pending,
+            Err(e) => {
+                warn!("Failed to list attachments of {} for OCR: {}", email_id, e);
+                return;
+            }
+        };
+    if pending.is_empty() || !ocr::is_available(ai.tesseract_path.as_deref()) {
+        return;
+    }
+
+    for (email_id, attachment) in pending {
+        let position = attachment.position;
+        let ai = ai.clone();
+        let text = match tokio::task::spawn_blocking(move || ocr_attachment(&attachment, &ai)).await
+        {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("OCR of {} #{} failed: {}", email_id, position, e);
+                None
+            }
+        };
+        if let Err(e) = database.set_attachment_ocr_text(&email_id, position, text.as_deref()) {
+            warn!(
+                "Failed to store OCR text of {} #{}: {}",
+                email_id, position, e
+            );
+        }
+    }
+}
+
+/// Periodically read attachments downloaded since the last round, while OCR is on
+pub fn spawn_attachment_ocr(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut unavailable_logged = false;
+        loop {
+            tokio::time::sleep(OCR_CHECK_INTERVAL).await;
+            let ai = settings::current().ai;
+            if !ai.ocr_enabled {
+                continue;
+            }
+            if !ocr::is_available(ai.tesseract_path.as_deref()) {
+                if !unavailable_logged {
+                    warn!("OCR is on but Tesseract can't be run");
+                    unavailable_logged = true;
+                }
+                continue;
+            }
+            unavailable_logged = false;
+
+            let db = app.state::<DbState>().inner().clone();
+            match tokio::task::spawn_blocking(move || read_pending_attachments(&db, &ai)).await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(read)) => info!("Read {} attachments with OCR", read),
+                Ok(Err(e)) => warn!("Attachment OCR failed: {}", e),
+                Err(e) => warn!("Attachment OCR failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Read one batch of pending attachments, locking the database only to list and store
+fn read_pending_attachments(db: &DbState, ai: &AiSettings) -> Result<usize, String> {
+    let pending = {
+        let db_lock = db.lock().unwrap();
+        let Some(database) = db_lock.as_ref() else {
+            return Ok(0);
+        };
+        database
+            .get_ocr_pending_attachments(None, MAX_OCR_BYTES, OCR_BATCH)
+            .map_err(|e| e.to_string())?
+    };
+
+    for (email_id, attachment) in &pending {
+        let text = ocr_attachment(attachment, ai);
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .set_attachment_ocr_text(email_id, attachment.position, text.as_deref())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(pending.len())
+}
+
+/// Text of a downloaded attachment, None when there is none or it couldn't be read
+fn ocr_attachment(attachment: &Attachment, ai: &AiSettings) -> Option<String> {
+    let source = ocr::ocr_source(
+        attachment.kind,
+        &attachment.content_type,
+        &attachment.filename,
+    )?;
+    let path = attachment.local_path.as_deref()?;
+    match ocr::read_attachment(
+        Path::new(path),
+        source,
+        &ai.ocr_languages,
+        ai.tesseract_path.as_deref(),
+    ) {
+        Ok(text) if !text.is_empty() => Some(text),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("OCR of {} failed: {}", attachment.filename, e);
+            None
+        }
+    }
+}
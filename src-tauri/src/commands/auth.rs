@@ -1,8 +1,20 @@
+use crate::auth::storage::{get_account_tokens, get_app_password};
 use crate::auth::{
-    clear_tokens, get_tokens, handle_oauth_callback, has_valid_tokens, refresh_access_token,
-    start_oauth_flow, start_oauth_flow_for_provider, TokenData,
+    clear_tokens, complete_device_flow, get_tokens, handle_oauth_callback, has_valid_tokens,
+    refresh_access_token, refresh_access_token_for_provider, start_device_flow, start_oauth_flow,
+    start_oauth_flow_for_provider, start_reauth_flow, Account, DeviceCodePrompt, TokenData,
 };
+use crate::commands::account::AccountManager;
+use crate::db::EmailDatabase;
+use crate::email::imap_client::ImapCredentials;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+use tauri_plugin_opener::OpenerExt;
+use tracing::{info, warn};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthStatus {
@@ -24,9 +36,7 @@ pub async fn check_auth_status() -> Result<AuthStatus, String> {
 
     // Check if we have any accounts stored
     let has_accounts = {
-        let project_dirs = directories::ProjectDirs::from("com", "inboxed", "inboxed");
-        if let Some(dirs) = project_dirs {
-            let db_path = dirs.data_dir().join("emails.db");
+        if let Ok(db_path) = crate::paths::email_db_path() {
             if let Ok(database) = crate::db::EmailDatabase::new(db_path) {
                 database.list_accounts().map(|a| !a.is_empty()).unwrap_or(false)
             } else {
@@ -56,7 +66,7 @@ pub async fn check_auth_status() -> Result<AuthStatus, String> {
                         });
                     }
                     Err(e) => {
-                        eprintln!("Failed to refresh token: {}", e);
+                        warn!("Failed to refresh token: {}", e);
                     }
                 }
             }
@@ -89,6 +99,26 @@ pub async fn complete_auth() -> Result<TokenData, String> {
         .map_err(|e| e.to_string())
 }
 
+/// Start signing in with a code instead of a browser redirect, for systems where the
+/// local callback server is blocked. Show the returned code and page to the user, then
+/// call `complete_device_auth`.
+#[tauri::command]
+pub async fn start_device_auth(
+    provider: Option<String>,
+    account_id: Option<String>,
+) -> Result<DeviceCodePrompt, String> {
+    let provider_str = provider.as_deref().unwrap_or("outlook");
+    start_device_flow(provider_str, account_id.as_deref())
+        .await
+        .map_err(|e| format!("{:#}", e))
+}
+
+/// Wait until the user has entered the code from `start_device_auth`
+#[tauri::command]
+pub async fn complete_device_auth() -> Result<TokenData, String> {
+    complete_device_flow().await.map_err(|e| format!("{:#}", e))
+}
+
 /// Refresh access token
 #[tauri::command]
 pub async fn refresh_token() -> Result<TokenData, String> {
@@ -115,3 +145,135 @@ pub async fn get_access_token() -> Result<String, String> {
     let tokens = get_tokens().map_err(|e| e.to_string())?;
     Ok(tokens.access_token)
 }
+
+/// Whether an account's credentials still work
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialState {
+    /// The access token is current (refreshed if needed) or an app password is stored
+    Valid,
+    /// The token expired and can't be refreshed; `reauthenticate_account` fixes it
+    NeedsReauth,
+    /// No credentials are stored for the account
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountAuthStatus {
+    pub account_id: String,
+    pub auth_type: String,
+    pub state: CredentialState,
+    /// When the access token expires (Unix seconds); `None` for app passwords
+    pub expires_at: Option<i64>,
+    pub has_refresh_token: bool,
+    /// Why the token couldn't be refreshed
+    pub error: Option<String>,
+}
+
+/// Check an account's credentials. An expired access token is refreshed, so a refresh
+/// token the provider has revoked shows up as `needs_reauth`.
+#[tauri::command]
+pub async fn get_account_auth_status(
+    db: State<'_, DbState>,
+    account_id: String,
+) -> Result<AccountAuthStatus, String> {
+    let account = load_account(&db, &account_id)?;
+    Ok(account_auth_status(&account).await)
+}
+
+/// Sign an OAuth account in again, e.g. after its refresh token expired or was revoked,
+/// without removing it. Opens the provider's sign-in page with the account preselected,
+/// waits for the callback and hands the new token to the account's live IMAP client.
+#[tauri::command]
+pub async fn reauthenticate_account(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    account_id: String,
+) -> Result<AccountAuthStatus, String> {
+    let account = load_account(&db, &account_id)?;
+    if account.auth_type != "oauth2" {
+        return Err("This account signs in with an app password".to_string());
+    }
+
+    let auth_url = start_reauth_flow(&account.provider, &account.id, &account.email)
+        .map_err(|e| e.to_string())?;
+    app.opener()
+        .open_url(auth_url, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let tokens = handle_oauth_callback().await.map_err(|e| e.to_string())?;
+    info!(account = %account.id, "Account signed in again");
+
+    // The IDLE monitors read the stored token when they reconnect
+    if let Some(client) = account_manager.get_client(&account.id) {
+        let mut client = client.lock().await;
+        client.update_credentials(ImapCredentials::OAuth2 {
+            user: account.email.clone(),
+            access_token: tokens.access_token,
+        });
+        client
+            .reconnect()
+            .await
+            .map_err(|e| format!("Signed in, but the server rejected the new token: {}", e))?;
+    }
+
+    Ok(account_auth_status(&account).await)
+}
+
+fn load_account(db: &DbState, account_id: &str) -> Result<Account, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .get_account(account_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Account not found".to_string())
+}
+
+async fn account_auth_status(account: &Account) -> AccountAuthStatus {
+    let mut status = AccountAuthStatus {
+        account_id: account.id.clone(),
+        auth_type: account.auth_type.clone(),
+        state: CredentialState::Missing,
+        expires_at: None,
+        has_refresh_token: false,
+        error: None,
+    };
+
+    if account.auth_type != "oauth2" {
+        if get_app_password(&account.id).is_ok() {
+            status.state = CredentialState::Valid;
+        }
+        return status;
+    }
+
+    let Ok(tokens) = get_account_tokens(&account.id).or_else(|_| get_tokens()) else {
+        return status;
+    };
+    status.expires_at = Some(tokens.expires_at.timestamp());
+    status.has_refresh_token = tokens.refresh_token.is_some();
+
+    // Same 60s margin as when connecting
+    if tokens.expires_at > Utc::now() + chrono::Duration::seconds(60) {
+        status.state = CredentialState::Valid;
+        return status;
+    }
+    let Some(refresh_token) = tokens.refresh_token else {
+        status.state = CredentialState::NeedsReauth;
+        status.error = Some("Token expired and no refresh token is stored".to_string());
+        return status;
+    };
+    match refresh_access_token_for_provider(&refresh_token, &account.provider, Some(&account.id))
+        .await
+    {
+        Ok(refreshed) => {
+            status.state = CredentialState::Valid;
+            status.expires_at = Some(refreshed.expires_at.timestamp());
+        }
+        Err(e) => {
+            warn!(account = %account.id, "Token refresh failed: {:#}", e);
+            status.state = CredentialState::NeedsReauth;
+            status.error = Some(format!("{:#}", e));
+        }
+    }
+    status
+}
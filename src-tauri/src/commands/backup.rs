@@ -0,0 +1,227 @@
+//! Full local backup and restore of the mail data
+//!
+//! `create_backup` writes one zip archive with snapshots of the email and vector
+//! databases and the media cache (inline images, avatars, downloaded attachments).
+//! `restore_backup` replaces all three with the contents of such an archive. Settings
+//! and credentials are not part of a backup; see the configuration export for settings.
+//!
+//! Both run off the async runtime and report their progress as `backup:progress`
+//! events. An archive is verified against its checksums after it is written and before
+//! anything is restored from it, and restored databases must pass SQLite's integrity
+//! check before they replace the live ones.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::warn;
+
+use crate::commands::cache::{get_data_dir, get_media_cache_dir};
+use crate::commands::rag::VectorDbState;
+use crate::db::backup::{
+    check_integrity, extract_archive, verify_archive, ArchiveWriter, BackupManifest,
+    EMAIL_DB_ENTRY, MEDIA_ENTRY_PREFIX, VECTOR_DB_ENTRY,
+};
+use crate::db::EmailDatabase;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Payload of `backup:progress`: `done` out of `total` units (database pages or files)
+/// of the current stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupProgress {
+    pub stage: String,
+    pub done: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub path: String,
+    pub created_at: i64,
+    pub app_version: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+}
+
+impl BackupSummary {
+    fn new(path: &Path, manifest: &BackupManifest) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            created_at: manifest.created_at,
+            app_version: manifest.app_version.clone(),
+            file_count: manifest.files.len(),
+            total_bytes: manifest.total_bytes(),
+        }
+    }
+}
+
+/// Back up the mail data to `path` (a dated zip in the data directory by default)
+#[tauri::command]
+pub async fn create_backup(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    path: Option<String>,
+) -> Result<BackupSummary, String> {
+    ensure_unlocked()?;
+    let data_dir = get_data_dir()?;
+    let now = Utc::now();
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => data_dir.join(format!(
+            "inboxed-backup-{}.zip",
+            now.format("%Y%m%d-%H%M%S")
+        )),
+    };
+    let staging = data_dir.join(format!("backup-tmp-{}", now.timestamp_millis()));
+    let db = db.inner().clone();
+
+    let result = tokio::task::spawn_blocking({
+        let path = path.clone();
+        let staging = staging.clone();
+        move || write_backup(&app, &db, &path, &staging, now.timestamp())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        warn!("Failed to remove backup staging directory: {}", e);
+    }
+    match result {
+        Ok(manifest) => Ok(BackupSummary::new(&path, &manifest)),
+        Err(e) => {
+            let _ = fs::remove_file(&path);
+            Err(format!("Backup failed: {}", e))
+        }
+    }
+}
+
+/// Replace the mail data with the backup at `path`
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    path: String,
+) -> Result<BackupSummary, String> {
+    ensure_unlocked()?;
+    let path = PathBuf::from(path);
+    let staging = get_data_dir()?.join(format!("restore-tmp-{}", Utc::now().timestamp_millis()));
+    let db = db.inner().clone();
+
+    let result = tokio::task::spawn_blocking({
+        let path = path.clone();
+        let staging = staging.clone();
+        move || read_backup(&app, &db, &path, &staging)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        warn!("Failed to remove restore staging directory: {}", e);
+    }
+    result
+        .map(|manifest| BackupSummary::new(&path, &manifest))
+        .map_err(|e| format!("Restore failed: {}", e))
+}
+
+fn write_backup(
+    app: &AppHandle,
+    db: &DbState,
+    path: &Path,
+    staging: &Path,
+    created_at: i64,
+) -> anyhow::Result<BackupManifest> {
+    fs::create_dir_all(staging)?;
+
+    let email_snapshot = staging.join(EMAIL_DB_ENTRY);
+    {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        database.backup_to(&email_snapshot, progress(app, "emails"))?;
+    }
+    check_integrity(&email_snapshot)?;
+
+    let vector_snapshot = staging.join(VECTOR_DB_ENTRY);
+    let vector_db = app
+        .state::<VectorDbState>()
+        .open()
+        .map_err(anyhow::Error::msg)?;
+    vector_db.backup_to(&vector_snapshot, progress(app, "vectors"))?;
+    check_integrity(&vector_snapshot)?;
+
+    let mut archive = ArchiveWriter::create(path)?;
+    archive.add_file(EMAIL_DB_ENTRY, &email_snapshot, true)?;
+    archive.add_file(VECTOR_DB_ENTRY, &vector_snapshot, true)?;
+    let media_dir = get_media_cache_dir().map_err(anyhow::Error::msg)?;
+    archive.add_dir(MEDIA_ENTRY_PREFIX, &media_dir, progress(app, "media"))?;
+    archive.finish(created_at)?;
+
+    verify_archive(path, progress(app, "verify"))
+}
+
+fn read_backup(
+    app: &AppHandle,
+    db: &DbState,
+    path: &Path,
+    staging: &Path,
+) -> anyhow::Result<BackupManifest> {
+    let manifest = extract_archive(path, staging, progress(app, "extract"))?;
+    if !manifest.contains(EMAIL_DB_ENTRY) {
+        anyhow::bail!("The backup has no email database");
+    }
+    let email_snapshot = staging.join(EMAIL_DB_ENTRY);
+    check_integrity(&email_snapshot)?;
+    let vector_snapshot = staging.join(VECTOR_DB_ENTRY);
+    let has_vectors = manifest.contains(VECTOR_DB_ENTRY);
+    if has_vectors {
+        check_integrity(&vector_snapshot)?;
+    }
+
+    {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Database not initialized"))?;
+        database.restore_from(&email_snapshot, progress(app, "emails"))?;
+    }
+    if has_vectors {
+        let vector_db = app
+            .state::<VectorDbState>()
+            .open()
+            .map_err(anyhow::Error::msg)?;
+        vector_db.restore_from(&vector_snapshot, progress(app, "vectors"))?;
+    }
+
+    // Swap the media cache in whole, so no stale files of the current one survive
+    let media_dir = get_media_cache_dir().map_err(anyhow::Error::msg)?;
+    let restored_media = staging.join(MEDIA_ENTRY_PREFIX.trim_end_matches('/'));
+    let old_media = staging.join("media_cache.old");
+    if media_dir.exists() {
+        fs::rename(&media_dir, &old_media)?;
+    }
+    if restored_media.exists() {
+        fs::rename(&restored_media, &media_dir)?;
+    }
+    progress(app, "media")(1, 1);
+
+    Ok(manifest)
+}
+
+/// Progress callback emitting `backup:progress` for `stage`
+fn progress<'a>(app: &'a AppHandle, stage: &'a str) -> impl FnMut(i64, i64) + 'a {
+    move |done, total| {
+        let _ = app.emit(
+            "backup:progress",
+            BackupProgress {
+                stage: stage.to_string(),
+                done,
+                total,
+            },
+        );
+    }
+}
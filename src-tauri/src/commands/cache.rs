@@ -1,11 +1,24 @@
-use directories::ProjectDirs;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tauri::State;
-
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+use crate::commands::links::untrusted_url_client;
+use crate::commands::settings::settings_changed;
+use crate::db::changes;
+use crate::db::email_db::MaintenanceReport;
 use crate::db::EmailDatabase;
+use crate::email::attachments::cache_filename;
+use crate::email::avatars::{self, AvatarSource, AVATAR_TTL_SECS, MISSING_AVATAR_TTL_SECS};
+use crate::email::inline::InlinePart;
+use crate::email::links;
+use crate::email::types::Email;
+use crate::security::ensure_unlocked;
+use crate::settings::{self, CacheSettings};
 
 type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
@@ -13,32 +26,72 @@ type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 pub struct StorageInfo {
     pub database_size_bytes: u64,
     pub media_cache_size_bytes: u64,
+    /// Downloaded attachments, part of the media cache
+    pub attachments_size_bytes: u64,
+    pub attachments_downloaded: i64,
     pub total_emails_cached: i64,
     pub total_indexed_emails: i64,
     pub data_directory: String,
+    /// Space held by deleted rows that the next maintenance run returns to the disk
+    pub free_space_bytes: i64,
+    pub last_maintenance: Option<MaintenanceReport>,
 }
 
+/// Inline (`cid:`) asset stored in the media cache for an email
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CacheSettings {
-    pub cache_enabled: bool,
-    pub auto_sync_on_start: bool,
-    pub cache_media_assets: bool,
-    pub max_cache_age_days: u32,
+pub struct InlineAsset {
+    pub content_id: String,
+    pub content_type: String,
+    pub path: String,
+}
+
+/// Manifest file mapping Content-IDs to cached files, stored per email
+const INLINE_MANIFEST_FILE: &str = "inline_assets.json";
+
+/// Folder of an email's media cache directory holding its downloaded attachments
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Extension of the empty file recording that a sender has no avatar
+const MISSING_AVATAR_EXT: &str = "none";
+
+/// Largest avatar image downloaded
+const MAX_AVATAR_BYTES: usize = 512 * 1024;
+
+/// How often the maintenance schedule is checked
+const MAINTENANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often changes to the cache are sent to the frontend
+const CACHE_EVENTS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Payload of the cache change events
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheChangeEvent {
+    pub email_ids: Vec<String>,
 }
 
-/// Get the project data directory
-fn get_data_dir() -> Result<PathBuf, String> {
-    let project_dirs =
-        ProjectDirs::from("com", "inboxed", "inboxed").ok_or("Failed to get project directory")?;
-    Ok(project_dirs.data_dir().to_path_buf())
+/// Get the data directory of the active profile
+pub(crate) fn get_data_dir() -> Result<PathBuf, String> {
+    crate::paths::data_dir().map_err(|e| e.to_string())
 }
 
 /// Get the media cache directory
-fn get_media_cache_dir() -> Result<PathBuf, String> {
+pub(crate) fn get_media_cache_dir() -> Result<PathBuf, String> {
     let data_dir = get_data_dir()?;
     Ok(data_dir.join("media_cache"))
 }
 
+/// Derive a file extension from a MIME type (e.g., "image/png; name=x" -> "png")
+fn extension_for_content_type(content_type: &str) -> &str {
+    content_type
+        .split('/')
+        .last()
+        .unwrap_or("bin")
+        .split(';')
+        .next()
+        .unwrap_or("bin")
+        .trim()
+}
+
 /// Calculate total size of a directory recursively
 fn get_dir_size(path: &PathBuf) -> u64 {
     if !path.exists() {
@@ -65,72 +118,160 @@ fn get_dir_size(path: &PathBuf) -> u64 {
 #[tauri::command]
 pub async fn get_storage_info(db: State<'_, DbState>) -> Result<StorageInfo, String> {
     let data_dir = get_data_dir()?;
-    let db_path = data_dir.join("emails.db");
+    let db_path = data_dir.join(crate::paths::EMAIL_DB_FILE);
     let media_cache_dir = get_media_cache_dir()?;
 
-    // Get database file size
-    let database_size_bytes = if db_path.exists() {
-        fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0)
-    } else {
-        0
-    };
+    // Get database file size, with the write-ahead log not yet checkpointed into it
+    let wal_path = data_dir.join(format!("{}-wal", crate::paths::EMAIL_DB_FILE));
+    let database_size_bytes = [&db_path, &wal_path]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|m| m.len())
+        .sum();
 
     // Get media cache directory size
     let media_cache_size_bytes = get_dir_size(&media_cache_dir);
 
-    // Get email counts from database
-    let (total_emails_cached, total_indexed_emails) = {
+    // Get email and attachment counts from database
+    let (
+        total_emails_cached,
+        total_indexed_emails,
+        attachments,
+        free_space_bytes,
+        last_maintenance,
+    ) = {
         let db_lock = db.lock().unwrap();
         if let Some(database) = db_lock.as_ref() {
             let cached = database.get_email_count().unwrap_or(0);
             let indexed = database.get_indexed_count().unwrap_or(0);
-            (cached, indexed)
+            let attachments = database.get_attachment_storage().unwrap_or((0, 0));
+            let free = database.free_space_bytes().unwrap_or(0);
+            let last = database.get_last_maintenance().ok().flatten();
+            (cached, indexed, attachments, free, last)
         } else {
-            (0, 0)
+            (0, 0, (0, 0), 0, None)
         }
     };
+    let (attachments_downloaded, attachments_size_bytes) = attachments;
 
     Ok(StorageInfo {
         database_size_bytes,
         media_cache_size_bytes,
+        attachments_size_bytes,
+        attachments_downloaded,
         total_emails_cached,
         total_indexed_emails,
         data_directory: data_dir.to_string_lossy().to_string(),
+        free_space_bytes,
+        last_maintenance,
     })
 }
 
+/// Compact the database, refresh its statistics and check it for corruption
+#[tauri::command]
+pub async fn run_db_maintenance(db: State<'_, DbState>) -> Result<MaintenanceReport, String> {
+    let db = db.inner().clone();
+    tokio::task::spawn_blocking(move || maintain_database(&db))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Run database maintenance whenever the configured number of days has passed since
+/// the last run
+pub fn spawn_db_maintenance(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(MAINTENANCE_CHECK_INTERVAL).await;
+            let interval_days = settings::current().cache.maintenance_interval_days;
+            if interval_days == 0 {
+                continue;
+            }
+            let db = app.state::<DbState>().inner().clone();
+            let due = {
+                let db_lock = db.lock().unwrap();
+                let Some(database) = db_lock.as_ref() else {
+                    continue;
+                };
+                let last_run = database
+                    .get_last_maintenance()
+                    .ok()
+                    .flatten()
+                    .map_or(0, |report| report.started_at);
+                Utc::now().timestamp() - last_run >= interval_days as i64 * 24 * 60 * 60
+            };
+            if !due {
+                continue;
+            }
+            match tokio::task::spawn_blocking(move || maintain_database(&db)).await {
+                Ok(Ok(report)) => info!(
+                    "Database maintenance reclaimed {} bytes in {} ms",
+                    report.size_before - report.size_after,
+                    report.duration_ms
+                ),
+                Ok(Err(e)) => warn!("Database maintenance failed: {}", e),
+                Err(e) => warn!("Database maintenance failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Emit `emails:added`, `emails:updated`, `emails:removed` and `insights:updated` with
+/// the IDs of the emails written since the last round
+pub fn spawn_cache_events(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CACHE_EVENTS_INTERVAL).await;
+            let pending = changes::take();
+            if pending.is_empty() {
+                continue;
+            }
+            for (event, ids) in [
+                ("emails:added", pending.added),
+                ("emails:updated", pending.updated),
+                ("emails:removed", pending.removed),
+                ("insights:updated", pending.insights),
+            ] {
+                if ids.is_empty() {
+                    continue;
+                }
+                let payload = CacheChangeEvent {
+                    email_ids: ids.into_iter().collect(),
+                };
+                if let Err(e) = app.emit(event, payload) {
+                    warn!("Failed to emit {}: {}", event, e);
+                }
+            }
+        }
+    });
+}
+
+fn maintain_database(db: &DbState) -> Result<MaintenanceReport, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let report = database.run_maintenance().map_err(|e| e.to_string())?;
+    if !report.integrity_problems.is_empty() {
+        warn!(
+            "Database integrity check found {} problems: {}",
+            report.integrity_problems.len(),
+            report.integrity_problems.join("; ")
+        );
+    }
+    Ok(report)
+}
+
 /// Get current cache settings
 #[tauri::command]
 pub async fn get_cache_settings() -> Result<CacheSettings, String> {
-    let data_dir = get_data_dir()?;
-    let settings_path = data_dir.join("cache_settings.json");
-
-    if settings_path.exists() {
-        let content = fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read cache settings: {}", e))?;
-        serde_json::from_str(&content).map_err(|e| format!("Failed to parse cache settings: {}", e))
-    } else {
-        // Return default settings
-        Ok(CacheSettings {
-            cache_enabled: true,
-            auto_sync_on_start: false,
-            cache_media_assets: true,
-            max_cache_age_days: 30,
-        })
-    }
+    Ok(settings::current().cache)
 }
 
 /// Save cache settings
 #[tauri::command]
-pub async fn save_cache_settings(settings: CacheSettings) -> Result<(), String> {
-    let data_dir = get_data_dir()?;
-    fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
-
-    let settings_path = data_dir.join("cache_settings.json");
-    let content = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize cache settings: {}", e))?;
-
-    fs::write(&settings_path, content).map_err(|e| format!("Failed to write cache settings: {}", e))
+pub async fn save_cache_settings(app: AppHandle, settings: CacheSettings) -> Result<(), String> {
+    let updated = settings::update(|current| current.cache = settings)
+        .map_err(|e| format!("Failed to save cache settings: {}", e))?;
+    settings_changed(&app, &updated);
+    Ok(())
 }
 
 /// Clear the email database (keeps the schema)
@@ -144,7 +285,21 @@ pub async fn clear_email_cache(db: State<'_, DbState>) -> Result<(), String> {
 
 /// Clear the media cache directory
 #[tauri::command]
-pub async fn clear_media_cache() -> Result<(), String> {
+pub async fn clear_media_cache(db: State<'_, DbState>) -> Result<(), String> {
+    remove_media_cache()?;
+
+    // Downloaded attachments were in there too
+    let db_lock = db.lock().unwrap();
+    if let Some(database) = db_lock.as_ref() {
+        database
+            .clear_attachment_paths()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Delete everything in the media cache directory
+fn remove_media_cache() -> Result<(), String> {
     let media_cache_dir = get_media_cache_dir()?;
 
     if media_cache_dir.exists() {
@@ -164,7 +319,7 @@ pub async fn clear_all_caches(db: State<'_, DbState>) -> Result<(), String> {
     clear_email_cache(db).await?;
 
     // Clear media cache
-    clear_media_cache().await?;
+    remove_media_cache()?;
 
     Ok(())
 }
@@ -185,14 +340,7 @@ pub async fn cache_media_asset(
 
     // Generate a safe filename from the URL
     let url_hash = format!("{:x}", md5::compute(asset_url.as_bytes()));
-    let extension = content_type
-        .split('/')
-        .last()
-        .unwrap_or("bin")
-        .split(';')
-        .next()
-        .unwrap_or("bin");
-    let filename = format!("{}.{}", url_hash, extension);
+    let filename = format!("{}.{}", url_hash, extension_for_content_type(&content_type));
     let file_path = email_cache_dir.join(&filename);
 
     fs::write(&file_path, data).map_err(|e| format!("Failed to write cached asset: {}", e))?;
@@ -206,6 +354,7 @@ pub async fn get_cached_media_asset(
     email_id: String,
     asset_url: String,
 ) -> Result<Option<String>, String> {
+    ensure_unlocked()?;
     let media_cache_dir = get_media_cache_dir()?;
     let email_cache_dir = media_cache_dir.join(&email_id);
 
@@ -228,6 +377,276 @@ pub async fn get_cached_media_asset(
     Ok(None)
 }
 
+/// Store inline MIME parts for an email in the media cache and record them in its manifest
+pub fn store_inline_parts(email_id: &str, parts: &[InlinePart]) -> Result<Vec<InlineAsset>, String> {
+    let email_cache_dir = get_media_cache_dir()?.join(email_id);
+    fs::create_dir_all(&email_cache_dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let mut assets = Vec::with_capacity(parts.len());
+    for part in parts {
+        let cid_hash = format!("{:x}", md5::compute(part.content_id.as_bytes()));
+        let filename = format!(
+            "cid-{}.{}",
+            cid_hash,
+            extension_for_content_type(&part.content_type)
+        );
+        let file_path = email_cache_dir.join(&filename);
+
+        fs::write(&file_path, &part.data)
+            .map_err(|e| format!("Failed to write inline asset: {}", e))?;
+
+        assets.push(InlineAsset {
+            content_id: part.content_id.clone(),
+            content_type: part.content_type.clone(),
+            path: file_path.to_string_lossy().to_string(),
+        });
+    }
+
+    let manifest = serde_json::to_string_pretty(&assets)
+        .map_err(|e| format!("Failed to serialize inline manifest: {}", e))?;
+    fs::write(email_cache_dir.join(INLINE_MANIFEST_FILE), manifest)
+        .map_err(|e| format!("Failed to write inline manifest: {}", e))?;
+
+    Ok(assets)
+}
+
+/// Largest attachment downloaded while syncing, from the cache settings; `None` when
+/// attachments are only downloaded when opened
+pub fn attachment_auto_download_limit() -> Option<u64> {
+    let settings = settings::current().cache;
+    let enabled = settings.cache_media_assets && settings.auto_download_attachments_mb > 0;
+    enabled.then(|| settings.auto_download_attachments_mb as u64 * 1024 * 1024)
+}
+
+/// Save the attachments of a freshly parsed email that are at most `max_bytes` to the
+/// media cache. The email must already be stored. Returns how many were saved.
+pub fn store_attachments(
+    database: &EmailDatabase,
+    email: &Email,
+    max_bytes: u64,
+) -> Result<usize, String> {
+    let mut stored = 0;
+    for attachment in &email.attachments {
+        if attachment.data.is_empty() || attachment.size > max_bytes {
+            continue;
+        }
+        save_attachment(
+            database,
+            &email.id,
+            attachment.position,
+            &attachment.filename,
+            &attachment.data,
+        )?;
+        stored += 1;
+    }
+    Ok(stored)
+}
+
+/// Write an attachment to the email's media cache directory and record its path
+pub fn save_attachment(
+    database: &EmailDatabase,
+    email_id: &str,
+    position: u32,
+    filename: &str,
+    data: &[u8],
+) -> Result<String, String> {
+    let attachments_dir = get_media_cache_dir()?.join(email_id).join(ATTACHMENTS_DIR);
+    fs::create_dir_all(&attachments_dir)
+        .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+
+    let file_path = attachments_dir.join(cache_filename(position, filename));
+    fs::write(&file_path, data).map_err(|e| format!("Failed to write attachment: {}", e))?;
+
+    let path = file_path.to_string_lossy().to_string();
+    database
+        .set_attachment_path(email_id, position, &path)
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Read the inline asset manifest for an email (empty if nothing was cached)
+pub fn load_inline_assets(email_id: &str) -> Result<Vec<InlineAsset>, String> {
+    let manifest_path = get_media_cache_dir()?
+        .join(email_id)
+        .join(INLINE_MANIFEST_FILE);
+
+    if !manifest_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&manifest_path)
+        .map_err(|e| format!("Failed to read inline manifest: {}", e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse inline manifest: {}", e))
+}
+
+/// Get the locally cached inline (`cid:`) assets for an email
+#[tauri::command]
+pub async fn get_inline_assets(email_id: String) -> Result<Vec<InlineAsset>, String> {
+    ensure_unlocked()?;
+    load_inline_assets(&email_id)
+}
+
+/// Local path of the sender's avatar (Gravatar, or the BIMI logo or favicon of their
+/// domain when the privacy settings allow domain logos), or `None` when they have none.
+/// Images are cached in the media cache; when a refresh fails the stale copy is
+/// returned, so avatars keep working offline.
+#[tauri::command]
+pub async fn get_sender_avatar(email: String) -> Result<Option<String>, String> {
+    let email = email.trim().to_lowercase();
+    let avatar_dir = get_media_cache_dir()?.join("avatars");
+    let key = format!("{:x}", md5::compute(email.as_bytes()));
+
+    let cached = fs::read_dir(&avatar_dir).ok().and_then(|entries| {
+        entries
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| path.file_stem().is_some_and(|stem| *stem == *key))
+    });
+    if let Some(path) = &cached {
+        let missing = path
+            .extension()
+            .is_some_and(|ext| ext == MISSING_AVATAR_EXT);
+        let ttl = if missing {
+            MISSING_AVATAR_TTL_SECS
+        } else {
+            AVATAR_TTL_SECS
+        };
+        if file_age(path).is_some_and(|age| age < Duration::from_secs(ttl)) {
+            return Ok((!missing).then(|| path.to_string_lossy().to_string()));
+        }
+    }
+
+    let client = untrusted_url_client().map_err(|e| e.to_string())?;
+    let (image, reachable) = resolve_avatar(&client, &email, &key).await;
+    if !reachable {
+        // Offline: keep whatever was cached before
+        let stale = cached.filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext != MISSING_AVATAR_EXT)
+        });
+        return Ok(stale.map(|path| path.to_string_lossy().to_string()));
+    }
+
+    fs::create_dir_all(&avatar_dir).map_err(|e| format!("Failed to create avatar cache: {}", e))?;
+    if let Some(path) = &cached {
+        let _ = fs::remove_file(path);
+    }
+    match image {
+        Some((extension, data)) => {
+            let path = avatar_dir.join(format!("{}.{}", key, extension));
+            fs::write(&path, data).map_err(|e| format!("Failed to write avatar: {}", e))?;
+            Ok(Some(path.to_string_lossy().to_string()))
+        }
+        None => {
+            let path = avatar_dir.join(format!("{}.{}", key, MISSING_AVATAR_EXT));
+            fs::write(&path, []).map_err(|e| format!("Failed to write avatar: {}", e))?;
+            Ok(None)
+        }
+    }
+}
+
+/// First avatar found for `email` as (extension, image), and whether any source could be
+/// reached at all
+async fn resolve_avatar(
+    client: &reqwest::Client,
+    email: &str,
+    key: &str,
+) -> (Option<(&'static str, Vec<u8>)>, bool) {
+    let mut reachable = false;
+    let domain_logos = settings::current().privacy.fetch_domain_logos;
+    for source in avatars::avatar_sources(email, domain_logos) {
+        let url = match &source {
+            // `d=404` makes Gravatar answer 404 instead of a generated placeholder
+            AvatarSource::Gravatar => Some(format!(
+                "https://www.gravatar.com/avatar/{}?s=128&d=404",
+                key
+            )),
+            AvatarSource::Bimi(domain) => bimi_logo(client, domain).await,
+            AvatarSource::Favicon(domain) => Some(format!("https://{}/favicon.ico", domain)),
+        };
+        // BIMI logo URLs come from the sender's DNS
+        let Some(url) = url.filter(|url| links::is_previewable(url)) else {
+            continue;
+        };
+        match download_image(client, &url).await {
+            Ok(image) => {
+                reachable = true;
+                if image.is_some() {
+                    return (image, true);
+                }
+            }
+            Err(e) => warn!("Failed to fetch avatar from {}: {}", url, e),
+        }
+    }
+    (None, reachable)
+}
+
+/// Logo URL from the domain's BIMI record, looked up over DNS-over-HTTPS
+async fn bimi_logo(client: &reqwest::Client, domain: &str) -> Option<String> {
+    let response: serde_json::Value = client
+        .get("https://cloudflare-dns.com/dns-query")
+        .query(&[
+            ("name", format!("default._bimi.{}", domain).as_str()),
+            ("type", "TXT"),
+        ])
+        .header("accept", "application/dns-json")
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    response["Answer"]
+        .as_array()?
+        .iter()
+        .filter_map(|answer| answer["data"].as_str())
+        .find_map(avatars::bimi_logo_url)
+}
+
+/// The image at `url`; `Ok(None)` when the server has none (an error status, a
+/// non-image response or an oversized file). Oversized files are given up on as soon as
+/// they pass the limit rather than downloaded in full.
+async fn download_image(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Option<(&'static str, Vec<u8>)>, reqwest::Error> {
+    let mut response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    let extension = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(avatars::image_extension);
+    let Some(extension) = extension else {
+        return Ok(None);
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_AVATAR_BYTES as u64)
+    {
+        return Ok(None);
+    }
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        data.extend_from_slice(&chunk);
+        if data.len() > MAX_AVATAR_BYTES {
+            return Ok(None);
+        }
+    }
+    if data.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((extension, data)))
+}
+
+fn file_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
 /// Get cached emails count
 #[tauri::command]
 pub async fn get_cached_emails_count(db: State<'_, DbState>) -> Result<i64, String> {
@@ -247,17 +666,13 @@ pub async fn has_cached_emails(db: State<'_, DbState>) -> Result<bool, String> {
 /// Clear all app data including database, cache, and settings
 /// This does NOT clear OAuth tokens - use sign_out for that
 #[tauri::command]
-pub async fn clear_all_app_data(db: State<'_, DbState>) -> Result<(), String> {
+pub async fn clear_all_app_data(app: AppHandle, db: State<'_, DbState>) -> Result<(), String> {
     // Clear email cache and media cache
     clear_all_caches(db).await?;
 
-    // Clear cache settings file
-    let data_dir = get_data_dir()?;
-    let settings_path = data_dir.join("cache_settings.json");
-    if settings_path.exists() {
-        fs::remove_file(&settings_path)
-            .map_err(|e| format!("Failed to clear cache settings: {}", e))?;
-    }
+    // Back to the default settings
+    let settings = settings::reset().map_err(|e| format!("Failed to reset settings: {}", e))?;
+    settings_changed(&app, &settings);
 
     Ok(())
 }
@@ -265,8 +680,7 @@ pub async fn clear_all_app_data(db: State<'_, DbState>) -> Result<(), String> {
 /// Delete downloaded AI models
 #[tauri::command]
 pub async fn clear_ai_models() -> Result<(), String> {
-    let data_dir = get_data_dir()?;
-    let models_dir = data_dir.join("models");
+    let models_dir = crate::paths::models_dir().map_err(|e| e.to_string())?;
 
     if models_dir.exists() {
         fs::remove_dir_all(&models_dir)
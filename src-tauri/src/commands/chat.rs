@@ -0,0 +1,246 @@
+//! Multi-turn AI chat sessions
+//!
+//! Sessions and their messages are persisted in SQLite. Each new message is answered with
+//! the session's history folded into the prompt, plus semantically related emails when the
+//! RAG engine is ready. Responses stream as `chat:token` events; any chat generation can be
+//! stopped with `cancel_chat_generation`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Runtime, State};
+
+use crate::commands::ai::{begin_ai_task, cancel_ai_tasks, with_summarizer, AiTask};
+use crate::commands::rag::{ensure_embedding_loaded, question_candidates, RAG_ENGINE};
+use crate::db::email_db::{ChatMessage, ChatSession};
+use crate::db::EmailDatabase;
+use crate::llm::Priority;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+const DEFAULT_SESSION_TITLE: &str = "New chat";
+
+/// Task id chat generations are registered under (see `cancel_ai_task`)
+const CHAT_TASK_ID: &str = "chat";
+
+/// Payload of the `chat:token` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTokenEvent {
+    /// Session the token belongs to; None for stateless chat queries
+    pub session_id: Option<String>,
+    pub token: String,
+}
+
+/// Register a new cancellable chat generation
+pub(crate) fn begin_chat_generation() -> AiTask {
+    begin_ai_task(Some(CHAT_TASK_ID.to_string()))
+}
+
+pub(crate) fn emit_chat_token<R: Runtime>(app: &AppHandle<R>, session_id: Option<&str>, token: &str) {
+    let _ = app.emit(
+        "chat:token",
+        ChatTokenEvent {
+            session_id: session_id.map(str::to_string),
+            token: token.to_string(),
+        },
+    );
+}
+
+/// Stop the chat answer currently being generated; the partial answer is kept
+#[tauri::command]
+pub async fn cancel_chat_generation() -> Result<(), String> {
+    cancel_ai_tasks(Some(CHAT_TASK_ID));
+    Ok(())
+}
+
+/// Title derived from the first user message
+fn session_title(text: &str) -> String {
+    let first_line = text.lines().next().unwrap_or("").trim();
+    if first_line.chars().count() > 60 {
+        format!("{}…", first_line.chars().take(60).collect::<String>().trim_end())
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Related emails for the message, when the RAG engine is ready. Senders, dates, folders
+/// and accounts the message mentions narrow the search. The database is only locked to
+/// read from it, not during the search.
+fn retrieve_email_context(db: &DbState, query: &str) -> Option<String> {
+    let candidates = {
+        let db_lock = db.lock().unwrap();
+        question_candidates(db_lock.as_ref()?, query).ok()?
+    };
+    let similar = {
+        let rag_guard = RAG_ENGINE.lock().unwrap();
+        let rag = rag_guard.as_ref().filter(|rag| rag.is_initialized())?;
+        rag.search_similar(query, 5, None, candidates.as_ref())
+            .ok()?
+    };
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref()?;
+    let lines: Vec<String> = similar
+        .iter()
+        .filter_map(|s| database.get_email_by_id(&s.email_id).ok().flatten())
+        .enumerate()
+        .map(|(i, email)| {
+            let snippet: String = email
+                .body_clean
+                .as_deref()
+                .unwrap_or(&email.snippet)
+                .chars()
+                .take(200)
+                .collect();
+            format!("Email {}: From: {} | Subject: {} | {}", i + 1, email.from, email.subject, snippet)
+        })
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+#[tauri::command]
+pub async fn create_chat_session(
+    db: State<'_, DbState>,
+    title: Option<String>,
+) -> Result<ChatSession, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let title = title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| DEFAULT_SESSION_TITLE.to_string());
+
+    database
+        .create_chat_session(&id, &title)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_chat_sessions(db: State<'_, DbState>) -> Result<Vec<ChatSession>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database.list_chat_sessions().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_chat_messages(
+    db: State<'_, DbState>,
+    session_id: String,
+) -> Result<Vec<ChatMessage>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_chat_messages(&session_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_chat_session(
+    db: State<'_, DbState>,
+    session_id: String,
+    title: String,
+) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .rename_chat_session(&session_id, title.trim())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_chat_session(db: State<'_, DbState>, session_id: String) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .delete_chat_session(&session_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Send a message in a session and stream the reply (`chat:token`, then `chat:complete`
+/// with the stored assistant message). Returns the assistant message. The message and the
+/// reply are stored together once the reply is done, so a failed generation leaves the
+/// session as it was.
+#[tauri::command]
+pub async fn send_chat_message(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    session_id: String,
+    text: String,
+) -> Result<ChatMessage, String> {
+    ensure_unlocked()?;
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Message cannot be empty".to_string());
+    }
+
+    ensure_embedding_loaded(&app).await;
+
+    // Load history and gather email context
+    let history: Vec<(String, String)> = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_chat_messages(&session_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(|m| (m.role, m.content))
+            .collect()
+    };
+    let email_context = retrieve_email_context(&db, &text);
+
+    let app_clone = app.clone();
+    let session_clone = session_id.clone();
+    let prompt = text.clone();
+    let first_message = history.is_empty();
+    let task = begin_chat_generation();
+    let cancel = task.token();
+    let reply = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .chat_stream(Some(&session_clone), &history, &prompt, email_context.as_deref(), Some(cancel), |token| {
+                emit_chat_token(&app_clone, Some(&session_clone), token);
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    let message = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        database
+            .add_chat_message(&session_id, "user", &text)
+            .map_err(|e| e.to_string())?;
+        let message = database
+            .add_chat_message(&session_id, "assistant", reply.trim())
+            .map_err(|e| e.to_string())?;
+
+        if first_message {
+            let is_untitled = database
+                .list_chat_sessions()
+                .map_err(|e| e.to_string())?
+                .iter()
+                .any(|s| s.id == session_id && s.title == DEFAULT_SESSION_TITLE);
+            if is_untitled {
+                let _ = database.rename_chat_session(&session_id, &session_title(&text));
+            }
+        }
+        message
+    };
+
+    app.emit("chat:complete", &message).map_err(|e| e.to_string())?;
+
+    Ok(message)
+}
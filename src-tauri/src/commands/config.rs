@@ -0,0 +1,327 @@
+//! Export and import of the app configuration
+//!
+//! `export_app_config` writes one JSON bundle with the account list, sending identities
+//! and their signatures, categories, labels, inbox sections, saved searches, per-account
+//! notification and sync settings, reply reminders, the senders allowed to load remote
+//! images and the app settings, so a setup can be moved to another machine. Credentials stay in the keychain and are never exported:
+//! imported accounts have to be signed in to again (`reauthenticate_account` for OAuth,
+//! the app password for the rest). No cached mail goes into the bundle either, nor
+//! anything that hangs off it: priorities set by hand belong to cached emails, and the
+//! priority weights learned from them are refit once the new machine has corrections
+//! of its own.
+//!
+//! Importing merges the bundle into what is already there. Accounts whose address is
+//! already set up are skipped, and sections and saved searches pointing at them are
+//! moved to the local account. Sections and saved searches whose name is already taken
+//! are skipped. The compute settings and the Tesseract path describe
+//! this machine and are kept; the Tesseract path isn't exported at all, since it names
+//! a program to run.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+use crate::auth::account::{Account, Identity};
+use crate::commands::ai::apply_llm_settings;
+use crate::commands::cache::get_data_dir;
+use crate::commands::settings::settings_changed;
+use crate::db::email_db::{EmailCategory, InboxSection, ReplyReminderSettings, SavedSearch};
+use crate::db::EmailDatabase;
+use crate::email::notifications::NotificationSettings;
+use crate::email::sync_window::AccountSyncSettings;
+use crate::email::Label;
+use crate::settings::{self, AppSettings};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Marks a file as an Inboxed configuration bundle
+const BUNDLE_FORMAT: &str = "inboxed-config";
+
+/// Bumped when the bundle changes in a way older versions can't read
+const BUNDLE_VERSION: u32 = 1;
+
+/// Settings of one account that live in the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountConfig {
+    pub account: Account,
+    #[serde(default)]
+    pub identities: Vec<Identity>,
+    #[serde(default)]
+    pub notifications: Option<NotificationSettings>,
+    #[serde(default)]
+    pub sync: Option<AccountSyncSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    pub app_version: String,
+    #[serde(default)]
+    pub accounts: Vec<AccountConfig>,
+    #[serde(default)]
+    pub categories: Vec<EmailCategory>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub inbox_sections: Vec<InboxSection>,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    #[serde(default)]
+    pub reply_reminders: Option<ReplyReminderSettings>,
+    #[serde(default)]
+    pub remote_image_senders: Vec<String>,
+    #[serde(default)]
+    pub settings: Option<AppSettings>,
+}
+
+/// What `import_app_config` changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfigImportSummary {
+    pub accounts_added: usize,
+    /// Addresses already set up here, left as they are
+    pub accounts_skipped: Vec<String>,
+    /// Imported accounts that need to be signed in to before they can sync
+    pub needs_sign_in: Vec<String>,
+    pub categories_added: usize,
+    pub labels_added: usize,
+    pub sections_added: usize,
+    pub saved_searches_imported: usize,
+    pub remote_image_senders_added: usize,
+    pub settings_imported: bool,
+}
+
+/// Write the configuration bundle to `path` (a dated file in the data directory by
+/// default) and return the path
+#[tauri::command]
+pub async fn export_app_config(
+    db: State<'_, DbState>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let bundle = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        build_bundle(database).map_err(|e| e.to_string())?
+    };
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => get_data_dir()?.join(format!(
+            "inboxed-config-{}.json",
+            Utc::now().format("%Y%m%d-%H%M%S")
+        )),
+    };
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write the configuration: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Merge the configuration bundle at `path` into this installation
+#[tauri::command]
+pub async fn import_app_config(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    path: String,
+) -> Result<ConfigImportSummary, String> {
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle: ConfigBundle =
+        serde_json::from_str(&json).map_err(|e| format!("Not a configuration bundle: {}", e))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err("Not an Inboxed configuration bundle".to_string());
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(format!(
+            "This bundle was exported by a newer version of Inboxed ({})",
+            bundle.app_version
+        ));
+    }
+
+    let mut summary = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        import_into(database, &bundle).map_err(|e| e.to_string())?
+    };
+
+    if let Some(imported) = bundle.settings {
+        let previous = settings::current();
+        let updated = settings::update(|settings| {
+            let mut imported = AppSettings {
+                compute: settings.compute.clone(),
+                ..imported
+            };
+            imported.ai.tesseract_path = settings.ai.tesseract_path.clone();
+            *settings = imported;
+        })
+        .map_err(|e| e.to_string())?;
+        settings_changed(&app, &updated);
+        if previous.llm != updated.llm {
+            apply_llm_settings().await?;
+        }
+        summary.settings_imported = true;
+    }
+    Ok(summary)
+}
+
+fn build_bundle(database: &EmailDatabase) -> anyhow::Result<ConfigBundle> {
+    let mut accounts = Vec::new();
+    for account in database.list_accounts()? {
+        accounts.push(AccountConfig {
+            identities: database.list_identities(&account.id)?,
+            notifications: Some(database.get_notification_settings(&account.id)?),
+            sync: Some(database.get_account_sync_settings(&account.id)?),
+            account,
+        });
+    }
+
+    Ok(ConfigBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        exported_at: Utc::now().timestamp(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        accounts,
+        categories: database.get_categories()?,
+        labels: database.list_labels()?,
+        inbox_sections: database.get_inbox_sections()?,
+        saved_searches: database.list_saved_searches()?,
+        reply_reminders: Some(database.get_reply_reminder_settings()?),
+        remote_image_senders: database.list_remote_image_senders()?,
+        settings: Some(exported_settings()),
+    })
+}
+
+/// The settings as exported, without what only applies to this machine
+fn exported_settings() -> AppSettings {
+    let mut settings = settings::current();
+    settings.ai.tesseract_path = None;
+    settings
+}
+
+fn import_into(
+    database: &EmailDatabase,
+    bundle: &ConfigBundle,
+) -> anyhow::Result<ConfigImportSummary> {
+    let mut summary = ConfigImportSummary::default();
+
+    // Accounts, mapping the bundle's account ids to the local ones
+    let local_accounts = database.list_accounts()?;
+    let had_accounts = !local_accounts.is_empty();
+    let local_by_email: HashMap<String, String> = local_accounts
+        .into_iter()
+        .map(|account| (account.email.to_lowercase(), account.id))
+        .collect();
+    let mut account_ids = HashMap::new();
+    for config in &bundle.accounts {
+        if let Some(local_id) = local_by_email.get(&config.account.email.to_lowercase()) {
+            account_ids.insert(config.account.id.clone(), local_id.clone());
+            summary.accounts_skipped.push(config.account.email.clone());
+            continue;
+        }
+        if database.get_account(&config.account.id)?.is_some() {
+            summary.accounts_skipped.push(config.account.email.clone());
+            continue;
+        }
+
+        let mut account = config.account.clone();
+        account.is_active = account.is_active && !had_accounts;
+        account.last_synced_at = None;
+        account.sort_order = database.next_account_sort_order()?;
+        database.store_account(&account)?;
+        for identity in &config.identities {
+            database.add_identity(&Identity {
+                account_id: account.id.clone(),
+                ..identity.clone()
+            })?;
+        }
+        if let Some(notifications) = &config.notifications {
+            database.save_notification_settings(&account.id, notifications)?;
+        }
+        if let Some(sync) = &config.sync {
+            database.save_account_sync_settings(&account.id, sync)?;
+        }
+        account_ids.insert(account.id.clone(), account.id.clone());
+        summary.accounts_added += 1;
+        summary.needs_sign_in.push(account.email);
+    }
+    let local_account = |id: &Option<String>| -> Option<Option<String>> {
+        match id {
+            Some(id) => account_ids.get(id).cloned().map(Some),
+            None => Some(None),
+        }
+    };
+
+    let local_categories: HashSet<String> = database
+        .get_categories()?
+        .into_iter()
+        .map(|category| category.id)
+        .collect();
+    for category in &bundle.categories {
+        if !local_categories.contains(&category.id) {
+            database.add_category(&category.id, &category.name, &category.description)?;
+            summary.categories_added += 1;
+        }
+    }
+
+    for label in &bundle.labels {
+        if database.get_label(&label.name)?.is_none()
+            && database
+                .create_label(&label.name, &label.keyword, label.color.as_deref())
+                .is_ok()
+        {
+            summary.labels_added += 1;
+        }
+    }
+
+    // Sections and searches tied to an account that wasn't in the bundle are dropped
+    let local_sections: HashSet<String> = database
+        .get_inbox_sections()?
+        .into_iter()
+        .map(|section| section.name.to_lowercase())
+        .collect();
+    for section in &bundle.inbox_sections {
+        if local_sections.contains(&section.name.to_lowercase()) {
+            continue;
+        }
+        let Some(account_id) = local_account(&section.filter.account_id) else {
+            continue;
+        };
+        let mut filter = section.filter.clone();
+        filter.account_id = account_id;
+        database.create_inbox_section(&section.name, &filter)?;
+        summary.sections_added += 1;
+    }
+
+    let local_searches: HashSet<String> = database
+        .list_saved_searches()?
+        .into_iter()
+        .map(|search| search.name.to_lowercase())
+        .collect();
+    for search in &bundle.saved_searches {
+        if local_searches.contains(&search.name.to_lowercase()) {
+            continue;
+        }
+        let Some(account_id) = local_account(&search.filters.account_id) else {
+            continue;
+        };
+        let mut filters = search.filters.clone();
+        filters.account_id = account_id;
+        database.save_search(&search.name, &search.query, &filters)?;
+        summary.saved_searches_imported += 1;
+    }
+
+    if let Some(reply_reminders) = &bundle.reply_reminders {
+        database.save_reply_reminder_settings(reply_reminders)?;
+    }
+
+    for sender in &bundle.remote_image_senders {
+        if !database.is_remote_images_allowed(sender)? {
+            database.set_remote_images_allowed(sender, true)?;
+            summary.remote_image_senders_added += 1;
+        }
+    }
+    Ok(summary)
+}
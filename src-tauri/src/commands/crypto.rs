@@ -0,0 +1,33 @@
+use crate::email::crypto::{self, PgpKeyInfo};
+use crate::security::ensure_unlocked;
+
+/// Import armored OpenPGP keys; the passphrase (if any) is stored in the system keychain
+#[tauri::command]
+pub async fn import_pgp_key(
+    armored: String,
+    passphrase: Option<String>,
+) -> Result<Vec<PgpKeyInfo>, String> {
+    ensure_unlocked()?;
+    crypto::import_keys(&armored, passphrase.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Export a key as ASCII armor (public only unless `include_secret` is set)
+#[tauri::command]
+pub async fn export_pgp_key(
+    fingerprint: String,
+    include_secret: Option<bool>,
+) -> Result<String, String> {
+    ensure_unlocked()?;
+    crypto::export_key(&fingerprint, include_secret.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_pgp_keys() -> Result<Vec<PgpKeyInfo>, String> {
+    Ok(crypto::list_keys())
+}
+
+#[tauri::command]
+pub async fn delete_pgp_key(fingerprint: String) -> Result<(), String> {
+    ensure_unlocked()?;
+    crypto::delete_key(&fingerprint).map_err(|e| e.to_string())
+}
@@ -1,24 +1,63 @@
-use tauri::{State, Emitter};
+use tauri::{State, Emitter, Manager};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use directories::ProjectDirs;
+use std::time::Duration;
 use anyhow::Result;
 use tokio::task;
+use futures::stream::{self, StreamExt};
 use chrono::Utc;
+use tracing::{error, info, warn};
 
-use crate::db::{EmailDatabase, email_db::{EmailWithInsight, IndexingStatus, EmailInsight}};
+use crate::db::{EmailDatabase, email_db::{EmailWithInsight, IndexingStatus, EmailInsight, SuspiciousEmail, DeadlineItem, SenderStats, SenderSort, InboxAnalytics, EmailCategory, ReindexStatus, AccountIndexingStatus, EmailCopy, EmailPage, ThreadSummary}};
+use crate::auth::account::Account;
 use crate::email::types::Email;
-use crate::commands::ai::SUMMARIZER;
+use crate::email::dates::{extract_deadline, local_naive, parse_llm_datetime, to_timestamp};
+use crate::email::{language, reply};
+use crate::email::priority::{self, PriorityFeatures};
+use crate::email::trackers::{detect_trackers, TrackerInfo};
+use crate::commands::account::AccountManager;
+use crate::commands::attachments::read_email_attachments;
+use crate::commands::ai::{summary_language, LLM_WORKER};
+use crate::commands::chat::{begin_chat_generation, emit_chat_token};
+use crate::commands::email::cache_recent_messages;
+use crate::llm::{CancellationToken, Priority};
+use crate::llm::summarizer::Summarizer;
+use crate::commands::jobs::TrackedJob;
+use crate::commands::rag::{force_reset_embedding, recover_stale_embedding};
+use crate::jobs::{JobControl, JobKind, JobState, JobStatus};
+use crate::security::ensure_unlocked;
+use crate::paths;
 
 type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
+/// Category that cannot be deleted and receives emails from deleted categories
+const DEFAULT_CATEGORY: &str = "general";
+
+/// Emails analyzed per re-index batch (progress and the resume cursor are saved per batch)
+const DEFAULT_REINDEX_BATCH_SIZE: usize = 25;
+/// Emails analyzed concurrently within a batch; matches the LLM worker's background queue
+const MAX_REINDEX_CONCURRENCY: usize = 4;
+
+/// How often the status watchdog looks for stalled jobs
+const STATUS_WATCHDOG_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// A running job whose status hasn't been updated for this long is considered stalled
+const STALE_STATUS_AFTER_SECS: i64 = 30 * 60;
+
+lazy_static::lazy_static! {
+    /// Pause/cancel control for the indexing loop
+    static ref INDEXING_JOB: Arc<JobControl> = Arc::new(JobControl::new());
+    /// Set while a re-index run is active in this process
+    static ref REINDEX_JOB: Arc<JobControl> = Arc::new(JobControl::new());
+    /// Set while a re-classification run is active in this process
+    static ref RECLASSIFY_JOB: Arc<JobControl> = Arc::new(JobControl::new());
+}
+
 #[tauri::command]
 pub async fn init_database() -> Result<(), String> {
-    let project_dirs = ProjectDirs::from("com", "inboxed", "inboxed")
-        .ok_or("Failed to get project directory")?;
-    let data_dir = project_dirs.data_dir();
-    std::fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    let data_dir = paths::data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&data_dir).map_err(|e| e.to_string())?;
 
-    let db_path = data_dir.join("emails.db");
+    let db_path = data_dir.join(paths::EMAIL_DB_FILE);
     let db = EmailDatabase::new(db_path).map_err(|e| e.to_string())?;
 
     // Migrate old categories to new buckets
@@ -33,6 +72,7 @@ pub async fn get_smart_inbox(
     limit: Option<i64>,
     offset: Option<i64>,
 ) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
     let db_lock = db.lock().unwrap();
     let database = db_lock.as_ref().ok_or("Database not initialized")?;
 
@@ -43,20 +83,59 @@ pub async fn get_smart_inbox(
     Ok(emails)
 }
 
+/// ISO 639-3 code for a language filter given by code or name
+fn parse_language(filter: Option<&str>) -> Result<Option<&'static str>, String> {
+    filter
+        .map(|value| language::parse(value).ok_or_else(|| format!("Unknown language: {}", value)))
+        .transpose()
+}
+
+/// A page of a category, paged like the smart inbox by `limit` and `offset`, with the
+/// number of emails in the whole category under the same filters
 #[tauri::command]
 pub async fn get_emails_by_category(
     db: State<'_, DbState>,
     category: String,
+    sentiment: Option<String>,
+    language: Option<String>,
+    unread_only: Option<bool>,
     limit: Option<i64>,
-) -> Result<Vec<EmailWithInsight>, String> {
+    offset: Option<i64>,
+) -> Result<EmailPage, String> {
+    ensure_unlocked()?;
+    let language = parse_language(language.as_deref())?;
     let db_lock = db.lock().unwrap();
     let database = db_lock.as_ref().ok_or("Database not initialized")?;
 
-    let emails = database
-        .get_emails_by_category(&category, limit.unwrap_or(500))
-        .map_err(|e: anyhow::Error| e.to_string())?;
+    database
+        .get_emails_by_category(
+            &category,
+            sentiment.as_deref(),
+            language,
+            unread_only.unwrap_or(false),
+            limit.unwrap_or(500),
+            offset.unwrap_or(0),
+        )
+        .map_err(|e: anyhow::Error| e.to_string())
+}
 
-    Ok(emails)
+/// Emails with the given sentiments (default: frustrated and negative), most negative first
+#[tauri::command]
+pub async fn get_emails_by_sentiment(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    sentiments: Option<Vec<String>>,
+    limit: Option<i64>,
+) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let sentiments = sentiments
+        .unwrap_or_else(|| vec!["frustrated".to_string(), "negative".to_string()]);
+    database
+        .get_emails_by_sentiment(account_id.as_deref(), &sentiments, limit.unwrap_or(100))
+        .map_err(|e: anyhow::Error| e.to_string())
 }
 
 #[tauri::command]
@@ -64,18 +143,26 @@ pub async fn get_emails_by_account_and_category(
     db: State<'_, DbState>,
     account_id: String,
     category: String,
+    language: Option<String>,
     limit: Option<i64>,
 ) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let language = parse_language(language.as_deref())?;
     let db_lock = db.lock().unwrap();
     let database = db_lock.as_ref().ok_or("Database not initialized")?;
 
     let emails = if category == "important" {
         database
-            .get_important_emails_by_account(&account_id, limit.unwrap_or(500))
+            .get_important_emails_by_account(&account_id, language, limit.unwrap_or(500))
             .map_err(|e: anyhow::Error| e.to_string())?
     } else {
         database
-            .get_emails_by_account_and_category(&account_id, &category, limit.unwrap_or(500))
+            .get_emails_by_account_and_category(
+                &account_id,
+                &category,
+                language,
+                limit.unwrap_or(500),
+            )
             .map_err(|e: anyhow::Error| e.to_string())?
     };
 
@@ -88,6 +175,7 @@ pub async fn search_smart_emails(
     query: String,
     limit: Option<i64>,
 ) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
     let db_lock = db.lock().unwrap();
     let database = db_lock.as_ref().ok_or("Database not initialized")?;
 
@@ -117,163 +205,970 @@ pub async fn reset_indexing_status(db: State<'_, DbState>) -> Result<(), String>
     database
         .update_indexing_status(false, None, None, None)
         .map_err(|e: anyhow::Error| e.to_string())?;
+    database
+        .reset_account_indexing_statuses()
+        .map_err(|e: anyhow::Error| e.to_string())?;
     Ok(())
 }
 
+/// Clear every job status, in the database and in this process, for when a job is stuck
+/// as running. Jobs still running are cancelled; their progress so far is kept.
+#[tauri::command]
+pub async fn force_reset_all_statuses<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    db: State<'_, DbState>,
+) -> Result<(), String> {
+    INDEXING_JOB.cancel();
+    REINDEX_JOB.cancel();
+    RECLASSIFY_JOB.cancel();
+    {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .recover_interrupted_indexing(None)
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        database
+            .set_indexing_paused(false)
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        database
+            .reset_account_indexing_statuses()
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        database
+            .recover_interrupted_reindex(None)
+            .map_err(|e: anyhow::Error| e.to_string())?;
+    }
+    force_reset_embedding(&app)
+}
+
+/// Reconcile job statuses left running by an earlier session at startup, then
+/// periodically mark jobs whose status has stopped updating as interrupted so they can
+/// be resumed or restarted. Runs for the lifetime of the app.
+pub fn spawn_status_watchdog<R: tauri::Runtime>(app: tauri::AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        // Nothing runs yet, so any status marked as running is left over
+        recover_stale_statuses(&app, None);
+        loop {
+            tokio::time::sleep(STATUS_WATCHDOG_INTERVAL).await;
+            recover_stale_statuses(&app, Some(Utc::now().timestamp() - STALE_STATUS_AFTER_SECS));
+        }
+    });
+}
+
+/// Recover statuses running without a heartbeat since `stale_before` (all with `None`).
+/// A stalled job of this process is cancelled so that a new run can start.
+fn recover_stale_statuses<R: tauri::Runtime>(app: &tauri::AppHandle<R>, stale_before: Option<i64>) {
+    let (indexing, reindex) = {
+        let db = app.state::<DbState>();
+        let db_lock = db.lock().unwrap();
+        let Some(database) = db_lock.as_ref() else {
+            return;
+        };
+        let indexing = database
+            .recover_interrupted_indexing(stale_before)
+            .unwrap_or_else(|e| {
+                warn!("Failed to recover indexing status: {}", e);
+                false
+            });
+        let reindex = database
+            .recover_interrupted_reindex(stale_before)
+            .unwrap_or_else(|e| {
+                warn!("Failed to recover re-index status: {}", e);
+                false
+            });
+        (indexing, reindex)
+    };
+
+    if indexing {
+        warn!("Recovered an interrupted indexing run");
+        INDEXING_JOB.cancel();
+    }
+    if reindex {
+        warn!("Recovered an interrupted re-index run");
+        REINDEX_JOB.cancel();
+    }
+    if let Some(stale_before) = stale_before {
+        recover_stale_embedding(app, stale_before);
+    }
+}
+
+/// Upcoming deadlines (soonest first) for the agenda panel
+#[tauri::command]
+pub async fn get_upcoming_deadlines(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    days: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<DeadlineItem>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let now = Utc::now().timestamp();
+    let until = now + days.unwrap_or(30) * 86400;
+    database
+        .get_upcoming_deadlines(account_id.as_deref(), now, until, limit.unwrap_or(50))
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_suspicious_emails(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<SuspiciousEmail>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_suspicious_emails(account_id.as_deref(), limit.unwrap_or(50))
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Per-sender counts, unread ratio, priority, last contact and reply state, for the
+/// top senders and waiting-on-reply views
+#[tauri::command]
+pub async fn get_sender_stats(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    sort: Option<SenderSort>,
+    limit: Option<i64>,
+) -> Result<Vec<SenderStats>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_sender_stats(account_id.as_deref(), sort.unwrap_or_default(), limit.unwrap_or(50))
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Time window for inbox analytics, ending now
+#[derive(Debug, Clone, Copy, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsRange {
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl AnalyticsRange {
+    fn days(self) -> i64 {
+        match self {
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Quarter => 90,
+            Self::Year => 365,
+        }
+    }
+}
+
+/// Daily received/sent volume, category mix, reply latency and busiest hours
+#[tauri::command]
+pub async fn get_inbox_analytics(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    range: Option<AnalyticsRange>,
+) -> Result<InboxAnalytics, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let until = Utc::now().timestamp();
+    let from = until - range.unwrap_or_default().days() * 86400;
+    database
+        .get_inbox_analytics(account_id.as_deref(), from, until)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_email_trackers(
+    db: State<'_, DbState>,
+    email_id: String,
+) -> Result<Vec<TrackerInfo>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_email_trackers(&email_id)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Subject, participants and message count of a conversation
+#[tauri::command]
+pub async fn get_thread_summary(
+    db: State<'_, DbState>,
+    thread_id: String,
+) -> Result<Option<ThreadSummary>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_thread_summary(&thread_id)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Other folders and accounts the same message is cached in
+#[tauri::command]
+pub async fn get_email_copies(
+    db: State<'_, DbState>,
+    email_id: String,
+) -> Result<Vec<EmailCopy>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_email_copies(&email_id)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+// ========== Categories ==========
+
+/// Lowercase, dash-separated id derived from a category name
+fn category_id(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Recompute the RAG engine's category reference embeddings after a taxonomy change
+fn refresh_category_embeddings(categories: &[EmailCategory]) {
+    let mut rag_guard = crate::commands::rag::RAG_ENGINE.lock().unwrap();
+    if let Some(rag) = rag_guard.as_mut().filter(|rag| rag.is_initialized()) {
+        let references: Vec<(String, String)> = categories
+            .iter()
+            .map(|c| (c.id.clone(), format!("{}: {}", c.name, c.description)))
+            .collect();
+        if let Err(e) = rag.init_category_embeddings(&references) {
+            warn!("Failed to refresh category embeddings: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn get_categories(db: State<'_, DbState>) -> Result<Vec<EmailCategory>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_categories()
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_category(
+    db: State<'_, DbState>,
+    name: String,
+    description: Option<String>,
+) -> Result<EmailCategory, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let id = category_id(&name);
+    if id.is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
+
+    let category = database
+        .add_category(&id, name.trim(), description.as_deref().unwrap_or("").trim())
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    refresh_category_embeddings(&database.get_categories().map_err(|e: anyhow::Error| e.to_string())?);
+
+    Ok(category)
+}
+
+/// Rename a category or change its description; the id (stored on emails) is unchanged
+#[tauri::command]
+pub async fn update_category(
+    db: State<'_, DbState>,
+    id: String,
+    name: String,
+    description: Option<String>,
+) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    if name.trim().is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
+
+    let description = match description {
+        Some(description) => description,
+        None => database
+            .get_categories()
+            .map_err(|e: anyhow::Error| e.to_string())?
+            .into_iter()
+            .find(|c| c.id == id)
+            .map(|c| c.description)
+            .unwrap_or_default(),
+    };
+
+    database
+        .update_category(&id, name.trim(), description.trim())
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    refresh_category_embeddings(&database.get_categories().map_err(|e: anyhow::Error| e.to_string())?);
+
+    Ok(())
+}
+
+/// Delete a category; its emails move to "general"
+#[tauri::command]
+pub async fn delete_category(db: State<'_, DbState>, id: String) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    if id == DEFAULT_CATEGORY {
+        return Err(format!("The '{}' category cannot be deleted", DEFAULT_CATEGORY));
+    }
+
+    database
+        .delete_category(&id, DEFAULT_CATEGORY)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    refresh_category_embeddings(&database.get_categories().map_err(|e: anyhow::Error| e.to_string())?);
+
+    Ok(())
+}
+
+/// Re-run category classification over already indexed emails in the background.
+/// Emits `reclassify:progress` (percent) and `reclassify:complete` (number updated).
+/// Only one run at a time; starting another while one is active is an error.
+#[tauri::command]
+pub async fn reclassify_emails<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    _db: State<'_, DbState>,
+    account_id: Option<String>,
+    max_emails: Option<i64>,
+) -> Result<(), String> {
+    let db_path = paths::email_db_path().map_err(|e| e.to_string())?;
+    let database = EmailDatabase::new(db_path).map_err(|e| e.to_string())?;
+
+    if !RECLASSIFY_JOB.start() {
+        return Err("Re-classification already in progress".to_string());
+    }
+    let job = TrackedJob::start(&app, JobKind::Reclassify, Some(RECLASSIFY_JOB.clone()));
+
+    task::spawn(async move {
+        let result = reclassify_emails_background(&app, &database, &RECLASSIFY_JOB, &job, account_id, max_emails.unwrap_or(1000)).await;
+        RECLASSIFY_JOB.finish();
+        match &result {
+            Ok(false) => job.finish(JobStatus::Cancelled, None),
+            _ => job.finish_with(&result),
+        }
+        if let Err(e) = result {
+            error!("Re-classification error: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns false when cancelled before finishing
+#[tracing::instrument(name = "reclassify", skip_all)]
+async fn reclassify_emails_background<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    database: &EmailDatabase,
+    control: &JobControl,
+    job: &TrackedJob<R>,
+    account_id: Option<String>,
+    max_emails: i64,
+) -> Result<bool> {
+    // Models freed while idle are needed for the whole run
+    crate::commands::ai::ensure_model_loaded().await;
+    crate::commands::rag::ensure_embedding_loaded(app).await;
+
+    let categories = database.get_categories()?;
+    let emails = database.get_indexed_emails(account_id.as_deref(), max_emails)?;
+    let total = emails.len();
+    info!(
+        "Re-classifying {} emails into {} categories",
+        total,
+        categories.len()
+    );
+
+    for (idx, email) in emails.iter().enumerate() {
+        if !control.checkpoint().await {
+            return Ok(false);
+        }
+
+        let category = classify_category(email, &categories).await;
+        if let Err(e) = database.update_email_category(&email.id, &category) {
+            warn!("Failed to update category for {}: {}", email.id, e);
+        }
+
+        let progress = ((idx + 1) as f64 / total as f64 * 100.0) as i32;
+        let _ = app.emit("reclassify:progress", progress);
+        job.progress((idx + 1) as i64, total as i64);
+    }
+
+    let _ = app.emit("reclassify:complete", total);
+    Ok(true)
+}
+
+/// Run insight generation over emails already in the local cache, without touching the network.
+/// Works in batches of `batch_size` with up to `concurrency` emails in flight. An interrupted run
+/// (app closed, error) resumes where it stopped unless `restart` is set. By default only emails
+/// without insights are analyzed; `only_missing: false` re-analyzes everything.
+/// Emits `reindex:progress` and `reindex:complete` / `reindex:error` with the `ReindexStatus`.
+#[tauri::command]
+pub async fn reindex_cached_emails<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    _db: State<'_, DbState>,
+    account_id: Option<String>,
+    only_missing: Option<bool>,
+    batch_size: Option<usize>,
+    concurrency: Option<usize>,
+    restart: Option<bool>,
+) -> Result<ReindexStatus, String> {
+    let db_path = paths::email_db_path().map_err(|e| e.to_string())?;
+    let database = EmailDatabase::new(db_path).map_err(|e| e.to_string())?;
+
+    if !REINDEX_JOB.start() {
+        return Err("Re-indexing already in progress".to_string());
+    }
+
+    let status = match prepare_reindex(&database, account_id, only_missing.unwrap_or(true), restart.unwrap_or(false)) {
+        Ok(status) => status,
+        Err(e) => {
+            REINDEX_JOB.finish();
+            return Err(e.to_string());
+        }
+    };
+
+    let batch_size = batch_size.unwrap_or(DEFAULT_REINDEX_BATCH_SIZE).clamp(1, 200);
+    let concurrency = concurrency.unwrap_or(MAX_REINDEX_CONCURRENCY).clamp(1, MAX_REINDEX_CONCURRENCY);
+
+    let job = TrackedJob::start(&app, JobKind::Reindex, Some(REINDEX_JOB.clone()));
+
+    task::spawn(async move {
+        let result = reindex_cached_background(&app, &database, &job, batch_size, concurrency).await;
+        let (event, error) = match &result {
+            Ok(true) => ("reindex:complete", None),
+            // Keep the cursor so the run can be resumed
+            Ok(false) => ("reindex:cancelled", Some("Cancelled".to_string())),
+            Err(e) => {
+                error!("Reindexing failed: {}", e);
+                ("reindex:error", Some(e.to_string()))
+            }
+        };
+        let _ = database.finish_reindex(error.as_deref());
+        REINDEX_JOB.finish();
+
+        match &result {
+            Ok(false) => job.finish(JobStatus::Cancelled, None),
+            _ => job.finish_with(&result),
+        }
+        if let Ok(status) = database.get_reindex_status() {
+            let _ = app.emit(event, status);
+        }
+    });
+
+    Ok(status)
+}
+
+/// Progress of the current or last re-index run
+#[tauri::command]
+pub async fn get_reindex_status(db: State<'_, DbState>) -> Result<ReindexStatus, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let mut status = database
+        .get_reindex_status()
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    // A run marked as running in the database but not in this process was interrupted
+    status.is_running = status.is_running && REINDEX_JOB.state() != JobState::Idle;
+    Ok(status)
+}
+
+/// Resume the previous run when it was interrupted with the same scope, otherwise start over
+fn prepare_reindex(
+    database: &EmailDatabase,
+    account_id: Option<String>,
+    only_missing: bool,
+    restart: bool,
+) -> Result<ReindexStatus> {
+    let previous = database.get_reindex_status()?;
+    let resumable = previous.cursor_id.is_some()
+        && previous.account_id == account_id
+        && previous.only_missing == only_missing;
+
+    if resumable && !restart {
+        info!(
+            "Resuming at {}/{} emails",
+            previous.processed_emails, previous.total_emails
+        );
+        database.resume_reindex()?;
+    } else {
+        let total = database.count_cached_emails(account_id.as_deref(), only_missing)?;
+        database.start_reindex(account_id.as_deref(), only_missing, total)?;
+    }
+
+    database.get_reindex_status()
+}
+
+/// Returns false when cancelled before finishing
+#[tracing::instrument(name = "reindex", skip_all)]
+async fn reindex_cached_background<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    database: &EmailDatabase,
+    job: &TrackedJob<R>,
+    batch_size: usize,
+    concurrency: usize,
+) -> Result<bool> {
+    // Models freed while idle are needed for the whole run
+    crate::commands::ai::ensure_model_loaded().await;
+    crate::commands::rag::ensure_embedding_loaded(app).await;
+
+    let status = database.get_reindex_status()?;
+    let categories = database.get_categories()?;
+    let mut processed = status.processed_emails;
+    let mut cursor = status.cursor_date.zip(status.cursor_id);
+
+    info!(
+        "Analyzing cached emails in batches of {} ({} concurrent)",
+        batch_size, concurrency
+    );
+
+    loop {
+        if !REINDEX_JOB.checkpoint().await {
+            info!("Cancelled after {} emails", processed);
+            return Ok(false);
+        }
+
+        let batch = database.get_cached_emails_after(
+            status.account_id.as_deref(),
+            status.only_missing,
+            cursor.as_ref().map(|(date, id)| (*date, id.as_str())),
+            batch_size as i64,
+        )?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        let next_cursor = (last.date_timestamp, last.id.clone());
+
+        let insights: Vec<EmailInsight> = stream::iter(batch.iter())
+            .map(|email| generate_email_insights(database, email, &categories))
+            .buffer_unordered(concurrency)
+            .collect()
+            .await;
+
+        for insight in &insights {
+            if let Err(e) = database.store_insights(insight) {
+                warn!("Failed to store insights for {}: {}", insight.email_id, e);
+            }
+        }
+
+        processed += batch.len() as i64;
+        database.update_reindex_progress(processed, next_cursor.0, &next_cursor.1)?;
+        cursor = Some(next_cursor);
+
+        let _ = app.emit("reindex:progress", database.get_reindex_status()?);
+        job.progress(processed, status.total_emails);
+    }
+
+    info!("Done, {} emails analyzed", processed);
+    Ok(true)
+}
+
+/// Emails pulled per account from IMAP when its cache has nothing new to index
+const INDEXING_FETCH_LIMIT: u32 = 50;
+
+/// Build the smart inbox for every connected account (or just `account_id`).
+/// Each account is first topped up from IMAP when `sync_first` is set or its cache is empty,
+/// then its unindexed cached emails are analyzed. Progress is recorded per account.
 #[tauri::command]
 pub async fn start_email_indexing<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
     _db: State<'_, DbState>,
     max_emails: Option<usize>,
+    account_id: Option<String>,
+    sync_first: Option<bool>,
 ) -> Result<(), String> {
-    let project_dirs = ProjectDirs::from("com", "inboxed", "inboxed")
-        .ok_or("Failed to get project directory")?;
-    let data_dir = project_dirs.data_dir();
-    let db_path = data_dir.join("emails.db");
+    let db_path = paths::email_db_path().map_err(|e| e.to_string())?;
     let database = EmailDatabase::new(db_path).map_err(|e| e.to_string())?;
 
-    let status = database
-        .get_indexing_status()
-        .map_err(|e: anyhow::Error| e.to_string())?;
-    if status.is_indexing {
+    let accounts = match account_id {
+        Some(id) => vec![database
+            .get_account(&id)
+            .map_err(|e: anyhow::Error| e.to_string())?
+            .ok_or_else(|| format!("Account not found: {}", id))?],
+        None => database
+            .list_accounts()
+            .map_err(|e: anyhow::Error| e.to_string())?,
+    };
+
+    spawn_indexing(app, database, accounts, max_emails.unwrap_or(100), sync_first.unwrap_or(false))
+}
+
+/// Start the indexing loop in the background unless a run is already active
+fn spawn_indexing<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    database: EmailDatabase,
+    accounts: Vec<Account>,
+    max_emails: usize,
+    sync_first: bool,
+) -> Result<(), String> {
+    if !INDEXING_JOB.start() {
         return Err("Indexing already in progress".to_string());
     }
 
+    let job = TrackedJob::start(&app, JobKind::Indexing, Some(INDEXING_JOB.clone()));
+
     task::spawn(async move {
-        if let Err(e) = index_emails_background(app, database, max_emails.unwrap_or(100)).await {
-            eprintln!("Indexing error: {}", e);
+        let result = index_emails_background(app, &database, &job, accounts, max_emails, sync_first).await;
+        if let Err(e) = &result {
+            error!("Indexing error: {}", e);
+            let _ = database.update_indexing_status(false, None, None, Some(e.to_string()));
+            let _ = database.reset_account_indexing_statuses();
+        }
+        INDEXING_JOB.finish();
+
+        match &result {
+            Ok(false) => job.finish(JobStatus::Cancelled, None),
+            _ => job.finish_with(&result),
         }
     });
 
     Ok(())
 }
 
+/// Pause the running indexing job after the email it is currently analyzing
+#[tauri::command]
+pub async fn pause_indexing<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    db: State<'_, DbState>,
+) -> Result<(), String> {
+    if !INDEXING_JOB.pause() {
+        return Err("Indexing is not running".to_string());
+    }
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .set_indexing_paused(true)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let _ = app.emit("indexing:paused", ());
+    Ok(())
+}
+
+/// Resume a paused indexing job. A job that was paused or interrupted in an earlier
+/// session is restarted and continues with the emails that are still unindexed.
+#[tauri::command]
+pub async fn resume_indexing<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    db: State<'_, DbState>,
+) -> Result<(), String> {
+    let (status, accounts) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let status = database
+            .get_indexing_status()
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        let accounts = database
+            .list_accounts()
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        (status, accounts)
+    };
+
+    if INDEXING_JOB.resume() {
+        // Paused in this session: the loop picks up where it waited
+    } else if INDEXING_JOB.state() == JobState::Idle && (status.is_paused || status.is_indexing) {
+        let db_path = paths::email_db_path().map_err(|e| e.to_string())?;
+        let database = EmailDatabase::new(db_path).map_err(|e| e.to_string())?;
+        let max_emails = (status.total_emails - status.processed_emails).max(1) as usize;
+        spawn_indexing(app.clone(), database, accounts, max_emails, false)?;
+    } else {
+        return Err("No paused indexing job".to_string());
+    }
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .set_indexing_paused(false)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    let _ = app.emit("indexing:resumed", ());
+    Ok(())
+}
+
+/// Stop indexing after the current email. Insights already stored are kept.
+#[tauri::command]
+pub async fn cancel_indexing(db: State<'_, DbState>) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    if !INDEXING_JOB.cancel() {
+        // Nothing running here; drop a job left paused by an earlier session
+        database
+            .update_indexing_status(false, None, None, None)
+            .map_err(|e: anyhow::Error| e.to_string())?;
+        database
+            .reset_account_indexing_statuses()
+            .map_err(|e: anyhow::Error| e.to_string())?;
+    }
+    database
+        .set_indexing_paused(false)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    database
+        .set_indexing_checkpoint(None)
+        .map_err(|e: anyhow::Error| e.to_string())?;
+    Ok(())
+}
+
+/// Indexing progress of each connected account
+#[tauri::command]
+pub async fn get_account_indexing_status(
+    db: State<'_, DbState>,
+) -> Result<Vec<AccountIndexingStatus>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_account_indexing_statuses()
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Returns false when cancelled before finishing
+#[tracing::instrument(name = "indexing", skip_all)]
 async fn index_emails_background<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
-    database: EmailDatabase,
+    database: &EmailDatabase,
+    job: &TrackedJob<R>,
+    accounts: Vec<Account>,
     max_emails: usize,
-) -> Result<()> {
+    sync_first: bool,
+) -> Result<bool> {
+    // Models freed while idle are needed for the whole run
+    crate::commands::ai::ensure_model_loaded().await;
+    crate::commands::rag::ensure_embedding_loaded(&app).await;
+
     // Check if summarizer is available and model is loaded
-    {
-        let summarizer_guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = summarizer_guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                println!("[Indexing] Starting with LLM model loaded - summaries will use AI");
-            } else {
-                println!("[Indexing] Starting with fallback mode - summaries will use keyword extraction");
-            }
-        } else {
-            println!("[Indexing] WARNING: No summarizer available - summaries will be skipped");
-        }
+    if LLM_WORKER.is_model_loaded() {
+        info!("Starting with LLM model loaded - summaries will use AI");
+    } else if LLM_WORKER.is_initialized() {
+        info!("Starting with fallback mode - summaries will use keyword extraction");
+    } else {
+        warn!("No summarizer available - summaries will be skipped");
     }
 
     // Mark as indexing
     database.update_indexing_status(true, None, Some(0), None)?;
     let _ = app.emit("indexing:started", ());
 
-    // Get unindexed emails from local DB
-    let emails = match database.get_unindexed_emails(max_emails as i64) {
-        Ok(e) => e,
-        Err(e) => {
-            eprintln!("[Indexing] Failed to get unindexed emails: {}", e);
-            let _ = database.update_indexing_status(false, None, None, None);
-            let _ = app.emit("indexing:error", format!("Failed to get emails: {}", e));
-            return Err(anyhow::anyhow!("Failed to get unindexed emails: {}", e));
+    // Stage 1: top up each account's cache from IMAP where needed
+    let account_manager = app.state::<AccountManager>();
+    let mut fetch_errors: HashMap<String, String> = HashMap::new();
+    for account in &accounts {
+        database.update_account_indexing_status(&account.id, true, Some(0), None, None)?;
+
+        let needs_fetch = sync_first || database.count_cached_emails(Some(&account.id), false)? == 0;
+        if !needs_fetch {
+            continue;
         }
-    };
 
-    let total = emails.len() as i64;
+        if !INDEXING_JOB.checkpoint().await {
+            break;
+        }
+
+        job.message(format!("Fetching {}", account.email));
+        match cache_recent_messages(account, &account_manager, database, "INBOX", INDEXING_FETCH_LIMIT).await {
+            Ok(stored) => info!(account = %account.id, "Cached {} new emails from IMAP", stored),
+            Err(e) => {
+                // Keep going with whatever is already cached
+                warn!(account = %account.id, "IMAP fetch failed: {}", e);
+                fetch_errors.insert(account.id.clone(), e);
+            }
+        }
+    }
+
+    // Stage 2: collect unindexed cached emails per account, within the overall budget
+    let mut batches: Vec<(Option<&str>, Vec<Email>)> = Vec::new();
+    let mut budget = max_emails;
+    if accounts.is_empty() {
+        // No accounts configured: index whatever is cached
+        batches.push((None, database.get_unindexed_emails(None, budget as i64)?));
+    }
+    for account in &accounts {
+        let emails = if budget > 0 {
+            database.get_unindexed_emails(Some(&account.id), budget as i64)?
+        } else {
+            Vec::new()
+        };
+        budget -= emails.len();
+        database.update_account_indexing_status(&account.id, true, Some(emails.len() as i64), None, None)?;
+        batches.push((Some(account.id.as_str()), emails));
+    }
+
+    let total: i64 = batches.iter().map(|(_, emails)| emails.len() as i64).sum();
     database.update_indexing_status(true, Some(total), Some(0), None)?;
+    let categories = database.get_categories()?;
+    job.message("Analyzing emails");
 
-    // Process each email (generate insights)
-    for (idx, email) in emails.iter().enumerate() {
-        let insight = generate_email_insights(email).await;
+    // Stage 3: process each email (generate insights)
+    let mut processed: i64 = 0;
+    let mut cancelled = false;
+    for (account_id, emails) in &batches {
+        for (idx, email) in emails.iter().enumerate() {
+            if !INDEXING_JOB.checkpoint().await {
+                cancelled = true;
+                break;
+            }
+
+            let insight = generate_email_insights(database, email, &categories).await;
 
-        if let Err(e) = database.store_insights(&insight) {
-            eprintln!("Failed to store insights for {}: {}", email.id, e);
+            if let Err(e) = database.store_insights(&insight) {
+                warn!("Failed to store insights for {}: {}", email.id, e);
+            }
+            let _ = database.set_indexing_checkpoint(Some(&email.id));
+
+            processed += 1;
+            if let Err(e) = database.update_indexing_status(true, None, Some(processed), None) {
+                warn!("Failed to update progress: {}", e);
+            }
+            if let Some(account_id) = account_id {
+                let _ = database.update_account_indexing_status(account_id, true, None, Some((idx + 1) as i64), None);
+            }
+
+            let progress = (processed as f64 / total as f64 * 100.0) as i32;
+            let _ = app.emit("indexing:progress", progress);
+            job.progress(processed, total);
         }
 
-        let processed = (idx + 1) as i64;
-        if let Err(e) = database.update_indexing_status(true, None, Some(processed), None) {
-            eprintln!("Failed to update progress: {}", e);
+        if let Some(account_id) = account_id {
+            database.update_account_indexing_status(
+                account_id,
+                false,
+                None,
+                None,
+                fetch_errors.get(*account_id).map(String::as_str),
+            )?;
+        }
+        if cancelled {
+            break;
         }
+    }
+
+    database.reset_account_indexing_statuses()?;
+    database.set_indexing_paused(false)?;
+    database.update_indexing_status(false, None, None, None)?;
 
-        let progress = (processed as f64 / total as f64 * 100.0) as i32;
-        let _ = app.emit("indexing:progress", progress);
+    if cancelled || INDEXING_JOB.state() == JobState::Cancelled {
+        info!("Cancelled after {} emails", processed);
+        let _ = app.emit("indexing:cancelled", processed);
+        return Ok(false);
     }
 
     // Mark as complete
-    database.update_indexing_status(false, None, None, None)?;
+    database.set_indexing_checkpoint(None)?;
     let _ = app.emit("indexing:complete", ());
 
-    Ok(())
+    Ok(true)
 }
 
-async fn generate_email_insights(email: &Email) -> EmailInsight {
-    let body = email.body_plain.as_deref()
-        .or(email.body_html.as_deref())
-        .unwrap_or("");
+/// Pick a configured category: LLM when a model is loaded, embedding similarity otherwise
+async fn classify_category(email: &Email, categories: &[EmailCategory]) -> String {
+    let body = email.ai_text();
 
     let subject = email.subject.clone();
     let from = email.from.clone();
     let body_owned = body.to_string();
-    let is_starred = email.is_starred;
-
-    // --- LLM calls (summary + priority) in one spawn_blocking ---
-    let (summary, priority, priority_score) = match task::spawn_blocking(move || {
-        let summarizer_guard = SUMMARIZER.lock().unwrap();
-        if let Some(summarizer) = summarizer_guard.as_ref() {
-            if summarizer.is_model_loaded() {
-                let sum = summarizer.summarize_email(&subject, &from, &body_owned).ok();
-                let pri = summarizer.classify_priority(&subject, &from, &body_owned)
-                    .unwrap_or_else(|_| "MEDIUM".to_string());
-                let score: f64 = match pri.as_str() {
-                    "HIGH" => 0.85,
-                    "LOW" => 0.2,
-                    _ => 0.5,
-                };
-                // Boost score if starred
-                let score: f64 = if is_starred { (score + 0.15).min(1.0) } else { score };
-                // Upgrade to HIGH if starred and at least MEDIUM
-                let pri = if is_starred && score >= 0.5 { "HIGH".to_string() } else { pri };
-                (sum, pri, score)
-            } else {
-                // Model not loaded — defaults
-                let sum = summarizer.summarize_email(&subject, &from, &body_owned).ok();
-                let (pri, score) = if is_starred {
-                    ("HIGH".to_string(), 0.7)
-                } else {
-                    ("MEDIUM".to_string(), 0.5)
-                };
-                (sum, pri, score)
-            }
-        } else {
-            let (pri, score) = if is_starred {
-                ("HIGH".to_string(), 0.7)
-            } else {
-                ("MEDIUM".to_string(), 0.5)
-            };
-            (None, pri, score)
-        }
+    let options: Vec<(String, String, String)> = categories
+        .iter()
+        .map(|c| (c.id.clone(), c.name.clone(), c.description.clone()))
+        .collect();
+
+    let llm_category = LLM_WORKER
+        .run(Priority::Background, move |slot| {
+            slot.as_ref()
+                .filter(|summarizer| summarizer.is_model_loaded())
+                .and_then(|summarizer| summarizer.classify_category(&subject, &from, &body_owned, &options).ok())
+                .flatten()
+        })
+        .await
+        .ok()
+        .flatten();
+
+    if let Some(category) = llm_category {
+        return category;
+    }
+
+    let rag_guard = crate::commands::rag::RAG_ENGINE.lock().unwrap();
+    match rag_guard.as_ref() {
+        Some(rag) if rag.is_initialized() => rag
+            .classify_category(&email.subject, &email.from, body)
+            .unwrap_or_else(|_| DEFAULT_CATEGORY.to_string()),
+        _ => DEFAULT_CATEGORY.to_string(),
+    }
+}
+
+/// Priority label and score of an email: the model's guess (`model_score`, 0.5 when
+/// no model is loaded) and the starred flag weighed with the account's tuned weights,
+/// then adjusted for how the user treats the sender and the priorities they set by hand
+fn classify_priority_internal(
+    database: &EmailDatabase,
+    email: &Email,
+    model_score: f64,
+) -> (String, f64) {
+    let own_override = database.get_priority_override(&email.id).ok().flatten();
+    let sender = database
+        .get_sender_history(&email.account_id, &email.from_email, &email.id)
+        .unwrap_or_default();
+    let weights = database
+        .get_priority_weights(&email.account_id)
+        .unwrap_or_default();
+    let features = PriorityFeatures::new(model_score, email.is_starred, &sender);
+    priority::classify(&features, &weights, &sender, own_override.as_deref())
+}
+
+async fn generate_email_insights(
+    database: &EmailDatabase,
+    email: &Email,
+    categories: &[EmailCategory],
+) -> EmailInsight {
+    // Text read from attachments (receipts, scanned letters) counts as part of the body
+    if email.has_attachments {
+        read_email_attachments(database, &email.id).await;
+    }
+    let body = match database.get_email_attachment_text(&email.id).ok().flatten() {
+        Some(text) => format!("{}\n\n{}", email.ai_text(), text),
+        None => email.ai_text().to_string(),
+    };
+    let body = body.as_str();
+
+    let subject = email.subject.clone();
+    let from = email.from.clone();
+    let body_owned = body.to_string();
+
+    let detected_language = language::detect(&Summarizer::strip_html(body));
+    let language = summary_language(detected_language);
+
+    // --- LLM calls (summary + priority guess) in one background job ---
+    let (summary, model_priority) = match LLM_WORKER.run(Priority::Background, move |slot| {
+        let Some(summarizer) = slot.as_ref() else {
+            return (None, None);
+        };
+        let sum = summarizer.summarize_email(&subject, &from, &body_owned, language).ok();
+        let pri = summarizer.is_model_loaded().then(|| {
+            summarizer.classify_priority(&subject, &from, &body_owned)
+                .unwrap_or_else(|_| "MEDIUM".to_string())
+        });
+        (sum, pri)
     }).await {
         Ok(result) => result,
-        Err(_) => (None, "MEDIUM".to_string(), 0.5),
+        Err(_) => (None, None),
     };
 
-    // --- Embedding-based category classification (uses RAG engine) ---
-    let category = {
-        let rag_guard = crate::commands::rag::RAG_ENGINE.lock().unwrap();
-        if let Some(rag) = rag_guard.as_ref() {
-            if rag.is_initialized() {
-                rag.classify_category(&email.subject, &email.from, body)
-                    .unwrap_or_else(|_| "general".to_string())
-            } else {
-                "general".to_string()
-            }
-        } else {
-            "general".to_string()
-        }
-    };
+    // --- Priority (model guess, sender history and the user's corrections) ---
+    let model_score = model_priority.as_deref().map_or(0.5, priority::label_score);
+    let (priority, priority_score) = classify_priority_internal(database, email, model_score);
+
+    let category = classify_category(email, categories).await;
 
     // --- Metadata flags (simple checks, not used for classification) ---
     let body_lower = body.to_lowercase();
@@ -281,18 +1176,105 @@ async fn generate_email_insights(email: &Email) -> EmailInsight {
     let has_meeting = body_lower.contains("meeting") || body_lower.contains("call") || body_lower.contains("zoom") || body_lower.contains("teams");
     let has_financial = body_lower.contains("invoice") || body_lower.contains("payment") || body_lower.contains("$") || body_lower.contains("price");
 
+    // --- Deadline extraction (LLM first, rule-based fallback) ---
+    let rule_deadline = extract_deadline(&format!("{}\n{}", email.subject, body), email.date_timestamp);
+    let deadline_at = if has_deadline || rule_deadline.is_some() {
+        let subject = email.subject.clone();
+        let body_owned = body.to_string();
+        let sent_at = local_naive(email.date_timestamp)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M, %A").to_string())
+            .unwrap_or_default();
+
+        let llm_deadline = LLM_WORKER
+            .run(Priority::Background, move |slot| {
+                slot.as_ref()
+                    .filter(|summarizer| summarizer.is_model_loaded())
+                    .and_then(|summarizer| summarizer.extract_deadline(&subject, &body_owned, &sent_at).ok())
+                    .flatten()
+            })
+            .await
+        .ok()
+        .flatten()
+        .and_then(|answer| parse_llm_datetime(&answer))
+        .and_then(to_timestamp)
+        .filter(|ts| *ts >= email.date_timestamp);
+
+        llm_deadline.or(rule_deadline)
+    } else {
+        None
+    };
+    let has_deadline = has_deadline || deadline_at.is_some();
+
+    // --- Sentiment (LLM when loaded, keyword fallback otherwise) ---
+    let sentiment = {
+        let subject = email.subject.clone();
+        let body_owned = body.to_string();
+        LLM_WORKER
+            .run(Priority::Background, move |slot| match slot.as_ref() {
+                Some(summarizer) => summarizer.classify_sentiment(&subject, &body_owned).ok(),
+                None => Some(Summarizer::simple_sentiment(&subject, &body_owned)),
+            })
+            .await
+        .ok()
+        .flatten()
+    };
+
+    // --- Reply needed (heuristic filter, LLM confirmation when loaded) ---
+    let needs_reply = {
+        let subject = email.subject.clone();
+        let from_email = email.from_email.clone();
+        let body_owned = body.to_string();
+        LLM_WORKER
+            .run(Priority::Background, move |slot| match slot.as_ref() {
+                Some(summarizer) => summarizer.detect_needs_reply(&subject, &from_email, &body_owned).ok(),
+                None => Some(reply::needs_reply(&from_email, &subject, &Summarizer::strip_html(&body_owned))),
+            })
+            .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+    };
+
+    // --- Tracking pixels / tracker domains in the HTML body ---
+    let trackers = email
+        .body_html
+        .as_deref()
+        .map(detect_trackers)
+        .unwrap_or_default();
+    let has_trackers = !trackers.is_empty();
+
+    // --- Phishing heuristics ---
+    let phishing = crate::email::phishing::analyze(email);
+
     EmailInsight {
         email_id: email.id.clone(),
         summary,
         priority,
         priority_score,
+        model_score: Some(model_score),
         category: Some(category),
         insights: None,
         action_items: None,
         has_deadline,
+        deadline_at,
         has_meeting,
         has_financial,
-        sentiment: None,
+        sentiment,
+        has_trackers,
+        trackers: if has_trackers {
+            serde_json::to_string(&trackers).ok()
+        } else {
+            None
+        },
+        is_suspicious: phishing.is_suspicious,
+        phishing_score: phishing.score,
+        phishing_reasons: if phishing.reasons.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&phishing.reasons).ok()
+        },
+        needs_reply,
+        language: detected_language.map(str::to_string),
         indexed_at: Utc::now().timestamp(),
     }
 }
@@ -392,6 +1374,30 @@ pub async fn chat_query(
     app: tauri::AppHandle,
     db: State<'_, DbState>,
     query: String,
+) -> Result<String, String> {
+    ensure_unlocked()?;
+    answer_chat_query(app, db, query, None).await
+}
+
+/// Like `chat_query`, but streams the answer as `chat:token` events.
+/// Can be stopped with `cancel_chat_generation`.
+#[tauri::command]
+pub async fn chat_query_stream(
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    query: String,
+) -> Result<String, String> {
+    ensure_unlocked()?;
+    let task = begin_chat_generation();
+    answer_chat_query(app, db, query, Some(task.token())).await
+}
+
+/// Answer a chat query from email context; streams when a cancellation token is given
+async fn answer_chat_query(
+    app: tauri::AppHandle,
+    db: State<'_, DbState>,
+    query: String,
+    stream: Option<CancellationToken>,
 ) -> Result<String, String> {
     // Handle identity queries without LLM
     if is_identity_query(&query) {
@@ -402,14 +1408,15 @@ pub async fn chat_query(
 
     // Try RAG for search and general email questions
     if matches!(intent, QueryIntent::SearchEmails(_) | QueryIntent::GeneralEmailQuestion) {
+        crate::commands::rag::ensure_embedding_loaded(&app).await;
         let rag_ready = {
             let guard = crate::commands::rag::RAG_ENGINE.lock().unwrap();
             guard.as_ref().map(|r| r.is_initialized()).unwrap_or(false)
         };
         if rag_ready {
-            match crate::commands::rag::chat_with_context(app.clone(), query.clone(), 5) {
-                Ok(response) => return Ok(response),
-                Err(e) => eprintln!("[Chat] RAG fallback to SQL: {}", e),
+            match crate::commands::rag::answer_with_context(&app, &query, 5, stream.clone()).await {
+                Ok(response) => return Ok(response.answer),
+                Err(e) => warn!("RAG fallback to SQL: {}", e),
             }
         }
     }
@@ -476,49 +1483,52 @@ pub async fn chat_query(
     };
 
     // Ensure AI is initialized before chat
-    let needs_init = {
-        let summarizer_guard = SUMMARIZER.lock().unwrap();
-        match summarizer_guard.as_ref() {
-            Some(s) => !s.is_model_loaded(),
-            None => true,
-        }
-    };
-
-    if needs_init {
-        eprintln!("[Chat] SUMMARIZER not loaded, attempting initialization...");
-        match crate::commands::ai::init_ai().await {
-            Ok(_) => eprintln!("[Chat] Model loaded successfully"),
-            Err(e) => eprintln!("[Chat] Could not load model: {}", e),
+    crate::commands::ai::ensure_model_loaded().await;
+    if !LLM_WORKER.is_model_loaded() {
+        info!("Model not loaded, attempting initialization...");
+        match crate::commands::ai::init_ai(app.clone()).await {
+            Ok(_) => info!("Model loaded successfully"),
+            Err(e) => warn!("Could not load model: {}", e),
         }
     }
 
-    // Try to use LLM for response
-    let summarizer_guard = SUMMARIZER.lock().unwrap();
-    if let Some(summarizer) = summarizer_guard.as_ref() {
-        if summarizer.is_model_loaded() {
+    // Try to use LLM for response (None when no model is loaded)
+    let app_clone = app.clone();
+    let query_clone = query.clone();
+    let context_clone = email_context.clone();
+    let generated = LLM_WORKER
+        .run(Priority::Interactive, move |slot| {
+            let summarizer = slot.as_ref().filter(|s| s.is_model_loaded())?;
             // Use LLM for intelligent response
-            match summarizer.chat(&query, email_context.as_deref()) {
-                Ok(response) => return Ok(response),
-                Err(e) => {
-                    let err_msg = e.to_string();
-                    eprintln!("[Chat] LLM error: {}", err_msg);
-                    drop(summarizer_guard);
-                    // Return context with actual error info, not "model not loaded"
-                    if let Some(ctx) = email_context {
-                        return Ok(format!(
-                            "Here's what I found:\n\n{}\n\n(AI generation error: {})",
-                            ctx, err_msg
-                        ));
-                    }
-                    return Ok(format!(
-                        "I encountered an error generating a response: {}. Try asking again!",
-                        err_msg
-                    ));
-                }
+            Some(match stream {
+                Some(cancel) => summarizer.chat_stream(None, &[], &query_clone, context_clone.as_deref(), Some(cancel), |token| {
+                    emit_chat_token(&app_clone, None, token);
+                }),
+                None => summarizer.chat(&query_clone, context_clone.as_deref()),
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match generated {
+        Some(Ok(response)) => return Ok(response),
+        Some(Err(e)) => {
+            let err_msg = e.to_string();
+            warn!("LLM error: {}", err_msg);
+            // Return context with actual error info, not "model not loaded"
+            if let Some(ctx) = email_context {
+                return Ok(format!(
+                    "Here's what I found:\n\n{}\n\n(AI generation error: {})",
+                    ctx, err_msg
+                ));
             }
+            return Ok(format!(
+                "I encountered an error generating a response: {}. Try asking again!",
+                err_msg
+            ));
         }
+        None => {}
     }
-    drop(summarizer_guard);
 
     // Fallback: model genuinely not loaded
     if let Some(ctx) = email_context {
@@ -0,0 +1,338 @@
+//! Logs, health checks and diagnostics for bug reports
+//!
+//! `get_recent_logs` shows the tail of the log files in the app. `run_diagnostics` checks
+//! each account's servers and sign-in along with the database and AI models, for the
+//! diagnostics panel. `export_diagnostics` writes a plain-text report with the app
+//! version, platform, account setup and cache counts followed by recent logs. Email
+//! addresses in it are masked, and it holds no message contents or credentials.
+
+use chrono::Utc;
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::auth::account::Account;
+use crate::auth::storage::{get_account_tokens, get_tokens};
+use crate::commands::cache::get_data_dir;
+use crate::commands::email::{new_account_client, pop3_client_for};
+use crate::db::EmailDatabase;
+use crate::email::server_presets::ProviderType;
+use crate::llm::ModelManager;
+use crate::logging::{self, mask_emails};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Lines returned by `get_recent_logs` unless asked otherwise
+const DEFAULT_LOG_LINES: usize = 500;
+
+/// Log lines included in a diagnostics report
+const DIAGNOSTICS_LOG_LINES: usize = 5000;
+
+/// Longest a single network check may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Warning,
+    Failed,
+    /// Not applicable to the account, or not run because an earlier check failed
+    Skipped,
+}
+
+/// Outcome of one health check
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    /// `None` for checks of the app itself
+    pub account_id: Option<String>,
+    pub account_email: Option<String>,
+    /// `dns`, `token`, `imap`, `pop3`, `smtp`, `database` or `models`
+    pub check: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+impl DiagnosticCheck {
+    fn new(
+        account: Option<&Account>,
+        check: &str,
+        (status, detail): (CheckStatus, String),
+        started: Instant,
+    ) -> Self {
+        Self {
+            account_id: account.map(|account| account.id.clone()),
+            account_email: account.map(|account| account.email.clone()),
+            check: check.to_string(),
+            status,
+            detail,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+fn outcome(result: Result<String, String>) -> (CheckStatus, String) {
+    match result {
+        Ok(detail) => (CheckStatus::Ok, detail),
+        Err(e) => (CheckStatus::Failed, e),
+    }
+}
+
+/// Run a network check, failing it if it takes longer than `CHECK_TIMEOUT`
+async fn timed_check(
+    account: &Account,
+    check: &str,
+    future: impl Future<Output = Result<String, String>>,
+) -> DiagnosticCheck {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, future).await {
+        Ok(result) => outcome(result),
+        Err(_) => (
+            CheckStatus::Failed,
+            format!("Timed out after {} seconds", CHECK_TIMEOUT.as_secs()),
+        ),
+    };
+    DiagnosticCheck::new(Some(account), check, result, started)
+}
+
+async fn resolve_host(host: &str, port: u16) -> Result<String, String> {
+    let address = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("{} did not resolve: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("{} has no addresses", host))?;
+    Ok(format!("{} is {}", host, address.ip()))
+}
+
+fn check_token(account: &Account) -> (CheckStatus, String) {
+    if account.auth_type != "oauth2" {
+        return (CheckStatus::Skipped, "Signs in with a password".to_string());
+    }
+    let tokens = match get_account_tokens(&account.id).or_else(|_| get_tokens()) {
+        Ok(tokens) => tokens,
+        Err(e) => return (CheckStatus::Failed, format!("No token stored: {}", e)),
+    };
+    let remaining = tokens.expires_at - Utc::now();
+    if remaining > chrono::Duration::zero() {
+        (
+            CheckStatus::Ok,
+            format!("Expires in {} minutes", remaining.num_minutes()),
+        )
+    } else if tokens.refresh_token.is_some() {
+        (
+            CheckStatus::Ok,
+            "Expired, and will be refreshed on the next connection".to_string(),
+        )
+    } else {
+        (
+            CheckStatus::Failed,
+            "Expired with no refresh token; sign in again".to_string(),
+        )
+    }
+}
+
+/// DNS, sign-in and server checks for one account
+async fn check_account(account: &Account) -> Vec<DiagnosticCheck> {
+    let is_pop3 = account.provider_type() == ProviderType::Pop3;
+    let mut checks = Vec::new();
+
+    let dns = timed_check(account, "dns", async {
+        let mut resolved = vec![resolve_host(&account.imap_host, account.imap_port).await?];
+        if !is_pop3 {
+            resolved.push(resolve_host(&account.smtp_host, account.smtp_port).await?);
+        }
+        Ok(resolved.join(", "))
+    })
+    .await;
+    let resolved = dns.status == CheckStatus::Ok;
+    checks.push(dns);
+
+    let started = Instant::now();
+    checks.push(DiagnosticCheck::new(
+        Some(account),
+        "token",
+        check_token(account),
+        started,
+    ));
+
+    let skip = |check: &str, detail: &str| {
+        DiagnosticCheck::new(
+            Some(account),
+            check,
+            (CheckStatus::Skipped, detail.to_string()),
+            Instant::now(),
+        )
+    };
+    if is_pop3 {
+        if resolved {
+            checks.push(
+                timed_check(account, "pop3", async {
+                    pop3_client_for(account)?
+                        .check()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(format!("Signed in to {}", account.imap_host))
+                })
+                .await,
+            );
+        } else {
+            checks.push(skip("pop3", "The server's address did not resolve"));
+        }
+        checks.push(skip("smtp", "POP3 accounts can only download mail"));
+        return checks;
+    }
+    if !resolved {
+        checks.push(skip("imap", "The server's address did not resolve"));
+        checks.push(skip("smtp", "The server's address did not resolve"));
+        return checks;
+    }
+
+    checks.push(
+        timed_check(account, "imap", async {
+            let client = new_account_client(account).await?;
+            client.reconnect().await.map_err(|e| e.to_string())?;
+            Ok(format!("Signed in to {}", account.imap_host))
+        })
+        .await,
+    );
+    checks.push(
+        timed_check(account, "smtp", async {
+            let client = new_account_client(account).await?;
+            client.check_smtp().await.map_err(|e| e.to_string())?;
+            Ok(format!("Signed in to {}", account.smtp_host))
+        })
+        .await,
+    );
+    checks
+}
+
+fn check_models() -> (CheckStatus, String) {
+    let manager = match ModelManager::new() {
+        Ok(manager) => manager,
+        Err(e) => return (CheckStatus::Failed, e.to_string()),
+    };
+    match manager.find_any_downloaded_model() {
+        Some((model, _)) => (CheckStatus::Ok, format!("{} is downloaded", model.name)),
+        None => (
+            CheckStatus::Warning,
+            "No AI model downloaded, so summaries and chat are unavailable".to_string(),
+        ),
+    }
+}
+
+/// Check every account's servers and sign-in, the database's integrity and the AI
+/// models. Accounts are checked at the same time.
+#[tauri::command]
+pub async fn run_diagnostics(db: State<'_, DbState>) -> Result<Vec<DiagnosticCheck>, String> {
+    let (accounts, database_check) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let accounts = database.list_accounts().map_err(|e| e.to_string())?;
+
+        let started = Instant::now();
+        let integrity = match database.quick_check() {
+            Ok(problems) if problems.is_empty() => {
+                (CheckStatus::Ok, "No problems found".to_string())
+            }
+            Ok(problems) => (CheckStatus::Failed, problems.join("; ")),
+            Err(e) => (CheckStatus::Failed, e.to_string()),
+        };
+        (
+            accounts,
+            DiagnosticCheck::new(None, "database", integrity, started),
+        )
+    };
+
+    let mut checks: Vec<DiagnosticCheck> =
+        futures::future::join_all(accounts.iter().map(check_account))
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+    checks.push(database_check);
+    let started = Instant::now();
+    checks.push(DiagnosticCheck::new(
+        None,
+        "models",
+        check_models(),
+        started,
+    ));
+    Ok(checks)
+}
+
+/// The most recent log lines, oldest first
+#[tauri::command]
+pub async fn get_recent_logs(limit: Option<usize>) -> Result<Vec<String>, String> {
+    let data_dir = get_data_dir()?;
+    Ok(logging::recent_logs(
+        &data_dir,
+        limit.unwrap_or(DEFAULT_LOG_LINES),
+    ))
+}
+
+/// Write a diagnostics report to `path`, or to the data directory when no path is
+/// given. Returns where it was written.
+#[tauri::command]
+pub async fn export_diagnostics(
+    db: State<'_, DbState>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let data_dir = get_data_dir()?;
+    let now = Utc::now();
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Inboxed diagnostics");
+    let _ = writeln!(report, "Generated: {}", now.to_rfc3339());
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "Platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    {
+        let db_lock = db.lock().unwrap();
+        if let Some(database) = db_lock.as_ref() {
+            let accounts = database.list_accounts().unwrap_or_default();
+            let _ = writeln!(report, "Accounts: {}", accounts.len());
+            for account in &accounts {
+                let _ = writeln!(
+                    report,
+                    "  {} ({}, {})",
+                    account.email, account.provider, account.auth_type
+                );
+            }
+            let _ = writeln!(
+                report,
+                "Cached emails: {}",
+                database.get_email_count().unwrap_or(0)
+            );
+            let _ = writeln!(
+                report,
+                "Indexed emails: {}",
+                database.get_indexed_count().unwrap_or(0)
+            );
+        } else {
+            let _ = writeln!(report, "Database not initialized");
+        }
+    }
+
+    let _ = writeln!(report, "\n--- Recent logs ---");
+    for line in logging::recent_logs(&data_dir, DIAGNOSTICS_LOG_LINES) {
+        let _ = writeln!(report, "{}", line);
+    }
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => data_dir.join(format!("diagnostics-{}.txt", now.format("%Y%m%d-%H%M%S"))),
+    };
+    fs::write(&path, mask_emails(&report))
+        .map_err(|e| format!("Failed to write diagnostics: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
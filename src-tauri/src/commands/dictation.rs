@@ -0,0 +1,143 @@
+//! Dictation commands
+//!
+//! `transcribe_audio` turns a WAV recording from the composer into text with a local
+//! Whisper model, so dictation never leaves the machine. Speech models are downloaded
+//! like the LLMs, reporting `speech:progress`, and the loaded one is freed after the
+//! same idle timeout as the other models.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+use crate::commands::ai::model_manager;
+use crate::commands::jobs::TrackedJob;
+use crate::jobs::JobKind;
+use crate::llm::audio;
+use crate::llm::whisper::{self, SpeechModelOption};
+use crate::llm::SpeechEngine;
+use crate::settings;
+
+lazy_static::lazy_static! {
+    /// The loaded speech model and when it last transcribed
+    static ref SPEECH_ENGINE: Mutex<Option<(SpeechEngine, Instant)>> = Mutex::new(None);
+}
+
+/// Supported speech models
+#[tauri::command]
+pub fn get_available_speech_models() -> Vec<SpeechModelOption> {
+    whisper::get_available_speech_models()
+}
+
+/// Ids of the speech models that are downloaded
+#[tauri::command]
+pub fn get_downloaded_speech_models() -> Result<Vec<String>, String> {
+    let manager = model_manager()?;
+    Ok(whisper::get_available_speech_models()
+        .into_iter()
+        .map(|model| model.id)
+        .filter(|id| manager.is_speech_model_downloaded(id))
+        .collect())
+}
+
+/// Download a speech model, the default one unless `model_id` is given
+#[tauri::command]
+pub async fn download_speech_model(app: AppHandle, model_id: Option<String>) -> Result<(), String> {
+    let model_id = model_id.unwrap_or_else(|| whisper::DEFAULT_SPEECH_MODEL.to_string());
+    let manager = model_manager()?;
+
+    app.emit("speech:progress", 0.0f32)
+        .map_err(|e| e.to_string())?;
+
+    let app_clone = app.clone();
+    let job = TrackedJob::start(&app, JobKind::ModelDownload, None);
+    job.message(model_id.clone());
+    let job_clone = job.clone();
+
+    let result = manager
+        .download_speech_model(&model_id, move |progress| {
+            let _ = app_clone.emit("speech:progress", progress);
+            job_clone.percent(progress);
+        })
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string());
+    job.finish_with(&result);
+    result
+}
+
+/// Delete a speech model, unloading it first if it's loaded
+#[tauri::command]
+pub fn delete_speech_model(model_id: String) -> Result<(), String> {
+    {
+        let mut engine = SPEECH_ENGINE.lock().unwrap();
+        if engine
+            .as_ref()
+            .is_some_and(|(engine, _)| engine.model_id() == model_id)
+        {
+            *engine = None;
+        }
+    }
+    model_manager()?
+        .delete_speech_model(&model_id)
+        .map_err(|e| e.to_string())?;
+
+    if settings::current().ai.speech_model_id.as_deref() == Some(model_id.as_str()) {
+        if let Err(e) = settings::update(|settings| settings.ai.speech_model_id = None) {
+            warn!("Failed to clear the speech model choice: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Transcribe a WAV recording. Uses the speech model chosen in the settings, or the
+/// first downloaded one.
+#[tauri::command]
+pub async fn transcribe_audio(wav_bytes: Vec<u8>) -> Result<String, String> {
+    let manager = model_manager()?;
+    let model_id = settings::current()
+        .ai
+        .speech_model_id
+        .filter(|id| manager.is_speech_model_downloaded(id))
+        .or_else(|| manager.find_downloaded_speech_model())
+        .ok_or("No speech model downloaded")?;
+    let dir = manager.speech_model_dir(&model_id);
+
+    tokio::task::spawn_blocking(move || {
+        let samples = audio::decode_wav(&wav_bytes).map_err(|e| e.to_string())?;
+
+        let mut guard = SPEECH_ENGINE.lock().unwrap();
+        if guard
+            .as_ref()
+            .is_none_or(|(engine, _)| engine.model_id() != model_id)
+        {
+            info!("Loading speech model {}", model_id);
+            *guard = None;
+            let engine = SpeechEngine::load(&model_id, &dir).map_err(|e| e.to_string())?;
+            *guard = Some((engine, Instant::now()));
+        }
+        let (engine, last_used) = guard.as_mut().ok_or("Speech model not loaded")?;
+        *last_used = Instant::now();
+        engine.transcribe(&samples).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Free the speech model if it hasn't transcribed anything for `timeout`
+pub(crate) fn unload_speech_if_idle(timeout: Duration) {
+    // Busy transcribing, so not idle
+    let Ok(mut guard) = SPEECH_ENGINE.try_lock() else {
+        return;
+    };
+    if guard
+        .as_ref()
+        .is_some_and(|(_, last_used)| last_used.elapsed() >= timeout)
+    {
+        *guard = None;
+        info!(
+            "Unloaded speech model after {} idle minutes",
+            timeout.as_secs() / 60
+        );
+    }
+}
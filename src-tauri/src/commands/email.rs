@@ -1,16 +1,41 @@
+use crate::auth::account::Account;
 use crate::auth::oauth::refresh_access_token_for_provider;
 use crate::auth::storage::{get_account_tokens, get_tokens, store_account_tokens, store_tokens};
 use crate::commands::account::AccountManager;
+use crate::commands::jobs::TrackedJob;
+use crate::commands::rag::forget_embeddings;
+use crate::db::email_db::NewSentEmail;
+use crate::db::search_query::{SearchFilter, SearchQuery};
 use crate::db::EmailDatabase;
 use crate::email::idle::IdleManager;
-use crate::email::imap_client::{ImapClient, ImapCredentials};
+use crate::email::imap_client::{thread_id_for, ImapClient, ImapCredentials, OutgoingCrypto};
+use crate::email::inline::{rewrite_cid_references, to_data_uri};
+use crate::email::pop3::POP3_FOLDER;
+use crate::email::pop3_client::Pop3Client;
 use crate::email::provider::{EmailProvider, ImapFlag};
-use crate::email::server_presets::ServerConfig;
-use crate::email::types::{Email, EmailListItem};
+use crate::email::reply;
+use crate::email::sanitize::sanitize_html;
+use crate::email::server_presets::{get_special_folders, ProviderType, ServerConfig};
+use crate::email::special_folders::apply_overrides;
+use crate::email::sync_window::AccountSyncSettings;
+use crate::email::types::{Email, EmailListItem, Folder, FolderStatus, SpecialFolder};
+use crate::email::utf7;
+use crate::jobs::{JobControl, JobKind, JobState, JobStatus};
+use crate::llm::Summarizer;
+use crate::security::ensure_unlocked;
 use chrono::Utc;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
-use tauri::State;
+use tauri::{AppHandle, State};
+use tracing::{info, warn};
+
+/// Messages fetched per IMAP command when filling the cache
+const FETCH_BATCH_SIZE: usize = 25;
+
+/// Newest messages of a listed folder whose bodies are fetched right away
+const PREFETCH_BODIES: usize = 10;
 
 type DbState = Arc<Mutex<Option<EmailDatabase>>>;
 
@@ -23,7 +48,7 @@ pub struct FolderStats {
 }
 
 /// Parse a unified email ID "{account_id}:{folder}:{uid}" into parts
-fn parse_email_id(email_id: &str) -> Option<(String, String, u32)> {
+pub(crate) fn parse_email_id(email_id: &str) -> Option<(String, String, u32)> {
     let parts: Vec<&str> = email_id.splitn(3, ':').collect();
     if parts.len() == 3 {
         let uid = parts[2].parse::<u32>().ok()?;
@@ -46,7 +71,7 @@ async fn resolve_oauth2_credentials(
     // Check if token is expired (with 60s buffer to avoid edge-case failures)
     let buffer = chrono::Duration::seconds(60);
     if tokens.expires_at <= Utc::now() + buffer {
-        eprintln!("[IMAP:{}] Token expired, refreshing...", account_id);
+        info!(account = %account_id, "Token expired, refreshing...");
         if let Some(refresh_token) = &tokens.refresh_token {
             let new_tokens = refresh_access_token_for_provider(
                 refresh_token,
@@ -60,7 +85,7 @@ async fn resolve_oauth2_credentials(
             let _ = store_account_tokens(account_id, &new_tokens);
             let _ = store_tokens(&new_tokens);
 
-            eprintln!("[IMAP:{}] Token refreshed successfully", account_id);
+            info!(account = %account_id, "Token refreshed successfully");
             return Ok(ImapCredentials::OAuth2 {
                 user: email.to_string(),
                 access_token: new_tokens.access_token,
@@ -76,22 +101,31 @@ async fn resolve_oauth2_credentials(
     })
 }
 
+/// Load the active account from the DB
+fn get_active_account(db: &DbState) -> Result<Account, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .get_active_account()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No active account. Please add an account first.".to_string())
+}
+
 /// Get or create an ImapClient for the active account.
 /// For OAuth2 accounts, automatically refreshes expired tokens and recreates the client.
 async fn get_active_client(
     db: &DbState,
     account_manager: &AccountManager,
 ) -> Result<Arc<tokio::sync::Mutex<ImapClient>>, String> {
-    // Get active account from DB
-    let account = {
-        let db_lock = db.lock().unwrap();
-        let database = db_lock.as_ref().ok_or("Database not initialized")?;
-        database
-            .get_active_account()
-            .map_err(|e| e.to_string())?
-            .ok_or("No active account. Please add an account first.")?
-    };
+    let account = get_active_account(db)?;
+    get_account_client(&account, account_manager).await
+}
 
+/// Get or create an ImapClient for any connected account
+pub(crate) async fn get_account_client(
+    account: &Account,
+    account_manager: &AccountManager,
+) -> Result<Arc<tokio::sync::Mutex<ImapClient>>, String> {
     // For OAuth2 accounts, check token expiry even if client is cached
     if account.auth_type == "oauth2" {
         let tokens = get_account_tokens(&account.id)
@@ -109,12 +143,26 @@ async fn get_active_client(
         }
     }
 
+    if account.provider_type() == ProviderType::Pop3 {
+        return Err("POP3 accounts can only download mail".to_string());
+    }
+
     // Return cached client if it exists
     if let Some(client) = account_manager.get_client(&account.id) {
         return Ok(client);
     }
 
-    // Create a new client with fresh credentials
+    let client = new_account_client(account).await?;
+    account_manager.add_client(account.id.clone(), client);
+
+    account_manager
+        .get_client(&account.id)
+        .ok_or_else(|| "Failed to store client".to_string())
+}
+
+/// Build a client for an IMAP account with fresh credentials, refreshing an expired
+/// OAuth2 token first. It connects on first use.
+pub(crate) async fn new_account_client(account: &Account) -> Result<ImapClient, String> {
     let provider_str = match account.provider_type() {
         crate::email::server_presets::ProviderType::Gmail => "gmail",
         crate::email::server_presets::ProviderType::Outlook => "microsoft",
@@ -138,21 +186,231 @@ async fn get_active_client(
         smtp_host: account.smtp_host.clone(),
         smtp_port: account.smtp_port,
         use_tls: true,
+        cert_fingerprint: account.cert_fingerprint.clone(),
     };
 
-    let client = ImapClient::new(
+    Ok(ImapClient::new(
         account.id.clone(),
         account.email.clone(),
         account.provider_type(),
         server_config,
         credentials,
-    );
+    ))
+}
 
-    account_manager.add_client(account.id.clone(), client);
+/// Build the download client for a POP3 account
+pub(crate) fn pop3_client_for(account: &Account) -> Result<Pop3Client, String> {
+    let password = crate::auth::storage::get_app_password(&account.id)
+        .map_err(|e| format!("No password for account: {}", e))?;
+    let server_config = ServerConfig {
+        imap_host: account.imap_host.clone(),
+        imap_port: account.imap_port,
+        smtp_host: account.smtp_host.clone(),
+        smtp_port: account.smtp_port,
+        use_tls: true,
+        cert_fingerprint: account.cert_fingerprint.clone(),
+    };
+    Ok(Pop3Client::new(
+        account.id.clone(),
+        account.email.clone(),
+        server_config,
+        ImapCredentials::Password {
+            user: account.email.clone(),
+            password,
+        },
+    ))
+}
 
-    account_manager
-        .get_client(&account.id)
-        .ok_or_else(|| "Failed to store client".to_string())
+/// Download up to `limit` POP3 messages whose UIDL hasn't been downloaded before
+async fn download_pop3_messages(
+    account: &Account,
+    known: &HashSet<String>,
+    limit: u32,
+) -> Result<Vec<(String, Email)>, String> {
+    pop3_client_for(account)?
+        .download_new(known, limit as usize)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Cache downloaded POP3 messages and record their UIDLs. Returns how many were stored.
+fn store_pop3_messages(
+    account: &Account,
+    database: &EmailDatabase,
+    downloads: Vec<(String, Email)>,
+) -> usize {
+    let mut stored = 0;
+    for (uidl, email) in downloads {
+        if !email.inline_parts.is_empty() {
+            let _ = crate::commands::cache::store_inline_parts(&email.id, &email.inline_parts);
+        }
+        let result = database
+            .store_email(&email)
+            .and_then(|_| database.record_pop3_uidl(&account.id, &uidl, &email.id));
+        match result {
+            Ok(()) => {
+                // POP3 can't fetch a single message again later, so every attachment is kept
+                let _ = crate::commands::cache::store_attachments(database, &email, u64::MAX);
+                stored += 1
+            }
+            Err(e) => warn!(account = %account.id, "Failed to cache {}: {}", email.id, e),
+        }
+    }
+    stored
+}
+
+/// Pull the newest `limit` messages of `folder` for an account into the local cache,
+/// skipping ones already cached. Returns how many were newly stored.
+#[tracing::instrument(skip_all, fields(account = %account.id, folder = %folder))]
+pub(crate) async fn cache_recent_messages(
+    account: &Account,
+    account_manager: &AccountManager,
+    database: &EmailDatabase,
+    folder: &str,
+    limit: u32,
+) -> Result<usize, String> {
+    let sync_settings = database
+        .get_account_sync_settings(&account.id)
+        .map_err(|e| e.to_string())?;
+    if !sync_settings.syncs_folder(folder) {
+        return Ok(0);
+    }
+    let limit = sync_settings.limit(limit);
+
+    if account.provider_type() == ProviderType::Pop3 {
+        if !folder.eq_ignore_ascii_case(POP3_FOLDER) {
+            return Ok(0);
+        }
+        let known = database
+            .get_pop3_uidls(&account.id)
+            .map_err(|e| e.to_string())?;
+        let downloads = download_pop3_messages(account, &known, limit).await?;
+        return Ok(store_pop3_messages(account, database, downloads));
+    }
+
+    let client_arc = get_account_client(account, account_manager).await?;
+    let client = client_arc.lock().await;
+    let items = client
+        .list_messages(folder, limit, 0)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let attachment_limit = crate::commands::cache::attachment_auto_download_limit();
+    let now = Utc::now().timestamp();
+    let uids: Vec<u32> = items
+        .iter()
+        .filter(|item| {
+            sync_settings.in_window(&item.date, now)
+                && !matches!(database.has_body(&item.id), Ok(Some(true)))
+        })
+        .filter_map(|item| parse_email_id(&item.id).map(|(_, _, uid)| uid))
+        .collect();
+
+    // Batches download side by side and are stored as they arrive
+    let mut stored = 0;
+    let mut batches = client.fetch_batches(folder, &uids, FETCH_BATCH_SIZE);
+    while let Some((batch, fetched)) = batches.next().await {
+        let emails = match fetched {
+            Ok(emails) => emails,
+            Err(e) => {
+                warn!(account = %account.id, "Failed to fetch {} messages: {}", batch.len(), e);
+                continue;
+            }
+        };
+        for email in emails {
+            if !email.inline_parts.is_empty() {
+                let _ = crate::commands::cache::store_inline_parts(&email.id, &email.inline_parts);
+            }
+            match database.store_email(&email) {
+                Ok(()) => {
+                    if let Some(limit) = attachment_limit {
+                        let _ = crate::commands::cache::store_attachments(database, &email, limit);
+                    }
+                    stored += 1
+                }
+                Err(e) => {
+                    warn!(account = %account.id, "Failed to cache {}: {}", email.id, e)
+                }
+            }
+        }
+    }
+
+    sync_cached_flags(account, &client, database, folder, limit).await;
+
+    Ok(stored)
+}
+
+/// Bring read/starred state of cached emails in line with the server. With CONDSTORE
+/// only what changed since the last sync is fetched.
+async fn sync_cached_flags(
+    account: &Account,
+    client: &ImapClient,
+    database: &EmailDatabase,
+    folder: &str,
+    limit: u32,
+) {
+    let since = database
+        .get_folder_sync_state(&account.id, folder)
+        .ok()
+        .flatten();
+    let changes = match client.sync_flags(folder, since.as_ref(), limit).await {
+        Ok(changes) => changes,
+        Err(e) => {
+            warn!(account = %account.id, "Flag sync of {} failed: {}", folder, e);
+            return;
+        }
+    };
+
+    for message in &changes.flags {
+        let email_id = format!("{}:{}:{}", account.id, folder, message.uid);
+        if let Err(e) = database.update_email_flags(&email_id, message.is_read, message.is_starred)
+        {
+            warn!(account = %account.id, "Failed to update flags of {}: {}", email_id, e);
+        }
+    }
+    if let Some(state) = &changes.state {
+        if let Err(e) = database.set_folder_sync_state(&account.id, folder, state) {
+            warn!(account = %account.id, "Failed to save sync state: {}", e);
+        }
+    }
+}
+
+/// Cache any freshly parsed inline parts and rewrite `cid:` references in the HTML body
+/// to `data:` URIs so embedded images render without network access.
+fn resolve_inline_images(email: &mut Email) {
+    if !email.inline_parts.is_empty() {
+        if let Err(e) = crate::commands::cache::store_inline_parts(&email.id, &email.inline_parts) {
+            warn!("Failed to cache inline parts for {}: {}", email.id, e);
+        }
+    }
+
+    let Some(html) = email.body_html.as_ref() else {
+        return;
+    };
+    if !html.contains("cid:") && !html.contains("CID:") {
+        return;
+    }
+
+    let mut urls = HashMap::new();
+    if email.inline_parts.is_empty() {
+        // Loaded from the DB cache: rebuild from the cached files
+        for asset in crate::commands::cache::load_inline_assets(&email.id).unwrap_or_default() {
+            if let Ok(data) = std::fs::read(&asset.path) {
+                urls.insert(asset.content_id, to_data_uri(&asset.content_type, &data));
+            }
+        }
+    } else {
+        for part in &email.inline_parts {
+            urls.insert(
+                part.content_id.clone(),
+                to_data_uri(&part.content_type, &part.data),
+            );
+        }
+    }
+
+    if !urls.is_empty() {
+        email.body_html = Some(rewrite_cid_references(html, &urls));
+    }
 }
 
 /// Map frontend folder name (lowercase) to IMAP folder name (capitalized)
@@ -169,6 +427,7 @@ fn map_folder_name(folder: &str) -> &str {
 
 #[tauri::command]
 pub async fn fetch_emails(
+    app: AppHandle,
     db: State<'_, DbState>,
     account_manager: State<'_, AccountManager>,
     max_results: Option<u32>,
@@ -176,6 +435,7 @@ pub async fn fetch_emails(
     force_refresh: Option<bool>,
     folder: Option<String>,
 ) -> Result<Vec<EmailListItem>, String> {
+    ensure_unlocked()?;
     let should_refresh = force_refresh.unwrap_or(false);
     let imap_folder = folder
         .as_deref()
@@ -196,46 +456,207 @@ pub async fn fetch_emails(
         }
     }
 
+    let account = get_active_account(&db)?;
+    let (known, sync_settings) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let known = if account.provider_type() == ProviderType::Pop3 {
+            database
+                .get_pop3_uidls(&account.id)
+                .map_err(|e| e.to_string())?
+        } else {
+            HashSet::new()
+        };
+        let sync_settings = database
+            .get_account_sync_settings(&account.id)
+            .map_err(|e| e.to_string())?;
+        (known, sync_settings)
+    };
+
+    // POP3 accounts download new mail into the cache, which then serves the list
+    if account.provider_type() == ProviderType::Pop3 {
+        if imap_folder == POP3_FOLDER && sync_settings.syncs_folder(imap_folder) {
+            let limit = sync_settings.limit(max_results.unwrap_or(50));
+            let downloads = download_pop3_messages(&account, &known, limit).await?;
+            let db_lock = db.lock().unwrap();
+            let database = db_lock.as_ref().ok_or("Database not initialized")?;
+            store_pop3_messages(&account, database, downloads);
+        }
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        return database
+            .get_cached_emails(imap_folder, max_results.unwrap_or(50) as i64)
+            .map_err(|e| e.to_string());
+    }
+
     // Fetch via IMAP client
-    let client_arc = get_active_client(&db, &account_manager).await?;
-    let client = client_arc.lock().await;
-    let items = client
+    let client_arc = get_account_client(&account, &account_manager).await?;
+    let items = client_arc
+        .lock()
+        .await
         .list_messages(imap_folder, max_results.unwrap_or(50), 0)
         .await
         .map_err(|e| e.to_string())?;
 
-    // Cache the emails we fetched (fetch full for caching)
-    for item in &items {
-        if let Some((_, folder, uid)) = parse_email_id(&item.id) {
-            match client.get_message(&folder, uid).await {
-                Ok(email) => {
-                    let db_lock = db.lock().unwrap();
-                    if let Some(database) = db_lock.as_ref() {
-                        let _ = database.store_email(&email);
-                    }
-                }
-                Err(e) => eprintln!("Failed to fetch message uid={}: {}", uid, e),
-            }
+    // Cache the list entries right away and fetch the bodies of the newest few in the
+    // background; the others are fetched when opened. Only messages inside the account's
+    // sync window are cached, and none from folders it doesn't sync.
+    let now = Utc::now().timestamp();
+    let to_cache: Vec<EmailListItem> = if sync_settings.syncs_folder(imap_folder) {
+        items
+            .iter()
+            .filter(|item| sync_settings.in_window(&item.date, now))
+            .take(sync_settings.limit(items.len() as u32) as usize)
+            .cloned()
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let uids: Vec<u32> = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        if let Err(e) = database.store_email_headers(&account.id, imap_folder, &to_cache) {
+            warn!("Failed to cache the list of {}: {}", imap_folder, e);
         }
+        to_cache
+            .iter()
+            .take(PREFETCH_BODIES)
+            .filter(|item| !matches!(database.has_body(&item.id), Ok(Some(true))))
+            .filter_map(|item| parse_email_id(&item.id).map(|(_, _, uid)| uid))
+            .collect()
+    };
+    if !uids.is_empty() {
+        tauri::async_runtime::spawn(prefetch_bodies(
+            app,
+            db.inner().clone(),
+            client_arc,
+            imap_folder.to_string(),
+            uids,
+        ));
     }
 
     Ok(items)
 }
 
+/// Fetch and cache the bodies of messages listed by `fetch_emails`, as a cancellable
+/// sync job. The client is only held per batch, so opening an email doesn't wait for it.
+async fn prefetch_bodies(
+    app: AppHandle,
+    db: DbState,
+    client_arc: Arc<tokio::sync::Mutex<ImapClient>>,
+    folder: String,
+    uids: Vec<u32>,
+) {
+    let control = Arc::new(JobControl::new());
+    control.start();
+    let job = TrackedJob::start(&app, JobKind::Sync, Some(control.clone()));
+    job.message(folder.clone());
+    let attachment_limit = crate::commands::cache::attachment_auto_download_limit();
+
+    for (idx, batch) in uids.chunks(FETCH_BATCH_SIZE).enumerate() {
+        if !control.checkpoint().await {
+            break;
+        }
+        job.progress((idx * FETCH_BATCH_SIZE) as i64, uids.len() as i64);
+
+        let fetched = client_arc.lock().await.get_messages(&folder, batch).await;
+        let emails = match fetched {
+            Ok(emails) => emails,
+            Err(e) => {
+                warn!("Failed to fetch {} messages: {}", batch.len(), e);
+                continue;
+            }
+        };
+        let db_lock = db.lock().unwrap();
+        if let Some(database) = db_lock.as_ref() {
+            for email in &emails {
+                cache_email(database, email, attachment_limit);
+            }
+        }
+    }
+
+    if control.state() == JobState::Cancelled {
+        job.finish(JobStatus::Cancelled, None);
+    } else {
+        job.finish(JobStatus::Completed, None);
+    }
+}
+
+/// Store a fetched email with its inline parts and, within `attachment_limit`, its
+/// attachments
+fn cache_email(database: &EmailDatabase, email: &Email, attachment_limit: Option<u64>) {
+    if !email.inline_parts.is_empty() {
+        let _ = crate::commands::cache::store_inline_parts(&email.id, &email.inline_parts);
+    }
+    match database.store_email(email) {
+        Ok(()) => {
+            if let Some(limit) = attachment_limit {
+                let _ = crate::commands::cache::store_attachments(database, email, limit);
+            }
+        }
+        Err(e) => warn!("Failed to cache {}: {}", email.id, e),
+    }
+}
+
 #[tauri::command]
 pub async fn get_email(
     db: State<'_, DbState>,
     account_manager: State<'_, AccountManager>,
     email_id: String,
+    sanitize: Option<bool>,
+) -> Result<Email, String> {
+    ensure_unlocked()?;
+    let mut email = fetch_email_by_id(&db, &account_manager, &email_id).await?;
+    resolve_inline_images(&mut email);
+    clear_handled_receipt_request(&db, &mut email);
+
+    // Sanitize by default; callers must explicitly ask for the raw HTML
+    if sanitize.unwrap_or(true) {
+        sanitize_email_body(&db, &mut email);
+    }
+
+    Ok(email)
+}
+
+/// Drop the read receipt request of a freshly fetched email once the user answered it
+fn clear_handled_receipt_request(db: &State<'_, DbState>, email: &mut Email) {
+    if email.read_receipt_to.is_none() {
+        return;
+    }
+    let db_lock = db.lock().unwrap();
+    if let Some(database) = db_lock.as_ref() {
+        if database
+            .is_read_receipt_handled(&email.message_id)
+            .unwrap_or(false)
+        {
+            email.read_receipt_to = None;
+        }
+    }
+}
+
+/// Load an email from IMAP when its account is connected, falling back to the DB cache
+async fn fetch_email_by_id(
+    db: &State<'_, DbState>,
+    account_manager: &State<'_, AccountManager>,
+    email_id: &str,
 ) -> Result<Email, String> {
     // Try IMAP path: parse the composite ID
-    if let Some((account_id, folder, uid)) = parse_email_id(&email_id) {
+    if let Some((account_id, folder, uid)) = parse_email_id(email_id) {
         if let Some(client_arc) = account_manager.get_client(&account_id) {
             let client = client_arc.lock().await;
-            return client
+            let email = client
                 .get_message(&folder, uid)
                 .await
-                .map_err(|e| e.to_string());
+                .map_err(|e| e.to_string())?;
+            // Fill in the body of an email only listed so far
+            let db_lock = db.lock().unwrap();
+            if let Some(database) = db_lock.as_ref() {
+                if let Ok(Some(false)) = database.has_body(email_id) {
+                    let attachment_limit = crate::commands::cache::attachment_auto_download_limit();
+                    cache_email(database, &email, attachment_limit);
+                }
+            }
+            return Ok(email);
         }
     }
 
@@ -243,7 +664,7 @@ pub async fn get_email(
     {
         let db_lock = db.lock().unwrap();
         if let Some(database) = db_lock.as_ref() {
-            if let Ok(Some(email)) = database.get_email_by_id(&email_id) {
+            if let Ok(Some(email)) = database.get_email_by_id(email_id) {
                 return Ok(email);
             }
         }
@@ -252,7 +673,60 @@ pub async fn get_email(
     Err(format!("Email not found: {}", email_id))
 }
 
+/// Strip scripts, event handlers and (unless the sender is trusted) remote images from the HTML body
+fn sanitize_email_body(db: &State<'_, DbState>, email: &mut Email) {
+    let Some(html) = email.body_html.as_ref() else {
+        return;
+    };
+
+    let allow_remote = {
+        let db_lock = db.lock().unwrap();
+        db_lock
+            .as_ref()
+            .and_then(|database| database.is_remote_images_allowed(&email.from_email).ok())
+            .unwrap_or(false)
+    };
+
+    let sanitized = sanitize_html(html, allow_remote);
+    email.body_html = Some(sanitized.html);
+    email.remote_images_blocked = sanitized.blocked_remote;
+}
+
+/// Allow or revoke loading remote images in emails from a sender
 #[tauri::command]
+pub async fn set_remote_images_allowed(
+    db: State<'_, DbState>,
+    sender_email: String,
+    allowed: bool,
+) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .set_remote_images_allowed(&sender_email, allowed)
+        .map_err(|e| e.to_string())
+}
+
+/// Check whether remote images are allowed for a sender
+#[tauri::command]
+pub async fn get_remote_images_allowed(
+    db: State<'_, DbState>,
+    sender_email: String,
+) -> Result<bool, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .is_remote_images_allowed(&sender_email)
+        .map_err(|e| e.to_string())
+}
+
+/// Longest sent-body excerpt kept for follow-up drafts
+const SENT_PREVIEW_CHARS: usize = 1000;
+
+/// Send an email from the active account. `in_reply_to` is the id of the received email
+/// being answered; `followup_of` is the id of a sent email this nudges, which stops it
+/// being listed as awaiting a response.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn send_email(
     db: State<'_, DbState>,
     account_manager: State<'_, AccountManager>,
@@ -261,22 +735,105 @@ pub async fn send_email(
     body: String,
     cc: Option<Vec<String>>,
     bcc: Option<Vec<String>>,
+    sign: Option<bool>,
+    encrypt: Option<bool>,
+    in_reply_to: Option<String>,
+    followup_of: Option<i64>,
+    from_identity: Option<String>,
+    request_read_receipt: Option<bool>,
 ) -> Result<String, String> {
+    let (account, parent_message_id, identity) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let account = database
+            .get_active_account()
+            .map_err(|e| e.to_string())?
+            .ok_or("No active account. Please add an account first.")?;
+
+        // Thread the message under the email it answers
+        let parent_message_id = match (&in_reply_to, followup_of) {
+            (_, Some(sent_id)) => database
+                .get_sent_email(sent_id)
+                .map_err(|e| e.to_string())?
+                .map(|sent| sent.message_id),
+            (Some(email_id), None) => database
+                .get_email_by_id(email_id)
+                .map_err(|e| e.to_string())?
+                .map(|email| email.message_id),
+            (None, None) => None,
+        };
+
+        // Send-as address: must be one of the account's identities
+        let identity = match from_identity
+            .as_deref()
+            .map(str::trim)
+            .filter(|address| !address.eq_ignore_ascii_case(&account.email))
+        {
+            Some(address) => Some(
+                database
+                    .get_identity(&account.id, address)
+                    .map_err(|e| e.to_string())?
+                    .ok_or_else(|| {
+                        format!("{} is not an identity of {}", address, account.email)
+                    })?,
+            ),
+            None => None,
+        };
+        (account, parent_message_id, identity)
+    };
+
+    let from = identity.as_ref().map(|identity| identity.mailbox());
+    let signature = identity.and_then(|identity| identity.signature);
+    let body = match signature {
+        Some(signature) => format!("{}<br><br>-- <br>{}", body, signature),
+        None => body,
+    };
+
     // Send via IMAP/SMTP
-    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client_arc = get_account_client(&account, &account_manager).await?;
     let client = client_arc.lock().await;
-    client
-        .send_email(
-            &client.email,
-            to,
+    let message_id = client
+        .send_email_with_crypto(
+            from.as_deref().unwrap_or(&client.email),
+            to.clone(),
             cc.unwrap_or_default(),
             bcc.unwrap_or_default(),
             &subject,
             &body,
             "", // plain text version
+            parent_message_id.as_deref().filter(|id| !id.is_empty()),
+            request_read_receipt.unwrap_or(false),
+            OutgoingCrypto {
+                sign: sign.unwrap_or(false),
+                encrypt: encrypt.unwrap_or(false),
+            },
         )
         .await
         .map_err(|e| e.to_string())?;
+
+    // Tracked for analytics and follow-ups only, so a failure here doesn't fail the send
+    let body_text = reply::new_text(&Summarizer::strip_html(&body));
+    let body_preview: String = body_text.trim().chars().take(SENT_PREVIEW_CHARS).collect();
+    let sent = NewSentEmail {
+        account_id: &account.id,
+        to: &to,
+        subject: &subject,
+        message_id: &message_id,
+        thread_id: &thread_id_for(&message_id),
+        in_reply_to: in_reply_to.as_deref(),
+        body_preview: &body_preview,
+        expects_reply: reply::asks_for_reply(&subject, &body_text),
+    };
+    let db_lock = db.lock().unwrap();
+    if let Some(database) = db_lock.as_ref() {
+        let recorded = database.record_sent_email(&sent).and_then(|_| match followup_of {
+            Some(sent_id) => database.dismiss_followup(sent_id),
+            None => Ok(()),
+        });
+        if let Err(e) = recorded {
+            warn!("Failed to record sent email: {}", e);
+        }
+    }
     Ok("sent".to_string())
 }
 
@@ -318,42 +875,178 @@ pub async fn star_email(
         .map_err(|e| e.to_string())
 }
 
+/// Drop an email that left its folder from the cache, with its insights and embedding.
+/// It comes back under its new id when the folder it went to is fetched.
+fn forget_email(app: &AppHandle, db: &DbState, email_id: &str) {
+    {
+        let db_lock = db.lock().unwrap();
+        if let Some(database) = db_lock.as_ref() {
+            if let Err(e) = database.remove_email(email_id) {
+                warn!("Failed to drop email {} from cache: {}", email_id, e);
+            }
+        }
+    }
+    forget_embeddings(app, &[email_id.to_string()]);
+}
+
 #[tauri::command]
 pub async fn trash_email(
-    _db: State<'_, DbState>,
+    app: AppHandle,
+    db: State<'_, DbState>,
     account_manager: State<'_, AccountManager>,
     email_id: String,
 ) -> Result<(), String> {
-    let (account_id, folder, uid) = parse_email_id(&email_id)
+    move_to_role(&app, &db, &account_manager, &email_id, SpecialFolder::Trash).await
+}
+
+#[tauri::command]
+pub async fn archive_email(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    email_id: String,
+) -> Result<(), String> {
+    let archive = SpecialFolder::Archive;
+    move_to_role(&app, &db, &account_manager, &email_id, archive).await
+}
+
+/// Move an email to its account's Trash, Archive… folder. If the move fails, the
+/// remembered folder may be out of date, so it is looked up again next time.
+async fn move_to_role(
+    app: &AppHandle,
+    db: &DbState,
+    account_manager: &AccountManager,
+    email_id: &str,
+    role: SpecialFolder,
+) -> Result<(), String> {
+    let (account_id, _, _) =
+        parse_email_id(email_id).ok_or_else(|| format!("Invalid email ID: {}", email_id))?;
+    let target = role_folder(db, account_manager, &account_id, role).await?;
+    let result = move_and_forget(app, db, account_manager, email_id, &target).await;
+    if result.is_err() {
+        let db_lock = db.lock().unwrap();
+        if let Some(database) = db_lock.as_ref() {
+            if let Err(e) = database.clear_special_folders(&account_id) {
+                warn!("Failed to forget special folders of {}: {}", account_id, e);
+            }
+        }
+    }
+    result
+}
+
+/// Name of the folder with `role` in an account. The folders found by the last listing
+/// are remembered in the database; the server is only asked when none has the role.
+/// An account without an Archive folder gets one; for other roles the provider's usual
+/// name is used.
+async fn role_folder(
+    db: &DbState,
+    account_manager: &AccountManager,
+    account_id: &str,
+    role: SpecialFolder,
+) -> Result<String, String> {
+    let cached = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_special_folder(account_id, &role)
+            .map_err(|e| e.to_string())?
+    };
+    if let Some(folder) = cached {
+        return Ok(folder);
+    }
+
+    let client_arc = account_manager
+        .get_client(account_id)
+        .ok_or_else(|| format!("No client for account: {}", account_id))?;
+    let client = client_arc.lock().await;
+    let folders = account_folders(db, &client).await?;
+    if let Some(folder) = folders
+        .into_iter()
+        .find(|folder| folder.special.as_ref() == Some(&role))
+    {
+        return Ok(folder.name);
+    }
+
+    let presets = get_special_folders(&client.provider);
+    let name = match role {
+        SpecialFolder::Inbox => "INBOX",
+        SpecialFolder::Sent => presets.sent,
+        SpecialFolder::Trash => presets.trash,
+        SpecialFolder::Drafts => presets.drafts,
+        SpecialFolder::Spam => presets.spam,
+        SpecialFolder::Archive => presets.archive,
+        SpecialFolder::Starred => "Starred",
+    };
+    if role == SpecialFolder::Archive {
+        info!("Creating folder {} in account {}", name, account_id);
+        client
+            .create_folder(name)
+            .await
+            .map_err(|e| e.to_string())?;
+        account_folders(db, &client).await?;
+    }
+    Ok(name.to_string())
+}
+
+/// Move an email to `target_folder` on the server and drop it from the cache
+pub(crate) async fn move_and_forget(
+    app: &AppHandle,
+    db: &DbState,
+    account_manager: &AccountManager,
+    email_id: &str,
+    target_folder: &str,
+) -> Result<(), String> {
+    let (account_id, folder, uid) = parse_email_id(email_id)
         .ok_or_else(|| format!("Invalid email ID: {}", email_id))?;
     let client_arc = account_manager
         .get_client(&account_id)
         .ok_or_else(|| format!("No client for account: {}", account_id))?;
-    let client = client_arc.lock().await;
-    // Move to Trash folder
-    client
-        .move_message(&folder, uid, "Trash")
-        .await
-        .map_err(|e| e.to_string())
+    {
+        let client = client_arc.lock().await;
+        client
+            .move_message(&folder, uid, target_folder)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    forget_email(app, db, email_id);
+    Ok(())
 }
 
+/// Move an email to any folder of its account, e.g. when filed by drag-and-drop or a
+/// shortcut. The folder must exist on the server. The cached copy is dropped; the email
+/// comes back under its new id when the target folder is fetched.
 #[tauri::command]
-pub async fn archive_email(
-    _db: State<'_, DbState>,
+pub async fn move_email(
+    app: AppHandle,
+    db: State<'_, DbState>,
     account_manager: State<'_, AccountManager>,
     email_id: String,
+    target_folder: String,
 ) -> Result<(), String> {
     let (account_id, folder, uid) = parse_email_id(&email_id)
         .ok_or_else(|| format!("Invalid email ID: {}", email_id))?;
     let client_arc = account_manager
         .get_client(&account_id)
         .ok_or_else(|| format!("No client for account: {}", account_id))?;
-    let client = client_arc.lock().await;
-    // Move to Archive folder
-    client
-        .move_message(&folder, uid, "Archive")
-        .await
-        .map_err(|e| e.to_string())
+
+    {
+        let client = client_arc.lock().await;
+        let folders = client.list_folders().await.map_err(|e| e.to_string())?;
+        let target = folders
+            .iter()
+            .find(|f| f.name == target_folder || f.path == target_folder)
+            .ok_or_else(|| format!("No folder named {}", target_folder))?;
+        if target.name == folder {
+            return Ok(());
+        }
+        client
+            .move_message(&folder, uid, &target.name)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    forget_email(&app, &db, &email_id);
+    Ok(())
 }
 
 #[tauri::command]
@@ -362,14 +1055,34 @@ pub async fn start_idle_monitoring(
     db: State<'_, DbState>,
     idle_manager: State<'_, IdleManager>,
 ) -> Result<(), String> {
-    let account = {
+    let (account, sync_settings) = {
         let db_lock = db.lock().unwrap();
         let database = db_lock.as_ref().ok_or("Database not initialized")?;
-        database
+        let account = database
             .get_active_account()
             .map_err(|e| e.to_string())?
-            .ok_or("No active account")?
+            .ok_or("No active account")?;
+        let sync_settings = database
+            .get_account_sync_settings(&account.id)
+            .map_err(|e| e.to_string())?;
+        (account, sync_settings)
     };
+    watch_account(app, &idle_manager, &account, &sync_settings).await;
+    Ok(())
+}
+
+/// Start monitoring the folders an account's sync settings watch, in IDLE or by fast
+/// polling, replacing any monitors it already has
+pub(crate) async fn watch_account(
+    app: AppHandle,
+    idle_manager: &IdleManager,
+    account: &Account,
+    sync_settings: &AccountSyncSettings,
+) {
+    // POP3 has no push; its mail arrives when the inbox is refreshed
+    if account.provider_type() == ProviderType::Pop3 {
+        return;
+    }
 
     let server_config = ServerConfig {
         imap_host: account.imap_host.clone(),
@@ -377,6 +1090,7 @@ pub async fn start_idle_monitoring(
         smtp_host: account.smtp_host.clone(),
         smtp_port: account.smtp_port,
         use_tls: true,
+        cert_fingerprint: account.cert_fingerprint.clone(),
     };
 
     idle_manager
@@ -387,10 +1101,10 @@ pub async fn start_idle_monitoring(
             account.provider_type(),
             server_config,
             account.auth_type.clone(),
+            sync_settings.watched_folders(),
+            sync_settings.watch_mode(),
         )
         .await;
-
-    Ok(())
 }
 
 #[tauri::command]
@@ -438,7 +1152,7 @@ pub async fn get_folder_stats(
             }
             Err(e) => {
                 // Log error but continue with other folders
-                eprintln!("Failed to get stats for folder {}: {}", folder, e);
+                warn!("Failed to get stats for folder {}: {}", folder, e);
                 // Add zero counts for failed folders
                 stats.push(FolderStats {
                     folder_name: folder.to_string(),
@@ -450,4 +1164,239 @@ pub async fn get_folder_stats(
     }
 
     Ok(stats)
-}
\ No newline at end of file
+}
+
+/// Unread, total and recent counts of every folder of the active account. The counts
+/// are cached, and the cached ones are returned when the server can't be reached.
+#[tauri::command]
+pub async fn get_folder_status(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+) -> Result<Vec<FolderStatus>, String> {
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+
+    match client.folder_status().await {
+        Ok(statuses) => {
+            let db_lock = db.lock().unwrap();
+            if let Some(database) = db_lock.as_ref() {
+                if let Err(e) = database.set_folder_status(&client.account_id, &statuses) {
+                    warn!("Failed to cache folder status: {}", e);
+                }
+            }
+            Ok(statuses)
+        }
+        Err(e) => {
+            warn!("Failed to get folder status, using cached counts: {}", e);
+            let db_lock = db.lock().unwrap();
+            let database = db_lock.as_ref().ok_or("Database not initialized")?;
+            database
+                .get_folder_status(&client.account_id)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Folders of the active account
+#[tauri::command]
+pub async fn list_folders(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+) -> Result<Vec<Folder>, String> {
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+    account_folders(&db, &client).await
+}
+
+/// The folders of a client's account, with the special-folder roles the user gave them.
+/// Which folders have which role is remembered for `role_folder`.
+async fn account_folders(db: &DbState, client: &ImapClient) -> Result<Vec<Folder>, String> {
+    let mut folders = client.list_folders().await.map_err(|e| e.to_string())?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let overrides = database
+        .get_folder_roles(&client.account_id)
+        .map_err(|e| e.to_string())?;
+    apply_overrides(&mut folders, &overrides);
+    if let Err(e) = database.save_special_folders(&client.account_id, &folders) {
+        warn!("Failed to remember special folders: {}", e);
+    }
+    Ok(folders)
+}
+
+/// Search a folder of the active account on the server, to find mail the cache doesn't
+/// hold. `query` takes the same operators as the local search; the folder is `folder`,
+/// else the query's `folder:` filter, else the inbox. Results carry the ids cached
+/// emails have, so they can be merged with local results.
+#[tauri::command]
+pub async fn search_server(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    query: String,
+    folder: Option<String>,
+    max_results: Option<u32>,
+) -> Result<Vec<EmailListItem>, String> {
+    ensure_unlocked()?;
+    let account = get_active_account(&db)?;
+    // POP3 mail is all downloaded, so the local search already covers it
+    if account.provider_type() == ProviderType::Pop3 {
+        return Ok(Vec::new());
+    }
+
+    let criteria = SearchQuery::parse(&query);
+    let folder = folder.or_else(|| {
+        criteria.filters.iter().find_map(|filter| match filter {
+            SearchFilter::Folder(name) => Some(name.clone()),
+            _ => None,
+        })
+    });
+    let client_arc = get_account_client(&account, &account_manager).await?;
+    let client = client_arc.lock().await;
+    let folder = match folder {
+        // Like the local `folder:` filter, the last part of a path is enough
+        Some(name) => account_folders(&db, &client)
+            .await?
+            .into_iter()
+            .find(|f| {
+                f.name == name || f.path == name || f.display_name.eq_ignore_ascii_case(&name)
+            })
+            .map(|f| f.name)
+            .ok_or_else(|| format!("No folder named {}", name))?,
+        None => "INBOX".to_string(),
+    };
+    client
+        .search_messages(&folder, &criteria, max_results.unwrap_or(100))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Make a folder of the active account its Sent, Trash… folder in place of the detected
+/// one, or with no `role` mark it as not special
+#[tauri::command]
+pub async fn set_folder_role(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    folder: String,
+    role: Option<SpecialFolder>,
+) -> Result<(), String> {
+    if role == Some(SpecialFolder::Inbox) {
+        return Err("Only INBOX can be the inbox".to_string());
+    }
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+    let folders = client.list_folders().await.map_err(|e| e.to_string())?;
+    let folder = folders
+        .into_iter()
+        .find(|f| f.name == folder || f.path == folder)
+        .ok_or_else(|| format!("No folder named {}", folder))?;
+    if folder.special == Some(SpecialFolder::Inbox) {
+        return Err("INBOX is always the inbox".to_string());
+    }
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .set_folder_role(&client.account_id, &folder.name, role.as_ref())
+        .and_then(|_| database.clear_special_folders(&client.account_id))
+        .map_err(|e| e.to_string())
+}
+
+/// Go back to the detected role of a folder of the active account
+#[tauri::command]
+pub async fn reset_folder_role(db: State<'_, DbState>, folder: String) -> Result<(), String> {
+    let account = get_active_account(&db)?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .clear_folder_role(&account.id, &utf7::wire_name(&folder))
+        .and_then(|_| database.clear_special_folders(&account.id))
+        .map_err(|e| e.to_string())
+}
+
+/// Create a folder in the active account. Nested folders use the server's hierarchy
+/// delimiter, e.g. "Projects/2026".
+#[tauri::command]
+pub async fn create_folder(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    name: String,
+) -> Result<(), String> {
+    let name = valid_folder_name(&name)?;
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+    client.create_folder(name).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_folder(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    old_name: String,
+    new_name: String,
+) -> Result<(), String> {
+    let new_name = valid_folder_name(&new_name)?;
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+    let old_name = user_folder(&db, &client, &old_name).await?;
+    client
+        .rename_folder(&old_name, new_name)
+        .await
+        .map_err(|e| e.to_string())?;
+    forget_folder(&db, &client.account_id, &old_name);
+    Ok(())
+}
+
+/// Delete a folder of the active account together with the messages in it
+#[tauri::command]
+pub async fn delete_folder(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    name: String,
+) -> Result<(), String> {
+    let client_arc = get_active_client(&db, &account_manager).await?;
+    let client = client_arc.lock().await;
+    let name = user_folder(&db, &client, &name).await?;
+    client
+        .delete_folder(&name)
+        .await
+        .map_err(|e| e.to_string())?;
+    forget_folder(&db, &client.account_id, &name);
+    Ok(())
+}
+
+fn valid_folder_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Folder name can't be empty".to_string());
+    }
+    // `*` and `%` are LIST wildcards
+    if name.contains(['*', '%']) || name.chars().any(char::is_control) {
+        return Err(format!("Invalid folder name: {}", name));
+    }
+    Ok(name)
+}
+
+/// Check that `name` (the server's name or the decoded path) exists and isn't a special
+/// folder (Inbox, Sent, Trash…), which can't be renamed or deleted. Returns the server's
+/// name.
+async fn user_folder(db: &DbState, client: &ImapClient, name: &str) -> Result<String, String> {
+    let folders = account_folders(db, client).await?;
+    let folder = folders
+        .into_iter()
+        .find(|f| f.name == name || f.path == name)
+        .ok_or_else(|| format!("No folder named {}", name))?;
+    if folder.special.is_some() {
+        return Err(format!("{} is a special folder", folder.display_name));
+    }
+    Ok(folder.name)
+}
+
+/// Drop cached emails of a folder that no longer exists under its name
+fn forget_folder(db: &DbState, account_id: &str, folder: &str) {
+    let db_lock = db.lock().unwrap();
+    if let Some(database) = db_lock.as_ref() {
+        if let Err(e) = database.remove_folder(account_id, folder) {
+            warn!("Failed to drop cached emails of {}: {}", folder, e);
+        }
+    }
+}
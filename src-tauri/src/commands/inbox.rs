@@ -0,0 +1,229 @@
+//! Smart inbox layout: pinned emails, user-defined sections and priority corrections
+//!
+//! Pinned emails stay at the top of the smart inbox and of every section until unpinned.
+//! A section is a named saved filter shown alongside the built-in priority and category
+//! views, in an order the user picks.
+//!
+//! When the user moves an email to another priority, the correction is kept: the email
+//! stays where it was put, the sender's next emails lean the same way, and a background
+//! task refits each account's scoring weights once its corrections change.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, Runtime, State};
+use tracing::{info, warn};
+
+use crate::db::email_db::{EmailWithInsight, InboxCounts, InboxSection, InboxSectionFilter};
+use crate::db::EmailDatabase;
+use crate::email::priority;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+const PRIORITIES: [&str; 3] = ["HIGH", "MEDIUM", "LOW"];
+
+/// How often the retune task looks for accounts with new corrections
+const RETUNE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Pin an email to the top of the smart inbox, or unpin it with `pinned: false`
+#[tauri::command]
+pub async fn pin_email(
+    db: State<'_, DbState>,
+    email_id: String,
+    pinned: Option<bool>,
+) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .set_email_pinned(&email_id, pinned.unwrap_or(true))
+        .map_err(|e| e.to_string())
+}
+
+/// Set the priority of an email by hand (HIGH, MEDIUM or LOW)
+#[tauri::command]
+pub async fn set_email_priority_override(
+    db: State<'_, DbState>,
+    email_id: String,
+    priority: String,
+) -> Result<(), String> {
+    let priority = normalize_priority(Some(priority))?.ok_or("Priority cannot be empty")?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .set_priority_override(&email_id, &priority)
+        .map_err(|e| e.to_string())
+}
+
+/// Periodically refit the priority weights of accounts whose corrections changed
+pub fn spawn_priority_retune<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(RETUNE_CHECK_INTERVAL).await;
+            if let Err(e) = retune_priority_weights(&app) {
+                warn!("Failed to retune priority weights: {}", e);
+            }
+        }
+    });
+}
+
+fn retune_priority_weights<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let db = app.state::<DbState>();
+    let db_lock = db.lock().unwrap();
+    let Some(database) = db_lock.as_ref() else {
+        return Ok(());
+    };
+
+    let accounts = database
+        .get_priority_retune_accounts()
+        .map_err(|e| e.to_string())?;
+    for account_id in accounts {
+        let corrections = database
+            .get_priority_corrections(&account_id)
+            .map_err(|e| e.to_string())?;
+        let current = database
+            .get_priority_weights(&account_id)
+            .map_err(|e| e.to_string())?;
+        let weights = priority::retune(&current, &corrections);
+        database
+            .save_priority_weights(&account_id, &weights, corrections.len())
+            .map_err(|e| e.to_string())?;
+        info!(account = %account_id, "Refit priority weights to {} corrections", corrections.len());
+    }
+    Ok(())
+}
+
+/// Total and unread counts per priority, category, account and folder for the inbox
+/// badges, optionally for one account
+#[tauri::command]
+pub async fn get_inbox_counts(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+) -> Result<InboxCounts, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_inbox_counts(account_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Custom sections in display order
+#[tauri::command]
+pub async fn get_inbox_sections(db: State<'_, DbState>) -> Result<Vec<InboxSection>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database.get_inbox_sections().map_err(|e| e.to_string())
+}
+
+/// Add a section after the existing ones
+#[tauri::command]
+pub async fn create_inbox_section(
+    db: State<'_, DbState>,
+    name: String,
+    filter: InboxSectionFilter,
+) -> Result<InboxSection, String> {
+    let name = section_name(&name)?;
+    let filter = normalize_filter(filter)?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .create_inbox_section(&name, &filter)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_inbox_section(
+    db: State<'_, DbState>,
+    id: i64,
+    name: String,
+    filter: InboxSectionFilter,
+) -> Result<(), String> {
+    let name = section_name(&name)?;
+    let filter = normalize_filter(filter)?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .update_inbox_section(id, &name, &filter)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_inbox_section(db: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database.delete_inbox_section(id).map_err(|e| e.to_string())
+}
+
+/// Show sections in the order of `ids`
+#[tauri::command]
+pub async fn reorder_inbox_sections(db: State<'_, DbState>, ids: Vec<i64>) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .reorder_inbox_sections(&ids)
+        .map_err(|e| e.to_string())
+}
+
+/// Emails in a section, pinned ones first, then newest first
+#[tauri::command]
+pub async fn get_inbox_section_emails(
+    db: State<'_, DbState>,
+    id: i64,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let section = database
+        .get_inbox_section(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Inbox section {} not found", id))?;
+    database
+        .get_inbox_section_emails(&section.filter, limit.unwrap_or(50), offset.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
+
+fn section_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Section name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Drop blank fields and check the priority
+fn normalize_filter(mut filter: InboxSectionFilter) -> Result<InboxSectionFilter, String> {
+    filter.account_id = non_blank(filter.account_id);
+    filter.folder = non_blank(filter.folder);
+    filter.category = non_blank(filter.category);
+    filter.from = non_blank(filter.from);
+    filter.priority = normalize_priority(filter.priority)?;
+    Ok(filter)
+}
+
+/// Trimmed value, or None when it's blank
+pub(crate) fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Upper-cased priority filter; unknown priorities are an error
+pub(crate) fn normalize_priority(priority: Option<String>) -> Result<Option<String>, String> {
+    match non_blank(priority).map(|p| p.to_uppercase()) {
+        Some(p) if !PRIORITIES.contains(&p.as_str()) => Err(format!("Unknown priority: {}", p)),
+        priority => Ok(priority),
+    }
+}
@@ -0,0 +1,79 @@
+//! Background job registry commands
+//!
+//! Long-running tasks register themselves through `TrackedJob`, which keeps the shared
+//! `JobManager` up to date and emits every change as a `job:update` event.
+
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::jobs::{JobControl, JobInfo, JobKind, JobManager, JobStatus};
+
+/// Handle a running task uses to report to the job registry
+#[derive(Clone)]
+pub(crate) struct TrackedJob<R: Runtime> {
+    app: AppHandle<R>,
+    id: String,
+}
+
+impl<R: Runtime> TrackedJob<R> {
+    /// Register a new running job; pass its control to make it pausable and cancellable
+    pub(crate) fn start(app: &AppHandle<R>, kind: JobKind, control: Option<Arc<JobControl>>) -> Self {
+        let info = app.state::<JobManager>().register(kind, control);
+        let job = Self {
+            app: app.clone(),
+            id: info.id.clone(),
+        };
+        job.emit(Some(info));
+        job
+    }
+
+    pub(crate) fn progress(&self, processed: i64, total: i64) {
+        self.emit(self.manager().set_progress(&self.id, processed, total));
+    }
+
+    pub(crate) fn percent(&self, progress: f32) {
+        self.emit(self.manager().set_percent(&self.id, progress));
+    }
+
+    pub(crate) fn message(&self, message: impl Into<String>) {
+        self.emit(self.manager().set_message(&self.id, message));
+    }
+
+    pub(crate) fn finish(&self, status: JobStatus, message: Option<String>) {
+        self.emit(self.manager().finish(&self.id, status, message));
+    }
+
+    /// Finish from a task result: `Completed` on success, `Failed` with the error otherwise
+    pub(crate) fn finish_with<T, E: ToString>(&self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.finish(JobStatus::Completed, None),
+            Err(e) => self.finish(JobStatus::Failed, Some(e.to_string())),
+        }
+    }
+
+    fn manager(&self) -> State<'_, JobManager> {
+        self.app.state::<JobManager>()
+    }
+
+    fn emit(&self, info: Option<JobInfo>) {
+        if let Some(info) = info {
+            let _ = self.app.emit("job:update", info);
+        }
+    }
+}
+
+/// All running and recently finished background jobs, newest first
+#[tauri::command]
+pub async fn list_jobs(jobs: State<'_, JobManager>) -> Result<Vec<JobInfo>, String> {
+    Ok(jobs.list())
+}
+
+/// Cancel a running job; it stops after the item it is currently working on
+#[tauri::command]
+pub async fn cancel_job(jobs: State<'_, JobManager>, job_id: String) -> Result<(), String> {
+    if jobs.cancel(&job_id) {
+        Ok(())
+    } else {
+        Err("Job not found, already finished, or cannot be cancelled".to_string())
+    }
+}
@@ -0,0 +1,233 @@
+//! User label commands
+//!
+//! Labels live in the local database and are mirrored to the server on a best-effort
+//! basis: IMAP keywords on most servers, Gmail labels on Gmail. A server that refuses
+//! custom keywords doesn't stop a label from being applied locally.
+//!
+//! Gmail's category tabs arrive as `CATEGORY_*` labels during sync and are listed here as
+//! inbox tabs.
+
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tracing::warn;
+
+use crate::commands::account::AccountManager;
+use crate::commands::email::parse_email_id;
+use crate::db::email_db::{EmailWithInsight, InboxTab};
+use crate::db::EmailDatabase;
+use crate::email::gmail::GmailCategory;
+use crate::email::labels::{keyword_for, normalize_name};
+use crate::email::provider::EmailProvider;
+use crate::email::Label;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// All labels with how many cached emails carry each
+#[tauri::command]
+pub async fn list_labels(db: State<'_, DbState>) -> Result<Vec<Label>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .list_labels()
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_label(
+    db: State<'_, DbState>,
+    name: String,
+    color: Option<String>,
+) -> Result<Label, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    create_label_in(database, &name, color.as_deref())
+}
+
+/// Delete a label, taking it off every cached email and, where possible, the server
+#[tauri::command]
+pub async fn delete_label(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    name: String,
+) -> Result<(), String> {
+    let (label, email_ids) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let label = database
+            .get_label(&name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Label '{}' not found", name))?;
+        let email_ids = database
+            .label_email_ids(&label.keyword)
+            .map_err(|e| e.to_string())?;
+        database
+            .delete_label(&label.name)
+            .map_err(|e| e.to_string())?;
+        (label, email_ids)
+    };
+
+    for email_id in email_ids {
+        sync_label(&account_manager, &email_id, &label, false).await;
+    }
+    Ok(())
+}
+
+/// Apply a label to an email, creating the label if it doesn't exist yet
+#[tauri::command]
+pub async fn apply_label(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    email_id: String,
+    label: String,
+) -> Result<Label, String> {
+    let label = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let name = normalize_name(&label)?;
+        let label = match database.get_label(&name).map_err(|e| e.to_string())? {
+            Some(label) => label,
+            None => create_label_in(database, &name, None)?,
+        };
+        database
+            .set_email_label(&email_id, &label.keyword, true)
+            .map_err(|e| e.to_string())?;
+        label
+    };
+
+    sync_label(&account_manager, &email_id, &label, true).await;
+    Ok(label)
+}
+
+#[tauri::command]
+pub async fn remove_label(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    email_id: String,
+    label: String,
+) -> Result<(), String> {
+    let label = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+        let label = database
+            .get_label(label.trim())
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Label '{}' not found", label))?;
+        database
+            .set_email_label(&email_id, &label.keyword, false)
+            .map_err(|e| e.to_string())?;
+        label
+    };
+
+    sync_label(&account_manager, &email_id, &label, false).await;
+    Ok(())
+}
+
+/// Cached emails carrying a label, newest first
+#[tauri::command]
+pub async fn get_emails_by_label(
+    db: State<'_, DbState>,
+    label: String,
+    account_id: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let label = database
+        .get_label(label.trim())
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Label '{}' not found", label))?;
+    database
+        .get_emails_by_label(
+            &label.keyword,
+            account_id.as_deref(),
+            limit.unwrap_or(50),
+            offset.unwrap_or(0),
+        )
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Inbox tabs (Primary and Gmail's categories) with their email counts
+#[tauri::command]
+pub async fn get_inbox_tabs(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+) -> Result<Vec<InboxTab>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_inbox_tabs(account_id.as_deref())
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Cached inbox emails in a tab: `primary`, `social`, `promotions`, `updates` or `forums`
+#[tauri::command]
+pub async fn get_inbox_tab_emails(
+    db: State<'_, DbState>,
+    tab: String,
+    account_id: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let category = match tab.to_lowercase().as_str() {
+        "primary" => None,
+        other => Some(
+            GmailCategory::from_id(other).ok_or_else(|| format!("Unknown inbox tab: {}", tab))?,
+        ),
+    };
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_inbox_tab_emails(
+            account_id.as_deref(),
+            category,
+            limit.unwrap_or(50),
+            offset.unwrap_or(0),
+        )
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn create_label_in(
+    database: &EmailDatabase,
+    name: &str,
+    color: Option<&str>,
+) -> Result<Label, String> {
+    let name = normalize_name(name)?;
+    let keyword = keyword_for(&name)
+        .ok_or("Label names need at least one letter or digit in the ASCII range")?;
+    database
+        .create_label(&name, &keyword, color)
+        .map_err(|e| e.to_string())
+}
+
+/// Mirror a label change to the server; failures are logged and otherwise ignored
+async fn sync_label(account_manager: &AccountManager, email_id: &str, label: &Label, add: bool) {
+    let Some((account_id, folder, uid)) = parse_email_id(email_id) else {
+        return;
+    };
+    let Some(client_arc) = account_manager.get_client(&account_id) else {
+        return;
+    };
+    let client = client_arc.lock().await;
+    if let Err(e) = client
+        .set_labels(&folder, uid, std::slice::from_ref(label), add)
+        .await
+    {
+        warn!(
+            "Failed to update label '{}' on the server for {}: {}",
+            label.name, email_id, e
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Links shared in emails
+//!
+//! URLs are extracted from message bodies when they're cached. `get_email_links` lists
+//! them for one email and `get_shared_links` across the mailbox; `get_link_preview`
+//! fetches a page's title, description and icon on demand and caches them. Tracker
+//! links and local-network addresses are never fetched, including as a redirect hop on
+//! the way to a page.
+
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::State;
+
+use crate::db::email_db::SharedLink;
+use crate::db::EmailDatabase;
+use crate::email::links::{self, EmailLink, LinkMetadata};
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// How long a fetched preview is reused
+const PREVIEW_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Most of a page read when looking for its metadata
+const MAX_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// Most redirects followed for one request
+const MAX_REDIRECTS: usize = 5;
+
+/// HTTP client for addresses taken from mail. Each redirect is checked with
+/// `links::is_previewable` before it's followed, so a public link can't bounce the
+/// request to a tracker or into the local network.
+pub(crate) fn untrusted_url_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("Too many redirects")
+            } else if !links::is_previewable(attempt.url().as_str()) {
+                attempt.error("Redirected to a link without previews")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+}
+
+#[tauri::command]
+pub async fn get_email_links(
+    db: State<'_, DbState>,
+    email_id: String,
+) -> Result<Vec<EmailLink>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_email_links(&email_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Links others have sent, newest first
+#[tauri::command]
+pub async fn get_shared_links(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<SharedLink>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_shared_links(account_id.as_deref(), limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
+}
+
+/// Title, description and icon of a linked page. Cached previews are reused for a
+/// week unless `refresh` is set; a stale one is returned when the page can't be fetched.
+#[tauri::command]
+pub async fn get_link_preview(
+    db: State<'_, DbState>,
+    url: String,
+    refresh: Option<bool>,
+) -> Result<LinkMetadata, String> {
+    ensure_unlocked()?;
+    if !links::is_previewable(&url) {
+        return Err("No preview for this link".to_string());
+    }
+
+    let cached = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database.get_link_preview(&url).map_err(|e| e.to_string())?
+    };
+    if let Some((metadata, fetched_at)) = &cached {
+        if !refresh.unwrap_or(false) && Utc::now().timestamp() - fetched_at < PREVIEW_TTL_SECS {
+            return Ok(metadata.clone());
+        }
+    }
+
+    let metadata = match fetch_metadata(&url).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return cached
+                .map(|(metadata, _)| metadata)
+                .ok_or_else(|| format!("Failed to fetch {}: {}", url, e));
+        }
+    };
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .save_link_preview(&url, &metadata)
+        .map_err(|e| e.to_string())?;
+    Ok(metadata)
+}
+
+async fn fetch_metadata(url: &str) -> Result<LinkMetadata, String> {
+    let client = untrusted_url_client().map_err(|e| e.to_string())?;
+    let mut response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let final_url = response.url().to_string();
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("html"));
+    if !is_html {
+        // Not a page (an image, a PDF…): nothing to read but the address
+        return Ok(links::parse_link_metadata("", &final_url));
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PREVIEW_BYTES {
+            break;
+        }
+    }
+    Ok(links::parse_link_metadata(
+        &String::from_utf8_lossy(&body),
+        &final_url,
+    ))
+}
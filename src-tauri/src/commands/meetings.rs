@@ -0,0 +1,157 @@
+//! Meeting scheduling
+//!
+//! Indexing flags emails that mention a meeting (`has_meeting`). For those,
+//! `extract_meeting_proposals` lists the times the sender suggests, read by the model
+//! when one is available and by the rules in `email::meetings` otherwise, and
+//! `draft_scheduling_reply` writes an answer accepting the slot the user picked.
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+use tracing::info;
+
+use crate::commands::ai::{begin_ai_task, with_summarizer};
+use crate::db::EmailDatabase;
+use crate::email::dates::local_naive;
+use crate::email::meetings::{self, MeetingSlot};
+use crate::email::reply;
+use crate::email::types::Email;
+use crate::llm::{Priority, Summarizer};
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Meeting times proposed in an email
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeetingProposals {
+    pub email_id: String,
+    /// Candidate slots after the email was sent, earliest first
+    pub slots: Vec<MeetingSlot>,
+    /// Length of the meeting in minutes, when the email gives one
+    pub duration_minutes: Option<i64>,
+}
+
+/// A scheduling reply ready to open in the composer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulingDraft {
+    pub email_id: String,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+fn load_email(db: &DbState, email_id: &str) -> Result<(Email, Option<bool>), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let email = database
+        .get_email_by_id(email_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Email not found")?;
+    let has_meeting = database
+        .email_has_meeting(email_id)
+        .map_err(|e| e.to_string())?;
+    Ok((email, has_meeting))
+}
+
+/// Times an email proposes for a meeting. The model is asked unless indexing found no
+/// meeting in the email and the rules find no times either; its answer is used when it
+/// gives any slots, the rules' otherwise.
+#[tauri::command]
+pub async fn extract_meeting_proposals(
+    db: State<'_, DbState>,
+    email_id: String,
+) -> Result<MeetingProposals, String> {
+    ensure_unlocked()?;
+    let (email, has_meeting) = load_email(&db, &email_id)?;
+
+    let body = Summarizer::strip_html(email.ai_text());
+    let text = format!("{}\n{}", email.subject, body);
+    let rule_slots = meetings::extract_slots(&text, email.date_timestamp);
+
+    let slots = if has_meeting != Some(false) || !rule_slots.is_empty() {
+        let subject = email.subject.clone();
+        let sent_at = local_naive(email.date_timestamp)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M, %A").to_string())
+            .unwrap_or_default();
+        let answer = with_summarizer(Priority::Interactive, move |summarizer| {
+            summarizer
+                .extract_meeting_slots(&subject, &body, &sent_at)
+                .map_err(|e| e.to_string())
+        })
+        .await;
+        let lines = answer.unwrap_or_else(|e| {
+            info!("Falling back to rule-based meeting times: {}", e);
+            Vec::new()
+        });
+        let model_slots: Vec<_> = lines
+            .iter()
+            .filter_map(|line| meetings::parse_llm_slot(line))
+            .collect();
+        let model_slots = meetings::resolve_slots(&model_slots, &text, email.date_timestamp);
+        if model_slots.is_empty() {
+            rule_slots
+        } else {
+            model_slots
+        }
+    } else {
+        rule_slots
+    };
+
+    Ok(MeetingProposals {
+        email_id,
+        slots,
+        duration_minutes: meetings::meeting_minutes(&text),
+    })
+}
+
+/// Draft a reply accepting `chosen_slot`. Streams `compose:token` events like
+/// `compose_with_ai` when a model is loaded; otherwise returns a short template.
+#[tauri::command]
+pub async fn draft_scheduling_reply(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    email_id: String,
+    chosen_slot: MeetingSlot,
+    task_id: Option<String>,
+) -> Result<SchedulingDraft, String> {
+    ensure_unlocked()?;
+    let (email, _) = load_email(&db, &email_id)?;
+    let slot = meetings::describe_slot(&chosen_slot);
+    if slot.is_empty() {
+        return Err("Invalid meeting time".to_string());
+    }
+
+    let subject = email.subject.clone();
+    let body = email.ai_text().to_string();
+    let slot_owned = slot.clone();
+    let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+
+    let drafted = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .draft_scheduling_reply_stream(&subject, &body, &slot_owned, Some(cancel), |token| {
+                let _ = app_clone.emit("compose:token", token);
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await;
+    let body = match drafted {
+        Ok(body) => body.trim().to_string(),
+        Err(e) => {
+            info!("Falling back to the scheduling template: {}", e);
+            meetings::scheduling_template(&slot)
+        }
+    };
+
+    app.emit("compose:complete", ())
+        .map_err(|e| e.to_string())?;
+
+    let to = email.reply_to.unwrap_or(email.from_email);
+    Ok(SchedulingDraft {
+        email_id,
+        to: vec![to],
+        subject: reply::reply_subject(&email.subject),
+        body,
+    })
+}
@@ -1,15 +1,59 @@
 pub mod account;
 pub mod ai;
+pub mod attachments;
 pub mod auth;
+pub mod backup;
 pub mod cache;
+pub mod chat;
+pub mod config;
+pub mod crypto;
 pub mod db;
+pub mod diagnostics;
+pub mod dictation;
 pub mod email;
+pub mod inbox;
+pub mod jobs;
+pub mod labels;
+pub mod links;
+pub mod meetings;
+pub mod notifications;
 pub mod rag;
+pub mod receipts;
+pub mod reminders;
+pub mod saved_searches;
+pub mod security;
+pub mod settings;
+pub mod shipments;
+pub mod sync;
+pub mod translation;
+pub mod triage;
 
 pub use account::*;
 pub use ai::*;
+pub use attachments::*;
 pub use auth::*;
+pub use backup::*;
 pub use cache::*;
+pub use chat::*;
+pub use config::*;
+pub use crypto::*;
 pub use db::*;
+pub use diagnostics::*;
+pub use dictation::*;
 pub use email::*;
+pub use inbox::*;
+pub use jobs::*;
+pub use labels::*;
+pub use links::*;
+pub use meetings::*;
+pub use notifications::*;
 pub use rag::*;
+pub use receipts::*;
+pub use reminders::*;
+pub use saved_searches::*;
+pub use security::*;
+pub use settings::*;
+pub use shipments::*;
+pub use sync::*;
+pub use translation::*;
+pub use triage::*;
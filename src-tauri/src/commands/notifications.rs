@@ -0,0 +1,53 @@
+//! Notification preferences
+//!
+//! Settings are kept per account. The IDLE loop checks new INBOX mail against them and
+//! emits an `email:notify` event for each email that should raise a desktop alert.
+
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::db::EmailDatabase;
+use crate::email::notifications::NotificationSettings;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Minutes in a day, the bound for quiet hours
+const DAY_MINUTES: u32 = 24 * 60;
+
+#[tauri::command]
+pub async fn get_notification_settings(
+    db: State<'_, DbState>,
+    account_id: String,
+) -> Result<NotificationSettings, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_notification_settings(&account_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_notification_settings(
+    db: State<'_, DbState>,
+    account_id: String,
+    mut settings: NotificationSettings,
+) -> Result<(), String> {
+    settings.min_priority = settings.min_priority.to_uppercase();
+    if !matches!(settings.min_priority.as_str(), "HIGH" | "MEDIUM" | "LOW") {
+        return Err(format!("Unknown priority: {}", settings.min_priority));
+    }
+    if let Some(quiet) = settings.quiet_hours {
+        if quiet.start_minute >= DAY_MINUTES || quiet.end_minute >= DAY_MINUTES {
+            return Err("Quiet hours must be within a day".to_string());
+        }
+    }
+    settings.vip_senders.retain(|sender| sender.contains('@'));
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .save_notification_settings(&account_id, &settings)
+        .map_err(|e| e.to_string())
+}
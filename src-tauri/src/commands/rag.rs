@@ -1,19 +1,98 @@
 //! RAG (Retrieval-Augmented Generation) commands
 //!
 //! Tauri commands for embedding generation, semantic search, and contextual AI chat.
-
-use crate::db::vector_db::{EmbeddingStatus, VectorDatabase};
-use crate::llm::embeddings::{self, EmbeddingEngine, DEFAULT_EMBEDDING_MODEL};
-use crate::llm::rag::{calculate_text_hash, prepare_email_text, RagEngine};
+//! Email contents come from the shared email database in `DbState`, and embeddings live
+//! in a vector database next to it, held in the managed `VectorDbState`.
+
+use crate::commands::ai::LLM_WORKER;
+use crate::commands::chat::{begin_chat_generation, emit_chat_token};
+use crate::db::query_understanding;
+use crate::db::search_query::SearchQuery;
+use crate::db::vector_db::{EmbeddingModelInfo, EmbeddingStatus, SimilarEmail, VectorDatabase};
+use crate::db::EmailDatabase;
+use crate::commands::jobs::TrackedJob;
+use crate::jobs::{JobControl, JobKind, JobState, JobStatus};
+use crate::llm::embeddings::{self, EmbeddingEngine, EmbeddingModelOption, DEFAULT_EMBEDDING_MODEL};
+use crate::llm::rag::{
+    calculate_text_hash, prepare_email_text, RagEngine, RetrievedContext, DEFAULT_CATEGORIES,
+};
+use crate::llm::reranker::{self, Reranker, DEFAULT_RERANKER_MODEL};
+use crate::llm::{CancellationToken, Priority};
+use crate::security::ensure_unlocked;
+use crate::settings;
+use chrono::Local;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter, Manager};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tracing::{debug, error, info, warn};
 
 lazy_static! {
     pub static ref RAG_ENGINE: Mutex<Option<RagEngine>> = Mutex::new(None);
     static ref EMBEDDING_ENGINE: Mutex<Option<Arc<EmbeddingEngine>>> = Mutex::new(None);
-    static ref VECTOR_DB: Mutex<Option<Arc<VectorDatabase>>> = Mutex::new(None);
+    /// Pause/cancel control for `embed_all_emails`
+    static ref EMBEDDING_JOB: Arc<JobControl> = Arc::new(JobControl::new());
+    /// Model freed by `unload_embedding_model` or the idle timeout, reloaded on next use
+    static ref UNLOADED_EMBEDDING_MODEL: Mutex<Option<String>> = Mutex::new(None);
+    /// Serializes unloading and reloading so concurrent callers reload only once
+    static ref EMBEDDING_RELOAD: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    /// Cross-encoder for `ai.rerank_results`, loaded on first use and freed with the
+    /// embedding model
+    static ref RERANKER: Mutex<Option<Arc<Reranker>>> = Mutex::new(None);
+}
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// How often insights and embeddings of emails gone from the cache are cleaned up
+const ORPHAN_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// File name of the vector database, kept in the same directory as the email database
+pub const VECTOR_DB_FILE: &str = "email_vectors.db";
+
+/// The vector database, opened by `init_rag`
+pub struct VectorDbState {
+    path: PathBuf,
+    db: Mutex<Option<Arc<VectorDatabase>>>,
+}
+
+impl VectorDbState {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            db: Mutex::new(None),
+        }
+    }
+
+    /// The open database
+    fn get(&self) -> Result<Arc<VectorDatabase>, String> {
+        self.db
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| "Vector database not initialized".to_string())
+    }
+
+    /// The database, opening it if it isn't already
+    pub(crate) fn open(&self) -> Result<Arc<VectorDatabase>, String> {
+        let mut guard = self.db.lock().unwrap();
+        if let Some(db) = guard.as_ref() {
+            return Ok(db.clone());
+        }
+        let db = Arc::new(
+            VectorDatabase::new(self.path.clone())
+                .map_err(|e| format!("Failed to create vector database: {}", e))?,
+        );
+        // Nothing has embedded with it yet in this session, so a run marked as
+        // embedding was interrupted
+        if let Err(e) = db.recover_interrupted_embedding(None) {
+            warn!("Failed to recover embedding status: {}", e);
+        }
+        *guard = Some(db.clone());
+        Ok(db)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,6 +104,45 @@ pub struct SearchResult {
     pub snippet: Option<String>,
 }
 
+/// Answer of `chat_with_context`, with the emails it was given to work from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatAnswer {
+    /// Generated text; refers to sources as `[n]`
+    pub answer: String,
+    pub sources: Vec<ChatSource>,
+}
+
+/// An email used as context for a chat answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSource {
+    /// Number the answer cites the email by, starting at 1
+    pub number: usize,
+    pub email_id: String,
+    pub subject: String,
+    pub from: String,
+    /// Similarity of the email to the question, or the reranker's 0–1 relevance when
+    /// results were reranked
+    pub relevance: f32,
+}
+
+impl ChatAnswer {
+    fn without_sources(answer: String) -> Self {
+        Self {
+            answer,
+            sources: Vec::new(),
+        }
+    }
+}
+
+/// Payload of `embedding:model_mismatch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelMismatch {
+    pub current_model: String,
+    pub dimensions: usize,
+    /// Stored embeddings that searches with the current model skip
+    pub stale: Vec<EmbeddingModelInfo>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingProgress {
     pub total: i64,
@@ -35,88 +153,232 @@ pub struct EmbeddingProgress {
 /// Initialize the RAG system (embedding engine + vector database)
 #[tauri::command]
 pub async fn init_rag(app: AppHandle) -> Result<bool, String> {
-    eprintln!("[RAG] Initializing RAG system...");
+    start_rag(&app, true).await
+}
+
+/// Initialize the RAG system, downloading the embedding model if needed when `download`
+/// is set. Without it, returns `Ok(false)` when the model isn't downloaded yet.
+pub(crate) async fn start_rag(app: &AppHandle, download: bool) -> Result<bool, String> {
+    info!("Initializing RAG system...");
 
     // Skip if already initialized
     {
         let guard = RAG_ENGINE.lock().unwrap();
         if guard.as_ref().map(|r| r.is_initialized()).unwrap_or(false) {
-            eprintln!("[RAG] RAG system already initialized, skipping");
+            debug!("RAG system already initialized, skipping");
             return Ok(true);
         }
     }
 
-    // Get app data directory for vector database
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-
-    std::fs::create_dir_all(&app_data_dir)
-        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    let state = app.state::<VectorDbState>();
+    move_legacy_vector_db(app, &state.path);
+    let vector_db = state.open()?;
+
+    // Use the model the store was last built with, so one chosen via `reembed_all`
+    // survives restarts
+    let model_id = vector_db
+        .get_embedding_status()
+        .ok()
+        .and_then(|status| status.current_model)
+        .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string());
+
+    if !download && !embeddings::is_model_downloaded(Some(&model_id)) {
+        info!("{} isn't downloaded, skipping RAG", model_id);
+        return Ok(false);
+    }
 
-    let db_path = app_data_dir.join("email_vectors.db");
+    let engine = load_embedding_engine(app, &model_id, vector_db.clone()).await?;
+    check_embedding_models(app, &vector_db, &engine);
 
-    // Initialize vector database
-    let vector_db = Arc::new(
-        VectorDatabase::new(db_path)
-            .map_err(|e| format!("Failed to create vector database: {}", e))?,
-    );
+    info!("RAG system initialized successfully");
+    Ok(true)
+}
 
-    // Store vector db
-    {
-        let mut db_guard = VECTOR_DB.lock().unwrap();
-        *db_guard = Some(vector_db.clone());
+/// Move a vector database left in Tauri's app data directory by older versions, which
+/// isn't always the directory of the email database, to `path`
+fn move_legacy_vector_db(app: &AppHandle, path: &Path) {
+    if path.exists() {
+        return;
     }
+    let Ok(app_data_dir) = app.path().app_data_dir() else {
+        return;
+    };
+    let legacy = app_data_dir.join(VECTOR_DB_FILE);
+    if legacy == path || !legacy.exists() {
+        return;
+    }
+    match std::fs::rename(&legacy, path) {
+        Ok(()) => info!("Moved vector database from {:?} to {:?}", legacy, path),
+        Err(e) => warn!("Failed to move vector database from {:?}: {}", legacy, e),
+    }
+}
 
+/// Download and load an embedding model, and make it the engine behind `RAG_ENGINE`
+#[tracing::instrument(skip_all, fields(model = %model_id))]
+async fn load_embedding_engine<R: Runtime>(
+    app: &AppHandle<R>,
+    model_id: &str,
+    vector_db: Arc<VectorDatabase>,
+) -> Result<Arc<EmbeddingEngine>, String> {
     // Download embedding model (async, with direct HTTP fallback)
     let (config_path, tokenizer_path, weights_path) =
-        embeddings::download_embedding_model(None)
+        embeddings::download_embedding_model(Some(model_id))
             .await
             .map_err(|e| format!("Failed to download embedding model: {}", e))?;
 
-    eprintln!("[RAG] Embedding model files ready");
+    info!("Embedding model files ready");
 
     // Load embedding engine from downloaded paths
-    match EmbeddingEngine::from_paths(
-        DEFAULT_EMBEDDING_MODEL,
-        &config_path,
-        &tokenizer_path,
-        &weights_path,
-    ) {
-        Ok(engine) => {
-            let engine = Arc::new(engine);
-            {
-                let mut engine_guard = EMBEDDING_ENGINE.lock().unwrap();
-                *engine_guard = Some(engine.clone());
-            }
+    let engine = EmbeddingEngine::from_paths(model_id, &config_path, &tokenizer_path, &weights_path)
+        .map_err(|e| format!("Failed to initialize embedding engine: {}", e))?;
+    let engine = Arc::new(engine);
+    {
+        let mut engine_guard = EMBEDDING_ENGINE.lock().unwrap();
+        *engine_guard = Some(engine.clone());
+    }
 
-            // Initialize RAG engine
-            let mut rag = RagEngine::new();
-            rag.init(engine, vector_db);
+    // Initialize RAG engine
+    let mut rag = RagEngine::new();
+    rag.init(engine.clone(), vector_db);
 
-            // Pre-compute category reference embeddings for zero-shot classification
-            if let Err(e) = rag.init_category_embeddings() {
-                eprintln!("[RAG] Warning: Failed to initialize category embeddings: {}", e);
-            } else {
-                eprintln!("[RAG] Category embeddings initialized for zero-shot classification");
-            }
+    // Pre-compute category reference embeddings for zero-shot classification
+    let categories = category_references(app);
+    if let Err(e) = rag.init_category_embeddings(&categories) {
+        warn!("Failed to initialize category embeddings: {}", e);
+    } else {
+        info!("Category embeddings initialized for zero-shot classification");
+    }
 
-            {
-                let mut rag_guard = RAG_ENGINE.lock().unwrap();
-                *rag_guard = Some(rag);
-            }
+    {
+        let mut rag_guard = RAG_ENGINE.lock().unwrap();
+        *rag_guard = Some(rag);
+    }
 
-            eprintln!("[RAG] RAG system initialized successfully");
-            Ok(true)
-        }
+    Ok(engine)
+}
+
+/// Emit `embedding:model_mismatch` if the store holds vectors the current model can't be
+/// compared with: ones from another model, or with a different vector size
+fn check_embedding_models(app: &AppHandle, vector_db: &VectorDatabase, engine: &EmbeddingEngine) {
+    let models = match vector_db.get_embedding_models() {
+        Ok(models) => models,
         Err(e) => {
-            Err(format!("Failed to initialize embedding engine: {}", e))
+            warn!("Failed to check stored embedding models: {}", e);
+            return;
         }
+    };
+
+    let stale: Vec<EmbeddingModelInfo> = models
+        .into_iter()
+        .filter(|m| m.embedding_model != engine.model_id() || m.dimensions != engine.dimensions())
+        .collect();
+    if stale.is_empty() {
+        return;
+    }
+
+    warn!(
+        "{} stored embeddings don't match {} ({} dims); run reembed_all to migrate them",
+        stale.iter().map(|m| m.count).sum::<i64>(),
+        engine.model_id(),
+        engine.dimensions()
+    );
+    let _ = app.emit(
+        "embedding:model_mismatch",
+        EmbeddingModelMismatch {
+            current_model: engine.model_id().to_string(),
+            dimensions: engine.dimensions(),
+            stale,
+        },
+    );
+}
+
+/// `(id, reference text)` pairs for the configured categories, or the built-in defaults
+/// when the email database isn't open yet
+fn category_references<R: Runtime>(app: &AppHandle<R>) -> Vec<(String, String)> {
+    let stored = app.try_state::<Arc<Mutex<Option<EmailDatabase>>>>().and_then(|db| {
+        let db_lock = db.lock().unwrap();
+        let categories = db_lock.as_ref().and_then(|database| database.get_categories().ok());
+        categories
+    });
+
+    match stored {
+        Some(categories) => categories
+            .into_iter()
+            .map(|c| (c.id, format!("{}: {}", c.name, c.description)))
+            .collect(),
+        None => DEFAULT_CATEGORIES
+            .iter()
+            .map(|(id, name, description)| (id.to_string(), format!("{}: {}", name, description)))
+            .collect(),
+    }
+}
+
+/// Drop the embedding engine, the RAG engine holding it and the reranker; returns the
+/// freed embedding model's id
+fn release_embedding_engine() -> Option<String> {
+    let engine = EMBEDDING_ENGINE.lock().unwrap().take()?;
+    RAG_ENGINE.lock().unwrap().take();
+    RERANKER.lock().unwrap().take();
+
+    let model_id = engine.model_id().to_string();
+    *UNLOADED_EMBEDDING_MODEL.lock().unwrap() = Some(model_id.clone());
+    info!("Unloaded embedding model {}", model_id);
+    Some(model_id)
+}
+
+/// Reload an embedding model freed by `unload_embedding_model` or the idle timeout.
+/// Call before using `RAG_ENGINE`; does nothing if the model is loaded.
+pub(crate) async fn ensure_embedding_loaded<R: Runtime>(app: &AppHandle<R>) {
+    let _reload = EMBEDDING_RELOAD.lock().await;
+    let Some(model_id) = UNLOADED_EMBEDDING_MODEL.lock().unwrap().take() else {
+        return;
+    };
+    let Ok(vector_db) = vector_db(app) else {
+        return;
+    };
+
+    info!("Reloading embedding model {}", model_id);
+    if let Err(e) = load_embedding_engine(app, &model_id, vector_db).await {
+        warn!("Failed to reload embedding model: {}", e);
+        *UNLOADED_EMBEDDING_MODEL.lock().unwrap() = Some(model_id);
     }
 }
 
-/// Check if RAG is initialized
+/// Free the embedding model if it hasn't encoded anything for `timeout`
+pub(crate) async fn unload_embedding_if_idle(timeout: Duration) {
+    if EMBEDDING_JOB.state() != JobState::Idle {
+        return;
+    }
+    let _reload = EMBEDDING_RELOAD.lock().await;
+    let idle = EMBEDDING_ENGINE
+        .lock()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|engine| engine.idle_for() >= timeout);
+    if idle {
+        release_embedding_engine();
+    }
+}
+
+/// Id and approximate size in bytes of the loaded embedding model
+pub(crate) fn embedding_memory() -> Option<(String, u64)> {
+    let guard = EMBEDDING_ENGINE.lock().unwrap();
+    let engine = guard.as_ref()?;
+    Some((engine.model_id().to_string(), engine.weights_bytes()))
+}
+
+/// Free the embedding model's memory. It is reloaded the next time semantic search,
+/// embedding or RAG chat needs it. Returns whether a model was loaded.
+#[tauri::command]
+pub async fn unload_embedding_model() -> Result<bool, String> {
+    if EMBEDDING_JOB.state() != JobState::Idle {
+        return Err("Cannot unload the embedding model while embedding is in progress".to_string());
+    }
+    let _reload = EMBEDDING_RELOAD.lock().await;
+    Ok(release_embedding_engine().is_some())
+}
+
+/// Check if RAG is initialized (an unloaded model counts, since it reloads on demand)
 #[tauri::command]
 pub fn is_rag_ready() -> bool {
     let rag_guard = RAG_ENGINE.lock().unwrap();
@@ -124,42 +386,74 @@ pub fn is_rag_ready() -> bool {
         .as_ref()
         .map(|r| r.is_initialized())
         .unwrap_or(false)
+        || UNLOADED_EMBEDDING_MODEL.lock().unwrap().is_some()
 }
 
-/// Check if the embedding model is downloaded
+/// Check if an embedding model (default: the recommended one) is downloaded
 #[tauri::command]
-pub fn is_embedding_model_downloaded() -> bool {
-    crate::llm::embeddings::is_model_downloaded(None)
+pub fn is_embedding_model_downloaded(model_id: Option<String>) -> bool {
+    crate::llm::embeddings::is_model_downloaded(model_id.as_deref())
 }
 
-/// Get embedding status
+/// Embedding models the user can choose from
 #[tauri::command]
-pub fn get_embedding_status() -> Result<EmbeddingStatus, String> {
-    let db_guard = VECTOR_DB.lock().unwrap();
-    let db = db_guard.as_ref().ok_or("Vector database not initialized")?;
+pub fn get_available_embedding_models() -> Vec<EmbeddingModelOption> {
+    embeddings::get_available_embedding_models()
+}
+
+/// Switch semantic search to another supported embedding model, downloading it if needed,
+/// and re-embed all emails with it. Returns the number of emails embedded.
+#[tauri::command]
+pub async fn set_embedding_model(app: AppHandle, model_id: String) -> Result<i64, String> {
+    if embeddings::find_embedding_model(&model_id).is_none() {
+        return Err(format!("Unknown embedding model: {}", model_id));
+    }
+    reembed_all(app, Some(model_id)).await
+}
+
+/// Stored embeddings grouped by model and vector size
+#[tauri::command]
+pub fn get_embedding_models(
+    vector_db: State<'_, VectorDbState>,
+) -> Result<Vec<EmbeddingModelInfo>, String> {
+    vector_db
+        .get()?
+        .get_embedding_models()
+        .map_err(|e| format!("Failed to get embedding models: {}", e))
+}
 
-    db.get_embedding_status()
+/// Get embedding status
+#[tauri::command]
+pub fn get_embedding_status(
+    vector_db: State<'_, VectorDbState>,
+) -> Result<EmbeddingStatus, String> {
+    vector_db
+        .get()?
+        .get_embedding_status()
         .map_err(|e| format!("Failed to get embedding status: {}", e))
 }
 
 /// Embed a single email
 #[tauri::command]
-pub fn embed_email(
+pub async fn embed_email(
+    app: AppHandle,
     email_id: String,
     subject: String,
     from: String,
     body: String,
 ) -> Result<(), String> {
+    ensure_embedding_loaded(&app).await;
+
     let rag_guard = RAG_ENGINE.lock().unwrap();
     let rag = rag_guard.as_ref().ok_or("RAG engine not initialized")?;
 
     let text = prepare_email_text(&subject, &from, &body);
     let text_hash = calculate_text_hash(&text);
 
-    // Check if already embedded with same hash
-    if let Some(vector_db) = rag.vector_db() {
+    // Check if already embedded with same hash and model
+    if let (Some(vector_db), Some(model)) = (rag.vector_db(), rag.embedding_model()) {
         if vector_db
-            .has_embedding(&email_id, &text_hash)
+            .has_embedding(&email_id, &text_hash, model)
             .unwrap_or(false)
         {
             return Ok(()); // Already embedded
@@ -170,23 +464,87 @@ pub fn embed_email(
         .map_err(|e| format!("Failed to embed email: {}", e))
 }
 
-/// Embed all unembedded emails (batch operation)
+/// Emails encoded per forward pass by `embed_all_emails`
+const EMBEDDING_BATCH_SIZE: usize = 24;
+
+/// Embed a batch in one forward pass. If the batch fails, each text is retried on its own
+/// so one bad email doesn't lose the rest; failures come back as `None`.
+fn embed_texts(engine: &EmbeddingEngine, texts: &[String]) -> Vec<Option<Vec<f32>>> {
+    let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+    match engine.embed_batch(&refs) {
+        Ok(vectors) if vectors.len() == texts.len() => vectors.into_iter().map(Some).collect(),
+        result => {
+            if let Err(e) = result {
+                warn!("Batch embedding failed, retrying one by one: {}", e);
+            }
+            texts
+                .iter()
+                .map(|text| match engine.embed(text) {
+                    Ok(vector) => Some(vector),
+                    Err(e) => {
+                        warn!("Failed to embed email: {}", e);
+                        None
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Switch to `model_id` (default: the current model) and re-embed every email whose vector
+/// came from another model. Runs as the embedding job, so it can be paused and cancelled
+/// like `embed_all_emails`.
 #[tauri::command]
-pub async fn embed_all_emails(app: AppHandle) -> Result<i64, String> {
-    // Get email database to fetch emails
-    let email_db = crate::db::EmailDatabase::new(
-        app.path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?
-            .join("emails.db"),
-    )
-    .map_err(|e| format!("Failed to open email database: {}", e))?;
-
-    let vector_db = {
-        let db_guard = VECTOR_DB.lock().unwrap();
-        db_guard.clone().ok_or("Vector database not initialized")?
+pub async fn reembed_all(app: AppHandle, model_id: Option<String>) -> Result<i64, String> {
+    if EMBEDDING_JOB.state() != JobState::Idle {
+        return Err("Embedding already in progress".to_string());
+    }
+    ensure_embedding_loaded(&app).await;
+    let vector_db = vector_db(&app)?;
+
+    let current = EMBEDDING_ENGINE.lock().unwrap().clone();
+    let engine = match (current, model_id) {
+        (Some(engine), None) => engine,
+        (Some(engine), Some(id)) if engine.model_id() == id => engine,
+        (_, model_id) => {
+            let model_id = model_id.as_deref().unwrap_or(DEFAULT_EMBEDDING_MODEL);
+            info!("Switching embedding model to {}", model_id);
+            load_embedding_engine(&app, model_id, vector_db.clone()).await?
+        }
     };
 
+    vector_db
+        .update_embedding_status(false, None, None, Some(engine.model_id()), None)
+        .map_err(|e| format!("Failed to update status: {}", e))?;
+
+    embed_all_emails(app).await
+}
+
+/// Embed all unembedded emails (batch operation).
+/// Can be paused, resumed and cancelled with the `*_embedding` commands.
+#[tauri::command]
+pub async fn embed_all_emails(app: AppHandle) -> Result<i64, String> {
+    if !EMBEDDING_JOB.start() {
+        return Err("Embedding already in progress".to_string());
+    }
+    ensure_embedding_loaded(&app).await;
+    let job = TrackedJob::start(&app, JobKind::Embedding, Some(EMBEDDING_JOB.clone()));
+    let result = embed_unembedded_emails(&app, &job).await;
+    let cancelled = EMBEDDING_JOB.state() == JobState::Cancelled;
+    EMBEDDING_JOB.finish();
+
+    match &result {
+        Ok(_) if cancelled => job.finish(JobStatus::Cancelled, None),
+        _ => job.finish_with(&result),
+    }
+    result
+}
+
+#[tracing::instrument(name = "embedding", skip_all)]
+async fn embed_unembedded_emails(app: &AppHandle, job: &TrackedJob<tauri::Wry>) -> Result<i64, String> {
+    let db = app.state::<DbState>();
+    let vector_db = vector_db(app)?;
+
     let embedding_engine = {
         let engine_guard = EMBEDDING_ENGINE.lock().unwrap();
         engine_guard
@@ -195,27 +553,32 @@ pub async fn embed_all_emails(app: AppHandle) -> Result<i64, String> {
     };
 
     // Get all email IDs from the email database, then filter out already-embedded ones
-    let all_email_ids = email_db
-        .get_all_email_ids(1000)
-        .map_err(|e| format!("Failed to get email IDs: {}", e))?;
+    let all_email_ids = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_all_email_ids(1000)
+            .map_err(|e| format!("Failed to get email IDs: {}", e))?
+    };
 
-    eprintln!("[RAG] Found {} email IDs in email DB", all_email_ids.len());
+    debug!("Found {} email IDs in email DB", all_email_ids.len());
 
+    // Emails embedded by another model count as unembedded, so they get migrated
     let embedded_ids = vector_db
-        .get_embedded_email_ids()
+        .get_embedded_email_ids(embedding_engine.model_id())
         .map_err(|e| format!("Failed to get embedded email IDs: {}", e))?;
 
-    eprintln!("[RAG] Already embedded: {}", embedded_ids.len());
+    debug!("Already embedded: {}", embedded_ids.len());
 
     let unembedded_ids: Vec<String> = all_email_ids
         .into_iter()
         .filter(|id| !embedded_ids.contains(id))
         .collect();
 
-    eprintln!("[RAG] Unembedded emails to process: {}", unembedded_ids.len());
+    debug!("Unembedded emails to process: {}", unembedded_ids.len());
 
     if unembedded_ids.is_empty() {
-        eprintln!("[RAG] All emails already embedded, nothing to do");
+        info!("All emails already embedded, nothing to do");
         return Ok(0);
     }
 
@@ -233,71 +596,112 @@ pub async fn embed_all_emails(app: AppHandle) -> Result<i64, String> {
         .map_err(|e| format!("Failed to update status: {}", e))?;
 
     let mut embedded_count = 0i64;
+    let mut cancelled = false;
+
+    for chunk in unembedded_ids.chunks(EMBEDDING_BATCH_SIZE) {
+        if !EMBEDDING_JOB.checkpoint().await {
+            cancelled = true;
+            break;
+        }
 
-    for email_id in unembedded_ids {
         // Get email content
-        match email_db.get_email_by_id(&email_id) {
-            Ok(Some(email)) => {
-                let body = email.body_plain.as_deref().unwrap_or("");
-                let text = prepare_email_text(&email.subject, &email.from_email, body);
-                let text_hash = calculate_text_hash(&text);
-
-                // Generate embedding
-                match embedding_engine.embed(&text) {
-                    Ok(embedding) => {
-                        let email_embedding = crate::db::vector_db::EmailEmbedding {
-                            email_id: email_id.clone(),
-                            embedding,
-                            embedding_model: embedding_engine.model_id().to_string(),
-                            text_hash,
-                            created_at: chrono::Utc::now().timestamp(),
-                        };
-
-                        if vector_db.store_embedding(&email_embedding).is_ok() {
-                            embedded_count += 1;
-
-                            // Emit progress event
-                            let _ = app.emit(
-                                "embedding:progress",
-                                EmbeddingProgress {
-                                    total,
-                                    embedded: embedded_count,
-                                    current_email_id: Some(email_id),
-                                },
-                            );
-
-                            // Update status periodically
-                            if embedded_count % 10 == 0 {
-                                let _ = vector_db.update_embedding_status(
-                                    true,
-                                    Some(total),
-                                    Some(embedded_count),
-                                    None,
-                                    None,
-                                );
-                            }
-                        }
+        let mut ids = Vec::with_capacity(chunk.len());
+        let mut texts = Vec::with_capacity(chunk.len());
+        {
+            let db_lock = db.lock().unwrap();
+            let database = db_lock.as_ref().ok_or("Database not initialized")?;
+            for email_id in chunk {
+                match database.get_email_by_id(email_id) {
+                    Ok(Some(email)) => {
+                        ids.push(email_id.clone());
+                        texts.push(prepare_email_text(
+                            &email.subject,
+                            &email.from_email,
+                            email.ai_text(),
+                        ));
+                    }
+                    Ok(None) => {
+                        info!("Email {} not found in DB, skipping", email_id);
                     }
                     Err(e) => {
-                        eprintln!("[RAG] Failed to embed email {}: {}", email_id, e);
+                        warn!("Failed to fetch email {}: {}", email_id, e);
                     }
                 }
             }
-            Ok(None) => {
-                eprintln!("[RAG] Email {} not found in DB, skipping", email_id);
-            }
-            Err(e) => {
-                eprintln!("[RAG] Failed to fetch email {}: {}", email_id, e);
-            }
         }
+        if texts.is_empty() {
+            continue;
+        }
+
+        // Generate embeddings for the whole batch off the async runtime
+        let engine = embedding_engine.clone();
+        let (texts, vectors) = tokio::task::spawn_blocking(move || {
+            let vectors = embed_texts(&engine, &texts);
+            (texts, vectors)
+        })
+        .await
+        .map_err(|e| format!("Embedding task failed: {}", e))?;
+
+        let created_at = chrono::Utc::now().timestamp();
+        let batch: Vec<crate::db::vector_db::EmailEmbedding> = ids
+            .into_iter()
+            .zip(texts.iter())
+            .zip(vectors)
+            .filter_map(|((email_id, text), embedding)| {
+                Some(crate::db::vector_db::EmailEmbedding {
+                    email_id,
+                    embedding: embedding?,
+                    embedding_model: embedding_engine.model_id().to_string(),
+                    text_hash: calculate_text_hash(text),
+                    created_at,
+                })
+            })
+            .collect();
+        let Some(last_id) = batch.last().map(|e| e.email_id.clone()) else {
+            continue;
+        };
+
+        if let Err(e) = vector_db.store_embeddings(&batch) {
+            warn!("Failed to store embedding batch: {}", e);
+            continue;
+        }
+        embedded_count += batch.len() as i64;
+        let _ = vector_db.set_embedding_checkpoint(Some(&last_id));
+
+        // Emit progress event
+        let _ = app.emit(
+            "embedding:progress",
+            EmbeddingProgress {
+                total,
+                embedded: embedded_count,
+                current_email_id: Some(last_id),
+            },
+        );
+        job.progress(embedded_count, total);
+
+        let _ = vector_db.update_embedding_status(true, Some(total), Some(embedded_count), None, None);
     }
 
     // Update final status
     vector_db
         .update_embedding_status(false, Some(total), Some(embedded_count), None, None)
         .map_err(|e| format!("Failed to update status: {}", e))?;
+    let _ = vector_db.set_embedding_paused(false);
+
+    if cancelled {
+        info!(
+            "Embedding cancelled: {}/{} emails embedded",
+            embedded_count, total
+        );
+        let _ = app.emit("embedding:cancelled", embedded_count);
+        return Ok(embedded_count);
+    }
+    let _ = vector_db.set_embedding_checkpoint(None);
 
-    eprintln!("[RAG] Embedding complete: {}/{} emails embedded", embedded_count, total);
+    info!(
+        "Embedding complete: {}/{} emails embedded",
+        embedded_count, total
+    );
 
     // Emit completion event
     let _ = app.emit("embedding:complete", embedded_count);
@@ -305,29 +709,241 @@ pub async fn embed_all_emails(app: AppHandle) -> Result<i64, String> {
     Ok(embedded_count)
 }
 
-/// Semantic search for emails
+fn vector_db<R: Runtime>(app: &AppHandle<R>) -> Result<Arc<VectorDatabase>, String> {
+    app.state::<VectorDbState>().get()
+}
+
+/// Delete the embeddings of emails dropped from the cache. Does nothing before the vector
+/// database is opened; the orphan sweep removes those later.
+pub(crate) fn forget_embeddings<R: Runtime>(app: &AppHandle<R>, email_ids: &[String]) {
+    let Ok(vector_db) = vector_db(app) else {
+        return;
+    };
+    if let Err(e) = vector_db.delete_embeddings(email_ids) {
+        warn!("Failed to delete embeddings: {}", e);
+    }
+}
+
+/// Mark an embedding run without a heartbeat since `stale_before` as interrupted, and
+/// cancel it if it belongs to this process. Does nothing before the vector database is opened.
+pub(crate) fn recover_stale_embedding<R: Runtime>(app: &AppHandle<R>, stale_before: i64) {
+    let Ok(vector_db) = vector_db(app) else {
+        return;
+    };
+    match vector_db.recover_interrupted_embedding(Some(stale_before)) {
+        Ok(true) => {
+            warn!("Recovered an interrupted embedding run");
+            EMBEDDING_JOB.cancel();
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Failed to recover embedding status: {}", e),
+    }
+}
+
+/// Cancel any embedding run and clear its status, keeping the embeddings stored so far
+pub(crate) fn force_reset_embedding<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    EMBEDDING_JOB.cancel();
+    let Ok(vector_db) = vector_db(app) else {
+        return Ok(());
+    };
+    vector_db
+        .recover_interrupted_embedding(None)
+        .map_err(|e| e.to_string())?;
+    vector_db
+        .set_embedding_paused(false)
+        .map_err(|e| e.to_string())
+}
+
+/// Periodically delete insights and embeddings whose email is no longer cached. Runs for
+/// the lifetime of the app.
+pub fn spawn_orphan_sweep<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(ORPHAN_SWEEP_INTERVAL).await;
+            if let Err(e) = sweep_orphans(&app) {
+                warn!("Failed to remove data of deleted emails: {}", e);
+            }
+        }
+    });
+}
+
+fn sweep_orphans<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    // Embedding runs read emails as they go, so leave their embeddings until they finish
+    if EMBEDDING_JOB.state() != JobState::Idle {
+        return Ok(());
+    }
+    // Listed before the cached emails, so an email embedded in between is never
+    // mistaken for a deleted one
+    let embedded = match vector_db(app) {
+        Ok(vector_db) => Some((
+            vector_db
+                .get_all_embedded_email_ids()
+                .map_err(|e| e.to_string())?,
+            vector_db,
+        )),
+        Err(_) => None,
+    };
+
+    let (insights, cached) = {
+        let db = app.state::<DbState>();
+        let db_lock = db.lock().unwrap();
+        let Some(database) = db_lock.as_ref() else {
+            return Ok(());
+        };
+        let insights = database
+            .remove_orphaned_insights()
+            .map_err(|e| e.to_string())?;
+        let cached = database.get_email_id_set().map_err(|e| e.to_string())?;
+        (insights, cached)
+    };
+
+    let mut embeddings = 0;
+    if let Some((embedded, vector_db)) = embedded {
+        let orphaned: Vec<String> = embedded
+            .into_iter()
+            .filter(|id| !cached.contains(id))
+            .collect();
+        if !orphaned.is_empty() {
+            embeddings = vector_db
+                .delete_embeddings(&orphaned)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    if insights + embeddings > 0 {
+        info!(
+            "Removed {} insights and {} embeddings of deleted emails",
+            insights, embeddings
+        );
+    }
+    Ok(())
+}
+
+/// Pause `embed_all_emails` after the email it is currently embedding
+#[tauri::command]
+pub async fn pause_embedding(app: AppHandle) -> Result<(), String> {
+    if !EMBEDDING_JOB.pause() {
+        return Err("Embedding is not running".to_string());
+    }
+    vector_db(&app)?
+        .set_embedding_paused(true)
+        .map_err(|e| e.to_string())?;
+    let _ = app.emit("embedding:paused", ());
+    Ok(())
+}
+
+/// Resume paused embedding. A job paused or interrupted in an earlier session is restarted
+/// in the background and continues with the emails that are still unembedded.
 #[tauri::command]
-pub fn search_emails_semantic(
+pub async fn resume_embedding(app: AppHandle) -> Result<(), String> {
+    let vector_db = vector_db(&app)?;
+
+    if !EMBEDDING_JOB.resume() {
+        let status = vector_db
+            .get_embedding_status()
+            .map_err(|e| e.to_string())?;
+        if EMBEDDING_JOB.state() != JobState::Idle || !(status.is_paused || status.is_embedding) {
+            return Err("No paused embedding job".to_string());
+        }
+
+        let app_clone = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = embed_all_emails(app_clone).await {
+                error!("Resumed embedding failed: {}", e);
+            }
+        });
+    }
+
+    vector_db
+        .set_embedding_paused(false)
+        .map_err(|e| e.to_string())?;
+    let _ = app.emit("embedding:resumed", ());
+    Ok(())
+}
+
+/// Stop embedding after the current email. Embeddings already stored are kept.
+#[tauri::command]
+pub async fn cancel_embedding(app: AppHandle) -> Result<(), String> {
+    let vector_db = vector_db(&app)?;
+
+    if !EMBEDDING_JOB.cancel() {
+        // Nothing running here; drop a job left paused by an earlier session
+        vector_db
+            .update_embedding_status(false, None, None, None, None)
+            .map_err(|e| e.to_string())?;
+    }
+    vector_db
+        .set_embedding_paused(false)
+        .map_err(|e| e.to_string())?;
+    vector_db
+        .set_embedding_checkpoint(None)
+        .map_err(|e| e.to_string())
+}
+
+/// Most emails metadata filters pass on to semantic ranking, newest first
+const MAX_FILTERED_CANDIDATES: i64 = 5000;
+
+/// Vector hits the reranker reorders before the best are passed to the model
+const RERANK_CANDIDATES: usize = 50;
+
+/// Characters of each email, after its subject, the reranker reads
+const RERANK_PASSAGE_CHARS: usize = 1500;
+
+/// Semantic search for emails.
+/// Gmail-style operators (`from:`, `before:`, `is:unread`, ...) in the query filter the matches;
+/// a query made only of operators returns the newest matching emails.
+#[tauri::command]
+pub async fn search_emails_semantic(
     app: AppHandle,
     query: String,
     limit: usize,
 ) -> Result<Vec<SearchResult>, String> {
-    // Step 1: Lock RAG_ENGINE, perform search, drop lock
-    let similar = {
+    ensure_unlocked()?;
+    ensure_embedding_loaded(&app).await;
+
+    let parsed = SearchQuery::parse(&query);
+    let operators_only = parsed.text.is_empty() && parsed.has_filters();
+
+    // Step 1: Lock the email database, find the emails passing the filters, drop lock
+    let candidates = if parsed.has_filters() && !operators_only {
+        let db = app.state::<DbState>();
+        let db_lock = db.lock().unwrap();
+        let email_db = db_lock.as_ref().ok_or("Database not initialized")?;
+        Some(
+            email_db
+                .get_filtered_email_ids(&parsed, MAX_FILTERED_CANDIDATES)
+                .map_err(|e| format!("Failed to filter emails: {}", e))?,
+        )
+    } else {
+        None
+    };
+
+    // Step 2: Lock RAG_ENGINE, rank the candidates (or all emails), drop lock
+    let mut similar = if operators_only {
+        Vec::new()
+    } else {
         let rag_guard = RAG_ENGINE.lock().unwrap();
         let rag = rag_guard.as_ref().ok_or("RAG engine not initialized")?;
-        rag.search_similar(&query, limit, None)
+        rag.search_similar(&parsed.text, limit, None, candidates.as_ref())
             .map_err(|e| format!("Failed to search: {}", e))?
     };
 
-    // Step 2: Open EmailDatabase to enrich results with metadata
-    let email_db = crate::db::EmailDatabase::new(
-        app.path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?
-            .join("emails.db"),
-    )
-    .map_err(|e| format!("Failed to open email database: {}", e))?;
+    let db = app.state::<DbState>();
+    let db_lock = db.lock().unwrap();
+    let email_db = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    if operators_only {
+        similar = email_db
+            .search_emails(&query, limit as i64)
+            .map_err(|e| format!("Failed to search: {}", e))?
+            .into_iter()
+            .map(|email| SimilarEmail {
+                email_id: email.id,
+                similarity: 0.0,
+            })
+            .collect();
+    }
+
+    // Step 3: Enrich results with metadata
 
     let results: Vec<SearchResult> = similar
         .into_iter()
@@ -357,7 +973,14 @@ pub fn search_emails_semantic(
 
 /// Find emails similar to a given email
 #[tauri::command]
-pub fn find_similar_emails(email_id: String, limit: usize) -> Result<Vec<SearchResult>, String> {
+pub async fn find_similar_emails(
+    app: AppHandle,
+    email_id: String,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    ensure_unlocked()?;
+    ensure_embedding_loaded(&app).await;
+
     let rag_guard = RAG_ENGINE.lock().unwrap();
     let rag = rag_guard.as_ref().ok_or("RAG engine not initialized")?;
 
@@ -371,7 +994,13 @@ pub fn find_similar_emails(email_id: String, limit: usize) -> Result<Vec<SearchR
 
     // Search for similar (excluding the source email)
     let similar = vector_db
-        .search_similar(&embedding.embedding, limit, Some(&email_id))
+        .search_similar(
+            &embedding.embedding,
+            &embedding.embedding_model,
+            limit,
+            Some(&email_id),
+            None,
+        )
         .map_err(|e| format!("Failed to search: {}", e))?;
 
     let results: Vec<SearchResult> = similar
@@ -390,89 +1019,241 @@ pub fn find_similar_emails(email_id: String, limit: usize) -> Result<Vec<SearchR
 
 /// Get count of embedded emails
 #[tauri::command]
-pub fn get_embedded_count() -> Result<i64, String> {
-    let db_guard = VECTOR_DB.lock().unwrap();
-    let db = db_guard.as_ref().ok_or("Vector database not initialized")?;
-
-    db.get_embedded_count()
+pub fn get_embedded_count(vector_db: State<'_, VectorDbState>) -> Result<i64, String> {
+    vector_db
+        .get()?
+        .get_embedded_count()
         .map_err(|e| format!("Failed to get count: {}", e))
 }
 
 /// Clear all embeddings
 #[tauri::command]
-pub fn clear_embeddings() -> Result<(), String> {
-    let db_guard = VECTOR_DB.lock().unwrap();
-    let db = db_guard.as_ref().ok_or("Vector database not initialized")?;
-
-    db.clear_all_embeddings()
+pub fn clear_embeddings(vector_db: State<'_, VectorDbState>) -> Result<(), String> {
+    vector_db
+        .get()?
+        .clear_all_embeddings()
         .map_err(|e| format!("Failed to clear embeddings: {}", e))
 }
 
-/// Chat with RAG context
+/// Chat with RAG context. The answer cites the emails in `sources` by number, e.g. `[2]`.
 #[tauri::command]
-pub fn chat_with_context(
+pub async fn chat_with_context(
     app: AppHandle,
     query: String,
     limit: usize,
-) -> Result<String, String> {
-    use crate::llm::rag::RetrievedContext;
+) -> Result<ChatAnswer, String> {
+    ensure_unlocked()?;
+    answer_with_context(&app, &query, limit, None).await
+}
+
+/// Chat with RAG context, streaming the answer as `chat:token` events.
+/// Can be stopped with `cancel_chat_generation`.
+#[tauri::command]
+pub async fn chat_with_context_stream(
+    app: AppHandle,
+    query: String,
+    limit: usize,
+) -> Result<ChatAnswer, String> {
+    ensure_unlocked()?;
+    let task = begin_chat_generation();
+    answer_with_context(&app, &query, limit, Some(task.token())).await
+}
+
+/// Emails matching what a chat question says about sender, dates, folder or account (see
+/// `query_understanding`), or None when it names none of them
+pub(crate) fn question_candidates(
+    database: &EmailDatabase,
+    question: &str,
+) -> Result<Option<HashSet<String>>, String> {
+    let accounts = database.list_accounts().map_err(|e| e.to_string())?;
+    let names: Vec<(&str, &str)> = accounts
+        .iter()
+        .flat_map(|account| {
+            let names = [
+                Some(account.email.as_str()),
+                Some(account.display_name.as_str()),
+                account.label.as_deref(),
+            ];
+            names
+                .into_iter()
+                .flatten()
+                .map(|name| (account.id.as_str(), name))
+        })
+        .collect();
+
+    let constraints = query_understanding::understand(question, Local::now().naive_local(), &names);
+    if !constraints.has_filters() {
+        return Ok(None);
+    }
+    debug!("Chat question constraints: {:?}", constraints.filters);
+    database
+        .get_filtered_email_ids(&constraints, MAX_FILTERED_CANDIDATES)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// The `limit` contexts the reranker finds most relevant to `query`, best first, with its
+/// 0–1 relevance as their similarity. Keeps the vector order if reranking fails.
+async fn rerank_contexts(
+    query: &str,
+    mut contexts: Vec<RetrievedContext>,
+    passages: Vec<String>,
+    limit: usize,
+) -> Vec<RetrievedContext> {
+    let query = query.to_string();
+    let scored = tokio::task::spawn_blocking(move || {
+        let reranker = load_reranker()?;
+        let passages: Vec<&str> = passages.iter().map(String::as_str).collect();
+        reranker.score(&query, &passages).map_err(|e| e.to_string())
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.to_string()));
+
+    match scored {
+        Ok(scores) => {
+            let mut slots: Vec<Option<_>> = contexts.into_iter().map(Some).collect();
+            reranker::top_indices(&scores, limit)
+                .into_iter()
+                .filter_map(|i| {
+                    let mut context = slots[i].take()?;
+                    context.similarity = reranker::relevance(scores[i]);
+                    Some(context)
+                })
+                .collect()
+        }
+        Err(e) => {
+            warn!("Reranking failed, keeping the vector order: {}", e);
+            contexts.truncate(limit);
+            contexts
+        }
+    }
+}
+
+/// The reranker, loaded from the model cache on first use
+fn load_reranker() -> Result<Arc<Reranker>, String> {
+    let mut guard = RERANKER.lock().unwrap();
+    if let Some(reranker) = guard.as_ref() {
+        return Ok(reranker.clone());
+    }
+    let reranker = Arc::new(Reranker::new().map_err(|e| e.to_string())?);
+    *guard = Some(reranker.clone());
+    Ok(reranker)
+}
+
+/// Download the reranker model used when `ai.rerank_results` is on, and load it
+#[tauri::command]
+pub async fn download_reranker() -> Result<bool, String> {
+    embeddings::download_embedding_model(Some(DEFAULT_RERANKER_MODEL))
+        .await
+        .map_err(|e| format!("Failed to download reranker model: {}", e))?;
+    tokio::task::spawn_blocking(load_reranker)
+        .await
+        .map_err(|e| e.to_string())??;
+    Ok(true)
+}
+
+/// Check if the reranker model is downloaded (local cache only)
+#[tauri::command]
+pub fn is_reranker_downloaded() -> bool {
+    reranker::is_model_downloaded()
+}
+
+/// Answer a question from semantically similar emails. With a cancellation token the answer is
+/// streamed as `chat:token` events; otherwise it is generated in one go.
+pub(crate) async fn answer_with_context(
+    app: &AppHandle,
+    query: &str,
+    limit: usize,
+    stream: Option<CancellationToken>,
+) -> Result<ChatAnswer, String> {
+    ensure_embedding_loaded(app).await;
+
+    // Step 1: Lock the email database → emails the question's constraints allow → drop lock
+    let candidates = {
+        let db = app.state::<DbState>();
+        let db_lock = db.lock().unwrap();
+        let email_db = db_lock.as_ref().ok_or("Database not initialized")?;
+        question_candidates(email_db, query)?
+    };
 
-    // Step 1: Lock RAG_ENGINE → semantic search → drop lock
+    // Step 2: Lock RAG_ENGINE → semantic search → drop lock.
+    // When reranking, fetch more hits for the reranker to choose from.
+    let rerank = settings::current().ai.rerank_results && reranker::is_model_downloaded();
+    let hits = if rerank {
+        limit.max(RERANK_CANDIDATES)
+    } else {
+        limit
+    };
     let similar = {
         let rag_guard = RAG_ENGINE.lock().unwrap();
         let rag = rag_guard.as_ref().ok_or("RAG engine not initialized")?;
-        rag.search_similar(&query, limit, None)
+        rag.search_similar(query, hits, None, candidates.as_ref())
             .map_err(|e| format!("Failed to search: {}", e))?
     };
 
     if similar.is_empty() {
-        return Ok(format!("No relevant emails found for: {}", query));
+        return Ok(ChatAnswer::without_sources(format!(
+            "No relevant emails found for: {}",
+            query
+        )));
     }
 
-    // Step 2: Open EmailDatabase → fetch metadata → build RetrievedContext list
-    let email_db = crate::db::EmailDatabase::new(
-        app.path()
-            .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?
-            .join("emails.db"),
-    )
-    .map_err(|e| format!("Failed to open email database: {}", e))?;
+    // Step 3: Lock the email database → fetch metadata → build RetrievedContext list → drop lock
+    // (with the longer text the reranker reads)
+    let (contexts, passages): (Vec<RetrievedContext>, Vec<String>) = {
+        let db = app.state::<DbState>();
+        let db_lock = db.lock().unwrap();
+        let email_db = db_lock.as_ref().ok_or("Database not initialized")?;
+        similar
+            .into_iter()
+            .filter_map(|s| {
+                if let Ok(Some(email)) = email_db.get_email_by_id(&s.email_id) {
+                    let body = email.body_clean.as_deref().unwrap_or(&email.snippet);
+                    let snippet = body.chars().take(200).collect::<String>();
+                    let passage = if rerank {
+                        let text: String = body.chars().take(RERANK_PASSAGE_CHARS).collect();
+                        format!("{}\n{}", email.subject, text)
+                    } else {
+                        String::new()
+                    };
+                    Some((
+                        RetrievedContext {
+                            email_id: s.email_id,
+                            subject: email.subject,
+                            from: email.from,
+                            snippet,
+                            similarity: s.similarity,
+                        },
+                        passage,
+                    ))
+                } else {
+                    None
+                }
+            })
+            .unzip()
+    };
 
-    let contexts: Vec<RetrievedContext> = similar
-        .into_iter()
-        .filter_map(|s| {
-            if let Ok(Some(email)) = email_db.get_email_by_id(&s.email_id) {
-                let snippet = email
-                    .body_plain
-                    .as_deref()
-                    .unwrap_or(&email.snippet)
-                    .chars()
-                    .take(200)
-                    .collect::<String>();
-                Some(RetrievedContext {
-                    email_id: s.email_id,
-                    subject: email.subject,
-                    from: email.from,
-                    snippet,
-                    similarity: s.similarity,
-                })
-            } else {
-                None
-            }
-        })
-        .collect();
+    // Step 4: Reorder the hits with the reranker and keep the best `limit`
+    let contexts = if rerank {
+        rerank_contexts(query, contexts, passages, limit).await
+    } else {
+        contexts
+    };
 
     if contexts.is_empty() {
-        return Ok(format!("No relevant emails found for: {}", query));
+        return Ok(ChatAnswer::without_sources(format!(
+            "No relevant emails found for: {}",
+            query
+        )));
     }
 
-    // Build context string for the LLM
+    // Build context string for the LLM, numbering emails so the answer can cite them
     let context_str = contexts
         .iter()
         .enumerate()
         .map(|(i, ctx)| {
             format!(
-                "Email {}: From: {} | Subject: {} | {}",
+                "[{}] From: {} | Subject: {} | {}",
                 i + 1,
                 ctx.from,
                 ctx.subject,
@@ -481,31 +1262,56 @@ pub fn chat_with_context(
         })
         .collect::<Vec<_>>()
         .join("\n");
+    let sources: Vec<ChatSource> = contexts
+        .iter()
+        .enumerate()
+        .map(|(i, ctx)| ChatSource {
+            number: i + 1,
+            email_id: ctx.email_id.clone(),
+            subject: ctx.subject.clone(),
+            from: ctx.from.clone(),
+            relevance: ctx.similarity,
+        })
+        .collect();
 
-    // Step 3: Lock SUMMARIZER → generate response → drop lock
-    let summarizer_guard = crate::commands::ai::SUMMARIZER.lock().unwrap();
-    if let Some(summarizer) = summarizer_guard.as_ref() {
-        if summarizer.is_model_loaded() {
-            match summarizer.chat(&query, Some(&context_str)) {
-                Ok(response) => return Ok(response),
-                Err(e) => {
-                    let err_msg = e.to_string();
-                    eprintln!("[RAG Chat] LLM error: {}", err_msg);
-                    drop(summarizer_guard);
-                    return Ok(format!(
-                        "Found {} relevant emails:\n\n{}\n\n(AI generation error: {})",
-                        contexts.len(), context_str, err_msg
-                    ));
-                }
-            }
+    // Step 5: Generate the response on the LLM worker (None when no model is loaded)
+    let app_clone = app.clone();
+    let query_owned = query.to_string();
+    let context_owned = format!(
+        "Each email is numbered. When you use information from an email, cite it by its \
+         number in square brackets, like [1] or [2][3].\n\n{}",
+        context_str
+    );
+    let generated = LLM_WORKER
+        .run(Priority::Interactive, move |slot| {
+            let summarizer = slot.as_ref().filter(|s| s.is_model_loaded())?;
+            Some(match stream {
+                Some(cancel) => summarizer.chat_stream(None, &[], &query_owned, Some(&context_owned), Some(cancel), |token| {
+                    emit_chat_token(&app_clone, None, token);
+                }),
+                None => summarizer.chat(&query_owned, Some(&context_owned)),
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let answer = match generated {
+        Some(Ok(response)) => response,
+        Some(Err(e)) => {
+            let err_msg = e.to_string();
+            warn!("LLM error: {}", err_msg);
+            format!(
+                "Found {} relevant emails:\n\n{}\n\n(AI generation error: {})",
+                contexts.len(), context_str, err_msg
+            )
         }
-    }
-    drop(summarizer_guard);
+        // Fallback: model genuinely not loaded
+        None => format!(
+            "Found {} relevant emails:\n\n{}\n\n(AI model not loaded for detailed analysis)",
+            contexts.len(),
+            context_str
+        ),
+    };
 
-    // Fallback: model genuinely not loaded
-    Ok(format!(
-        "Found {} relevant emails:\n\n{}\n\n(AI model not loaded for detailed analysis)",
-        contexts.len(),
-        context_str
-    ))
+    Ok(ChatAnswer { answer, sources })
 }
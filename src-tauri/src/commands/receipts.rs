@@ -0,0 +1,119 @@
+//! Read receipts and bounces
+//!
+//! Emails that ask for a read receipt carry `read_receipt_to` until the user sends one
+//! with `send_read_receipt` or declines with `decline_read_receipt`; receipts are never
+//! sent automatically. Bounces of sent mail are picked up when they sync:
+//! `get_bounced_messages` lists the affected emails, and `check_recipients` tells the
+//! composer which addresses have bounced before.
+
+use lettre::message::Mailbox;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::commands::account::AccountManager;
+use crate::commands::email::get_account_client;
+use crate::db::email_db::{BouncedAddress, PendingFollowup};
+use crate::db::EmailDatabase;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Send the read receipt an email asked for
+#[tauri::command]
+pub async fn send_read_receipt(
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    email_id: String,
+) -> Result<(), String> {
+    let (email, account) = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        let email = database
+            .get_email_by_id(&email_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Email not found")?;
+        let account = database
+            .get_account(&email.account_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Account not found")?;
+        (email, account)
+    };
+    let receipt_to = email
+        .read_receipt_to
+        .clone()
+        .ok_or("This email doesn't ask for a read receipt")?;
+
+    let client_arc = get_account_client(&account, &account_manager).await?;
+    let client = client_arc.lock().await;
+    client
+        .send_read_receipt(&receipt_to, &email)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .mark_read_receipt_handled(&email.message_id, true)
+        .map_err(|e| e.to_string())
+}
+
+/// Don't send the read receipt an email asked for, and stop asking about it
+#[tauri::command]
+pub async fn decline_read_receipt(db: State<'_, DbState>, email_id: String) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let email = database
+        .get_email_by_id(&email_id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Email not found")?;
+    database
+        .mark_read_receipt_handled(&email.message_id, false)
+        .map_err(|e| e.to_string())
+}
+
+/// Sent emails that bounced, latest first, with the failed recipients and the reason
+#[tauri::command]
+pub async fn get_bounced_messages(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<PendingFollowup>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .get_bounced_messages(account_id.as_deref(), limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+/// Which recipients mail has bounced from, so the composer can warn before sending.
+/// Accepts bare addresses or "Name <address>".
+#[tauri::command]
+pub async fn check_recipients(
+    db: State<'_, DbState>,
+    recipients: Vec<String>,
+) -> Result<Vec<BouncedAddress>, String> {
+    let addresses: Vec<String> = recipients
+        .iter()
+        .map(|recipient| match recipient.parse::<Mailbox>() {
+            Ok(mailbox) => mailbox.email.to_string(),
+            Err(_) => recipient.trim().to_string(),
+        })
+        .collect();
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .get_bounced_addresses(&addresses)
+        .map_err(|e| e.to_string())
+}
+
+/// Stop warning about an address that bounced
+#[tauri::command]
+pub async fn clear_bounced_address(db: State<'_, DbState>, address: String) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .clear_bounced_address(&address)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,246 @@
+//! Awaiting-reply commands and follow-up reminders
+//!
+//! Indexing flags emails that ask the user something (`needs_reply`). These commands list
+//! the ones still unanswered, and a background task emits a `reply:reminder` event for
+//! emails that have waited longer than the configured number of days.
+//!
+//! The other direction is covered too: emails the user sent that nobody has answered are
+//! listed as pending follow-ups, and `draft_followup` writes a nudge for one.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tracing::{info, warn};
+
+use crate::commands::ai::{begin_ai_task, with_summarizer};
+use crate::db::email_db::{AwaitingReplyEmail, PendingFollowup, ReplyReminderSettings};
+use crate::db::EmailDatabase;
+use crate::email::reply;
+use crate::llm::Priority;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// How often the reminder task looks for overdue emails
+const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Most emails included in one reminder
+const REMINDER_BATCH: i64 = 20;
+
+/// Emails the user hasn't replied to yet, oldest first. `min_age_days` leaves out ones
+/// received more recently.
+#[tauri::command]
+pub async fn get_awaiting_reply(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    min_age_days: Option<i64>,
+    limit: Option<i64>,
+) -> Result<Vec<AwaitingReplyEmail>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let received_before = Utc::now().timestamp() - min_age_days.unwrap_or(0) * 86400;
+    database
+        .get_awaiting_reply(
+            account_id.as_deref(),
+            received_before,
+            false,
+            limit.unwrap_or(50),
+        )
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Mark an email as needing a reply or not, overriding the detection
+#[tauri::command]
+pub async fn set_needs_reply(
+    db: State<'_, DbState>,
+    email_id: String,
+    needs_reply: bool,
+) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .set_needs_reply(&email_id, needs_reply)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_reply_reminder_settings(
+    db: State<'_, DbState>,
+) -> Result<ReplyReminderSettings, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_reply_reminder_settings()
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_reply_reminder_settings(
+    db: State<'_, DbState>,
+    settings: ReplyReminderSettings,
+) -> Result<(), String> {
+    if settings.after_days == 0 {
+        return Err("Reminders need a delay of at least one day".to_string());
+    }
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .save_reply_reminder_settings(&settings)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// A follow-up ready to open in the composer; send it with `followup_of` set to
+/// `sent_id` so it threads under the original
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowupDraft {
+    pub sent_id: i64,
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Sent emails that haven't been answered, oldest first. Only ones that ask the
+/// recipients something are listed unless `include_all` is set.
+#[tauri::command]
+pub async fn get_pending_followups(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    min_age_days: Option<i64>,
+    include_all: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Vec<PendingFollowup>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let sent_before = Utc::now().timestamp() - min_age_days.unwrap_or(3) * 86400;
+    database
+        .get_pending_followups(
+            account_id.as_deref(),
+            sent_before,
+            include_all.unwrap_or(false),
+            limit.unwrap_or(50),
+        )
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Stop listing a sent email as awaiting a response
+#[tauri::command]
+pub async fn dismiss_followup(db: State<'_, DbState>, sent_id: i64) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .dismiss_followup(sent_id)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Draft a nudge for an unanswered sent email. Streams `compose:token` events like
+/// `compose_with_ai` when a model is loaded; otherwise returns a short template.
+#[tauri::command]
+pub async fn draft_followup(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    sent_id: i64,
+    task_id: Option<String>,
+) -> Result<FollowupDraft, String> {
+    ensure_unlocked()?;
+    let sent = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .get_sent_email(sent_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Sent email not found")?
+    };
+
+    let days_waiting = (Utc::now().timestamp() - sent.sent_at) / 86400;
+    let subject = sent.subject.clone();
+    let body_preview = sent.body_preview.clone();
+    let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+
+    let drafted = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .draft_followup_stream(
+                &subject,
+                &body_preview,
+                days_waiting,
+                Some(cancel),
+                |token| {
+                    let _ = app_clone.emit("compose:token", token);
+                },
+            )
+            .map_err(|e| e.to_string())
+    })
+    .await;
+    let body = match drafted {
+        Ok(body) => body.trim().to_string(),
+        Err(e) => {
+            info!("Falling back to the follow-up template: {}", e);
+            reply::followup_template(&sent.subject)
+        }
+    };
+
+    app.emit("compose:complete", ())
+        .map_err(|e| e.to_string())?;
+
+    Ok(FollowupDraft {
+        sent_id,
+        to: sent.to_emails,
+        subject: reply::reply_subject(&sent.subject),
+        body,
+    })
+}
+
+/// Periodically emit `reply:reminder` with emails that have waited longer than the
+/// configured delay. Each email is reminded about once.
+pub fn spawn_reply_reminders<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(REMINDER_CHECK_INTERVAL).await;
+            if let Err(e) = send_due_reminders(&app) {
+                warn!("Failed to check for overdue replies: {}", e);
+            }
+        }
+    });
+}
+
+fn send_due_reminders<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let db = app.state::<DbState>();
+    let db_lock = db.lock().unwrap();
+    let Some(database) = db_lock.as_ref() else {
+        return Ok(());
+    };
+
+    let settings = database
+        .get_reply_reminder_settings()
+        .map_err(|e| e.to_string())?;
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let received_before = Utc::now().timestamp() - settings.after_days as i64 * 86400;
+    let due = database
+        .get_awaiting_reply(None, received_before, true, REMINDER_BATCH)
+        .map_err(|e| e.to_string())?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let ids: Vec<String> = due.iter().map(|email| email.id.clone()).collect();
+    database
+        .mark_reply_reminded(&ids)
+        .map_err(|e| e.to_string())?;
+    info!("{} emails awaiting a reply", due.len());
+    app.emit("reply:reminder", due).map_err(|e| e.to_string())
+}
@@ -0,0 +1,94 @@
+//! Saved searches
+//!
+//! A saved search is search text (with Gmail-style operators such as `from:` or
+//! `is:unread`) plus filters the operators can't express, kept under a name so it can be
+//! run again with one click.
+
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::commands::inbox::{non_blank, normalize_priority};
+use crate::db::email_db::{EmailWithInsight, SavedSearch, SavedSearchFilters};
+use crate::db::EmailDatabase;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Save a search under `name`; saving under an existing name replaces that search
+#[tauri::command]
+pub async fn save_search(
+    db: State<'_, DbState>,
+    name: String,
+    query: String,
+    filters: Option<SavedSearchFilters>,
+) -> Result<SavedSearch, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Search name cannot be empty".to_string());
+    }
+    let filters = normalize_filters(filters.unwrap_or_default())?;
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .save_search(name, query.trim(), &filters)
+        .map_err(|e| e.to_string())
+}
+
+/// Saved searches by name, with their unread counts when `with_counts` is set
+#[tauri::command]
+pub async fn list_saved_searches(
+    db: State<'_, DbState>,
+    with_counts: Option<bool>,
+) -> Result<Vec<SavedSearch>, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let mut searches = database.list_saved_searches().map_err(|e| e.to_string())?;
+    if with_counts.unwrap_or(false) {
+        for search in &mut searches {
+            let (_, unread) = database
+                .count_search_matches(&search.query, &search.filters)
+                .map_err(|e| e.to_string())?;
+            search.unread_count = Some(unread);
+        }
+    }
+    Ok(searches)
+}
+
+/// Emails matching a saved search, newest first
+#[tauri::command]
+pub async fn run_saved_search(
+    db: State<'_, DbState>,
+    id: i64,
+    limit: Option<i64>,
+) -> Result<Vec<EmailWithInsight>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    let search = database
+        .get_saved_search(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Saved search {} not found", id))?;
+    database
+        .search_emails_filtered(&search.query, &search.filters, limit.unwrap_or(500))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_saved_search(db: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database.delete_saved_search(id).map_err(|e| e.to_string())
+}
+
+/// Drop blank fields and check the priority
+fn normalize_filters(mut filters: SavedSearchFilters) -> Result<SavedSearchFilters, String> {
+    filters.account_id = non_blank(filters.account_id);
+    filters.category = non_blank(filters.category);
+    filters.priority = normalize_priority(filters.priority)?;
+    Ok(filters)
+}
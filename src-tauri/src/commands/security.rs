@@ -0,0 +1,103 @@
+//! App lock commands
+//!
+//! See `crate::security` for what the lock covers. Locking and unlocking emit
+//! `app:locked` and `app:unlocked` so the frontend can cover or show the mail, including
+//! when the app locks itself after being idle.
+
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::security::{self, AppLockStatus, DEFAULT_AUTO_LOCK_MINUTES};
+
+/// How often the idle timeout is checked
+const AUTO_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[tauri::command]
+pub async fn get_app_lock_status() -> Result<AppLockStatus, String> {
+    Ok(security::status())
+}
+
+/// Set up the app lock with `passphrase`, or change the passphrase and options when it
+/// is already set up, which takes `current_passphrase`
+#[tauri::command]
+pub async fn set_app_lock(
+    passphrase: String,
+    current_passphrase: Option<String>,
+    biometric: Option<bool>,
+    auto_lock_minutes: Option<u32>,
+) -> Result<AppLockStatus, String> {
+    tokio::task::spawn_blocking(move || {
+        security::configure(
+            &passphrase,
+            current_passphrase.as_deref(),
+            biometric.unwrap_or(false),
+            auto_lock_minutes.unwrap_or(DEFAULT_AUTO_LOCK_MINUTES),
+        )
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn disable_app_lock(passphrase: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || security::disable(&passphrase).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn lock_app(app: AppHandle) -> Result<(), String> {
+    if !security::lock() {
+        return Err("The app lock is not set up".to_string());
+    }
+    let _ = app.emit("app:locked", ());
+    Ok(())
+}
+
+/// Unlock with `passphrase`, or with the biometric prompt when no passphrase is given
+#[tauri::command]
+pub async fn unlock_app(app: AppHandle, passphrase: Option<String>) -> Result<(), String> {
+    match passphrase {
+        Some(passphrase) => tokio::task::spawn_blocking(move || {
+            security::unlock_with_passphrase(&passphrase).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())??,
+        None => {
+            if !security::status().biometric {
+                return Err("Enter the passphrase".to_string());
+            }
+            biometric_prompt(&app)?;
+            security::unlock_with_biometric().map_err(|e| e.to_string())?;
+        }
+    }
+    let _ = app.emit("app:unlocked", ());
+    Ok(())
+}
+
+#[cfg(mobile)]
+fn biometric_prompt(app: &AppHandle) -> Result<(), String> {
+    use tauri_plugin_biometric::{AuthOptions, BiometricExt};
+
+    app.biometric()
+        .authenticate("Unlock Inboxed".to_string(), AuthOptions::default())
+        .map_err(|e| format!("Biometric unlock failed: {}", e))
+}
+
+#[cfg(not(mobile))]
+fn biometric_prompt(_app: &AppHandle) -> Result<(), String> {
+    Err("Biometric unlock isn't available on this platform".to_string())
+}
+
+/// Lock the app once it has been idle past its auto-lock timeout
+pub fn spawn_auto_lock<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(AUTO_LOCK_CHECK_INTERVAL).await;
+            if security::lock_if_idle() {
+                let _ = app.emit("app:locked", ());
+            }
+        }
+    });
+}
@@ -0,0 +1,43 @@
+//! Settings commands
+//!
+//! `update_settings` takes any part of the settings, e.g. `{ "sync": {
+//! "polling_interval_minutes": 5 } }`, and leaves the rest as they are. Changes made
+//! here or through the older per-subsystem settings commands are emitted as a
+//! `settings:changed` event carrying the new settings.
+
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+use crate::commands::ai::apply_llm_settings;
+use crate::settings::{self, AppSettings};
+
+/// Event emitted with the new settings whenever they change
+pub const SETTINGS_CHANGED_EVENT: &str = "settings:changed";
+
+/// Get all settings
+#[tauri::command]
+pub async fn get_settings() -> Result<AppSettings, String> {
+    Ok(settings::current())
+}
+
+/// Merge a partial settings object into the settings and save them. Returns the new
+/// settings.
+#[tauri::command]
+pub async fn update_settings(app: AppHandle, patch: Value) -> Result<AppSettings, String> {
+    let previous = settings::current();
+    let updated = settings::apply_patch(patch).map_err(|e| e.to_string())?;
+    settings_changed(&app, &updated);
+
+    if previous.llm != updated.llm {
+        apply_llm_settings().await?;
+    }
+    Ok(updated)
+}
+
+/// Tell the frontend the settings changed
+pub(crate) fn settings_changed(app: &AppHandle, settings: &AppSettings) {
+    if let Err(e) = app.emit(SETTINGS_CHANGED_EVENT, settings) {
+        warn!("Failed to emit settings change: {}", e);
+    }
+}
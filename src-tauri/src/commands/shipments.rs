@@ -0,0 +1,166 @@
+//! Package tracking
+//!
+//! Tracking numbers are picked out of shipping notices when they're cached (see
+//! `email::shipments`), and `get_shipments` lists the packages with their latest
+//! status. A background task tells the frontend about packages that are out for
+//! delivery with a `shipment:out_for_delivery` event. When the privacy settings allow
+//! it, the same task opens the carrier's tracking page of each undelivered package now
+//! and then to follow it between emails; otherwise nothing is sent to the carriers.
+
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tracing::{info, warn};
+
+use crate::db::email_db::Shipment;
+use crate::db::EmailDatabase;
+use crate::email::shipments::{self, ShipmentStatus};
+use crate::security::ensure_unlocked;
+use crate::settings;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// How often packages are looked at
+const TRACKING_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Shortest time between two checks of the same carrier page
+const RECHECK_SECS: i64 = 3 * 60 * 60;
+
+/// Packages first seen longer ago than this are no longer checked; they were delivered
+/// without a notice saying so, or lost
+const MAX_TRACKING_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Most carrier pages opened per run
+const TRACKING_BATCH: i64 = 10;
+
+/// Most of a tracking page read when looking for the status
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+
+/// Tracked packages, latest news first. Delivered ones are left out unless
+/// `include_delivered` is set.
+#[tauri::command]
+pub async fn get_shipments(
+    db: State<'_, DbState>,
+    account_id: Option<String>,
+    include_delivered: Option<bool>,
+    limit: Option<i64>,
+) -> Result<Vec<Shipment>, String> {
+    ensure_unlocked()?;
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_shipments(
+            account_id.as_deref(),
+            include_delivered.unwrap_or(false),
+            limit.unwrap_or(50),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Check carrier pages when allowed and announce packages out for delivery, every
+/// `TRACKING_CHECK_INTERVAL`
+pub fn spawn_shipment_tracking<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TRACKING_CHECK_INTERVAL).await;
+            let db = app.state::<DbState>().inner().clone();
+            if settings::current().privacy.check_shipment_status {
+                if let Err(e) = check_carrier_pages(&db).await {
+                    warn!("Failed to check shipment status: {}", e);
+                }
+            }
+            if let Err(e) = announce_out_for_delivery(&app, &db) {
+                warn!("Failed to announce deliveries: {}", e);
+            }
+        }
+    });
+}
+
+/// Read the tracking page of packages due for a check and store the status they show
+async fn check_carrier_pages(db: &DbState) -> Result<(), String> {
+    let now = Utc::now().timestamp();
+    let due = {
+        let db_lock = db.lock().unwrap();
+        let Some(database) = db_lock.as_ref() else {
+            return Ok(());
+        };
+        database
+            .get_shipments_to_check(
+                now - MAX_TRACKING_AGE_SECS,
+                now - RECHECK_SECS,
+                TRACKING_BATCH,
+            )
+            .map_err(|e| e.to_string())?
+    };
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    for shipment in due {
+        let status = match fetch_status(&client, &shipment.tracking_url).await {
+            Ok(status) => status,
+            Err(e) => {
+                warn!(
+                    "Failed to check {} package {}: {}",
+                    shipment.carrier_name, shipment.tracking_number, e
+                );
+                None
+            }
+        };
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .set_shipment_checked(shipment.id, status)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Status shown on a tracking page, or None when the page doesn't say (many carriers
+/// only fill it in with JavaScript)
+async fn fetch_status(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Option<ShipmentStatus>, String> {
+    let mut response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+    Ok(shipments::status_from_page(&String::from_utf8_lossy(&body)))
+}
+
+/// Emit `shipment:out_for_delivery` once for each package that went out for delivery
+fn announce_out_for_delivery<R: Runtime>(app: &AppHandle<R>, db: &DbState) -> Result<(), String> {
+    let db_lock = db.lock().unwrap();
+    let Some(database) = db_lock.as_ref() else {
+        return Ok(());
+    };
+    let out = database
+        .get_unnotified_out_for_delivery()
+        .map_err(|e| e.to_string())?;
+    if out.is_empty() {
+        return Ok(());
+    }
+
+    for shipment in &out {
+        database
+            .set_shipment_notified(shipment.id)
+            .map_err(|e| e.to_string())?;
+    }
+    info!("{} packages out for delivery", out.len());
+    app.emit("shipment:out_for_delivery", out)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,76 @@
+//! Sync preferences
+//!
+//! Settings are kept per account: which folders are synced, how far back, and whether
+//! new mail is noticed through IDLE or fast polling. The monitors, `fetch_emails` and
+//! indexing all consult them before caching mail.
+
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+use crate::commands::email::watch_account;
+use crate::db::EmailDatabase;
+use crate::email::idle::IdleManager;
+use crate::email::sync_window::{AccountSyncSettings, MIN_FAST_POLL_SECS};
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+#[tauri::command]
+pub async fn get_sync_settings(
+    db: State<'_, DbState>,
+    account_id: String,
+) -> Result<AccountSyncSettings, String> {
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+
+    database
+        .get_account_sync_settings(&account_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Save an account's sync settings. If its folders are being monitored, the monitors
+/// are restarted for the new folder selection.
+#[tauri::command]
+pub async fn set_sync_settings(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    idle_manager: State<'_, IdleManager>,
+    account_id: String,
+    mut settings: AccountSyncSettings,
+) -> Result<(), String> {
+    if let Some(folders) = settings.folders.as_mut() {
+        folders.retain(|folder| !folder.trim().is_empty());
+        if folders.is_empty() {
+            return Err("Choose at least one folder to sync".to_string());
+        }
+    }
+    if settings.days_back == Some(0) || settings.max_messages == Some(0) {
+        return Err("The sync window must include at least one day and one message".to_string());
+    }
+    if settings
+        .fast_poll_secs
+        .is_some_and(|secs| secs < MIN_FAST_POLL_SECS)
+    {
+        return Err(format!(
+            "Fast polling can't check more often than every {} seconds",
+            MIN_FAST_POLL_SECS
+        ));
+    }
+
+    let account = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .save_account_sync_settings(&account_id, &settings)
+            .map_err(|e| e.to_string())?;
+        database
+            .get_account(&account_id)
+            .map_err(|e| e.to_string())?
+    };
+
+    if let Some(account) = account {
+        if idle_manager.is_monitoring(&account.id).await {
+            watch_account(app, &idle_manager, &account, &settings).await;
+        }
+    }
+    Ok(())
+}
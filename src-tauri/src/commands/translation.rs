@@ -0,0 +1,96 @@
+//! Email translation
+//!
+//! `translate_email` translates an email's body into another language with the loaded
+//! model, streaming `translate:token` events and finishing with `translate:complete`.
+//! Translations are stored per email and language, so reading the email again shows the
+//! stored one instead of generating it again.
+
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::ai::{begin_ai_task, with_summarizer};
+use crate::db::email_db::EmailTranslation;
+use crate::db::EmailDatabase;
+use crate::email::language;
+use crate::llm::summarizer::Summarizer;
+use crate::llm::Priority;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Translate an email's body into `target_lang`, an ISO 639-3 code or a language name.
+/// A stored translation is returned without generating unless `refresh` is set. A
+/// translation stopped with `cancel_ai_task` is returned as far as it got but not stored.
+#[tauri::command]
+pub async fn translate_email(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    email_id: String,
+    target_lang: String,
+    refresh: Option<bool>,
+    task_id: Option<String>,
+) -> Result<EmailTranslation, String> {
+    ensure_unlocked()?;
+    let code = language::parse(&target_lang)
+        .ok_or_else(|| format!("Unknown language: {}", target_lang))?;
+    let language_name = language::english_name(code).unwrap_or(code);
+
+    let body = {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        if !refresh.unwrap_or(false) {
+            let cached = database
+                .get_translation(&email_id, code)
+                .map_err(|e| e.to_string())?;
+            if let Some(cached) = cached {
+                return Ok(cached);
+            }
+        }
+        let email = database
+            .get_email_by_id(&email_id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Email not found")?;
+        match (email.body_plain, email.body_html) {
+            (Some(plain), _) if !plain.trim().is_empty() => plain,
+            (_, Some(html)) => Summarizer::strip_html(&html),
+            _ => String::new(),
+        }
+    };
+    if body.trim().is_empty() {
+        return Err("This email has no text to translate".to_string());
+    }
+    if language::detect(&body) == Some(code) {
+        return Err(format!("This email is already in {}", language_name));
+    }
+
+    let app_clone = app.clone();
+    let task = begin_ai_task(task_id);
+    let cancel = task.token();
+    let translated = with_summarizer(Priority::Interactive, move |summarizer| {
+        summarizer
+            .translate_stream(&body, language_name, Some(cancel), |token| {
+                let _ = app_clone.emit("translate:token", token);
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    app.emit("translate:complete", ())
+        .map_err(|e| e.to_string())?;
+
+    let translation = EmailTranslation {
+        email_id,
+        language: code.to_string(),
+        body: translated,
+        translated_at: Utc::now().timestamp(),
+    };
+    if !task.token().is_cancelled() {
+        let db_lock = db.lock().unwrap();
+        let database = db_lock.as_ref().ok_or("Database not initialized")?;
+        database
+            .save_translation(&translation)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(translation)
+}
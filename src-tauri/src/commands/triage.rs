@@ -0,0 +1,131 @@
+//! Keyboard triage ("inbox zero") sessions
+//!
+//! `start_triage_session` hands the UI a queue of emails to go through one key press at
+//! a time, and `triage_action` archives, trashes, snoozes or keeps each one. Every
+//! session records how many emails it got through and how long it took. Snoozed emails
+//! stay where they are but are left out of triage queues until the snooze ends.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
+
+use crate::commands::account::AccountManager;
+use crate::commands::email::{move_and_forget, parse_email_id};
+use crate::db::email_db::{TriageFilter, TriageItem, TriageStats};
+use crate::db::EmailDatabase;
+use crate::security::ensure_unlocked;
+
+type DbState = Arc<Mutex<Option<EmailDatabase>>>;
+
+/// Most emails in one triage queue
+const MAX_QUEUE: i64 = 500;
+
+/// How long a snooze lasts unless a time is given
+const DEFAULT_SNOOZE_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageAction {
+    Archive,
+    Trash,
+    Snooze,
+    /// Leave the email as it is and move on
+    Keep,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageSession {
+    pub session_id: i64,
+    pub items: Vec<TriageItem>,
+}
+
+/// Start a triage session over the cached emails matching `filter`, newest first
+#[tauri::command]
+pub async fn start_triage_session(
+    db: State<'_, DbState>,
+    filter: Option<TriageFilter>,
+    limit: Option<i64>,
+) -> Result<TriageSession, String> {
+    ensure_unlocked()?;
+    let filter = filter.unwrap_or_default();
+    let limit = limit.unwrap_or(MAX_QUEUE).clamp(1, MAX_QUEUE);
+    let now = Utc::now().timestamp();
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    let items = database
+        .get_triage_queue(&filter, now, limit)
+        .map_err(|e| e.to_string())?;
+    let session_id = database
+        .start_triage_session(items.len() as i64, now)
+        .map_err(|e| e.to_string())?;
+
+    Ok(TriageSession { session_id, items })
+}
+
+/// Apply `action` to one email of a session and return the session's stats so far.
+/// `snooze_until` (Unix seconds) defaults to a day from now.
+#[tauri::command]
+pub async fn triage_action(
+    app: AppHandle,
+    db: State<'_, DbState>,
+    account_manager: State<'_, AccountManager>,
+    session_id: i64,
+    email_id: String,
+    action: TriageAction,
+    snooze_until: Option<i64>,
+) -> Result<TriageStats, String> {
+    let counted = match action {
+        TriageAction::Archive => {
+            move_and_forget(&app, &db, &account_manager, &email_id, "Archive").await?;
+            "archived"
+        }
+        TriageAction::Trash => {
+            move_and_forget(&app, &db, &account_manager, &email_id, "Trash").await?;
+            "trashed"
+        }
+        TriageAction::Snooze => {
+            let now = Utc::now().timestamp();
+            let until = snooze_until.unwrap_or(now + DEFAULT_SNOOZE_SECS);
+            if until <= now {
+                return Err("Snooze until a time in the future".to_string());
+            }
+            let (account_id, _, _) = parse_email_id(&email_id)
+                .ok_or_else(|| format!("Invalid email ID: {}", email_id))?;
+            let db_lock = db.lock().unwrap();
+            let database = db_lock.as_ref().ok_or("Database not initialized")?;
+            database
+                .snooze_email(&email_id, &account_id, until)
+                .map_err(|e| e.to_string())?;
+            "snoozed"
+        }
+        TriageAction::Keep => "kept",
+    };
+
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .record_triage_action(session_id, counted)
+        .map_err(|e| e.to_string())?;
+    database
+        .get_triage_stats(session_id, Utc::now().timestamp())
+        .map_err(|e| e.to_string())
+}
+
+/// Close a session and return what it got through
+#[tauri::command]
+pub async fn end_triage_session(
+    db: State<'_, DbState>,
+    session_id: i64,
+) -> Result<TriageStats, String> {
+    let now = Utc::now().timestamp();
+    let db_lock = db.lock().unwrap();
+    let database = db_lock.as_ref().ok_or("Database not initialized")?;
+    database
+        .end_triage_session(session_id, now)
+        .map_err(|e| e.to_string())?;
+    database
+        .get_triage_stats(session_id, now)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,450 @@
+//! Approximate nearest neighbor index for email embeddings
+//!
+//! An in-memory HNSW (hierarchical navigable small world) graph over normalized vectors,
+//! so a similarity query visits a few hundred embeddings instead of all of them.
+//! Removal leaves a tombstone that still routes searches but is never returned, nor
+//! counted towards the results a search collects; the graph is rebuilt from the live
+//! entries once tombstones outnumber them.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Links per node on the upper layers (layer 0 keeps twice as many)
+const DEFAULT_M: usize = 16;
+/// Candidate list size while inserting
+const DEFAULT_EF_CONSTRUCTION: usize = 100;
+/// Minimum candidate list size while searching
+const DEFAULT_EF_SEARCH: usize = 64;
+
+struct Node {
+    id: String,
+    vector: Vec<f32>,
+    /// Neighbor lists, one per layer the node lives on
+    links: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+/// A node and its distance from the current query
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct HnswIndex {
+    dimensions: usize,
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    level_factor: f64,
+    nodes: Vec<Node>,
+    lookup: HashMap<String, u32>,
+    entry_point: Option<u32>,
+    max_level: usize,
+    deleted: usize,
+    rng_state: u64,
+}
+
+impl HnswIndex {
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions,
+            m: DEFAULT_M,
+            ef_construction: DEFAULT_EF_CONSTRUCTION,
+            ef_search: DEFAULT_EF_SEARCH,
+            level_factor: 1.0 / (DEFAULT_M as f64).ln(),
+            nodes: Vec::new(),
+            lookup: HashMap::new(),
+            entry_point: None,
+            max_level: 0,
+            deleted: 0,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Number of live (searchable) entries
+    pub fn len(&self) -> usize {
+        self.lookup.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookup.is_empty()
+    }
+
+    /// Add or replace the vector for `id`. Returns false (and leaves the index unchanged)
+    /// if the vector has the wrong dimensions or zero length.
+    pub fn insert(&mut self, id: &str, vector: &[f32]) -> bool {
+        let Some(vector) = normalize(vector, self.dimensions) else {
+            return false;
+        };
+        self.remove(id);
+
+        let node = self.nodes.len() as u32;
+        let level = self.random_level();
+        self.nodes.push(Node {
+            id: id.to_string(),
+            vector,
+            links: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.lookup.insert(id.to_string(), node);
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            self.max_level = level;
+            return true;
+        };
+
+        let query = self.nodes[node as usize].vector.clone();
+
+        // Greedy descent through the layers above the new node's level
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer, false)[0].node;
+        }
+
+        let mut entry_points = vec![entry];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates =
+                self.search_layer(&query, &entry_points, self.ef_construction, layer, false);
+            let neighbors: Vec<u32> = candidates
+                .iter()
+                .take(self.max_links(layer))
+                .map(|c| c.node)
+                .collect();
+
+            for &neighbor in &neighbors {
+                self.link(neighbor, node, layer);
+            }
+            self.nodes[node as usize].links[layer] = neighbors;
+            entry_points = candidates.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry_point = Some(node);
+        }
+        true
+    }
+
+    /// Remove `id` from search results; returns whether it was present
+    pub fn remove(&mut self, id: &str) -> bool {
+        let Some(node) = self.lookup.remove(id) else {
+            return false;
+        };
+        self.nodes[node as usize].deleted = true;
+        self.deleted += 1;
+
+        if self.deleted > self.lookup.len() {
+            self.rebuild();
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.dimensions);
+    }
+
+    /// The `k` entries most similar to `query`, as (id, cosine similarity), best first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        let (Some(entry), Some(query)) = (self.entry_point, normalize(query, self.dimensions)) else {
+            return Vec::new();
+        };
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let mut entry = entry;
+        for layer in (1..=self.max_level).rev() {
+            entry = self.search_layer(&query, &[entry], 1, layer, false)[0].node;
+        }
+
+        self.search_layer(&query, &[entry], self.ef_search.max(k), 0, true)
+            .into_iter()
+            .take(k)
+            .map(|c| (self.nodes[c.node as usize].id.clone(), 1.0 - c.distance))
+            .collect()
+    }
+
+    /// Rebuild the graph from live entries, dropping tombstones
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.clear();
+        for node in nodes.into_iter().filter(|n| !n.deleted) {
+            self.insert(&node.id, &node.vector);
+        }
+    }
+
+    /// Beam search on one layer; returns up to `ef` nodes closest to `query`, nearest first.
+    /// With `live_only`, tombstones are still expanded through but not collected, so they
+    /// don't take the place of live nodes in the `ef` found.
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[u32],
+        ef: usize,
+        layer: usize,
+        live_only: bool,
+    ) -> Vec<Candidate> {
+        let collect = |node: u32| !live_only || !self.nodes[node as usize].deleted;
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        // Min-heap of nodes to expand and max-heap of the best `ef` found so far
+        let mut to_visit: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+        let mut found: BinaryHeap<Candidate> = BinaryHeap::new();
+
+        for &node in entry_points {
+            let candidate = Candidate {
+                distance: self.distance(query, node),
+                node,
+            };
+            to_visit.push(std::cmp::Reverse(candidate));
+            if collect(node) {
+                found.push(candidate);
+            }
+        }
+        while found.len() > ef {
+            found.pop();
+        }
+
+        while let Some(std::cmp::Reverse(current)) = to_visit.pop() {
+            let worst = found.peek().map_or(f32::INFINITY, |c| c.distance);
+            if current.distance > worst && found.len() >= ef {
+                break;
+            }
+
+            let Some(links) = self.nodes[current.node as usize].links.get(layer) else {
+                continue;
+            };
+            for &neighbor in links {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: self.distance(query, neighbor),
+                    node: neighbor,
+                };
+                let worst = found.peek().map_or(f32::INFINITY, |c| c.distance);
+                if found.len() < ef || candidate.distance < worst {
+                    to_visit.push(std::cmp::Reverse(candidate));
+                    if collect(neighbor) {
+                        found.push(candidate);
+                        if found.len() > ef {
+                            found.pop();
+                        }
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Add a link `from -> to`, dropping `from`'s farthest links if it has too many
+    fn link(&mut self, from: u32, to: u32, layer: usize) {
+        let max_links = self.max_links(layer);
+        let mut links = std::mem::take(&mut self.nodes[from as usize].links[layer]);
+        links.push(to);
+
+        if links.len() > max_links {
+            let origin = &self.nodes[from as usize].vector;
+            let mut ranked: Vec<Candidate> = links
+                .iter()
+                .map(|&node| Candidate {
+                    distance: self.distance(origin, node),
+                    node,
+                })
+                .collect();
+            ranked.sort();
+            links = ranked.into_iter().take(max_links).map(|c| c.node).collect();
+        }
+        self.nodes[from as usize].links[layer] = links;
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.m * 2
+        } else {
+            self.m
+        }
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> f32 {
+        let vector = &self.nodes[node as usize].vector;
+        1.0 - query.iter().zip(vector).map(|(a, b)| a * b).sum::<f32>()
+    }
+
+    /// Exponentially distributed level, so each layer holds ~1/m of the one below
+    fn random_level(&mut self) -> usize {
+        // xorshift64*: deterministic, and good enough for level assignment
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let uniform = ((bits >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() * self.level_factor) as usize
+    }
+}
+
+/// Scale to unit length so cosine similarity is a dot product
+fn normalize(vector: &[f32], dimensions: usize) -> Option<Vec<f32>> {
+    if vector.len() != dimensions {
+        return None;
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return None;
+    }
+    Some(vector.iter().map(|v| v / norm).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                (0..dimensions)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn brute_force(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<usize> {
+        let query = normalize(query, query.len()).unwrap();
+        let mut scored: Vec<(f32, usize)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let v = normalize(v, v.len()).unwrap();
+                (query.iter().zip(&v).map(|(a, b)| a * b).sum(), i)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(k).map(|(_, i)| i).collect()
+    }
+
+    #[test]
+    fn test_recall_against_brute_force() {
+        let vectors = random_vectors(1000, 32);
+        let mut index = HnswIndex::new(32);
+        for (i, vector) in vectors.iter().enumerate() {
+            assert!(index.insert(&i.to_string(), vector));
+        }
+
+        let mut hits = 0;
+        for query in random_vectors(1020, 32).iter().skip(1000) {
+            let expected = brute_force(&vectors, query, 10);
+            let found: Vec<usize> = index
+                .search(query, 10)
+                .into_iter()
+                .map(|(id, _)| id.parse().unwrap())
+                .collect();
+            hits += expected.iter().filter(|i| found.contains(i)).count();
+        }
+        assert!(hits >= 180, "recall too low: {}/200", hits);
+    }
+
+    #[test]
+    fn test_exact_match_ranks_first() {
+        let vectors = random_vectors(200, 8);
+        let mut index = HnswIndex::new(8);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector);
+        }
+
+        let results = index.search(&vectors[17], 3);
+        assert_eq!(results[0].0, "17");
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_remove_and_replace() {
+        let vectors = random_vectors(50, 8);
+        let mut index = HnswIndex::new(8);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector);
+        }
+
+        assert!(index.remove("3"));
+        assert!(!index.remove("3"));
+        assert_eq!(index.len(), 49);
+        assert!(index.search(&vectors[3], 50).iter().all(|(id, _)| id != "3"));
+
+        // Re-inserting an id replaces its vector
+        index.insert("4", &vectors[5]);
+        assert_eq!(index.len(), 49);
+        let results = index.search(&vectors[5], 2);
+        assert!(results.iter().any(|(id, _)| id == "4"));
+        assert!(results.iter().any(|(id, _)| id == "5"));
+    }
+
+    #[test]
+    fn test_rebuild_after_many_removals() {
+        let vectors = random_vectors(100, 8);
+        let mut index = HnswIndex::new(8);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector);
+        }
+        for i in 0..80 {
+            index.remove(&i.to_string());
+        }
+
+        assert_eq!(index.len(), 20);
+        assert!(index.nodes.len() < 100);
+        assert_eq!(index.search(&vectors[90], 1)[0].0, "90");
+    }
+
+    #[test]
+    fn test_search_fills_k_around_tombstones() {
+        let vectors = random_vectors(300, 16);
+        let mut index = HnswIndex::new(16);
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), vector);
+        }
+        // Just under the rebuild threshold: nearly half the graph is tombstones
+        for i in (0..298).step_by(2) {
+            index.remove(&i.to_string());
+        }
+        assert_eq!(index.nodes.len(), 300);
+
+        for query in [&vectors[10], &vectors[11], &vectors[200]] {
+            let results = index.search(query, 50);
+            assert_eq!(results.len(), 50);
+            assert!(results.iter().all(|(id, _)| index.lookup.contains_key(id)));
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_vectors() {
+        let mut index = HnswIndex::new(4);
+        assert!(!index.insert("a", &[1.0, 2.0]));
+        assert!(!index.insert("b", &[0.0; 4]));
+        assert!(index.is_empty());
+        assert!(index.search(&[1.0, 0.0, 0.0, 0.0], 5).is_empty());
+    }
+}
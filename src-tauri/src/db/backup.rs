@@ -0,0 +1,363 @@
+//! Backup archives of the local mail data
+//!
+//! A backup is a zip holding snapshots of the email and vector databases, the media
+//! cache and a `manifest.json` listing every file with its size and SHA-256. Database
+//! snapshots are taken with SQLite's online backup, so the app keeps working while they
+//! are copied, and restored the same way into the open connections. Archives are checked
+//! against their manifest right after they are written and again while being extracted
+//! for a restore.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Archive entries of the two databases
+pub const EMAIL_DB_ENTRY: &str = "emails.db";
+pub const VECTOR_DB_ENTRY: &str = "email_vectors.db";
+
+/// Folder of the archive holding the media cache
+pub const MEDIA_ENTRY_PREFIX: &str = "media_cache/";
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const BACKUP_FORMAT: &str = "inboxed-backup";
+
+/// Bumped when the archive layout changes in a way older versions can't restore
+const BACKUP_VERSION: u32 = 1;
+
+/// Pages copied per step of an online backup
+const PAGES_PER_STEP: i32 = 256;
+
+/// Wait before retrying a step while another connection holds a lock
+const BUSY_RETRY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: u32,
+    pub created_at: i64,
+    pub app_version: String,
+    pub files: Vec<BackupFile>,
+}
+
+impl BackupManifest {
+    pub fn contains(&self, name: &str) -> bool {
+        self.files.iter().any(|file| file.name == name)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// A file in a backup archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub name: String,
+    pub size: u64,
+    /// Lowercase hex SHA-256 of the contents
+    pub sha256: String,
+}
+
+/// Copy the database open in `from` into `to` page by page, calling `progress` with
+/// (pages copied, total pages) after each step
+pub fn copy_database(
+    from: &Connection,
+    to: &mut Connection,
+    mut progress: impl FnMut(i64, i64),
+) -> Result<()> {
+    let backup = Backup::new(from, to)?;
+    loop {
+        let step = backup.step(PAGES_PER_STEP)?;
+        let state = backup.progress();
+        progress(
+            i64::from(state.pagecount - state.remaining),
+            i64::from(state.pagecount),
+        );
+        match step {
+            StepResult::Done => return Ok(()),
+            StepResult::More => {}
+            _ => std::thread::sleep(BUSY_RETRY),
+        }
+    }
+}
+
+/// Fail unless SQLite finds the database at `path` intact
+pub fn check_integrity(path: &Path) -> Result<()> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let result: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if result != "ok" {
+        bail!("{:?} failed the integrity check: {}", path, result);
+    }
+    Ok(())
+}
+
+/// Zip archive being written, collecting the manifest as files are added
+pub struct ArchiveWriter {
+    zip: ZipWriter<File>,
+    files: Vec<BackupFile>,
+}
+
+impl ArchiveWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        Ok(Self {
+            zip: ZipWriter::new(file),
+            files: Vec::new(),
+        })
+    }
+
+    /// Add the file at `source` as `name`. Databases are worth compressing; media files
+    /// are mostly compressed already.
+    pub fn add_file(&mut self, name: &str, source: &Path, compress: bool) -> Result<()> {
+        let method = if compress {
+            CompressionMethod::Deflated
+        } else {
+            CompressionMethod::Stored
+        };
+        let options = SimpleFileOptions::default()
+            .compression_method(method)
+            .large_file(true);
+        self.zip.start_file(name, options)?;
+
+        let mut reader =
+            File::open(source).with_context(|| format!("Failed to open {:?}", source))?;
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            self.zip.write_all(&buffer[..read])?;
+            size += read as u64;
+        }
+
+        self.files.push(BackupFile {
+            name: name.to_string(),
+            size,
+            sha256: hex(&hasher.finalize()),
+        });
+        Ok(())
+    }
+
+    /// Add every file under `dir` below `prefix`, calling `progress` with (files added,
+    /// total files)
+    pub fn add_dir(
+        &mut self,
+        prefix: &str,
+        dir: &Path,
+        mut progress: impl FnMut(i64, i64),
+    ) -> Result<()> {
+        let mut files = Vec::new();
+        if dir.exists() {
+            list_files(dir, dir, &mut files)?;
+        }
+        let total = files.len() as i64;
+        for (done, relative) in files.iter().enumerate() {
+            let name = format!("{}{}", prefix, relative);
+            self.add_file(&name, &dir.join(relative), false)?;
+            progress(done as i64 + 1, total);
+        }
+        Ok(())
+    }
+
+    /// Write the manifest and close the archive
+    pub fn finish(mut self, created_at: i64) -> Result<BackupManifest> {
+        let manifest = BackupManifest {
+            format: BACKUP_FORMAT.to_string(),
+            version: BACKUP_VERSION,
+            created_at,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            files: self.files,
+        };
+        self.zip
+            .start_file(MANIFEST_ENTRY, SimpleFileOptions::default())?;
+        self.zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        self.zip.finish()?.sync_all()?;
+        Ok(manifest)
+    }
+}
+
+/// Check every file of the archive at `path` against its manifest, calling `progress`
+/// with (files checked, total files)
+pub fn verify_archive(path: &Path, progress: impl FnMut(i64, i64)) -> Result<BackupManifest> {
+    read_archive(path, None, progress)
+}
+
+/// Extract the archive at `path` into `dest`, checking every file against the manifest
+/// on the way. Fails, leaving a partial extraction behind, at the first mismatch.
+pub fn extract_archive(
+    path: &Path,
+    dest: &Path,
+    progress: impl FnMut(i64, i64),
+) -> Result<BackupManifest> {
+    read_archive(path, Some(dest), progress)
+}
+
+fn read_archive(
+    path: &Path,
+    dest: Option<&Path>,
+    mut progress: impl FnMut(i64, i64),
+) -> Result<BackupManifest> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut archive = ZipArchive::new(file).context("Not a backup archive")?;
+
+    let manifest: BackupManifest = {
+        let entry = archive
+            .by_name(MANIFEST_ENTRY)
+            .map_err(|_| anyhow!("Not a backup archive: it has no manifest"))?;
+        serde_json::from_reader(entry).context("The backup manifest is unreadable")?
+    };
+    if manifest.format != BACKUP_FORMAT {
+        bail!("Not an Inboxed backup");
+    }
+    if manifest.version > BACKUP_VERSION {
+        bail!(
+            "This backup was made by a newer version of Inboxed ({})",
+            manifest.app_version
+        );
+    }
+
+    let total = manifest.files.len() as i64;
+    for (done, expected) in manifest.files.iter().enumerate() {
+        let relative = safe_relative_path(&expected.name)?;
+        let mut entry = archive
+            .by_name(&expected.name)
+            .map_err(|_| anyhow!("The backup is missing {}", expected.name))?;
+        let mut writer = match dest {
+            Some(dest) => {
+                let target = dest.join(&relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Some(File::create(&target)?)
+            }
+            None => None,
+        };
+
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = entry.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            if let Some(writer) = writer.as_mut() {
+                writer.write_all(&buffer[..read])?;
+            }
+            size += read as u64;
+        }
+        if size != expected.size || hex(&hasher.finalize()) != expected.sha256 {
+            bail!(
+                "The backup is damaged: {} doesn't match its checksum",
+                expected.name
+            );
+        }
+        progress(done as i64 + 1, total);
+    }
+    Ok(manifest)
+}
+
+/// Relative paths of the files under `dir`, with `/` separators
+fn list_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            list_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let parts: Vec<_> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect();
+            files.push(parts.join("/"));
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// An archive entry name as a path that stays inside the extraction directory
+fn safe_relative_path(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name);
+    let safe = !name.is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if !safe {
+        bail!("The backup holds an unsafe path: {}", name);
+    }
+    Ok(path)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip_and_tampering() {
+        let dir = std::env::temp_dir().join(format!("inboxed-backup-test-{}", std::process::id()));
+        let media = dir.join("media");
+        fs::create_dir_all(media.join("abc")).unwrap();
+        fs::write(dir.join("emails.db"), b"not really sqlite").unwrap();
+        fs::write(media.join("abc").join("logo.png"), b"png bytes").unwrap();
+
+        let archive_path = dir.join("backup.zip");
+        let mut writer = ArchiveWriter::create(&archive_path).unwrap();
+        writer
+            .add_file(EMAIL_DB_ENTRY, &dir.join("emails.db"), false)
+            .unwrap();
+        writer
+            .add_dir(MEDIA_ENTRY_PREFIX, &media, |_, _| {})
+            .unwrap();
+        let manifest = writer.finish(1_700_000_000).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert!(manifest.contains("media_cache/abc/logo.png"));
+
+        let verified = verify_archive(&archive_path, |_, _| {}).unwrap();
+        assert_eq!(verified.total_bytes(), manifest.total_bytes());
+
+        let restored = dir.join("restored");
+        extract_archive(&archive_path, &restored, |_, _| {}).unwrap();
+        assert_eq!(
+            fs::read(restored.join("media_cache/abc/logo.png")).unwrap(),
+            b"png bytes"
+        );
+
+        // Flip a byte of the stored database; the checksum catches it
+        let mut bytes = fs::read(&archive_path).unwrap();
+        let at = bytes
+            .windows(b"not really".len())
+            .position(|window| window == b"not really")
+            .unwrap();
+        bytes[at] ^= 0xff;
+        fs::write(&archive_path, bytes).unwrap();
+        assert!(verify_archive(&archive_path, |_, _| {}).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert!(safe_relative_path("media_cache/abc/logo.png").is_ok());
+        assert!(safe_relative_path("../outside").is_err());
+        assert!(safe_relative_path("/etc/passwd").is_err());
+        assert!(safe_relative_path("").is_err());
+    }
+}
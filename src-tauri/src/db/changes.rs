@@ -0,0 +1,132 @@
+//! What changed in the cache, for views to follow
+//!
+//! Writes record the IDs of the emails they touched here rather than emitting events
+//! themselves: the database layer has no app handle, several `EmailDatabase`s are open
+//! at once, and a sync storing thousands of messages would send an event per message.
+//! A background task takes what is pending a few times a second and emits one event per
+//! kind of change (`emails:added`, `emails:updated`, `emails:removed`, `insights:updated`).
+
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// A change to one cached email
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Newly cached
+    Added,
+    /// Flags, body or anything else stored with the email
+    Updated,
+    /// No longer cached
+    Removed,
+    /// Its AI insights were stored or changed
+    Insights,
+}
+
+/// Emails changed since the last `take`, each listed under the change that sums up
+/// what happened to it
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct CacheChanges {
+    pub added: BTreeSet<String>,
+    pub updated: BTreeSet<String>,
+    pub removed: BTreeSet<String>,
+    pub insights: BTreeSet<String>,
+}
+
+impl CacheChanges {
+    const fn new() -> Self {
+        Self {
+            added: BTreeSet::new(),
+            updated: BTreeSet::new(),
+            removed: BTreeSet::new(),
+            insights: BTreeSet::new(),
+        }
+    }
+
+    fn record(&mut self, change: Change, email_id: &str) {
+        match change {
+            Change::Added => {
+                self.updated.remove(email_id);
+                // Removed and cached again, e.g. re-synced: the view has it already
+                if self.removed.remove(email_id) {
+                    self.updated.insert(email_id.to_string());
+                } else {
+                    self.added.insert(email_id.to_string());
+                }
+            }
+            Change::Updated => {
+                if !self.added.contains(email_id) && !self.removed.contains(email_id) {
+                    self.updated.insert(email_id.to_string());
+                }
+            }
+            Change::Removed => {
+                self.updated.remove(email_id);
+                self.insights.remove(email_id);
+                // Added and removed before anyone heard of it
+                if !self.added.remove(email_id) {
+                    self.removed.insert(email_id.to_string());
+                }
+            }
+            Change::Insights => {
+                if !self.removed.contains(email_id) {
+                    self.insights.insert(email_id.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.insights.is_empty()
+    }
+}
+
+static PENDING: Mutex<CacheChanges> = Mutex::new(CacheChanges::new());
+
+/// Note a change to an email for the next round of events
+pub fn record(change: Change, email_id: &str) {
+    PENDING.lock().unwrap().record(change, email_id);
+}
+
+/// The changes recorded since the last call
+pub fn take() -> CacheChanges {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(set: &BTreeSet<String>) -> Vec<&str> {
+        set.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_record() {
+        let mut changes = CacheChanges::new();
+        changes.record(Change::Added, "a");
+        changes.record(Change::Updated, "a");
+        changes.record(Change::Updated, "b");
+        changes.record(Change::Insights, "b");
+        assert_eq!(ids(&changes.added), vec!["a"]);
+        assert_eq!(ids(&changes.updated), vec!["b"]);
+        assert_eq!(ids(&changes.insights), vec!["b"]);
+
+        // Removal wins over earlier updates and cancels an addition
+        changes.record(Change::Removed, "a");
+        changes.record(Change::Removed, "b");
+        changes.record(Change::Insights, "b");
+        assert!(changes.added.is_empty());
+        assert!(changes.updated.is_empty());
+        assert!(changes.insights.is_empty());
+        assert_eq!(ids(&changes.removed), vec!["b"]);
+
+        // Cached again after removal
+        changes.record(Change::Added, "b");
+        assert!(changes.removed.is_empty());
+        assert_eq!(ids(&changes.updated), vec!["b"]);
+        assert!(!changes.is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+//! One message cached more than once
+//!
+//! Gmail lists a message in INBOX and in All Mail, and a message sent to two connected
+//! accounts is delivered to both, so the cache can hold several copies of it under
+//! different IDs. Copies are linked by `emails.message_key`, the Message-ID header in a
+//! canonical form. One copy is the primary: the one with a body, then the one with the
+//! lowest ID. Only the primary is analyzed and embedded, its insights are shared with
+//! the other copies, and lists can show the primary alone.
+
+/// The canonical form of a Message-ID header: without surrounding whitespace and angle
+/// brackets, in lowercase. `None` for an empty or missing one, which links nothing.
+pub fn message_key(message_id: &str) -> Option<String> {
+    let key = message_id
+        .trim()
+        .trim_matches(|c| c == '<' || c == '>')
+        .trim()
+        .to_lowercase();
+    (!key.is_empty()).then_some(key)
+}
+
+/// SQL condition that holds when the email `e` is a copy other than the primary
+pub const IS_DUPLICATE: &str = "EXISTS (SELECT 1 FROM emails dup
+             WHERE dup.message_key = e.message_key
+               AND (dup.has_body > e.has_body OR (dup.has_body = e.has_body AND dup.id < e.id)))";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_key() {
+        assert_eq!(
+            message_key(" <CAF1x=Ab@mail.Example.com>\r\n").as_deref(),
+            Some("caf1x=ab@mail.example.com")
+        );
+        assert_eq!(
+            message_key("caf1x=ab@mail.example.com"),
+            message_key("<CAF1x=AB@MAIL.example.com>")
+        );
+        assert_eq!(message_key("< a@b >").as_deref(), Some("a@b"));
+        assert_eq!(message_key(""), None);
+        assert_eq!(message_key(" <> "), None);
+    }
+}
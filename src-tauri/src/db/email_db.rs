@@ -1,13 +1,37 @@
 use anyhow::{Context, Result as AnyhowResult};
 use chrono::Utc;
-use rusqlite::{params, Connection, OptionalExtension, Result};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
 
-use super::schema::create_tables;
-use crate::auth::account::Account;
-use crate::email::types::Email;
+use super::backup::copy_database;
+use super::changes::{self, Change};
+use super::dedupe::{message_key, IS_DUPLICATE};
+use super::schema::{configure_connection, create_tables, BUSY_TIMEOUT};
+use super::search_query::{SearchQuery, SqlValue};
+use crate::auth::account::{Account, Identity};
+use crate::email::attachments::AttachmentKind;
+use crate::email::capabilities::FolderSyncState;
+use crate::email::gmail::{self, GmailCategory};
+use crate::email::links::{EmailLink, LinkMetadata};
+use crate::email::notifications::{NotificationSettings, QuietHours};
+use crate::email::priority::{
+    self, Correction, PriorityFeatures, PriorityWeights, SenderHistory, MIN_CORRECTIONS,
+};
+use crate::email::quotes::clean_body;
+use crate::email::shipments::{Carrier, ShipmentStatus};
+use crate::email::special_folders::{parse_role_key, role_key};
+use crate::email::sync_window::AccountSyncSettings;
+use crate::email::threading::{self, address_of, is_reply_subject, subject_key};
+use crate::email::types::{
+    Attachment, Email, EmailListItem, Folder, FolderStatus, Label, SpecialFolder,
+};
+use crate::llm::{LlmSettings, ModelBenchmark};
+use crate::settings;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailInsight {
@@ -15,13 +39,30 @@ pub struct EmailInsight {
     pub summary: Option<String>,
     pub priority: String,
     pub priority_score: f64,
+    /// Score of the model's own label (0.5 without a model), before the sender's history
+    /// and the user's corrections were taken into account. None for emails indexed
+    /// before it was kept.
+    pub model_score: Option<f64>,
     pub category: Option<String>,
     pub insights: Option<String>,
     pub action_items: Option<String>,
     pub has_deadline: bool,
+    /// Extracted deadline (Unix timestamp), when one could be resolved
+    pub deadline_at: Option<i64>,
     pub has_meeting: bool,
     pub has_financial: bool,
     pub sentiment: Option<String>,
+    pub has_trackers: bool,
+    /// JSON-encoded list of detected trackers
+    pub trackers: Option<String>,
+    pub is_suspicious: bool,
+    pub phishing_score: f64,
+    /// JSON-encoded list of human-readable phishing reasons
+    pub phishing_reasons: Option<String>,
+    /// The sender asks the user something and expects an answer
+    pub needs_reply: bool,
+    /// ISO 639-3 code of the language the email is written in, when it could be detected
+    pub language: Option<String>,
     pub indexed_at: i64,
 }
 
@@ -42,10 +83,489 @@ pub struct EmailWithInsight {
     pub priority_score: f64,
     pub category: Option<String>,
     pub summary: Option<String>,
+    pub sentiment: Option<String>,
+    pub language: Option<String>,
+    /// Pinned to the top of the smart inbox
+    pub pinned: bool,
+}
+
+/// A conversation, as stored in `threads`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThreadSummary {
+    pub thread_id: String,
+    /// Subject of the first message
+    pub subject: String,
+    /// Addresses of everyone who wrote in the thread, lowercase
+    pub participants: Vec<String>,
+    /// Messages in the thread, each counted once however many copies are cached
+    pub message_count: i64,
+    pub first_date: i64,
+    pub last_date: i64,
+}
+
+/// What a database maintenance run did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub started_at: i64,
+    pub duration_ms: i64,
+    /// Size of the database file before and after, in bytes
+    pub size_before: i64,
+    pub size_after: i64,
+    /// Problems found by `PRAGMA integrity_check`; empty when the database is intact
+    pub integrity_problems: Vec<String>,
+}
+
+/// Another cached copy of a message (see `dedupe`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailCopy {
+    pub id: String,
+    pub account_id: String,
+    pub folder: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuspiciousEmail {
+    pub id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub date: i64,
+    pub snippet: String,
+    pub phishing_score: f64,
+    pub reasons: Vec<String>,
+}
+
+/// Per-sender aggregates for the people view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SenderStats {
+    pub from_email: String,
+    /// Display name from the sender's most recent email
+    pub from_name: String,
+    pub email_count: i64,
+    pub unread_count: i64,
+    pub unread_ratio: f64,
+    /// Mean priority score of the sender's indexed emails
+    pub avg_priority_score: Option<f64>,
+    pub last_received_at: i64,
+    /// When the user last wrote to this sender
+    pub last_sent_at: Option<i64>,
+    pub attachment_count: i64,
+    /// Threads where the sender has the last word
+    pub awaiting_reply: i64,
+}
+
+/// Ordering for `get_sender_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SenderSort {
+    #[default]
+    EmailCount,
+    LastReceived,
+    AwaitingReply,
+    Unread,
+    Priority,
+}
+
+impl SenderSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            Self::EmailCount => "COUNT(*) DESC, MAX(r.date) DESC",
+            Self::LastReceived => "MAX(r.date) DESC",
+            Self::AwaitingReply => "COALESCE(w.threads, 0) DESC, MAX(r.date) DESC",
+            Self::Unread => "SUM(r.is_read = 0) DESC, MAX(r.date) DESC",
+            Self::Priority => "AVG(r.priority_score) IS NULL, AVG(r.priority_score) DESC, COUNT(*) DESC",
+        }
+    }
+}
+
+/// A received email that asks for an answer the user hasn't sent yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwaitingReplyEmail {
+    pub id: String,
+    pub thread_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub date: i64,
+    pub snippet: String,
+    pub summary: Option<String>,
+    /// When a follow-up reminder was last shown for it
+    pub reply_reminded_at: Option<i64>,
+}
+
+/// Which emails a triage session goes through
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageFilter {
+    /// All accounts when unset
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// INBOX when unset
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub unread_only: bool,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// An email in a triage queue, with just enough to decide on it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageItem {
+    pub id: String,
+    pub account_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub date: i64,
+    pub snippet: String,
+    pub is_read: bool,
+    pub is_starred: bool,
+    pub has_attachments: bool,
+    pub priority: Option<String>,
+    pub category: Option<String>,
+}
+
+/// What a triage session got through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriageStats {
+    pub session_id: i64,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub queued: i64,
+    pub processed: i64,
+    pub archived: i64,
+    pub trashed: i64,
+    pub snoozed: i64,
+    pub kept: i64,
+    /// Seconds from the start to the end, or to now while the session is open
+    pub duration_secs: i64,
+}
+
+/// Reminders for emails still awaiting a reply after some days
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyReminderSettings {
+    pub enabled: bool,
+    pub after_days: u32,
+}
+
+impl Default for ReplyReminderSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_days: 3,
+        }
+    }
+}
+
+/// Size of an inbox tab: Primary, or one of Gmail's categories
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxTab {
+    /// `primary`, `social`, `promotions`, `updates` or `forums`
+    pub id: String,
+    pub total: i64,
+    pub unread: i64,
+}
+
+/// One page of a list of emails, with how many the whole list holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailPage {
+    pub emails: Vec<EmailWithInsight>,
+    pub total: i64,
+}
+
+/// Total and unread emails in one group of the smart inbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadCount {
+    /// The priority, category or account ID
+    pub key: String,
+    pub total: i64,
+    pub unread: i64,
+}
+
+/// Total and unread emails cached in a folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderCount {
+    pub account_id: String,
+    pub folder: String,
+    pub total: i64,
+    pub unread: i64,
+}
+
+/// Counts behind the smart inbox badges. Priorities, categories and accounts count a
+/// message cached more than once as one when duplicates are collapsed; folders count
+/// every copy they hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InboxCounts {
+    pub priorities: Vec<UnreadCount>,
+    pub categories: Vec<UnreadCount>,
+    pub accounts: Vec<UnreadCount>,
+    pub folders: Vec<FolderCount>,
+}
+
+/// Saved filter behind a custom smart inbox section; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InboxSectionFilter {
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// Any folder when unset
+    #[serde(default)]
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// HIGH, MEDIUM or LOW
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// Part of the sender's address or name
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub unread_only: bool,
+    #[serde(default)]
+    pub starred_only: bool,
+    #[serde(default)]
+    pub pinned_only: bool,
+}
+
+/// A user-defined smart inbox section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxSection {
+    pub id: i64,
+    pub name: String,
+    pub filter: InboxSectionFilter,
+    pub position: i64,
+}
+
+/// Narrowing a saved search can't express with search operators; unset fields match
+/// everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedSearchFilters {
+    #[serde(default)]
+    pub account_id: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// HIGH, MEDIUM or LOW
+    #[serde(default)]
+    pub priority: Option<String>,
+}
+
+/// A search the user saved under a name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: i64,
+    pub name: String,
+    /// Search text, with Gmail-style operators
+    pub query: String,
+    pub filters: SavedSearchFilters,
+    pub created_at: i64,
+    /// Unread emails matching the search, when counts were asked for
+    pub unread_count: Option<i64>,
+}
+
+/// An outgoing email to record with `record_sent_email`
+pub struct NewSentEmail<'a> {
+    pub account_id: &'a str,
+    pub to: &'a [String],
+    pub subject: &'a str,
+    /// Message-ID the email was sent with, without angle brackets
+    pub message_id: &'a str,
+    /// Thread id replies to it will get when synced, unless it answers a cached email and
+    /// joins that one's thread
+    pub thread_id: &'a str,
+    /// Id of the received email this answered
+    pub in_reply_to: Option<&'a str>,
+    pub body_preview: &'a str,
+    /// Whether the email asks the recipients something
+    pub expects_reply: bool,
+}
+
+/// An email the user sent that hasn't been answered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingFollowup {
+    pub id: i64,
+    pub account_id: String,
+    pub to_emails: Vec<String>,
+    pub subject: String,
+    pub message_id: String,
+    pub body_preview: String,
+    pub expects_reply: bool,
+    pub sent_at: i64,
+    /// Recipients a delivery status notification reported as failed
+    #[serde(default)]
+    pub failed_recipients: Vec<String>,
+    /// Reason given for the failed delivery
+    #[serde(default)]
+    pub delivery_error: Option<String>,
+}
+
+/// An address mail bounced from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BouncedAddress {
+    pub address: String,
+    pub reason: Option<String>,
+    pub bounce_count: i64,
+    pub last_bounced_at: i64,
+}
+
+/// Filter for `list_attachments`; fields left unset match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentFilter {
+    pub account_id: Option<String>,
+    pub kind: Option<AttachmentKind>,
+    /// Sender address, or part of it
+    pub from: Option<String>,
+    /// Part of the file name
+    pub filename: Option<String>,
+    /// Received at or after this Unix time
+    pub after: Option<i64>,
+    /// Received before this Unix time
+    pub before: Option<i64>,
+    /// Size bounds in bytes
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// An attachment with the email it came with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentItem {
+    pub email_id: String,
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub date: i64,
+}
+
+/// A link someone sent, with the email it was most recently shared in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedLink {
+    #[serde(flatten)]
+    pub link: EmailLink,
+    pub email_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub date: i64,
+}
+
+/// A package from a shipping notice, with its latest known status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shipment {
+    pub id: i64,
+    pub account_id: String,
+    pub carrier: Carrier,
+    pub carrier_name: String,
+    pub tracking_number: String,
+    pub tracking_url: String,
+    pub status: ShipmentStatus,
+    /// When the status was last seen changing, from an email or the carrier's page
+    pub status_at: i64,
+    /// The most recent email about the package
+    pub email_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub first_seen_at: i64,
+    /// When the carrier's page was last checked
+    pub checked_at: Option<i64>,
+}
+
+/// An email body translated by the LLM
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailTranslation {
+    pub email_id: String,
+    /// ISO 639-3 code of the language it was translated into
+    pub language: String,
+    pub body: String,
+    pub translated_at: i64,
+}
+
+/// Mail volume for one local calendar day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyVolume {
+    /// `YYYY-MM-DD`
+    pub day: String,
+    pub received: i64,
+    pub sent: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryCount {
+    /// Category id, or "uncategorized" for emails that haven't been indexed
+    pub category: String,
+    pub count: i64,
+}
+
+/// Time-bucketed inbox statistics for the analytics dashboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InboxAnalytics {
+    pub from: i64,
+    pub until: i64,
+    /// One entry per day in the range, oldest first
+    pub daily: Vec<DailyVolume>,
+    pub categories: Vec<CategoryCount>,
+    /// Mean time between receiving an email and replying to it from the app
+    pub avg_response_secs: Option<f64>,
+    pub replies_measured: i64,
+    /// Emails received in each local hour of the day, 0 to 23
+    pub received_by_hour: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailCategory {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub position: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: i64,
+    pub session_id: String,
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadlineItem {
+    pub email_id: String,
+    pub subject: String,
+    pub from_name: String,
+    pub from_email: String,
+    pub deadline_at: i64,
+    pub summary: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexingStatus {
+    pub is_indexing: bool,
+    pub is_paused: bool,
+    pub total_emails: i64,
+    pub processed_emails: i64,
+    pub last_indexed_at: Option<i64>,
+    pub error_message: Option<String>,
+    /// Last email whose insights were stored in the current or interrupted run
+    pub last_processed_id: Option<String>,
+}
+
+/// Indexing progress of a single account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountIndexingStatus {
+    pub account_id: String,
     pub is_indexing: bool,
     pub total_emails: i64,
     pub processed_emails: i64,
@@ -53,35 +573,435 @@ pub struct IndexingStatus {
     pub error_message: Option<String>,
 }
 
+/// Progress of a `reindex_cached_emails` run. `cursor_*` point at the last email whose
+/// insights were stored; an interrupted run resumes after it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReindexStatus {
+    pub is_running: bool,
+    pub account_id: Option<String>,
+    pub only_missing: bool,
+    pub total_emails: i64,
+    pub processed_emails: i64,
+    pub cursor_date: Option<i64>,
+    pub cursor_id: Option<String>,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+/// Columns read by `email_from_row`, in order (table aliased as `e`)
+const EMAIL_COLUMNS: &str = "e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+    e.date, e.snippet, e.body_html, e.body_plain, e.is_read, e.is_starred,
+    e.has_attachments, e.labels, e.account_id, e.uid, e.folder, e.message_id,
+    e.reply_to, e.auth_results,
+    CASE WHEN EXISTS (SELECT 1 FROM read_receipts rr WHERE rr.message_id = e.message_id)
+         THEN NULL ELSE e.read_receipt_to END,
+    e.body_clean";
+
+/// Columns read by `followup_from_row`, in order (table aliased as `s`)
+const FOLLOWUP_COLUMNS: &str = "s.id, s.account_id, s.to_emails, s.subject,
+    COALESCE(s.message_id, ''), COALESCE(s.body_preview, ''), s.expects_reply, s.sent_at,
+    s.failed_recipients, s.delivery_error";
+
+/// Build a `PendingFollowup` from a row selected with `FOLLOWUP_COLUMNS`
+fn followup_from_row(row: &rusqlite::Row<'_>) -> Result<PendingFollowup> {
+    let to_emails_json: String = row.get(2)?;
+    Ok(PendingFollowup {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        to_emails: serde_json::from_str(&to_emails_json).unwrap_or_default(),
+        subject: row.get(3)?,
+        message_id: row.get(4)?,
+        body_preview: row.get(5)?,
+        expects_reply: row.get::<_, i32>(6)? != 0,
+        sent_at: row.get(7)?,
+        failed_recipients: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        delivery_error: row.get(9)?,
+    })
+}
+
+/// Columns read by `shipment_from_row`, in order
+const SHIPMENT_COLUMNS: &str = "id, account_id, carrier, tracking_number, status, status_at,
+    email_id, subject, from_name, first_seen_at, checked_at";
+
+/// Build a `Shipment` from a row selected with `SHIPMENT_COLUMNS`, or None for a carrier
+/// or status this version doesn't know
+fn shipment_from_row(row: &rusqlite::Row<'_>) -> Result<Option<Shipment>> {
+    let carrier = Carrier::from_id(&row.get::<_, String>(2)?);
+    let status = ShipmentStatus::from_id(&row.get::<_, String>(4)?);
+    let (Some(carrier), Some(status)) = (carrier, status) else {
+        return Ok(None);
+    };
+    let tracking_number: String = row.get(3)?;
+    Ok(Some(Shipment {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        carrier,
+        carrier_name: carrier.name().to_string(),
+        tracking_url: carrier.tracking_url(&tracking_number),
+        tracking_number,
+        status,
+        status_at: row.get(5)?,
+        email_id: row.get(6)?,
+        subject: row.get(7)?,
+        from_name: row.get(8)?,
+        first_seen_at: row.get(9)?,
+        checked_at: row.get(10)?,
+    }))
+}
+
+/// Error recorded on a job status row found running after its run ended
+pub const INTERRUPTED_MESSAGE: &str = "Interrupted before it finished";
+
+/// Columns of `email_insights` besides `email_id`, for sharing insights between copies
+/// of a message
+const SHARED_INSIGHT_COLUMNS: &str = "summary, priority, priority_score, model_score, category,
+    insights, action_items, has_deadline, has_meeting, has_financial, sentiment, has_trackers,
+    trackers, is_suspicious, phishing_score, phishing_reasons, deadline_at, indexed_at,
+    needs_reply, language, reply_reminded_at";
+
+/// Condition on `emails e` that leaves out copies of a message other than its primary when
+/// the settings collapse them (see `dedupe`)
+fn collapse_condition() -> String {
+    if settings::current().cache.collapse_duplicates {
+        format!("NOT {}", IS_DUPLICATE)
+    } else {
+        "1".to_string()
+    }
+}
+
+/// Thread an email is stored in (see `threading`): the thread of the first of its
+/// Message-IDs that is already linked, with any other thread it links merged into it.
+/// An email without known IDs but with a reply subject joins a recent thread with the
+/// same subject and a participant in common. Gmail's own thread ids are kept as they are.
+fn resolve_thread(conn: &Connection, email: &Email, key: Option<&str>) -> Result<String> {
+    let keys: Vec<String> = email
+        .references
+        .iter()
+        .filter_map(|id| message_key(id))
+        .chain(key.map(str::to_string))
+        .collect();
+
+    let mut linked: Vec<String> = Vec::new();
+    for key in &keys {
+        let thread_id: Option<String> = conn
+            .query_row(
+                "SELECT thread_id FROM thread_links WHERE message_key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(thread_id) = thread_id {
+            if !linked.contains(&thread_id) {
+                linked.push(thread_id);
+            }
+        }
+    }
+
+    let thread_id = if gmail::is_thread_id(&email.thread_id) {
+        email.thread_id.clone()
+    } else if let Some(thread_id) = linked.first() {
+        thread_id.clone()
+    } else {
+        subject_thread(conn, email)?.unwrap_or_else(|| email.thread_id.clone())
+    };
+
+    // Gmail threads stay as Gmail split them
+    for other in &linked {
+        if *other != thread_id && !gmail::is_thread_id(other) {
+            merge_thread(conn, other, &thread_id)?;
+        }
+    }
+    for key in &keys {
+        conn.execute(
+            "INSERT OR IGNORE INTO thread_links (message_key, thread_id) VALUES (?1, ?2)",
+            params![key, thread_id],
+        )?;
+    }
+    Ok(thread_id)
+}
+
+/// A recent thread an orphan reply belongs to, going by its subject and addresses
+fn subject_thread(conn: &Connection, email: &Email) -> Result<Option<String>> {
+    let subject = subject_key(&email.subject);
+    if subject.is_empty() || !is_reply_subject(&email.subject) {
+        return Ok(None);
+    }
+    let addresses: Vec<String> = email
+        .to
+        .iter()
+        .map(|to| address_of(to))
+        .chain(std::iter::once(address_of(&email.from_email)))
+        .collect();
+    let addresses = serde_json::to_string(&addresses).unwrap_or_default();
+
+    conn.query_row(
+        "SELECT thread_id FROM threads
+         WHERE subject_key = ?1
+           AND ?2 BETWEEN first_date - ?3 AND last_date + ?3
+           AND EXISTS (SELECT 1 FROM json_each(participants) p
+                       WHERE p.value IN (SELECT value FROM json_each(?4)))
+         ORDER BY last_date DESC
+         LIMIT 1",
+        params![
+            subject,
+            email.date_timestamp,
+            threading::SUBJECT_MATCH_WINDOW_SECS,
+            addresses
+        ],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Move everything in thread `from` to thread `into`
+fn merge_thread(conn: &Connection, from: &str, into: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE emails SET thread_id = ?2 WHERE thread_id = ?1",
+        params![from, into],
+    )?;
+    conn.execute(
+        "UPDATE thread_links SET thread_id = ?2 WHERE thread_id = ?1",
+        params![from, into],
+    )?;
+    conn.execute(
+        "UPDATE sent_emails SET thread_id = ?2 WHERE thread_id = ?1",
+        params![from, into],
+    )?;
+    conn.execute("DELETE FROM threads WHERE thread_id = ?1", params![from])?;
+    Ok(())
+}
+
+/// Recount a thread from its cached emails, or drop it when none are left
+pub(super) fn refresh_thread(conn: &Connection, thread_id: &str) -> Result<()> {
+    let subject: Option<String> = conn
+        .query_row(
+            "SELECT subject FROM emails WHERE thread_id = ?1 ORDER BY date LIMIT 1",
+            params![thread_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(subject) = subject else {
+        conn.execute(
+            "DELETE FROM threads WHERE thread_id = ?1",
+            params![thread_id],
+        )?;
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT OR REPLACE INTO threads
+         (thread_id, subject, subject_key, participants, message_count, first_date, last_date)
+         SELECT ?1, ?2, ?3,
+                (SELECT json_group_array(address)
+                 FROM (SELECT DISTINCT lower(from_email) AS address FROM emails
+                       WHERE thread_id = ?1 AND from_email != '')),
+                COUNT(DISTINCT COALESCE(message_key, id)), MIN(date), MAX(date)
+         FROM emails WHERE thread_id = ?1",
+        params![thread_id, subject, subject_key(&subject)],
+    )?;
+    Ok(())
+}
+
+/// Columns read by `identity_from_row`, in order
+const IDENTITY_COLUMNS: &str = "id, account_id, address, display_name, signature, created_at";
+
+/// Build an `Identity` from a row selected with `IDENTITY_COLUMNS`
+/// Size of the database in bytes, free pages included
+/// What the user did with `from_email`'s mail in `account_id`, leaving out `except_email_id`.
+/// A message counts as answered when the user wrote later in its thread.
+fn sender_history(
+    conn: &Connection,
+    account_id: &str,
+    from_email: &str,
+    except_email_id: &str,
+) -> Result<SenderHistory> {
+    let from_email = from_email.to_lowercase();
+    let (received, read, replied) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(e.is_read), 0),
+                COALESCE(SUM(
+                    EXISTS (SELECT 1 FROM emails r
+                            WHERE r.thread_id = e.thread_id AND r.date > e.date
+                              AND lower(r.from_email) IN (SELECT lower(email) FROM accounts))
+                    OR EXISTS (SELECT 1 FROM sent_emails s
+                               WHERE s.thread_id = e.thread_id AND s.sent_at >= e.date)
+                ), 0)
+         FROM emails e
+         WHERE e.account_id = ?1 AND lower(e.from_email) = ?2 AND e.id != ?3",
+        params![account_id, from_email, except_email_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT priority FROM priority_overrides
+         WHERE account_id = ?1 AND from_email = ?2 AND email_id != ?3",
+    )?;
+    let overrides = stmt
+        .query_map(params![account_id, from_email, except_email_id], |row| {
+            row.get::<_, String>(0)
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let total: f64 = overrides.iter().map(|p| priority::label_score(p)).sum();
+    let override_score = if overrides.is_empty() {
+        0.0
+    } else {
+        total / overrides.len() as f64
+    };
+
+    Ok(SenderHistory {
+        received,
+        read,
+        replied,
+        overrides: overrides.len() as i64,
+        override_score,
+    })
+}
+
+fn database_size(conn: &Connection) -> Result<i64> {
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok(page_count * page_size)
+}
+
+fn identity_from_row(row: &rusqlite::Row<'_>) -> Result<Identity> {
+    Ok(Identity {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        address: row.get(2)?,
+        display_name: row.get(3)?,
+        signature: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Build a full `Email` from a row selected with `EMAIL_COLUMNS`
+fn email_from_row(row: &rusqlite::Row<'_>) -> Result<Email> {
+    let to_emails_json: String = row.get(5)?;
+    let labels_json: String = row.get(13)?;
+    let date_timestamp: i64 = row.get(6)?;
+    let body_plain: Option<String> = row.get(9)?;
+    // Emails cached before `body_clean` was stored get it worked out here
+    let body_clean = row
+        .get::<_, Option<String>>(21)?
+        .or_else(|| body_plain.as_deref().map(clean_body));
+
+    Ok(Email {
+        id: row.get(0)?,
+        thread_id: row.get(1)?,
+        subject: row.get(2)?,
+        from: row.get(3)?,
+        from_email: row.get(4)?,
+        to: serde_json::from_str(&to_emails_json).unwrap_or_default(),
+        date: chrono::DateTime::from_timestamp(date_timestamp, 0)
+            .map(|dt| dt.format("%a, %d %b %Y %H:%M:%S %z").to_string())
+            .unwrap_or_default(),
+        date_timestamp,
+        snippet: row.get(7)?,
+        body_html: row.get(8)?,
+        body_plain,
+        body_clean,
+        is_read: row.get::<_, i32>(10)? != 0,
+        is_starred: row.get::<_, i32>(11)? != 0,
+        has_attachments: row.get::<_, i32>(12)? != 0,
+        labels: serde_json::from_str(&labels_json).unwrap_or_default(),
+        account_id: row.get::<_, String>(14).unwrap_or_else(|_| "legacy".to_string()),
+        uid: row.get::<_, i64>(15).unwrap_or(0) as u32,
+        folder: row.get::<_, String>(16).unwrap_or_else(|_| "INBOX".to_string()),
+        message_id: row.get::<_, String>(17).unwrap_or_default(),
+        references: Vec::new(),
+        reply_to: row.get(18)?,
+        auth_results: row.get(19)?,
+        inline_parts: Vec::new(),
+        remote_images_blocked: 0,
+        crypto: None,
+        smime: None,
+        read_receipt_to: row.get(20)?,
+        delivery_failure: None,
+        links: Vec::new(),
+        shipments: Vec::new(),
+        attachments: Vec::new(),
+    })
+}
+
+/// Read-only connections kept next to the writer
+const READER_CONNECTIONS: usize = 4;
+
 pub struct EmailDatabase {
+    /// The connection every write goes through
     conn: Arc<Mutex<Connection>>,
+    /// Read-only connections for queries, so the inbox renders while sync or indexing
+    /// writes; with WAL they see the last committed state
+    readers: Vec<Mutex<Connection>>,
+    next_reader: AtomicUsize,
 }
 
 impl EmailDatabase {
     pub fn new(db_path: PathBuf) -> AnyhowResult<Self> {
-        let conn = Connection::open(db_path).context("Failed to open database")?;
+        let conn = Connection::open(&db_path).context("Failed to open database")?;
+        // Lets maintenance return the pages of deleted rows a few at a time; only takes
+        // effect on a new database, older ones are converted by their first maintenance run
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL")?;
+        configure_connection(&conn).context("Failed to configure database")?;
 
         create_tables(&conn).context("Failed to create database tables")?;
 
+        // Opened after the writer has created the tables and switched to WAL
+        let readers = (0..READER_CONNECTIONS)
+            .map(|_| {
+                let reader =
+                    Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                        .context("Failed to open database for reading")?;
+                reader.busy_timeout(BUSY_TIMEOUT)?;
+                Ok(Mutex::new(reader))
+            })
+            .collect::<AnyhowResult<Vec<_>>>()?;
+
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
+            readers,
+            next_reader: AtomicUsize::new(0),
         })
     }
 
+    /// A reader connection, preferring one no other query is using
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        for reader in &self.readers {
+            if let Ok(conn) = reader.try_lock() {
+                return conn;
+            }
+        }
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[next].lock().unwrap()
+    }
+
     // Store or update an email
     pub fn store_email(&self, email: &Email) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
         let now = Utc::now().timestamp();
+        let key = message_key(&email.message_id);
+        let thread_id = resolve_thread(&conn, email, key.as_deref())?;
+        let existed = conn
+            .query_row(
+                "SELECT 1 FROM emails WHERE id = ?1",
+                params![&email.id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
 
         conn.execute(
             "INSERT OR REPLACE INTO emails
             (id, thread_id, subject, from_name, from_email, to_emails, date, snippet,
              body_html, body_plain, is_read, is_starred, has_attachments, labels,
-             created_at, updated_at, account_id, uid, folder, message_id)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+             created_at, updated_at, account_id, uid, folder, message_id, reply_to, auth_results,
+             read_receipt_to, body_clean, message_key)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25)",
             params![
                 &email.id,
-                &email.thread_id,
+                &thread_id,
                 &email.subject,
                 &email.from,
                 &email.from_email,
@@ -100,21 +1020,292 @@ impl EmailDatabase {
                 email.uid as i64,
                 &email.folder,
                 &email.message_id,
+                &email.reply_to,
+                &email.auth_results,
+                &email.read_receipt_to,
+                &email.body_clean,
+                key,
             ],
         )?;
 
+        // A copy of a message that is already analyzed gets its insights
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO email_insights (email_id, {0})
+                 SELECT ?1, {0}
+                 FROM email_insights
+                 WHERE email_id IN (SELECT id FROM emails WHERE message_key = ?2 AND id != ?1)
+                 LIMIT 1",
+                SHARED_INSIGHT_COLUMNS
+            ),
+            params![&email.id, key],
+        )?;
+        let shared_insights = conn.changes() > 0;
+        refresh_thread(&conn, &thread_id)?;
+
+        // A message never changes on the server, so a re-synced email keeps the
+        // attachments already downloaded for it
+        for attachment in &email.attachments {
+            conn.execute(
+                "INSERT INTO attachments (email_id, position, filename, content_type, kind, size)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(email_id, position) DO UPDATE SET
+                    filename = excluded.filename,
+                    content_type = excluded.content_type,
+                    kind = excluded.kind,
+                    size = excluded.size",
+                params![
+                    &email.id,
+                    attachment.position,
+                    attachment.filename,
+                    attachment.content_type,
+                    attachment.kind.id(),
+                    attachment.size as i64
+                ],
+            )?;
+        }
+
+        conn.execute(
+            "DELETE FROM email_links WHERE email_id = ?1",
+            params![&email.id],
+        )?;
+        for (position, link) in email.links.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO email_links (email_id, position, url, text, domain, is_tracker)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    &email.id,
+                    position as i64,
+                    link.url,
+                    link.text,
+                    link.domain,
+                    link.is_tracker as i32
+                ],
+            )?;
+        }
+
+        // A newer notice about a package moves its status on
+        for shipment in &email.shipments {
+            conn.execute(
+                "INSERT INTO shipments (account_id, carrier, tracking_number, email_id, subject,
+                                        from_name, status, status_at, first_seen_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+                 ON CONFLICT(account_id, carrier, tracking_number) DO UPDATE SET
+                    email_id = excluded.email_id,
+                    subject = excluded.subject,
+                    from_name = excluded.from_name,
+                    status = excluded.status,
+                    status_at = excluded.status_at
+                 WHERE excluded.status_at >= shipments.status_at",
+                params![
+                    email.account_id,
+                    shipment.carrier.id(),
+                    shipment.tracking_number,
+                    &email.id,
+                    email.subject,
+                    email.from,
+                    shipment.status.id(),
+                    email.date_timestamp,
+                ],
+            )?;
+        }
+
+        // A bounce flags the sent email it reports on and the addresses that failed
+        if let Some(failure) = &email.delivery_failure {
+            let updated = conn.execute(
+                "UPDATE sent_emails
+                 SET delivery_failed_at = ?2, failed_recipients = ?3, delivery_error = ?4
+                 WHERE message_id = ?1 AND delivery_failed_at IS NULL",
+                params![
+                    failure.message_id,
+                    email.date_timestamp,
+                    serde_json::to_string(&failure.recipients)?,
+                    failure.reason,
+                ],
+            )?;
+            // Only count bounces of mail sent from the app, once each
+            if updated > 0 {
+                for address in &failure.recipients {
+                    conn.execute(
+                        "INSERT INTO bounced_addresses (address, reason, last_bounced_at)
+                         VALUES (?1, ?2, ?3)
+                         ON CONFLICT(address) DO UPDATE SET
+                             reason = excluded.reason,
+                             bounce_count = bounce_count + 1,
+                             last_bounced_at = excluded.last_bounced_at",
+                        params![address, failure.reason, email.date_timestamp],
+                    )?;
+                }
+            }
+        } else if !email.from_email.is_empty() {
+            // Mail from an address shows it works again
+            conn.execute(
+                "DELETE FROM bounced_addresses WHERE address = ?1 AND last_bounced_at < ?2",
+                params![email.from_email, email.date_timestamp],
+            )?;
+        }
+
+        let change = if existed {
+            Change::Updated
+        } else {
+            Change::Added
+        };
+        changes::record(change, &email.id);
+        if shared_insights {
+            changes::record(Change::Insights, &email.id);
+        }
         Ok(())
     }
 
-    // Store AI insights for an email
-    pub fn store_insights(&self, insight: &EmailInsight) -> AnyhowResult<()> {
+    /// Drop a cached email with its insights and embedding, e.g. after it moved folders
+    /// and got a new id
+    pub fn remove_email(&self, email_id: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let thread_id: Option<String> = conn
+            .query_row(
+                "SELECT thread_id FROM emails WHERE id = ?1",
+                params![email_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        conn.execute(
+            "DELETE FROM email_insights WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM email_embeddings WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM email_links WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM email_translations WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM attachments WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM snoozed_emails WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute(
+            "DELETE FROM pinned_emails WHERE email_id = ?1",
+            params![email_id],
+        )?;
+        conn.execute("DELETE FROM emails WHERE id = ?1", params![email_id])?;
+        if let Some(thread_id) = thread_id {
+            refresh_thread(&conn, &thread_id)?;
+        }
+        changes::record(Change::Removed, email_id);
+        Ok(())
+    }
+
+    /// Delete insights whose email is no longer cached. Returns how many were deleted.
+    pub fn remove_orphaned_insights(&self) -> AnyhowResult<usize> {
+        let conn = self.conn.lock().unwrap();
+        let deleted = conn.execute(
+            "DELETE FROM email_insights WHERE email_id NOT IN (SELECT id FROM emails)",
+            [],
+        )?;
+        conn.execute(
+            "DELETE FROM email_embeddings WHERE email_id NOT IN (SELECT id FROM emails)",
+            [],
+        )?;
+        conn.execute(
+            "DELETE FROM email_translations WHERE email_id NOT IN (SELECT id FROM emails)",
+            [],
+        )?;
+        // Folders and accounts are removed wholesale, so recount the threads they were in
+        conn.execute(
+            "DELETE FROM threads WHERE thread_id NOT IN (SELECT thread_id FROM emails)",
+            [],
+        )?;
+        conn.execute(
+            "UPDATE threads SET
+                message_count = (SELECT COUNT(DISTINCT COALESCE(message_key, id)) FROM emails
+                                 WHERE emails.thread_id = threads.thread_id),
+                first_date = (SELECT MIN(date) FROM emails WHERE emails.thread_id = threads.thread_id),
+                last_date = (SELECT MAX(date) FROM emails WHERE emails.thread_id = threads.thread_id)",
+            [],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Drop the cached emails of a folder that was renamed or deleted on the server
+    pub fn remove_folder(&self, account_id: &str, folder: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2")?;
+        let removed = stmt
+            .query_map(params![account_id, folder], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        conn.execute(
+            "DELETE FROM email_insights WHERE email_id IN
+                 (SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2)",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM email_embeddings WHERE email_id IN
+                 (SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2)",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM email_links WHERE email_id IN
+                 (SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2)",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM email_translations WHERE email_id IN
+                 (SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2)",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM attachments WHERE email_id IN
+                 (SELECT id FROM emails WHERE account_id = ?1 AND folder = ?2)",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM emails WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM imap_sync_state WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM folder_status WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM folder_roles WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        conn.execute(
+            "DELETE FROM special_folders WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        for email_id in &removed {
+            changes::record(Change::Removed, email_id);
+        }
+        Ok(())
+    }
+
+    // Store AI insights for an email
+    pub fn store_insights(&self, insight: &EmailInsight) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
 
         conn.execute(
             "INSERT OR REPLACE INTO email_insights
             (email_id, summary, priority, priority_score, category, insights,
-             action_items, has_deadline, has_meeting, has_financial, sentiment, indexed_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+             action_items, has_deadline, has_meeting, has_financial, sentiment,
+             has_trackers, trackers, is_suspicious, phishing_score, phishing_reasons,
+             deadline_at, indexed_at, needs_reply, language, model_score, reply_reminded_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+                    ?21, (SELECT reply_reminded_at FROM email_insights WHERE email_id = ?1))",
             params![
                 &insight.email_id,
                 &insight.summary,
@@ -127,35 +1318,2737 @@ impl EmailDatabase {
                 insight.has_meeting as i32,
                 insight.has_financial as i32,
                 &insight.sentiment,
+                insight.has_trackers as i32,
+                &insight.trackers,
+                insight.is_suspicious as i32,
+                insight.phishing_score,
+                &insight.phishing_reasons,
+                insight.deadline_at,
                 insight.indexed_at,
+                insight.needs_reply as i32,
+                &insight.language,
+                insight.model_score,
             ],
         )?;
 
+        // Other copies of the message share the insights
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO email_insights (email_id, {0})
+                 SELECT copy.id, {0}
+                 FROM email_insights
+                 JOIN emails e ON e.id = email_insights.email_id
+                 JOIN emails copy ON copy.message_key = e.message_key AND copy.id != e.id
+                 WHERE email_insights.email_id = ?1",
+                SHARED_INSIGHT_COLUMNS
+            ),
+            params![&insight.email_id],
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT copy.id FROM emails e
+             JOIN emails copy ON copy.message_key = e.message_key
+             WHERE e.id = ?1",
+        )?;
+        let copies = stmt
+            .query_map(params![&insight.email_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        changes::record(Change::Insights, &insight.email_id);
+        for copy in &copies {
+            changes::record(Change::Insights, copy);
+        }
+        Ok(())
+    }
+
+    // Get emails sorted by priority
+    pub fn get_emails_by_priority(
+        &self,
+        limit: i64,
+        offset: i64,
+    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    COALESCE(i.priority, 'MEDIUM') as priority,
+                    COALESCE(i.priority_score, 0.5) as priority_score,
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE {}
+             ORDER BY pinned DESC, COALESCE(i.priority_score, 0.5) DESC, e.date DESC
+             LIMIT ?1 OFFSET ?2",
+            collapse_condition()
+        ))?;
+
+        let emails = stmt
+            .query_map(params![limit, offset], |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    // Get emails by category, optionally only those with a sentiment or in a language
+    /// A page of the emails in a category, highest priority first, with the size of the
+    /// whole category under the same filters
+    pub fn get_emails_by_category(
+        &self,
+        category: &str,
+        sentiment: Option<&str>,
+        language: Option<&str>,
+        unread_only: bool,
+        limit: i64,
+        offset: i64,
+    ) -> AnyhowResult<EmailPage> {
+        let conn = self.reader();
+        let condition = format!(
+            "i.category = ?1 AND (?2 IS NULL OR i.sentiment = ?2)
+             AND (?3 IS NULL OR i.language = ?3)
+             AND (?4 = 0 OR e.is_read = 0)
+             AND {}",
+            collapse_condition()
+        );
+
+        let total: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM emails e
+                 INNER JOIN email_insights i ON e.id = i.email_id
+                 WHERE {}",
+                condition
+            ),
+            params![category, sentiment, language, unread_only],
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    i.priority, i.priority_score, i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE {}
+             ORDER BY i.priority_score DESC, e.date DESC, e.id
+             LIMIT ?5 OFFSET ?6",
+            condition
+        ))?;
+
+        let emails = stmt
+            .query_map(
+                params![category, sentiment, language, unread_only, limit, offset],
+                |row| {
+                    Ok(EmailWithInsight {
+                        id: row.get(0)?,
+                        thread_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from_name: row.get(3)?,
+                        from_email: row.get(4)?,
+                        to_emails: serde_json::from_str(&row.get::<_, String>(5)?)
+                            .unwrap_or_default(),
+                        date: row.get(6)?,
+                        snippet: row.get(7)?,
+                        is_read: row.get::<_, i32>(8)? != 0,
+                        is_starred: row.get::<_, i32>(9)? != 0,
+                        has_attachments: row.get::<_, i32>(10)? != 0,
+                        priority: row.get(11)?,
+                        priority_score: row.get(12)?,
+                        category: row.get(13)?,
+                        summary: row.get(14)?,
+                        sentiment: row.get(15)?,
+                        language: row.get(16)?,
+                        pinned: row.get::<_, i32>(17)? != 0,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EmailPage { emails, total })
+    }
+
+    /// Get emails with one of the given sentiments, most negative first
+    pub fn get_emails_by_sentiment(
+        &self,
+        account_id: Option<&str>,
+        sentiments: &[String],
+        limit: i64,
+    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let conn = self.reader();
+        let sentiments_json = serde_json::to_string(sentiments)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    i.priority, i.priority_score, i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE i.sentiment IN (SELECT value FROM json_each(?2))
+                   AND (?1 IS NULL OR e.account_id = ?1)
+                   AND {}
+             ORDER BY CASE i.sentiment
+                          WHEN 'frustrated' THEN 0
+                          WHEN 'negative' THEN 1
+                          WHEN 'neutral' THEN 2
+                          ELSE 3
+                      END,
+                      i.priority_score DESC, e.date DESC
+             LIMIT ?3",
+            collapse_condition()
+        ))?;
+
+        let emails = stmt
+            .query_map(params![account_id, sentiments_json, limit], |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    // Get emails from today
+    pub fn get_emails_from_today(&self) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let conn = self.reader();
+        let today_start = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    COALESCE(i.priority, 'MEDIUM') as priority,
+                    COALESCE(i.priority_score, 0.5) as priority_score,
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE e.date >= ?1 AND {}
+             ORDER BY e.date DESC",
+            collapse_condition()
+        ))?;
+
+        let emails = stmt
+            .query_map(params![today_start], |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    // Search emails by text. Gmail-style operators (`from:`, `before:`, `has:attachment`, ...)
+    // in the query narrow the results; see `SearchQuery`.
+    pub fn search_emails(&self, query: &str, limit: i64) -> AnyhowResult<Vec<EmailWithInsight>> {
+        self.search_emails_filtered(query, &SavedSearchFilters::default(), limit)
+    }
+
+    /// `search_emails` narrowed further by `filters`
+    pub fn search_emails_filtered(
+        &self,
+        query: &str,
+        filters: &SavedSearchFilters,
+        limit: i64,
+    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let query = SearchQuery::parse(query);
+        let conn = self.reader();
+
+        let (where_clause, mut values) = search_where_clause(&query, filters, 2);
+        values.insert(0, Value::Integer(limit));
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    COALESCE(i.priority, 'MEDIUM') as priority,
+                    COALESCE(i.priority_score, 0.5) as priority_score,
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             {}
+             ORDER BY e.date DESC
+             LIMIT ?1",
+            where_clause
+        ))?;
+
+        let emails = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// How many cached emails a search matches, and how many of them are unread
+    pub fn count_search_matches(
+        &self,
+        query: &str,
+        filters: &SavedSearchFilters,
+    ) -> AnyhowResult<(i64, i64)> {
+        let query = SearchQuery::parse(query);
+        let conn = self.reader();
+
+        let (where_clause, values) = search_where_clause(&query, filters, 1);
+        let counts = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(e.is_read = 0), 0)
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 {}",
+                where_clause
+            ),
+            params_from_iter(values),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(counts)
+    }
+
+    // Ids of the newest emails (at most `limit`) that pass the operator filters in `query`,
+    // as candidates for semantic retrieval. The free text is left to the ranking.
+    pub fn get_filtered_email_ids(
+        &self,
+        query: &SearchQuery,
+        limit: i64,
+    ) -> AnyhowResult<HashSet<String>> {
+        let conn = self.reader();
+
+        let (mut conditions, values) = query.sql_conditions(2);
+        conditions.push(collapse_condition());
+        let values =
+            std::iter::once(Value::Integer(limit)).chain(values.into_iter().map(sql_value));
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id FROM emails e WHERE {} ORDER BY e.date DESC LIMIT ?1",
+            conditions.join(" AND ")
+        ))?;
+        let ids = stmt
+            .query_map(params_from_iter(values), |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?;
+
+        Ok(ids)
+    }
+
+    // Update indexing status
+    pub fn update_indexing_status(
+        &self,
+        is_indexing: bool,
+        total: Option<i64>,
+        processed: Option<i64>,
+        error: Option<String>,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        if let Some(total) = total {
+            conn.execute(
+                "UPDATE indexing_status SET total_emails = ?1 WHERE id = 1",
+                params![total],
+            )?;
+        }
+
+        if let Some(processed) = processed {
+            conn.execute(
+                "UPDATE indexing_status SET processed_emails = ?1 WHERE id = 1",
+                params![processed],
+            )?;
+        }
+
+        conn.execute(
+            "UPDATE indexing_status SET is_indexing = ?1, heartbeat_at = ?2 WHERE id = 1",
+            params![is_indexing as i32, Utc::now().timestamp()],
+        )?;
+
+        if !is_indexing {
+            let now = Utc::now().timestamp();
+            conn.execute(
+                "UPDATE indexing_status SET last_indexed_at = ?1 WHERE id = 1",
+                params![now],
+            )?;
+        }
+
+        if let Some(error_msg) = error {
+            conn.execute(
+                "UPDATE indexing_status SET error_message = ?1 WHERE id = 1",
+                params![error_msg],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Get indexing status
+    pub fn get_indexing_status(&self) -> AnyhowResult<IndexingStatus> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT is_indexing, total_emails, processed_emails, last_indexed_at, error_message,
+                    is_paused, last_processed_id
+             FROM indexing_status WHERE id = 1",
+        )?;
+
+        let status = stmt.query_row([], |row| {
+            Ok(IndexingStatus {
+                is_indexing: row.get::<_, i32>(0)? != 0,
+                total_emails: row.get(1)?,
+                processed_emails: row.get(2)?,
+                last_indexed_at: row.get(3)?,
+                error_message: row.get(4)?,
+                is_paused: row.get::<_, i32>(5)? != 0,
+                last_processed_id: row.get(6)?,
+            })
+        })?;
+
+        Ok(status)
+    }
+
+    /// Record the last email processed by the indexing job (`None` clears the checkpoint)
+    pub fn set_indexing_checkpoint(&self, email_id: Option<&str>) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE indexing_status SET last_processed_id = ?1 WHERE id = 1",
+            params![email_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_indexing_paused(&self, paused: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE indexing_status SET is_paused = ?1 WHERE id = 1",
+            params![paused as i32],
+        )?;
+        Ok(())
+    }
+
+    /// Update one account's indexing progress. Starting a run (`is_indexing` with a `total`)
+    /// clears the previous error; finishing stamps `last_indexed_at`.
+    pub fn update_account_indexing_status(
+        &self,
+        account_id: &str,
+        is_indexing: bool,
+        total: Option<i64>,
+        processed: Option<i64>,
+        error: Option<&str>,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT OR IGNORE INTO account_indexing_status (account_id) VALUES (?1)",
+            params![account_id],
+        )?;
+
+        if let Some(total) = total {
+            conn.execute(
+                "UPDATE account_indexing_status SET total_emails = ?2, processed_emails = 0, error_message = NULL
+                 WHERE account_id = ?1",
+                params![account_id, total],
+            )?;
+        }
+
+        if let Some(processed) = processed {
+            conn.execute(
+                "UPDATE account_indexing_status SET processed_emails = ?2 WHERE account_id = ?1",
+                params![account_id, processed],
+            )?;
+        }
+
+        conn.execute(
+            "UPDATE account_indexing_status SET is_indexing = ?2, heartbeat_at = ?3
+             WHERE account_id = ?1",
+            params![account_id, is_indexing as i32, Utc::now().timestamp()],
+        )?;
+
+        if !is_indexing {
+            conn.execute(
+                "UPDATE account_indexing_status SET last_indexed_at = ?2 WHERE account_id = ?1",
+                params![account_id, Utc::now().timestamp()],
+            )?;
+        }
+
+        if let Some(error_msg) = error {
+            conn.execute(
+                "UPDATE account_indexing_status SET error_message = ?2 WHERE account_id = ?1",
+                params![account_id, error_msg],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Get indexing progress for every account that has been indexed
+    pub fn get_account_indexing_statuses(&self) -> AnyhowResult<Vec<AccountIndexingStatus>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT s.account_id, s.is_indexing, s.total_emails, s.processed_emails,
+                    s.last_indexed_at, s.error_message
+             FROM account_indexing_status s
+             INNER JOIN accounts a ON a.id = s.account_id
+             ORDER BY a.created_at",
+        )?;
+
+        let statuses = stmt
+            .query_map([], |row| {
+                Ok(AccountIndexingStatus {
+                    account_id: row.get(0)?,
+                    is_indexing: row.get::<_, i32>(1)? != 0,
+                    total_emails: row.get(2)?,
+                    processed_emails: row.get(3)?,
+                    last_indexed_at: row.get(4)?,
+                    error_message: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(statuses)
+    }
+
+    /// Mark an indexing run whose last heartbeat is before `stale_before` (any run with
+    /// `None`) as interrupted: it shows as paused, so it can be resumed, instead of as
+    /// running forever. Returns whether there was one.
+    pub fn recover_interrupted_indexing(&self, stale_before: Option<i64>) -> AnyhowResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let recovered = conn.execute(
+            "UPDATE indexing_status
+             SET is_indexing = 0, is_paused = 1, error_message = ?2
+             WHERE id = 1 AND is_indexing = 1 AND is_paused = 0
+               AND (?1 IS NULL OR COALESCE(heartbeat_at, 0) < ?1)",
+            params![stale_before, INTERRUPTED_MESSAGE],
+        )?;
+        if recovered > 0 {
+            conn.execute("UPDATE account_indexing_status SET is_indexing = 0", [])?;
+        }
+        Ok(recovered > 0)
+    }
+
+    /// Like `recover_interrupted_indexing`, for a re-index run. Its cursor is kept, so
+    /// the next run with the same scope resumes it.
+    pub fn recover_interrupted_reindex(&self, stale_before: Option<i64>) -> AnyhowResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let recovered = conn.execute(
+            "UPDATE reindex_status
+             SET is_running = 0, error_message = ?2
+             WHERE id = 1 AND is_running = 1
+               AND (?1 IS NULL OR COALESCE(heartbeat_at, 0) < ?1)",
+            params![stale_before, INTERRUPTED_MESSAGE],
+        )?;
+        Ok(recovered > 0)
+    }
+
+    /// Mark every account as not indexing (after an interrupted run)
+    pub fn reset_account_indexing_statuses(&self) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE account_indexing_status SET is_indexing = 0", [])?;
+        Ok(())
+    }
+
+    /// Get all email IDs (for use by embedding pipeline). Copies of a message are left
+    /// out; only its primary is embedded. So are emails whose body hasn't been fetched,
+    /// which would be embedded from their subject alone and never again.
+    pub fn get_all_email_ids(&self, limit: i64) -> AnyhowResult<Vec<String>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM emails e WHERE e.has_body = 1 AND NOT {} ORDER BY date DESC LIMIT ?1",
+            IS_DUPLICATE
+        ))?;
+        let ids = stmt
+            .query_map(params![limit], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        Ok(ids)
+    }
+
+    /// The conversation an email is in
+    pub fn get_thread_summary(&self, thread_id: &str) -> AnyhowResult<Option<ThreadSummary>> {
+        let conn = self.reader();
+        let summary = conn
+            .query_row(
+                "SELECT thread_id, subject, participants, message_count, first_date, last_date
+                 FROM threads WHERE thread_id = ?1",
+                params![thread_id],
+                |row| {
+                    Ok(ThreadSummary {
+                        thread_id: row.get(0)?,
+                        subject: row.get(1)?,
+                        participants: serde_json::from_str(&row.get::<_, String>(2)?)
+                            .unwrap_or_default(),
+                        message_count: row.get(3)?,
+                        first_date: row.get(4)?,
+                        last_date: row.get(5)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(summary)
+    }
+
+    /// The other cached copies of an email, in other folders or accounts
+    pub fn get_email_copies(&self, email_id: &str) -> AnyhowResult<Vec<EmailCopy>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT copy.id, copy.account_id, copy.folder
+             FROM emails e
+             JOIN emails copy ON copy.message_key = e.message_key AND copy.id != e.id
+             WHERE e.id = ?1
+             ORDER BY copy.account_id, copy.folder",
+        )?;
+        let copies = stmt
+            .query_map(params![email_id], |row| {
+                Ok(EmailCopy {
+                    id: row.get(0)?,
+                    account_id: row.get(1)?,
+                    folder: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(copies)
+    }
+
+    /// Ids of all cached emails
+    pub fn get_email_id_set(&self) -> AnyhowResult<HashSet<String>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare("SELECT id FROM emails")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?;
+
+        Ok(ids)
+    }
+
+    // Get total count of emails
+    pub fn get_email_count(&self) -> AnyhowResult<i64> {
+        let conn = self.reader();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM emails", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    // Get count of indexed emails
+    pub fn get_indexed_count(&self) -> AnyhowResult<i64> {
+        let conn = self.reader();
+        let count: i64 =
+            conn.query_row("SELECT COUNT(*) FROM email_insights", [], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Problems found by SQLite's `PRAGMA quick_check`; empty when the database is intact
+    pub fn quick_check(&self) -> AnyhowResult<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare("PRAGMA quick_check")?;
+        let problems = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(problems.into_iter().filter(|p| p != "ok").collect())
+    }
+
+    /// Bytes held by pages of deleted rows that maintenance can return to the disk
+    pub fn free_space_bytes(&self) -> AnyhowResult<i64> {
+        let conn = self.reader();
+        let free_pages: i64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+        let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(free_pages * page_size)
+    }
+
+    /// Return free pages to the disk, refresh the query planner's statistics, merge
+    /// full-text index segments and check the database for corruption. A database
+    /// created before incremental vacuuming is converted with one full VACUUM.
+    pub fn run_maintenance(&self) -> AnyhowResult<MaintenanceReport> {
+        let conn = self.conn.lock().unwrap();
+        let started = Utc::now();
+        let size_before = database_size(&conn)?;
+
+        let auto_vacuum: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+        if auto_vacuum == 2 {
+            conn.execute_batch("PRAGMA incremental_vacuum")?;
+        } else {
+            conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM")?;
+        }
+        conn.execute_batch("ANALYZE")?;
+
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%USING fts%'",
+        )?;
+        let fts_tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for table in fts_tables {
+            conn.execute(
+                &format!("INSERT INTO \"{0}\"(\"{0}\") VALUES('optimize')", table),
+                [],
+            )?;
+        }
+
+        let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+        let integrity_problems = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter(|result| !matches!(result, Ok(problem) if problem == "ok"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let report = MaintenanceReport {
+            started_at: started.timestamp(),
+            duration_ms: (Utc::now() - started).num_milliseconds(),
+            size_before,
+            size_after: database_size(&conn)?,
+            integrity_problems,
+        };
+        conn.execute(
+            "INSERT INTO maintenance_runs
+             (started_at, duration_ms, size_before, size_after, integrity_problems)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                report.started_at,
+                report.duration_ms,
+                report.size_before,
+                report.size_after,
+                serde_json::to_string(&report.integrity_problems)?,
+            ],
+        )?;
+        Ok(report)
+    }
+
+    /// The most recent maintenance run
+    pub fn get_last_maintenance(&self) -> AnyhowResult<Option<MaintenanceReport>> {
+        let conn = self.reader();
+        let report = conn
+            .query_row(
+                "SELECT started_at, duration_ms, size_before, size_after, integrity_problems
+                 FROM maintenance_runs ORDER BY started_at DESC, id DESC LIMIT 1",
+                [],
+                |row| {
+                    let problems: String = row.get(4)?;
+                    Ok(MaintenanceReport {
+                        started_at: row.get(0)?,
+                        duration_ms: row.get(1)?,
+                        size_before: row.get(2)?,
+                        size_after: row.get(3)?,
+                        integrity_problems: serde_json::from_str(&problems).unwrap_or_default(),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(report)
+    }
+
+    // Clear all emails and insights from the database
+    pub fn clear_all_emails(&self) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+
+        // Delete all email insights first (due to foreign key)
+        conn.execute("DELETE FROM email_insights", [])?;
+
+        // Delete all emails
+        conn.execute("DELETE FROM email_links", [])?;
+        conn.execute("DELETE FROM email_translations", [])?;
+        conn.execute("DELETE FROM attachments", [])?;
+        conn.execute("DELETE FROM emails", [])?;
+        conn.execute("DELETE FROM threads", [])?;
+        conn.execute("DELETE FROM thread_links", [])?;
+
+        // Reset indexing status
+        conn.execute(
+            "UPDATE indexing_status SET is_indexing = 0, is_paused = 0, total_emails = 0, processed_emails = 0, last_indexed_at = NULL, error_message = NULL, last_processed_id = NULL WHERE id = 1",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    // Get email by ID from cache
+    pub fn get_email_by_id(
+        &self,
+        email_id: &str,
+    ) -> AnyhowResult<Option<crate::email::types::Email>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM emails e WHERE e.id = ?1", EMAIL_COLUMNS),
+        )?;
+
+        let email = stmt
+            .query_row([email_id], email_from_row)
+            .optional()?;
+
+        Ok(email)
+    }
+
+    // ========== Account Management ==========
+
+    /// Store a new account
+    pub fn store_account(&self, account: &Account) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO accounts
+            (id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
+             auth_type, is_active, created_at, last_synced_at, cert_fingerprint, color,
+             sort_order, label)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                &account.id,
+                &account.email,
+                &account.display_name,
+                &account.provider,
+                &account.imap_host,
+                account.imap_port as i32,
+                &account.smtp_host,
+                account.smtp_port as i32,
+                &account.auth_type,
+                account.is_active as i32,
+                account.created_at,
+                account.last_synced_at,
+                &account.cert_fingerprint,
+                &account.color,
+                account.sort_order,
+                &account.label,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Remove an account and all its data
+    pub fn remove_account(&self, account_id: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        // Delete insights for this account's emails
+        conn.execute(
+            "DELETE FROM email_insights WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            params![account_id],
+        )?;
+        // Delete embeddings for this account's emails
+        conn.execute(
+            "DELETE FROM email_embeddings WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM email_links WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM email_translations WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM attachments WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
+            params![account_id],
+        )?;
+        // Delete emails
+        conn.execute(
+            "DELETE FROM emails WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM account_indexing_status WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM pop3_uidls WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM imap_sync_state WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM identities WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM folder_status WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM notification_settings WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM account_sync_settings WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM folder_roles WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM special_folders WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM snoozed_emails WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM pinned_emails WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM shipments WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM priority_overrides WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM priority_weights WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        conn.execute(
+            "DELETE FROM inbox_sections WHERE json_extract(filter, '$.account_id') = ?1",
+            params![account_id],
+        )?;
+        // Delete account
+        conn.execute("DELETE FROM accounts WHERE id = ?1", params![account_id])?;
+        Ok(())
+    }
+
+    /// List all accounts
+    pub fn list_accounts(&self) -> AnyhowResult<Vec<Account>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
+                    auth_type, is_active, created_at, last_synced_at, cert_fingerprint,
+                    color, sort_order, label
+             FROM accounts ORDER BY sort_order ASC, created_at ASC",
+        )?;
+
+        let accounts = stmt
+            .query_map([], |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    email: row.get(1)?,
+                    display_name: row.get(2)?,
+                    provider: row.get(3)?,
+                    imap_host: row.get(4)?,
+                    imap_port: row.get::<_, i32>(5)? as u16,
+                    smtp_host: row.get(6)?,
+                    smtp_port: row.get::<_, i32>(7)? as u16,
+                    auth_type: row.get(8)?,
+                    is_active: row.get::<_, i32>(9)? != 0,
+                    created_at: row.get(10)?,
+                    last_synced_at: row.get(11)?,
+                    cert_fingerprint: row.get(12)?,
+                    color: row.get(13)?,
+                    sort_order: row.get(14)?,
+                    label: row.get(15)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(accounts)
+    }
+
+    /// Get a single account by ID
+    pub fn get_account(&self, account_id: &str) -> AnyhowResult<Option<Account>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
+                    auth_type, is_active, created_at, last_synced_at, cert_fingerprint,
+                    color, sort_order, label
+             FROM accounts WHERE id = ?1",
+        )?;
+
+        let account = stmt
+            .query_row([account_id], |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    email: row.get(1)?,
+                    display_name: row.get(2)?,
+                    provider: row.get(3)?,
+                    imap_host: row.get(4)?,
+                    imap_port: row.get::<_, i32>(5)? as u16,
+                    smtp_host: row.get(6)?,
+                    smtp_port: row.get::<_, i32>(7)? as u16,
+                    auth_type: row.get(8)?,
+                    is_active: row.get::<_, i32>(9)? != 0,
+                    created_at: row.get(10)?,
+                    last_synced_at: row.get(11)?,
+                    cert_fingerprint: row.get(12)?,
+                    color: row.get(13)?,
+                    sort_order: row.get(14)?,
+                    label: row.get(15)?,
+                })
+            })
+            .optional()?;
+
+        Ok(account)
+    }
+
+    /// Set active account (deactivate all others, activate specified)
+    pub fn set_active_account(&self, account_id: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE accounts SET is_active = 0", [])?;
+        conn.execute(
+            "UPDATE accounts SET is_active = 1 WHERE id = ?1",
+            params![account_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear an account's pinned certificate fingerprint
+    pub fn set_account_cert_fingerprint(
+        &self,
+        account_id: &str,
+        cert_fingerprint: Option<&str>,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE accounts SET cert_fingerprint = ?2 WHERE id = ?1",
+            params![account_id, cert_fingerprint],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Account '{}' not found", account_id);
+        }
+        Ok(())
+    }
+
+    /// Save an account's display name, color, position and label
+    pub fn update_account_metadata(&self, account: &Account) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE accounts SET display_name = ?2, color = ?3, sort_order = ?4, label = ?5
+             WHERE id = ?1",
+            params![
+                &account.id,
+                &account.display_name,
+                &account.color,
+                account.sort_order,
+                &account.label,
+            ],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Account '{}' not found", account.id);
+        }
+        Ok(())
+    }
+
+    /// Position for a newly added account, after all existing ones
+    pub fn next_account_sort_order(&self) -> AnyhowResult<i64> {
+        let conn = self.reader();
+        let next = conn.query_row(
+            "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM accounts",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(next)
+    }
+
+    /// Get the active account
+    pub fn get_active_account(&self) -> AnyhowResult<Option<Account>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
+                    auth_type, is_active, created_at, last_synced_at, cert_fingerprint,
+                    color, sort_order, label
+             FROM accounts WHERE is_active = 1 LIMIT 1",
+        )?;
+
+        let account = stmt
+            .query_row([], |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    email: row.get(1)?,
+                    display_name: row.get(2)?,
+                    provider: row.get(3)?,
+                    imap_host: row.get(4)?,
+                    imap_port: row.get::<_, i32>(5)? as u16,
+                    smtp_host: row.get(6)?,
+                    smtp_port: row.get::<_, i32>(7)? as u16,
+                    auth_type: row.get(8)?,
+                    is_active: row.get::<_, i32>(9)? != 0,
+                    created_at: row.get(10)?,
+                    last_synced_at: row.get(11)?,
+                    cert_fingerprint: row.get(12)?,
+                    color: row.get(13)?,
+                    sort_order: row.get(14)?,
+                    label: row.get(15)?,
+                })
+            })
+            .optional()?;
+
+        Ok(account)
+    }
+
+    // Get important emails by account (HIGH priority or starred)
+    pub fn get_important_emails_by_account(
+        &self,
+        account_id: &str,
+        language: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    COALESCE(i.priority, 'MEDIUM') as priority,
+                    COALESCE(i.priority_score, 0.5) as priority_score,
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE e.account_id = ?1 AND (i.priority = 'HIGH' OR e.is_starred = 1)
+                   AND (?3 IS NULL OR i.language = ?3)
+                   AND {}
+             ORDER BY COALESCE(i.priority_score, 0.5) DESC, e.date DESC
+             LIMIT ?2",
+            collapse_condition()
+        ))?;
+
+        let emails = stmt
+            .query_map(params![account_id, limit, language], |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    // Get emails by account and category, excluding important emails
+    pub fn get_emails_by_account_and_category(
+        &self,
+        account_id: &str,
+        category: &str,
+        language: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
+                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
+                    i.priority, i.priority_score, i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE e.account_id = ?1 AND i.category = ?2
+                   AND i.priority != 'HIGH' AND e.is_starred = 0
+                   AND (?4 IS NULL OR i.language = ?4)
+                   AND {}
+             ORDER BY i.priority_score DESC, e.date DESC
+             LIMIT ?3",
+            collapse_condition()
+        ))?;
+
+        let emails = stmt
+            .query_map(params![account_id, category, limit, language], |row| {
+                Ok(EmailWithInsight {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from_name: row.get(3)?,
+                    from_email: row.get(4)?,
+                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    date: row.get(6)?,
+                    snippet: row.get(7)?,
+                    is_read: row.get::<_, i32>(8)? != 0,
+                    is_starred: row.get::<_, i32>(9)? != 0,
+                    has_attachments: row.get::<_, i32>(10)? != 0,
+                    priority: row.get(11)?,
+                    priority_score: row.get(12)?,
+                    category: row.get(13)?,
+                    summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Get emails that already have insights, newest first (used for re-classification)
+    pub fn get_indexed_emails(
+        &self,
+        account_id: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<crate::email::types::Email>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {}
+                 FROM emails e
+                 INNER JOIN email_insights i ON e.id = i.email_id
+                 WHERE (?1 IS NULL OR e.account_id = ?1)
+                 ORDER BY e.date DESC
+                 LIMIT ?2",
+                EMAIL_COLUMNS
+            ),
+        )?;
+
+        let emails = stmt
+            .query_map(params![account_id, limit], email_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Cache list entries of messages whose bodies haven't been fetched yet, for the list
+    /// view. Messages already cached are left as they are; `store_email` fills in the rest
+    /// later. Returns how many were new.
+    pub fn store_email_headers(
+        &self,
+        account_id: &str,
+        folder: &str,
+        items: &[EmailListItem],
+    ) -> AnyhowResult<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        let tx = conn.transaction()?;
+        let mut stored = Vec::new();
+        for item in items {
+            let uid = item.id.rsplit(':').next().map(str::parse::<u32>);
+            let Some(Ok(uid)) = uid else {
+                continue;
+            };
+            let date = chrono::DateTime::parse_from_rfc2822(&item.date)
+                .or_else(|_| chrono::DateTime::parse_from_rfc3339(&item.date))
+                .map(|date| date.timestamp())
+                .unwrap_or(now);
+            // Until the body is fetched each message is its own thread
+            let thread_id = if item.thread_id.is_empty() {
+                &item.id
+            } else {
+                &item.thread_id
+            };
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO emails
+                 (id, thread_id, subject, from_name, from_email, to_emails, date, snippet,
+                  is_read, is_starred, has_attachments, created_at, updated_at, account_id, uid,
+                  folder, has_body)
+                 VALUES (?1, ?2, ?3, ?4, ?5, '[]', ?6, ?7, ?8, ?9, ?10, ?11, ?11, ?12, ?13, ?14, 0)",
+                params![
+                    item.id,
+                    thread_id,
+                    item.subject,
+                    item.from,
+                    item.from_email,
+                    date,
+                    item.snippet,
+                    item.is_read as i32,
+                    item.is_starred as i32,
+                    item.has_attachments as i32,
+                    now,
+                    account_id,
+                    uid as i64,
+                    folder,
+                ],
+            )?;
+            if inserted > 0 {
+                stored.push(item.id.as_str());
+            }
+        }
+        tx.commit()?;
+        for email_id in &stored {
+            changes::record(Change::Added, email_id);
+        }
+        Ok(stored.len())
+    }
+
+    /// Whether a cached email has its body or only its list entry; `None` if it isn't
+    /// cached at all
+    pub fn has_body(&self, email_id: &str) -> AnyhowResult<Option<bool>> {
+        let conn = self.reader();
+        let has_body = conn
+            .query_row(
+                "SELECT has_body FROM emails WHERE id = ?1",
+                params![email_id],
+                |row| row.get::<_, i32>(0),
+            )
+            .optional()?;
+        Ok(has_body.map(|has_body| has_body != 0))
+    }
+
+    /// Get emails that haven't been indexed yet (no entry in email_insights),
+    /// optionally only those of one account
+    pub fn get_unindexed_emails(
+        &self,
+        account_id: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<crate::email::types::Email>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {}
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 WHERE i.email_id IS NULL
+                   AND e.has_body = 1
+                   AND NOT {}
+                   AND (?1 IS NULL OR e.account_id = ?1)
+                 ORDER BY e.date DESC
+                 LIMIT ?2",
+                EMAIL_COLUMNS, IS_DUPLICATE
+            ),
+        )?;
+
+        let emails = stmt
+            .query_map(params![account_id, limit], email_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Count cached emails a re-index run would cover
+    pub fn count_cached_emails(&self, account_id: Option<&str>, only_missing: bool) -> AnyhowResult<i64> {
+        let conn = self.reader();
+        let count: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*)
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 WHERE e.has_body = 1
+                   AND NOT {}
+                   AND (?1 IS NULL OR e.account_id = ?1)
+                   AND (?2 = 0 OR i.email_id IS NULL)",
+                IS_DUPLICATE
+            ),
+            params![account_id, only_missing as i32],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    /// Next page of cached emails, newest first, strictly after `cursor` (date, id)
+    pub fn get_cached_emails_after(
+        &self,
+        account_id: Option<&str>,
+        only_missing: bool,
+        cursor: Option<(i64, &str)>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<crate::email::types::Email>> {
+        let conn = self.reader();
+        let (cursor_date, cursor_id) = cursor.unzip();
+
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {}
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 WHERE e.has_body = 1
+                   AND NOT {}
+                   AND (?1 IS NULL OR e.account_id = ?1)
+                   AND (?2 = 0 OR i.email_id IS NULL)
+                   AND (?3 IS NULL OR e.date < ?3 OR (e.date = ?3 AND e.id < ?4))
+                 ORDER BY e.date DESC, e.id DESC
+                 LIMIT ?5",
+                EMAIL_COLUMNS, IS_DUPLICATE
+            ),
+        )?;
+
+        let emails = stmt
+            .query_map(
+                params![account_id, only_missing as i32, cursor_date, cursor_id, limit],
+                email_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Get the state of the last (or current) re-index run
+    pub fn get_reindex_status(&self) -> AnyhowResult<ReindexStatus> {
+        let conn = self.reader();
+
+        let status = conn.query_row(
+            "SELECT is_running, account_id, only_missing, total_emails, processed_emails,
+                    cursor_date, cursor_id, started_at, finished_at, error_message
+             FROM reindex_status WHERE id = 1",
+            [],
+            |row| {
+                Ok(ReindexStatus {
+                    is_running: row.get::<_, i32>(0)? != 0,
+                    account_id: row.get(1)?,
+                    only_missing: row.get::<_, i32>(2)? != 0,
+                    total_emails: row.get(3)?,
+                    processed_emails: row.get(4)?,
+                    cursor_date: row.get(5)?,
+                    cursor_id: row.get(6)?,
+                    started_at: row.get(7)?,
+                    finished_at: row.get(8)?,
+                    error_message: row.get(9)?,
+                })
+            },
+        )?;
+
+        Ok(status)
+    }
+
+    /// Begin a fresh re-index run, discarding any previous cursor
+    pub fn start_reindex(&self, account_id: Option<&str>, only_missing: bool, total: i64) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reindex_status
+             SET is_running = 1, account_id = ?1, only_missing = ?2, total_emails = ?3,
+                 processed_emails = 0, cursor_date = NULL, cursor_id = NULL,
+                 started_at = ?4, finished_at = NULL, error_message = NULL, heartbeat_at = ?4
+             WHERE id = 1",
+            params![account_id, only_missing as i32, total, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Mark an interrupted re-index run as running again, keeping its cursor
+    pub fn resume_reindex(&self) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reindex_status SET is_running = 1, finished_at = NULL, error_message = NULL, heartbeat_at = ?1
+             WHERE id = 1",
+            params![Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Record a finished batch: the new processed count and the last email it covered
+    pub fn update_reindex_progress(&self, processed: i64, cursor_date: i64, cursor_id: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reindex_status SET processed_emails = ?1, cursor_date = ?2, cursor_id = ?3, heartbeat_at = ?4
+             WHERE id = 1",
+            params![processed, cursor_date, cursor_id, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// End the re-index run. The cursor is cleared on success and kept on error so the run can resume.
+    pub fn finish_reindex(&self, error: Option<&str>) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reindex_status
+             SET is_running = 0, finished_at = ?1, error_message = ?2,
+                 cursor_date = CASE WHEN ?2 IS NULL THEN NULL ELSE cursor_date END,
+                 cursor_id = CASE WHEN ?2 IS NULL THEN NULL ELSE cursor_id END
+             WHERE id = 1",
+            params![Utc::now().timestamp(), error],
+        )?;
+        Ok(())
+    }
+
+    // LLM runtime parameters saved before they moved to the settings file, or the defaults
+    pub fn get_llm_settings(&self) -> AnyhowResult<LlmSettings> {
+        let conn = self.reader();
+        let settings = conn
+            .query_row(
+                "SELECT n_ctx, n_batch, n_threads, gpu_layers, temperature, top_p
+                 FROM llm_settings WHERE id = 1",
+                [],
+                |row| {
+                    Ok(LlmSettings {
+                        n_ctx: row.get(0)?,
+                        n_batch: row.get(1)?,
+                        n_threads: row.get(2)?,
+                        gpu_layers: row.get(3)?,
+                        temperature: row.get::<_, f64>(4)? as f32,
+                        top_p: row.get::<_, f64>(5)? as f32,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    // Save a model's benchmark, replacing any earlier run
+    pub fn save_model_benchmark(&self, benchmark: &ModelBenchmark) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO model_benchmarks
+                 (model_id, load_time_ms, prompt_tokens, prompt_tokens_per_sec,
+                  generated_tokens, tokens_per_sec, peak_memory_bytes, measured_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                benchmark.model_id,
+                benchmark.load_time_ms as i64,
+                benchmark.prompt_tokens as i64,
+                benchmark.prompt_tokens_per_sec as f64,
+                benchmark.generated_tokens as i64,
+                benchmark.tokens_per_sec as f64,
+                benchmark.peak_memory_bytes.map(|bytes| bytes as i64),
+                benchmark.measured_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Get the latest benchmark of every model that has been measured
+    pub fn get_model_benchmarks(&self) -> AnyhowResult<Vec<ModelBenchmark>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT model_id, load_time_ms, prompt_tokens, prompt_tokens_per_sec,
+                    generated_tokens, tokens_per_sec, peak_memory_bytes, measured_at
+             FROM model_benchmarks ORDER BY model_id",
+        )?;
+        let benchmarks = stmt
+            .query_map([], |row| {
+                Ok(ModelBenchmark {
+                    model_id: row.get(0)?,
+                    load_time_ms: row.get::<_, i64>(1)? as u64,
+                    prompt_tokens: row.get::<_, i64>(2)? as usize,
+                    prompt_tokens_per_sec: row.get::<_, f64>(3)? as f32,
+                    generated_tokens: row.get::<_, i64>(4)? as usize,
+                    tokens_per_sec: row.get::<_, f64>(5)? as f32,
+                    peak_memory_bytes: row.get::<_, Option<i64>>(6)?.map(|bytes| bytes as u64),
+                    measured_at: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(benchmarks)
+    }
+
+    // Run category migration to remap old categories to new buckets
+    pub fn migrate_categories(&self) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE email_insights SET category = 'subscriptions' WHERE category IN ('notifications', 'financial')",
+            [],
+        )?;
+        conn.execute(
+            "UPDATE email_insights SET category = 'general' WHERE category IN ('conversation', 'meetings')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    // Get all cached emails as EmailListItem for a specific folder
+    pub fn get_cached_emails(
+        &self,
+        folder: &str,
+        limit: i64,
+    ) -> AnyhowResult<Vec<crate::email::types::EmailListItem>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, thread_id, subject, from_name, from_email, date, snippet,
+                    is_read, is_starred, has_attachments
+             FROM emails 
+             WHERE folder = ?1
+             ORDER BY date DESC LIMIT ?2",
+        )?;
+
+        let emails = stmt
+            .query_map(params![folder, limit], |row| {
+                let date_timestamp: i64 = row.get(5)?;
+
+                Ok(crate::email::types::EmailListItem {
+                    id: row.get(0)?,
+                    thread_id: row.get(1)?,
+                    subject: row.get(2)?,
+                    from: row.get(3)?,
+                    from_email: row.get(4)?,
+                    date: chrono::DateTime::from_timestamp(date_timestamp, 0)
+                        .map(|dt| dt.format("%a, %d %b %Y %H:%M:%S %z").to_string())
+                        .unwrap_or_default(),
+                    snippet: row.get(6)?,
+                    is_read: row.get::<_, i32>(7)? != 0,
+                    is_starred: row.get::<_, i32>(8)? != 0,
+                    has_attachments: row.get::<_, i32>(9)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Whether indexing found a meeting mentioned in an email; None before it's indexed
+    pub fn email_has_meeting(&self, email_id: &str) -> AnyhowResult<Option<bool>> {
+        let conn = self.reader();
+        let has_meeting = conn
+            .query_row(
+                "SELECT has_meeting FROM email_insights WHERE email_id = ?1",
+                [email_id],
+                |row| row.get::<_, i32>(0),
+            )
+            .optional()?;
+        Ok(has_meeting.map(|flag| flag != 0))
+    }
+
+    /// Get the trackers detected in an email during indexing
+    pub fn get_email_trackers(
+        &self,
+        email_id: &str,
+    ) -> AnyhowResult<Vec<crate::email::trackers::TrackerInfo>> {
+        let conn = self.reader();
+        let trackers_json: Option<String> = conn
+            .query_row(
+                "SELECT trackers FROM email_insights WHERE email_id = ?1",
+                [email_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(trackers_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
+
+    /// Get emails with a deadline in `[from, until]`, soonest first
+    pub fn get_upcoming_deadlines(
+        &self,
+        account_id: Option<&str>,
+        from: i64,
+        until: i64,
+        limit: i64,
+    ) -> AnyhowResult<Vec<DeadlineItem>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.subject, e.from_name, e.from_email, i.deadline_at, i.summary
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE i.deadline_at BETWEEN ?2 AND ?3 AND (?1 IS NULL OR e.account_id = ?1)
+             ORDER BY i.deadline_at ASC
+             LIMIT ?4",
+        )?;
+
+        let items = stmt
+            .query_map(params![account_id, from, until, limit], |row| {
+                Ok(DeadlineItem {
+                    email_id: row.get(0)?,
+                    subject: row.get(1)?,
+                    from_name: row.get(2)?,
+                    from_email: row.get(3)?,
+                    deadline_at: row.get(4)?,
+                    summary: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(items)
+    }
+
+    /// Get emails flagged as suspicious during indexing, most suspicious first
+    pub fn get_suspicious_emails(
+        &self,
+        account_id: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<SuspiciousEmail>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.subject, e.from_name, e.from_email, e.date, e.snippet,
+                    i.phishing_score, i.phishing_reasons
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE i.is_suspicious = 1 AND (?1 IS NULL OR e.account_id = ?1)
+             ORDER BY i.phishing_score DESC, e.date DESC
+             LIMIT ?2",
+        )?;
+
+        let emails = stmt
+            .query_map(params![account_id, limit], |row| {
+                let reasons_json: Option<String> = row.get(7)?;
+                Ok(SuspiciousEmail {
+                    id: row.get(0)?,
+                    subject: row.get(1)?,
+                    from_name: row.get(2)?,
+                    from_email: row.get(3)?,
+                    date: row.get(4)?,
+                    snippet: row.get(5)?,
+                    phishing_score: row.get(6)?,
+                    reasons: reasons_json
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Aggregate received mail per sender. Mail from the user's own accounts is excluded,
+    /// and counts as a reply when it's the latest message in a thread.
+    pub fn get_sender_stats(
+        &self,
+        account_id: Option<&str>,
+        sort: SenderSort,
+        limit: i64,
+    ) -> AnyhowResult<Vec<SenderStats>> {
+        let conn = self.reader();
+
+        // `r.from_name` next to MAX(r.date) takes the name from the most recent row
+        let mut stmt = conn.prepare(&format!(
+            "WITH mine AS (
+                 SELECT lower(email) AS email FROM accounts
+             ),
+             received AS MATERIALIZED (
+                 SELECT e.thread_id, lower(e.from_email) AS sender, e.from_name, e.date,
+                        e.is_read, e.has_attachments, i.priority_score
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 WHERE (?1 IS NULL OR e.account_id = ?1)
+                   AND lower(e.from_email) NOT IN (SELECT email FROM mine)
+             ),
+             sent AS MATERIALIZED (
+                 SELECT lower(e.to_emails) AS recipients, e.date
+                 FROM emails e
+                 WHERE (?1 IS NULL OR e.account_id = ?1)
+                   AND lower(e.from_email) IN (SELECT email FROM mine)
+             ),
+             waiting AS (
+                 SELECT r.sender, COUNT(DISTINCT r.thread_id) AS threads
+                 FROM received r
+                 WHERE r.date = (SELECT MAX(t.date) FROM emails t
+                                 WHERE t.thread_id = r.thread_id AND (?1 IS NULL OR t.account_id = ?1))
+                 GROUP BY r.sender
+             )
+             SELECT r.sender, r.from_name, COUNT(*), SUM(r.is_read = 0), AVG(r.priority_score),
+                    MAX(r.date),
+                    (SELECT MAX(s.date) FROM sent s WHERE instr(s.recipients, r.sender) > 0),
+                    SUM(r.has_attachments), COALESCE(w.threads, 0)
+             FROM received r
+             LEFT JOIN waiting w ON w.sender = r.sender
+             GROUP BY r.sender
+             ORDER BY {}
+             LIMIT ?2",
+            sort.order_by()
+        ))?;
+
+        let stats = stmt
+            .query_map(params![account_id, limit], |row| {
+                let email_count: i64 = row.get(2)?;
+                let unread_count: i64 = row.get(3)?;
+                Ok(SenderStats {
+                    from_email: row.get(0)?,
+                    from_name: row.get(1)?,
+                    email_count,
+                    unread_count,
+                    unread_ratio: if email_count > 0 {
+                        unread_count as f64 / email_count as f64
+                    } else {
+                        0.0
+                    },
+                    avg_priority_score: row.get(4)?,
+                    last_received_at: row.get(5)?,
+                    last_sent_at: row.get(6)?,
+                    attachment_count: row.get(7)?,
+                    awaiting_reply: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(stats)
+    }
+
+    // ========== Awaiting Reply ==========
+
+    /// Emails flagged as needing a reply, received at or before `received_before`, that
+    /// have no later message from the user in their thread and weren't answered from the
+    /// app. Oldest first. With `unreminded_only`, skips emails already reminded about.
+    pub fn get_awaiting_reply(
+        &self,
+        account_id: Option<&str>,
+        received_before: i64,
+        unreminded_only: bool,
+        limit: i64,
+    ) -> AnyhowResult<Vec<AwaitingReplyEmail>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.date, e.snippet,
+                    i.summary, i.reply_reminded_at
+             FROM emails e
+             INNER JOIN email_insights i ON e.id = i.email_id
+             WHERE i.needs_reply = 1 AND e.date <= ?2
+               AND (?1 IS NULL OR e.account_id = ?1)
+               AND (?3 = 0 OR i.reply_reminded_at IS NULL)
+               AND lower(e.from_email) NOT IN (SELECT lower(email) FROM accounts)
+               AND NOT EXISTS (
+                   SELECT 1 FROM emails r
+                   WHERE r.thread_id = e.thread_id AND r.date > e.date
+                     AND lower(r.from_email) IN (SELECT lower(email) FROM accounts)
+               )
+               AND NOT EXISTS (
+                   SELECT 1 FROM sent_emails s
+                   INNER JOIN emails o ON o.id = s.in_reply_to
+                   WHERE o.thread_id = e.thread_id AND s.sent_at >= e.date
+               )
+             ORDER BY e.date ASC
+             LIMIT ?4",
+        )?;
+
+        let emails = stmt
+            .query_map(
+                params![account_id, received_before, unreminded_only as i32, limit],
+                |row| {
+                    Ok(AwaitingReplyEmail {
+                        id: row.get(0)?,
+                        thread_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from_name: row.get(3)?,
+                        from_email: row.get(4)?,
+                        date: row.get(5)?,
+                        snippet: row.get(6)?,
+                        summary: row.get(7)?,
+                        reply_reminded_at: row.get(8)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    /// Sent emails nobody has answered, oldest first. A received email answers one when it
+    /// is in the reply thread, or comes from a recipient with the same subject (for clients
+    /// that drop threading headers). Unless `include_all`, only emails that ask something
+    /// are listed.
+    pub fn get_pending_followups(
+        &self,
+        account_id: Option<&str>,
+        sent_before: i64,
+        include_all: bool,
+        limit: i64,
+    ) -> AnyhowResult<Vec<PendingFollowup>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM sent_emails s
+             WHERE s.message_id IS NOT NULL AND s.followup_dismissed = 0
+               AND s.sent_at <= ?2
+               AND (?1 IS NULL OR s.account_id = ?1)
+               AND (?3 = 1 OR s.expects_reply = 1)
+               AND NOT EXISTS (
+                   SELECT 1 FROM emails r
+                   WHERE r.date >= s.sent_at
+                     AND lower(r.from_email) NOT IN (SELECT lower(email) FROM accounts)
+                     AND (r.thread_id = s.thread_id
+                          -- Gmail threads by conversation: match the synced Sent copy's thread
+                          OR r.thread_id IN (SELECT o.thread_id FROM emails o
+                                             WHERE o.message_id = s.message_id)
+                          OR (instr(lower(s.to_emails), lower(r.from_email)) > 0
+                              AND lower(r.subject) IN (lower(s.subject), 're: ' || lower(s.subject))))
+               )
+             ORDER BY s.sent_at ASC
+             LIMIT ?4",
+            FOLLOWUP_COLUMNS
+        ))?;
+
+        let followups = stmt
+            .query_map(
+                params![account_id, sent_before, include_all as i32, limit],
+                followup_from_row,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(followups)
+    }
+
+    /// Get a sent email by id, whether or not it has been answered
+    pub fn get_sent_email(&self, id: i64) -> AnyhowResult<Option<PendingFollowup>> {
+        let conn = self.reader();
+        let followup = conn
+            .query_row(
+                &format!("SELECT {} FROM sent_emails s WHERE s.id = ?1", FOLLOWUP_COLUMNS),
+                [id],
+                followup_from_row,
+            )
+            .optional()?;
+        Ok(followup)
+    }
+
+    /// Stop tracking a sent email for follow-up, e.g. after nudging or when it needs no answer
+    pub fn dismiss_followup(&self, id: i64) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE sent_emails SET followup_dismissed = 1 WHERE id = ?1",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    /// Override the reply-needed flag, e.g. when the user dismisses an email
+    pub fn set_needs_reply(&self, email_id: &str, needs_reply: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE email_insights SET needs_reply = ?2 WHERE email_id = ?1",
+            params![email_id, needs_reply as i32],
+        )?;
+        Ok(())
+    }
+
+    /// Remember that a reminder was shown for these emails
+    pub fn mark_reply_reminded(&self, email_ids: &[String]) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        for email_id in email_ids {
+            conn.execute(
+                "UPDATE email_insights SET reply_reminded_at = ?2 WHERE email_id = ?1",
+                params![email_id, now],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn get_reply_reminder_settings(&self) -> AnyhowResult<ReplyReminderSettings> {
+        let conn = self.reader();
+        let settings = conn
+            .query_row(
+                "SELECT enabled, after_days FROM reply_reminder_settings WHERE id = 1",
+                [],
+                |row| {
+                    Ok(ReplyReminderSettings {
+                        enabled: row.get::<_, i32>(0)? != 0,
+                        after_days: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn save_reply_reminder_settings(&self, settings: &ReplyReminderSettings) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO reply_reminder_settings (id, enabled, after_days)
+             VALUES (1, ?1, ?2)",
+            params![settings.enabled as i32, settings.after_days],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_notification_settings(
+        &self,
+        account_id: &str,
+    ) -> AnyhowResult<NotificationSettings> {
+        let conn = self.reader();
+        let settings = conn
+            .query_row(
+                "SELECT enabled, min_priority, muted_categories, quiet_start, quiet_end,
+                        vip_only, vip_senders
+                 FROM notification_settings WHERE account_id = ?1",
+                params![account_id],
+                |row| {
+                    let muted_categories: String = row.get(2)?;
+                    let quiet_start: Option<u32> = row.get(3)?;
+                    let quiet_end: Option<u32> = row.get(4)?;
+                    let vip_senders: String = row.get(6)?;
+                    Ok(NotificationSettings {
+                        enabled: row.get::<_, i32>(0)? != 0,
+                        min_priority: row.get(1)?,
+                        muted_categories: serde_json::from_str(&muted_categories)
+                            .unwrap_or_default(),
+                        quiet_hours: quiet_start.zip(quiet_end).map(
+                            |(start_minute, end_minute)| QuietHours {
+                                start_minute,
+                                end_minute,
+                            },
+                        ),
+                        vip_only: row.get::<_, i32>(5)? != 0,
+                        vip_senders: serde_json::from_str(&vip_senders).unwrap_or_default(),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn save_notification_settings(
+        &self,
+        account_id: &str,
+        settings: &NotificationSettings,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO notification_settings
+                 (account_id, enabled, min_priority, muted_categories, quiet_start, quiet_end,
+                  vip_only, vip_senders)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                account_id,
+                settings.enabled as i32,
+                settings.min_priority,
+                serde_json::to_string(&settings.muted_categories)?,
+                settings.quiet_hours.map(|quiet| quiet.start_minute),
+                settings.quiet_hours.map(|quiet| quiet.end_minute),
+                settings.vip_only as i32,
+                serde_json::to_string(&settings.vip_senders)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_account_sync_settings(&self, account_id: &str) -> AnyhowResult<AccountSyncSettings> {
+        let conn = self.reader();
+        let settings = conn
+            .query_row(
+                "SELECT folders, days_back, max_messages, fast_poll_secs
+                 FROM account_sync_settings WHERE account_id = ?1",
+                params![account_id],
+                |row| {
+                    let folders: Option<String> = row.get(0)?;
+                    Ok(AccountSyncSettings {
+                        folders: folders.and_then(|folders| serde_json::from_str(&folders).ok()),
+                        days_back: row.get(1)?,
+                        max_messages: row.get(2)?,
+                        fast_poll_secs: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(settings.unwrap_or_default())
+    }
+
+    pub fn save_account_sync_settings(
+        &self,
+        account_id: &str,
+        settings: &AccountSyncSettings,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO account_sync_settings
+                 (account_id, folders, days_back, max_messages, fast_poll_secs)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                account_id,
+                settings
+                    .folders
+                    .as_ref()
+                    .map(serde_json::to_string)
+                    .transpose()?,
+                settings.days_back,
+                settings.max_messages,
+                settings.fast_poll_secs,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Special-folder roles the user gave an account's folders, keyed by folder name;
+    /// `None` marks a folder as not special
+    pub fn get_folder_roles(
+        &self,
+        account_id: &str,
+    ) -> AnyhowResult<HashMap<String, Option<SpecialFolder>>> {
+        let conn = self.reader();
+        let mut stmt =
+            conn.prepare("SELECT folder, role FROM folder_roles WHERE account_id = ?1")?;
+        let roles = stmt
+            .query_map(params![account_id], |row| {
+                let folder: String = row.get(0)?;
+                let role: Option<String> = row.get(1)?;
+                Ok((folder, role.as_deref().and_then(parse_role_key)))
+            })?
+            .collect::<Result<_>>()?;
+        Ok(roles)
+    }
+
+    pub fn set_folder_role(
+        &self,
+        account_id: &str,
+        folder: &str,
+        role: Option<&SpecialFolder>,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO folder_roles (account_id, folder, role) VALUES (?1, ?2, ?3)",
+            params![account_id, folder, role.map(role_key)],
+        )?;
+        Ok(())
+    }
+
+    /// Go back to the detected role of a folder
+    pub fn clear_folder_role(&self, account_id: &str, folder: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM folder_roles WHERE account_id = ?1 AND folder = ?2",
+            params![account_id, folder],
+        )?;
+        Ok(())
+    }
+
+    /// Folder an account uses for `role`, as of the last folder listing
+    pub fn get_special_folder(
+        &self,
+        account_id: &str,
+        role: &SpecialFolder,
+    ) -> AnyhowResult<Option<String>> {
+        let conn = self.reader();
+        let folder = conn
+            .query_row(
+                "SELECT folder FROM special_folders WHERE account_id = ?1 AND role = ?2",
+                params![account_id, role_key(role)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(folder)
+    }
+
+    /// Remember which of an account's folders, just listed, have which role
+    pub fn save_special_folders(&self, account_id: &str, folders: &[Folder]) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM special_folders WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        for folder in folders {
+            if let Some(role) = &folder.special {
+                tx.execute(
+                    "INSERT OR IGNORE INTO special_folders (account_id, role, folder)
+                     VALUES (?1, ?2, ?3)",
+                    params![account_id, role_key(role), folder.name],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Forget an account's special folders, so they are looked up again on next use
+    pub fn clear_special_folders(&self, account_id: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM special_folders WHERE account_id = ?1",
+            params![account_id],
+        )?;
+        Ok(())
+    }
+
+    /// Priority and category indexing gave an email, if it has been indexed
+    pub fn get_email_priority(
+        &self,
+        email_id: &str,
+    ) -> AnyhowResult<Option<(String, Option<String>)>> {
+        let conn = self.reader();
+        let priority = conn
+            .query_row(
+                "SELECT priority, category FROM email_insights WHERE email_id = ?1",
+                params![email_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(priority)
+    }
+
+    /// Sent emails a bounce reported as failed, latest bounce first
+    pub fn get_bounced_messages(
+        &self,
+        account_id: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<PendingFollowup>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM sent_emails s
+             WHERE s.delivery_failed_at IS NOT NULL AND (?1 IS NULL OR s.account_id = ?1)
+             ORDER BY s.delivery_failed_at DESC
+             LIMIT ?2",
+            FOLLOWUP_COLUMNS
+        ))?;
+        let failed = stmt
+            .query_map(params![account_id, limit], followup_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(failed)
+    }
+
+    /// Which of `addresses` mail has bounced from
+    pub fn get_bounced_addresses(&self, addresses: &[String]) -> AnyhowResult<Vec<BouncedAddress>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT address, reason, bounce_count, last_bounced_at
+             FROM bounced_addresses WHERE address = ?1",
+        )?;
+        let mut bounced = Vec::new();
+        for address in addresses {
+            let found = stmt
+                .query_row(params![address.trim()], |row| {
+                    Ok(BouncedAddress {
+                        address: row.get(0)?,
+                        reason: row.get(1)?,
+                        bounce_count: row.get(2)?,
+                        last_bounced_at: row.get(3)?,
+                    })
+                })
+                .optional()?;
+            bounced.extend(found);
+        }
+        Ok(bounced)
+    }
+
+    /// Stop warning about an address, e.g. after the user fixed their contact
+    pub fn clear_bounced_address(&self, address: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM bounced_addresses WHERE address = ?1",
+            params![address],
+        )?;
+        Ok(())
+    }
+
+    /// Remember that the user sent or declined the read receipt requested by a message,
+    /// so it isn't asked for again
+    pub fn mark_read_receipt_handled(&self, message_id: &str, sent: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO read_receipts (message_id, sent, handled_at)
+             VALUES (?1, ?2, ?3)",
+            params![message_id, sent as i32, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Whether the user already sent or declined the read receipt a message asked for
+    pub fn is_read_receipt_handled(&self, message_id: &str) -> AnyhowResult<bool> {
+        let conn = self.reader();
+        let handled = conn
+            .query_row(
+                "SELECT 1 FROM read_receipts WHERE message_id = ?1",
+                params![message_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(handled)
+    }
+
+    // ========== Inbox Analytics ==========
+
+    /// Record an email sent from the app
+    pub fn record_sent_email(&self, sent: &NewSentEmail) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let thread_id: String = conn.query_row(
+            "SELECT COALESCE((SELECT thread_id FROM emails WHERE id = ?1), ?2)",
+            params![sent.in_reply_to, sent.thread_id],
+            |row| row.get(0),
+        )?;
+        // Replies to it are threaded with the conversation even if it isn't synced
+        if let Some(key) = message_key(sent.message_id) {
+            conn.execute(
+                "INSERT OR IGNORE INTO thread_links (message_key, thread_id) VALUES (?1, ?2)",
+                params![key, thread_id],
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO sent_emails (account_id, to_emails, subject, in_reply_to, sent_at,
+                                      message_id, thread_id, body_preview, expects_reply)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                sent.account_id,
+                serde_json::to_string(sent.to)?,
+                sent.subject,
+                sent.in_reply_to,
+                Utc::now().timestamp(),
+                sent.message_id,
+                thread_id,
+                sent.body_preview,
+                sent.expects_reply as i32,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Aggregate received and sent mail between `from` and `until` (Unix seconds). Days and
+    /// hours are bucketed in local time; sent mail counts what was sent from the app.
+    pub fn get_inbox_analytics(
+        &self,
+        account_id: Option<&str>,
+        from: i64,
+        until: i64,
+    ) -> AnyhowResult<InboxAnalytics> {
+        let conn = self.reader();
+
+        // Mail from the user's own accounts (e.g. a synced Sent folder) isn't "received"
+        let received_filter = "e.date >= ?2 AND e.date < ?3
+             AND (?1 IS NULL OR e.account_id = ?1)
+             AND lower(e.from_email) NOT IN (SELECT lower(email) FROM accounts)";
+
+        let mut received_by_day = std::collections::HashMap::new();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT date(e.date, 'unixepoch', 'localtime') AS day, COUNT(*)
+             FROM emails e
+             WHERE {received_filter}
+             GROUP BY day"
+        ))?;
+        for row in stmt.query_map(params![account_id, from, until], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })? {
+            let (day, count) = row?;
+            received_by_day.insert(day, count);
+        }
+
+        let mut sent_by_day = std::collections::HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT date(s.sent_at, 'unixepoch', 'localtime') AS day, COUNT(*)
+             FROM sent_emails s
+             WHERE s.sent_at >= ?2 AND s.sent_at < ?3 AND (?1 IS NULL OR s.account_id = ?1)
+             GROUP BY day",
+        )?;
+        for row in stmt.query_map(params![account_id, from, until], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })? {
+            let (day, count) = row?;
+            sent_by_day.insert(day, count);
+        }
+
+        let mut categories_stmt = conn.prepare(&format!(
+            "SELECT COALESCE(i.category, 'uncategorized') AS category, COUNT(*)
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE {received_filter}
+             GROUP BY category
+             ORDER BY COUNT(*) DESC"
+        ))?;
+        let categories = categories_stmt
+            .query_map(params![account_id, from, until], |row| {
+                Ok(CategoryCount {
+                    category: row.get(0)?,
+                    count: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut received_by_hour = vec![0; 24];
+        let mut stmt = conn.prepare(&format!(
+            "SELECT CAST(strftime('%H', e.date, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+                    COUNT(*)
+             FROM emails e
+             WHERE {received_filter}
+             GROUP BY hour"
+        ))?;
+        for row in stmt.query_map(params![account_id, from, until], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })? {
+            let (hour, count) = row?;
+            if let Some(slot) = received_by_hour.get_mut(hour as usize) {
+                *slot = count;
+            }
+        }
+
+        let (avg_response_secs, replies_measured) = conn.query_row(
+            "SELECT AVG(s.sent_at - e.date), COUNT(*)
+             FROM sent_emails s
+             INNER JOIN emails e ON e.id = s.in_reply_to
+             WHERE s.sent_at >= ?2 AND s.sent_at < ?3 AND s.sent_at >= e.date
+               AND (?1 IS NULL OR s.account_id = ?1)",
+            params![account_id, from, until],
+            |row| Ok((row.get::<_, Option<f64>>(0)?, row.get::<_, i64>(1)?)),
+        )?;
+
+        // Every day in the range, including quiet ones
+        let mut daily = Vec::new();
+        let local_day = |timestamp: i64| {
+            chrono::DateTime::from_timestamp(timestamp, 0)
+                .map(|t| t.with_timezone(&chrono::Local).date_naive())
+        };
+        if let (Some(mut day), Some(last)) = (local_day(from), local_day(until - 1)) {
+            while day <= last {
+                let key = day.format("%Y-%m-%d").to_string();
+                daily.push(DailyVolume {
+                    received: received_by_day.get(&key).copied().unwrap_or(0),
+                    sent: sent_by_day.get(&key).copied().unwrap_or(0),
+                    day: key,
+                });
+                match day.succ_opt() {
+                    Some(next) => day = next,
+                    None => break,
+                }
+            }
+        }
+
+        Ok(InboxAnalytics {
+            from,
+            until,
+            daily,
+            categories,
+            avg_response_secs,
+            replies_measured,
+            received_by_hour,
+        })
+    }
+
+    // ========== Remote Image Preferences ==========
+
+    /// Allow or revoke loading remote images for a sender
+    pub fn set_remote_images_allowed(&self, sender_email: &str, allowed: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let sender = sender_email.trim().to_lowercase();
+        if allowed {
+            conn.execute(
+                "INSERT OR REPLACE INTO remote_image_senders (sender_email, allowed_at) VALUES (?1, ?2)",
+                params![sender, Utc::now().timestamp()],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM remote_image_senders WHERE sender_email = ?1",
+                params![sender],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Whether the user opted in to remote images for this sender
+    pub fn is_remote_images_allowed(&self, sender_email: &str) -> AnyhowResult<bool> {
+        let conn = self.reader();
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM remote_image_senders WHERE sender_email = ?1",
+            params![sender_email.trim().to_lowercase()],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Senders the user opted in to remote images for
+    pub fn list_remote_image_senders(&self) -> AnyhowResult<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt =
+            conn.prepare("SELECT sender_email FROM remote_image_senders ORDER BY sender_email")?;
+        let senders = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(senders)
+    }
+
+    // ========== Categories ==========
+
+    /// Configured categories in display order
+    pub fn get_categories(&self) -> AnyhowResult<Vec<EmailCategory>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, position FROM categories ORDER BY position, created_at",
+        )?;
+
+        let categories = stmt
+            .query_map([], |row| {
+                Ok(EmailCategory {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    position: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(categories)
+    }
+
+    /// Add a category at the end of the list
+    pub fn add_category(&self, id: &str, name: &str, description: &str) -> AnyhowResult<EmailCategory> {
+        let conn = self.conn.lock().unwrap();
+
+        let position: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(position), -1) + 1 FROM categories",
+            [],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "INSERT INTO categories (id, name, description, position, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![id, name, description, position, Utc::now().timestamp()],
+        )
+        .with_context(|| format!("Category '{}' already exists", id))?;
+
+        Ok(EmailCategory {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            position,
+        })
+    }
+
+    /// Change a category's display name and description (its id stays stable)
+    pub fn update_category(&self, id: &str, name: &str, description: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE categories SET name = ?2, description = ?3 WHERE id = ?1",
+            params![id, name, description],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Category '{}' not found", id);
+        }
+        Ok(())
+    }
+
+    /// Delete a category, moving its emails to `fallback`
+    pub fn delete_category(&self, id: &str, fallback: &str) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE email_insights SET category = ?2 WHERE category = ?1",
+            params![id, fallback],
+        )?;
+        tx.execute("DELETE FROM categories WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Overwrite the stored category of an indexed email
+    pub fn update_email_category(&self, email_id: &str, category: &str) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE email_insights SET category = ?2 WHERE email_id = ?1",
+            params![email_id, category],
+        )?;
+        changes::record(Change::Insights, email_id);
+        Ok(())
+    }
+
+    // ========== Labels ==========
+
+    /// User labels sorted by name, with how many cached emails carry each
+    pub fn list_labels(&self) -> AnyhowResult<Vec<Label>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT l.name, l.keyword, l.color,
+                    (SELECT COUNT(*) FROM emails e, json_each(e.labels) j
+                     WHERE j.value = l.keyword COLLATE NOCASE)
+             FROM labels l
+             ORDER BY l.name",
+        )?;
+
+        let labels = stmt
+            .query_map([], |row| {
+                Ok(Label {
+                    name: row.get(0)?,
+                    keyword: row.get(1)?,
+                    color: row.get(2)?,
+                    email_count: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(labels)
+    }
+
+    /// Look up a label by name, ignoring case
+    pub fn get_label(&self, name: &str) -> AnyhowResult<Option<Label>> {
+        let conn = self.reader();
+        let label = conn
+            .query_row(
+                "SELECT name, keyword, color FROM labels WHERE name = ?1",
+                [name],
+                |row| {
+                    Ok(Label {
+                        name: row.get(0)?,
+                        keyword: row.get(1)?,
+                        color: row.get(2)?,
+                        email_count: 0,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(label)
+    }
+
+    pub fn create_label(
+        &self,
+        name: &str,
+        keyword: &str,
+        color: Option<&str>,
+    ) -> AnyhowResult<Label> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO labels (name, keyword, color, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, keyword, color, Utc::now().timestamp()],
+        )
+        .with_context(|| format!("A label like '{}' already exists", name))?;
+
+        Ok(Label {
+            name: name.to_string(),
+            keyword: keyword.to_string(),
+            color: color.map(str::to_string),
+            email_count: 0,
+        })
+    }
+
+    /// Delete a label and take it off every cached email
+    pub fn delete_label(&self, name: &str) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let keyword: Option<String> = tx
+            .query_row("SELECT keyword FROM labels WHERE name = ?1", [name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if let Some(keyword) = keyword {
+            tx.execute(
+                "UPDATE emails SET labels = (
+                     SELECT json_group_array(j.value) FROM json_each(emails.labels) j
+                     WHERE j.value <> ?1 COLLATE NOCASE
+                 )
+                 WHERE EXISTS (
+                     SELECT 1 FROM json_each(emails.labels) j WHERE j.value = ?1 COLLATE NOCASE
+                 )",
+                [&keyword],
+            )?;
+            tx.execute("DELETE FROM labels WHERE name = ?1", [name])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Ids of the cached emails carrying a label keyword
+    pub fn label_email_ids(&self, keyword: &str) -> AnyhowResult<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT e.id FROM emails e, json_each(e.labels) j WHERE j.value = ?1 COLLATE NOCASE",
+        )?;
+        let ids = stmt
+            .query_map([keyword], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// Add or remove a label keyword on a cached email
+    pub fn set_email_label(&self, email_id: &str, keyword: &str, add: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        if add {
+            conn.execute(
+                "UPDATE emails SET labels = json_insert(COALESCE(labels, '[]'), '$[#]', ?2)
+                 WHERE id = ?1 AND NOT EXISTS (
+                     SELECT 1 FROM json_each(COALESCE(emails.labels, '[]')) j
+                     WHERE j.value = ?2 COLLATE NOCASE
+                 )",
+                params![email_id, keyword],
+            )?;
+        } else {
+            conn.execute(
+                "UPDATE emails SET labels = (
+                     SELECT json_group_array(j.value) FROM json_each(emails.labels) j
+                     WHERE j.value <> ?2 COLLATE NOCASE
+                 )
+                 WHERE id = ?1",
+                params![email_id, keyword],
+            )?;
+        }
+        changes::record(Change::Updated, email_id);
         Ok(())
     }
 
-    // Get emails sorted by priority
-    pub fn get_emails_by_priority(
+    /// Cached emails carrying a label keyword, newest first
+    pub fn get_emails_by_label(
         &self,
+        keyword: &str,
+        account_id: Option<&str>,
         limit: i64,
         offset: i64,
     ) -> AnyhowResult<Vec<EmailWithInsight>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.reader();
 
         let mut stmt = conn.prepare(
             "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
                     e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
                     COALESCE(i.priority, 'MEDIUM') as priority,
                     COALESCE(i.priority_score, 0.5) as priority_score,
-                    i.category, i.summary
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
              FROM emails e
              LEFT JOIN email_insights i ON e.id = i.email_id
-             ORDER BY COALESCE(i.priority_score, 0.5) DESC, e.date DESC
-             LIMIT ?1 OFFSET ?2",
+             WHERE (?2 IS NULL OR e.account_id = ?2)
+               AND EXISTS (
+                   SELECT 1 FROM json_each(e.labels) j WHERE j.value = ?1 COLLATE NOCASE
+               )
+             ORDER BY e.date DESC
+             LIMIT ?3 OFFSET ?4",
         )?;
 
         let emails = stmt
-            .query_map(params![limit, offset], |row| {
+            .query_map(params![keyword, account_id, limit, offset], |row| {
                 Ok(EmailWithInsight {
                     id: row.get(0)?,
                     thread_id: row.get(1)?,
@@ -172,6 +4065,9 @@ impl EmailDatabase {
                     priority_score: row.get(12)?,
                     category: row.get(13)?,
                     summary: row.get(14)?,
+                    sentiment: row.get(15)?,
+                    language: row.get(16)?,
+                    pinned: row.get::<_, i32>(17)? != 0,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -179,653 +4075,1567 @@ impl EmailDatabase {
         Ok(emails)
     }
 
-    // Get emails by category
-    pub fn get_emails_by_category(
+    /// Inbox email counts per tab. Emails without a Gmail category label (including every
+    /// email of other providers) count as Primary.
+    pub fn get_inbox_tabs(&self, account_id: Option<&str>) -> AnyhowResult<Vec<InboxTab>> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(
+                        (SELECT lower(substr(j.value, 10)) FROM json_each(e.labels) j
+                         WHERE j.value LIKE 'CATEGORY\\_%' ESCAPE '\\' LIMIT 1),
+                        'primary') AS tab,
+                    COUNT(*), SUM(e.is_read = 0)
+             FROM emails e
+             WHERE e.folder = 'INBOX' AND (?1 IS NULL OR e.account_id = ?1)
+             GROUP BY tab",
+        )?;
+        let mut counts = stmt
+            .query_map([account_id], |row| {
+                Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?)))
+            })?
+            .collect::<Result<HashMap<String, (i64, i64)>, _>>()?;
+
+        let ids = std::iter::once("primary").chain(GmailCategory::ALL.iter().map(|c| c.id()));
+        Ok(ids
+            .map(|id| {
+                let (total, unread) = counts.remove(id).unwrap_or((0, 0));
+                InboxTab {
+                    id: id.to_string(),
+                    total,
+                    unread,
+                }
+            })
+            .collect())
+    }
+
+    /// Total and unread counts per priority, category, account and folder, in one pass
+    /// over the cache
+    pub fn get_inbox_counts(&self, account_id: Option<&str>) -> AnyhowResult<InboxCounts> {
+        let conn = self.reader();
+
+        let mut stmt = conn.prepare(&format!(
+            "WITH counted AS (
+                 SELECT COALESCE(e.account_id, 'legacy') AS account_id,
+                        COALESCE(e.folder, 'INBOX') AS folder, e.is_read, i.category,
+                        COALESCE(i.priority, 'MEDIUM') AS priority,
+                        ({}) AS shown
+                 FROM emails e
+                 LEFT JOIN email_insights i ON e.id = i.email_id
+                 WHERE ?1 IS NULL OR e.account_id = ?1
+             )
+             SELECT 'priority', NULL, priority, COUNT(*), SUM(is_read = 0)
+             FROM counted WHERE shown GROUP BY priority
+             UNION ALL
+             SELECT 'category', NULL, category, COUNT(*), SUM(is_read = 0)
+             FROM counted WHERE shown AND category IS NOT NULL GROUP BY category
+             UNION ALL
+             SELECT 'account', NULL, account_id, COUNT(*), SUM(is_read = 0)
+             FROM counted WHERE shown GROUP BY account_id
+             UNION ALL
+             SELECT 'folder', account_id, folder, COUNT(*), SUM(is_read = 0)
+             FROM counted GROUP BY account_id, folder",
+            collapse_condition()
+        ))?;
+        let mut rows = stmt.query([account_id])?;
+
+        let mut counts = InboxCounts::default();
+        while let Some(row) = rows.next()? {
+            let group: String = row.get(0)?;
+            let key: String = row.get(2)?;
+            let total: i64 = row.get(3)?;
+            let unread: i64 = row.get(4)?;
+            let list = match group.as_str() {
+                "priority" => &mut counts.priorities,
+                "category" => &mut counts.categories,
+                "account" => &mut counts.accounts,
+                _ => {
+                    counts.folders.push(FolderCount {
+                        account_id: row.get(1)?,
+                        folder: key,
+                        total,
+                        unread,
+                    });
+                    continue;
+                }
+            };
+            list.push(UnreadCount { key, total, unread });
+        }
+        Ok(counts)
+    }
+
+    /// Inbox emails in a tab, newest first; `category` None is Primary
+    pub fn get_inbox_tab_emails(
         &self,
-        category: &str,
+        account_id: Option<&str>,
+        category: Option<GmailCategory>,
         limit: i64,
+        offset: i64,
     ) -> AnyhowResult<Vec<EmailWithInsight>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.reader();
 
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare(&format!(
             "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
                     e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
-                    i.priority, i.priority_score, i.category, i.summary
+                    COALESCE(i.priority, 'MEDIUM') as priority,
+                    COALESCE(i.priority_score, 0.5) as priority_score,
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
              FROM emails e
-             INNER JOIN email_insights i ON e.id = i.email_id
-             WHERE i.category = ?1
-             ORDER BY i.priority_score DESC, e.date DESC
-             LIMIT ?2",
-        )?;
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE e.folder = 'INBOX' AND (?1 IS NULL OR e.account_id = ?1)
+               AND CASE WHEN ?2 IS NULL
+                   THEN NOT EXISTS (SELECT 1 FROM json_each(e.labels) j
+                                    WHERE j.value LIKE 'CATEGORY\\_%' ESCAPE '\\')
+                   ELSE EXISTS (SELECT 1 FROM json_each(e.labels) j WHERE j.value = ?2)
+               END
+               AND {}
+             ORDER BY e.date DESC
+             LIMIT ?3 OFFSET ?4",
+            collapse_condition()
+        ))?;
 
         let emails = stmt
-            .query_map(params![category, limit], |row| {
-                Ok(EmailWithInsight {
-                    id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from_name: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
-                    date: row.get(6)?,
-                    snippet: row.get(7)?,
-                    is_read: row.get::<_, i32>(8)? != 0,
-                    is_starred: row.get::<_, i32>(9)? != 0,
-                    has_attachments: row.get::<_, i32>(10)? != 0,
-                    priority: row.get(11)?,
-                    priority_score: row.get(12)?,
-                    category: row.get(13)?,
-                    summary: row.get(14)?,
+            .query_map(
+                params![account_id, category.map(|c| c.label()), limit, offset],
+                |row| {
+                    Ok(EmailWithInsight {
+                        id: row.get(0)?,
+                        thread_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from_name: row.get(3)?,
+                        from_email: row.get(4)?,
+                        to_emails: serde_json::from_str(&row.get::<_, String>(5)?)
+                            .unwrap_or_default(),
+                        date: row.get(6)?,
+                        snippet: row.get(7)?,
+                        is_read: row.get::<_, i32>(8)? != 0,
+                        is_starred: row.get::<_, i32>(9)? != 0,
+                        has_attachments: row.get::<_, i32>(10)? != 0,
+                        priority: row.get(11)?,
+                        priority_score: row.get(12)?,
+                        category: row.get(13)?,
+                        summary: row.get(14)?,
+                        sentiment: row.get(15)?,
+                        language: row.get(16)?,
+                        pinned: row.get::<_, i32>(17)? != 0,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(emails)
+    }
+
+    // ========== POP3 ==========
+
+    /// UIDLs of the messages already downloaded from a POP3 account
+    pub fn get_pop3_uidls(&self, account_id: &str) -> AnyhowResult<HashSet<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare("SELECT uidl FROM pop3_uidls WHERE account_id = ?1")?;
+        let uidls = stmt
+            .query_map(params![account_id], |row| row.get(0))?
+            .collect::<Result<HashSet<String>>>()?;
+        Ok(uidls)
+    }
+
+    /// Remember that a POP3 message was downloaded, so later syncs skip it even if the
+    /// cached email is deleted
+    pub fn record_pop3_uidl(
+        &self,
+        account_id: &str,
+        uidl: &str,
+        email_id: &str,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO pop3_uidls (account_id, uidl, email_id, downloaded_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![account_id, uidl, email_id, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    // ========== Identities ==========
+
+    pub fn list_identities(&self, account_id: &str) -> AnyhowResult<Vec<Identity>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM identities WHERE account_id = ?1 ORDER BY address",
+            IDENTITY_COLUMNS
+        ))?;
+        let identities = stmt
+            .query_map(params![account_id], identity_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(identities)
+    }
+
+    /// Identity of an account by address (case-insensitive)
+    pub fn get_identity(&self, account_id: &str, address: &str) -> AnyhowResult<Option<Identity>> {
+        let conn = self.reader();
+        let identity = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM identities WHERE account_id = ?1 AND address = ?2",
+                    IDENTITY_COLUMNS
+                ),
+                params![account_id, address],
+                identity_from_row,
+            )
+            .optional()?;
+        Ok(identity)
+    }
+
+    pub fn add_identity(&self, identity: &Identity) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO identities (id, account_id, address, display_name, signature, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                identity.id,
+                identity.account_id,
+                identity.address,
+                identity.display_name,
+                identity.signature,
+                identity.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    // ========== Attachments ==========
+
+    /// Attachments across the mailbox matching `filter`, newest email first
+    pub fn list_attachments(&self, filter: &AttachmentFilter) -> AnyhowResult<Vec<AttachmentItem>> {
+        let conn = self.reader();
+
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let mut condition = |sql: &str, value: Value| {
+            values.push(value);
+            conditions.push(sql.replace('?', &format!("?{}", values.len())));
+        };
+        if let Some(account_id) = &filter.account_id {
+            condition("e.account_id = ?", Value::Text(account_id.clone()));
+        }
+        if let Some(kind) = filter.kind {
+            condition("a.kind = ?", Value::Text(kind.id().to_string()));
+        }
+        if let Some(from) = &filter.from {
+            condition(
+                "e.from_email LIKE ?",
+                Value::Text(format!("%{}%", from.trim())),
+            );
+        }
+        if let Some(filename) = &filter.filename {
+            condition(
+                "a.filename LIKE ?",
+                Value::Text(format!("%{}%", filename.trim())),
+            );
+        }
+        if let Some(after) = filter.after {
+            condition("e.date >= ?", Value::Integer(after));
+        }
+        if let Some(before) = filter.before {
+            condition("e.date < ?", Value::Integer(before));
+        }
+        if let Some(min_size) = filter.min_size {
+            condition("a.size >= ?", Value::Integer(min_size));
+        }
+        if let Some(max_size) = filter.max_size {
+            condition("a.size <= ?", Value::Integer(max_size));
+        }
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        values.push(Value::Integer(filter.limit.unwrap_or(100)));
+        values.push(Value::Integer(filter.offset.unwrap_or(0)));
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT a.email_id, a.position, a.filename, a.content_type, a.kind, a.size,
+                    a.local_path, e.subject, e.from_name, e.from_email, e.date
+             FROM attachments a
+             INNER JOIN emails e ON e.id = a.email_id
+             {}
+             ORDER BY e.date DESC, a.position
+             LIMIT ?{} OFFSET ?{}",
+            where_clause,
+            values.len() - 1,
+            values.len()
+        ))?;
+        let attachments = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok(AttachmentItem {
+                    email_id: row.get(0)?,
+                    attachment: Attachment {
+                        position: row.get(1)?,
+                        filename: row.get(2)?,
+                        content_type: row.get(3)?,
+                        kind: AttachmentKind::from_id(&row.get::<_, String>(4)?),
+                        size: row.get::<_, i64>(5)? as u64,
+                        local_path: row.get(6)?,
+                        data: Vec::new(),
+                    },
+                    subject: row.get(7)?,
+                    from_name: row.get(8)?,
+                    from_email: row.get(9)?,
+                    date: row.get(10)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(attachments)
+    }
 
-        Ok(emails)
+    pub fn get_attachment(
+        &self,
+        email_id: &str,
+        position: u32,
+    ) -> AnyhowResult<Option<Attachment>> {
+        let conn = self.reader();
+        let attachment = conn
+            .query_row(
+                "SELECT filename, content_type, kind, size, local_path
+                 FROM attachments WHERE email_id = ?1 AND position = ?2",
+                params![email_id, position],
+                |row| {
+                    Ok(Attachment {
+                        position,
+                        filename: row.get(0)?,
+                        content_type: row.get(1)?,
+                        kind: AttachmentKind::from_id(&row.get::<_, String>(2)?),
+                        size: row.get::<_, i64>(3)? as u64,
+                        local_path: row.get(4)?,
+                        data: Vec::new(),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(attachment)
     }
 
-    // Get emails from today
-    pub fn get_emails_from_today(&self) -> AnyhowResult<Vec<EmailWithInsight>> {
+    /// Record where an attachment was downloaded to
+    pub fn set_attachment_path(
+        &self,
+        email_id: &str,
+        position: u32,
+        path: &str,
+    ) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        let today_start = Utc::now()
-            .date_naive()
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_utc()
-            .timestamp();
+        conn.execute(
+            "UPDATE attachments SET local_path = ?3 WHERE email_id = ?1 AND position = ?2",
+            params![email_id, position, path],
+        )?;
+        Ok(())
+    }
+
+    /// Number and total size of downloaded attachments
+    pub fn get_attachment_storage(&self) -> AnyhowResult<(i64, u64)> {
+        let conn = self.reader();
+        let (count, bytes): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0) FROM attachments WHERE local_path IS NOT NULL",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((count, bytes as u64))
+    }
+
+    /// Forget downloaded attachment files, after the media cache was cleared
+    pub fn clear_attachment_paths(&self) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE attachments SET local_path = NULL", [])?;
+        Ok(())
+    }
 
+    /// Downloaded image and PDF attachments up to `max_size` bytes that OCR hasn't read
+    /// yet, newest email first, optionally of one email
+    pub fn get_ocr_pending_attachments(
+        &self,
+        email_id: Option<&str>,
+        max_size: u64,
+        limit: i64,
+    ) -> AnyhowResult<Vec<(String, Attachment)>> {
+        let conn = self.reader();
         let mut stmt = conn.prepare(
-            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
-                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
-                    COALESCE(i.priority, 'MEDIUM') as priority,
-                    COALESCE(i.priority_score, 0.5) as priority_score,
-                    i.category, i.summary
-             FROM emails e
-             LEFT JOIN email_insights i ON e.id = i.email_id
-             WHERE e.date >= ?1
-             ORDER BY e.date DESC",
+            "SELECT a.email_id, a.position, a.filename, a.content_type, a.kind, a.size,
+                    a.local_path
+             FROM attachments a
+             INNER JOIN emails e ON e.id = a.email_id
+             WHERE a.local_path IS NOT NULL AND a.ocr_at IS NULL
+               AND a.kind IN ('image', 'document') AND a.size <= ?2
+               AND (?1 IS NULL OR a.email_id = ?1)
+             ORDER BY e.date DESC, a.position
+             LIMIT ?3",
         )?;
+        let attachments = stmt
+            .query_map(params![email_id, max_size as i64, limit], |row| {
+                Ok((
+                    row.get(0)?,
+                    Attachment {
+                        position: row.get(1)?,
+                        filename: row.get(2)?,
+                        content_type: row.get(3)?,
+                        kind: AttachmentKind::from_id(&row.get::<_, String>(4)?),
+                        size: row.get::<_, i64>(5)? as u64,
+                        local_path: row.get(6)?,
+                        data: Vec::new(),
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(attachments)
+    }
 
-        let emails = stmt
-            .query_map(params![today_start], |row| {
-                Ok(EmailWithInsight {
-                    id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from_name: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
-                    date: row.get(6)?,
-                    snippet: row.get(7)?,
-                    is_read: row.get::<_, i32>(8)? != 0,
-                    is_starred: row.get::<_, i32>(9)? != 0,
-                    has_attachments: row.get::<_, i32>(10)? != 0,
-                    priority: row.get(11)?,
-                    priority_score: row.get(12)?,
-                    category: row.get(13)?,
-                    summary: row.get(14)?,
-                })
+    /// Record what OCR read from an attachment; None when it found no text or couldn't
+    /// read the file, so it isn't tried again
+    pub fn set_attachment_ocr_text(
+        &self,
+        email_id: &str,
+        position: u32,
+        text: Option<&str>,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE attachments SET ocr_text = ?3, ocr_at = ?4
+             WHERE email_id = ?1 AND position = ?2",
+            params![email_id, position, text, Utc::now().timestamp()],
+        )?;
+        changes::record(Change::Updated, email_id);
+        Ok(())
+    }
+
+    /// Text OCR read from one attachment
+    pub fn get_attachment_ocr_text(
+        &self,
+        email_id: &str,
+        position: u32,
+    ) -> AnyhowResult<Option<String>> {
+        let conn = self.reader();
+        let text = conn
+            .query_row(
+                "SELECT ocr_text FROM attachments WHERE email_id = ?1 AND position = ?2",
+                params![email_id, position],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(text)
+    }
+
+    /// Text OCR read from an email's attachments, each under its file name, for the AI
+    /// to summarize and classify along with the body
+    pub fn get_email_attachment_text(&self, email_id: &str) -> AnyhowResult<Option<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT filename, ocr_text FROM attachments
+             WHERE email_id = ?1 AND ocr_text IS NOT NULL AND ocr_text != ''
+             ORDER BY position",
+        )?;
+        let texts = stmt
+            .query_map(params![email_id], |row| {
+                Ok(format!(
+                    "[Attachment: {}]\n{}",
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?
+                ))
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok((!texts.is_empty()).then(|| texts.join("\n\n")))
+    }
 
-        Ok(emails)
+    // ========== Links ==========
+
+    /// Links found in an email's body, in order of appearance
+    pub fn get_email_links(&self, email_id: &str) -> AnyhowResult<Vec<EmailLink>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT url, text, domain, is_tracker FROM email_links
+             WHERE email_id = ?1 ORDER BY position",
+        )?;
+        let links = stmt
+            .query_map(params![email_id], |row| {
+                Ok(EmailLink {
+                    url: row.get(0)?,
+                    text: row.get(1)?,
+                    domain: row.get(2)?,
+                    is_tracker: row.get::<_, i32>(3)? != 0,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// Links others sent, newest first, each with the latest email it appeared in.
+    /// Tracker links and the user's own sent mail are left out.
+    pub fn get_shared_links(
+        &self,
+        account_id: Option<&str>,
+        limit: i64,
+    ) -> AnyhowResult<Vec<SharedLink>> {
+        let conn = self.reader();
+        // SQLite takes the bare columns from the row with the MAX(date)
+        let mut stmt = conn.prepare(
+            "SELECT l.url, l.text, l.domain, e.id, e.subject, e.from_name, e.from_email,
+                    MAX(e.date)
+             FROM email_links l
+             INNER JOIN emails e ON e.id = l.email_id
+             WHERE l.is_tracker = 0
+               AND (?1 IS NULL OR e.account_id = ?1)
+               AND e.from_email NOT IN (SELECT email FROM accounts)
+             GROUP BY l.url
+             ORDER BY MAX(e.date) DESC
+             LIMIT ?2",
+        )?;
+        let links = stmt
+            .query_map(params![account_id, limit], |row| {
+                Ok(SharedLink {
+                    link: EmailLink {
+                        url: row.get(0)?,
+                        text: row.get(1)?,
+                        domain: row.get(2)?,
+                        is_tracker: false,
+                    },
+                    email_id: row.get(3)?,
+                    subject: row.get(4)?,
+                    from_name: row.get(5)?,
+                    from_email: row.get(6)?,
+                    date: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// Cached preview of a URL with when it was fetched
+    pub fn get_link_preview(&self, url: &str) -> AnyhowResult<Option<(LinkMetadata, i64)>> {
+        let conn = self.reader();
+        let preview = conn
+            .query_row(
+                "SELECT title, description, favicon, fetched_at FROM link_previews WHERE url = ?1",
+                params![url],
+                |row| {
+                    Ok((
+                        LinkMetadata {
+                            title: row.get(0)?,
+                            description: row.get(1)?,
+                            favicon: row.get(2)?,
+                        },
+                        row.get(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+        Ok(preview)
+    }
+
+    pub fn save_link_preview(&self, url: &str, metadata: &LinkMetadata) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO link_previews (url, title, description, favicon, fetched_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                url,
+                metadata.title,
+                metadata.description,
+                metadata.favicon,
+                Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    // ========== Translations ==========
+
+    pub fn get_translation(
+        &self,
+        email_id: &str,
+        language: &str,
+    ) -> AnyhowResult<Option<EmailTranslation>> {
+        let conn = self.reader();
+        let translation = conn
+            .query_row(
+                "SELECT body, translated_at FROM email_translations
+                 WHERE email_id = ?1 AND language = ?2",
+                params![email_id, language],
+                |row| {
+                    Ok(EmailTranslation {
+                        email_id: email_id.to_string(),
+                        language: language.to_string(),
+                        body: row.get(0)?,
+                        translated_at: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(translation)
+    }
+
+    pub fn save_translation(&self, translation: &EmailTranslation) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO email_translations (email_id, language, body, translated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                translation.email_id,
+                translation.language,
+                translation.body,
+                translation.translated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    // ========== IMAP Sync State ==========
+
+    /// Where the last CONDSTORE flag sync of a folder left off
+    pub fn get_folder_sync_state(
+        &self,
+        account_id: &str,
+        folder: &str,
+    ) -> AnyhowResult<Option<FolderSyncState>> {
+        let conn = self.reader();
+        let state = conn
+            .query_row(
+                "SELECT uid_validity, highest_modseq FROM imap_sync_state
+                 WHERE account_id = ?1 AND folder = ?2",
+                params![account_id, folder],
+                |row| {
+                    Ok(FolderSyncState {
+                        uid_validity: row.get(0)?,
+                        highest_modseq: row.get::<_, i64>(1)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(state)
+    }
+
+    pub fn set_folder_sync_state(
+        &self,
+        account_id: &str,
+        folder: &str,
+        state: &FolderSyncState,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO imap_sync_state (account_id, folder, uid_validity, highest_modseq)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                account_id,
+                folder,
+                state.uid_validity,
+                state.highest_modseq as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Apply read and starred state synced from the server to a cached email
+    pub fn update_email_flags(
+        &self,
+        email_id: &str,
+        is_read: bool,
+        is_starred: bool,
+    ) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE emails SET is_read = ?2, is_starred = ?3 WHERE id = ?1",
+            params![email_id, is_read as i32, is_starred as i32],
+        )?;
+        changes::record(Change::Updated, email_id);
+        Ok(())
     }
 
-    // Search emails by text
-    pub fn search_emails(&self, query: &str, limit: i64) -> AnyhowResult<Vec<EmailWithInsight>> {
-        let conn = self.conn.lock().unwrap();
-        let search_pattern = format!("%{}%", query);
+    // ========== Folder Status ==========
 
+    /// Folder counts from the last STATUS pass of an account
+    pub fn get_folder_status(&self, account_id: &str) -> AnyhowResult<Vec<FolderStatus>> {
+        let conn = self.reader();
         let mut stmt = conn.prepare(
-            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
-                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
-                    COALESCE(i.priority, 'MEDIUM') as priority,
-                    COALESCE(i.priority_score, 0.5) as priority_score,
-                    i.category, i.summary
-             FROM emails e
-             LEFT JOIN email_insights i ON e.id = i.email_id
-             WHERE e.subject LIKE ?1 OR e.from_name LIKE ?1 OR e.snippet LIKE ?1
-                   OR COALESCE(i.summary, '') LIKE ?1
-             ORDER BY e.date DESC
-             LIMIT ?2",
+            "SELECT folder, total_count, unread_count, recent_count FROM folder_status
+             WHERE account_id = ?1 ORDER BY folder",
         )?;
-
-        let emails = stmt
-            .query_map(params![&search_pattern, limit], |row| {
-                Ok(EmailWithInsight {
-                    id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from_name: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
-                    date: row.get(6)?,
-                    snippet: row.get(7)?,
-                    is_read: row.get::<_, i32>(8)? != 0,
-                    is_starred: row.get::<_, i32>(9)? != 0,
-                    has_attachments: row.get::<_, i32>(10)? != 0,
-                    priority: row.get(11)?,
-                    priority_score: row.get(12)?,
-                    category: row.get(13)?,
-                    summary: row.get(14)?,
+        let statuses = stmt
+            .query_map(params![account_id], |row| {
+                Ok(FolderStatus {
+                    folder: row.get(0)?,
+                    total_count: row.get(1)?,
+                    unread_count: row.get(2)?,
+                    recent_count: row.get(3)?,
                 })
             })?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(emails)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(statuses)
     }
 
-    // Update indexing status
-    pub fn update_indexing_status(
+    /// Replace the cached folder counts of an account, forgetting folders that are gone
+    pub fn set_folder_status(
         &self,
-        is_indexing: bool,
-        total: Option<i64>,
-        processed: Option<i64>,
-        error: Option<String>,
+        account_id: &str,
+        statuses: &[FolderStatus],
     ) -> AnyhowResult<()> {
-        let conn = self.conn.lock().unwrap();
-
-        if let Some(total) = total {
-            conn.execute(
-                "UPDATE indexing_status SET total_emails = ?1 WHERE id = 1",
-                params![total],
-            )?;
-        }
-
-        if let Some(processed) = processed {
-            conn.execute(
-                "UPDATE indexing_status SET processed_emails = ?1 WHERE id = 1",
-                params![processed],
-            )?;
-        }
-
-        conn.execute(
-            "UPDATE indexing_status SET is_indexing = ?1 WHERE id = 1",
-            params![is_indexing as i32],
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM folder_status WHERE account_id = ?1",
+            params![account_id],
         )?;
-
-        if !is_indexing {
-            let now = Utc::now().timestamp();
-            conn.execute(
-                "UPDATE indexing_status SET last_indexed_at = ?1 WHERE id = 1",
-                params![now],
-            )?;
-        }
-
-        if let Some(error_msg) = error {
-            conn.execute(
-                "UPDATE indexing_status SET error_message = ?1 WHERE id = 1",
-                params![error_msg],
+        let now = Utc::now().timestamp();
+        for status in statuses {
+            tx.execute(
+                "INSERT INTO folder_status
+                     (account_id, folder, total_count, unread_count, recent_count, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    account_id,
+                    status.folder,
+                    status.total_count,
+                    status.unread_count,
+                    status.recent_count,
+                    now
+                ],
             )?;
         }
-
+        tx.commit()?;
         Ok(())
     }
 
-    // Get indexing status
-    pub fn get_indexing_status(&self) -> AnyhowResult<IndexingStatus> {
-        let conn = self.conn.lock().unwrap();
+    // ========== Triage ==========
+
+    /// Emails matching `filter`, newest first, leaving out ones snoozed past `now`
+    pub fn get_triage_queue(
+        &self,
+        filter: &TriageFilter,
+        now: i64,
+        limit: i64,
+    ) -> AnyhowResult<Vec<TriageItem>> {
+        let conn = self.reader();
 
         let mut stmt = conn.prepare(
-            "SELECT is_indexing, total_emails, processed_emails, last_indexed_at, error_message
-             FROM indexing_status WHERE id = 1",
+            "SELECT e.id, e.account_id, e.subject, e.from_name, e.from_email, e.date, e.snippet,
+                    e.is_read, e.is_starred, e.has_attachments, i.priority, i.category
+             FROM emails e
+             LEFT JOIN email_insights i ON e.id = i.email_id
+             WHERE e.folder = ?2
+               AND (?1 IS NULL OR e.account_id = ?1)
+               AND (?3 = 0 OR e.is_read = 0)
+               AND (?4 IS NULL OR i.category = ?4)
+               AND NOT EXISTS (
+                   SELECT 1 FROM snoozed_emails s
+                   WHERE s.email_id = e.id AND s.snoozed_until > ?5
+               )
+             ORDER BY e.date DESC
+             LIMIT ?6",
         )?;
 
-        let status = stmt.query_row([], |row| {
-            Ok(IndexingStatus {
-                is_indexing: row.get::<_, i32>(0)? != 0,
-                total_emails: row.get(1)?,
-                processed_emails: row.get(2)?,
-                last_indexed_at: row.get(3)?,
-                error_message: row.get(4)?,
-            })
-        })?;
+        let items = stmt
+            .query_map(
+                params![
+                    filter.account_id,
+                    filter.folder.as_deref().unwrap_or("INBOX"),
+                    filter.unread_only as i32,
+                    filter.category,
+                    now,
+                    limit
+                ],
+                |row| {
+                    Ok(TriageItem {
+                        id: row.get(0)?,
+                        account_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from_name: row.get(3)?,
+                        from_email: row.get(4)?,
+                        date: row.get(5)?,
+                        snippet: row.get(6)?,
+                        is_read: row.get::<_, i32>(7)? != 0,
+                        is_starred: row.get::<_, i32>(8)? != 0,
+                        has_attachments: row.get::<_, i32>(9)? != 0,
+                        priority: row.get(10)?,
+                        category: row.get(11)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(status)
+        Ok(items)
     }
 
-    /// Get all email IDs (for use by embedding pipeline)
-    pub fn get_all_email_ids(&self, limit: i64) -> AnyhowResult<Vec<String>> {
+    /// Open a triage session over `queued` emails and return its id
+    pub fn start_triage_session(&self, queued: i64, now: i64) -> AnyhowResult<i64> {
         let conn = self.conn.lock().unwrap();
-
-        let mut stmt = conn.prepare("SELECT id FROM emails ORDER BY date DESC LIMIT ?1")?;
-        let ids = stmt
-            .query_map(params![limit], |row| row.get(0))?
-            .collect::<Result<Vec<String>, _>>()?;
-
-        Ok(ids)
+        conn.execute(
+            "INSERT INTO triage_sessions (started_at, queued) VALUES (?1, ?2)",
+            params![now, queued],
+        )?;
+        Ok(conn.last_insert_rowid())
     }
 
-    // Get total count of emails
-    pub fn get_email_count(&self) -> AnyhowResult<i64> {
+    /// Count an action in a session; `action` is archived, trashed, snoozed or kept
+    pub fn record_triage_action(&self, session_id: i64, action: &str) -> AnyhowResult<()> {
+        let column = match action {
+            "archived" | "trashed" | "snoozed" | "kept" => action,
+            _ => anyhow::bail!("Unknown triage action '{}'", action),
+        };
         let conn = self.conn.lock().unwrap();
-        let count: i64 = conn.query_row("SELECT COUNT(*) FROM emails", [], |row| row.get(0))?;
-        Ok(count)
+        let updated = conn.execute(
+            &format!(
+                "UPDATE triage_sessions SET {0} = {0} + 1 WHERE id = ?1",
+                column
+            ),
+            params![session_id],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Triage session {} not found", session_id);
+        }
+        Ok(())
     }
 
-    // Get count of indexed emails
-    pub fn get_indexed_count(&self) -> AnyhowResult<i64> {
+    /// Close a session; closing it again keeps the first end time
+    pub fn end_triage_session(&self, session_id: i64, now: i64) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        let count: i64 =
-            conn.query_row("SELECT COUNT(*) FROM email_insights", [], |row| row.get(0))?;
-        Ok(count)
+        conn.execute(
+            "UPDATE triage_sessions SET ended_at = COALESCE(ended_at, ?2) WHERE id = ?1",
+            params![session_id, now],
+        )?;
+        Ok(())
     }
 
-    // Clear all emails and insights from the database
-    pub fn clear_all_emails(&self) -> AnyhowResult<()> {
-        let conn = self.conn.lock().unwrap();
-
-        // Delete all email insights first (due to foreign key)
-        conn.execute("DELETE FROM email_insights", [])?;
-
-        // Delete all emails
-        conn.execute("DELETE FROM emails", [])?;
+    pub fn get_triage_stats(&self, session_id: i64, now: i64) -> AnyhowResult<TriageStats> {
+        let conn = self.reader();
+        let stats = conn
+            .query_row(
+                "SELECT started_at, ended_at, queued, archived, trashed, snoozed, kept
+                 FROM triage_sessions WHERE id = ?1",
+                params![session_id],
+                |row| {
+                    let started_at: i64 = row.get(0)?;
+                    let ended_at: Option<i64> = row.get(1)?;
+                    let (archived, trashed, snoozed, kept): (i64, i64, i64, i64) =
+                        (row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?);
+                    Ok(TriageStats {
+                        session_id,
+                        started_at,
+                        ended_at,
+                        queued: row.get(2)?,
+                        processed: archived + trashed + snoozed + kept,
+                        archived,
+                        trashed,
+                        snoozed,
+                        kept,
+                        duration_secs: ended_at.unwrap_or(now) - started_at,
+                    })
+                },
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Triage session {} not found", session_id))?;
+        Ok(stats)
+    }
 
-        // Reset indexing status
+    /// Leave an email out of triage queues until `until`
+    pub fn snooze_email(&self, email_id: &str, account_id: &str, until: i64) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE indexing_status SET is_indexing = 0, total_emails = 0, processed_emails = 0, last_indexed_at = NULL, error_message = NULL WHERE id = 1",
-            [],
+            "INSERT OR REPLACE INTO snoozed_emails (email_id, account_id, snoozed_until, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![email_id, account_id, until, Utc::now().timestamp()],
         )?;
-
+        changes::record(Change::Updated, email_id);
         Ok(())
     }
 
-    // Get email by ID from cache
-    pub fn get_email_by_id(
+    // ========== Priority ==========
+
+    /// What the user did with a sender's mail, for scoring `email_id`
+    pub fn get_sender_history(
         &self,
+        account_id: &str,
+        from_email: &str,
         email_id: &str,
-    ) -> AnyhowResult<Option<crate::email::types::Email>> {
+    ) -> AnyhowResult<SenderHistory> {
+        let conn = self.reader();
+        Ok(sender_history(&conn, account_id, from_email, email_id)?)
+    }
+
+    /// The priority the user set on an email by hand
+    pub fn get_priority_override(&self, email_id: &str) -> AnyhowResult<Option<String>> {
+        let conn = self.reader();
+        let priority = conn
+            .query_row(
+                "SELECT priority FROM priority_overrides WHERE email_id = ?1",
+                params![email_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(priority)
+    }
+
+    /// Record the user's priority for an email and apply it to the email and its copies.
+    /// What the email looked like is kept with the override for refitting the weights.
+    pub fn set_priority_override(&self, email_id: &str, priority: &str) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, thread_id, subject, from_name, from_email, to_emails,
-                    date, snippet, body_html, body_plain, is_read, is_starred,
-                    has_attachments, labels, account_id, uid, folder, message_id
-             FROM emails WHERE id = ?1",
+        let (account_id, from_email, starred, model_score): (String, String, bool, Option<f64>) =
+            conn.query_row(
+                "SELECT e.account_id, e.from_email, e.is_starred,
+                        COALESCE(i.model_score, i.priority_score)
+                 FROM emails e
+                 LEFT JOIN email_insights i ON i.email_id = e.id
+                 WHERE e.id = ?1",
+                params![email_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("Email not found: {}", email_id))?;
+
+        let sender = sender_history(&conn, &account_id, &from_email, email_id)?;
+        let features = PriorityFeatures::new(model_score.unwrap_or(0.5), starred, &sender);
+        conn.execute(
+            "INSERT OR REPLACE INTO priority_overrides
+             (email_id, account_id, from_email, priority, features, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                email_id,
+                account_id,
+                from_email.to_lowercase(),
+                priority,
+                serde_json::to_string(&features)?,
+                Utc::now().timestamp(),
+            ],
         )?;
 
-        let email = stmt
-            .query_row([email_id], |row| {
-                let to_emails_json: String = row.get(5)?;
-                let labels_json: String = row.get(13)?;
-                let date_timestamp: i64 = row.get(6)?;
+        let mut stmt = conn.prepare(
+            "SELECT ?1 UNION
+             SELECT copy.id FROM emails e
+             JOIN emails copy ON copy.message_key = e.message_key
+             WHERE e.id = ?1",
+        )?;
+        let copies = stmt
+            .query_map(params![email_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for copy in &copies {
+            let updated = conn.execute(
+                "UPDATE email_insights SET priority = ?2, priority_score = ?3 WHERE email_id = ?1",
+                params![copy, priority, priority::label_score(priority)],
+            )?;
+            if updated > 0 {
+                changes::record(Change::Insights, copy);
+            }
+        }
+        Ok(())
+    }
 
-                Ok(crate::email::types::Email {
-                    id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to: serde_json::from_str(&to_emails_json).unwrap_or_default(),
-                    date: chrono::DateTime::from_timestamp(date_timestamp, 0)
-                        .map(|dt| dt.format("%a, %d %b %Y %H:%M:%S %z").to_string())
-                        .unwrap_or_default(),
-                    date_timestamp,
-                    snippet: row.get(7)?,
-                    body_html: row.get(8)?,
-                    body_plain: row.get(9)?,
-                    is_read: row.get::<_, i32>(10)? != 0,
-                    is_starred: row.get::<_, i32>(11)? != 0,
-                    has_attachments: row.get::<_, i32>(12)? != 0,
-                    labels: serde_json::from_str(&labels_json).unwrap_or_default(),
-                    account_id: row.get::<_, String>(14).unwrap_or_else(|_| "legacy".to_string()),
-                    uid: row.get::<_, i64>(15).unwrap_or(0) as u32,
-                    folder: row.get::<_, String>(16).unwrap_or_else(|_| "INBOX".to_string()),
-                    message_id: row.get::<_, String>(17).unwrap_or_default(),
+    /// The user's priority overrides in an account, as training data for the weights
+    pub fn get_priority_corrections(&self, account_id: &str) -> AnyhowResult<Vec<Correction>> {
+        let conn = self.reader();
+        let mut stmt = conn
+            .prepare("SELECT features, priority FROM priority_overrides WHERE account_id = ?1")?;
+        let rows = stmt
+            .query_map(params![account_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(features, priority)| {
+                Some(Correction {
+                    features: serde_json::from_str(&features).ok()?,
+                    priority,
                 })
             })
-            .optional()?;
+            .collect())
+    }
 
-        Ok(email)
+    /// Accounts with enough overrides whose weights were last fit to a different number
+    /// of them
+    pub fn get_priority_retune_accounts(&self) -> AnyhowResult<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT o.account_id
+             FROM priority_overrides o
+             LEFT JOIN priority_weights w ON w.account_id = o.account_id
+             GROUP BY o.account_id
+             HAVING COUNT(*) >= ?1 AND COUNT(*) != COALESCE(MAX(w.corrections), -1)",
+        )?;
+        let accounts = stmt
+            .query_map(params![MIN_CORRECTIONS as i64], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(accounts)
     }
 
-    // ========== Account Management ==========
+    /// Scoring weights of an account, the defaults until they were first refit
+    pub fn get_priority_weights(&self, account_id: &str) -> AnyhowResult<PriorityWeights> {
+        let conn = self.reader();
+        let weights: Option<String> = conn
+            .query_row(
+                "SELECT weights FROM priority_weights WHERE account_id = ?1",
+                params![account_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(weights
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default())
+    }
 
-    /// Store a new account
-    pub fn store_account(&self, account: &Account) -> AnyhowResult<()> {
+    /// Store weights refit from `corrections` overrides
+    pub fn save_priority_weights(
+        &self,
+        account_id: &str,
+        weights: &PriorityWeights,
+        corrections: usize,
+    ) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO accounts
-            (id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
-             auth_type, is_active, created_at, last_synced_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            "INSERT OR REPLACE INTO priority_weights (account_id, weights, corrections, tuned_at)
+             VALUES (?1, ?2, ?3, ?4)",
             params![
-                &account.id,
-                &account.email,
-                &account.display_name,
-                &account.provider,
-                &account.imap_host,
-                account.imap_port as i32,
-                &account.smtp_host,
-                account.smtp_port as i32,
-                &account.auth_type,
-                account.is_active as i32,
-                account.created_at,
-                account.last_synced_at,
+                account_id,
+                serde_json::to_string(weights)?,
+                corrections as i64,
+                Utc::now().timestamp(),
             ],
         )?;
         Ok(())
     }
 
-    /// Remove an account and all its data
-    pub fn remove_account(&self, account_id: &str) -> AnyhowResult<()> {
+    // ========== Shipments ==========
+
+    /// Tracked packages, latest news first
+    pub fn get_shipments(
+        &self,
+        account_id: Option<&str>,
+        include_delivered: bool,
+        limit: i64,
+    ) -> AnyhowResult<Vec<Shipment>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM shipments
+             WHERE (?1 IS NULL OR account_id = ?1) AND (?2 OR status != 'delivered')
+             ORDER BY status_at DESC
+             LIMIT ?3",
+            SHIPMENT_COLUMNS
+        ))?;
+        let shipments = stmt
+            .query_map(
+                params![account_id, include_delivered, limit],
+                shipment_from_row,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(shipments.into_iter().flatten().collect())
+    }
+
+    /// Undelivered packages first seen after `seen_after` whose carrier page wasn't
+    /// checked since `checked_before`, least recently checked first
+    pub fn get_shipments_to_check(
+        &self,
+        seen_after: i64,
+        checked_before: i64,
+        limit: i64,
+    ) -> AnyhowResult<Vec<Shipment>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM shipments
+             WHERE status != 'delivered' AND first_seen_at > ?1
+               AND (checked_at IS NULL OR checked_at < ?2)
+             ORDER BY COALESCE(checked_at, 0)
+             LIMIT ?3",
+            SHIPMENT_COLUMNS
+        ))?;
+        let shipments = stmt
+            .query_map(
+                params![seen_after, checked_before, limit],
+                shipment_from_row,
+            )?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(shipments.into_iter().flatten().collect())
+    }
+
+    /// Record a check of a package's carrier page, with the status it showed if any
+    pub fn set_shipment_checked(
+        &self,
+        id: i64,
+        status: Option<ShipmentStatus>,
+    ) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        // Delete insights for this account's emails
-        conn.execute(
-            "DELETE FROM email_insights WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
-            params![account_id],
-        )?;
-        // Delete embeddings for this account's emails
-        conn.execute(
-            "DELETE FROM email_embeddings WHERE email_id IN (SELECT id FROM emails WHERE account_id = ?1)",
-            params![account_id],
-        )?;
-        // Delete emails
+        let now = Utc::now().timestamp();
         conn.execute(
-            "DELETE FROM emails WHERE account_id = ?1",
-            params![account_id],
+            "UPDATE shipments SET checked_at = ?2 WHERE id = ?1",
+            params![id, now],
         )?;
-        // Delete account
-        conn.execute("DELETE FROM accounts WHERE id = ?1", params![account_id])?;
+        if let Some(status) = status {
+            conn.execute(
+                "UPDATE shipments SET status = ?2, status_at = ?3 WHERE id = ?1 AND status != ?2",
+                params![id, status.id(), now],
+            )?;
+        }
         Ok(())
     }
 
-    /// List all accounts
-    pub fn list_accounts(&self) -> AnyhowResult<Vec<Account>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
-                    auth_type, is_active, created_at, last_synced_at
-             FROM accounts ORDER BY created_at ASC",
-        )?;
-
-        let accounts = stmt
-            .query_map([], |row| {
-                Ok(Account {
-                    id: row.get(0)?,
-                    email: row.get(1)?,
-                    display_name: row.get(2)?,
-                    provider: row.get(3)?,
-                    imap_host: row.get(4)?,
-                    imap_port: row.get::<_, i32>(5)? as u16,
-                    smtp_host: row.get(6)?,
-                    smtp_port: row.get::<_, i32>(7)? as u16,
-                    auth_type: row.get(8)?,
-                    is_active: row.get::<_, i32>(9)? != 0,
-                    created_at: row.get(10)?,
-                    last_synced_at: row.get(11)?,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
-
-        Ok(accounts)
+    /// Packages out for delivery that the user hasn't been told about
+    pub fn get_unnotified_out_for_delivery(&self) -> AnyhowResult<Vec<Shipment>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM shipments
+             WHERE status = 'out_for_delivery' AND notified_at IS NULL",
+            SHIPMENT_COLUMNS
+        ))?;
+        let shipments = stmt
+            .query_map([], shipment_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(shipments.into_iter().flatten().collect())
     }
 
-    /// Get a single account by ID
-    pub fn get_account(&self, account_id: &str) -> AnyhowResult<Option<Account>> {
+    pub fn set_shipment_notified(&self, id: i64) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
-                    auth_type, is_active, created_at, last_synced_at
-             FROM accounts WHERE id = ?1",
+        conn.execute(
+            "UPDATE shipments SET notified_at = ?2 WHERE id = ?1",
+            params![id, Utc::now().timestamp()],
         )?;
+        Ok(())
+    }
 
-        let account = stmt
-            .query_row([account_id], |row| {
-                Ok(Account {
+    // ========== Pinning and Inbox Sections ==========
+
+    /// Pin an email to the top of the smart inbox, or unpin it
+    pub fn set_email_pinned(&self, email_id: &str, pinned: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        if !pinned {
+            conn.execute(
+                "DELETE FROM pinned_emails WHERE email_id = ?1",
+                params![email_id],
+            )?;
+            changes::record(Change::Updated, email_id);
+            return Ok(());
+        }
+        let inserted = conn.execute(
+            "INSERT OR REPLACE INTO pinned_emails (email_id, account_id, pinned_at)
+             SELECT id, account_id, ?2 FROM emails WHERE id = ?1",
+            params![email_id, Utc::now().timestamp()],
+        )?;
+        if inserted == 0 {
+            anyhow::bail!("Email not found: {}", email_id);
+        }
+        changes::record(Change::Updated, email_id);
+        Ok(())
+    }
+
+    /// Custom inbox sections in display order
+    pub fn get_inbox_sections(&self) -> AnyhowResult<Vec<InboxSection>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, filter, position FROM inbox_sections ORDER BY position, id",
+        )?;
+        let sections = stmt
+            .query_map([], |row| {
+                Ok(InboxSection {
                     id: row.get(0)?,
-                    email: row.get(1)?,
-                    display_name: row.get(2)?,
-                    provider: row.get(3)?,
-                    imap_host: row.get(4)?,
-                    imap_port: row.get::<_, i32>(5)? as u16,
-                    smtp_host: row.get(6)?,
-                    smtp_port: row.get::<_, i32>(7)? as u16,
-                    auth_type: row.get(8)?,
-                    is_active: row.get::<_, i32>(9)? != 0,
-                    created_at: row.get(10)?,
-                    last_synced_at: row.get(11)?,
+                    name: row.get(1)?,
+                    filter: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or_default(),
+                    position: row.get(3)?,
                 })
-            })
-            .optional()?;
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(sections)
+    }
 
-        Ok(account)
+    pub fn get_inbox_section(&self, id: i64) -> AnyhowResult<Option<InboxSection>> {
+        Ok(self.get_inbox_sections()?.into_iter().find(|s| s.id == id))
     }
 
-    /// Set active account (deactivate all others, activate specified)
-    pub fn set_active_account(&self, account_id: &str) -> AnyhowResult<()> {
+    /// Add a section after the existing ones
+    pub fn create_inbox_section(
+        &self,
+        name: &str,
+        filter: &InboxSectionFilter,
+    ) -> AnyhowResult<InboxSection> {
         let conn = self.conn.lock().unwrap();
-        conn.execute("UPDATE accounts SET is_active = 0", [])?;
+        let position: i64 = conn.query_row(
+            "SELECT COALESCE(MAX(position), -1) + 1 FROM inbox_sections",
+            [],
+            |row| row.get(0),
+        )?;
         conn.execute(
-            "UPDATE accounts SET is_active = 1 WHERE id = ?1",
-            params![account_id],
+            "INSERT INTO inbox_sections (name, filter, position, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, serde_json::to_string(filter)?, position, Utc::now().timestamp()],
         )?;
-        Ok(())
+        Ok(InboxSection {
+            id: conn.last_insert_rowid(),
+            name: name.to_string(),
+            filter: filter.clone(),
+            position,
+        })
     }
 
-    /// Get the active account
-    pub fn get_active_account(&self) -> AnyhowResult<Option<Account>> {
+    pub fn update_inbox_section(
+        &self,
+        id: i64,
+        name: &str,
+        filter: &InboxSectionFilter,
+    ) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, email, display_name, provider, imap_host, imap_port, smtp_host, smtp_port,
-                    auth_type, is_active, created_at, last_synced_at
-             FROM accounts WHERE is_active = 1 LIMIT 1",
+        let updated = conn.execute(
+            "UPDATE inbox_sections SET name = ?2, filter = ?3 WHERE id = ?1",
+            params![id, name, serde_json::to_string(filter)?],
         )?;
+        if updated == 0 {
+            anyhow::bail!("Inbox section {} not found", id);
+        }
+        Ok(())
+    }
 
-        let account = stmt
-            .query_row([], |row| {
-                Ok(Account {
-                    id: row.get(0)?,
-                    email: row.get(1)?,
-                    display_name: row.get(2)?,
-                    provider: row.get(3)?,
-                    imap_host: row.get(4)?,
-                    imap_port: row.get::<_, i32>(5)? as u16,
-                    smtp_host: row.get(6)?,
-                    smtp_port: row.get::<_, i32>(7)? as u16,
-                    auth_type: row.get(8)?,
-                    is_active: row.get::<_, i32>(9)? != 0,
-                    created_at: row.get(10)?,
-                    last_synced_at: row.get(11)?,
-                })
-            })
-            .optional()?;
+    pub fn delete_inbox_section(&self, id: i64) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM inbox_sections WHERE id = ?1", params![id])?;
+        Ok(())
+    }
 
-        Ok(account)
+    /// Put sections in the order of `ids`; sections left out keep their place after them
+    pub fn reorder_inbox_sections(&self, ids: &[i64]) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE inbox_sections SET position = position + ?1",
+            params![ids.len() as i64],
+        )?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE inbox_sections SET position = ?2 WHERE id = ?1",
+                params![id, position as i64],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
     }
 
-    // Get important emails by account (HIGH priority or starred)
-    pub fn get_important_emails_by_account(
+    /// Emails matching a section's filter, pinned ones first, then newest first
+    pub fn get_inbox_section_emails(
         &self,
-        account_id: &str,
+        filter: &InboxSectionFilter,
         limit: i64,
+        offset: i64,
     ) -> AnyhowResult<Vec<EmailWithInsight>> {
-        let conn = self.conn.lock().unwrap();
+        let conn = self.reader();
 
-        let mut stmt = conn.prepare(
+        let mut stmt = conn.prepare(&format!(
             "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
                     e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
                     COALESCE(i.priority, 'MEDIUM') as priority,
                     COALESCE(i.priority_score, 0.5) as priority_score,
-                    i.category, i.summary
+                    i.category, i.summary, i.sentiment, i.language,
+                    EXISTS (SELECT 1 FROM pinned_emails p WHERE p.email_id = e.id) AS pinned
              FROM emails e
              LEFT JOIN email_insights i ON e.id = i.email_id
-             WHERE e.account_id = ?1 AND (i.priority = 'HIGH' OR e.is_starred = 1)
-             ORDER BY COALESCE(i.priority_score, 0.5) DESC, e.date DESC
-             LIMIT ?2",
-        )?;
+             WHERE (?1 IS NULL OR e.account_id = ?1)
+               AND (?2 IS NULL OR e.folder = ?2)
+               AND (?3 IS NULL OR i.category = ?3)
+               AND (?4 IS NULL OR COALESCE(i.priority, 'MEDIUM') = ?4)
+               AND (?5 IS NULL OR e.from_email LIKE ?5 OR e.from_name LIKE ?5)
+               AND (?6 = 0 OR e.is_read = 0)
+               AND (?7 = 0 OR e.is_starred = 1)
+               AND (?8 = 0 OR pinned)
+               AND {}
+             ORDER BY pinned DESC, e.date DESC
+             LIMIT ?9 OFFSET ?10",
+            collapse_condition()
+        ))?;
 
+        let from = filter.from.as_ref().map(|from| format!("%{}%", from));
         let emails = stmt
-            .query_map(params![account_id, limit], |row| {
-                Ok(EmailWithInsight {
-                    id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from_name: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
-                    date: row.get(6)?,
-                    snippet: row.get(7)?,
-                    is_read: row.get::<_, i32>(8)? != 0,
-                    is_starred: row.get::<_, i32>(9)? != 0,
-                    has_attachments: row.get::<_, i32>(10)? != 0,
-                    priority: row.get(11)?,
-                    priority_score: row.get(12)?,
-                    category: row.get(13)?,
-                    summary: row.get(14)?,
-                })
-            })?
+            .query_map(
+                params![
+                    filter.account_id,
+                    filter.folder,
+                    filter.category,
+                    filter.priority,
+                    from,
+                    filter.unread_only as i32,
+                    filter.starred_only as i32,
+                    filter.pinned_only as i32,
+                    limit,
+                    offset
+                ],
+                |row| {
+                    Ok(EmailWithInsight {
+                        id: row.get(0)?,
+                        thread_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from_name: row.get(3)?,
+                        from_email: row.get(4)?,
+                        to_emails: serde_json::from_str(&row.get::<_, String>(5)?)
+                            .unwrap_or_default(),
+                        date: row.get(6)?,
+                        snippet: row.get(7)?,
+                        is_read: row.get::<_, i32>(8)? != 0,
+                        is_starred: row.get::<_, i32>(9)? != 0,
+                        has_attachments: row.get::<_, i32>(10)? != 0,
+                        priority: row.get(11)?,
+                        priority_score: row.get(12)?,
+                        category: row.get(13)?,
+                        summary: row.get(14)?,
+                        sentiment: row.get(15)?,
+                        language: row.get(16)?,
+                        pinned: row.get::<_, i32>(17)? != 0,
+                    })
+                },
+            )?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(emails)
     }
 
-    // Get emails by account and category, excluding important emails
-    pub fn get_emails_by_account_and_category(
+    // ========== Saved Searches ==========
+
+    /// Save a search under `name`, replacing the one saved under that name before
+    pub fn save_search(
         &self,
-        account_id: &str,
-        category: &str,
-        limit: i64,
-    ) -> AnyhowResult<Vec<EmailWithInsight>> {
+        name: &str,
+        query: &str,
+        filters: &SavedSearchFilters,
+    ) -> AnyhowResult<SavedSearch> {
         let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        let (id, created_at) = conn.query_row(
+            "INSERT INTO saved_searches (name, query, filters, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET query = excluded.query, filters = excluded.filters
+             RETURNING id, created_at",
+            params![name, query, serde_json::to_string(filters)?, now],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(SavedSearch {
+            id,
+            name: name.to_string(),
+            query: query.to_string(),
+            filters: filters.clone(),
+            created_at,
+            unread_count: None,
+        })
+    }
 
+    /// Saved searches by name
+    pub fn list_saved_searches(&self) -> AnyhowResult<Vec<SavedSearch>> {
+        let conn = self.reader();
         let mut stmt = conn.prepare(
-            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
-                    e.date, e.snippet, e.is_read, e.is_starred, e.has_attachments,
-                    i.priority, i.priority_score, i.category, i.summary
-             FROM emails e
-             INNER JOIN email_insights i ON e.id = i.email_id
-             WHERE e.account_id = ?1 AND i.category = ?2
-                   AND i.priority != 'HIGH' AND e.is_starred = 0
-             ORDER BY i.priority_score DESC, e.date DESC
-             LIMIT ?3",
+            "SELECT id, name, query, filters, created_at FROM saved_searches
+             ORDER BY name COLLATE NOCASE",
         )?;
-
-        let emails = stmt
-            .query_map(params![account_id, category, limit], |row| {
-                Ok(EmailWithInsight {
+        let searches = stmt
+            .query_map([], |row| {
+                Ok(SavedSearch {
                     id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from_name: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to_emails: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
-                    date: row.get(6)?,
-                    snippet: row.get(7)?,
-                    is_read: row.get::<_, i32>(8)? != 0,
-                    is_starred: row.get::<_, i32>(9)? != 0,
-                    has_attachments: row.get::<_, i32>(10)? != 0,
-                    priority: row.get(11)?,
-                    priority_score: row.get(12)?,
-                    category: row.get(13)?,
-                    summary: row.get(14)?,
+                    name: row.get(1)?,
+                    query: row.get(2)?,
+                    filters: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+                    created_at: row.get(4)?,
+                    unread_count: None,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
+        Ok(searches)
+    }
 
-        Ok(emails)
+    pub fn get_saved_search(&self, id: i64) -> AnyhowResult<Option<SavedSearch>> {
+        Ok(self.list_saved_searches()?.into_iter().find(|s| s.id == id))
     }
 
-    /// Get emails that haven't been indexed yet (no entry in email_insights)
-    pub fn get_unindexed_emails(&self, limit: i64) -> AnyhowResult<Vec<crate::email::types::Email>> {
+    pub fn delete_saved_search(&self, id: i64) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM saved_searches WHERE id = ?1", params![id])?;
+        Ok(())
+    }
 
-        let mut stmt = conn.prepare(
-            "SELECT e.id, e.thread_id, e.subject, e.from_name, e.from_email, e.to_emails,
-                    e.date, e.snippet, e.body_html, e.body_plain, e.is_read, e.is_starred,
-                    e.has_attachments, e.labels, e.account_id, e.uid, e.folder, e.message_id
-             FROM emails e
-             LEFT JOIN email_insights i ON e.id = i.email_id
-             WHERE i.email_id IS NULL
-             ORDER BY e.date DESC
-             LIMIT ?1",
+    // ========== Chat Sessions ==========
+
+    pub fn create_chat_session(&self, id: &str, title: &str) -> AnyhowResult<ChatSession> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO chat_sessions (id, title, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![id, title, now],
         )?;
 
-        let emails = stmt
-            .query_map(params![limit], |row| {
-                let to_emails_json: String = row.get(5)?;
-                let labels_json: String = row.get(13)?;
-                let date_timestamp: i64 = row.get(6)?;
+        Ok(ChatSession {
+            id: id.to_string(),
+            title: title.to_string(),
+            created_at: now,
+            updated_at: now,
+            message_count: 0,
+        })
+    }
+
+    /// Chat sessions, most recently active first
+    pub fn list_chat_sessions(&self) -> AnyhowResult<Vec<ChatSession>> {
+        let conn = self.reader();
 
-                Ok(crate::email::types::Email {
+        let mut stmt = conn.prepare(
+            "SELECT s.id, s.title, s.created_at, s.updated_at, COUNT(m.id)
+             FROM chat_sessions s
+             LEFT JOIN chat_messages m ON m.session_id = s.id
+             GROUP BY s.id
+             ORDER BY s.updated_at DESC",
+        )?;
+
+        let sessions = stmt
+            .query_map([], |row| {
+                Ok(ChatSession {
                     id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from: row.get(3)?,
-                    from_email: row.get(4)?,
-                    to: serde_json::from_str(&to_emails_json).unwrap_or_default(),
-                    date: chrono::DateTime::from_timestamp(date_timestamp, 0)
-                        .map(|dt| dt.format("%a, %d %b %Y %H:%M:%S %z").to_string())
-                        .unwrap_or_default(),
-                    date_timestamp,
-                    snippet: row.get(7)?,
-                    body_html: row.get(8)?,
-                    body_plain: row.get(9)?,
-                    is_read: row.get::<_, i32>(10)? != 0,
-                    is_starred: row.get::<_, i32>(11)? != 0,
-                    has_attachments: row.get::<_, i32>(12)? != 0,
-                    labels: serde_json::from_str(&labels_json).unwrap_or_default(),
-                    account_id: row.get::<_, String>(14).unwrap_or_else(|_| "legacy".to_string()),
-                    uid: row.get::<_, i64>(15).unwrap_or(0) as u32,
-                    folder: row.get::<_, String>(16).unwrap_or_else(|_| "INBOX".to_string()),
-                    message_id: row.get::<_, String>(17).unwrap_or_default(),
+                    title: row.get(1)?,
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                    message_count: row.get(4)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(emails)
+        Ok(sessions)
     }
 
-    // Run category migration to remap old categories to new buckets
-    pub fn migrate_categories(&self) -> AnyhowResult<()> {
+    pub fn rename_chat_session(&self, id: &str, title: &str) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "UPDATE email_insights SET category = 'subscriptions' WHERE category IN ('notifications', 'financial')",
-            [],
-        )?;
-        conn.execute(
-            "UPDATE email_insights SET category = 'general' WHERE category IN ('conversation', 'meetings')",
-            [],
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET title = ?2 WHERE id = ?1",
+            params![id, title],
         )?;
+        if updated == 0 {
+            anyhow::bail!("Chat session '{}' not found", id);
+        }
         Ok(())
     }
 
-    // Get all cached emails as EmailListItem for a specific folder
-    pub fn get_cached_emails(
-        &self,
-        folder: &str,
-        limit: i64,
-    ) -> AnyhowResult<Vec<crate::email::types::EmailListItem>> {
+    pub fn delete_chat_session(&self, id: &str) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM chat_messages WHERE session_id = ?1", params![id])?;
+        conn.execute("DELETE FROM chat_sessions WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Messages of a session in conversation order
+    pub fn get_chat_messages(&self, session_id: &str) -> AnyhowResult<Vec<ChatMessage>> {
+        let conn = self.reader();
 
         let mut stmt = conn.prepare(
-            "SELECT id, thread_id, subject, from_name, from_email, date, snippet,
-                    is_read, is_starred, has_attachments
-             FROM emails 
-             WHERE folder = ?1
-             ORDER BY date DESC LIMIT ?2",
+            "SELECT id, session_id, role, content, created_at
+             FROM chat_messages
+             WHERE session_id = ?1
+             ORDER BY id ASC",
         )?;
 
-        let emails = stmt
-            .query_map(params![folder, limit], |row| {
-                let date_timestamp: i64 = row.get(5)?;
-
-                Ok(crate::email::types::EmailListItem {
+        let messages = stmt
+            .query_map(params![session_id], |row| {
+                Ok(ChatMessage {
                     id: row.get(0)?,
-                    thread_id: row.get(1)?,
-                    subject: row.get(2)?,
-                    from: row.get(3)?,
-                    from_email: row.get(4)?,
-                    date: chrono::DateTime::from_timestamp(date_timestamp, 0)
-                        .map(|dt| dt.format("%a, %d %b %Y %H:%M:%S %z").to_string())
-                        .unwrap_or_default(),
-                    snippet: row.get(6)?,
-                    is_read: row.get::<_, i32>(7)? != 0,
-                    is_starred: row.get::<_, i32>(8)? != 0,
-                    has_attachments: row.get::<_, i32>(9)? != 0,
+                    session_id: row.get(1)?,
+                    role: row.get(2)?,
+                    content: row.get(3)?,
+                    created_at: row.get(4)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(emails)
+        Ok(messages)
+    }
+
+    /// Append a message to a session and bump its activity time
+    pub fn add_chat_message(&self, session_id: &str, role: &str, content: &str) -> AnyhowResult<ChatMessage> {
+        let conn = self.conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+
+        let updated = conn.execute(
+            "UPDATE chat_sessions SET updated_at = ?2 WHERE id = ?1",
+            params![session_id, now],
+        )?;
+        if updated == 0 {
+            anyhow::bail!("Chat session '{}' not found", session_id);
+        }
+
+        conn.execute(
+            "INSERT INTO chat_messages (session_id, role, content, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, role, content, now],
+        )?;
+
+        Ok(ChatMessage {
+            id: conn.last_insert_rowid(),
+            session_id: session_id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: now,
+        })
+    }
+
+    // ========== Backup ==========
+
+    /// Snapshot the database into a new file at `path` while it stays in use
+    pub fn backup_to(&self, path: &Path, progress: impl FnMut(i64, i64)) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let mut target = Connection::open(path)
+            .with_context(|| format!("Failed to create snapshot at {:?}", path))?;
+        copy_database(&conn, &mut target, progress)
+    }
+
+    /// Replace the contents of the database with the snapshot at `path`, then bring its
+    /// schema up to date in case the snapshot came from an older version
+    pub fn restore_from(&self, path: &Path, progress: impl FnMut(i64, i64)) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open snapshot at {:?}", path))?;
+        copy_database(&source, &mut conn, progress)?;
+        create_tables(&conn).context("Failed to migrate restored database")?;
+        Ok(())
+    }
+}
+
+/// WHERE clause (empty when nothing narrows the search) over `emails e` joined with
+/// `email_insights i`, with placeholders numbered from `first_param`
+fn search_where_clause(
+    query: &SearchQuery,
+    filters: &SavedSearchFilters,
+    first_param: usize,
+) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values = Vec::new();
+    let mut push = |condition: &str, value: Value| {
+        let n = first_param + values.len();
+        conditions.push(condition.replace("{n}", &n.to_string()));
+        values.push(value);
+    };
+    if !query.text.is_empty() {
+        push(
+            "(e.subject LIKE ?{n} OR e.from_name LIKE ?{n} OR e.snippet LIKE ?{n}
+              OR COALESCE(i.summary, '') LIKE ?{n}
+              OR EXISTS (SELECT 1 FROM attachments a
+                         WHERE a.email_id = e.id AND a.ocr_text LIKE ?{n}))",
+            Value::Text(format!("%{}%", query.text)),
+        );
+    }
+    if let Some(account_id) = &filters.account_id {
+        push("e.account_id = ?{n}", Value::Text(account_id.clone()));
+    }
+    if let Some(category) = &filters.category {
+        push("i.category = ?{n}", Value::Text(category.clone()));
+    }
+    if let Some(priority) = &filters.priority {
+        push(
+            "COALESCE(i.priority, 'MEDIUM') = ?{n}",
+            Value::Text(priority.clone()),
+        );
+    }
+
+    let (operator_conditions, operator_values) = query.sql_conditions(first_param + values.len());
+    conditions.extend(operator_conditions);
+    values.extend(operator_values.into_iter().map(sql_value));
+    conditions.push(collapse_condition());
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, values)
+}
+
+fn sql_value(value: SqlValue) -> Value {
+    match value {
+        SqlValue::Text(text) => Value::Text(text),
+        SqlValue::Integer(n) => Value::Integer(n),
     }
 }
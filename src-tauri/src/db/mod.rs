@@ -1,5 +1,11 @@
+pub mod ann_index;
+pub mod backup;
+pub mod changes;
+pub mod dedupe;
 pub mod email_db;
+pub mod query_understanding;
 pub mod schema;
+pub mod search_query;
 pub mod vector_db;
 
 pub use email_db::EmailDatabase;
@@ -0,0 +1,573 @@
+//! Metadata constraints in chat questions
+//!
+//! Semantic retrieval alone ranks emails by topic, so "what did Alice send last week"
+//! brings back whatever Alice-like mail is closest, however old. Before retrieval,
+//! `understand` picks out what a question says about the emails it is after — a
+//! sender ("from Alice", "did Alice send"), a date range ("yesterday", "last week",
+//! "in March", "past 3 days"), a folder ("in my sent folder") or an account ("my work
+//! account") — as `SearchQuery` filters, alongside any Gmail-style operators it
+//! contains. Retrieval then only ranks emails matching them.
+//!
+//! The rules are deliberately conservative: a phrase they don't recognise adds no
+//! filter, since a wrong filter hides the emails the user is asking about.
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use super::search_query::{SearchFilter, SearchQuery};
+use crate::email::dates::to_timestamp;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("monday", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+];
+
+/// Words that can stand where a sender's name would but never are one
+const NOT_SENDERS: &[&str] = &[
+    "i",
+    "me",
+    "my",
+    "mine",
+    "we",
+    "us",
+    "our",
+    "you",
+    "your",
+    "he",
+    "she",
+    "they",
+    "them",
+    "it",
+    "the",
+    "a",
+    "an",
+    "any",
+    "anyone",
+    "anybody",
+    "someone",
+    "somebody",
+    "everyone",
+    "all",
+    "some",
+    "this",
+    "that",
+    "these",
+    "those",
+    "last",
+    "past",
+    "next",
+    "recent",
+    "today",
+    "yesterday",
+    "tomorrow",
+    "week",
+    "month",
+    "year",
+    "what",
+    "who",
+    "whom",
+    "when",
+    "where",
+    "which",
+    "why",
+    "how",
+    "did",
+    "does",
+    "do",
+    "has",
+    "have",
+    "had",
+    "was",
+    "were",
+    "is",
+    "are",
+    "email",
+    "emails",
+    "mail",
+    "mails",
+    "message",
+    "messages",
+    "inbox",
+    "work",
+    "home",
+    "there",
+    "here",
+    "people",
+    "him",
+    "her",
+    "his",
+];
+
+/// Verbs that follow the sender in "did Alice send", "Bob wrote"
+const SENDING_VERBS: &[&str] = &[
+    "send",
+    "sent",
+    "sends",
+    "write",
+    "wrote",
+    "writes",
+    "written",
+    "email",
+    "emailed",
+    "mail",
+    "mailed",
+    "forward",
+    "forwarded",
+    "reply",
+    "replied",
+    "say",
+    "said",
+];
+
+/// Folder words and the folder name they filter on
+const FOLDERS: &[(&str, &str)] = &[
+    ("inbox", "INBOX"),
+    ("sent", "Sent"),
+    ("outbox", "Sent"),
+    ("draft", "Drafts"),
+    ("drafts", "Drafts"),
+    ("archive", "Archive"),
+    ("archived", "Archive"),
+    ("spam", "Spam"),
+    ("junk", "Junk"),
+    ("trash", "Trash"),
+    ("deleted", "Trash"),
+    ("bin", "Trash"),
+];
+
+/// Filters for what `question` says about the emails it asks about, relative to local
+/// time `now`. `accounts` pairs account ids with names the user may call them by
+/// (address, label, display name); an account is only picked when one matches. The
+/// question itself, minus any operators, stays the text of the query.
+pub fn understand(question: &str, now: NaiveDateTime, accounts: &[(&str, &str)]) -> SearchQuery {
+    let mut query = SearchQuery::parse(question);
+    let words = words(&query.text);
+    let lower: Vec<String> = words.iter().map(|w| w.to_lowercase()).collect();
+    let has = |query: &SearchQuery, f: fn(&SearchFilter) -> bool| query.filters.iter().any(f);
+
+    if !has(&query, |f| {
+        matches!(f, SearchFilter::Before(_) | SearchFilter::After(_))
+    }) {
+        if let Some((after, before)) = date_range(&lower, now.date()) {
+            query.filters.extend(after.map(SearchFilter::After));
+            query.filters.extend(before.map(SearchFilter::Before));
+        }
+    }
+    if !has(&query, |f| matches!(f, SearchFilter::From(_))) {
+        if let Some(sender) = sender(&words, &lower) {
+            query.filters.push(SearchFilter::From(sender));
+        }
+    }
+    if !has(&query, |f| matches!(f, SearchFilter::Folder(_))) {
+        if let Some(folder) = folder(&lower) {
+            query.filters.push(SearchFilter::Folder(folder.to_string()));
+        }
+    }
+    if !has(&query, |f| matches!(f, SearchFilter::Account(_))) {
+        if let Some(account_id) = account(&lower, accounts) {
+            query.filters.push(SearchFilter::Account(account_id));
+        }
+    }
+    query
+}
+
+/// Words of the text with surrounding punctuation removed; addresses are kept whole
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '@'))
+        .filter(|w| !w.is_empty())
+        .map(|w| w.replace('\u{2019}', "'"))
+        .collect()
+}
+
+/// `(after, before)` timestamps of the first date phrase in the words
+fn date_range(lower: &[String], today: NaiveDate) -> Option<(Option<i64>, Option<i64>)> {
+    // "yesterday's", "last week's"
+    let word = |i: usize| {
+        let word = lower.get(i).map(String::as_str).unwrap_or("");
+        word.strip_suffix("'s").unwrap_or(word)
+    };
+    let start = |date: NaiveDate| to_timestamp(date.and_time(NaiveTime::MIN));
+    let range =
+        |from: NaiveDate, until: Option<NaiveDate>| Some((start(from), until.and_then(start)));
+    let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let month_start = today.with_day(1)?;
+    let year_start = NaiveDate::from_ymd_opt(today.year(), 1, 1)?;
+
+    for i in 0..lower.len() {
+        match (word(i), word(i + 1)) {
+            ("today", _) => return range(today, None),
+            ("yesterday", _) => return range(today - Duration::days(1), Some(today)),
+            ("this", "week") => return range(monday, None),
+            ("last", "week") => return range(monday - Duration::days(7), Some(monday)),
+            ("this", "month") => return range(month_start, None),
+            ("last", "month") => {
+                return range(
+                    month_start.checked_sub_months(Months::new(1))?,
+                    Some(month_start),
+                )
+            }
+            ("this", "year") => return range(year_start, None),
+            ("last", "year") => {
+                return range(
+                    NaiveDate::from_ymd_opt(today.year() - 1, 1, 1)?,
+                    Some(year_start),
+                )
+            }
+            ("past", "week") => return range(today - Duration::days(7), None),
+            ("past", "month") => return range(today.checked_sub_months(Months::new(1))?, None),
+            ("past" | "last", n) => {
+                if let Some(n) = number(n) {
+                    let from = match word(i + 2) {
+                        "day" | "days" => today - Duration::days(n),
+                        "week" | "weeks" => today - Duration::weeks(n),
+                        "month" | "months" => today.checked_sub_months(Months::new(n as u32))?,
+                        _ => continue,
+                    };
+                    return range(from, None);
+                }
+                if let Some(day) = weekday(n) {
+                    let date = previous_weekday(today, day);
+                    return range(date, Some(date + Duration::days(1)));
+                }
+            }
+            (preposition @ ("in" | "since" | "during"), name) => {
+                if let Some(month) = MONTHS.iter().position(|m| *m == name) {
+                    let first = latest_month_start(today, month as u32 + 1)?;
+                    let end = first.checked_add_months(Months::new(1))?;
+                    let end = (preposition != "since").then_some(end);
+                    return range(first, end);
+                }
+                if preposition == "since" {
+                    if let Some(day) = weekday(name) {
+                        return range(previous_weekday(today, day), None);
+                    }
+                }
+            }
+            ("on", name) => {
+                if let Some(day) = weekday(name) {
+                    let date = previous_weekday(today, day);
+                    return range(date, Some(date + Duration::days(1)));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn number(word: &str) -> Option<i64> {
+    const WORDS: [&str; 10] = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    let n = match WORDS.iter().position(|w| *w == word) {
+        Some(i) => i as i64 + 1,
+        None => word.parse().ok()?,
+    };
+    (1..=366).contains(&n).then_some(n)
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    WEEKDAYS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, day)| *day)
+}
+
+/// Most recent `day` before `today`
+fn previous_weekday(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let back = (7 + today.weekday().num_days_from_monday() - day.num_days_from_monday()) % 7;
+    today - Duration::days(if back == 0 { 7 } else { back as i64 })
+}
+
+/// First day of the most recent `month` that has started, this year or last
+fn latest_month_start(today: NaiveDate, month: u32) -> Option<NaiveDate> {
+    let year = if month <= today.month() {
+        today.year()
+    } else {
+        today.year() - 1
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+}
+
+/// Sender named in "from Alice", "by alice@example.com", "did Alice send", "Alice's emails"
+fn sender(words: &[String], lower: &[String]) -> Option<String> {
+    let is_name = |i: usize| {
+        words.get(i).is_some_and(|w| {
+            w.contains('@')
+                || (w
+                    .chars()
+                    .all(|c| c.is_alphabetic() || c == '-' || c == '\'')
+                    && !NOT_SENDERS.contains(&lower[i].as_str())
+                    && !MONTHS.contains(&lower[i].as_str())
+                    && weekday(&lower[i]).is_none())
+        })
+    };
+    let capitalized = |i: usize| {
+        words[i].contains('@') || words[i].chars().next().is_some_and(char::is_uppercase)
+    };
+    // A following capitalized word is the rest of the name: "from Alice Smith"
+    let full_name = |i: usize| {
+        if !words[i].contains('@') && is_name(i + 1) && capitalized(i + 1) && capitalized(i) {
+            format!("{} {}", words[i], words[i + 1])
+        } else {
+            words[i].clone()
+        }
+    };
+
+    for i in 0..words.len() {
+        let next = lower.get(i + 1).map(String::as_str);
+        // "from alice" is clear enough in lowercase; "by" needs a capital ("by Friday")
+        if lower[i] == "from" && is_name(i + 1) {
+            return Some(full_name(i + 1));
+        }
+        if lower[i] == "by" && is_name(i + 1) && capitalized(i + 1) {
+            return Some(full_name(i + 1));
+        }
+        if let Some(name) = lower[i].strip_suffix("'s") {
+            let is_mail = matches!(
+                next,
+                Some("email" | "emails" | "mail" | "message" | "messages")
+            );
+            if is_mail && !NOT_SENDERS.contains(&name) && capitalized(i) {
+                return words[i].strip_suffix("'s").map(str::to_string);
+            }
+        }
+        if next.is_some_and(|verb| SENDING_VERBS.contains(&verb)) && is_name(i) {
+            // Lowercase names only after an auxiliary: "what did alice send"
+            let after_auxiliary =
+                i > 0 && matches!(lower[i - 1].as_str(), "did" | "has" | "have" | "had");
+            if capitalized(i) || after_auxiliary {
+                // "did Alice Smith send": the name starts a word earlier
+                if i > 0 && is_name(i - 1) && capitalized(i - 1) && capitalized(i) {
+                    return Some(format!("{} {}", words[i - 1], words[i]));
+                }
+                return Some(words[i].clone());
+            }
+        }
+    }
+    None
+}
+
+/// Folder named in "in my sent folder", "from spam", "the trash folder", "what I sent"
+fn folder(lower: &[String]) -> Option<&'static str> {
+    let word = |i: usize| lower.get(i).map(String::as_str).unwrap_or("");
+    for i in 0..lower.len() {
+        if let Some((_, name)) = FOLDERS.iter().find(|(w, _)| *w == word(i)) {
+            let mut before = i;
+            while before > 0 && matches!(word(before - 1), "my" | "the" | "our") {
+                before -= 1;
+            }
+            let introduced = before > 0 && matches!(word(before - 1), "in" | "from");
+            if introduced || matches!(word(i + 1), "folder" | "box" | "mailbox") {
+                return Some(name);
+            }
+        }
+        if word(i) == "i" && matches!(word(i + 1), "sent" | "send" | "wrote" | "write") {
+            return Some("Sent");
+        }
+    }
+    None
+}
+
+/// Id of the one account named in the question, by address or as "<name> account"
+fn account(lower: &[String], accounts: &[(&str, &str)]) -> Option<String> {
+    let text = lower.join(" ");
+    let mut matched: Vec<&str> = accounts
+        .iter()
+        .filter(|(_, name)| {
+            let name = name.trim().to_lowercase();
+            if name.is_empty() {
+                return false;
+            }
+            if name.contains('@') {
+                return lower.contains(&name);
+            }
+            ["account", "inbox", "address", "mailbox"]
+                .iter()
+                .any(|noun| contains_phrase(&text, &format!("{} {}", name, noun)))
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    matched.dedup();
+    match matched.as_slice() {
+        [id] => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `phrase` appears in `text` on word boundaries
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday, 14 October 2026, 10:00
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    fn day(m: u32, d: u32) -> i64 {
+        let year = if m > 10 { 2025 } else { 2026 };
+        to_timestamp(
+            NaiveDate::from_ymd_opt(year, m, d)
+                .unwrap()
+                .and_time(NaiveTime::MIN),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sender_and_last_week() {
+        let query = understand("What did Alice send last week?", now(), &[]);
+        assert_eq!(query.text, "What did Alice send last week?");
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::After(day(10, 5)),
+                SearchFilter::Before(day(10, 12)),
+                SearchFilter::From("Alice".to_string()),
+            ]
+        );
+
+        let query = understand("what did alice send about the budget", now(), &[]);
+        assert_eq!(query.filters, vec![SearchFilter::From("alice".to_string())]);
+    }
+
+    #[test]
+    fn test_sender_phrasings() {
+        let sender = |q: &str| {
+            understand(q, now(), &[])
+                .filters
+                .into_iter()
+                .find_map(|f| match f {
+                    SearchFilter::From(name) => Some(name),
+                    _ => None,
+                })
+        };
+        assert_eq!(
+            sender("emails from Alice Smith about rent").as_deref(),
+            Some("Alice Smith")
+        );
+        assert_eq!(
+            sender("anything from bob@example.com?").as_deref(),
+            Some("bob@example.com")
+        );
+        assert_eq!(sender("Summarize Carol's emails").as_deref(), Some("Carol"));
+        assert_eq!(sender("did Dan Brown reply").as_deref(), Some("Dan Brown"));
+        assert_eq!(sender("invoices from the bank"), None);
+        assert_eq!(sender("what is due by Friday"), None);
+        assert_eq!(sender("emails from last week"), None);
+        assert_eq!(sender("what did you send"), None);
+    }
+
+    #[test]
+    fn test_date_phrases() {
+        let range = |q: &str| {
+            let filters = understand(q, now(), &[]).filters;
+            let after = filters.iter().find_map(|f| match f {
+                SearchFilter::After(t) => Some(*t),
+                _ => None,
+            });
+            let before = filters.iter().find_map(|f| match f {
+                SearchFilter::Before(t) => Some(*t),
+                _ => None,
+            });
+            (after, before)
+        };
+        assert_eq!(range("anything new today"), (Some(day(10, 14)), None));
+        assert_eq!(
+            range("yesterday's invoices"),
+            (Some(day(10, 13)), Some(day(10, 14)))
+        );
+        assert_eq!(range("mail this month"), (Some(day(10, 1)), None));
+        assert_eq!(
+            range("bills last month"),
+            (Some(day(9, 1)), Some(day(10, 1)))
+        );
+        assert_eq!(range("the past 3 days"), (Some(day(10, 11)), None));
+        assert_eq!(range("last two weeks"), (Some(day(9, 30)), None));
+        assert_eq!(
+            range("what came in March"),
+            (Some(day(3, 1)), Some(day(4, 1)))
+        );
+        assert_eq!(range("since November"), (Some(day(11, 1)), None));
+        assert_eq!(
+            range("the call on Monday"),
+            (Some(day(10, 12)), Some(day(10, 13)))
+        );
+        assert_eq!(range("before:2026-01-01 today"), (None, Some(1767225600)));
+        assert_eq!(range("the last invoice"), (None, None));
+    }
+
+    #[test]
+    fn test_folder_and_account() {
+        let accounts = [
+            ("a1", "me@work.example"),
+            ("a1", "Work"),
+            ("a2", "me@gmail.com"),
+            ("a2", "Personal"),
+        ];
+        let query = understand(
+            "what's in my sent folder on my work account",
+            now(),
+            &accounts,
+        );
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::Folder("Sent".to_string()),
+                SearchFilter::Account("a1".to_string()),
+            ]
+        );
+
+        let query = understand(
+            "Which newsletters did I send to me@gmail.com",
+            now(),
+            &accounts,
+        );
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::Folder("Sent".to_string()),
+                SearchFilter::Account("a2".to_string()),
+            ]
+        );
+
+        assert!(understand("anything in spam about taxes", now(), &accounts)
+            .filters
+            .contains(&SearchFilter::Folder("Spam".to_string())));
+        assert!(understand("who sent the work plan", now(), &accounts)
+            .filters
+            .is_empty());
+    }
+}
@@ -1,5 +1,25 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{params, Connection, Result};
+use std::time::Duration;
+use tracing::info;
 
+use super::dedupe::message_key;
+use super::email_db::refresh_thread;
+
+/// How long a statement waits for another connection's lock before giving up
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Settings for every connection that writes: WAL, so readers on other connections
+/// aren't blocked by a write in progress and see the last committed state, and a busy
+/// timeout, so writers from separate connections wait for each other instead of failing
+pub fn configure_connection(conn: &Connection) -> Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let _mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+    // Safe with WAL: a crash can lose the last commits but never corrupts the database
+    conn.execute_batch("PRAGMA synchronous = NORMAL")?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
 pub fn create_tables(conn: &Connection) -> Result<()> {
     // Check if we need to migrate the date column from TEXT to INTEGER
     migrate_date_column_if_needed(conn)?;
@@ -18,7 +38,11 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             auth_type TEXT NOT NULL,
             is_active INTEGER NOT NULL DEFAULT 1,
             created_at INTEGER NOT NULL,
-            last_synced_at INTEGER
+            last_synced_at INTEGER,
+            cert_fingerprint TEXT,
+            color TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            label TEXT
         )",
         [],
     )?;
@@ -45,7 +69,13 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             account_id TEXT NOT NULL DEFAULT 'legacy',
             uid INTEGER NOT NULL DEFAULT 0,
             folder TEXT NOT NULL DEFAULT 'INBOX',
-            message_id TEXT NOT NULL DEFAULT ''
+            message_id TEXT NOT NULL DEFAULT '',
+            reply_to TEXT,
+            auth_results TEXT,
+            read_receipt_to TEXT,
+            body_clean TEXT,
+            has_body INTEGER NOT NULL DEFAULT 1,
+            message_key TEXT
         )",
         [],
     )?;
@@ -57,13 +87,23 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
             summary TEXT,
             priority TEXT NOT NULL DEFAULT 'MEDIUM',
             priority_score REAL NOT NULL DEFAULT 0.5,
+            model_score REAL,
             category TEXT,
             insights TEXT,
             action_items TEXT,
             has_deadline INTEGER NOT NULL DEFAULT 0,
+            deadline_at INTEGER,
             has_meeting INTEGER NOT NULL DEFAULT 0,
             has_financial INTEGER NOT NULL DEFAULT 0,
             sentiment TEXT,
+            has_trackers INTEGER NOT NULL DEFAULT 0,
+            trackers TEXT,
+            is_suspicious INTEGER NOT NULL DEFAULT 0,
+            phishing_score REAL NOT NULL DEFAULT 0,
+            phishing_reasons TEXT,
+            needs_reply INTEGER NOT NULL DEFAULT 0,
+            reply_reminded_at INTEGER,
+            language TEXT,
             indexed_at INTEGER NOT NULL,
             FOREIGN KEY (email_id) REFERENCES emails(id) ON DELETE CASCADE
         )",
@@ -110,26 +150,528 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    // Senders whose remote images the user chose to load
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS remote_image_senders (
+            sender_email TEXT PRIMARY KEY,
+            allowed_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // User-configurable category taxonomy used for classification
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS categories (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            position INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // AI chat sessions and their messages
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chat_sessions (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chat_messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES chat_sessions(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+
+    // Per-account indexing progress
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_indexing_status (
+            account_id TEXT PRIMARY KEY,
+            is_indexing INTEGER NOT NULL DEFAULT 0,
+            total_emails INTEGER NOT NULL DEFAULT 0,
+            processed_emails INTEGER NOT NULL DEFAULT 0,
+            last_indexed_at INTEGER,
+            error_message TEXT
+        )",
+        [],
+    )?;
+
+    // Re-analysis of cached emails - progress plus a resume cursor
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reindex_status (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            is_running INTEGER NOT NULL DEFAULT 0,
+            account_id TEXT,
+            only_missing INTEGER NOT NULL DEFAULT 1,
+            total_emails INTEGER NOT NULL DEFAULT 0,
+            processed_emails INTEGER NOT NULL DEFAULT 0,
+            cursor_date INTEGER,
+            cursor_id TEXT,
+            started_at INTEGER,
+            finished_at INTEGER,
+            error_message TEXT
+        )",
+        [],
+    )?;
+
+    // LLM runtime parameters from before the settings file, imported into it once
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS llm_settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            n_ctx INTEGER NOT NULL,
+            n_batch INTEGER NOT NULL,
+            n_threads INTEGER,
+            gpu_layers INTEGER,
+            temperature REAL NOT NULL,
+            top_p REAL NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Mail sent from the app, for sent-volume and response-time analytics
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sent_emails (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            to_emails TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            in_reply_to TEXT,
+            sent_at INTEGER NOT NULL,
+            message_id TEXT,
+            thread_id TEXT,
+            body_preview TEXT,
+            expects_reply INTEGER NOT NULL DEFAULT 0,
+            followup_dismissed INTEGER NOT NULL DEFAULT 0,
+            delivery_failed_at INTEGER,
+            failed_recipients TEXT,
+            delivery_error TEXT
+        )",
+        [],
+    )?;
+
+    // Follow-up reminders for emails awaiting the user's reply (no row means disabled)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS reply_reminder_settings (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            enabled INTEGER NOT NULL DEFAULT 0,
+            after_days INTEGER NOT NULL DEFAULT 3
+        )",
+        [],
+    )?;
+
+    // Latest benchmark result per model, measured on this machine
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS model_benchmarks (
+            model_id TEXT PRIMARY KEY,
+            load_time_ms INTEGER NOT NULL,
+            prompt_tokens INTEGER NOT NULL,
+            prompt_tokens_per_sec REAL NOT NULL,
+            generated_tokens INTEGER NOT NULL,
+            tokens_per_sec REAL NOT NULL,
+            peak_memory_bytes INTEGER,
+            measured_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // User-defined labels; emails list the keyword of each label they carry
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS labels (
+            name TEXT PRIMARY KEY COLLATE NOCASE,
+            keyword TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            color TEXT,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Messages already downloaded from POP3 accounts, keyed by the server's UIDL
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pop3_uidls (
+            account_id TEXT NOT NULL,
+            uidl TEXT NOT NULL,
+            email_id TEXT NOT NULL,
+            downloaded_at INTEGER NOT NULL,
+            PRIMARY KEY (account_id, uidl)
+        )",
+        [],
+    )?;
+
+    // Addresses mail bounced from; the composer warns before sending to them again
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bounced_addresses (
+            address TEXT PRIMARY KEY COLLATE NOCASE,
+            reason TEXT,
+            bounce_count INTEGER NOT NULL DEFAULT 1,
+            last_bounced_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Read receipt requests the user answered (sent = 1) or declined, by Message-ID
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS read_receipts (
+            message_id TEXT PRIMARY KEY,
+            sent INTEGER NOT NULL,
+            handled_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Addresses each account can send as besides its own
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS identities (
+            id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            address TEXT NOT NULL COLLATE NOCASE,
+            display_name TEXT,
+            signature TEXT,
+            created_at INTEGER NOT NULL,
+            UNIQUE (account_id, address)
+        )",
+        [],
+    )?;
+
+    // Where the last CONDSTORE flag sync of each IMAP folder left off
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS imap_sync_state (
+            account_id TEXT NOT NULL,
+            folder TEXT NOT NULL,
+            uid_validity INTEGER NOT NULL,
+            highest_modseq INTEGER NOT NULL,
+            PRIMARY KEY (account_id, folder)
+        )",
+        [],
+    )?;
+
+    // Message counts of each IMAP folder from the last STATUS pass
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS folder_status (
+            account_id TEXT NOT NULL,
+            folder TEXT NOT NULL,
+            total_count INTEGER NOT NULL,
+            unread_count INTEGER NOT NULL,
+            recent_count INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY (account_id, folder)
+        )",
+        [],
+    )?;
+
+    // Which new mail of each account raises a desktop notification (no row means defaults)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_settings (
+            account_id TEXT PRIMARY KEY,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            min_priority TEXT NOT NULL DEFAULT 'LOW',
+            muted_categories TEXT NOT NULL DEFAULT '[]',
+            quiet_start INTEGER,
+            quiet_end INTEGER,
+            vip_only INTEGER NOT NULL DEFAULT 0,
+            vip_senders TEXT NOT NULL DEFAULT '[]'
+        )",
+        [],
+    )?;
+
+    // Which folders of each account are synced and how far back (no row means defaults)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_sync_settings (
+            account_id TEXT PRIMARY KEY,
+            folders TEXT,
+            days_back INTEGER,
+            max_messages INTEGER,
+            fast_poll_secs INTEGER
+        )",
+        [],
+    )?;
+
+    // Special-folder roles the user gave folders, overriding detection (NULL role: not special)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS folder_roles (
+            account_id TEXT NOT NULL,
+            folder TEXT NOT NULL,
+            role TEXT,
+            PRIMARY KEY (account_id, folder)
+        )",
+        [],
+    )?;
+
+    // Folder each account uses for each special-folder role, as last listed
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS special_folders (
+            account_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            folder TEXT NOT NULL,
+            PRIMARY KEY (account_id, role)
+        )",
+        [],
+    )?;
+
+    // Conversations, kept up to date as emails are stored (see `email::threading`)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS threads (
+            thread_id TEXT PRIMARY KEY,
+            subject TEXT NOT NULL,
+            subject_key TEXT NOT NULL,
+            participants TEXT NOT NULL DEFAULT '[]',
+            message_count INTEGER NOT NULL DEFAULT 0,
+            first_date INTEGER NOT NULL,
+            last_date INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Thread of every Message-ID seen, as a message's own or in its reference chain
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS thread_links (
+            message_key TEXT PRIMARY KEY,
+            thread_id TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // URLs found in each email's body, in order of appearance
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS email_links (
+            email_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            text TEXT,
+            domain TEXT NOT NULL,
+            is_tracker INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (email_id, position)
+        )",
+        [],
+    )?;
+
+    // Files attached to each email, for browsing them across the mailbox
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attachments (
+            email_id TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            filename TEXT NOT NULL,
+            content_type TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            PRIMARY KEY (email_id, position)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attachments_kind ON attachments(kind)",
+        [],
+    )?;
+
+    // Page metadata fetched for link previews
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS link_previews (
+            url TEXT PRIMARY KEY,
+            title TEXT,
+            description TEXT,
+            favicon TEXT,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // AI translations of email bodies, one per email and target language
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS email_translations (
+            email_id TEXT NOT NULL,
+            language TEXT NOT NULL,
+            body TEXT NOT NULL,
+            translated_at INTEGER NOT NULL,
+            PRIMARY KEY (email_id, language)
+        )",
+        [],
+    )?;
+
+    // Emails snoozed during triage, left out of triage queues until the time passes
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS snoozed_emails (
+            email_id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            snoozed_until INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Emails the user pinned to the top of the smart inbox
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pinned_emails (
+            email_id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            pinned_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // User-defined smart inbox sections: a named, ordered saved filter (JSON)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS inbox_sections (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            filter TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Searches saved under a name: search text plus filters (JSON)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS saved_searches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            query TEXT NOT NULL,
+            filters TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Keyboard triage sessions and what was done in them
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS triage_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            queued INTEGER NOT NULL,
+            archived INTEGER NOT NULL DEFAULT 0,
+            trashed INTEGER NOT NULL DEFAULT 0,
+            snoozed INTEGER NOT NULL DEFAULT 0,
+            kept INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+
+    // Database maintenance runs and what they did
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS maintenance_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            started_at INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            size_before INTEGER NOT NULL,
+            size_after INTEGER NOT NULL,
+            integrity_problems TEXT NOT NULL DEFAULT '[]'
+        )",
+        [],
+    )?;
+
+    // Priorities the user set by hand, with what the email looked like at the time
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS priority_overrides (
+            email_id TEXT PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            from_email TEXT NOT NULL,
+            priority TEXT NOT NULL,
+            features TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    // Packages found in shipping notices, with their latest known status
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS shipments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            carrier TEXT NOT NULL,
+            tracking_number TEXT NOT NULL,
+            email_id TEXT NOT NULL,
+            subject TEXT NOT NULL DEFAULT '',
+            from_name TEXT NOT NULL DEFAULT '',
+            status TEXT NOT NULL,
+            status_at INTEGER NOT NULL,
+            first_seen_at INTEGER NOT NULL,
+            checked_at INTEGER,
+            notified_at INTEGER,
+            UNIQUE(account_id, carrier, tracking_number)
+        )",
+        [],
+    )?;
+
+    // Priority scoring weights refit from the overrides, per account
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS priority_weights (
+            account_id TEXT PRIMARY KEY,
+            weights TEXT NOT NULL,
+            corrections INTEGER NOT NULL,
+            tuned_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
     // Initialize indexing status if not exists
     conn.execute("INSERT OR IGNORE INTO indexing_status (id) VALUES (1)", [])?;
 
+    // Initialize re-index status if not exists
+    conn.execute("INSERT OR IGNORE INTO reindex_status (id) VALUES (1)", [])?;
+
     // Initialize embedding status if not exists
     conn.execute("INSERT OR IGNORE INTO embedding_status (id) VALUES (1)", [])?;
 
     // Run IMAP migration to add new columns to existing tables
     migrate_add_imap_columns(conn)?;
+    migrate_add_columns(conn)?;
+    backfill_message_keys(conn)?;
+    backfill_threads(conn)?;
+    seed_default_categories(conn)?;
 
     // Create indexes for performance
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_emails_date ON emails(date DESC)",
         [],
     )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sent_emails_sent_at ON sent_emails(sent_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sent_emails_thread ON sent_emails(thread_id)",
+        [],
+    )?;
 
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_emails_thread ON emails(thread_id)",
         [],
     )?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_emails_message_id ON emails(message_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_emails_message_key ON emails(message_key)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_threads_subject ON threads(subject_key, last_date)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_thread_links_thread ON thread_links(thread_id)",
+        [],
+    )?;
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_insights_priority ON email_insights(priority_score DESC)",
         [],
@@ -140,6 +682,37 @@ pub fn create_tables(conn: &Connection) -> Result<()> {
         [],
     )?;
 
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_chat_messages_session ON chat_messages(session_id, id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_insights_sentiment ON email_insights(sentiment)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_insights_deadline ON email_insights(deadline_at)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_insights_language ON email_insights(language)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_priority_overrides_sender
+         ON priority_overrides(account_id, from_email)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_shipments_status ON shipments(status, status_at DESC)",
+        [],
+    )?;
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_embeddings_model ON email_embeddings(embedding_model)",
         [],
@@ -189,6 +762,7 @@ pub fn create_vector_tables(conn: &Connection) -> Result<()> {
 
     // Initialize embedding status if not exists
     conn.execute("INSERT OR IGNORE INTO embedding_status (id) VALUES (1)", [])?;
+    add_embedding_checkpoint_columns(conn)?;
 
     // Create index for performance
     conn.execute(
@@ -243,6 +817,147 @@ fn migrate_add_imap_columns(conn: &Connection) -> Result<()> {
     Ok(())
 }
 
+/// Add a column to an existing table if it is missing
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let exists: bool = conn
+        .query_row(
+            &format!(
+                "SELECT count(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1",
+                table
+            ),
+            [column],
+            |row| row.get(0),
+        )
+        .unwrap_or(false);
+
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Add columns introduced after the initial schema
+fn migrate_add_columns(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "accounts", "cert_fingerprint", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "color", "TEXT")?;
+    add_column_if_missing(conn, "accounts", "sort_order", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "accounts", "label", "TEXT")?;
+    add_column_if_missing(conn, "emails", "reply_to", "TEXT")?;
+    add_column_if_missing(conn, "emails", "auth_results", "TEXT")?;
+    add_column_if_missing(conn, "emails", "read_receipt_to", "TEXT")?;
+    add_column_if_missing(conn, "emails", "body_clean", "TEXT")?;
+    add_column_if_missing(conn, "emails", "has_body", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(conn, "emails", "message_key", "TEXT")?;
+    add_column_if_missing(conn, "email_insights", "has_trackers", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "email_insights", "trackers", "TEXT")?;
+    add_column_if_missing(conn, "email_insights", "is_suspicious", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "email_insights", "phishing_score", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "email_insights", "phishing_reasons", "TEXT")?;
+    add_column_if_missing(conn, "email_insights", "deadline_at", "INTEGER")?;
+    add_column_if_missing(conn, "email_insights", "needs_reply", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "email_insights", "reply_reminded_at", "INTEGER")?;
+    add_column_if_missing(conn, "email_insights", "language", "TEXT")?;
+    add_column_if_missing(conn, "email_insights", "model_score", "REAL")?;
+    add_column_if_missing(conn, "sent_emails", "message_id", "TEXT")?;
+    add_column_if_missing(conn, "sent_emails", "thread_id", "TEXT")?;
+    add_column_if_missing(conn, "sent_emails", "body_preview", "TEXT")?;
+    add_column_if_missing(conn, "sent_emails", "expects_reply", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "sent_emails", "followup_dismissed", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "sent_emails", "delivery_failed_at", "INTEGER")?;
+    add_column_if_missing(conn, "sent_emails", "failed_recipients", "TEXT")?;
+    add_column_if_missing(conn, "sent_emails", "delivery_error", "TEXT")?;
+    add_column_if_missing(conn, "attachments", "local_path", "TEXT")?;
+    add_column_if_missing(conn, "attachments", "ocr_text", "TEXT")?;
+    add_column_if_missing(conn, "attachments", "ocr_at", "INTEGER")?;
+    add_column_if_missing(conn, "indexing_status", "is_paused", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "indexing_status", "last_processed_id", "TEXT")?;
+    add_column_if_missing(conn, "indexing_status", "heartbeat_at", "INTEGER")?;
+    add_column_if_missing(conn, "account_indexing_status", "heartbeat_at", "INTEGER")?;
+    add_column_if_missing(conn, "reindex_status", "heartbeat_at", "INTEGER")?;
+    add_column_if_missing(conn, "account_sync_settings", "fast_poll_secs", "INTEGER")?;
+    add_embedding_checkpoint_columns(conn)?;
+    Ok(())
+}
+
+/// Link copies of the emails cached before `message_key` was added
+fn backfill_message_keys(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, message_id FROM emails WHERE message_key IS NULL AND message_id != ''",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    for (id, message_id) in rows {
+        if let Some(key) = message_key(&message_id) {
+            conn.execute(
+                "UPDATE emails SET message_key = ?2 WHERE id = ?1",
+                params![id, key],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Build the threads of emails cached before threads were stored, from the thread ids
+/// they were synced with
+fn backfill_threads(conn: &Connection) -> Result<()> {
+    let needed: bool = conn.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM threads)
+                AND EXISTS (SELECT 1 FROM emails WHERE message_key IS NOT NULL)",
+        [],
+        |row| row.get(0),
+    )?;
+    if !needed {
+        return Ok(());
+    }
+
+    conn.execute(
+        "INSERT OR IGNORE INTO thread_links (message_key, thread_id)
+         SELECT message_key, thread_id FROM emails WHERE message_key IS NOT NULL",
+        [],
+    )?;
+    let mut stmt = conn.prepare("SELECT DISTINCT thread_id FROM emails")?;
+    let thread_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>>>()?;
+    for thread_id in thread_ids {
+        refresh_thread(conn, &thread_id)?;
+    }
+    Ok(())
+}
+
+/// Pause flag, checkpoint and heartbeat for the embedding job (shared by both schemas)
+fn add_embedding_checkpoint_columns(conn: &Connection) -> Result<()> {
+    add_column_if_missing(conn, "embedding_status", "is_paused", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "embedding_status", "last_processed_id", "TEXT")?;
+    add_column_if_missing(conn, "embedding_status", "heartbeat_at", "INTEGER")?;
+    Ok(())
+}
+
+/// Seed the built-in categories the first time the table is created
+fn seed_default_categories(conn: &Connection) -> Result<()> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM categories", [], |row| row.get(0))?;
+    if count > 0 {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    for (position, (id, name, description)) in crate::llm::rag::DEFAULT_CATEGORIES.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO categories (id, name, description, position, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![id, name, description, position as i64, now],
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Migrates the date column from TEXT to INTEGER if needed
 fn migrate_date_column_if_needed(conn: &Connection) -> Result<()> {
     let table_exists: bool = conn
@@ -272,7 +987,7 @@ fn migrate_date_column_if_needed(conn: &Connection) -> Result<()> {
         return Ok(());
     }
 
-    eprintln!("Migrating emails table: converting date column from TEXT to INTEGER...");
+    info!("Migrating emails table: converting date column from TEXT to INTEGER...");
 
     conn.execute("BEGIN TRANSACTION", [])?;
 
@@ -331,7 +1046,7 @@ fn migrate_date_column_if_needed(conn: &Connection) -> Result<()> {
 
     conn.execute("COMMIT", [])?;
 
-    eprintln!("Migration complete: date column converted to INTEGER");
+    info!("Migration complete: date column converted to INTEGER");
 
     Ok(())
 }
@@ -0,0 +1,380 @@
+//! Gmail-style search operators
+//!
+//! A query like `from:alice before:2024-06-01 has:attachment is:unread folder:Sent invoice`,
+//! `lang:french` or `account:work` splits into filters, which become SQL conditions on the `emails e` table, and the
+//! remaining free text, which goes to the text or semantic search as before.
+//! Values containing spaces can be quoted: `from:"Alice Smith"`. Tokens that aren't a
+//! known operator with a valid value are kept as free text.
+//!
+//! The same query can also run on the mail server, as IMAP SEARCH criteria or, on
+//! Gmail, as an `X-GM-RAW` search in Gmail's own syntax. The folder, language and
+//! account filters have no server equivalent: the folder and account are the ones
+//! searched, and languages are only known for indexed emails.
+
+use chrono::{DateTime, NaiveDate};
+
+use crate::email::language;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchFilter {
+    From(String),
+    To(String),
+    Subject(String),
+    /// Sent before this unix timestamp (exclusive)
+    Before(i64),
+    /// Sent on or after this unix timestamp
+    After(i64),
+    Folder(String),
+    /// ISO 639-3 code of the language detected when the email was indexed
+    Language(String),
+    /// Account id, address or label
+    Account(String),
+    HasAttachment,
+    Unread,
+    Read,
+    Starred,
+}
+
+/// A bound parameter for the generated SQL
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Text(String),
+    Integer(i64),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Free text left after removing operators
+    pub text: String,
+    pub filters: Vec<SearchFilter>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut text = Vec::new();
+        let mut filters = Vec::new();
+
+        for token in tokenize(input) {
+            match parse_filter(&token) {
+                Some(filter) => filters.push(filter),
+                None => text.push(token),
+            }
+        }
+
+        Self {
+            text: text.join(" "),
+            filters,
+        }
+    }
+
+    pub fn has_filters(&self) -> bool {
+        !self.filters.is_empty()
+    }
+
+    /// SQL conditions (to be joined with AND) over `emails e`, with their parameters.
+    /// Placeholders are numbered from `first_param` so they can follow existing ones.
+    pub fn sql_conditions(&self, first_param: usize) -> (Vec<String>, Vec<SqlValue>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+
+        for filter in &self.filters {
+            let n = first_param + values.len();
+            let condition = match filter {
+                SearchFilter::From(value) => {
+                    values.push(SqlValue::Text(format!("%{}%", value)));
+                    format!("(e.from_email LIKE ?{n} OR e.from_name LIKE ?{n})")
+                }
+                SearchFilter::To(value) => {
+                    values.push(SqlValue::Text(format!("%{}%", value)));
+                    format!("e.to_emails LIKE ?{n}")
+                }
+                SearchFilter::Subject(value) => {
+                    values.push(SqlValue::Text(format!("%{}%", value)));
+                    format!("e.subject LIKE ?{n}")
+                }
+                SearchFilter::Before(timestamp) => {
+                    values.push(SqlValue::Integer(*timestamp));
+                    format!("e.date < ?{n}")
+                }
+                SearchFilter::After(timestamp) => {
+                    values.push(SqlValue::Integer(*timestamp));
+                    format!("e.date >= ?{n}")
+                }
+                // Match the full name or its last segment, so `Sent` finds `[Gmail]/Sent`
+                // and `INBOX.Sent` too
+                SearchFilter::Folder(value) => {
+                    values.push(SqlValue::Text(value.clone()));
+                    format!(
+                        "(e.folder LIKE ?{n} OR e.folder LIKE '%/' || ?{n} OR e.folder LIKE '%.' || ?{n})"
+                    )
+                }
+                SearchFilter::Language(code) => {
+                    values.push(SqlValue::Text(code.clone()));
+                    format!("e.id IN (SELECT email_id FROM email_insights WHERE language = ?{n})")
+                }
+                SearchFilter::Account(value) => {
+                    values.push(SqlValue::Text(value.clone()));
+                    format!(
+                        "(e.account_id = ?{n} OR e.account_id IN (SELECT id FROM accounts WHERE email LIKE ?{n} OR label LIKE ?{n}))"
+                    )
+                }
+                SearchFilter::HasAttachment => "e.has_attachments = 1".to_string(),
+                SearchFilter::Unread => "e.is_read = 0".to_string(),
+                SearchFilter::Read => "e.is_read = 1".to_string(),
+                SearchFilter::Starred => "e.is_starred = 1".to_string(),
+            };
+            conditions.push(condition);
+        }
+
+        (conditions, values)
+    }
+
+    /// IMAP SEARCH criteria (RFC 3501 §6.4.4) matching the query, `ALL` if it has none.
+    /// Dates compare with the Date header, as the local search does.
+    pub fn imap_criteria(&self) -> String {
+        let mut keys = Vec::new();
+        for filter in &self.filters {
+            match filter {
+                SearchFilter::From(value) => keys.push(format!("FROM {}", quote(value))),
+                SearchFilter::To(value) => keys.push(format!("TO {}", quote(value))),
+                SearchFilter::Subject(value) => keys.push(format!("SUBJECT {}", quote(value))),
+                SearchFilter::Before(timestamp) => {
+                    keys.push(format!("SENTBEFORE {}", imap_date(*timestamp)))
+                }
+                SearchFilter::After(timestamp) => {
+                    keys.push(format!("SENTSINCE {}", imap_date(*timestamp)))
+                }
+                // There is no attachment key; mixed multiparts are the usual carriers
+                SearchFilter::HasAttachment => {
+                    keys.push("HEADER Content-Type \"multipart/mixed\"".to_string())
+                }
+                SearchFilter::Unread => keys.push("UNSEEN".to_string()),
+                SearchFilter::Read => keys.push("SEEN".to_string()),
+                SearchFilter::Starred => keys.push("FLAGGED".to_string()),
+                SearchFilter::Folder(_) | SearchFilter::Language(_) | SearchFilter::Account(_) => {}
+            }
+        }
+        for word in self.text.split_whitespace() {
+            keys.push(format!("TEXT {}", quote(word)));
+        }
+
+        let criteria = if keys.is_empty() {
+            "ALL".to_string()
+        } else {
+            keys.join(" ")
+        };
+        if criteria.is_ascii() {
+            criteria
+        } else {
+            format!("CHARSET UTF-8 {}", criteria)
+        }
+    }
+
+    /// Gmail search (`X-GM-RAW`) matching the query, for servers with X-GM-EXT-1
+    pub fn gmail_criteria(&self) -> String {
+        let mut terms = Vec::new();
+        for filter in &self.filters {
+            match filter {
+                SearchFilter::From(value) => terms.push(format!("from:{}", quote(value))),
+                SearchFilter::To(value) => terms.push(format!("to:{}", quote(value))),
+                SearchFilter::Subject(value) => terms.push(format!("subject:{}", quote(value))),
+                SearchFilter::Before(timestamp) => {
+                    terms.push(format!("before:{}", gmail_date(*timestamp)))
+                }
+                SearchFilter::After(timestamp) => {
+                    terms.push(format!("after:{}", gmail_date(*timestamp)))
+                }
+                SearchFilter::HasAttachment => terms.push("has:attachment".to_string()),
+                SearchFilter::Unread => terms.push("is:unread".to_string()),
+                SearchFilter::Read => terms.push("is:read".to_string()),
+                SearchFilter::Starred => terms.push("is:starred".to_string()),
+                SearchFilter::Folder(_) | SearchFilter::Language(_) | SearchFilter::Account(_) => {}
+            }
+        }
+        if !self.text.is_empty() {
+            terms.push(self.text.clone());
+        }
+
+        let raw = quote(&terms.join(" "));
+        if raw.is_ascii() {
+            format!("X-GM-RAW {}", raw)
+        } else {
+            format!("CHARSET UTF-8 X-GM-RAW {}", raw)
+        }
+    }
+}
+
+/// An IMAP quoted string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `1-Jun-2024`, the date format of IMAP SEARCH
+fn imap_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%-d-%b-%Y").to_string())
+        .unwrap_or_default()
+}
+
+fn gmail_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|date| date.format("%Y/%m/%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Split on whitespace, keeping double-quoted runs together (quotes are dropped)
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_filter(token: &str) -> Option<SearchFilter> {
+    let (key, value) = token.split_once(':')?;
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    match key.to_lowercase().as_str() {
+        "from" => Some(SearchFilter::From(value.to_string())),
+        "to" => Some(SearchFilter::To(value.to_string())),
+        "subject" => Some(SearchFilter::Subject(value.to_string())),
+        "folder" | "in" => Some(SearchFilter::Folder(value.to_string())),
+        "account" => Some(SearchFilter::Account(value.to_string())),
+        "lang" | "language" => {
+            language::parse(value).map(|code| SearchFilter::Language(code.to_string()))
+        }
+        "before" => parse_date(value).map(SearchFilter::Before),
+        "after" => parse_date(value).map(SearchFilter::After),
+        "has" => match value.to_lowercase().as_str() {
+            "attachment" | "attachments" => Some(SearchFilter::HasAttachment),
+            _ => None,
+        },
+        "is" => match value.to_lowercase().as_str() {
+            "unread" => Some(SearchFilter::Unread),
+            "read" => Some(SearchFilter::Read),
+            "starred" => Some(SearchFilter::Starred),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` or `YYYY/MM/DD` date, as a unix timestamp
+fn parse_date(value: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y/%m/%d"))
+        .ok()?;
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators_and_text() {
+        let query = SearchQuery::parse(
+            "from:alice before:2024-06-01 has:attachment is:unread folder:Sent quarterly report",
+        );
+
+        assert_eq!(query.text, "quarterly report");
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::From("alice".to_string()),
+                SearchFilter::Before(1717200000),
+                SearchFilter::HasAttachment,
+                SearchFilter::Unread,
+                SearchFilter::Folder("Sent".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quoted_values_and_unknown_tokens() {
+        let query = SearchQuery::parse(r#"from:"Alice Smith" "re: lunch" is:important after:someday"#);
+
+        assert_eq!(query.filters, vec![SearchFilter::From("Alice Smith".to_string())]);
+        assert_eq!(query.text, "re: lunch is:important after:someday");
+        assert!(!SearchQuery::parse("plain words").has_filters());
+    }
+
+    #[test]
+    fn test_sql_conditions() {
+        let query =
+            SearchQuery::parse("to:bob after:2024/01/01 is:starred subject:invoice lang:French");
+        let (conditions, values) = query.sql_conditions(2);
+
+        assert_eq!(
+            conditions,
+            vec![
+                "e.to_emails LIKE ?2",
+                "e.date >= ?3",
+                "e.is_starred = 1",
+                "e.subject LIKE ?4",
+                "e.id IN (SELECT email_id FROM email_insights WHERE language = ?5)",
+            ]
+        );
+        assert_eq!(
+            values,
+            vec![
+                SqlValue::Text("%bob%".to_string()),
+                SqlValue::Integer(1704067200),
+                SqlValue::Text("%invoice%".to_string()),
+                SqlValue::Text("fra".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_server_criteria() {
+        let query = SearchQuery::parse("from:alice before:2024-06-01 is:unread folder:Sent Grüße");
+        assert_eq!(
+            query.imap_criteria(),
+            r#"CHARSET UTF-8 FROM "alice" SENTBEFORE 1-Jun-2024 UNSEEN TEXT "Grüße""#
+        );
+
+        let query =
+            SearchQuery::parse(r#"from:"Alice Smith" has:attachment after:2024/01/01 invoice"#);
+        assert_eq!(
+            query.imap_criteria(),
+            r#"FROM "Alice Smith" HEADER Content-Type "multipart/mixed" SENTSINCE 1-Jan-2024 TEXT "invoice""#
+        );
+        assert_eq!(
+            query.gmail_criteria(),
+            r#"X-GM-RAW "from:\"Alice Smith\" has:attachment after:2024/01/01 invoice""#
+        );
+        assert_eq!(SearchQuery::parse("folder:Sent").imap_criteria(), "ALL");
+    }
+
+    #[test]
+    fn test_account_filter() {
+        let query = SearchQuery::parse("account:work invoice");
+        let (conditions, values) = query.sql_conditions(1);
+
+        assert_eq!(
+            conditions,
+            vec!["(e.account_id = ?1 OR e.account_id IN (SELECT id FROM accounts WHERE email LIKE ?1 OR label LIKE ?1))"]
+        );
+        assert_eq!(values, vec![SqlValue::Text("work".to_string())]);
+        assert_eq!(query.imap_criteria(), r#"TEXT "invoice""#);
+    }
+}
@@ -3,15 +3,20 @@
 //! Provides storage and retrieval of email embeddings for RAG functionality.
 
 use anyhow::{Context, Result as AnyhowResult};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OpenFlags};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
 
-use super::schema::create_vector_tables;
+use super::ann_index::HnswIndex;
+use super::backup::copy_database;
+use super::email_db::INTERRUPTED_MESSAGE;
+use super::schema::{configure_connection, create_vector_tables};
 
-/// Embedding dimensions (all-MiniLM-L6-v2 produces 384-dim vectors)
-pub const EMBEDDING_DIMENSIONS: usize = 384;
+/// Below this many embeddings a brute-force scan is fast enough and exact
+const ANN_MIN_EMBEDDINGS: usize = 2000;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmailEmbedding {
@@ -25,11 +30,14 @@ pub struct EmailEmbedding {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingStatus {
     pub is_embedding: bool,
+    pub is_paused: bool,
     pub total_emails: i64,
     pub embedded_emails: i64,
     pub current_model: Option<String>,
     pub last_embedded_at: Option<i64>,
     pub error_message: Option<String>,
+    /// Last email embedded in the current or interrupted run
+    pub last_processed_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,20 +46,54 @@ pub struct SimilarEmail {
     pub similarity: f32,
 }
 
+/// Stored embeddings for one model and vector size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelInfo {
+    pub embedding_model: String,
+    pub dimensions: usize,
+    pub count: i64,
+}
+
 pub struct VectorDatabase {
     conn: Arc<Mutex<Connection>>,
+    /// ANN index per embedding model; `None` if that model's vectors don't share one dimension
+    indexes: Mutex<HashMap<String, Option<HnswIndex>>>,
 }
 
 impl VectorDatabase {
     pub fn new(db_path: PathBuf) -> AnyhowResult<Self> {
         let conn = Connection::open(&db_path)
             .with_context(|| format!("Failed to open database at {:?}", db_path))?;
+        configure_connection(&conn).context("Failed to configure database")?;
 
         create_vector_tables(&conn).context("Failed to create vector tables")?;
 
-        Ok(Self {
+        let db = Self {
             conn: Arc::new(Mutex::new(conn)),
-        })
+            indexes: Mutex::new(HashMap::new()),
+        };
+        db.rebuild_index()?;
+        Ok(db)
+    }
+
+    /// Build the ANN indexes from every stored embedding
+    pub fn rebuild_index(&self) -> AnyhowResult<()> {
+        let mut indexes = HashMap::new();
+        for embedding in &self.get_all_embeddings()? {
+            add_to_index(&mut indexes, embedding);
+        }
+        for (model, index) in &indexes {
+            if let Some(index) = index {
+                info!(
+                    "Built ANN index over {} embeddings from {}",
+                    index.len(),
+                    model
+                );
+            }
+        }
+
+        *self.indexes.lock().unwrap() = indexes;
+        Ok(())
     }
 
     /// Store an embedding for an email
@@ -72,7 +114,38 @@ impl VectorDatabase {
                 embedding.created_at,
             ],
         )?;
+        drop(conn);
 
+        add_to_index(&mut self.indexes.lock().unwrap(), embedding);
+        Ok(())
+    }
+
+    /// Store several embeddings in one transaction
+    pub fn store_embeddings(&self, embeddings: &[EmailEmbedding]) -> AnyhowResult<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO email_embeddings (email_id, embedding, embedding_model, text_hash, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for embedding in embeddings {
+                stmt.execute(params![
+                    embedding.email_id,
+                    embedding_to_bytes(&embedding.embedding)?,
+                    embedding.embedding_model,
+                    embedding.text_hash,
+                    embedding.created_at,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        drop(conn);
+
+        let mut indexes = self.indexes.lock().unwrap();
+        for embedding in embeddings {
+            add_to_index(&mut indexes, embedding);
+        }
         Ok(())
     }
 
@@ -111,29 +184,118 @@ impl VectorDatabase {
         )?;
 
         let embeddings = stmt
+            .query_map([], row_to_embedding)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(embeddings)
+    }
+
+    /// Get the embeddings produced by one model
+    pub fn get_model_embeddings(&self, embedding_model: &str) -> AnyhowResult<Vec<EmailEmbedding>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT email_id, embedding, embedding_model, text_hash, created_at FROM email_embeddings
+             WHERE embedding_model = ?1",
+        )?;
+
+        let embeddings = stmt
+            .query_map(params![embedding_model], row_to_embedding)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(embeddings)
+    }
+
+    /// Stored embeddings grouped by model and vector size, largest group first
+    pub fn get_embedding_models(&self) -> AnyhowResult<Vec<EmbeddingModelInfo>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT embedding_model, length(embedding) / 4, COUNT(*) FROM email_embeddings
+             GROUP BY embedding_model, length(embedding)
+             ORDER BY COUNT(*) DESC",
+        )?;
+
+        let models = stmt
             .query_map([], |row| {
-                let embedding_bytes: Vec<u8> = row.get(1)?;
-                Ok(EmailEmbedding {
-                    email_id: row.get(0)?,
-                    embedding: bytes_to_embedding(&embedding_bytes).unwrap_or_default(),
-                    embedding_model: row.get(2)?,
-                    text_hash: row.get(3)?,
-                    created_at: row.get(4)?,
+                Ok(EmbeddingModelInfo {
+                    embedding_model: row.get(0)?,
+                    dimensions: row.get::<_, i64>(1)? as usize,
+                    count: row.get(2)?,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
+        Ok(models)
+    }
+
+    /// The embeddings `embedding_model` produced for some of `email_ids`
+    pub fn get_model_embeddings_for(
+        &self,
+        embedding_model: &str,
+        email_ids: &HashSet<String>,
+    ) -> AnyhowResult<Vec<EmailEmbedding>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT email_id, embedding, embedding_model, text_hash, created_at FROM email_embeddings
+             WHERE email_id = ?1 AND embedding_model = ?2",
+        )?;
+        let mut embeddings = Vec::new();
+        for email_id in email_ids {
+            let mut rows = stmt.query_map(params![email_id, embedding_model], row_to_embedding)?;
+            if let Some(embedding) = rows.next() {
+                embeddings.push(embedding?);
+            }
+        }
+
         Ok(embeddings)
     }
 
-    /// Find similar emails using cosine similarity
+    /// Find similar emails using cosine similarity, among embeddings from `embedding_model` only.
+    /// Uses the ANN index for large collections and an exact scan otherwise. With `candidates`,
+    /// only those emails are scanned, exactly.
+    /// Fails if stored vectors for the model don't match the query's dimensions.
     pub fn search_similar(
         &self,
         query_embedding: &[f32],
+        embedding_model: &str,
         top_k: usize,
         exclude_email_id: Option<&str>,
+        candidates: Option<&HashSet<String>>,
     ) -> AnyhowResult<Vec<SimilarEmail>> {
-        let embeddings = self.get_all_embeddings()?;
+        if candidates.is_none() {
+            let indexes = self.indexes.lock().unwrap();
+            let usable = indexes.get(embedding_model).and_then(Option::as_ref).filter(|i| {
+                i.len() >= ANN_MIN_EMBEDDINGS && i.dimensions() == query_embedding.len()
+            });
+            if let Some(index) = usable {
+                let extra = usize::from(exclude_email_id.is_some());
+                return Ok(index
+                    .search(query_embedding, top_k + extra)
+                    .into_iter()
+                    .filter(|(email_id, _)| Some(email_id.as_str()) != exclude_email_id)
+                    .take(top_k)
+                    .map(|(email_id, similarity)| SimilarEmail { email_id, similarity })
+                    .collect());
+            }
+        }
+
+        let embeddings = match candidates {
+            Some(email_ids) => self.get_model_embeddings_for(embedding_model, email_ids)?,
+            None => self.get_model_embeddings(embedding_model)?,
+        };
+        if let Some(mismatched) = embeddings
+            .iter()
+            .find(|e| e.embedding.len() != query_embedding.len())
+        {
+            anyhow::bail!(
+                "Stored embeddings for '{}' have {} dimensions but the query has {}; re-embed emails to fix",
+                embedding_model,
+                mismatched.embedding.len(),
+                query_embedding.len()
+            );
+        }
 
         let mut similarities: Vec<SimilarEmail> = embeddings
             .iter()
@@ -159,13 +321,19 @@ impl VectorDatabase {
         Ok(similarities)
     }
 
-    /// Check if an email has an embedding with the given text hash
-    pub fn has_embedding(&self, email_id: &str, text_hash: &str) -> AnyhowResult<bool> {
+    /// Check if an email has an embedding from `embedding_model` with the given text hash
+    pub fn has_embedding(
+        &self,
+        email_id: &str,
+        text_hash: &str,
+        embedding_model: &str,
+    ) -> AnyhowResult<bool> {
         let conn = self.conn.lock().unwrap();
 
         let count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM email_embeddings WHERE email_id = ?1 AND text_hash = ?2",
-            params![email_id, text_hash],
+            "SELECT COUNT(*) FROM email_embeddings
+             WHERE email_id = ?1 AND text_hash = ?2 AND embedding_model = ?3",
+            params![email_id, text_hash, embedding_model],
             |row| row.get(0),
         )?;
 
@@ -184,12 +352,28 @@ impl VectorDatabase {
     }
 
     /// Get all email IDs that already have embeddings
-    pub fn get_embedded_email_ids(&self) -> AnyhowResult<std::collections::HashSet<String>> {
+    /// Ids of all emails with a stored embedding, from any model
+    pub fn get_all_embedded_email_ids(&self) -> AnyhowResult<Vec<String>> {
         let conn = self.conn.lock().unwrap();
 
         let mut stmt = conn.prepare("SELECT email_id FROM email_embeddings")?;
         let ids = stmt
             .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        Ok(ids)
+    }
+
+    pub fn get_embedded_email_ids(
+        &self,
+        embedding_model: &str,
+    ) -> AnyhowResult<std::collections::HashSet<String>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt =
+            conn.prepare("SELECT email_id FROM email_embeddings WHERE embedding_model = ?1")?;
+        let ids = stmt
+            .query_map(params![embedding_model], |row| row.get(0))?
             .collect::<Result<std::collections::HashSet<String>, _>>()?;
 
         Ok(ids)
@@ -231,7 +415,8 @@ impl VectorDatabase {
              embedded_emails = COALESCE(?3, embedded_emails),
              current_model = COALESCE(?4, current_model),
              error_message = ?5,
-             last_embedded_at = CASE WHEN ?1 = 0 AND ?3 IS NOT NULL THEN strftime('%s', 'now') ELSE last_embedded_at END
+             last_embedded_at = CASE WHEN ?1 = 0 AND ?3 IS NOT NULL THEN strftime('%s', 'now') ELSE last_embedded_at END,
+             heartbeat_at = strftime('%s', 'now')
              WHERE id = 1",
             params![
                 is_embedding as i32,
@@ -250,7 +435,8 @@ impl VectorDatabase {
         let conn = self.conn.lock().unwrap();
 
         let status = conn.query_row(
-            "SELECT is_embedding, total_emails, embedded_emails, current_model, last_embedded_at, error_message 
+            "SELECT is_embedding, total_emails, embedded_emails, current_model, last_embedded_at, error_message,
+                    is_paused, last_processed_id
              FROM embedding_status WHERE id = 1",
             [],
             |row| {
@@ -261,6 +447,8 @@ impl VectorDatabase {
                     current_model: row.get(3)?,
                     last_embedded_at: row.get(4)?,
                     error_message: row.get(5)?,
+                    is_paused: row.get::<_, i32>(6)? != 0,
+                    last_processed_id: row.get(7)?,
                 })
             },
         )?;
@@ -268,6 +456,40 @@ impl VectorDatabase {
         Ok(status)
     }
 
+    /// Record the last email embedded by the embedding job (`None` clears the checkpoint)
+    pub fn set_embedding_checkpoint(&self, email_id: Option<&str>) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE embedding_status SET last_processed_id = ?1 WHERE id = 1",
+            params![email_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_embedding_paused(&self, paused: bool) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE embedding_status SET is_paused = ?1 WHERE id = 1",
+            params![paused as i32],
+        )?;
+        Ok(())
+    }
+
+    /// Mark an embedding run whose last heartbeat is before `stale_before` (any run with
+    /// `None`) as interrupted and paused, so `resume_embedding` picks it up. Returns
+    /// whether there was one.
+    pub fn recover_interrupted_embedding(&self, stale_before: Option<i64>) -> AnyhowResult<bool> {
+        let conn = self.conn.lock().unwrap();
+        let recovered = conn.execute(
+            "UPDATE embedding_status
+             SET is_embedding = 0, is_paused = 1, error_message = ?2
+             WHERE id = 1 AND is_embedding = 1 AND is_paused = 0
+               AND (?1 IS NULL OR COALESCE(heartbeat_at, 0) < ?1)",
+            params![stale_before, INTERRUPTED_MESSAGE],
+        )?;
+        Ok(recovered > 0)
+    }
+
     /// Delete embedding for an email
     pub fn delete_embedding(&self, email_id: &str) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
@@ -275,9 +497,37 @@ impl VectorDatabase {
             "DELETE FROM email_embeddings WHERE email_id = ?1",
             params![email_id],
         )?;
+        drop(conn);
+
+        for index in self.indexes.lock().unwrap().values_mut().flatten() {
+            index.remove(email_id);
+        }
         Ok(())
     }
 
+    /// Delete the embeddings of several emails in one transaction. Returns how many were
+    /// deleted.
+    pub fn delete_embeddings(&self, email_ids: &[String]) -> AnyhowResult<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM email_embeddings WHERE email_id = ?1")?;
+            for email_id in email_ids {
+                deleted += stmt.execute(params![email_id])?;
+            }
+        }
+        tx.commit()?;
+        drop(conn);
+
+        for index in self.indexes.lock().unwrap().values_mut().flatten() {
+            for email_id in email_ids {
+                index.remove(email_id);
+            }
+        }
+        Ok(deleted)
+    }
+
     /// Clear all embeddings
     pub fn clear_all_embeddings(&self) -> AnyhowResult<()> {
         let conn = self.conn.lock().unwrap();
@@ -286,8 +536,69 @@ impl VectorDatabase {
             "UPDATE embedding_status SET embedded_emails = 0, is_embedding = 0 WHERE id = 1",
             [],
         )?;
+        drop(conn);
+
+        self.indexes.lock().unwrap().clear();
         Ok(())
     }
+
+    /// Snapshot the embeddings into a new file at `path` while they stay in use
+    pub fn backup_to(&self, path: &Path, progress: impl FnMut(i64, i64)) -> AnyhowResult<()> {
+        let conn = self.conn.lock().unwrap();
+        let mut target = Connection::open(path)
+            .with_context(|| format!("Failed to create snapshot at {:?}", path))?;
+        copy_database(&conn, &mut target, progress)
+    }
+
+    /// Replace the stored embeddings with the snapshot at `path` and rebuild the indexes
+    pub fn restore_from(&self, path: &Path, progress: impl FnMut(i64, i64)) -> AnyhowResult<()> {
+        {
+            let mut conn = self.conn.lock().unwrap();
+            let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .with_context(|| format!("Failed to open snapshot at {:?}", path))?;
+            copy_database(&source, &mut conn, progress)?;
+            create_vector_tables(&conn).context("Failed to migrate restored vector tables")?;
+        }
+        self.rebuild_index()
+    }
+}
+
+fn row_to_embedding(row: &rusqlite::Row) -> rusqlite::Result<EmailEmbedding> {
+    let embedding_bytes: Vec<u8> = row.get(1)?;
+    Ok(EmailEmbedding {
+        email_id: row.get(0)?,
+        embedding: bytes_to_embedding(&embedding_bytes).unwrap_or_default(),
+        embedding_model: row.get(2)?,
+        text_hash: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Add an embedding to its model's index. A vector the index can't hold (a different
+/// dimension under the same model name) disables that index, so searches fall back to the
+/// exact scan, which reports the mismatch.
+fn add_to_index(indexes: &mut HashMap<String, Option<HnswIndex>>, embedding: &EmailEmbedding) {
+    // Each email has one embedding, so a re-embedded email leaves its old model's index
+    for (model, index) in indexes.iter_mut() {
+        if *model != embedding.embedding_model {
+            if let Some(index) = index {
+                index.remove(&embedding.email_id);
+            }
+        }
+    }
+
+    let entry = indexes
+        .entry(embedding.embedding_model.clone())
+        .or_insert_with(|| Some(HnswIndex::new(embedding.embedding.len())));
+    if let Some(index) = entry {
+        if !index.insert(&embedding.email_id, &embedding.embedding) {
+            warn!(
+                "Embeddings from {} have mixed dimensions, falling back to exact search",
+                embedding.embedding_model
+            );
+            *entry = None;
+        }
+    }
 }
 
 /// Convert f32 vector to bytes for storage
@@ -0,0 +1,186 @@
+//! Attachment kinds and downloads
+//!
+//! Attachments are grouped into a few kinds for the attachment gallery, from their MIME
+//! type or, when that's a generic `application/octet-stream`, their file extension.
+//! Small attachments are saved to the media cache while syncing; larger ones are
+//! downloaded when opened.
+
+use serde::{Deserialize, Serialize};
+
+/// Attachments up to this size are downloaded during sync unless the cache settings
+/// say otherwise
+pub const DEFAULT_AUTO_DOWNLOAD_MB: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Image,
+    /// PDFs, word processor files and plain text
+    Document,
+    Spreadsheet,
+    Presentation,
+    Archive,
+    Audio,
+    Video,
+    Calendar,
+    /// Forwarded messages
+    Email,
+    Other,
+}
+
+impl AttachmentKind {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Image => "image",
+            Self::Document => "document",
+            Self::Spreadsheet => "spreadsheet",
+            Self::Presentation => "presentation",
+            Self::Archive => "archive",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Calendar => "calendar",
+            Self::Email => "email",
+            Self::Other => "other",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Self {
+        match id {
+            "image" => Self::Image,
+            "document" => Self::Document,
+            "spreadsheet" => Self::Spreadsheet,
+            "presentation" => Self::Presentation,
+            "archive" => Self::Archive,
+            "audio" => Self::Audio,
+            "video" => Self::Video,
+            "calendar" => Self::Calendar,
+            "email" => Self::Email,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Kind of an attachment from its (lowercase) MIME type and file name
+pub fn attachment_kind(content_type: &str, filename: &str) -> AttachmentKind {
+    let (ctype, subtype) = content_type.split_once('/').unwrap_or((content_type, ""));
+    match ctype {
+        "image" => return AttachmentKind::Image,
+        "audio" => return AttachmentKind::Audio,
+        "video" => return AttachmentKind::Video,
+        "message" => return AttachmentKind::Email,
+        _ => {}
+    }
+    if subtype == "calendar" {
+        return AttachmentKind::Calendar;
+    }
+    if subtype.contains("spreadsheet") || subtype.contains("excel") || subtype == "csv" {
+        return AttachmentKind::Spreadsheet;
+    }
+    if subtype.contains("presentation") || subtype.contains("powerpoint") {
+        return AttachmentKind::Presentation;
+    }
+    if subtype == "pdf" || subtype.contains("word") || subtype.contains("opendocument.text") {
+        return AttachmentKind::Document;
+    }
+
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "heic" | "svg" | "bmp" | "tiff" => {
+            AttachmentKind::Image
+        }
+        "pdf" | "doc" | "docx" | "odt" | "rtf" | "txt" | "md" | "pages" => AttachmentKind::Document,
+        "xls" | "xlsx" | "ods" | "csv" | "numbers" => AttachmentKind::Spreadsheet,
+        "ppt" | "pptx" | "odp" | "key" => AttachmentKind::Presentation,
+        "zip" | "rar" | "7z" | "tar" | "gz" | "tgz" | "bz2" => AttachmentKind::Archive,
+        "mp3" | "wav" | "m4a" | "ogg" | "flac" => AttachmentKind::Audio,
+        "mp4" | "mov" | "avi" | "mkv" | "webm" => AttachmentKind::Video,
+        "ics" => AttachmentKind::Calendar,
+        "eml" => AttachmentKind::Email,
+        _ if ctype == "text" => AttachmentKind::Document,
+        _ if subtype.contains("zip") || subtype.contains("compressed") => AttachmentKind::Archive,
+        _ => AttachmentKind::Other,
+    }
+}
+
+/// Name to save an attachment under: its file name without path separators or
+/// characters Windows rejects, prefixed with its position so names never collide
+pub fn cache_filename(position: u32, filename: &str) -> String {
+    let name: String = filename
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if name.is_empty() {
+        format!("{}-attachment", position)
+    } else {
+        format!("{}-{}", position, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_kind_from_type() {
+        assert_eq!(attachment_kind("image/png", "scan"), AttachmentKind::Image);
+        assert_eq!(
+            attachment_kind("application/pdf", "invoice.bin"),
+            AttachmentKind::Document
+        );
+        assert_eq!(
+            attachment_kind(
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "q3.xlsx"
+            ),
+            AttachmentKind::Spreadsheet
+        );
+        assert_eq!(
+            attachment_kind("text/calendar", "invite.ics"),
+            AttachmentKind::Calendar
+        );
+        assert_eq!(
+            attachment_kind("message/rfc822", "fwd.eml"),
+            AttachmentKind::Email
+        );
+    }
+
+    #[test]
+    fn test_attachment_kind_from_extension() {
+        assert_eq!(
+            attachment_kind("application/octet-stream", "Slides.KEY"),
+            AttachmentKind::Presentation
+        );
+        assert_eq!(
+            attachment_kind("application/octet-stream", "photos.zip"),
+            AttachmentKind::Archive
+        );
+        assert_eq!(
+            attachment_kind("application/x-zip-compressed", "photos"),
+            AttachmentKind::Archive
+        );
+        assert_eq!(
+            attachment_kind("application/octet-stream", "data.bin"),
+            AttachmentKind::Other
+        );
+        assert_eq!(
+            AttachmentKind::from_id(AttachmentKind::Video.id()),
+            AttachmentKind::Video
+        );
+    }
+
+    #[test]
+    fn test_cache_filename() {
+        assert_eq!(cache_filename(0, "Q3 report.pdf"), "0-Q3 report.pdf");
+        assert_eq!(cache_filename(2, "../../etc/passwd"), "2-_.._etc_passwd");
+        assert_eq!(cache_filename(1, "a:b?.txt"), "1-a_b_.txt");
+        assert_eq!(cache_filename(3, " .. "), "3-attachment");
+    }
+}
@@ -0,0 +1,186 @@
+//! Where a sender's avatar can come from
+//!
+//! Senders are tried against Gravatar first, then their domain's BIMI logo (a TXT record
+//! at `default._bimi.<domain>` pointing to an SVG), then the domain's favicon. Domain
+//! logos are skipped for webmail providers, where they'd show the provider rather than
+//! the person. Resolved images and misses are cached for a while so the inbox list works
+//! offline.
+//!
+//! Domain logos are fetched from hosts the sender picks, which tells them their email
+//! was seen, so they are only looked up when the privacy settings allow it, and never
+//! from IP addresses or local network names.
+
+use super::links;
+
+/// How long a downloaded avatar is used before it's fetched again
+pub const AVATAR_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// How long to wait before retrying a sender that had no avatar
+pub const MISSING_AVATAR_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// Domains shared by unrelated people, whose logo says nothing about the sender
+const PERSONAL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "msn.com",
+    "yahoo.com",
+    "ymail.com",
+    "icloud.com",
+    "me.com",
+    "mac.com",
+    "aol.com",
+    "proton.me",
+    "protonmail.com",
+    "pm.me",
+    "gmx.com",
+    "gmx.de",
+    "web.de",
+    "mail.com",
+    "zoho.com",
+    "fastmail.com",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AvatarSource {
+    Gravatar,
+    /// BIMI logo of the domain
+    Bimi(String),
+    Favicon(String),
+}
+
+/// Sources to try for `email`, in order; the domain's logos only with `domain_logos`
+pub fn avatar_sources(email: &str, domain_logos: bool) -> Vec<AvatarSource> {
+    let mut sources = vec![AvatarSource::Gravatar];
+    if let Some(domain) = sender_domain(email).filter(|_| domain_logos) {
+        if !PERSONAL_DOMAINS.contains(&domain.as_str()) {
+            sources.push(AvatarSource::Bimi(domain.clone()));
+            sources.push(AvatarSource::Favicon(domain));
+        }
+    }
+    sources
+}
+
+/// Lowercased domain of an address, if it looks like a public hostname (not an IP
+/// address or a local network name)
+pub fn sender_domain(email: &str) -> Option<String> {
+    let domain = email.trim().rsplit_once('@')?.1.trim_end_matches('>');
+    let valid = domain.contains('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if !valid {
+        return None;
+    }
+    let url = format!("https://{}/", domain);
+    let is_hostname =
+        url::Url::parse(&url).is_ok_and(|url| matches!(url.host(), Some(url::Host::Domain(_))));
+    (is_hostname && links::is_previewable(&url)).then(|| domain.to_ascii_lowercase())
+}
+
+/// Logo URL of a BIMI record, e.g. `v=BIMI1; l=https://example.com/logo.svg`. The
+/// record may come as several quoted strings, as DNS returns long TXT values.
+pub fn bimi_logo_url(record: &str) -> Option<String> {
+    let record: String = if record.contains('"') {
+        record.split('"').skip(1).step_by(2).collect()
+    } else {
+        record.to_string()
+    };
+    let mut tags = record
+        .split(';')
+        .filter_map(|tag| tag.trim().split_once('='));
+    let (version, value) = tags.next()?;
+    if !version.trim().eq_ignore_ascii_case("v") || value.trim() != "BIMI1" {
+        return None;
+    }
+    tags.find(|(name, _)| name.trim().eq_ignore_ascii_case("l"))
+        .map(|(_, url)| url.trim())
+        .filter(|url| url.starts_with("https://"))
+        .map(str::to_string)
+}
+
+/// File extension for an image content type; `None` for anything that isn't an image
+/// (servers often answer a missing favicon with an HTML page)
+pub fn image_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    match mime.as_str() {
+        "image/png" => Some("png"),
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/svg+xml" => Some("svg"),
+        "image/x-icon" | "image/vnd.microsoft.icon" => Some("ico"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_avatar_sources() {
+        assert_eq!(
+            avatar_sources("Ada@Example.com", true),
+            vec![
+                AvatarSource::Gravatar,
+                AvatarSource::Bimi("example.com".to_string()),
+                AvatarSource::Favicon("example.com".to_string()),
+            ]
+        );
+        assert_eq!(
+            avatar_sources("bob@gmail.com", true),
+            vec![AvatarSource::Gravatar]
+        );
+        assert_eq!(
+            avatar_sources("Ada@Example.com", false),
+            vec![AvatarSource::Gravatar]
+        );
+        assert_eq!(sender_domain("undisclosed-recipients"), None);
+        assert_eq!(sender_domain("x@evil.com/path"), None);
+    }
+
+    #[test]
+    fn test_sender_domain_is_public_hostname() {
+        assert_eq!(
+            sender_domain("Ada <ada@Mail.Example.com>").as_deref(),
+            Some("mail.example.com")
+        );
+        for address in [
+            "x@192.168.1.1",
+            "x@10.0.0.1",
+            "x@8.8.8.8",
+            "x@0x7f.0.0.1",
+            "x@printer.local",
+            "x@dev.localhost",
+        ] {
+            assert_eq!(sender_domain(address), None, "{}", address);
+        }
+    }
+
+    #[test]
+    fn test_bimi_logo_url() {
+        assert_eq!(
+            bimi_logo_url("v=BIMI1; l=https://example.com/logo.svg; a=").as_deref(),
+            Some("https://example.com/logo.svg")
+        );
+        assert_eq!(
+            bimi_logo_url("\"v=BIMI1; l=https://example.com/\" \"brand/logo.svg\"").as_deref(),
+            Some("https://example.com/brand/logo.svg")
+        );
+        assert_eq!(
+            bimi_logo_url("v=BIMI1; l=http://example.com/logo.svg"),
+            None
+        );
+        assert_eq!(bimi_logo_url("v=spf1 include:example.com ~all"), None);
+    }
+
+    #[test]
+    fn test_image_extension() {
+        assert_eq!(image_extension("image/svg+xml"), Some("svg"));
+        assert_eq!(image_extension("image/PNG; charset=binary"), Some("png"));
+        assert_eq!(image_extension("text/html; charset=utf-8"), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! IMAP server capabilities and the strategies they allow
+//!
+//! Servers advertise their extensions in CAPABILITY after login. Operations with a
+//! standard fallback pick it up front from what's advertised instead of trying the
+//! extension and recovering from the error.
+
+use serde::{Deserialize, Serialize};
+
+/// Extensions the client makes use of, as advertised after login
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerCapabilities {
+    /// `MOVE` (RFC 6851)
+    pub move_command: bool,
+    /// `IDLE` (RFC 2177)
+    pub idle: bool,
+    /// `CONDSTORE` (RFC 7162): per-message modification sequences
+    pub condstore: bool,
+    /// `QRESYNC` (RFC 7162); implies CONDSTORE
+    pub qresync: bool,
+    /// `UIDPLUS` (RFC 4315): `UID EXPUNGE` among others
+    pub uidplus: bool,
+    /// `ID` (RFC 2971)
+    pub id: bool,
+    /// Server software reported through `ID`, e.g. "Dovecot 2.3"
+    pub server_name: Option<String>,
+}
+
+impl ServerCapabilities {
+    /// Capabilities from a check of whether the server advertises a name
+    pub fn detect(has: impl Fn(&str) -> bool) -> Self {
+        let qresync = has("QRESYNC");
+        Self {
+            move_command: has("MOVE"),
+            idle: has("IDLE"),
+            condstore: qresync || has("CONDSTORE"),
+            qresync,
+            uidplus: has("UIDPLUS"),
+            id: has("ID"),
+            server_name: None,
+        }
+    }
+
+    pub fn move_strategy(&self) -> MoveStrategy {
+        if self.move_command {
+            MoveStrategy::Move
+        } else if self.uidplus {
+            MoveStrategy::CopyUidExpunge
+        } else {
+            MoveStrategy::CopyExpunge
+        }
+    }
+
+    /// How to refresh flags of a folder last synced at `stored`, now reporting
+    /// `uid_validity`. Modification sequences only carry over while UIDVALIDITY holds.
+    pub fn flag_sync(
+        &self,
+        stored: Option<&FolderSyncState>,
+        uid_validity: Option<u32>,
+    ) -> FlagSync {
+        match stored {
+            Some(state) if self.condstore && uid_validity == Some(state.uid_validity) => {
+                FlagSync::ChangedSince(state.highest_modseq)
+            }
+            _ => FlagSync::Recent,
+        }
+    }
+}
+
+/// How a message is moved between folders
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveStrategy {
+    /// `UID MOVE`
+    Move,
+    /// `UID COPY`, mark `\Deleted`, then `UID EXPUNGE` of just that message
+    CopyUidExpunge,
+    /// `UID COPY`, mark `\Deleted`, then `EXPUNGE`, which also removes any other message
+    /// marked `\Deleted`
+    CopyExpunge,
+}
+
+/// How flags of already cached messages are refreshed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlagSync {
+    /// Only messages changed since this modification sequence (`CHANGEDSINCE`)
+    ChangedSince(u64),
+    /// Flags of the newest messages, whether they changed or not
+    Recent,
+}
+
+/// Where the last flag sync of a folder left off
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FolderSyncState {
+    pub uid_validity: u32,
+    pub highest_modseq: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advertised(names: &[&str]) -> ServerCapabilities {
+        ServerCapabilities::detect(|name| names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+    }
+
+    #[test]
+    fn test_detect() {
+        let capabilities = advertised(&["IMAP4rev1", "IDLE", "QRESYNC", "uidplus"]);
+        assert!(capabilities.idle);
+        assert!(capabilities.condstore);
+        assert!(capabilities.uidplus);
+        assert!(!capabilities.move_command);
+    }
+
+    #[test]
+    fn test_move_strategy() {
+        assert_eq!(
+            advertised(&["MOVE", "UIDPLUS"]).move_strategy(),
+            MoveStrategy::Move
+        );
+        assert_eq!(
+            advertised(&["UIDPLUS"]).move_strategy(),
+            MoveStrategy::CopyUidExpunge
+        );
+        assert_eq!(advertised(&[]).move_strategy(), MoveStrategy::CopyExpunge);
+    }
+
+    #[test]
+    fn test_flag_sync() {
+        let stored = FolderSyncState {
+            uid_validity: 7,
+            highest_modseq: 1200,
+        };
+        let condstore = advertised(&["CONDSTORE"]);
+        assert_eq!(
+            condstore.flag_sync(Some(&stored), Some(7)),
+            FlagSync::ChangedSince(1200)
+        );
+        assert_eq!(
+            condstore.flag_sync(Some(&stored), Some(8)),
+            FlagSync::Recent
+        );
+        assert_eq!(condstore.flag_sync(None, Some(7)), FlagSync::Recent);
+        assert_eq!(
+            advertised(&[]).flag_sync(Some(&stored), Some(7)),
+            FlagSync::Recent
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//! OpenPGP support for encrypted and signed mail
+//!
+//! Incoming messages are checked for PGP/MIME (`multipart/encrypted`, `multipart/signed`)
+//! and inline PGP blocks; they are decrypted and verified against the local keyring.
+//! Outgoing messages can be signed and/or encrypted before being handed to SMTP.
+//!
+//! Keys live as armored files in `<data_dir>/pgp/<FINGERPRINT>.asc`; passphrases for
+//! protected secret keys are kept in the system keychain.
+
+use anyhow::{anyhow, Context, Result};
+use keyring::Entry;
+use mail_parser::{MimeHeaders, PartType};
+use sequoia_openpgp as openpgp;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use openpgp::cert::prelude::*;
+use openpgp::crypto::{KeyPair, Password, SessionKey};
+use openpgp::packet::{PKESK, SKESK};
+use openpgp::parse::stream::{
+    DecryptionHelper, DecryptorBuilder, DetachedVerifierBuilder, GoodChecksum, MessageLayer,
+    MessageStructure, VerificationHelper,
+};
+use openpgp::parse::Parse;
+use openpgp::policy::StandardPolicy;
+use openpgp::serialize::stream::{Armorer, Encryptor2, LiteralWriter, Message, Signer};
+use openpgp::serialize::SerializeInto;
+use openpgp::types::{HashAlgorithm, SymmetricAlgorithm};
+use openpgp::{Fingerprint, KeyHandle};
+use tracing::info;
+
+const SERVICE_NAME: &str = "com.inboxed.app";
+
+/// Result of processing an encrypted and/or signed message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CryptoStatus {
+    pub encrypted: bool,
+    pub decrypted: bool,
+    pub signed: bool,
+    /// None when the message is unsigned
+    pub signature_valid: Option<bool>,
+    /// Fingerprint of the key that produced a valid signature
+    pub signer_fingerprint: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Bodies recovered from a decrypted (or cleartext-signed) message
+#[derive(Debug, Clone, Default)]
+pub struct ProtectedBody {
+    pub body_html: Option<String>,
+    pub body_plain: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PgpKeyInfo {
+    pub fingerprint: String,
+    pub user_ids: Vec<String>,
+    pub has_secret: bool,
+    pub created_at: i64,
+}
+
+// ========== Keyring ==========
+
+fn keys_dir() -> Result<PathBuf> {
+    let dir = crate::paths::data_dir()?.join("pgp");
+    std::fs::create_dir_all(&dir).context("Failed to create PGP key directory")?;
+    Ok(dir)
+}
+
+fn key_path(fingerprint: &str) -> Result<PathBuf> {
+    let fingerprint = fingerprint.replace(' ', "").to_uppercase();
+    if fingerprint.is_empty() || !fingerprint.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid fingerprint: {}", fingerprint));
+    }
+    Ok(keys_dir()?.join(format!("{}.asc", fingerprint)))
+}
+
+fn passphrase_entry(fingerprint: &Fingerprint) -> Result<Entry> {
+    Entry::new(
+        &crate::paths::keychain_service(SERVICE_NAME),
+        &format!("pgp_passphrase_{}", fingerprint.to_hex()),
+    )
+        .context("Failed to create keychain entry for PGP passphrase")
+}
+
+fn load_passphrase(fingerprint: &Fingerprint) -> Option<Password> {
+    passphrase_entry(fingerprint)
+        .ok()?
+        .get_password()
+        .ok()
+        .map(Password::from)
+}
+
+fn key_info(cert: &Cert) -> PgpKeyInfo {
+    PgpKeyInfo {
+        fingerprint: cert.fingerprint().to_hex(),
+        user_ids: cert
+            .userids()
+            .map(|uid| String::from_utf8_lossy(uid.userid().value()).to_string())
+            .collect(),
+        has_secret: cert.is_tsk(),
+        created_at: cert
+            .primary_key()
+            .creation_time()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0),
+    }
+}
+
+/// Load every certificate in the local keyring
+pub fn load_certs() -> Vec<Cert> {
+    let Ok(dir) = keys_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter(|e| e.path().extension().map(|ext| ext == "asc").unwrap_or(false))
+        .filter_map(|e| Cert::from_file(e.path()).ok())
+        .collect()
+}
+
+/// Import one or more armored certificates (public or secret), merging with existing copies
+pub fn import_keys(armored: &str, passphrase: Option<&str>) -> Result<Vec<PgpKeyInfo>> {
+    let mut imported = Vec::new();
+
+    for cert in CertParser::from_bytes(armored.as_bytes()).context("Failed to parse key")? {
+        let cert = cert.context("Failed to parse key")?;
+        let path = key_path(&cert.fingerprint().to_hex())?;
+
+        let cert = match Cert::from_file(&path) {
+            Ok(existing) => existing
+                .merge_public_and_secret(cert)
+                .context("Failed to merge with existing key")?,
+            Err(_) => cert,
+        };
+
+        let data = cert.as_tsk().armored().to_vec()?;
+        std::fs::write(&path, data).context("Failed to write key file")?;
+
+        if let (Some(passphrase), true) = (passphrase, cert.is_tsk()) {
+            passphrase_entry(&cert.fingerprint())?
+                .set_password(passphrase)
+                .context("Failed to store PGP passphrase in keychain")?;
+        }
+
+        info!("Imported key {}", cert.fingerprint());
+        imported.push(key_info(&cert));
+    }
+
+    if imported.is_empty() {
+        return Err(anyhow!("No keys found in input"));
+    }
+    Ok(imported)
+}
+
+/// Export a certificate as ASCII armor, optionally including secret key material
+pub fn export_key(fingerprint: &str, include_secret: bool) -> Result<String> {
+    let cert = Cert::from_file(key_path(fingerprint)?).context("Key not found")?;
+    let data = if include_secret {
+        cert.as_tsk().armored().to_vec()?
+    } else {
+        cert.armored().to_vec()?
+    };
+    Ok(String::from_utf8(data)?)
+}
+
+pub fn list_keys() -> Vec<PgpKeyInfo> {
+    load_certs().iter().map(key_info).collect()
+}
+
+pub fn delete_key(fingerprint: &str) -> Result<()> {
+    let path = key_path(fingerprint)?;
+    if let Ok(cert) = Cert::from_file(&path) {
+        if let Ok(entry) = passphrase_entry(&cert.fingerprint()) {
+            let _ = entry.delete_credential();
+        }
+    }
+    std::fs::remove_file(path).context("Failed to delete key")?;
+    Ok(())
+}
+
+/// Find a certificate whose user IDs contain the given email address
+pub fn find_cert_for_email<'a>(certs: &'a [Cert], email: &str) -> Option<&'a Cert> {
+    let needle = email.trim().to_lowercase();
+    let bracketed = format!("<{}>", needle);
+    certs.iter().find(|cert| {
+        cert.userids().any(|uid| {
+            let value = String::from_utf8_lossy(uid.userid().value()).to_lowercase();
+            value == needle || value.contains(&bracketed)
+        })
+    })
+}
+
+// ========== Stream helpers ==========
+
+struct Helper<'a> {
+    certs: &'a [Cert],
+    status: CryptoStatus,
+}
+
+impl<'a> Helper<'a> {
+    fn new(certs: &'a [Cert]) -> Self {
+        Self {
+            certs,
+            status: CryptoStatus::default(),
+        }
+    }
+}
+
+impl VerificationHelper for Helper<'_> {
+    fn get_certs(&mut self, _ids: &[KeyHandle]) -> openpgp::Result<Vec<Cert>> {
+        Ok(self.certs.to_vec())
+    }
+
+    fn check(&mut self, structure: MessageStructure) -> openpgp::Result<()> {
+        for layer in structure.into_iter() {
+            if let MessageLayer::SignatureGroup { results } = layer {
+                for result in results {
+                    self.status.signed = true;
+                    match result {
+                        Ok(GoodChecksum { ka, .. }) => {
+                            self.status.signature_valid = Some(true);
+                            self.status.signer_fingerprint = Some(ka.cert().fingerprint().to_hex());
+                        }
+                        Err(e) => {
+                            if self.status.signature_valid != Some(true) {
+                                self.status.signature_valid = Some(false);
+                                self.status.error = Some(e.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DecryptionHelper for Helper<'_> {
+    fn decrypt<D>(
+        &mut self,
+        pkesks: &[PKESK],
+        _skesks: &[SKESK],
+        sym_algo: Option<SymmetricAlgorithm>,
+        mut decrypt: D,
+    ) -> openpgp::Result<Option<Fingerprint>>
+    where
+        D: FnMut(SymmetricAlgorithm, &SessionKey) -> bool,
+    {
+        self.status.encrypted = true;
+
+        for pkesk in pkesks {
+            for cert in self.certs.iter().filter(|c| c.is_tsk()) {
+                for ka in cert.keys().secret() {
+                    if !pkesk.recipient().is_wildcard() && ka.keyid() != *pkesk.recipient() {
+                        continue;
+                    }
+
+                    let mut key = ka.key().clone();
+                    if !key.has_unencrypted_secret() {
+                        let Some(password) = load_passphrase(&cert.fingerprint()) else {
+                            continue;
+                        };
+                        key = match key.decrypt_secret(&password) {
+                            Ok(key) => key,
+                            Err(_) => continue,
+                        };
+                    }
+
+                    let mut pair = key.into_keypair()?;
+                    if pkesk
+                        .decrypt(&mut pair, sym_algo)
+                        .map(|(algo, session_key)| decrypt(algo, &session_key))
+                        .unwrap_or(false)
+                    {
+                        self.status.decrypted = true;
+                        return Ok(Some(cert.fingerprint()));
+                    }
+                }
+            }
+        }
+
+        Err(anyhow!("No secret key available to decrypt this message"))
+    }
+}
+
+/// Decrypt and/or verify an OpenPGP message (binary, armored or cleartext-signed)
+fn open_message(data: &[u8], certs: &[Cert]) -> (Option<Vec<u8>>, CryptoStatus) {
+    let policy = StandardPolicy::new();
+
+    let result = DecryptorBuilder::from_bytes(data).and_then(|builder| {
+        let mut decryptor = builder.with_policy(&policy, None, Helper::new(certs))?;
+        let mut plaintext = Vec::new();
+        decryptor.read_to_end(&mut plaintext)?;
+        Ok((plaintext, decryptor.into_helper().status))
+    });
+
+    match result {
+        Ok((plaintext, status)) => (Some(plaintext), status),
+        Err(e) => {
+            let status = CryptoStatus {
+                error: Some(e.to_string()),
+                ..Default::default()
+            };
+            (None, status)
+        }
+    }
+}
+
+/// Verify a detached signature over `data`
+fn verify_detached(data: &[u8], signature: &[u8], certs: &[Cert]) -> CryptoStatus {
+    let policy = StandardPolicy::new();
+
+    let result = DetachedVerifierBuilder::from_bytes(signature).and_then(|builder| {
+        let mut verifier = builder.with_policy(&policy, None, Helper::new(certs))?;
+        verifier.verify_bytes(data)?;
+        Ok(verifier.into_helper().status)
+    });
+
+    match result {
+        Ok(status) => status,
+        Err(e) => CryptoStatus {
+            signed: true,
+            signature_valid: Some(false),
+            error: Some(e.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// Normalize line endings to CRLF (RFC 3156 canonical form)
+pub(crate) fn canonicalize(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (i, &b) in data.iter().enumerate() {
+        if b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+            out.push(b'\r');
+        }
+        out.push(b);
+    }
+    out
+}
+
+pub(crate) fn protocol_is(part: &mail_parser::MessagePart<'_>, subtype: &str, protocol: &str) -> bool {
+    part.content_type()
+        .map(|ct| {
+            ct.ctype().eq_ignore_ascii_case("multipart")
+                && ct.subtype().map(|s| s.eq_ignore_ascii_case(subtype)).unwrap_or(false)
+                && ct
+                    .attribute("protocol")
+                    .map(|p| p.eq_ignore_ascii_case(protocol))
+                    .unwrap_or(false)
+        })
+        .unwrap_or(false)
+}
+
+pub(crate) fn child_ids(part: &mail_parser::MessagePart<'_>) -> Vec<usize> {
+    match &part.body {
+        PartType::Multipart(ids) => ids.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Extract the readable bodies from a decrypted MIME entity
+fn bodies_from_mime(data: &[u8]) -> ProtectedBody {
+    match mail_parser::MessageParser::default().parse(data) {
+        Some(inner) => ProtectedBody {
+            body_html: inner.body_html(0).map(|s| s.to_string()),
+            body_plain: inner.body_text(0).map(|s| s.to_string()),
+        },
+        None => ProtectedBody {
+            body_html: None,
+            body_plain: Some(String::from_utf8_lossy(data).to_string()),
+        },
+    }
+}
+
+/// Detect and process PGP/MIME or inline PGP content.
+///
+/// Returns `None` for ordinary messages. For protected messages, returns the crypto
+/// status plus replacement bodies when the content could be decrypted.
+pub fn process_message(
+    parsed: &mail_parser::Message<'_>,
+) -> Option<(CryptoStatus, Option<ProtectedBody>)> {
+    let root = parsed.parts.first()?;
+    let raw = parsed.raw_message();
+
+    // PGP/MIME encrypted (RFC 3156 section 4)
+    if protocol_is(root, "encrypted", "application/pgp-encrypted") {
+        let certs = load_certs();
+        let ciphertext = child_ids(root)
+            .get(1)
+            .and_then(|id| parsed.parts.get(*id))
+            .map(|part| part.contents().to_vec())
+            .unwrap_or_default();
+
+        let (plaintext, mut status) = open_message(&ciphertext, &certs);
+        status.encrypted = true;
+
+        let body = plaintext.map(|data| {
+            let inner = mail_parser::MessageParser::default().parse(&data);
+            // Signed-then-encrypted with PGP/MIME nesting
+            if let Some((inner_status, inner_body)) = inner.as_ref().and_then(process_message) {
+                status.signed = inner_status.signed;
+                status.signature_valid = inner_status.signature_valid;
+                status.signer_fingerprint = inner_status.signer_fingerprint;
+                if let Some(body) = inner_body {
+                    return body;
+                }
+            }
+            bodies_from_mime(&data)
+        });
+
+        return Some((status, body));
+    }
+
+    // PGP/MIME signed (RFC 3156 section 5)
+    if protocol_is(root, "signed", "application/pgp-signature") {
+        let ids = child_ids(root);
+        let signed_part = ids.first().and_then(|id| parsed.parts.get(*id))?;
+        let signature = ids.get(1).and_then(|id| parsed.parts.get(*id))?;
+
+        let signed_data = raw.get(signed_part.offset_header..signed_part.offset_end)?;
+        let status = verify_detached(&canonicalize(signed_data), signature.contents(), &load_certs());
+
+        return Some((status, None));
+    }
+
+    // Inline PGP
+    let text = parsed.body_text(0)?;
+    if text.contains("-----BEGIN PGP MESSAGE-----")
+        || text.contains("-----BEGIN PGP SIGNED MESSAGE-----")
+    {
+        let encrypted = text.contains("-----BEGIN PGP MESSAGE-----");
+        let (plaintext, mut status) = open_message(text.as_bytes(), &load_certs());
+        status.encrypted |= encrypted;
+
+        let body = plaintext.map(|data| ProtectedBody {
+            body_html: None,
+            body_plain: Some(String::from_utf8_lossy(&data).to_string()),
+        });
+        return Some((status, body));
+    }
+
+    None
+}
+
+// ========== Compose ==========
+
+/// Unlock the first usable signing key of a certificate
+fn signing_keypair(cert: &Cert) -> Result<KeyPair> {
+    let policy = StandardPolicy::new();
+    let ka = cert
+        .keys()
+        .with_policy(&policy, None)
+        .supported()
+        .alive()
+        .revoked(false)
+        .for_signing()
+        .secret()
+        .next()
+        .context("No usable signing key")?;
+
+    let mut key = ka.key().clone();
+    if !key.has_unencrypted_secret() {
+        let password = load_passphrase(&cert.fingerprint())
+            .context("Signing key is passphrase-protected and no passphrase is stored")?;
+        key = key
+            .decrypt_secret(&password)
+            .context("Failed to unlock signing key")?;
+    }
+
+    key.into_keypair()
+}
+
+/// Produce an armored detached signature (SHA-256) over a MIME entity as formatted for
+/// sending. The trailing CRLF belongs to the following boundary and is not signed.
+pub fn sign_detached(data: &[u8], signer: &Cert) -> Result<String> {
+    let keypair = signing_keypair(signer)?;
+    let mut data = canonicalize(data);
+    if data.ends_with(b"\r\n") {
+        data.truncate(data.len() - 2);
+    }
+
+    let mut sink = Vec::new();
+    {
+        let message = Message::new(&mut sink);
+        let message = Armorer::new(message)
+            .kind(openpgp::armor::Kind::Signature)
+            .build()?;
+        let mut message = Signer::new(message, keypair)
+            .detached()
+            .hash_algo(HashAlgorithm::SHA256)?
+            .build()?;
+        message.write_all(&data)?;
+        message.finalize()?;
+    }
+
+    Ok(String::from_utf8(sink)?)
+}
+
+/// Encrypt `data` to every recipient certificate, optionally signing it, as ASCII armor
+pub fn encrypt(data: &[u8], recipients: &[&Cert], signer: Option<&Cert>) -> Result<String> {
+    let policy = StandardPolicy::new();
+
+    let mut recipient_keys = Vec::new();
+    for cert in recipients {
+        let before = recipient_keys.len();
+        recipient_keys.extend(
+            cert.keys()
+                .with_policy(&policy, None)
+                .supported()
+                .alive()
+                .revoked(false)
+                .for_transport_encryption(),
+        );
+        if recipient_keys.len() == before {
+            return Err(anyhow!("No usable encryption key for {}", cert.fingerprint()));
+        }
+    }
+
+    let keypair = signer.map(signing_keypair).transpose()?;
+
+    let mut sink = Vec::new();
+    {
+        let message = Message::new(&mut sink);
+        let message = Armorer::new(message).build()?;
+        let mut message = Encryptor2::for_recipients(message, recipient_keys).build()?;
+        if let Some(keypair) = keypair {
+            message = Signer::new(message, keypair)
+                .hash_algo(HashAlgorithm::SHA256)?
+                .build()?;
+        }
+        let mut message = LiteralWriter::new(message).build()?;
+        message.write_all(data)?;
+        message.finalize()?;
+    }
+
+    Ok(String::from_utf8(sink)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize(b"a\nb\r\nc\n"), b"a\r\nb\r\nc\r\n".to_vec());
+    }
+
+    #[test]
+    fn test_encrypt_and_open_roundtrip() {
+        let (alice, _) = CertBuilder::general_purpose(None, Some("Alice <alice@example.com>"))
+            .generate()
+            .unwrap();
+        let certs = vec![alice.clone()];
+
+        let armored = encrypt(b"Content-Type: text/plain\r\n\r\nhello", &[&alice], Some(&alice)).unwrap();
+        let (plaintext, status) = open_message(armored.as_bytes(), &certs);
+
+        assert_eq!(plaintext.unwrap(), b"Content-Type: text/plain\r\n\r\nhello".to_vec());
+        assert!(status.decrypted);
+        assert_eq!(status.signature_valid, Some(true));
+        assert_eq!(status.signer_fingerprint, Some(alice.fingerprint().to_hex()));
+    }
+
+    #[test]
+    fn test_detached_signature() {
+        let (alice, _) = CertBuilder::general_purpose(None, Some("alice@example.com"))
+            .generate()
+            .unwrap();
+        let certs = vec![alice.clone()];
+
+        let signature = sign_detached(b"signed body\n", &alice).unwrap();
+        let good = verify_detached(b"signed body", signature.as_bytes(), &certs);
+        let bad = verify_detached(b"tampered", signature.as_bytes(), &certs);
+
+        assert_eq!(good.signature_valid, Some(true));
+        assert_eq!(bad.signature_valid, Some(false));
+        assert!(find_cert_for_email(&certs, "ALICE@example.com").is_some());
+    }
+}
@@ -0,0 +1,422 @@
+//! Rule-based deadline extraction
+//!
+//! Fallback for when no LLM is loaded (and a sanity check on its output): looks for
+//! deadline cues ("by", "due", "deadline", ...) followed by a date expression such as
+//! "Friday", "tomorrow 5pm", "end of month", "Oct 20th" or "2026-10-20", resolved
+//! relative to the date the email was sent.
+
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday};
+
+/// Phrases that introduce a deadline
+const DEADLINE_CUES: &[&str] = &[
+    "deadline",
+    "due",
+    "by",
+    "before",
+    "until",
+    "no later than",
+    "expires",
+    "closes",
+];
+
+const MONTHS: &[(&str, u32)] = &[
+    ("january", 1),
+    ("february", 2),
+    ("march", 3),
+    ("april", 4),
+    ("may", 5),
+    ("june", 6),
+    ("july", 7),
+    ("august", 8),
+    ("september", 9),
+    ("october", 10),
+    ("november", 11),
+    ("december", 12),
+];
+
+/// Words that end a date phrase
+const STOP_WORDS: &[&str] = &["and", "or", "so", "to", "if", "please", "we", "you", "i"];
+
+fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 0).unwrap()
+}
+
+fn close_of_business() -> NaiveTime {
+    NaiveTime::from_hms_opt(17, 0, 0).unwrap()
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    let token = token.trim_end_matches('.');
+    if token.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .find(|(name, _)| name.starts_with(token) && (token.len() >= 3))
+        .filter(|(name, _)| token.len() <= name.len())
+        .map(|(_, n)| *n)
+}
+
+fn parse_weekday(token: &str) -> Option<Weekday> {
+    match token.trim_end_matches('.') {
+        "monday" | "mon" => Some(Weekday::Mon),
+        "tuesday" | "tue" | "tues" => Some(Weekday::Tue),
+        "wednesday" | "wed" => Some(Weekday::Wed),
+        "thursday" | "thu" | "thur" | "thurs" => Some(Weekday::Thu),
+        "friday" | "fri" => Some(Weekday::Fri),
+        "saturday" | "sat" => Some(Weekday::Sat),
+        "sunday" | "sun" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Day of month from "20", "20th", "1st"
+fn parse_day(token: &str) -> Option<u32> {
+    let digits = token
+        .trim_end_matches("st")
+        .trim_end_matches("nd")
+        .trim_end_matches("rd")
+        .trim_end_matches("th");
+    digits.parse::<u32>().ok().filter(|d| (1..=31).contains(d))
+}
+
+fn parse_year(token: &str) -> Option<i32> {
+    token
+        .parse::<i32>()
+        .ok()
+        .filter(|y| (2000..=2100).contains(y))
+}
+
+/// Time of day from "5pm", "5:30pm", "17:00", "noon", "midnight"
+pub(super) fn parse_time(tokens: &[&str]) -> Option<NaiveTime> {
+    let first = *tokens.first()?;
+    match first {
+        "noon" | "midday" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(23, 59, 0),
+        "eod" | "cob" => return Some(close_of_business()),
+        _ => {}
+    }
+
+    // Allow a separate "am"/"pm" token ("5 pm")
+    let (text, suffix) = if let Some(t) = first.strip_suffix("am") {
+        (t, Some("am"))
+    } else if let Some(t) = first.strip_suffix("pm") {
+        (t, Some("pm"))
+    } else {
+        match tokens.get(1) {
+            Some(&"am") => (first, Some("am")),
+            Some(&"pm") => (first, Some("pm")),
+            _ => (first, None),
+        }
+    };
+
+    let (hour, minute) = match text.split_once(':') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        None if suffix.is_some() => (text.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match suffix {
+        Some("pm") if hour < 12 => hour + 12,
+        Some("am") if hour == 12 => 0,
+        _ => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Pick the first date on or after `reference` for a month/day without a year
+fn infer_year(month: u32, day: u32, year: Option<i32>, reference: NaiveDate) -> Option<NaiveDate> {
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year, month, day);
+    }
+    let this_year = NaiveDate::from_ymd_opt(reference.year(), month, day)?;
+    if this_year >= reference {
+        Some(this_year)
+    } else {
+        NaiveDate::from_ymd_opt(reference.year() + 1, month, day)
+    }
+}
+
+fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap() - Duration::days(1)
+}
+
+fn next_weekday(from: NaiveDate, target: Weekday, force_next_week: bool) -> NaiveDate {
+    let current = from.weekday().num_days_from_monday() as i64;
+    let wanted = target.num_days_from_monday() as i64;
+    let mut ahead = (wanted - current + 7) % 7;
+    if force_next_week && ahead == 0 {
+        ahead = 7;
+    }
+    from + Duration::days(ahead)
+}
+
+/// Parse a date expression at the start of `tokens`, returning the date, an optional
+/// time, and how many tokens were consumed
+pub(super) fn parse_date(tokens: &[&str], reference: NaiveDate) -> Option<(NaiveDate, Option<NaiveTime>, usize)> {
+    let mut i = 0;
+    let mut force_next = false;
+    while let Some(&word) = tokens.get(i) {
+        match word {
+            "on" | "the" | "this" | "of" => i += 1,
+            "next" => {
+                force_next = true;
+                i += 1;
+            }
+            _ => break,
+        }
+    }
+    let first = *tokens.get(i)?;
+
+    match first {
+        "today" | "tonight" => return Some((reference, None, i + 1)),
+        "eod" | "cob" => return Some((reference, Some(close_of_business()), i + 1)),
+        "tomorrow" => return Some((reference + Duration::days(1), None, i + 1)),
+        "end" => {
+            // "end of (the) day/week/month/year"
+            let rest: Vec<&str> = tokens[i + 1..]
+                .iter()
+                .copied()
+                .filter(|t| *t != "of" && *t != "the" && *t != "this")
+                .take(1)
+                .collect();
+            let consumed = tokens[i + 1..]
+                .iter()
+                .position(|t| matches!(*t, "day" | "week" | "month" | "year"))
+                .map(|p| i + 2 + p)?;
+            return match rest.first().copied() {
+                Some("day") => Some((reference, Some(close_of_business()), consumed)),
+                Some("week") => Some((next_weekday(reference, Weekday::Fri, false), Some(close_of_business()), consumed)),
+                Some("month") => Some((last_day_of_month(reference), None, consumed)),
+                Some("year") => Some((NaiveDate::from_ymd_opt(reference.year(), 12, 31)?, None, consumed)),
+                _ => None,
+            };
+        }
+        "in" | "within" => {
+            let n: i64 = tokens.get(i + 1)?.parse().ok()?;
+            let unit = *tokens.get(i + 2)?;
+            let days = if unit.starts_with("day") {
+                n
+            } else if unit.starts_with("week") {
+                n * 7
+            } else {
+                return None;
+            };
+            return Some((reference + Duration::days(days), None, i + 3));
+        }
+        _ => {}
+    }
+
+    if let Some(weekday) = parse_weekday(first) {
+        return Some((next_weekday(reference, weekday, force_next), None, i + 1));
+    }
+
+    // ISO: 2026-10-20
+    if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+        return Some((date, None, i + 1));
+    }
+
+    // US numeric: 10/20, 10/20/2026, 10/20/26
+    let parts: Vec<&str> = first.split('/').collect();
+    if parts.len() == 2 || parts.len() == 3 {
+        let month: u32 = parts[0].parse().ok()?;
+        let day: u32 = parts[1].parse().ok()?;
+        let year = match parts.get(2) {
+            Some(y) if y.len() == 2 => y.parse::<i32>().ok().map(|y| 2000 + y),
+            Some(y) => parse_year(y),
+            None => None,
+        };
+        return Some((infer_year(month, day, year, reference)?, None, i + 1));
+    }
+
+    // "October 20th, 2026"
+    if let Some(month) = parse_month(first) {
+        let day = parse_day(tokens.get(i + 1)?)?;
+        let year = tokens.get(i + 2).and_then(|t| parse_year(t));
+        let consumed = i + 2 + year.is_some() as usize;
+        return Some((infer_year(month, day, year, reference)?, None, consumed));
+    }
+
+    // "20th (of) October 2026"
+    if let Some(day) = parse_day(first) {
+        let mut j = i + 1;
+        if tokens.get(j) == Some(&"of") {
+            j += 1;
+        }
+        let month = parse_month(tokens.get(j)?)?;
+        let year = tokens.get(j + 1).and_then(|t| parse_year(t));
+        let consumed = j + 1 + year.is_some() as usize;
+        return Some((infer_year(month, day, year, reference)?, None, consumed));
+    }
+
+    None
+}
+
+/// Split text into lowercase tokens, keeping `:`, `/` and `-` inside tokens
+pub(super) fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | '(' | ')' | '!' | '?'))
+        .map(|t| t.trim_matches(|c: char| c == '.' || c == '"' || c == '\'').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Find the earliest deadline on or after `reference` mentioned in `text`
+pub fn extract_deadline_naive(text: &str, reference: NaiveDateTime) -> Option<NaiveDateTime> {
+    let owned = tokenize(text);
+    let tokens: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+    let mut best: Option<NaiveDateTime> = None;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let cue_len = DEADLINE_CUES
+            .iter()
+            .map(|cue| cue.split(' ').collect::<Vec<_>>())
+            .find(|words| tokens[idx..].starts_with(words))
+            .map(|words| words.len());
+        let Some(cue_len) = cue_len else {
+            continue;
+        };
+        if *token == "due" && tokens.get(idx + 1) == Some(&"to") {
+            continue; // "due to" is causal, not a deadline
+        }
+
+        let mut start = idx + cue_len;
+        // "deadline is ...", "due on ...", "due date: ..."
+        while matches!(tokens.get(start), Some(&"is") | Some(&"date") | Some(&"date:") | Some(&"by")) {
+            start += 1;
+        }
+        let window: Vec<&str> = tokens[start.min(tokens.len())..]
+            .iter()
+            .copied()
+            .take_while(|t| !STOP_WORDS.contains(t))
+            .take(8)
+            .collect();
+
+        let Some((date, time, consumed)) = parse_date(&window, reference.date()) else {
+            continue;
+        };
+
+        let mut rest = &window[consumed.min(window.len())..];
+        if rest.first() == Some(&"at") {
+            rest = &rest[1..];
+        }
+        let time = parse_time(rest).or(time).unwrap_or_else(end_of_day);
+        let deadline = date.and_time(time);
+
+        if deadline >= reference && best.map(|b| deadline < b).unwrap_or(true) {
+            best = Some(deadline);
+        }
+    }
+
+    best
+}
+
+/// Parse a timestamp string produced by the LLM ("YYYY-MM-DD HH:MM" or "YYYY-MM-DD")
+pub fn parse_llm_datetime(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(end_of_day()))
+        })
+}
+
+/// Convert a Unix timestamp to local naive time (the frame used for parsing)
+pub fn local_naive(timestamp: i64) -> Option<NaiveDateTime> {
+    chrono::DateTime::from_timestamp(timestamp, 0).map(|dt| dt.with_timezone(&Local).naive_local())
+}
+
+/// Convert local naive time back to a Unix timestamp
+pub fn to_timestamp(naive: NaiveDateTime) -> Option<i64> {
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp())
+}
+
+/// Extract a deadline from `text`, resolving relative dates against `sent_at`
+pub fn extract_deadline(text: &str, sent_at: i64) -> Option<i64> {
+    extract_deadline_naive(text, local_naive(sent_at)?).and_then(to_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wednesday, 14 October 2026, 09:00
+    fn reference() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_weekday_and_time() {
+        assert_eq!(
+            extract_deadline_naive("Please send it by Friday at 5pm.", reference()),
+            Some(at(2026, 10, 16, 17, 0))
+        );
+        assert_eq!(
+            extract_deadline_naive("Due next Wednesday", reference()),
+            Some(at(2026, 10, 21, 23, 59))
+        );
+    }
+
+    #[test]
+    fn test_relative_phrases() {
+        assert_eq!(
+            extract_deadline_naive("Need this by EOD", reference()),
+            Some(at(2026, 10, 14, 17, 0))
+        );
+        assert_eq!(
+            extract_deadline_naive("Submit before tomorrow 10:30am", reference()),
+            Some(at(2026, 10, 15, 10, 30))
+        );
+        assert_eq!(
+            extract_deadline_naive("Reports are due by the end of the month", reference()),
+            Some(at(2026, 10, 31, 23, 59))
+        );
+    }
+
+    #[test]
+    fn test_explicit_dates() {
+        assert_eq!(
+            extract_deadline_naive("The deadline is October 20th, 2026.", reference()),
+            Some(at(2026, 10, 20, 23, 59))
+        );
+        assert_eq!(
+            extract_deadline_naive("Payment due 2026-11-01", reference()),
+            Some(at(2026, 11, 1, 23, 59))
+        );
+        // Month/day already past this year rolls over
+        assert_eq!(
+            extract_deadline_naive("Register by 3 March", reference()),
+            Some(at(2027, 3, 3, 23, 59))
+        );
+    }
+
+    #[test]
+    fn test_no_deadline() {
+        assert_eq!(extract_deadline_naive("Delayed due to weather on Friday", reference()), None);
+        assert_eq!(extract_deadline_naive("Thanks for the update!", reference()), None);
+    }
+
+    #[test]
+    fn test_parse_llm_datetime() {
+        assert_eq!(parse_llm_datetime("2026-10-20 14:00"), Some(at(2026, 10, 20, 14, 0)));
+        assert_eq!(parse_llm_datetime("2026-10-20"), Some(at(2026, 10, 20, 23, 59)));
+        assert_eq!(parse_llm_datetime("NONE"), None);
+    }
+}
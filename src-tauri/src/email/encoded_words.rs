@@ -0,0 +1,59 @@
+//! Header text from IMAP envelopes
+//!
+//! ENVELOPE returns subjects and display names as the raw header bytes, so non-ASCII
+//! text arrives as RFC 2047 encoded-words (`=?UTF-8?B?...?=`) or, from sloppy senders,
+//! as unencoded 8-bit bytes. Full messages are decoded by mail-parser, which also reads
+//! RFC 2231 attachment filenames; this applies the same decoding to the list view.
+
+use mail_parser::parsers::MessageStream;
+use mail_parser::HeaderValue;
+
+/// Decode the encoded-words in a header value, in whatever charset they name. Bytes
+/// that aren't UTF-8 outside of encoded-words are replaced rather than failing the value.
+pub fn decode_header_text(raw: &[u8]) -> String {
+    // The parser reads one header line up to its line break
+    let mut line = raw.to_vec();
+    line.push(b'\n');
+    match MessageStream::new(&line).parse_unstructured() {
+        HeaderValue::Text(text) => text.into_owned(),
+        _ => String::from_utf8_lossy(raw).trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text() {
+        assert_eq!(decode_header_text(b"Quarterly report"), "Quarterly report");
+        assert_eq!(decode_header_text(b""), "");
+    }
+
+    #[test]
+    fn test_encoded_words() {
+        assert_eq!(
+            decode_header_text(b"=?UTF-8?B?R3LDvMOfZSBhdXMgS8O2bG4=?="),
+            "Grüße aus Köln"
+        );
+        assert_eq!(
+            decode_header_text(b"=?ISO-8859-1?Q?Caf=E9_au_lait?="),
+            "Café au lait"
+        );
+        // Whitespace between adjacent encoded-words is dropped
+        assert_eq!(
+            decode_header_text(b"=?UTF-8?Q?Jo=C3=A3o?= =?UTF-8?Q?_Silva?="),
+            "João Silva"
+        );
+        assert_eq!(
+            decode_header_text(b"Re: =?UTF-8?B?5pel5pys6Kqe?= notes"),
+            "Re: 日本語 notes"
+        );
+    }
+
+    #[test]
+    fn test_raw_8bit() {
+        assert_eq!(decode_header_text("Grüße".as_bytes()), "Grüße");
+        assert!(decode_header_text(b"Caf\xe9").starts_with("Caf"));
+    }
+}
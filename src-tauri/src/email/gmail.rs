@@ -0,0 +1,284 @@
+//! Gmail IMAP extensions
+//!
+//! Gmail keeps labels, its own thread ids and inbox categories outside standard IMAP. The
+//! `X-GM-MSGID`, `X-GM-THRID` and `X-GM-LABELS` FETCH items carry the first three; the
+//! category tabs are only reachable through `X-GM-RAW` searches. This module builds those
+//! commands and parses their responses, which async-imap leaves unparsed.
+
+use std::collections::HashMap;
+
+use super::labels::keyword_for;
+
+/// Gmail inbox tabs besides Primary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GmailCategory {
+    Social,
+    Promotions,
+    Updates,
+    Forums,
+}
+
+impl GmailCategory {
+    pub const ALL: [GmailCategory; 4] = [
+        GmailCategory::Social,
+        GmailCategory::Promotions,
+        GmailCategory::Updates,
+        GmailCategory::Forums,
+    ];
+
+    /// `X-GM-RAW` search matching the tab
+    pub fn search_query(&self) -> String {
+        format!("X-GM-RAW \"category:{}\"", self.id())
+    }
+
+    /// Label stored on emails in the tab, named like the Gmail API's
+    pub fn label(&self) -> &'static str {
+        match self {
+            GmailCategory::Social => "CATEGORY_SOCIAL",
+            GmailCategory::Promotions => "CATEGORY_PROMOTIONS",
+            GmailCategory::Updates => "CATEGORY_UPDATES",
+            GmailCategory::Forums => "CATEGORY_FORUMS",
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            GmailCategory::Social => "social",
+            GmailCategory::Promotions => "promotions",
+            GmailCategory::Updates => "updates",
+            GmailCategory::Forums => "forums",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.id().eq_ignore_ascii_case(id))
+    }
+}
+
+/// Start of the local thread ids made from Gmail's
+const LOCAL_THREAD_PREFIX: &str = "gm-";
+
+/// Gmail metadata of one message
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GmailMetadata {
+    pub uid: u32,
+    pub msg_id: Option<u64>,
+    pub thread_id: Option<u64>,
+    /// Labels as Gmail reports them; system labels keep their backslash (`\Inbox`)
+    pub labels: Vec<String>,
+}
+
+impl GmailMetadata {
+    /// Thread id for the local database; stable across the whole conversation, unlike the
+    /// header-based one
+    pub fn local_thread_id(&self) -> Option<String> {
+        self.thread_id
+            .map(|id| format!("{}{:x}", LOCAL_THREAD_PREFIX, id))
+    }
+}
+
+/// Whether a local thread id is one of Gmail's
+pub fn is_thread_id(thread_id: &str) -> bool {
+    thread_id.starts_with(LOCAL_THREAD_PREFIX)
+}
+
+/// FETCH command for the Gmail metadata of `uid_set`
+pub fn fetch_command(uid_set: &str) -> String {
+    format!(
+        "UID FETCH {} (UID X-GM-MSGID X-GM-THRID X-GM-LABELS)",
+        uid_set
+    )
+}
+
+/// Local label for a Gmail label: system labels become the upper-case names used for IMAP
+/// flags (`\Inbox` → `INBOX`), user labels the keyword a local label of that name uses
+pub fn local_label(label: &str) -> String {
+    match label.strip_prefix('\\') {
+        Some(system) => system.to_uppercase(),
+        None => keyword_for(label).unwrap_or_else(|| label.to_string()),
+    }
+}
+
+/// Parse the untagged responses of `fetch_command`, keyed by UID
+pub fn parse_fetch_response(raw: &str) -> HashMap<u32, GmailMetadata> {
+    let mut messages = HashMap::new();
+    let mut rest = raw;
+
+    while let Some(start) = rest.find(" FETCH (") {
+        let mut tokens = Tokenizer::new(&rest[start + " FETCH (".len()..]);
+        let mut meta = GmailMetadata::default();
+
+        while let Some(token) = tokens.next_token() {
+            let Token::Atom(name) = token else {
+                if token == Token::Close {
+                    break;
+                }
+                continue;
+            };
+            match name.to_ascii_uppercase().as_str() {
+                "UID" => meta.uid = tokens.next_number().unwrap_or(0) as u32,
+                "X-GM-MSGID" => meta.msg_id = tokens.next_number(),
+                "X-GM-THRID" => meta.thread_id = tokens.next_number(),
+                "X-GM-LABELS" => meta.labels = tokens.next_list(),
+                _ => {}
+            }
+        }
+
+        rest = tokens.remaining();
+        if meta.uid != 0 {
+            messages.insert(meta.uid, meta);
+        }
+    }
+
+    messages
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Atom(String),
+    Text(String),
+    Open,
+    Close,
+}
+
+/// Just enough of the IMAP grammar for FETCH attribute lists
+struct Tokenizer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn remaining(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let rest = self.remaining();
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+
+        let c = trimmed.chars().next()?;
+        match c {
+            '(' => {
+                self.pos += 1;
+                Some(Token::Open)
+            }
+            ')' => {
+                self.pos += 1;
+                Some(Token::Close)
+            }
+            '"' => {
+                let mut text = String::new();
+                let mut escaped = false;
+                for (i, ch) in trimmed.char_indices().skip(1) {
+                    if escaped {
+                        text.push(ch);
+                        escaped = false;
+                    } else if ch == '\\' {
+                        escaped = true;
+                    } else if ch == '"' {
+                        self.pos += i + 1;
+                        return Some(Token::Text(text));
+                    } else {
+                        text.push(ch);
+                    }
+                }
+                self.pos += trimmed.len();
+                Some(Token::Text(text))
+            }
+            // Literal: {n}\r\n followed by n bytes
+            '{' => {
+                let close = trimmed.find('}')?;
+                let len: usize = trimmed[1..close].parse().ok()?;
+                let body_start = trimmed[close + 1..].find('\n').map(|i| close + 1 + i + 1)?;
+                let body_end = (body_start + len).min(trimmed.len());
+                let text = trimmed.get(body_start..body_end)?.to_string();
+                self.pos += body_end;
+                Some(Token::Text(text))
+            }
+            _ => {
+                let end = trimmed
+                    .find(|ch: char| ch.is_whitespace() || ch == '(' || ch == ')')
+                    .unwrap_or(trimmed.len());
+                self.pos += end;
+                Some(Token::Atom(trimmed[..end].to_string()))
+            }
+        }
+    }
+
+    fn next_number(&mut self) -> Option<u64> {
+        match self.next_token()? {
+            Token::Atom(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// A parenthesized list of atoms and strings
+    fn next_list(&mut self) -> Vec<String> {
+        let mut items = Vec::new();
+        if self.next_token() != Some(Token::Open) {
+            return items;
+        }
+        while let Some(token) = self.next_token() {
+            match token {
+                Token::Atom(value) | Token::Text(value) => items.push(value),
+                Token::Close => break,
+                Token::Open => {}
+            }
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fetch_response() {
+        let raw = "* 12 FETCH (X-GM-THRID 1266894439832287888 X-GM-MSGID 1278455344230334865 \
+                   X-GM-LABELS (\"\\\\Important\" \\Inbox Work \"Project X\") UID 302)\r\n\
+                   * 13 FETCH (UID 303 X-GM-MSGID 17 X-GM-THRID 1266894439832287888 X-GM-LABELS ())\r\n\
+                   A4 OK Success\r\n";
+        let parsed = parse_fetch_response(raw);
+        assert_eq!(parsed.len(), 2);
+
+        let first = &parsed[&302];
+        assert_eq!(first.msg_id, Some(1278455344230334865));
+        assert_eq!(
+            first.labels,
+            vec!["\\Important", "\\Inbox", "Work", "Project X"]
+        );
+        assert_eq!(first.local_thread_id(), parsed[&303].local_thread_id());
+        assert!(parsed[&303].labels.is_empty());
+    }
+
+    #[test]
+    fn test_literal_labels() {
+        let raw = "* 1 FETCH (UID 7 X-GM-LABELS ({5}\r\nCaf\u{e9} Travel))\r\n";
+        let parsed = parse_fetch_response(raw);
+        assert_eq!(parsed[&7].labels, vec!["Café", "Travel"]);
+    }
+
+    #[test]
+    fn test_labels_and_categories() {
+        assert_eq!(local_label("\\Inbox"), "INBOX");
+        assert_eq!(local_label("\\Important"), "IMPORTANT");
+        assert_eq!(local_label("Project X"), "Project_X");
+        assert_eq!(
+            GmailCategory::Promotions.search_query(),
+            "X-GM-RAW \"category:promotions\""
+        );
+        assert_eq!(
+            GmailCategory::from_id("Social"),
+            Some(GmailCategory::Social)
+        );
+        assert_eq!(GmailCategory::from_id("primary"), None);
+    }
+}
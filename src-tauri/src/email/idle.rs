@@ -1,12 +1,20 @@
 use crate::auth::storage::{get_account_tokens, get_app_password};
+use crate::db::EmailDatabase;
 use crate::email::imap_client::{ImapClient, ImapCredentials};
+use crate::email::notifications::NewMail;
+use crate::email::provider::EmailProvider;
 use crate::email::server_presets::{ProviderType, ServerConfig};
+use crate::email::sync_window::WatchMode;
+use crate::email::types::EmailListItem;
+use crate::security;
+use chrono::{Local, Timelike};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::{watch, Mutex};
 use tokio::time::{sleep, Duration};
+use tracing::{debug, info, warn};
 
 /// Event payload emitted when new mail arrives
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,15 +23,33 @@ pub struct NewMailEvent {
     pub folder: String,
 }
 
+/// Event payload emitted for new mail that should raise a desktop notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailAlertEvent {
+    pub account_id: String,
+    pub email_id: String,
+    pub from: String,
+    pub subject: String,
+    pub snippet: String,
+}
+
+type DbState = Arc<std::sync::Mutex<Option<EmailDatabase>>>;
+
+/// Newest INBOX messages checked for alerts after new mail arrives
+const ALERT_LOOKBACK: u32 = 10;
+
+/// RFC 2177: IDLE should be re-issued every 29 minutes at most
+const IDLE_TIMEOUT_SECS: u64 = 29 * 60;
+
+/// Wait before reconnecting after a failure
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
 /// Manages IMAP IDLE connections for all accounts
 pub struct IdleManager {
     /// Per-account-folder shutdown senders (key: "account_id:folder")
     shutdown_senders: Arc<Mutex<HashMap<String, watch::Sender<bool>>>>,
 }
 
-/// List of folders to monitor for each account
-const MONITORED_FOLDERS: &[&str] = &["INBOX", "Sent", "Drafts", "Trash", "Spam"];
-
 impl IdleManager {
     pub fn new() -> Self {
         Self {
@@ -31,7 +57,9 @@ impl IdleManager {
         }
     }
 
-    /// Start IDLE monitoring for an account (all folders)
+    /// Start monitoring an account's `folders`, the ones its sync settings watch, in IDLE
+    /// or by polling as its sync settings choose
+    #[allow(clippy::too_many_arguments)]
     pub async fn start_idle<R: tauri::Runtime>(
         &self,
         app: AppHandle<R>,
@@ -40,12 +68,14 @@ impl IdleManager {
         provider: ProviderType,
         server_config: ServerConfig,
         auth_type: String,
+        folders: Vec<String>,
+        mode: WatchMode,
     ) {
         // Stop existing IDLE connections for this account
         self.stop_idle(&account_id).await;
 
         // Start IDLE monitoring for each folder
-        for folder in MONITORED_FOLDERS {
+        for folder in &folders {
             self.start_folder_idle(
                 app.clone(),
                 account_id.clone(),
@@ -54,12 +84,14 @@ impl IdleManager {
                 server_config.clone(),
                 auth_type.clone(),
                 folder,
+                mode,
             )
             .await;
         }
     }
 
-    /// Start IDLE monitoring for a specific folder
+    /// Start monitoring a specific folder
+    #[allow(clippy::too_many_arguments)]
     async fn start_folder_idle<R: tauri::Runtime>(
         &self,
         app: AppHandle<R>,
@@ -69,6 +101,7 @@ impl IdleManager {
         server_config: ServerConfig,
         auth_type: String,
         folder: &str,
+        mode: WatchMode,
     ) {
         let folder_key = format!("{}:{}", account_id, folder);
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
@@ -82,7 +115,7 @@ impl IdleManager {
         let folder = folder.to_string();
 
         tokio::spawn(async move {
-            idle_loop(
+            watch_loop(
                 app,
                 account_id,
                 email,
@@ -90,12 +123,23 @@ impl IdleManager {
                 server_config,
                 auth_type,
                 folder,
+                mode,
                 shutdown_rx,
             )
             .await;
         });
     }
 
+    /// Whether any folder of an account is being monitored
+    pub async fn is_monitoring(&self, account_id: &str) -> bool {
+        let prefix = format!("{}:", account_id);
+        self.shutdown_senders
+            .lock()
+            .await
+            .keys()
+            .any(|key| key.starts_with(&prefix))
+    }
+
     /// Stop IDLE monitoring for an account (all folders)
     pub async fn stop_idle(&self, account_id: &str) {
         let mut senders = self.shutdown_senders.lock().await;
@@ -123,8 +167,12 @@ impl IdleManager {
     }
 }
 
-/// The IDLE loop for a single folder in an account
-async fn idle_loop<R: tauri::Runtime>(
+/// The monitor of a single folder in an account. It stays connected between waits and
+/// reconnects after a failure. Whether it waits in IDLE or polls, a change is reported
+/// the same way, by `report_new_mail`.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(name = "idle", skip_all, fields(account = %account_id, folder = %folder))]
+async fn watch_loop<R: tauri::Runtime>(
     app: AppHandle<R>,
     account_id: String,
     email: String,
@@ -132,16 +180,16 @@ async fn idle_loop<R: tauri::Runtime>(
     server_config: ServerConfig,
     auth_type: String,
     folder: String,
+    mode: WatchMode,
     mut shutdown_rx: watch::Receiver<bool>,
 ) {
-    // RFC 2177: IDLE should be re-issued every 29 minutes max
-    let idle_timeout_secs = 29 * 60;
-    let retry_delay = Duration::from_secs(30);
+    // Highest INBOX UID already considered for alerts
+    let mut alerted_uid = None;
 
-    loop {
+    'connection: loop {
         // Check shutdown
         if *shutdown_rx.borrow() {
-            println!("[IDLE:{}:{}] Shutdown signal received", account_id, folder);
+            info!("Shutdown signal received");
             break;
         }
 
@@ -153,11 +201,8 @@ async fn idle_loop<R: tauri::Runtime>(
                     access_token: tokens.access_token,
                 },
                 Err(e) => {
-                    eprintln!(
-                        "[IDLE:{}:{}] Failed to get OAuth tokens: {}. Retrying...",
-                        account_id, folder, e
-                    );
-                    sleep(retry_delay).await;
+                    warn!("Failed to get OAuth tokens: {}. Retrying...", e);
+                    sleep(RETRY_DELAY).await;
                     continue;
                 }
             }
@@ -168,11 +213,8 @@ async fn idle_loop<R: tauri::Runtime>(
                     password,
                 },
                 Err(e) => {
-                    eprintln!(
-                        "[IDLE:{}:{}] Failed to get password: {}. Retrying...",
-                        account_id, folder, e
-                    );
-                    sleep(retry_delay).await;
+                    warn!("Failed to get password: {}. Retrying...", e);
+                    sleep(RETRY_DELAY).await;
                     continue;
                 }
             }
@@ -189,44 +231,135 @@ async fn idle_loop<R: tauri::Runtime>(
         // Connect
         match client.reconnect().await {
             Ok(()) => {
-                println!("[IDLE:{}:{}] Connected, starting IDLE", account_id, folder);
+                info!("Connected, watching for new mail ({:?})", mode);
             }
             Err(e) => {
-                eprintln!(
-                    "[IDLE:{}:{}] Connection failed: {}. Retrying in 30s...",
-                    account_id, folder, e
-                );
-                sleep(retry_delay).await;
+                warn!("Connection failed: {}. Retrying in 30s...", e);
+                sleep(RETRY_DELAY).await;
                 continue;
             }
         }
 
-        // IDLE loop (re-issue every 29 min)
-        match client.idle_wait(&folder, idle_timeout_secs).await {
-            Ok(true) => {
-                // New mail detected
-                println!("[IDLE:{}:{}] New mail detected", account_id, folder);
-                let _ = app.emit(
-                    "email:new_mail",
-                    NewMailEvent {
-                        account_id: account_id.clone(),
-                        folder: folder.clone(),
-                    },
-                );
-            }
-            Ok(false) => {
-                // Timeout — re-issue IDLE
-                println!("[IDLE:{}:{}] IDLE timeout, re-issuing", account_id, folder);
-            }
-            Err(e) => {
-                eprintln!(
-                    "[IDLE:{}:{}] IDLE error: {}. Reconnecting in 30s...",
-                    account_id, folder, e
-                );
-                sleep(retry_delay).await;
+        // Folder status at the last poll
+        let mut last_status = None;
+        loop {
+            let wait = async {
+                match mode {
+                    WatchMode::Idle => client.idle_wait(&folder, IDLE_TIMEOUT_SECS).await,
+                    WatchMode::Poll(interval) => {
+                        if last_status.is_some() {
+                            sleep(interval).await;
+                        }
+                        client.folder_changed(&folder, &mut last_status).await
+                    }
+                }
+            };
+            let changed = tokio::select! {
+                changed = wait => changed,
+                _ = shutdown_rx.changed() => break 'connection,
+            };
+
+            match changed {
+                Ok(true) => {
+                    info!("New mail detected");
+                    report_new_mail(&app, &client, &account_id, &folder, &mut alerted_uid).await;
+                }
+                Ok(false) => {
+                    // IDLE timed out or nothing changed since the last poll
+                    debug!("No change, waiting again");
+                }
+                Err(e) => {
+                    warn!("Watch error: {}. Reconnecting in 30s...", e);
+                    sleep(RETRY_DELAY).await;
+                    continue 'connection;
+                }
             }
         }
     }
 
-    println!("[IDLE:{}:{}] IDLE loop exited", account_id, folder);
+    info!("Monitor exited");
+}
+
+/// Emit `email:new_mail` for a changed folder, and `email:notify` for new INBOX mail
+async fn report_new_mail<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    client: &ImapClient,
+    account_id: &str,
+    folder: &str,
+    alerted_uid: &mut Option<u32>,
+) {
+    let _ = app.emit(
+        "email:new_mail",
+        NewMailEvent {
+            account_id: account_id.to_string(),
+            folder: folder.to_string(),
+        },
+    );
+    if folder == "INBOX" {
+        match client.list_messages(folder, ALERT_LOOKBACK, 0).await {
+            Ok(items) => alert_new_mail(app, account_id, &items, alerted_uid),
+            Err(e) => warn!("Failed to list new mail: {}", e),
+        }
+    }
+}
+
+/// Emit `email:notify` for unread messages newer than `alerted_uid` that pass the
+/// account's notification settings. Without an `alerted_uid` only the newest message
+/// is known to be new.
+fn alert_new_mail<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    account_id: &str,
+    items: &[EmailListItem],
+    alerted_uid: &mut Option<u32>,
+) {
+    let uid_of = |item: &EmailListItem| item.id.rsplit(':').next()?.parse::<u32>().ok();
+    let Some(newest) = items.iter().filter_map(uid_of).max() else {
+        return;
+    };
+    let since = alerted_uid.unwrap_or(newest - 1);
+    *alerted_uid = Some(since.max(newest));
+
+    let db = app.state::<DbState>();
+    let db_lock = db.lock().unwrap();
+    let Some(database) = db_lock.as_ref() else {
+        return;
+    };
+    let settings = match database.get_notification_settings(account_id) {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Failed to load notification settings: {}", e);
+            return;
+        }
+    };
+    let now = Local::now();
+    let minute = now.hour() * 60 + now.minute();
+    // A locked app only tells that mail arrived
+    let locked = security::status().locked;
+    let shown = |text: &String| if locked { String::new() } else { text.clone() };
+
+    for item in items {
+        if item.is_read || !uid_of(item).is_some_and(|uid| uid > since) {
+            continue;
+        }
+        let insight = database.get_email_priority(&item.id).ok().flatten();
+        let mail = NewMail {
+            from_email: &item.from_email,
+            priority: insight.as_ref().map(|(priority, _)| priority.as_str()),
+            category: insight
+                .as_ref()
+                .and_then(|(_, category)| category.as_deref()),
+        };
+        if settings.should_notify(&mail, minute) {
+            let _ = app.emit(
+                "email:notify",
+                MailAlertEvent {
+                    account_id: account_id.to_string(),
+                    email_id: item.id.clone(),
+                    from: shown(&item.from),
+                    subject: shown(&item.subject),
+                    snippet: shown(&item.snippet),
+                },
+            );
+        }
+    }
 }
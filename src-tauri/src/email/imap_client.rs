@@ -1,24 +1,112 @@
 use anyhow::{Context, Result};
 use async_imap::extensions::idle::IdleResponse;
-use async_imap::types::{Fetch, Flag};
-use async_native_tls::TlsConnector;
+use async_imap::types::{Fetch, Flag, NameAttribute};
 use futures::StreamExt;
-use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use lettre::message::header::{ContentType, HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart, SinglePart};
 use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::client::{Certificate, CertificateStore, Tls, TlsParameters};
 use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
-use mail_parser::MessageParser;
+use mail_parser::{MessageParser, MimeHeaders};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
-use tokio_util::compat::TokioAsyncReadCompatExt;
-
+use tracing::{info, warn};
+
+use super::attachments::attachment_kind;
+use super::capabilities::{FlagSync, FolderSyncState, MoveStrategy, ServerCapabilities};
+use super::crypto::{self, process_message};
+use super::encoded_words::decode_header_text;
+use super::gmail::{self, GmailCategory};
+use super::inline::extract_inline_parts;
+use super::labels::{gmail_label_arg, is_user_keyword};
+use super::links::extract_links;
+use super::preview;
 use super::provider::{EmailProvider, ImapFlag};
-use super::server_presets::{AuthType, ProviderType, ServerConfig};
-use super::types::{Email, EmailListItem, Folder, SpecialFolder};
+use super::quotes;
+use super::receipts::{self, DeliveryFailure, RECEIPT_REQUEST_HEADER};
+use super::server_presets::{
+    imap_uses_starttls, smtp_uses_implicit_tls, AuthType, ProviderType, ServerConfig,
+};
+use super::shipments;
+use super::smime::verify_message;
+use super::special_folders;
+use super::threading;
+use super::throttle::{self, RateLimiter, RetryPolicy};
+use super::tls::{self, Starttls, TlsStream};
+use super::types::{Attachment, Email, EmailListItem, Folder, FolderStatus, Label};
+use super::utf7;
+use crate::db::search_query::SearchQuery;
+
+type ImapSession = async_imap::Session<TlsStream>;
+
+/// Compose-side OpenPGP options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutgoingCrypto {
+    pub sign: bool,
+    pub encrypt: bool,
+}
+
+/// Bytes requested per FETCH when downloading a message with progress
+const FETCH_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Length of the preview shown in the email list
+const SNIPPET_CHARS: usize = 200;
+
+/// How long a folder's Gmail category membership is reused before searching again
+const GMAIL_CATEGORY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Message fetches sent per second for one account, well under Gmail's limits
+const FETCHES_PER_SECOND: u32 = 5;
+
+/// Extra connections bulk fetches run over side by side. Gmail allows 15 per account,
+/// and IDLE and the main session take some.
+const FETCH_LANES: usize = 3;
+
+/// Sessions unused this long get a NOOP from the keepalive task, well inside the 30
+/// minutes after which servers may log out an idle client (RFC 3501 §5.4)
+pub const KEEPALIVE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Sessions unused this long are checked with a NOOP before the next command, in case
+/// the server or a NAT in between dropped them in the meantime
+const CHECK_SESSION_AFTER: Duration = Duration::from_secs(2 * 60);
+
+/// How long a NOOP may take before the session is given up on
+const NOOP_TIMEOUT: Duration = Duration::from_secs(10);
 
-/// Type alias for the TLS stream using tokio compat
-type ImapTlsStream = async_native_tls::TlsStream<tokio_util::compat::Compat<TcpStream>>;
-type ImapSession = async_imap::Session<ImapTlsStream>;
+/// Which Gmail category tab each message of a folder is in
+struct GmailCategoryCache {
+    fetched_at: Instant,
+    /// The folder's UIDNEXT when searched; newer messages aren't covered
+    uid_next: u32,
+    categories: HashMap<u32, GmailCategory>,
+}
+
+/// Thread id of the messages answering `message_id`, matching what sync assigns replies
+pub fn thread_id_for(message_id: &str) -> String {
+    format!("{:x}", md5::compute(message_id.as_bytes()))
+}
+
+/// Message body before it is attached to the outgoing message (or wrapped by PGP/MIME)
+enum MimeBody {
+    Single(SinglePart),
+    Multi(MultiPart),
+}
+
+impl MimeBody {
+    fn formatted(&self) -> Vec<u8> {
+        match self {
+            MimeBody::Single(part) => part.formatted(),
+            MimeBody::Multi(part) => part.formatted(),
+        }
+    }
+}
+
+fn content_type(value: &str) -> Result<ContentType> {
+    ContentType::parse(value).map_err(|e| anyhow::anyhow!("Invalid content type {}: {:?}", value, e))
+}
 
 /// Credentials for connecting to IMAP/SMTP
 #[derive(Debug, Clone)]
@@ -42,6 +130,346 @@ impl ImapCredentials {
     }
 }
 
+/// Parse a raw message into our Email type; shared by the IMAP and POP3 clients
+pub(crate) fn parse_message(
+    account_id: &str,
+    uid: u32,
+    folder: &str,
+    raw: &[u8],
+    flags: &[Flag<'_>],
+) -> Result<Email> {
+    let parsed = MessageParser::default()
+        .parse(raw)
+        .context("Failed to parse email message")?;
+
+    let subject = parsed
+        .subject()
+        .unwrap_or("(No Subject)")
+        .to_string();
+
+    let from = parsed
+        .from()
+        .and_then(|addrs| addrs.first())
+        .map(|addr| {
+            if let Some(name) = addr.name() {
+                format!("{} <{}>", name, addr.address().unwrap_or(""))
+            } else {
+                addr.address().unwrap_or("").to_string()
+            }
+        })
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let from_email = parsed
+        .from()
+        .and_then(|addrs| addrs.first())
+        .and_then(|addr| addr.address())
+        .unwrap_or("")
+        .to_string();
+
+    let to: Vec<String> = parsed
+        .to()
+        .map(|addrs| {
+            addrs
+                .iter()
+                .map(|addr| {
+                    if let Some(name) = addr.name() {
+                        format!("{} <{}>", name, addr.address().unwrap_or(""))
+                    } else {
+                        addr.address().unwrap_or("").to_string()
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let date = parsed
+        .date()
+        .map(|d| d.to_rfc3339())
+        .unwrap_or_default();
+
+    let date_timestamp = parsed
+        .date()
+        .map(|d| d.to_timestamp())
+        .unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+    let mut body_html = parsed.body_html(0).map(|s| s.to_string());
+    let mut body_plain = parsed.body_text(0).map(|s| s.to_string());
+
+    // OpenPGP: replace bodies with decrypted content when possible
+    let crypto = process_message(&parsed).map(|(status, protected)| {
+        if let Some(protected) = protected {
+            body_html = protected.body_html;
+            body_plain = protected.body_plain;
+        }
+        status
+    });
+    let smime = verify_message(&parsed);
+
+    let body_clean = body_plain.as_deref().map(quotes::clean_body);
+    let snippet = quotes::snippet(body_clean.as_deref().unwrap_or(""), SNIPPET_CHARS);
+
+    let is_read = flags.iter().any(|f| matches!(f, Flag::Seen));
+    let is_starred = flags.iter().any(|f| matches!(f, Flag::Flagged));
+    let has_attachments = parsed.attachment_count() > 0;
+    let inline_parts = extract_inline_parts(&parsed);
+
+    let message_id = parsed.message_id().unwrap_or("").to_string();
+    let reply_to = parsed
+        .reply_to()
+        .and_then(|addrs| addrs.first())
+        .and_then(|addr| addr.address())
+        .map(|s| s.to_string());
+    let auth_results = parsed
+        .header_raw("Authentication-Results")
+        .map(|s| s.trim().to_string());
+    let read_receipt_to = parsed
+        .header_raw(RECEIPT_REQUEST_HEADER)
+        .and_then(receipts::receipt_address);
+    let delivery_failure = delivery_failure(&parsed);
+    let links = extract_links(body_html.as_deref(), body_plain.as_deref());
+    let shipments = shipments::detect(
+        &subject,
+        body_plain.as_deref(),
+        body_html.as_deref(),
+        &links,
+    );
+    let attachments = attachments(&parsed);
+    let references = reference_chain(&parsed);
+    let thread_id = compute_thread_id(&references, &message_id);
+    let id = format!("{}:{}:{}", account_id, folder, uid);
+
+    let mut labels = Vec::new();
+    if !is_read {
+        labels.push("UNREAD".to_string());
+    }
+    if is_starred {
+        labels.push("STARRED".to_string());
+    }
+    if folder.eq_ignore_ascii_case("INBOX") {
+        labels.push("INBOX".to_string());
+    }
+    // User labels set as IMAP keywords, here or by another client
+    for flag in flags {
+        if let Flag::Custom(keyword) = flag {
+            if is_user_keyword(keyword) {
+                labels.push(keyword.to_string());
+            }
+        }
+    }
+
+    Ok(Email {
+        id,
+        thread_id,
+        subject,
+        from,
+        from_email,
+        to,
+        date,
+        date_timestamp,
+        snippet,
+        body_html,
+        body_plain,
+        body_clean,
+        labels,
+        is_read,
+        is_starred,
+        has_attachments,
+        account_id: account_id.to_string(),
+        uid,
+        folder: folder.to_string(),
+        message_id,
+        references,
+        reply_to,
+        auth_results,
+        inline_parts,
+        remote_images_blocked: 0,
+        crypto,
+        smime,
+        read_receipt_to,
+        delivery_failure,
+        links,
+        shipments,
+        attachments,
+    })
+}
+
+/// Files attached to a message. Parts with a Content-ID are inline images of the body and
+/// are left out.
+fn attachments(parsed: &mail_parser::Message<'_>) -> Vec<Attachment> {
+    parsed
+        .attachments()
+        .enumerate()
+        .filter(|(_, part)| part.content_id().is_none())
+        .map(|(position, part)| {
+            let content_type = part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string())
+                .to_lowercase();
+            let filename = part
+                .attachment_name()
+                .map(str::to_string)
+                .or_else(|| {
+                    part.message()
+                        .and_then(|message| message.subject())
+                        .map(|subject| format!("{}.eml", subject))
+                })
+                .unwrap_or_else(|| format!("attachment-{}", position + 1));
+            Attachment {
+                position: position as u32,
+                kind: attachment_kind(&content_type, &filename),
+                filename,
+                content_type,
+                size: part.len() as u64,
+                local_path: None,
+                data: part.contents().to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Failed recipients reported by a bounce: a delivery status notification
+/// (`multipart/report; report-type=delivery-status`) or a message with
+/// `X-Failed-Recipients`
+fn delivery_failure(parsed: &mail_parser::Message<'_>) -> Option<DeliveryFailure> {
+    let is_dsn = parsed.content_type().is_some_and(|content_type| {
+        content_type.ctype().eq_ignore_ascii_case("multipart")
+            && content_type
+                .subtype()
+                .is_some_and(|subtype| subtype.eq_ignore_ascii_case("report"))
+            && content_type
+                .attribute("report-type")
+                .is_some_and(|report_type| report_type.eq_ignore_ascii_case("delivery-status"))
+    });
+    if !is_dsn {
+        let failed = parsed.header_raw(receipts::FAILED_RECIPIENTS_HEADER)?;
+        // The original is attached, or quoted in the text
+        let original = match parsed.parts.iter().find(|part| part.is_message()) {
+            Some(part) => String::from_utf8_lossy(part.contents()).into_owned(),
+            None => parsed.body_text(0)?.into_owned(),
+        };
+        return receipts::parse_failed_recipients(failed, &original);
+    }
+
+    let part_of_type = |subtypes: &[&str]| {
+        parsed.parts.iter().find(|part| {
+            part.content_type()
+                .and_then(|ct| ct.subtype())
+                .is_some_and(|subtype| subtypes.iter().any(|s| subtype.eq_ignore_ascii_case(s)))
+        })
+    };
+    let report = part_of_type(&["delivery-status", "global-delivery-status"])?;
+    // The bounced message, or just its headers
+    let original = part_of_type(&["rfc822-headers", "global-headers", "rfc822", "global"])?;
+    receipts::parse_delivery_failure(
+        &String::from_utf8_lossy(report.contents()),
+        &String::from_utf8_lossy(original.contents()),
+    )
+}
+
+/// The whole References chain of a message, with its In-Reply-To
+fn reference_chain(parsed: &mail_parser::Message<'_>) -> Vec<String> {
+    let references = parsed.references().as_text_list().unwrap_or_default();
+    let in_reply_to = parsed.in_reply_to().as_text_list().unwrap_or_default();
+    threading::reference_chain(&references, &in_reply_to)
+}
+
+/// Thread id of a message until it is threaded against the cache: that of the root of
+/// its reference chain, so replies to the same conversation agree even before any of
+/// its messages are cached
+fn compute_thread_id(references: &[String], message_id: &str) -> String {
+    match references.first() {
+        Some(root) => thread_id_for(root),
+        None if !message_id.is_empty() => thread_id_for(message_id),
+        None => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+/// Whether the server still answers on `session`
+async fn ping(session: &mut ImapSession) -> bool {
+    matches!(
+        tokio::time::timeout(NOOP_TIMEOUT, session.noop()).await,
+        Ok(Ok(_))
+    )
+}
+
+/// A LIST attribute as the server sent it (e.g. `\\Sent`), for the ones that can mark
+/// a special folder
+fn attribute_name(attribute: &NameAttribute<'_>) -> Option<String> {
+    let name = match attribute {
+        NameAttribute::All => "\\All",
+        NameAttribute::Archive => "\\Archive",
+        NameAttribute::Drafts => "\\Drafts",
+        NameAttribute::Flagged => "\\Flagged",
+        NameAttribute::Junk => "\\Junk",
+        NameAttribute::Sent => "\\Sent",
+        NameAttribute::Trash => "\\Trash",
+        NameAttribute::Extension(name) => return Some(name.to_string()),
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// What the list pass fetches: the envelope, flags and enough of the text for a snippet
+fn list_fetch_items() -> String {
+    format!(
+        "(UID FLAGS ENVELOPE BODY.PEEK[HEADER.FIELDS (DATE FROM SUBJECT {})] \
+         BODY.PEEK[TEXT]<0.{}> RFC822.SIZE)",
+        preview::PREVIEW_HEADERS,
+        preview::PREVIEW_BYTES
+    )
+}
+
+/// UIDs as an IMAP sequence set, runs of consecutive UIDs as ranges (`4,8:15,16`)
+fn uid_set(uids: impl Iterator<Item = u32>) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for uid in uids {
+        match ranges.last_mut() {
+            Some((_, end)) if end.checked_add(1) == Some(uid) => *end = uid,
+            _ => ranges.push((uid, uid)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}:{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// How often folders are checked on servers without IDLE
+const POLL_INTERVAL_SECS: u64 = 120;
+
+/// Read and starred state of one message
+#[derive(Debug, Clone)]
+pub struct MessageFlags {
+    pub uid: u32,
+    pub is_read: bool,
+    pub is_starred: bool,
+}
+
+/// Result of `ImapClient::sync_flags`
+#[derive(Debug, Clone)]
+pub struct FlagChanges {
+    pub flags: Vec<MessageFlags>,
+    /// Where the next sync can continue from; `None` without CONDSTORE
+    pub state: Option<FolderSyncState>,
+}
+
+/// A connection of its own for bulk fetches
+struct FetchLane {
+    session: Mutex<Option<ImapSession>>,
+    last_used: std::sync::Mutex<Instant>,
+}
+
 /// IMAP/SMTP client for a single email account
 pub struct ImapClient {
     pub account_id: String,
@@ -50,6 +478,15 @@ pub struct ImapClient {
     pub server_config: ServerConfig,
     credentials: ImapCredentials,
     session: Arc<Mutex<Option<ImapSession>>>,
+    gmail_categories: Arc<Mutex<HashMap<String, GmailCategoryCache>>>,
+    /// Probed after each login
+    capabilities: Arc<Mutex<ServerCapabilities>>,
+    /// Paces message fetches so bulk syncs don't get the account throttled
+    limiter: Arc<RateLimiter>,
+    /// When the session last answered a command
+    last_used: std::sync::Mutex<Instant>,
+    /// Connected on first use by `fetch_batches`
+    fetch_lanes: Vec<FetchLane>,
 }
 
 impl ImapClient {
@@ -67,6 +504,16 @@ impl ImapClient {
             server_config,
             credentials,
             session: Arc::new(Mutex::new(None)),
+            gmail_categories: Arc::new(Mutex::new(HashMap::new())),
+            capabilities: Arc::new(Mutex::new(ServerCapabilities::default())),
+            limiter: Arc::new(RateLimiter::new(FETCHES_PER_SECOND)),
+            last_used: std::sync::Mutex::new(Instant::now()),
+            fetch_lanes: (0..FETCH_LANES)
+                .map(|_| FetchLane {
+                    session: Mutex::new(None),
+                    last_used: std::sync::Mutex::new(Instant::now()),
+                })
+                .collect(),
         }
     }
 
@@ -76,7 +523,6 @@ impl ImapClient {
 
     /// Connect to IMAP server and authenticate
     async fn connect(&self) -> Result<ImapSession> {
-        let tls = TlsConnector::new();
         let tcp = TcpStream::connect((
             self.server_config.imap_host.as_str(),
             self.server_config.imap_port,
@@ -84,17 +530,22 @@ impl ImapClient {
         .await
         .context("Failed to connect to IMAP server")?;
 
-        // Convert tokio TcpStream to futures_io compatible stream
-        let tcp_compat = tcp.compat();
+        let tcp = if imap_uses_starttls(self.server_config.imap_port) {
+            tls::starttls(tcp, Starttls::Imap).await?
+        } else {
+            tcp
+        };
 
-        let tls_stream = tls
-            .connect(&self.server_config.imap_host, tcp_compat)
-            .await
-            .context("TLS handshake failed")?;
+        let tls_stream = tls::handshake(
+            tcp,
+            &self.server_config.imap_host,
+            self.server_config.cert_fingerprint.as_deref(),
+        )
+        .await?;
 
         let client = async_imap::Client::new(tls_stream);
 
-        let session = match &self.credentials {
+        let mut session = match &self.credentials {
             ImapCredentials::OAuth2 { user, access_token } => {
                 let auth_string = format!(
                     "user={}\x01auth=Bearer {}\x01\x01",
@@ -111,18 +562,126 @@ impl ImapClient {
                 .map_err(|(e, _)| anyhow::anyhow!("IMAP login failed: {}", e))?,
         };
 
+        let capabilities = self.probe_capabilities(&mut session).await;
+        *self.capabilities.lock().await = capabilities;
+
         Ok(session)
     }
 
+    /// CAPABILITY after login, when servers advertise their full set, and the server's
+    /// identity through ID where supported
+    async fn probe_capabilities(&self, session: &mut ImapSession) -> ServerCapabilities {
+        let mut capabilities = match session.capabilities().await {
+            Ok(advertised) => ServerCapabilities::detect(|name| advertised.has_str(name)),
+            Err(e) => {
+                // Assume nothing beyond IMAP4rev1
+                warn!(account = %self.account_id, "CAPABILITY failed: {}", e);
+                ServerCapabilities::default()
+            }
+        };
+
+        if capabilities.id {
+            let client_id = [
+                ("name", Some("Inboxed")),
+                ("version", Some(env!("CARGO_PKG_VERSION"))),
+            ];
+            match session.id(client_id).await {
+                Ok(Some(server_id)) => {
+                    let version = server_id.get("version");
+                    capabilities.server_name = server_id.get("name").map(|name| match version {
+                        Some(version) => format!("{} {}", name, version),
+                        None => name.clone(),
+                    });
+                }
+                Ok(None) => {}
+                Err(e) => warn!(account = %self.account_id, "ID failed: {}", e),
+            }
+        }
+
+        let server = capabilities
+            .server_name
+            .as_deref()
+            .unwrap_or("unknown server");
+        info!(account = %self.account_id, "Connected to {}: {:?}", server, capabilities);
+        capabilities
+    }
+
+    /// Capabilities of the server, connecting first if needed
+    pub async fn capabilities(&self) -> Result<ServerCapabilities> {
+        drop(self.get_session().await?);
+        Ok(self.capabilities.lock().await.clone())
+    }
+
+    /// The logged-in session, connecting first if needed. A session left unused for a
+    /// while is checked with a NOOP and replaced by a new login if the server has
+    /// dropped it; commands select their folder themselves, so nothing else needs
+    /// restoring.
     async fn get_session(&self) -> Result<tokio::sync::MutexGuard<'_, Option<ImapSession>>> {
         let mut guard = self.session.lock().await;
+        if let Some(session) = guard.as_mut() {
+            if self.unused_for() >= CHECK_SESSION_AFTER && !ping(session).await {
+                info!(account = %self.account_id, "Session was dropped, logging in again");
+                *guard = None;
+            }
+        }
         if guard.is_none() {
             let session = self.connect().await?;
             *guard = Some(session);
         }
+        self.mark_used();
         Ok(guard)
     }
 
+    /// Send a NOOP on a session left unused for `KEEPALIVE_AFTER`, so the server doesn't
+    /// log it out. A session that doesn't answer is dropped for the next command to
+    /// replace. Sessions in use, or in IDLE, are left alone.
+    pub async fn keep_alive(&self) {
+        let Ok(mut guard) = self.session.try_lock() else {
+            return;
+        };
+        let Some(session) = guard.as_mut() else {
+            return;
+        };
+        if self.unused_for() < KEEPALIVE_AFTER {
+            return;
+        }
+        if ping(session).await {
+            self.mark_used();
+        } else {
+            info!(account = %self.account_id, "Session was dropped, closing it");
+            *guard = None;
+        }
+    }
+
+    /// The session of a fetch lane, connecting or replacing a dropped one like
+    /// `get_session`
+    async fn lane_session<'a>(
+        &self,
+        lane: &'a FetchLane,
+    ) -> Result<tokio::sync::MutexGuard<'a, Option<ImapSession>>> {
+        let mut guard = lane.session.lock().await;
+        let unused_for = lane.last_used.lock().unwrap().elapsed();
+        if let Some(session) = guard.as_mut() {
+            if unused_for >= CHECK_SESSION_AFTER && !ping(session).await {
+                *guard = None;
+            }
+        }
+        if guard.is_none() {
+            *guard = Some(self.connect().await?);
+        }
+        *lane.last_used.lock().unwrap() = Instant::now();
+        Ok(guard)
+    }
+
+    fn unused_for(&self) -> Duration {
+        self.last_used.lock().unwrap().elapsed()
+    }
+
+    fn mark_used(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    #[tracing::instrument(skip_all, fields(account = %self.account_id))]
     pub async fn reconnect(&self) -> Result<()> {
         let mut guard = self.session.lock().await;
         if let Some(mut session) = guard.take() {
@@ -130,153 +689,124 @@ impl ImapClient {
         }
         let session = self.connect().await?;
         *guard = Some(session);
+        self.mark_used();
         Ok(())
     }
 
-    /// Parse a raw email message into our Email type
-    pub fn parse_raw_email(
-        &self,
-        uid: u32,
-        folder: &str,
-        raw: &[u8],
-        flags: &[Flag<'_>],
-    ) -> Result<Email> {
-        let parsed = MessageParser::default()
-            .parse(raw)
-            .context("Failed to parse email message")?;
-
-        let subject = parsed
-            .subject()
-            .unwrap_or("(No Subject)")
-            .to_string();
-
-        let from = parsed
-            .from()
-            .and_then(|addrs| addrs.first())
-            .map(|addr| {
-                if let Some(name) = addr.name() {
-                    format!("{} <{}>", name, addr.address().unwrap_or(""))
-                } else {
-                    addr.address().unwrap_or("").to_string()
-                }
-            })
-            .unwrap_or_else(|| "Unknown".to_string());
-
-        let from_email = parsed
-            .from()
-            .and_then(|addrs| addrs.first())
-            .and_then(|addr| addr.address())
-            .unwrap_or("")
-            .to_string();
-
-        let to: Vec<String> = parsed
-            .to()
-            .map(|addrs| {
-                addrs
-                    .iter()
-                    .map(|addr| {
-                        if let Some(name) = addr.name() {
-                            format!("{} <{}>", name, addr.address().unwrap_or(""))
-                        } else {
-                            addr.address().unwrap_or("").to_string()
-                        }
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        let date = parsed
-            .date()
-            .map(|d| d.to_rfc3339())
-            .unwrap_or_default();
-
-        let date_timestamp = parsed
-            .date()
-            .map(|d| d.to_timestamp())
-            .unwrap_or_else(|| chrono::Utc::now().timestamp());
-
-        let body_html = parsed.body_html(0).map(|s| s.to_string());
-        let body_plain = parsed.body_text(0).map(|s| s.to_string());
-
-        let snippet = body_plain
-            .as_deref()
-            .unwrap_or("")
-            .chars()
-            .take(200)
-            .collect::<String>()
-            .replace('\n', " ")
-            .replace('\r', "");
-
-        let is_read = flags.iter().any(|f| matches!(f, Flag::Seen));
-        let is_starred = flags.iter().any(|f| matches!(f, Flag::Flagged));
-        let has_attachments = parsed.attachment_count() > 0;
+    /// Fetch full messages in one command, in the order of `uids`. Messages that no
+    /// longer exist or can't be parsed are left out. Fetches are paced by the account's
+    /// rate limiter and retried with backoff when the server throttles them or the
+    /// connection drops.
+    pub async fn get_messages(&self, folder: &str, uids: &[u32]) -> Result<Vec<Email>> {
+        self.get_messages_via(None, folder, uids).await
+    }
 
-        let message_id = parsed.message_id().unwrap_or("").to_string();
-        let thread_id = self.compute_thread_id(&parsed);
-        let id = format!("{}:{}:{}", self.account_id, folder, uid);
+    /// Fetch `uids` in batches of `batch_size`, up to `FETCH_LANES` batches at a time,
+    /// each over a connection of its own so the main session stays free. Batches come
+    /// back as they complete, with the UIDs they were for.
+    pub fn fetch_batches<'a>(
+        &'a self,
+        folder: &'a str,
+        uids: &'a [u32],
+        batch_size: usize,
+    ) -> impl futures::Stream<Item = (&'a [u32], Result<Vec<Email>>)> + 'a {
+        futures::stream::iter(uids.chunks(batch_size.max(1)).enumerate())
+            .map(move |(index, batch)| async move {
+                let lane = &self.fetch_lanes[index % FETCH_LANES];
+                let fetched = self.get_messages_via(Some(lane), folder, batch).await;
+                (batch, fetched)
+            })
+            .buffer_unordered(FETCH_LANES)
+    }
 
-        let mut labels = Vec::new();
-        if !is_read {
-            labels.push("UNREAD".to_string());
-        }
-        if is_starred {
-            labels.push("STARRED".to_string());
-        }
-        if folder.eq_ignore_ascii_case("INBOX") {
-            labels.push("INBOX".to_string());
+    /// `get_messages` over a fetch lane, or over the main session without one
+    async fn get_messages_via(
+        &self,
+        lane: Option<&FetchLane>,
+        folder: &str,
+        uids: &[u32],
+    ) -> Result<Vec<Email>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
         }
-
-        Ok(Email {
-            id,
-            thread_id,
-            subject,
-            from,
-            from_email,
-            to,
-            date,
-            date_timestamp,
-            snippet,
-            body_html,
-            body_plain,
-            labels,
-            is_read,
-            is_starred,
-            has_attachments,
-            account_id: self.account_id.clone(),
-            uid,
-            folder: folder.to_string(),
-            message_id,
+        throttle::retry(&RetryPolicy::IMAP, "Message fetch", || async {
+            self.limiter.acquire().await;
+            let mut guard = match lane {
+                Some(lane) => self.lane_session(lane).await?,
+                None => self.get_session().await?,
+            };
+            let session = guard.as_mut().context("No IMAP session")?;
+            let result = self.fetch_messages(session, folder, uids).await;
+            if result.as_ref().is_err_and(throttle::is_connection_error) {
+                // Log in again on the next try
+                *guard = None;
+            }
+            result
         })
+        .await
     }
 
-    fn compute_thread_id(&self, parsed: &mail_parser::Message<'_>) -> String {
-        // Try In-Reply-To first for threading
-        // in_reply_to() returns &HeaderValue directly in mail-parser 0.9
-        let irt = parsed.in_reply_to();
-        if let Some(text) = irt.as_text() {
-            if !text.is_empty() {
-                return format!("{:x}", md5::compute(text.as_bytes()));
-            }
-        }
+    async fn fetch_messages(
+        &self,
+        session: &mut ImapSession,
+        folder: &str,
+        uids: &[u32],
+    ) -> Result<Vec<Email>> {
+        let mailbox = session
+            .select(utf7::wire_name(folder))
+            .await
+            .context("Failed to select folder")?;
 
-        // Try References header
-        let refs = parsed.references();
-        if let Some(text) = refs.as_text() {
-            if let Some(first) = text.split_whitespace().next() {
-                if !first.is_empty() {
-                    return format!("{:x}", md5::compute(first.as_bytes()));
-                }
+        let fetches: Vec<_> = session
+            .uid_fetch(uid_set(uids.iter().copied()), "(UID FLAGS BODY[])")
+            .await
+            .context("Failed to fetch messages")?
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut emails = Vec::with_capacity(uids.len());
+        for fetch in fetches {
+            let fetch = fetch.context("Failed to fetch messages")?;
+            let (Some(uid), Some(raw)) = (fetch.uid, fetch.body()) else {
+                continue;
+            };
+            let flags: Vec<Flag<'_>> = fetch.flags().collect();
+            match self.parse_raw_email(uid, folder, raw, &flags) {
+                Ok(email) => emails.push(email),
+                Err(e) => warn!(
+                    account = %self.account_id,
+                    "Failed to parse message uid={}: {}",
+                    uid, e
+                ),
             }
         }
+        emails.sort_by_key(|email| uids.iter().position(|&uid| uid == email.uid));
 
-        // Fallback to own message-id
-        if let Some(mid) = parsed.message_id() {
-            if !mid.is_empty() {
-                return format!("{:x}", md5::compute(mid.as_bytes()));
+        if self.provider == ProviderType::Gmail && !emails.is_empty() {
+            if let Err(e) = self
+                .apply_gmail_metadata(session, folder, mailbox.uid_next, &mut emails)
+                .await
+            {
+                warn!(
+                    account = %self.account_id,
+                    "Failed to read Gmail metadata for {} messages: {}",
+                    emails.len(),
+                    e
+                );
             }
         }
+        Ok(emails)
+    }
 
-        uuid::Uuid::new_v4().to_string()
+    /// Parse a raw email message into our Email type
+    pub fn parse_raw_email(
+        &self,
+        uid: u32,
+        folder: &str,
+        raw: &[u8],
+        flags: &[Flag<'_>],
+    ) -> Result<Email> {
+        parse_message(&self.account_id, uid, folder, raw, flags)
     }
 
     pub fn to_list_item(email: &Email) -> EmailListItem {
@@ -295,7 +825,24 @@ impl ImapClient {
     }
 
     async fn build_smtp_transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
-        let builder = if self.server_config.smtp_port == 465 {
+        let implicit_tls = smtp_uses_implicit_tls(self.server_config.smtp_port);
+        let builder = if let Some(pinned) = &self.server_config.cert_fingerprint {
+            // Trust only the pinned certificate, whatever name it was issued for
+            let certificate = self.pinned_smtp_certificate(pinned).await?;
+            let parameters = TlsParameters::builder(self.server_config.smtp_host.clone())
+                .certificate_store(CertificateStore::None)
+                .add_root_certificate(Certificate::from_der(certificate)?)
+                .dangerous_accept_invalid_hostnames(true)
+                .build()?;
+            let tls = if implicit_tls {
+                Tls::Wrapper(parameters)
+            } else {
+                Tls::Required(parameters)
+            };
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.server_config.smtp_host)
+                .port(self.server_config.smtp_port)
+                .tls(tls)
+        } else if implicit_tls {
             AsyncSmtpTransport::<Tokio1Executor>::relay(&self.server_config.smtp_host)?
                 .port(self.server_config.smtp_port)
         } else {
@@ -303,27 +850,331 @@ impl ImapClient {
                 .port(self.server_config.smtp_port)
         };
 
-        let transport = match &self.credentials {
-            ImapCredentials::OAuth2 { user, access_token } => builder
-                .credentials(Credentials::new(user.clone(), access_token.clone()))
-                .authentication(vec![Mechanism::Xoauth2])
-                .build(),
-            ImapCredentials::Password { user, password } => builder
-                .credentials(Credentials::new(user.clone(), password.clone()))
-                .build(),
-        };
+        let transport = match &self.credentials {
+            ImapCredentials::OAuth2 { user, access_token } => builder
+                .credentials(Credentials::new(user.clone(), access_token.clone()))
+                .authentication(vec![Mechanism::Xoauth2])
+                .build(),
+            ImapCredentials::Password { user, password } => builder
+                .credentials(Credentials::new(user.clone(), password.clone()))
+                .build(),
+        };
+
+        Ok(transport)
+    }
+
+    /// Connect and sign in to the SMTP server without sending anything
+    pub async fn check_smtp(&self) -> Result<()> {
+        let transport = self.build_smtp_transport().await?;
+        if !transport.test_connection().await? {
+            anyhow::bail!("SMTP server closed the connection");
+        }
+        Ok(())
+    }
+
+    /// The SMTP server's certificate, checked against the pinned fingerprint
+    async fn pinned_smtp_certificate(&self, pinned: &str) -> Result<Vec<u8>> {
+        let tcp = TcpStream::connect((
+            self.server_config.smtp_host.as_str(),
+            self.server_config.smtp_port,
+        ))
+        .await
+        .context("Failed to connect to SMTP server")?;
+
+        let tcp = if smtp_uses_implicit_tls(self.server_config.smtp_port) {
+            tcp
+        } else {
+            tls::starttls(tcp, Starttls::Smtp).await?
+        };
+
+        let stream = tls::handshake(tcp, &self.server_config.smtp_host, Some(pinned)).await?;
+        tls::peer_certificate(&stream)
+    }
+
+    /// Send an email, optionally PGP/MIME signed and/or encrypted (RFC 3156).
+    /// `in_reply_to` is the Message-ID being answered; `request_receipt` asks recipients
+    /// for a read receipt. Returns the sent message's Message-ID (without angle brackets).
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_email_with_crypto(
+        &self,
+        from: &str,
+        to: Vec<String>,
+        cc: Vec<String>,
+        bcc: Vec<String>,
+        subject: &str,
+        body_html: &str,
+        body_plain: &str,
+        in_reply_to: Option<&str>,
+        request_receipt: bool,
+        options: OutgoingCrypto,
+    ) -> Result<String> {
+        let from_mailbox: Mailbox = from.parse().context("Invalid from address")?;
+        let sender_address = from_mailbox.email.to_string();
+
+        let message_id = format!("{}@{}", uuid::Uuid::new_v4(), from_mailbox.email.domain());
+        let mut builder = Message::builder()
+            .from(from_mailbox)
+            .subject(subject)
+            .message_id(Some(format!("<{}>", message_id)));
+        if let Some(parent) = in_reply_to.filter(|id| !id.is_empty()) {
+            builder = builder
+                .in_reply_to(format!("<{}>", parent))
+                .references(format!("<{}>", parent));
+        }
+        if request_receipt {
+            builder = builder.raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str(RECEIPT_REQUEST_HEADER),
+                sender_address.clone(),
+            ));
+        }
+        let mut recipient_addresses = Vec::new();
+
+        for addr in &to {
+            let mbox: Mailbox = addr.parse().context("Invalid to address")?;
+            recipient_addresses.push(mbox.email.to_string());
+            builder = builder.to(mbox);
+        }
+        for addr in &cc {
+            let mbox: Mailbox = addr.parse().context("Invalid cc address")?;
+            recipient_addresses.push(mbox.email.to_string());
+            builder = builder.cc(mbox);
+        }
+        for addr in &bcc {
+            let mbox: Mailbox = addr.parse().context("Invalid bcc address")?;
+            recipient_addresses.push(mbox.email.to_string());
+            builder = builder.bcc(mbox);
+        }
+
+        let body = if !body_html.is_empty() && !body_plain.is_empty() {
+            MimeBody::Multi(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_PLAIN)
+                            .body(body_plain.to_string()),
+                    )
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(ContentType::TEXT_HTML)
+                            .body(body_html.to_string()),
+                    ),
+            )
+        } else if !body_html.is_empty() {
+            MimeBody::Single(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_HTML)
+                    .body(body_html.to_string()),
+            )
+        } else {
+            MimeBody::Single(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(body_plain.to_string()),
+            )
+        };
+
+        let email = if !options.sign && !options.encrypt {
+            match body {
+                MimeBody::Single(part) => builder.singlepart(part)?,
+                MimeBody::Multi(part) => builder.multipart(part)?,
+            }
+        } else {
+            let certs = crypto::load_certs();
+            let signer = if options.sign {
+                Some(
+                    crypto::find_cert_for_email(&certs, &sender_address)
+                        .filter(|cert| cert.is_tsk())
+                        .with_context(|| format!("No secret key for {}", sender_address))?,
+                )
+            } else {
+                None
+            };
+
+            if options.encrypt {
+                let mut recipients = Vec::new();
+                for address in &recipient_addresses {
+                    recipients.push(
+                        crypto::find_cert_for_email(&certs, address)
+                            .with_context(|| format!("No public key for {}", address))?,
+                    );
+                }
+                // Encrypt to self so the Sent copy stays readable
+                if let Some(own) = crypto::find_cert_for_email(&certs, &sender_address) {
+                    recipients.push(own);
+                }
+
+                let ciphertext = crypto::encrypt(&body.formatted(), &recipients, signer)?;
+                builder.multipart(
+                    MultiPart::encrypted("application/pgp-encrypted".to_string())
+                        .singlepart(
+                            SinglePart::builder()
+                                .header(content_type("application/pgp-encrypted")?)
+                                .body("Version: 1\r\n".to_string()),
+                        )
+                        .singlepart(
+                            SinglePart::builder()
+                                .header(content_type("application/octet-stream; name=\"encrypted.asc\"")?)
+                                .body(ciphertext),
+                        ),
+                )?
+            } else {
+                let signer = signer.context("No signing key")?;
+                let signature = crypto::sign_detached(&body.formatted(), signer)?;
+                let signature_part = SinglePart::builder()
+                    .header(content_type("application/pgp-signature; name=\"signature.asc\"")?)
+                    .body(signature);
+                let signed = MultiPart::signed(
+                    "application/pgp-signature".to_string(),
+                    "pgp-sha256".to_string(),
+                );
+                let signed = match body {
+                    MimeBody::Single(part) => signed.singlepart(part),
+                    MimeBody::Multi(part) => signed.multipart(part),
+                };
+                builder.multipart(signed.singlepart(signature_part))?
+            }
+        };
+
+        let transport = self.build_smtp_transport().await?;
+        transport
+            .send(email)
+            .await
+            .context("Failed to send email via SMTP")?;
+
+        Ok(message_id)
+    }
+
+    /// Send a read receipt (MDN) for `original` to `to`, the address it asked for one at
+    pub async fn send_read_receipt(&self, to: &str, original: &Email) -> Result<()> {
+        let from: Mailbox = self.email.parse().context("Invalid from address")?;
+        let to: Mailbox = to.parse().context("Invalid read receipt address")?;
+
+        let boundary = uuid::Uuid::new_v4().simple().to_string();
+        let report = MultiPart::builder()
+            .header(content_type(&format!(
+                "multipart/report; report-type=disposition-notification; boundary=\"{}\"",
+                boundary
+            ))?)
+            .singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(format!(
+                        "Your message \"{}\" was displayed by {}. This does not guarantee \
+                         that it was read or understood.",
+                        original.subject, self.email
+                    )),
+            )
+            .singlepart(
+                SinglePart::builder()
+                    .header(content_type("message/disposition-notification")?)
+                    .body(receipts::disposition_report(
+                        &format!("Inboxed {}", env!("CARGO_PKG_VERSION")),
+                        &self.email,
+                        &original.message_id,
+                    )),
+            );
+        let message = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(format!("Read: {}", original.subject))
+            .multipart(report)?;
+
+        let transport = self.build_smtp_transport().await?;
+        transport
+            .send(message)
+            .await
+            .context("Failed to send read receipt via SMTP")?;
+        Ok(())
+    }
+
+    /// Use Gmail's labels and conversation ids instead of the ones derived from headers,
+    /// and label the messages with their category tabs. `session` has `folder` selected.
+    async fn apply_gmail_metadata(
+        &self,
+        session: &mut ImapSession,
+        folder: &str,
+        uid_next: Option<u32>,
+        emails: &mut [Email],
+    ) -> Result<()> {
+        let uids = uid_set(emails.iter().map(|email| email.uid));
+        let raw = session
+            .run_command_and_read_response(gmail::fetch_command(&uids))
+            .await
+            .context("Failed to fetch Gmail metadata")?;
+        let metadata = gmail::parse_fetch_response(&String::from_utf8_lossy(&raw));
+
+        for email in emails.iter_mut() {
+            if let Some(meta) = metadata.get(&email.uid) {
+                if let Some(thread_id) = meta.local_thread_id() {
+                    email.thread_id = thread_id;
+                }
+                for label in meta.labels.iter().map(|label| gmail::local_label(label)) {
+                    if !email.labels.contains(&label) {
+                        email.labels.push(label);
+                    }
+                }
+            }
+
+            if let Some(category) = self
+                .gmail_category(session, folder, uid_next, email.uid)
+                .await?
+            {
+                email.labels.push(category.label().to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Category tab of a message, searching the folder again when the cached membership
+    /// is stale or older than the message
+    async fn gmail_category(
+        &self,
+        session: &mut ImapSession,
+        folder: &str,
+        uid_next: Option<u32>,
+        uid: u32,
+    ) -> Result<Option<GmailCategory>> {
+        let mut cache = self.gmail_categories.lock().await;
+        let fresh = cache.get(folder).is_some_and(|cached| {
+            cached.fetched_at.elapsed() < GMAIL_CATEGORY_TTL && uid < cached.uid_next
+        });
+
+        if !fresh {
+            let mut categories = HashMap::new();
+            for category in GmailCategory::ALL {
+                let uids = session
+                    .uid_search(category.search_query())
+                    .await
+                    .context("Failed to search Gmail category")?;
+                categories.extend(uids.into_iter().map(|uid| (uid, category)));
+            }
+            cache.insert(
+                folder.to_string(),
+                GmailCategoryCache {
+                    fetched_at: Instant::now(),
+                    uid_next: uid_next.unwrap_or(uid + 1),
+                    categories,
+                },
+            );
+        }
 
-        Ok(transport)
+        Ok(cache
+            .get(folder)
+            .and_then(|cached| cached.categories.get(&uid).copied()))
     }
 
     pub async fn idle_wait(&self, folder: &str, timeout_secs: u64) -> Result<bool> {
+        if !self.capabilities().await?.idle {
+            return self.poll_wait(folder, timeout_secs).await;
+        }
+
         let mut guard = self.session.lock().await;
         let session = guard.take().context("No IMAP session")?;
 
         // Select folder first, then start IDLE
         let mut session = session;
         session
-            .select(folder)
+            .select(utf7::wire_name(folder))
             .await
             .context("Failed to select folder")?;
 
@@ -347,6 +1198,132 @@ impl ImapClient {
         Ok(new_mail)
     }
 
+    /// Expunge messages marked `\\Deleted`. With UIDPLUS only `uid_set` is expunged;
+    /// plain EXPUNGE also removes anything else marked `\\Deleted` in the folder.
+    async fn expunge_uid(
+        &self,
+        session: &mut ImapSession,
+        uid_set: &str,
+        uidplus: bool,
+    ) -> Result<()> {
+        if uidplus {
+            session
+                .uid_expunge(uid_set)
+                .await
+                .context("Failed to expunge")?
+                .collect::<Vec<_>>()
+                .await;
+        } else {
+            session
+                .expunge()
+                .await
+                .context("Failed to expunge")?
+                .collect::<Vec<_>>()
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Stand-in for IDLE on servers without it: wait one poll interval and report whether
+    /// the folder's message count or next UID changed
+    async fn poll_wait(&self, folder: &str, timeout_secs: u64) -> Result<bool> {
+        let before = self.folder_marker(folder).await?;
+        tokio::time::sleep(Duration::from_secs(timeout_secs.min(POLL_INTERVAL_SECS))).await;
+        let after = self.folder_marker(folder).await?;
+        Ok(after != before)
+    }
+
+    /// Whether `folder` gained or lost messages since the check that left its state in
+    /// `last`. The first check only records the state.
+    pub async fn folder_changed(
+        &self,
+        folder: &str,
+        last: &mut Option<(u32, Option<u32>)>,
+    ) -> Result<bool> {
+        let marker = self.folder_marker(folder).await?;
+        let changed = last.is_some_and(|last| last != marker);
+        *last = Some(marker);
+        Ok(changed)
+    }
+
+    async fn folder_marker(&self, folder: &str) -> Result<(u32, Option<u32>)> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+        let mailbox = session
+            .status(utf7::wire_name(folder), "(MESSAGES UIDNEXT)")
+            .await
+            .context("Failed to get folder status")?;
+        Ok((mailbox.exists, mailbox.uid_next))
+    }
+
+    /// Read and starred state of messages in `folder` that may have changed since the
+    /// last sync. With CONDSTORE only messages changed since `since` are fetched;
+    /// otherwise the flags of the newest `limit` messages are.
+    pub async fn sync_flags(
+        &self,
+        folder: &str,
+        since: Option<&FolderSyncState>,
+        limit: u32,
+    ) -> Result<FlagChanges> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+        let capabilities = self.capabilities.lock().await.clone();
+
+        let mailbox = if capabilities.condstore {
+            session.select_condstore(folder).await
+        } else {
+            session.select(utf7::wire_name(folder)).await
+        }
+        .context("Failed to select folder")?;
+
+        let fetches: Vec<_> = match capabilities.flag_sync(since, mailbox.uid_validity) {
+            FlagSync::ChangedSince(modseq) => {
+                session
+                    .uid_fetch("1:*", format!("(UID FLAGS) (CHANGEDSINCE {})", modseq))
+                    .await
+                    .context("Failed to fetch changed flags")?
+                    .collect::<Vec<_>>()
+                    .await
+            }
+            FlagSync::Recent if mailbox.exists == 0 => Vec::new(),
+            FlagSync::Recent => {
+                let start = mailbox.exists.saturating_sub(limit).max(1);
+                session
+                    .fetch(format!("{}:{}", start, mailbox.exists), "(UID FLAGS)")
+                    .await
+                    .context("Failed to fetch flags")?
+                    .collect::<Vec<_>>()
+                    .await
+            }
+        };
+
+        let flags = fetches
+            .iter()
+            .filter_map(|fetch| fetch.as_ref().ok())
+            .filter_map(|fetch| {
+                let uid = fetch.uid?;
+                let flags: Vec<Flag<'_>> = fetch.flags().collect();
+                Some(MessageFlags {
+                    uid,
+                    is_read: flags.iter().any(|f| matches!(f, Flag::Seen)),
+                    is_starred: flags.iter().any(|f| matches!(f, Flag::Flagged)),
+                })
+            })
+            .collect();
+
+        let state = match (mailbox.uid_validity, mailbox.highest_modseq) {
+            (Some(uid_validity), Some(highest_modseq)) if capabilities.condstore => {
+                Some(FolderSyncState {
+                    uid_validity,
+                    highest_modseq,
+                })
+            }
+            _ => None,
+        };
+
+        Ok(FlagChanges { flags, state })
+    }
+
     /// Get folder statistics (total and unseen message counts)
     pub async fn get_folder_stats(&self, folder: &str) -> Result<(u32, u32)> {
         let mut guard = self.get_session().await?;
@@ -354,7 +1331,7 @@ impl ImapClient {
 
         // Use EXAMINE to check folder without marking messages as read
         let mailbox = session
-            .examine(folder)
+            .examine(utf7::wire_name(folder))
             .await
             .context(format!("Failed to examine folder: {}", folder))?;
 
@@ -364,6 +1341,110 @@ impl ImapClient {
         Ok((total, unseen))
     }
 
+    /// Raw source of a message, fetched in chunks so `on_progress` can report
+    /// `(bytes fetched, total bytes)` while a large message downloads
+    pub async fn fetch_raw_message(
+        &self,
+        folder: &str,
+        uid: u32,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> Result<Vec<u8>> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+
+        session
+            .examine(utf7::wire_name(folder))
+            .await
+            .context(format!("Failed to examine folder: {}", folder))?;
+
+        let uid_str = uid.to_string();
+        let size = session
+            .uid_fetch(&uid_str, "(RFC822.SIZE)")
+            .await
+            .context("Failed to fetch message size")?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .find_map(|fetch| fetch.ok()?.size)
+            .context("Message not found")? as usize;
+
+        let mut raw = Vec::with_capacity(size);
+        while raw.len() < size {
+            let query = format!("(BODY.PEEK[]<{}.{}>)", raw.len(), FETCH_CHUNK_BYTES);
+            let fetch = session
+                .uid_fetch(&uid_str, query)
+                .await
+                .context("Failed to fetch message")?
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .next()
+                .context("Message not found")?
+                .context("Failed to fetch message")?;
+            // RFC822.SIZE can be off by a few bytes on servers that convert line endings
+            let Some(chunk) = fetch.body().filter(|chunk| !chunk.is_empty()) else {
+                break;
+            };
+            raw.extend_from_slice(chunk);
+            on_progress(raw.len().min(size) as u64, size as u64);
+        }
+
+        if raw.is_empty() {
+            let fetch = session
+                .uid_fetch(&uid_str, "(BODY.PEEK[])")
+                .await
+                .context("Failed to fetch message")?
+                .collect::<Vec<_>>()
+                .await
+                .into_iter()
+                .next()
+                .context("Message not found")?
+                .context("Failed to fetch message")?;
+            raw = fetch.body().context("No message body")?.to_vec();
+            on_progress(raw.len() as u64, raw.len() as u64);
+        }
+        Ok(raw)
+    }
+
+    /// Message counts of every selectable folder, in one session and without selecting
+    /// any of them. Folders whose STATUS fails are left out.
+    pub async fn folder_status(&self) -> Result<Vec<FolderStatus>> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+
+        let folders: Vec<String> = session
+            .list(Some(""), Some("*"))
+            .await
+            .context("Failed to list folders")?
+            .collect::<Vec<_>>()
+            .await
+            .iter()
+            .filter_map(|name| name.as_ref().ok())
+            .filter(|name| {
+                !name
+                    .attributes()
+                    .iter()
+                    .any(|a| matches!(a, NameAttribute::NoSelect))
+            })
+            .map(|name| name.name().to_string())
+            .collect();
+
+        let mut statuses = Vec::with_capacity(folders.len());
+        for folder in folders {
+            match session.status(&folder, "(MESSAGES UNSEEN RECENT)").await {
+                Ok(mailbox) => statuses.push(FolderStatus {
+                    total_count: mailbox.exists,
+                    unread_count: mailbox.unseen.unwrap_or(0),
+                    recent_count: mailbox.recent,
+                    folder,
+                }),
+                Err(e) => warn!("Failed to get status of folder {}: {}", folder, e),
+            }
+        }
+
+        Ok(statuses)
+    }
+
     /// Parse a FETCH response into an EmailListItem
     fn parse_fetch_to_list_item(&self, uid: u32, folder: &str, fetch: &Fetch) -> EmailListItem {
         let flags: Vec<Flag<'_>> = fetch.flags().collect();
@@ -373,10 +1454,10 @@ impl ImapClient {
         let (subject, from, from_email, date) = if let Some(envelope) = fetch.envelope() {
             let subject = envelope
                 .subject
-                .as_ref()
-                .and_then(|s| std::str::from_utf8(s).ok())
-                .unwrap_or("(No Subject)")
-                .to_string();
+                .as_deref()
+                .map(decode_header_text)
+                .filter(|subject| !subject.is_empty())
+                .unwrap_or_else(|| "(No Subject)".to_string());
 
             let (from, from_email) = envelope
                 .from
@@ -385,9 +1466,9 @@ impl ImapClient {
                 .map(|addr| {
                     let name = addr
                         .name
-                        .as_ref()
-                        .and_then(|n| std::str::from_utf8(n).ok())
-                        .unwrap_or("");
+                        .as_deref()
+                        .map(decode_header_text)
+                        .unwrap_or_default();
                     let mailbox = addr
                         .mailbox
                         .as_ref()
@@ -433,36 +1514,16 @@ impl ImapClient {
             from,
             from_email,
             date,
-            snippet: String::new(),
+            snippet: preview::snippet(
+                fetch.header().unwrap_or_default(),
+                fetch.text().unwrap_or_default(),
+                SNIPPET_CHARS,
+            ),
             is_read,
             is_starred,
             has_attachments: false,
         }
     }
-
-    fn detect_special_folder(
-        &self,
-        name: &str,
-        _attributes: &[async_imap::types::NameAttribute<'_>],
-    ) -> Option<SpecialFolder> {
-        // Name-based detection (works across all IMAP servers)
-        let lower = name.to_lowercase();
-        if lower == "inbox" {
-            Some(SpecialFolder::Inbox)
-        } else if lower.contains("sent") {
-            Some(SpecialFolder::Sent)
-        } else if lower.contains("trash") || lower.contains("deleted") {
-            Some(SpecialFolder::Trash)
-        } else if lower.contains("draft") {
-            Some(SpecialFolder::Drafts)
-        } else if lower.contains("spam") || lower.contains("junk") {
-            Some(SpecialFolder::Spam)
-        } else if lower.contains("archive") || lower.contains("all mail") {
-            Some(SpecialFolder::Archive)
-        } else {
-            None
-        }
-    }
 }
 
 /// XOAUTH2 authenticator for async-imap
@@ -488,7 +1549,7 @@ impl EmailProvider for ImapClient {
         let session = guard.as_mut().context("No IMAP session")?;
 
         let mailbox = session
-            .select(folder)
+            .select(utf7::wire_name(folder))
             .await
             .context("Failed to select folder")?;
 
@@ -505,10 +1566,7 @@ impl EmailProvider for ImapClient {
 
         let range = format!("{}:{}", start, end);
         let fetches: Vec<_> = session
-            .fetch(
-                range,
-                "(UID FLAGS ENVELOPE BODY.PEEK[HEADER.FIELDS (DATE FROM SUBJECT)] RFC822.SIZE)",
-            )
+            .fetch(range, list_fetch_items())
             .await
             .context("Failed to fetch messages")?
             .collect::<Vec<_>>()
@@ -529,33 +1587,62 @@ impl EmailProvider for ImapClient {
         Ok(items)
     }
 
-    async fn get_message(&self, folder: &str, uid: u32) -> Result<Email> {
+    async fn search_messages(
+        &self,
+        folder: &str,
+        criteria: &SearchQuery,
+        max_results: u32,
+    ) -> Result<Vec<EmailListItem>> {
+        let criteria = if self.provider == ProviderType::Gmail {
+            criteria.gmail_criteria()
+        } else {
+            criteria.imap_criteria()
+        };
         let mut guard = self.get_session().await?;
         let session = guard.as_mut().context("No IMAP session")?;
 
         session
-            .select(folder)
+            .examine(utf7::wire_name(folder))
             .await
-            .context("Failed to select folder")?;
+            .context(format!("Failed to examine folder: {}", folder))?;
+        let mut uids: Vec<u32> = session
+            .uid_search(&criteria)
+            .await
+            .context("Failed to search folder")?
+            .into_iter()
+            .collect();
+        // Higher UIDs arrived later
+        uids.sort_unstable_by(|a, b| b.cmp(a));
+        uids.truncate(max_results as usize);
+        if uids.is_empty() {
+            return Ok(vec![]);
+        }
 
-        let uid_str = uid.to_string();
         let fetches: Vec<_> = session
-            .uid_fetch(&uid_str, "(FLAGS BODY[])")
+            .uid_fetch(uid_set(uids.iter().copied()), list_fetch_items())
             .await
-            .context("Failed to fetch message")?
+            .context("Failed to fetch messages")?
             .collect::<Vec<_>>()
             .await;
 
-        let fetch = fetches
+        let mut items: Vec<(u32, EmailListItem)> = fetches
+            .iter()
+            .filter_map(|fetch| fetch.as_ref().ok())
+            .filter_map(|fetch| {
+                let uid = fetch.uid?;
+                Some((uid, self.parse_fetch_to_list_item(uid, folder, fetch)))
+            })
+            .collect();
+        items.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(items.into_iter().map(|(_, item)| item).collect())
+    }
+
+    async fn get_message(&self, folder: &str, uid: u32) -> Result<Email> {
+        self.get_messages(folder, &[uid])
+            .await?
             .into_iter()
             .next()
-            .context("Message not found")?
-            .context("Failed to fetch message")?;
-
-        let raw = fetch.body().context("No message body")?;
-        let flags: Vec<Flag<'_>> = fetch.flags().collect();
-
-        self.parse_raw_email(uid, folder, raw, &flags)
+            .context("Message not found")
     }
 
     async fn send_email(
@@ -568,58 +1655,20 @@ impl EmailProvider for ImapClient {
         body_html: &str,
         body_plain: &str,
     ) -> Result<()> {
-        let from_mailbox: Mailbox = from.parse().context("Invalid from address")?;
-
-        let mut builder = Message::builder().from(from_mailbox).subject(subject);
-
-        for addr in &to {
-            let mbox: Mailbox = addr.parse().context("Invalid to address")?;
-            builder = builder.to(mbox);
-        }
-        for addr in &cc {
-            let mbox: Mailbox = addr.parse().context("Invalid cc address")?;
-            builder = builder.cc(mbox);
-        }
-        for addr in &bcc {
-            let mbox: Mailbox = addr.parse().context("Invalid bcc address")?;
-            builder = builder.bcc(mbox);
-        }
-
-        let email = if !body_html.is_empty() && !body_plain.is_empty() {
-            builder.multipart(
-                MultiPart::alternative()
-                    .singlepart(
-                        SinglePart::builder()
-                            .header(ContentType::TEXT_PLAIN)
-                            .body(body_plain.to_string()),
-                    )
-                    .singlepart(
-                        SinglePart::builder()
-                            .header(ContentType::TEXT_HTML)
-                            .body(body_html.to_string()),
-                    ),
-            )?
-        } else if !body_html.is_empty() {
-            builder.singlepart(
-                SinglePart::builder()
-                    .header(ContentType::TEXT_HTML)
-                    .body(body_html.to_string()),
-            )?
-        } else {
-            builder.singlepart(
-                SinglePart::builder()
-                    .header(ContentType::TEXT_PLAIN)
-                    .body(body_plain.to_string()),
-            )?
-        };
-
-        let transport = self.build_smtp_transport().await?;
-        transport
-            .send(email)
-            .await
-            .context("Failed to send email via SMTP")?;
-
-        Ok(())
+        self.send_email_with_crypto(
+            from,
+            to,
+            cc,
+            bcc,
+            subject,
+            body_html,
+            body_plain,
+            None,
+            false,
+            OutgoingCrypto::default(),
+        )
+        .await
+        .map(|_| ())
     }
 
     async fn set_flags(
@@ -633,7 +1682,7 @@ impl EmailProvider for ImapClient {
         let session = guard.as_mut().context("No IMAP session")?;
 
         session
-            .select(folder)
+            .select(utf7::wire_name(folder))
             .await
             .context("Failed to select folder")?;
 
@@ -659,37 +1708,77 @@ impl EmailProvider for ImapClient {
         Ok(())
     }
 
+    async fn set_labels(&self, folder: &str, uid: u32, labels: &[Label], add: bool) -> Result<()> {
+        if labels.is_empty() {
+            return Ok(());
+        }
+
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+
+        session
+            .select(utf7::wire_name(folder))
+            .await
+            .context("Failed to select folder")?;
+
+        let sign = if add { '+' } else { '-' };
+        let query = if self.provider == ProviderType::Gmail {
+            let names = labels
+                .iter()
+                .map(|label| gmail_label_arg(&label.name))
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{}X-GM-LABELS ({})", sign, names)
+        } else {
+            let keywords = labels
+                .iter()
+                .map(|label| label.keyword.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("{}FLAGS ({})", sign, keywords)
+        };
+
+        session
+            .uid_store(&uid.to_string(), query)
+            .await
+            .context("Failed to update labels")?;
+
+        Ok(())
+    }
+
     async fn move_message(&self, from_folder: &str, uid: u32, to_folder: &str) -> Result<()> {
         let mut guard = self.get_session().await?;
         let session = guard.as_mut().context("No IMAP session")?;
 
         session
-            .select(from_folder)
+            .select(utf7::wire_name(from_folder))
             .await
             .context("Failed to select source folder")?;
 
         let uid_str = uid.to_string();
+        let strategy = self.capabilities.lock().await.move_strategy();
 
-        // Try MOVE extension first (RFC 6851)
-        match session.uid_mv(&uid_str, to_folder).await {
-            Ok(_) => Ok(()),
-            Err(_) => {
-                // Fallback: COPY + STORE \Deleted + EXPUNGE
-                session
-                    .uid_copy(&uid_str, to_folder)
-                    .await
-                    .context("Failed to copy message")?;
-                session
-                    .uid_store(&uid_str, "+FLAGS (\\Deleted)")
-                    .await
-                    .context("Failed to mark as deleted")?;
-                session
-                    .expunge()
-                    .await
-                    .context("Failed to expunge")?;
-                Ok(())
-            }
+        if strategy == MoveStrategy::Move {
+            session
+                .uid_mv(&uid_str, utf7::wire_name(to_folder))
+                .await
+                .context("Failed to move message")?;
+            return Ok(());
         }
+
+        // No MOVE: COPY + STORE \Deleted + EXPUNGE
+        session
+            .uid_copy(&uid_str, utf7::wire_name(to_folder))
+            .await
+            .context("Failed to copy message")?;
+        session
+            .uid_store(&uid_str, "+FLAGS (\\Deleted)")
+            .await
+            .context("Failed to mark as deleted")?
+            .collect::<Vec<_>>()
+            .await;
+        self.expunge_uid(session, &uid_str, strategy == MoveStrategy::CopyUidExpunge)
+            .await
     }
 
     async fn delete_message(&self, folder: &str, uid: u32) -> Result<()> {
@@ -697,7 +1786,7 @@ impl EmailProvider for ImapClient {
         let session = guard.as_mut().context("No IMAP session")?;
 
         session
-            .select(folder)
+            .select(utf7::wire_name(folder))
             .await
             .context("Failed to select folder")?;
 
@@ -705,11 +1794,12 @@ impl EmailProvider for ImapClient {
         session
             .uid_store(&uid_str, "+FLAGS (\\Deleted)")
             .await
-            .context("Failed to mark as deleted")?;
-
-        session.expunge().await.context("Failed to expunge")?;
+            .context("Failed to mark as deleted")?
+            .collect::<Vec<_>>()
+            .await;
 
-        Ok(())
+        let uidplus = self.capabilities.lock().await.uidplus;
+        self.expunge_uid(session, &uid_str, uidplus).await
     }
 
     async fn list_folders(&self) -> Result<Vec<Folder>> {
@@ -730,16 +1820,20 @@ impl EmailProvider for ImapClient {
                 Err(_) => continue,
             };
             let full_name = name.name().to_string();
-            let display_name = full_name
-                .rsplit('/')
-                .next()
-                .unwrap_or(&full_name)
-                .to_string();
+            let path = utf7::decode_lossy(&full_name);
+            let delimiter = name.delimiter().unwrap_or("/");
+            let display_name = path.rsplit(delimiter).next().unwrap_or(&path).to_string();
 
-            let special = self.detect_special_folder(&full_name, name.attributes());
+            let attributes: Vec<String> = name
+                .attributes()
+                .iter()
+                .filter_map(attribute_name)
+                .collect();
+            let special = special_folders::detect(&path, name.delimiter(), &attributes);
 
             folders.push(Folder {
                 name: full_name,
+                path,
                 display_name,
                 special,
                 delimiter: name.delimiter().map(|s| s.to_string()),
@@ -748,4 +1842,31 @@ impl EmailProvider for ImapClient {
 
         Ok(folders)
     }
+
+    async fn create_folder(&self, name: &str) -> Result<()> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+        session
+            .create(utf7::wire_name(name))
+            .await
+            .with_context(|| format!("Failed to create folder {}", name))
+    }
+
+    async fn rename_folder(&self, from: &str, to: &str) -> Result<()> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+        session
+            .rename(utf7::wire_name(from), utf7::wire_name(to))
+            .await
+            .with_context(|| format!("Failed to rename folder {}", from))
+    }
+
+    async fn delete_folder(&self, name: &str) -> Result<()> {
+        let mut guard = self.get_session().await?;
+        let session = guard.as_mut().context("No IMAP session")?;
+        session
+            .delete(utf7::wire_name(name))
+            .await
+            .with_context(|| format!("Failed to delete folder {}", name))
+    }
 }
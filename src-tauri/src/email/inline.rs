@@ -0,0 +1,165 @@
+//! Inline (`cid:`) MIME part handling
+//!
+//! HTML bodies reference embedded images through `cid:` URLs that point at other
+//! MIME parts of the same message. These helpers extract those parts so they can
+//! be cached locally and substituted back into the HTML for offline rendering.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mail_parser::MimeHeaders;
+use std::collections::HashMap;
+
+/// A MIME part referenced from the HTML body by its Content-ID
+#[derive(Debug, Clone, Default)]
+pub struct InlinePart {
+    /// Content-ID without the surrounding angle brackets
+    pub content_id: String,
+    /// Full MIME type (e.g., "image/png")
+    pub content_type: String,
+    /// Decoded part contents
+    pub data: Vec<u8>,
+}
+
+/// Strip whitespace and the surrounding `<...>` from a Content-ID header value
+pub fn normalize_content_id(raw: &str) -> String {
+    raw.trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim()
+        .to_string()
+}
+
+/// Extract every non-text part that carries a Content-ID
+pub fn extract_inline_parts(parsed: &mail_parser::Message<'_>) -> Vec<InlinePart> {
+    parsed
+        .parts
+        .iter()
+        .filter(|part| !part.is_text() && !part.is_multipart() && !part.is_message())
+        .filter_map(|part| {
+            let content_id = normalize_content_id(part.content_id()?);
+            if content_id.is_empty() {
+                return None;
+            }
+
+            let content_type = part
+                .content_type()
+                .map(|ct| match ct.subtype() {
+                    Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                    None => ct.ctype().to_string(),
+                })
+                .unwrap_or_else(|| "application/octet-stream".to_string())
+                .to_lowercase();
+
+            Some(InlinePart {
+                content_id,
+                content_type,
+                data: part.contents().to_vec(),
+            })
+        })
+        .collect()
+}
+
+/// Build a `data:` URI for an inline part so the webview can render it without network access
+pub fn to_data_uri(content_type: &str, data: &[u8]) -> String {
+    format!("data:{};base64,{}", content_type, STANDARD.encode(data))
+}
+
+/// Replace `cid:` references in the HTML with the URLs from `urls` (keyed by Content-ID).
+/// Only a whole reference is replaced, up to the quote, whitespace or bracket ending it,
+/// so `cid:image10` is left alone when just `image1` has a URL.
+pub fn rewrite_cid_references(html: &str, urls: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = find_cid(rest) {
+        let id_start = start + "cid:".len();
+        let id_len = rest[id_start..]
+            .find(|c: char| matches!(c, '"' | '\'' | '(' | ')' | '<' | '>') || c.is_whitespace())
+            .unwrap_or(rest.len() - id_start);
+        let id = &rest[id_start..id_start + id_len];
+        result.push_str(&rest[..start]);
+        match urls.get(id) {
+            Some(url) => result.push_str(url),
+            None => result.push_str(&rest[start..id_start + id_len]),
+        }
+        rest = &rest[id_start + id_len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Byte offset of the next `cid:` in any letter case
+fn find_cid(html: &str) -> Option<usize> {
+    html.as_bytes()
+        .windows(4)
+        .position(|window| window.eq_ignore_ascii_case(b"cid:"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_content_id() {
+        assert_eq!(normalize_content_id("<logo@example.com>"), "logo@example.com");
+        assert_eq!(normalize_content_id("  image001.png "), "image001.png");
+    }
+
+    #[test]
+    fn test_rewrite_cid_references() {
+        let mut urls = HashMap::new();
+        urls.insert("logo@example.com".to_string(), "data:image/png;base64,AA==".to_string());
+
+        let html = r#"<img src="cid:logo@example.com"><img src="cid:missing">"#;
+        let rewritten = rewrite_cid_references(html, &urls);
+
+        assert!(rewritten.contains(r#"src="data:image/png;base64,AA==""#));
+        assert!(rewritten.contains(r#"src="cid:missing""#));
+    }
+
+    #[test]
+    fn test_rewrite_cid_references_sharing_a_prefix() {
+        let mut urls = HashMap::new();
+        urls.insert("image1".to_string(), "data:one".to_string());
+        urls.insert("image10".to_string(), "data:ten".to_string());
+        urls.insert("image2".to_string(), "data:two".to_string());
+
+        let html = r#"<img src="cid:image10"><img src='CID:image1'><div style="background:url(cid:image2)">"#;
+        assert_eq!(
+            rewrite_cid_references(html, &urls),
+            r#"<img src="data:ten"><img src='data:one'><div style="background:url(data:two)">"#
+        );
+
+        urls.remove("image10");
+        assert_eq!(
+            rewrite_cid_references(r#"<img src="cid:image10">"#, &urls),
+            r#"<img src="cid:image10">"#
+        );
+    }
+
+    #[test]
+    fn test_extract_inline_parts() {
+        let raw = b"From: a@example.com\r\n\
+Subject: Inline\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/related; boundary=\"b1\"\r\n\
+\r\n\
+--b1\r\n\
+Content-Type: text/html; charset=utf-8\r\n\
+\r\n\
+<img src=\"cid:pic1\">\r\n\
+--b1\r\n\
+Content-Type: image/png\r\n\
+Content-ID: <pic1>\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+iVBORw0KGgo=\r\n\
+--b1--\r\n";
+
+        let parsed = mail_parser::MessageParser::default().parse(&raw[..]).unwrap();
+        let parts = extract_inline_parts(&parsed);
+
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].content_id, "pic1");
+        assert_eq!(parts[0].content_type, "image/png");
+        assert!(!parts[0].data.is_empty());
+    }
+}
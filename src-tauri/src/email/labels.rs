@@ -0,0 +1,102 @@
+//! User label mapping to server-side tags
+//!
+//! Labels are stored locally by name. On the server they become IMAP keywords
+//! (`STORE +FLAGS`), which must be atoms, so each label gets a keyword derived from its
+//! name. Gmail exposes its own labels through the `X-GM-LABELS` extension instead, which
+//! takes the display name as a quoted string.
+
+use super::utf7;
+
+/// Characters that can't appear in an IMAP atom (RFC 3501 `atom-specials`)
+const ATOM_SPECIALS: &[char] = &['(', ')', '{', ' ', '%', '*', '"', '\\', ']'];
+
+/// Keywords other clients set for their own bookkeeping, not user labels
+const SYSTEM_KEYWORDS: &[&str] = &["junk", "nonjunk", "notjunk"];
+
+/// Longest label name accepted
+pub const MAX_LABEL_LEN: usize = 64;
+
+/// Trimmed label name, or an error message when it can't be used
+pub fn normalize_name(name: &str) -> Result<String, String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err("Label name cannot be empty".to_string());
+    }
+    if name.chars().count() > MAX_LABEL_LEN {
+        return Err(format!(
+            "Label names are limited to {} characters",
+            MAX_LABEL_LEN
+        ));
+    }
+    if SYSTEM_KEYWORDS.contains(&name.to_lowercase().as_str()) {
+        return Err(format!("\"{}\" is reserved", name));
+    }
+    Ok(name)
+}
+
+/// IMAP keyword for a label name: spaces become underscores and characters that aren't
+/// allowed in an atom are dropped. `None` when nothing usable is left.
+pub fn keyword_for(name: &str) -> Option<String> {
+    let keyword: String = name
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control() && (*c == ' ' || !ATOM_SPECIALS.contains(c)))
+        .map(|c| if c == ' ' { '_' } else { c })
+        .filter(char::is_ascii)
+        .collect::<String>()
+        // `$` and `\` prefixes are reserved for system keywords and flags
+        .trim_start_matches('$')
+        .to_string();
+    (!keyword.is_empty()).then_some(keyword)
+}
+
+/// Whether a keyword reported by the server is a user label rather than a client's
+/// internal marker such as `$Forwarded` or `NonJunk`
+pub fn is_user_keyword(keyword: &str) -> bool {
+    !keyword.is_empty()
+        && !keyword.starts_with('$')
+        && !keyword.starts_with('\\')
+        && !SYSTEM_KEYWORDS.contains(&keyword.to_lowercase().as_str())
+}
+
+/// Quoted string for `X-GM-LABELS`. Gmail labels are folders, so their names are sent in
+/// modified UTF-7 too.
+pub fn gmail_label_arg(name: &str) -> String {
+    let name = utf7::wire_name(name);
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("  Project   X ").unwrap(), "Project X");
+        assert!(normalize_name("   ").is_err());
+        assert!(normalize_name("NonJunk").is_err());
+        assert!(normalize_name(&"a".repeat(MAX_LABEL_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_keyword_for() {
+        assert_eq!(keyword_for("Work").as_deref(), Some("Work"));
+        assert_eq!(keyword_for("Project X").as_deref(), Some("Project_X"));
+        assert_eq!(
+            keyword_for("To do (urgent)").as_deref(),
+            Some("To_do_urgent")
+        );
+        assert_eq!(keyword_for("$label1").as_deref(), Some("label1"));
+        assert_eq!(keyword_for("Reçus").as_deref(), Some("Reus"));
+        assert_eq!(keyword_for("***"), None);
+    }
+
+    #[test]
+    fn test_user_keywords_and_gmail_args() {
+        assert!(is_user_keyword("Project_X"));
+        assert!(!is_user_keyword("$Forwarded"));
+        assert!(!is_user_keyword("NonJunk"));
+        assert_eq!(gmail_label_arg("Say \"hi\""), "\"Say \\\"hi\\\"\"");
+        assert_eq!(gmail_label_arg("Entwürfe"), "\"Entw&APw-rfe\"");
+    }
+}
@@ -0,0 +1,70 @@
+//! Email language detection
+//!
+//! Each email's language is detected from its text when it's indexed and stored with its
+//! insights as an ISO 639-3 code (`eng`, `fra`, `deu`, ...). Summaries are written in
+//! that language unless the settings name one, and `lang:` in a search narrows results
+//! to it.
+
+use whatlang::Lang;
+
+/// Shorter texts say too little to tell similar languages apart
+const MIN_DETECT_CHARS: usize = 20;
+
+/// Below this whatlang is mostly guessing
+const MIN_CONFIDENCE: f64 = 0.5;
+
+/// The language `text` is written in, as an ISO 639-3 code, unless it's too short or
+/// too mixed to tell
+pub fn detect(text: &str) -> Option<&'static str> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_DETECT_CHARS {
+        return None;
+    }
+    whatlang::detect(text)
+        .filter(|info| info.confidence() >= MIN_CONFIDENCE)
+        .map(|info| info.lang().code())
+}
+
+/// The ISO 639-3 code for a language given by code or by its English or native name,
+/// e.g. `fra`, `French` or `Français`
+pub fn parse(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_lowercase();
+    Lang::from_code(&value)
+        .or_else(|| {
+            Lang::all().iter().copied().find(|lang| {
+                lang.eng_name().to_lowercase() == value || lang.name().to_lowercase() == value
+            })
+        })
+        .map(|lang| lang.code())
+}
+
+/// The English name of the language with ISO 639-3 `code`, for prompts
+pub fn english_name(code: &str) -> Option<&'static str> {
+    Lang::from_code(code).map(|lang| lang.eng_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect("Bonjour, pourriez-vous m'envoyer la facture du mois dernier avant vendredi ?"),
+            Some("fra")
+        );
+        assert_eq!(
+            detect("Hi team, the quarterly report is attached. Please review it before Monday."),
+            Some("eng")
+        );
+        assert_eq!(detect("Ok, thanks!"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("deu"), Some("deu"));
+        assert_eq!(parse("German"), Some("deu"));
+        assert_eq!(parse("español"), Some("spa"));
+        assert_eq!(parse("klingon"), None);
+        assert_eq!(english_name("spa"), Some("Spanish"));
+    }
+}
@@ -0,0 +1,319 @@
+//! URLs shared in an email body
+//!
+//! Links are collected while a message is parsed: `<a href>` targets with their anchor
+//! text from the HTML body, then bare URLs from the plain-text body. Each is flagged when
+//! it goes through a known tracking service, so link views and previews can skip it.
+//! Page metadata for previews is parsed here too; fetching it is left to the caller.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::trackers::{is_tracker_url, parse_attributes, url_host};
+
+/// Most links kept per email
+const MAX_LINKS: usize = 200;
+
+/// Longest anchor text or preview field kept, in characters
+const MAX_TEXT_CHARS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmailLink {
+    pub url: String,
+    /// Anchor text of an HTML link
+    pub text: Option<String>,
+    pub domain: String,
+    /// Points at a known tracking service (usually a click-tracking redirect)
+    pub is_tracker: bool,
+}
+
+/// Title, description and icon of a linked page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinkMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Absolute URL of the page's icon
+    pub favicon: Option<String>,
+}
+
+/// Links in an email's bodies, HTML first, without duplicates
+pub fn extract_links(body_html: Option<&str>, body_plain: Option<&str>) -> Vec<EmailLink> {
+    let mut links: Vec<EmailLink> = Vec::new();
+    let mut add = |url: &str, text: Option<String>| {
+        let url = url.trim();
+        let Some(domain) = url_host(url).filter(|_| url.starts_with("http")) else {
+            return;
+        };
+        if links.len() < MAX_LINKS && !links.iter().any(|link| link.url == url) {
+            links.push(EmailLink {
+                url: url.to_string(),
+                text,
+                domain,
+                is_tracker: is_tracker_url(url),
+            });
+        }
+    };
+
+    if let Some(html) = body_html {
+        for (attrs, inner) in tags(html, "a") {
+            if let Some(href) = attrs.get("href") {
+                let text = inner.map(|inner| tidy_text(&strip_tags(inner)));
+                add(href, text.filter(|text| !text.is_empty()));
+            }
+        }
+    }
+    if let Some(text) = body_plain {
+        for url in bare_urls(text) {
+            add(url, None);
+        }
+    }
+    links
+}
+
+/// Metadata of a page fetched from `page_url`, preferring Open Graph fields. The icon
+/// falls back to `/favicon.ico` of the page's site.
+pub fn parse_link_metadata(html: &str, page_url: &str) -> LinkMetadata {
+    let mut metadata = LinkMetadata::default();
+    let base = url::Url::parse(page_url).ok();
+
+    for (attrs, _) in tags(html, "meta") {
+        let key = attrs
+            .get("property")
+            .or_else(|| attrs.get("name"))
+            .map(|key| key.to_ascii_lowercase());
+        let Some(content) = attrs.get("content").map(|content| tidy_text(content)) else {
+            continue;
+        };
+        match key.as_deref() {
+            Some("og:title") => metadata.title = Some(content),
+            Some("og:description") => metadata.description = Some(content),
+            Some("description") if metadata.description.is_none() => {
+                metadata.description = Some(content)
+            }
+            _ => {}
+        }
+    }
+    if metadata.title.is_none() {
+        metadata.title = tags(html, "title")
+            .into_iter()
+            .find_map(|(_, inner)| inner)
+            .map(tidy_text)
+            .filter(|title| !title.is_empty());
+    }
+
+    let icon = tags(html, "link").into_iter().find_map(|(attrs, _)| {
+        let rel = attrs.get("rel")?.to_ascii_lowercase();
+        rel.split_whitespace()
+            .any(|rel| rel == "icon")
+            .then(|| attrs.get("href").cloned())
+            .flatten()
+    });
+    metadata.favicon = base.and_then(|base| {
+        base.join(icon.as_deref().unwrap_or("/favicon.ico"))
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .map(|url| url.to_string())
+    });
+    metadata
+}
+
+/// Whether a preview may be fetched for `url`: an http(s) URL that isn't a tracker and
+/// doesn't point into the local network
+pub fn is_previewable(url: &str) -> bool {
+    let Ok(parsed) = url::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") || is_tracker_url(url) {
+        return false;
+    }
+    match parsed.host() {
+        Some(url::Host::Domain(domain)) => {
+            let domain = domain.to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost") && !domain.ends_with(".local")
+        }
+        Some(url::Host::Ipv4(ip)) => is_public_ipv4(ip),
+        Some(url::Host::Ipv6(ip)) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => {
+                let first = ip.segments()[0];
+                // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+        None => false,
+    }
+}
+
+fn is_public_ipv4(ip: std::net::Ipv4Addr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast())
+}
+
+/// Attributes of every `<name>` tag, with the text up to the matching closing tag when
+/// there is one
+fn tags<'a>(html: &'a str, name: &str) -> Vec<(HashMap<String, String>, Option<&'a str>)> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let close = format!("</{}", name);
+    let mut tags = Vec::new();
+    let mut pos = 0;
+
+    while let Some(offset) = lower[pos..].find(&open) {
+        let start = pos + offset + open.len();
+        pos = start;
+        // `<a` must not match `<abbr>` or `<area>`
+        if !lower[start..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>') {
+            continue;
+        }
+        let end = lower[start..]
+            .find('>')
+            .map_or(html.len(), |end| start + end);
+        let attrs = parse_attributes(&html[start..end]);
+        let body_start = (end + 1).min(html.len());
+        let inner = lower[body_start..]
+            .find(&close)
+            .map(|close| &html[body_start..body_start + close]);
+        tags.push((attrs, inner));
+        pos = end;
+    }
+    tags
+}
+
+pub(super) fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Collapse whitespace, decode common entities and cap the length
+fn tidy_text(text: &str) -> String {
+    text.replace("&amp;", "&")
+        .replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TEXT_CHARS)
+        .collect()
+}
+
+/// http(s) URLs in plain text, without trailing punctuation
+fn bare_urls(text: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = text[pos..].find("http") {
+        let start = pos + offset;
+        let rest = &text[start..];
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\''))
+            .unwrap_or(rest.len());
+        let mut url = &rest[..len];
+        while let Some(trimmed) = url.strip_suffix(['.', ',', ';', ':', '!', '?', ']', '}']) {
+            url = trimmed;
+        }
+        // Keep a closing parenthesis only when the URL opened one
+        if url.ends_with(')') && !url.contains('(') {
+            url = &url[..url.len() - 1];
+        }
+        let has_host = url
+            .split_once("://")
+            .is_some_and(|(scheme, rest)| matches!(scheme, "http" | "https") && !rest.is_empty());
+        if has_host {
+            urls.push(url);
+        }
+        pos = start + len.max(4);
+    }
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let html = r#"<p>See <A HREF="https://example.com/report?id=1&amp;v=2">the <b>Q3</b>
+            report</a>, <abbr title="x">abbr</abbr> and
+            <a href="https://click.list-manage.com/track?u=1">unsubscribe</a>
+            <a href="mailto:ada@example.com">mail</a>
+            <a href="https://example.com/report?id=1&v=2">again</a></p>"#;
+        let plain = "Report: https://example.com/report?id=1&v=2.\n\
+            Docs (https://docs.example.org/guide).";
+        let links = extract_links(Some(html), Some(plain));
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].url, "https://example.com/report?id=1&v=2");
+        assert_eq!(links[0].text.as_deref(), Some("the Q3 report"));
+        assert_eq!(links[0].domain, "example.com");
+        assert!(!links[0].is_tracker);
+        assert!(links[1].is_tracker);
+        assert_eq!(links[2].url, "https://docs.example.org/guide");
+        assert_eq!(links[2].text, None);
+    }
+
+    #[test]
+    fn test_bare_urls() {
+        assert_eq!(
+            bare_urls("Wiki: https://en.wikipedia.org/wiki/Rust_(language)! or http:// nothing"),
+            vec!["https://en.wikipedia.org/wiki/Rust_(language)"]
+        );
+    }
+
+    #[test]
+    fn test_is_previewable() {
+        assert!(is_previewable("https://example.com/post"));
+        assert!(!is_previewable("https://mailtrack.io/trace/link/abc"));
+        assert!(!is_previewable("http://localhost:8080/admin"));
+        assert!(!is_previewable("http://192.168.1.1/"));
+        assert!(!is_previewable("file:///etc/passwd"));
+        assert!(!is_previewable("http://10.0.0.1/"));
+        assert!(!is_previewable("http://[::1]/"));
+        assert!(!is_previewable("http://[fd12:3456::1]/"));
+        assert!(!is_previewable("http://[fc00::1]/"));
+        assert!(!is_previewable("http://[fe80::1]/"));
+        assert!(!is_previewable("http://[::ffff:192.168.1.1]/"));
+        assert!(!is_previewable("http://[::ffff:127.0.0.1]/"));
+        assert!(is_previewable("http://[2606:4700::1111]/"));
+        assert!(is_previewable("http://[::ffff:93.184.216.34]/"));
+    }
+
+    #[test]
+    fn test_parse_link_metadata() {
+        let html = r#"<html><head><title> Example
+            Domain </title>
+            <meta name="description" content="Plain description">
+            <meta property="og:description" content="Open Graph &amp; more">
+            <link rel="shortcut icon" href="/static/icon.png"></head></html>"#;
+        let metadata = parse_link_metadata(html, "https://example.com/a/b");
+        assert_eq!(metadata.title.as_deref(), Some("Example Domain"));
+        assert_eq!(metadata.description.as_deref(), Some("Open Graph & more"));
+        assert_eq!(
+            metadata.favicon.as_deref(),
+            Some("https://example.com/static/icon.png")
+        );
+
+        let bare = parse_link_metadata("<p>hi</p>", "https://example.com/a");
+        assert_eq!(bare.title, None);
+        assert_eq!(
+            bare.favicon.as_deref(),
+            Some("https://example.com/favicon.ico")
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Meeting times proposed in an email
+//!
+//! Rule-based fallback for when no model is loaded, built on the deadline parser's date
+//! expressions (see `dates`): a day ("Tuesday", "Oct 20", "tomorrow") next to a time or
+//! a range of times ("at 2pm", "10:00-11:00", "from 2 to 3pm") is a candidate slot,
+//! resolved relative to when the email was sent. "Tuesday at 2pm or 4pm" gives two
+//! slots. A day without a time isn't a slot, since the reply has to pin one down. When
+//! the email says how long the meeting is, slots without an end get that length.
+
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use super::dates::{local_naive, parse_date, parse_time, to_timestamp, tokenize};
+
+/// Most slots returned for one email
+const MAX_SLOTS: usize = 10;
+
+/// Longest phrase read for one slot, in tokens
+const SLOT_WINDOW: usize = 10;
+
+/// A candidate meeting time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeetingSlot {
+    /// Unix timestamp
+    pub start: i64,
+    /// None when the email gives neither an end nor a length
+    pub end: Option<i64>,
+}
+
+/// A slot in local time, before it is resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NaiveSlot {
+    pub start: NaiveDateTime,
+    pub end: Option<NaiveDateTime>,
+}
+
+/// Number of tokens `parse_time` reads for a time at the start of `tokens`
+fn time_len(tokens: &[&str]) -> usize {
+    match tokens {
+        [first, "am" | "pm", ..] if meridiem(first).is_none() => 2,
+        _ => 1,
+    }
+}
+
+fn meridiem(token: &str) -> Option<&'static str> {
+    if token.ends_with("am") {
+        Some("am")
+    } else if token.ends_with("pm") {
+        Some("pm")
+    } else {
+        None
+    }
+}
+
+/// Start of a range, taking the end's am/pm when it has none ("2" in "2-3pm")
+fn range_start(start: &str, end_tokens: &[&str]) -> Option<NaiveTime> {
+    parse_time(&[start]).or_else(|| {
+        let suffix = end_tokens
+            .first()
+            .and_then(|end| meridiem(end))
+            .or_else(|| end_tokens.get(1).and_then(|token| meridiem(token)))?;
+        parse_time(&[start, suffix])
+    })
+}
+
+/// A time or range of times at the start of `tokens`: "2pm", "2-3pm", "10:00-11:30",
+/// "2pm to 3pm", "2 - 3pm". Returns the start, the end and how many tokens were read.
+fn parse_time_range(tokens: &[&str]) -> Option<(NaiveTime, Option<NaiveTime>, usize)> {
+    let first = *tokens.first()?;
+    // "2-3pm" and "10:00-11:00" are one token
+    if let Some((start, end)) = first.split_once(['-', '–']) {
+        let mut end_tokens = vec![end];
+        end_tokens.extend(tokens.get(1));
+        let end = parse_time(&end_tokens)?;
+        let start = range_start(start, &end_tokens)?;
+        return Some((
+            start,
+            Some(end).filter(|end| *end > start),
+            time_len(&end_tokens),
+        ));
+    }
+
+    let start_len = time_len(tokens);
+    if let Some(["-" | "–" | "to" | "until" | "till", end_tokens @ ..]) = tokens.get(start_len..)
+    {
+        if let Some(end) = parse_time(end_tokens) {
+            let start = match start_len {
+                1 => range_start(first, end_tokens),
+                _ => parse_time(tokens),
+            }?;
+            let read = start_len + 1 + time_len(end_tokens);
+            return Some((start, Some(end).filter(|end| *end > start), read));
+        }
+    }
+    Some((parse_time(tokens)?, None, start_len))
+}
+
+/// A day and a time at the start of `tokens`, in either order: "Tuesday at 2pm",
+/// "2-3pm on Oct 20". Returns the slot and how many tokens were read.
+fn slot_at(tokens: &[&str], reference: NaiveDateTime) -> Option<(NaiveSlot, usize)> {
+    let (date, time, read) = match parse_date(tokens, reference.date()) {
+        Some((date, _, mut read)) => {
+            if matches!(tokens.get(read), Some(&("at" | "from" | "around" | "@"))) {
+                read += 1;
+            }
+            let (start, end, time_read) = parse_time_range(tokens.get(read..)?)?;
+            (date, (start, end), read + time_read)
+        }
+        None => {
+            let (start, end, time_read) = parse_time_range(tokens)?;
+            let (date, _, date_read) = parse_date(&tokens[time_read..], reference.date())?;
+            (date, (start, end), time_read + date_read)
+        }
+    };
+    let slot = NaiveSlot {
+        start: date.and_time(time.0),
+        end: time.1.map(|end| date.and_time(end)),
+    };
+    Some((slot, read))
+}
+
+/// Slots proposed in `text` in the order they're written, dates resolved against
+/// `reference`
+pub fn extract_slots_naive(text: &str, reference: NaiveDateTime) -> Vec<NaiveSlot> {
+    let owned = tokenize(text);
+    let tokens: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+    let mut slots: Vec<NaiveSlot> = Vec::new();
+    // Where the last slot's phrase ended, for "… at 2pm or 4pm"
+    let mut last_end: Option<usize> = None;
+
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let window = &tokens[idx..(idx + SLOT_WINDOW).min(tokens.len())];
+        if let Some((slot, read)) = slot_at(window, reference) {
+            slots.push(slot);
+            idx += read;
+            last_end = Some(idx);
+            continue;
+        }
+
+        // Another time on the same day as the slot just before
+        let follows_slot = last_end == Some(idx) && matches!(tokens[idx], "or" | "and");
+        if let (true, Some(previous)) = (follows_slot, slots.last().copied()) {
+            if let Some((start, end, read)) = parse_time_range(&window[1..]) {
+                let date = previous.start.date();
+                slots.push(NaiveSlot {
+                    start: date.and_time(start),
+                    end: end.map(|end| date.and_time(end)),
+                });
+                idx += 1 + read;
+                last_end = Some(idx);
+                continue;
+            }
+        }
+        idx += 1;
+    }
+    slots
+}
+
+/// How long the meeting is, when the email says: "30 minutes", "a 45-minute call",
+/// "1 hour", "half an hour"
+pub fn meeting_minutes(text: &str) -> Option<i64> {
+    let owned = tokenize(text);
+    let tokens: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+    for (i, token) in tokens.iter().copied().enumerate() {
+        let next = tokens.get(i + 1).copied().unwrap_or("");
+        if token == "half" && next == "an" && tokens.get(i + 2) == Some(&"hour") {
+            return Some(30);
+        }
+        let (count, unit) = if let Some(count) = token
+            .strip_suffix("mins")
+            .or_else(|| token.strip_suffix("min"))
+            .filter(|count| !count.is_empty())
+        {
+            (count, "min")
+        } else {
+            token.split_once('-').unwrap_or((token, next))
+        };
+        let count: f64 = match count {
+            "an" | "one" => 1.0,
+            _ => match count.parse() {
+                Ok(count) => count,
+                Err(_) => continue,
+            },
+        };
+        let minutes = if unit.starts_with("min") {
+            count
+        } else if unit.starts_with("hour") || unit.starts_with("hr") {
+            count * 60.0
+        } else {
+            continue;
+        };
+        if (5.0..=480.0).contains(&minutes) {
+            return Some(minutes as i64);
+        }
+    }
+    None
+}
+
+/// Read one line of the model's answer: "YYYY-MM-DD HH:MM", optionally followed by
+/// "-HH:MM" for the end
+pub fn parse_llm_slot(line: &str) -> Option<NaiveSlot> {
+    let line = line
+        .trim()
+        .trim_start_matches(|c: char| c == '-' || c == '*' || c.is_whitespace());
+    let start = NaiveDateTime::parse_from_str(line.get(..16)?, "%Y-%m-%d %H:%M").ok()?;
+    let rest = line[16..]
+        .trim_start_matches(|c: char| c == '-' || c == '–' || c.is_whitespace())
+        .trim();
+    let end = NaiveTime::parse_from_str(rest.get(..5).unwrap_or(rest), "%H:%M")
+        .ok()
+        .map(|end| start.date().and_time(end))
+        .filter(|end| *end > start);
+    Some(NaiveSlot { start, end })
+}
+
+/// Slots after the email was sent, earliest first and without repeats. Slots without an
+/// end get the meeting's length when `text` gives one.
+pub fn resolve_slots(slots: &[NaiveSlot], text: &str, sent_at: i64) -> Vec<MeetingSlot> {
+    let Some(reference) = local_naive(sent_at) else {
+        return Vec::new();
+    };
+    let minutes = meeting_minutes(text);
+    let mut resolved: Vec<MeetingSlot> = slots
+        .iter()
+        .filter(|slot| slot.start >= reference)
+        .filter_map(|slot| {
+            let end = slot
+                .end
+                .or_else(|| minutes.map(|minutes| slot.start + Duration::minutes(minutes)));
+            Some(MeetingSlot {
+                start: to_timestamp(slot.start)?,
+                end: end.and_then(to_timestamp),
+            })
+        })
+        .collect();
+    resolved.sort_by_key(|slot| slot.start);
+    resolved.dedup_by_key(|slot| slot.start);
+    resolved.truncate(MAX_SLOTS);
+    resolved
+}
+
+/// Slots proposed in an email sent at `sent_at`
+pub fn extract_slots(text: &str, sent_at: i64) -> Vec<MeetingSlot> {
+    match local_naive(sent_at) {
+        Some(reference) => resolve_slots(&extract_slots_naive(text, reference), text, sent_at),
+        None => Vec::new(),
+    }
+}
+
+/// A slot in words, in local time: "Tuesday, October 20 at 14:00–15:00"
+pub fn describe_slot(slot: &MeetingSlot) -> String {
+    let Some(start) = local_naive(slot.start) else {
+        return String::new();
+    };
+    let mut description = start.format("%A, %B %-d at %H:%M").to_string();
+    if let Some(end) = slot.end.and_then(local_naive) {
+        if end.date() == start.date() {
+            description.push_str(&end.format("–%H:%M").to_string());
+        }
+    }
+    description
+}
+
+/// Scheduling reply used when no model is available to draft one
+pub fn scheduling_template(slot_description: &str) -> String {
+    format!("Hi,\n\n{slot_description} works for me. Looking forward to it.\n\nThanks")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // Wednesday, 14 October 2026, 09:00
+    fn reference() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    fn at(d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn slot(start: NaiveDateTime, end: Option<NaiveDateTime>) -> NaiveSlot {
+        NaiveSlot { start, end }
+    }
+
+    #[test]
+    fn test_day_then_time() {
+        assert_eq!(
+            extract_slots_naive(
+                "Could we meet Thursday at 2pm or 4pm? Friday 10:00-11:00 also works.",
+                reference()
+            ),
+            vec![
+                slot(at(15, 14, 0), None),
+                slot(at(15, 16, 0), None),
+                slot(at(16, 10, 0), Some(at(16, 11, 0))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_then_day() {
+        assert_eq!(
+            extract_slots_naive("How about 2-3pm on October 20th?", reference()),
+            vec![slot(at(20, 14, 0), Some(at(20, 15, 0)))]
+        );
+        assert_eq!(
+            extract_slots_naive("Free tomorrow from 3 pm to 4:30 pm", reference()),
+            vec![slot(at(15, 15, 0), Some(at(15, 16, 30)))]
+        );
+    }
+
+    #[test]
+    fn test_no_slots() {
+        // A day without a time, and a time without a day
+        assert!(extract_slots_naive("Let's catch up next week on Monday", reference()).is_empty());
+        assert!(extract_slots_naive("The call is at 3pm", reference()).is_empty());
+    }
+
+    #[test]
+    fn test_meeting_minutes() {
+        assert_eq!(meeting_minutes("Do you have 30 minutes?"), Some(30));
+        assert_eq!(meeting_minutes("a quick 45-minute call"), Some(45));
+        assert_eq!(meeting_minutes("Block 1.5 hours"), Some(90));
+        assert_eq!(meeting_minutes("half an hour should do"), Some(30));
+        assert_eq!(meeting_minutes("15min sync"), Some(15));
+        assert_eq!(meeting_minutes("see you at 3"), None);
+    }
+
+    #[test]
+    fn test_parse_llm_slot() {
+        assert_eq!(
+            parse_llm_slot("- 2026-10-20 14:00-15:30"),
+            Some(slot(at(20, 14, 0), Some(at(20, 15, 30))))
+        );
+        assert_eq!(
+            parse_llm_slot("2026-10-20 14:00"),
+            Some(slot(at(20, 14, 0), None))
+        );
+        assert_eq!(parse_llm_slot("NONE"), None);
+    }
+
+    #[test]
+    fn test_resolve_slots() {
+        let sent_at = to_timestamp(reference()).unwrap();
+        let slots = [
+            slot(at(16, 10, 0), None),
+            slot(at(13, 10, 0), None),
+            slot(at(15, 14, 0), Some(at(15, 15, 0))),
+            slot(at(16, 10, 0), None),
+        ];
+        let resolved = resolve_slots(&slots, "a 30 min call", sent_at);
+        let ts = |t: NaiveDateTime| to_timestamp(t).unwrap();
+        assert_eq!(
+            resolved,
+            vec![
+                MeetingSlot {
+                    start: ts(at(15, 14, 0)),
+                    end: Some(ts(at(15, 15, 0))),
+                },
+                MeetingSlot {
+                    start: ts(at(16, 10, 0)),
+                    end: Some(ts(at(16, 10, 30))),
+                },
+            ]
+        );
+        assert_eq!(
+            describe_slot(&resolved[0]),
+            "Thursday, October 15 at 14:00–15:00"
+        );
+    }
+}
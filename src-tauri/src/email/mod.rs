@@ -1,8 +1,41 @@
+pub mod attachments;
+pub mod avatars;
+pub mod capabilities;
+pub mod crypto;
+pub mod dates;
+pub mod encoded_words;
+pub mod gmail;
 pub mod idle;
 pub mod imap_client;
+pub mod inline;
+pub mod labels;
+pub mod language;
+pub mod links;
+pub mod meetings;
+pub mod notifications;
+pub mod ocr;
+pub mod phishing;
+pub mod pop3;
+pub mod pop3_client;
+pub mod preview;
+pub mod priority;
 pub mod provider;
+pub mod quotes;
+pub mod receipts;
+pub mod reply;
+pub mod sanitize;
 pub mod server_presets;
+pub mod shipments;
+pub mod smime;
+pub mod special_folders;
+pub mod sync_window;
+pub mod threading;
+pub mod throttle;
+pub mod tls;
+pub mod trackers;
 pub mod types;
+pub mod utf7;
 
 pub use imap_client::ImapClient;
-pub use types::{Email, EmailListItem, Folder, SpecialFolder};
+pub use pop3_client::Pop3Client;
+pub use types::{Email, EmailListItem, Folder, Label, SpecialFolder};
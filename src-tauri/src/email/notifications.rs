@@ -0,0 +1,185 @@
+//! Which new mail raises a desktop notification
+//!
+//! Each account has its own settings. New mail is checked against them before the IDLE
+//! loop emits an alert: quiet hours silence everything, "VIP only" limits alerts to a
+//! list of senders, and the rest filter on the category and priority indexing assigned.
+//! Mail that isn't indexed yet has neither, so only automated senders are held back as
+//! low priority.
+
+use serde::{Deserialize, Serialize};
+
+use super::reply::is_automated_sender;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub enabled: bool,
+    /// Lowest priority that alerts: `HIGH`, `MEDIUM` or `LOW`
+    pub min_priority: String,
+    /// Categories that never alert, e.g. `newsletters`
+    #[serde(default)]
+    pub muted_categories: Vec<String>,
+    pub quiet_hours: Option<QuietHours>,
+    /// Alert only for mail from `vip_senders`
+    #[serde(default)]
+    pub vip_only: bool,
+    #[serde(default)]
+    pub vip_senders: Vec<String>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_priority: "LOW".to_string(),
+            muted_categories: Vec::new(),
+            quiet_hours: None,
+            vip_only: false,
+            vip_senders: Vec::new(),
+        }
+    }
+}
+
+/// Daily window without alerts, in minutes after local midnight. A window whose end is
+/// before its start runs past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_minute: u32,
+    pub end_minute: u32,
+}
+
+impl QuietHours {
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start_minute <= self.end_minute {
+            (self.start_minute..self.end_minute).contains(&minute)
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+}
+
+/// What is known about a new email when deciding whether to alert
+#[derive(Debug, Clone, Copy)]
+pub struct NewMail<'a> {
+    pub from_email: &'a str,
+    /// From the email's insights, once indexed
+    pub priority: Option<&'a str>,
+    pub category: Option<&'a str>,
+}
+
+impl NotificationSettings {
+    /// Whether `mail` should raise an alert at `minute` (minutes after local midnight)
+    pub fn should_notify(&self, mail: &NewMail<'_>, minute: u32) -> bool {
+        if !self.enabled || self.quiet_hours.is_some_and(|quiet| quiet.contains(minute)) {
+            return false;
+        }
+        let is_vip = self
+            .vip_senders
+            .iter()
+            .any(|vip| vip.trim().eq_ignore_ascii_case(mail.from_email));
+        if is_vip {
+            return true;
+        }
+        if self.vip_only {
+            return false;
+        }
+
+        if mail.category.is_some_and(|category| {
+            self.muted_categories
+                .iter()
+                .any(|muted| muted.eq_ignore_ascii_case(category))
+        }) {
+            return false;
+        }
+        let priority = match mail.priority {
+            Some(priority) => priority,
+            None if is_automated_sender(mail.from_email) => "LOW",
+            None => return true,
+        };
+        priority_rank(priority) >= priority_rank(&self.min_priority)
+    }
+}
+
+fn priority_rank(priority: &str) -> u8 {
+    match priority.to_ascii_uppercase().as_str() {
+        "HIGH" => 2,
+        "MEDIUM" => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mail<'a>(from_email: &'a str, priority: Option<&'a str>) -> NewMail<'a> {
+        NewMail {
+            from_email,
+            priority,
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let night = QuietHours {
+            start_minute: 22 * 60,
+            end_minute: 7 * 60,
+        };
+        assert!(night.contains(23 * 60));
+        assert!(night.contains(6 * 60 + 59));
+        assert!(!night.contains(7 * 60));
+        assert!(!night.contains(12 * 60));
+
+        let lunch = QuietHours {
+            start_minute: 12 * 60,
+            end_minute: 13 * 60,
+        };
+        assert!(lunch.contains(12 * 60 + 30));
+        assert!(!lunch.contains(13 * 60));
+
+        let settings = NotificationSettings {
+            quiet_hours: Some(night),
+            ..Default::default()
+        };
+        assert!(!settings.should_notify(&mail("bob@example.org", None), 23 * 60));
+        assert!(settings.should_notify(&mail("bob@example.org", None), 9 * 60));
+    }
+
+    #[test]
+    fn test_priority_and_category() {
+        let settings = NotificationSettings {
+            min_priority: "MEDIUM".to_string(),
+            muted_categories: vec!["newsletters".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.should_notify(&mail("bob@example.org", Some("HIGH")), 0));
+        assert!(!settings.should_notify(&mail("bob@example.org", Some("LOW")), 0));
+        // Not indexed yet
+        assert!(settings.should_notify(&mail("bob@example.org", None), 0));
+        assert!(!settings.should_notify(&mail("noreply@shop.example", None), 0));
+
+        let newsletter = NewMail {
+            category: Some("Newsletters"),
+            ..mail("bob@example.org", Some("HIGH"))
+        };
+        assert!(!settings.should_notify(&newsletter, 0));
+    }
+
+    #[test]
+    fn test_vip_senders() {
+        let settings = NotificationSettings {
+            min_priority: "HIGH".to_string(),
+            vip_only: true,
+            vip_senders: vec!["Ada@Example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(settings.should_notify(&mail("ada@example.com", Some("LOW")), 0));
+        assert!(!settings.should_notify(&mail("bob@example.org", Some("HIGH")), 0));
+
+        let disabled = NotificationSettings {
+            enabled: false,
+            ..settings
+        };
+        assert!(!disabled.should_notify(&mail("ada@example.com", None), 0));
+    }
+}
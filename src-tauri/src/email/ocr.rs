@@ -0,0 +1,386 @@
+//! Text from image attachments and scanned PDFs
+//!
+//! Runs the Tesseract command-line tool instead of linking libtesseract, so building
+//! the app doesn't need it and users who turn OCR on install it like any other program.
+//! Scanned PDFs are read from the JPEG page images embedded in them, which is how
+//! scanners and phone scanning apps store pages; other image encodings inside PDFs are
+//! not read.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::attachments::AttachmentKind;
+
+/// Languages read when the settings don't name any
+pub const DEFAULT_LANGUAGES: &str = "eng";
+
+/// Executable run when the settings don't give a path
+const TESSERACT: &str = "tesseract";
+
+/// Largest attachment read; bigger scans take minutes of CPU
+pub const MAX_OCR_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Most pages read from one PDF
+const MAX_PDF_PAGES: usize = 20;
+
+/// Embedded images smaller than this are logos and icons rather than scanned pages
+const MIN_PAGE_IMAGE_BYTES: usize = 16 * 1024;
+
+/// Longest Tesseract may take on one image before it's stopped
+const OCR_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Longest text kept per attachment
+const MAX_TEXT_CHARS: usize = 20_000;
+
+/// How an attachment's text is read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrSource {
+    Image,
+    Pdf,
+}
+
+/// How to read an attachment, or None for attachments OCR can't help with
+pub fn ocr_source(kind: AttachmentKind, content_type: &str, filename: &str) -> Option<OcrSource> {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match kind {
+        // Vector images carry their text already; HEIC isn't readable by Tesseract
+        AttachmentKind::Image
+            if !content_type.contains("svg")
+                && !content_type.contains("heic")
+                && !matches!(extension.as_str(), "svg" | "heic") =>
+        {
+            Some(OcrSource::Image)
+        }
+        AttachmentKind::Document if content_type == "application/pdf" || extension == "pdf" => {
+            Some(OcrSource::Pdf)
+        }
+        _ => None,
+    }
+}
+
+/// Tesseract language codes joined by `+`, such as `eng` or `eng+deu`
+pub fn is_valid_languages(languages: &str) -> bool {
+    !languages.is_empty()
+        && languages.split('+').all(|code| {
+            !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Whether `path` can be run as Tesseract: an existing file named `tesseract` (or
+/// `tesseract.exe`). The path is a setting, so this keeps it from naming any other
+/// program.
+pub fn is_valid_tesseract_path(path: &str) -> bool {
+    let path = Path::new(path);
+    let is_tesseract = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| stem.eq_ignore_ascii_case(TESSERACT))
+        && path
+            .extension()
+            .is_none_or(|extension| extension.eq_ignore_ascii_case("exe"));
+    is_tesseract && path.is_file()
+}
+
+/// The Tesseract executable to run
+fn program(tesseract: Option<&str>) -> Result<&str> {
+    match tesseract {
+        Some(path) if !is_valid_tesseract_path(path) => {
+            bail!("Not a Tesseract executable: {}", path)
+        }
+        Some(path) => Ok(path),
+        None => Ok(TESSERACT),
+    }
+}
+
+/// JPEG page images embedded in a PDF, in file order
+pub fn pdf_page_images(pdf: &[u8]) -> Vec<&[u8]> {
+    let mut images = Vec::new();
+    let mut rest = pdf;
+    while let Some(filter) = find(rest, b"/DCTDecode") {
+        rest = &rest[filter..];
+        let Some(stream) = find(rest, b"stream") else {
+            break;
+        };
+        let mut start = stream + b"stream".len();
+        if rest.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if rest.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let Some(length) = find(&rest[start..], b"endstream") else {
+            break;
+        };
+        let data = trim_eol(&rest[start..start + length]);
+        if data.len() >= MIN_PAGE_IMAGE_BYTES && data.starts_with(&[0xFF, 0xD8]) {
+            images.push(data);
+        }
+        rest = &rest[start + length..];
+    }
+    images
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn trim_eol(data: &[u8]) -> &[u8] {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.strip_suffix(b"\r").unwrap_or(data)
+}
+
+/// Recognized text with blank runs and lines of stray symbols dropped, capped at
+/// `MAX_TEXT_CHARS`
+pub fn clean_text(raw: &str) -> String {
+    let mut text = String::new();
+    let mut blank = false;
+    for line in raw.lines().map(str::trim) {
+        if !line.chars().any(char::is_alphanumeric) {
+            blank = !text.is_empty();
+            continue;
+        }
+        if blank {
+            text.push('\n');
+            blank = false;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((end, _)) => text.truncate(end),
+        None => text.truncate(text.trim_end().len()),
+    }
+    text
+}
+
+/// Whether Tesseract can be run
+pub fn is_available(tesseract: Option<&str>) -> bool {
+    let Ok(program) = program(tesseract) else {
+        return false;
+    };
+    Command::new(program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Text of one image, read by Tesseract from its standard input
+pub fn recognize(image: &[u8], languages: &str, tesseract: Option<&str>) -> Result<String> {
+    if !is_valid_languages(languages) {
+        bail!("Invalid OCR languages: {}", languages);
+    }
+    let program = program(tesseract)?;
+    let child = Command::new(program)
+        .args(["stdin", "stdout", "-l", languages])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {} (is Tesseract installed?)", program))?;
+    let output = wait_with_timeout(child, image, OCR_TIMEOUT)?;
+    if !output.status.success() {
+        bail!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Feed `input` to a child spawned with piped stdio and collect its output, killing it if
+/// it hasn't finished within `timeout`
+fn wait_with_timeout(mut child: Child, input: &[u8], timeout: Duration) -> Result<Output> {
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open Tesseract's input"))?;
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+
+    // Pipes are fed and drained on a thread so a stuck program can't block this one
+    let input = input.to_vec();
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let errors = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(stderr) = stderr.as_mut() {
+                let _ = stderr.read_to_end(&mut buf);
+            }
+            buf
+        });
+        let result = stdin.write_all(&input).and_then(|()| {
+            drop(stdin);
+            let mut buf = Vec::new();
+            if let Some(stdout) = stdout.as_mut() {
+                stdout.read_to_end(&mut buf)?;
+            }
+            Ok(buf)
+        });
+        let _ = tx.send(result.map(|out| (out, errors.join().unwrap_or_default())));
+    });
+
+    match rx.recv_timeout(timeout) {
+        Ok(result) => {
+            let (stdout, stderr) = result?;
+            let status = child.wait()?;
+            Ok(Output {
+                status,
+                stdout,
+                stderr,
+            })
+        }
+        Err(_) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("Tesseract stopped after {} seconds", timeout.as_secs())
+        }
+    }
+}
+
+/// Text of a downloaded attachment
+pub fn read_attachment(
+    path: &Path,
+    source: OcrSource,
+    languages: &str,
+    tesseract: Option<&str>,
+) -> Result<String> {
+    let data = std::fs::read(path)?;
+    let raw = match source {
+        OcrSource::Image => recognize(&data, languages, tesseract)?,
+        OcrSource::Pdf => pdf_page_images(&data)
+            .into_iter()
+            .take(MAX_PDF_PAGES)
+            .map(|page| recognize(page, languages, tesseract))
+            .collect::<Result<Vec<_>>>()?
+            .join("\n\n"),
+    };
+    Ok(clean_text(&raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ocr_source() {
+        assert_eq!(
+            ocr_source(AttachmentKind::Image, "image/jpeg", "receipt.jpg"),
+            Some(OcrSource::Image)
+        );
+        assert_eq!(
+            ocr_source(AttachmentKind::Image, "image/svg+xml", "logo.svg"),
+            None
+        );
+        assert_eq!(
+            ocr_source(AttachmentKind::Document, "application/pdf", "scan"),
+            Some(OcrSource::Pdf)
+        );
+        assert_eq!(
+            ocr_source(
+                AttachmentKind::Document,
+                "application/octet-stream",
+                "Letter.PDF"
+            ),
+            Some(OcrSource::Pdf)
+        );
+        assert_eq!(
+            ocr_source(AttachmentKind::Document, "text/plain", "notes.txt"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_languages() {
+        assert!(is_valid_languages("eng"));
+        assert!(is_valid_languages("eng+deu+chi_sim"));
+        assert!(!is_valid_languages(""));
+        assert!(!is_valid_languages("eng+"));
+        assert!(!is_valid_languages("eng --psm"));
+    }
+
+    #[test]
+    fn test_tesseract_path() {
+        let dir = std::env::temp_dir().join(format!("inboxed-ocr-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["tesseract", "tesseract.exe", "tesseract.sh", "sh"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+
+        assert!(is_valid_tesseract_path(&path("tesseract")));
+        assert!(is_valid_tesseract_path(&path("tesseract.exe")));
+        assert!(!is_valid_tesseract_path(&path("tesseract.sh")));
+        assert!(!is_valid_tesseract_path(&path("sh")));
+        // Missing, or a directory
+        assert!(!is_valid_tesseract_path(&path("missing/tesseract")));
+        assert!(!is_valid_tesseract_path(&dir.to_string_lossy()));
+        assert!(program(Some(&path("sh"))).is_err());
+        assert_eq!(program(None).unwrap(), TESSERACT);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_timeout() {
+        let spawn = |program: &str, args: &[&str]| {
+            Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap()
+        };
+        let output = wait_with_timeout(spawn("cat", &[]), b"scan", OCR_TIMEOUT).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"scan");
+
+        let started = std::time::Instant::now();
+        let slow = spawn("sleep", &["10"]);
+        let err = wait_with_timeout(slow, b"", Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("stopped after"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_pdf_page_images() {
+        let page = [&[0xFF, 0xD8][..], &[0x42; MIN_PAGE_IMAGE_BYTES]].concat();
+        let logo = [&[0xFF, 0xD8][..], &[0x42; 100]].concat();
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Type /XObject /Subtype /Image /Filter /DCTDecode >>\nstream\r\n".to_vec();
+        pdf.extend_from_slice(&page);
+        pdf.extend_from_slice(
+            b"\r\nendstream\nendobj\n2 0 obj\n<< /Filter /DCTDecode >>\nstream\n",
+        );
+        pdf.extend_from_slice(&logo);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n");
+
+        assert_eq!(pdf_page_images(&pdf), vec![page.as_slice()]);
+        assert!(pdf_page_images(b"%PDF-1.4 no images").is_empty());
+    }
+
+    #[test]
+    fn test_clean_text() {
+        assert_eq!(
+            clean_text("  TOTAL  $12.50 \n\n~~ |\n\n\nThank you\n"),
+            "TOTAL  $12.50\n\nThank you"
+        );
+        assert_eq!(clean_text(" \n--\n"), "");
+        assert_eq!(
+            clean_text(&"a".repeat(MAX_TEXT_CHARS + 10)).len(),
+            MAX_TEXT_CHARS
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Phishing heuristics
+//!
+//! Scores an email on signals commonly seen in phishing: display names that impersonate
+//! another address or brand, lookalike sender domains, urgent credential requests,
+//! failed SPF/DKIM/DMARC results and a Reply-To that diverts answers elsewhere.
+
+use serde::{Deserialize, Serialize};
+
+use super::types::Email;
+
+/// Score at or above which an email is flagged as suspicious
+pub const SUSPICIOUS_THRESHOLD: f64 = 0.5;
+
+/// Frequently impersonated brands and their legitimate domains
+const BRAND_DOMAINS: &[(&str, &str)] = &[
+    ("paypal", "paypal.com"),
+    ("apple", "apple.com"),
+    ("microsoft", "microsoft.com"),
+    ("office365", "microsoft.com"),
+    ("outlook", "outlook.com"),
+    ("google", "google.com"),
+    ("gmail", "gmail.com"),
+    ("amazon", "amazon.com"),
+    ("netflix", "netflix.com"),
+    ("facebook", "facebook.com"),
+    ("instagram", "instagram.com"),
+    ("linkedin", "linkedin.com"),
+    ("dropbox", "dropbox.com"),
+    ("docusign", "docusign.net"),
+    ("dhl", "dhl.com"),
+    ("fedex", "fedex.com"),
+    ("chase", "chase.com"),
+    ("wellsfargo", "wellsfargo.com"),
+    ("coinbase", "coinbase.com"),
+];
+
+/// Mailbox providers whose names sit close to a brand's (mail.com, ymail.com and
+/// gmail.com); addresses there are never lookalikes
+const MAIL_PROVIDERS: &[&str] = &[
+    "mail.com",
+    "ymail.com",
+    "email.com",
+    "hotmail.com",
+    "live.com",
+    "yahoo.com",
+    "aol.com",
+    "icloud.com",
+    "me.com",
+    "gmx.com",
+    "gmx.net",
+    "proton.me",
+    "protonmail.com",
+    "fastmail.com",
+    "zoho.com",
+    "yandex.com",
+];
+
+/// Labels under a country-code TLD that belong to the suffix, as in `co.uk`
+const COUNTRY_SECOND_LEVEL: &[&str] = &["co", "com", "net", "org", "gov", "ac", "edu", "ne", "or"];
+
+const URGENCY_PHRASES: &[&str] = &[
+    "urgent",
+    "immediately",
+    "within 24 hours",
+    "within 48 hours",
+    "account will be suspended",
+    "account has been suspended",
+    "account will be closed",
+    "unusual activity",
+    "suspicious activity",
+    "final notice",
+    "action required",
+    "failure to",
+];
+
+const CREDENTIAL_PHRASES: &[&str] = &[
+    "verify your account",
+    "verify your identity",
+    "confirm your account",
+    "confirm your identity",
+    "update your payment",
+    "update your billing",
+    "enter your password",
+    "reset your password",
+    "login details",
+    "log in to",
+    "sign in to",
+    "social security",
+    "credit card number",
+    "bank details",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhishingReport {
+    pub score: f64,
+    pub is_suspicious: bool,
+    pub reasons: Vec<String>,
+}
+
+fn domain_of(address: &str) -> String {
+    address
+        .rsplit('@')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .trim_end_matches('>')
+        .to_lowercase()
+}
+
+/// Registrable part of a domain (last two labels; good enough for heuristics)
+fn base_domain(domain: &str) -> String {
+    let labels: Vec<&str> = domain.split('.').filter(|l| !l.is_empty()).collect();
+    if labels.len() <= 2 {
+        labels.join(".")
+    } else {
+        labels[labels.len() - 2..].join(".")
+    }
+}
+
+/// The label a domain is registered under: `amazon` for `smile.amazon.co.uk`
+fn registrable_label(domain: &str) -> &str {
+    let labels: Vec<&str> = domain.split('.').filter(|l| !l.is_empty()).collect();
+    let suffix_len = match labels.as_slice() {
+        [.., _, second, tld] if tld.len() == 2 && COUNTRY_SECOND_LEVEL.contains(second) => 2,
+        _ => 1,
+    };
+    labels
+        .len()
+        .checked_sub(suffix_len + 1)
+        .map_or("", |i| labels[i])
+}
+
+/// Display name portion of a "Name <addr>" string
+fn display_name(from: &str) -> String {
+    match from.find('<') {
+        Some(i) => from[..i].trim().trim_matches('"').to_string(),
+        None => String::new(),
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut curr = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            curr[j] = (prev[j] + 1).min(curr[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
+
+/// Undo common character substitutions used in lookalike domains
+fn deconfuse(domain: &str) -> String {
+    domain
+        .replace("rn", "m")
+        .replace("vv", "w")
+        .replace('0', "o")
+        .replace('1', "l")
+        .replace('3', "e")
+        .replace('5', "s")
+}
+
+/// Brand domain that `domain` imitates without being it. Only the label the domain is
+/// registered under is compared with the brand's, so the brand's own country domains
+/// (amazon.ca, google.co.uk) and its subdomains pass.
+fn lookalike_of(domain: &str) -> Option<&'static str> {
+    let base = base_domain(domain);
+    if base.starts_with("xn--") || domain.contains(".xn--") {
+        return Some("an internationalized (punycode) domain");
+    }
+    if MAIL_PROVIDERS.contains(&base.as_str()) {
+        return None;
+    }
+
+    let label = registrable_label(domain);
+    BRAND_DOMAINS.iter().map(|(_, d)| *d).find(|legit| {
+        let brand = legit.split('.').next().unwrap_or("");
+        if label == brand {
+            return false;
+        }
+        // One edit is enough to turn a short word into another real one
+        let max_distance = if brand.len() >= 8 { 2 } else { 1 };
+        // Each word of the label on its own, so paypal-login.com and micros0ft-help.com
+        // match but pineapple-studio.com doesn't
+        label.split('-').any(|token| {
+            token == brand
+                || deconfuse(token) == brand
+                || (brand.len() >= 6 && levenshtein(token, brand) <= max_distance)
+        })
+            // e.g. paypal.com.account-check.net or paypal.com-security.net
+            || domain.contains(&format!("{}.", legit))
+            || domain.contains(&format!("{}-", legit))
+    })
+}
+
+fn contains_any(text: &str, phrases: &[&str]) -> bool {
+    phrases.iter().any(|p| text.contains(p))
+}
+
+/// Analyze an email for phishing signals
+pub fn analyze(email: &Email) -> PhishingReport {
+    let mut score: f64 = 0.0;
+    let mut reasons = Vec::new();
+
+    let from_domain = domain_of(&email.from_email);
+    let from_base = base_domain(&from_domain);
+    let name = display_name(&email.from).to_lowercase();
+
+    // Display name shows a different address, or names a brand the sender isn't
+    if name.contains('@') {
+        let shown = domain_of(&name);
+        if !shown.is_empty() && base_domain(&shown) != from_base {
+            score += 0.35;
+            reasons.push(format!(
+                "Display name shows {} but the message was sent from {}",
+                shown, from_domain
+            ));
+        }
+    } else if let Some((brand, legit)) = BRAND_DOMAINS.iter().find(|(brand, _)| {
+        name.split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == *brand)
+    }) {
+        if from_base != base_domain(legit) && !from_base.contains(brand) {
+            score += 0.3;
+            reasons.push(format!(
+                "Sender name mentions \"{}\" but the address is at {}",
+                brand, from_domain
+            ));
+        }
+    }
+
+    if !from_domain.is_empty() {
+        if let Some(target) = lookalike_of(&from_domain) {
+            score += 0.4;
+            reasons.push(format!("Sender domain {} looks like {}", from_domain, target));
+        }
+    }
+
+    let text = format!(
+        "{}\n{}",
+        email.subject,
+        email
+            .body_plain
+            .as_deref()
+            .or(email.body_html.as_deref())
+            .unwrap_or("")
+    )
+    .to_lowercase();
+    if contains_any(&text, URGENCY_PHRASES) && contains_any(&text, CREDENTIAL_PHRASES) {
+        score += 0.3;
+        reasons.push("Urgent language combined with a request for credentials or payment details".to_string());
+    }
+
+    if let Some(auth) = email.auth_results.as_deref() {
+        let auth = auth.to_lowercase();
+        let failed: Vec<&str> = ["spf", "dkim", "dmarc"]
+            .into_iter()
+            .filter(|check| auth.contains(&format!("{}=fail", check)))
+            .collect();
+        if !failed.is_empty() {
+            score += if failed.contains(&"dmarc") { 0.4 } else { 0.25 };
+            reasons.push(format!("Failed sender authentication: {}", failed.join(", ").to_uppercase()));
+        } else if auth.contains("spf=softfail") {
+            score += 0.1;
+            reasons.push("SPF soft-failed".to_string());
+        }
+    }
+
+    if let Some(reply_to) = email.reply_to.as_deref() {
+        let reply_domain = domain_of(reply_to);
+        if !reply_domain.is_empty() && !from_base.is_empty() && base_domain(&reply_domain) != from_base {
+            score += 0.2;
+            reasons.push(format!("Replies go to a different domain ({})", reply_domain));
+        }
+    }
+
+    let score = score.min(1.0);
+    PhishingReport {
+        score,
+        is_suspicious: score >= SUSPICIOUS_THRESHOLD,
+        reasons,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(from: &str, from_email: &str, body: &str) -> Email {
+        Email {
+            id: "acc:INBOX:1".to_string(),
+            thread_id: String::new(),
+            subject: "Notice".to_string(),
+            from: from.to_string(),
+            from_email: from_email.to_string(),
+            to: vec![],
+            date: String::new(),
+            date_timestamp: 0,
+            snippet: String::new(),
+            body_html: None,
+            body_plain: Some(body.to_string()),
+            body_clean: None,
+            labels: vec![],
+            is_read: false,
+            is_starred: false,
+            has_attachments: false,
+            account_id: "acc".to_string(),
+            uid: 1,
+            folder: "INBOX".to_string(),
+            message_id: String::new(),
+            references: Vec::new(),
+            reply_to: None,
+            auth_results: None,
+            inline_parts: Vec::new(),
+            remote_images_blocked: 0,
+            crypto: None,
+            smime: None,
+            read_receipt_to: None,
+            delivery_failure: None,
+            links: Vec::new(),
+            shipments: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_legitimate_email() {
+        let e = email("PayPal <service@paypal.com>", "service@paypal.com", "Your receipt");
+        let report = analyze(&e);
+        assert!(!report.is_suspicious);
+        assert!(report.reasons.is_empty());
+    }
+
+    #[test]
+    fn test_lookalike_domain_and_urgency() {
+        let e = email(
+            "PayPal Security <alert@paypa1.com>",
+            "alert@paypa1.com",
+            "Unusual activity detected. Verify your account immediately.",
+        );
+        let report = analyze(&e);
+        assert!(report.is_suspicious);
+        assert!(report.reasons.len() >= 2);
+    }
+
+    #[test]
+    fn test_auth_failure_and_reply_to() {
+        let mut e = email("Alice <alice@example.com>", "alice@example.com", "Hi");
+        e.auth_results = Some("mx.example.net; spf=pass; dkim=fail; dmarc=fail".to_string());
+        e.reply_to = Some("alice@other.net".to_string());
+        let report = analyze(&e);
+        assert!(report.is_suspicious);
+        assert_eq!(report.reasons.len(), 2);
+    }
+
+    #[test]
+    fn test_lookalike_domains() {
+        for domain in [
+            "paypa1.com",
+            "arnazon.com",
+            "netfllx.com",
+            "micros0ft-support.com",
+            "paypal-login.com",
+            "secure.paypal.com.account-check.net",
+            "paypal.com-security.net",
+        ] {
+            assert!(lookalike_of(domain).is_some(), "{} not flagged", domain);
+        }
+    }
+
+    #[test]
+    fn test_real_domains_are_not_lookalikes() {
+        for domain in [
+            "paypal.com",
+            "mail.google.com",
+            "amazon.ca",
+            "google.ca",
+            "smile.amazon.co.uk",
+            "mail.com",
+            "ymail.com",
+            "amazing.com",
+            "pineapple-studio.com",
+            "chased.org",
+        ] {
+            assert_eq!(lookalike_of(domain), None, "{} flagged", domain);
+        }
+
+        let mut e = email(
+            "Amazon.ca <orders@amazon.ca>",
+            "orders@amazon.ca",
+            "Shipped",
+        );
+        e.reply_to = Some("help@amazon-support.ca".to_string());
+        assert!(!analyze(&e).is_suspicious);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("paypal", "paypa1"), 1);
+        assert_eq!(levenshtein("amazon", "amazon"), 0);
+    }
+}
@@ -0,0 +1,142 @@
+//! POP3 protocol helpers (RFC 1939)
+//!
+//! POP3 exposes a single mailbox with no flags, and message numbers only hold for one
+//! session. `UIDL` is what identifies a message across sessions, so downloads are
+//! deduplicated on it and the `u32` uid in email ids is derived from it. That uid is a
+//! hash, so a message is only looked up by it when no other UIDL on the server shares it.
+
+use anyhow::{bail, Result};
+
+/// The only mailbox a POP3 server exposes
+pub const POP3_FOLDER: &str = "INBOX";
+
+/// Text of a `+OK` reply, or the server's message for `-ERR` as an error
+pub fn parse_status(line: &str) -> Result<&str> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if let Some(text) = line.strip_prefix("+OK") {
+        Ok(text.trim_start())
+    } else if let Some(text) = line.strip_prefix("-ERR") {
+        bail!("POP3 server error: {}", text.trim())
+    } else {
+        bail!("Unexpected POP3 response: {}", line)
+    }
+}
+
+/// Whether a line ends a multi-line reply
+pub fn is_terminator(line: &[u8]) -> bool {
+    matches!(line, b".\r\n" | b".\n" | b".")
+}
+
+/// Undo the byte-stuffing of a multi-line reply line (`..` at the start stands for `.`)
+pub fn unstuff(line: &[u8]) -> &[u8] {
+    if line.starts_with(b"..") {
+        &line[1..]
+    } else {
+        line
+    }
+}
+
+/// `(message number, unique id)` pairs of a `UIDL` listing
+pub fn parse_uidl(listing: &str) -> Vec<(u32, String)> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let number = fields.next()?.parse().ok()?;
+            let uidl = fields.next()?;
+            Some((number, uidl.to_string()))
+        })
+        .collect()
+}
+
+/// `(message number, size in octets)` pairs of a `LIST` listing
+pub fn parse_list(listing: &str) -> Vec<(u32, u64)> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let number = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            Some((number, size))
+        })
+        .collect()
+}
+
+/// Uid for a UIDL: a 32-bit FNV-1a hash, never 0. Stable across sessions and
+/// reinstalls, which server message numbers are not.
+pub fn uid_for(uidl: &str) -> u32 {
+    uidl.bytes()
+        .fold(0x811c_9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+        })
+        .max(1)
+}
+
+/// Message number of the message with `uid` in a `UIDL` listing. Fails when the message
+/// is gone, or when several UIDLs hash to `uid` and it's unclear which one is meant.
+pub fn number_for(uidls: &[(u32, String)], uid: u32) -> Result<u32> {
+    let mut matches = uidls.iter().filter(|(_, uidl)| uid_for(uidl) == uid);
+    let Some((number, _)) = matches.next() else {
+        bail!("Message is no longer on the POP3 server")
+    };
+    if matches.next().is_some() {
+        bail!("Several messages on the POP3 server share this id; refusing to pick one")
+    }
+    Ok(*number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("+OK 2 320\r\n").unwrap(), "2 320");
+        assert_eq!(parse_status("+OK\r\n").unwrap(), "");
+        let err = parse_status("-ERR invalid password\r\n").unwrap_err();
+        assert!(err.to_string().contains("invalid password"));
+        assert!(parse_status("* OK IMAP ready").is_err());
+    }
+
+    #[test]
+    fn test_listings() {
+        let uidl = "1 whqtswO00WBw418f9t5JxYwZ\r\n2 QhdPYR:00WBw1Ph7x7\r\n";
+        assert_eq!(
+            parse_uidl(uidl),
+            vec![
+                (1, "whqtswO00WBw418f9t5JxYwZ".to_string()),
+                (2, "QhdPYR:00WBw1Ph7x7".to_string()),
+            ]
+        );
+        assert_eq!(parse_list("1 120\r\n2 200\r\n"), vec![(1, 120), (2, 200)]);
+        assert!(parse_uidl("garbage\r\n").is_empty());
+    }
+
+    #[test]
+    fn test_multiline_framing() {
+        assert!(is_terminator(b".\r\n"));
+        assert!(!is_terminator(b"..\r\n"));
+        assert_eq!(unstuff(b"..signature\r\n"), b".signature\r\n");
+        assert_eq!(unstuff(b"Hello\r\n"), b"Hello\r\n");
+    }
+
+    #[test]
+    fn test_uid_for() {
+        assert_eq!(uid_for("abc"), uid_for("abc"));
+        assert_ne!(uid_for("abc"), uid_for("abd"));
+        assert_ne!(uid_for(""), 0);
+    }
+
+    #[test]
+    fn test_number_for() {
+        let uidls = vec![(1, "abc".to_string()), (2, "uidl549599".to_string())];
+        assert_eq!(number_for(&uidls, uid_for("abc")).unwrap(), 1);
+        assert_eq!(number_for(&uidls, uid_for("uidl549599")).unwrap(), 2);
+        assert!(number_for(&uidls, uid_for("gone")).is_err());
+
+        // Both hash to the same uid
+        assert_eq!(uid_for("uidl549599"), uid_for("uidl712382"));
+        let colliding = vec![(1, "uidl549599".to_string()), (2, "uidl712382".to_string())];
+        assert!(number_for(&colliding, uid_for("uidl549599")).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+use anyhow::{bail, Context, Result};
+use futures::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::collections::{HashMap, HashSet};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+use super::imap_client::{parse_message, ImapClient, ImapCredentials};
+use super::pop3::{self, POP3_FOLDER};
+use super::provider::{EmailProvider, ImapFlag};
+use super::server_presets::ServerConfig;
+use super::tls::{self, TlsStream};
+use super::types::{Email, EmailListItem, Folder, Label, SpecialFolder};
+use crate::db::search_query::SearchQuery;
+
+/// Messages larger than this are left on the server
+const MAX_MESSAGE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Download-only client for POP3 accounts. Each operation opens its own session: servers
+/// lock the maildrop while one is open, and message numbers only hold within it.
+pub struct Pop3Client {
+    pub account_id: String,
+    pub email: String,
+    server_config: ServerConfig,
+    credentials: ImapCredentials,
+}
+
+/// An authenticated POP3 connection
+struct Pop3Session {
+    stream: BufReader<TlsStream>,
+}
+
+impl Pop3Session {
+    async fn read_line(&mut self) -> Result<Vec<u8>> {
+        let mut line = Vec::new();
+        if self.stream.read_until(b'\n', &mut line).await? == 0 {
+            bail!("POP3 server closed the connection");
+        }
+        Ok(line)
+    }
+
+    /// Send a command and return the text of its `+OK` reply
+    async fn command(&mut self, command: &str) -> Result<String> {
+        self.stream
+            .write_all(format!("{}\r\n", command).as_bytes())
+            .await?;
+        self.stream.flush().await?;
+        let line = self.read_line().await?;
+        pop3::parse_status(&String::from_utf8_lossy(&line)).map(str::to_string)
+    }
+
+    /// Send a command with a multi-line reply and return its body, unstuffed
+    async fn multiline(&mut self, command: &str) -> Result<Vec<u8>> {
+        self.command(command).await?;
+        let mut body = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            if pop3::is_terminator(&line) {
+                return Ok(body);
+            }
+            body.extend_from_slice(pop3::unstuff(&line));
+        }
+    }
+
+    /// UIDLs of the maildrop, newest (highest message number) first
+    async fn uidls(&mut self) -> Result<Vec<(u32, String)>> {
+        let listing = self.multiline("UIDL").await?;
+        let mut uidls = pop3::parse_uidl(&String::from_utf8_lossy(&listing));
+        uidls.sort_by(|a, b| b.0.cmp(&a.0));
+        Ok(uidls)
+    }
+
+    /// Message number of the message with `uid` in this session
+    async fn find(&mut self, uid: u32) -> Result<u32> {
+        pop3::number_for(&self.uidls().await?, uid)
+    }
+
+    /// End the session; the server only applies `DELE`s once this succeeds
+    async fn quit(mut self) -> Result<()> {
+        self.command("QUIT").await?;
+        Ok(())
+    }
+}
+
+impl Pop3Client {
+    pub fn new(
+        account_id: String,
+        email: String,
+        server_config: ServerConfig,
+        credentials: ImapCredentials,
+    ) -> Self {
+        Self {
+            account_id,
+            email,
+            server_config,
+            credentials,
+        }
+    }
+
+    /// Connect to the POP3 server (the account's incoming host) and authenticate
+    async fn connect(&self) -> Result<Pop3Session> {
+        let tcp = TcpStream::connect((
+            self.server_config.imap_host.as_str(),
+            self.server_config.imap_port,
+        ))
+        .await
+        .context("Failed to connect to POP3 server")?;
+
+        let tls_stream = tls::handshake(
+            tcp,
+            &self.server_config.imap_host,
+            self.server_config.cert_fingerprint.as_deref(),
+        )
+        .await?;
+
+        let mut session = Pop3Session {
+            stream: BufReader::new(tls_stream),
+        };
+        let greeting = session.read_line().await?;
+        pop3::parse_status(&String::from_utf8_lossy(&greeting))?;
+
+        match &self.credentials {
+            ImapCredentials::Password { user, password } => {
+                session.command(&format!("USER {}", user)).await?;
+                session
+                    .command(&format!("PASS {}", password))
+                    .await
+                    .context("POP3 login failed")?;
+            }
+            ImapCredentials::OAuth2 { .. } => {
+                bail!("POP3 accounts sign in with a password")
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Check that the server is reachable and accepts the credentials
+    pub async fn check(&self) -> Result<()> {
+        self.connect().await?.quit().await
+    }
+
+    /// Download up to `limit` messages whose UIDL isn't in `known`, newest first.
+    /// Returns each email with its UIDL so the caller can record it.
+    pub async fn download_new(
+        &self,
+        known: &HashSet<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Email)>> {
+        let mut session = self.connect().await?;
+        let listing = session.multiline("LIST").await?;
+        let sizes: HashMap<u32, u64> = pop3::parse_list(&String::from_utf8_lossy(&listing))
+            .into_iter()
+            .collect();
+
+        let mut downloaded = Vec::new();
+        for (number, uidl) in session.uidls().await? {
+            if downloaded.len() >= limit {
+                break;
+            }
+            if known.contains(&uidl) {
+                continue;
+            }
+            if sizes
+                .get(&number)
+                .is_some_and(|size| *size > MAX_MESSAGE_SIZE)
+            {
+                info!(
+                    account = %self.account_id,
+                    "Skipping message {} larger than {} bytes",
+                    uidl, MAX_MESSAGE_SIZE
+                );
+                continue;
+            }
+
+            let raw = session.multiline(&format!("RETR {}", number)).await?;
+            match parse_message(
+                &self.account_id,
+                pop3::uid_for(&uidl),
+                POP3_FOLDER,
+                &raw,
+                &[],
+            ) {
+                Ok(email) => downloaded.push((uidl, email)),
+                Err(e) => warn!(
+                    account = %self.account_id,
+                    "Failed to parse message {}: {}",
+                    uidl, e
+                ),
+            }
+        }
+
+        session.quit().await?;
+        Ok(downloaded)
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailProvider for Pop3Client {
+    async fn list_messages(
+        &self,
+        folder: &str,
+        max_results: u32,
+        offset: u32,
+    ) -> Result<Vec<EmailListItem>> {
+        if !folder.eq_ignore_ascii_case(POP3_FOLDER) {
+            return Ok(Vec::new());
+        }
+
+        let mut session = self.connect().await?;
+        let mut items = Vec::new();
+        for (number, uidl) in session
+            .uidls()
+            .await?
+            .into_iter()
+            .skip(offset as usize)
+            .take(max_results as usize)
+        {
+            // Headers only
+            let headers = session.multiline(&format!("TOP {} 0", number)).await?;
+            let email = parse_message(
+                &self.account_id,
+                pop3::uid_for(&uidl),
+                POP3_FOLDER,
+                &headers,
+                &[],
+            )?;
+            items.push(ImapClient::to_list_item(&email));
+        }
+        session.quit().await?;
+
+        Ok(items)
+    }
+
+    async fn search_messages(
+        &self,
+        _folder: &str,
+        _criteria: &SearchQuery,
+        _max_results: u32,
+    ) -> Result<Vec<EmailListItem>> {
+        // POP3 can't search, and every message is downloaded anyway, so the local
+        // search already covers the mailbox
+        Ok(Vec::new())
+    }
+
+    async fn get_message(&self, folder: &str, uid: u32) -> Result<Email> {
+        if !folder.eq_ignore_ascii_case(POP3_FOLDER) {
+            bail!("POP3 accounts only have an inbox");
+        }
+
+        let mut session = self.connect().await?;
+        let number = session.find(uid).await?;
+        let raw = session.multiline(&format!("RETR {}", number)).await?;
+        session.quit().await?;
+
+        parse_message(&self.account_id, uid, POP3_FOLDER, &raw, &[])
+    }
+
+    async fn send_email(
+        &self,
+        _from: &str,
+        _to: Vec<String>,
+        _cc: Vec<String>,
+        _bcc: Vec<String>,
+        _subject: &str,
+        _body_html: &str,
+        _body_plain: &str,
+    ) -> Result<()> {
+        bail!("Sending from POP3 accounts is not supported")
+    }
+
+    async fn set_flags(
+        &self,
+        _folder: &str,
+        _uid: u32,
+        _flags: &[ImapFlag],
+        _add: bool,
+    ) -> Result<()> {
+        bail!("POP3 servers don't store flags")
+    }
+
+    async fn set_labels(
+        &self,
+        _folder: &str,
+        _uid: u32,
+        _labels: &[Label],
+        _add: bool,
+    ) -> Result<()> {
+        bail!("POP3 servers don't store labels")
+    }
+
+    async fn move_message(&self, _from_folder: &str, _uid: u32, _to_folder: &str) -> Result<()> {
+        bail!("POP3 accounts only have an inbox")
+    }
+
+    async fn delete_message(&self, folder: &str, uid: u32) -> Result<()> {
+        if !folder.eq_ignore_ascii_case(POP3_FOLDER) {
+            bail!("POP3 accounts only have an inbox");
+        }
+
+        let mut session = self.connect().await?;
+        let number = session.find(uid).await?;
+        session.command(&format!("DELE {}", number)).await?;
+        session.quit().await
+    }
+
+    async fn list_folders(&self) -> Result<Vec<Folder>> {
+        Ok(vec![Folder {
+            name: POP3_FOLDER.to_string(),
+            path: POP3_FOLDER.to_string(),
+            display_name: "Inbox".to_string(),
+            special: Some(SpecialFolder::Inbox),
+            delimiter: None,
+        }])
+    }
+
+    async fn create_folder(&self, _name: &str) -> Result<()> {
+        bail!("POP3 accounts only have an inbox")
+    }
+
+    async fn rename_folder(&self, _from: &str, _to: &str) -> Result<()> {
+        bail!("POP3 accounts only have an inbox")
+    }
+
+    async fn delete_folder(&self, _name: &str) -> Result<()> {
+        bail!("POP3 accounts only have an inbox")
+    }
+}
@@ -0,0 +1,77 @@
+//! Snippets for the list view from the start of a message
+//!
+//! The list pass only fetches envelopes, so until a body is downloaded there is nothing
+//! to preview. A partial fetch of the first bytes of the message text, with the headers
+//! that say how it is encoded, is enough for a snippet: the fragment is parsed as a
+//! message cut short, which yields the beginning of its first text part decoded.
+
+use mail_parser::MessageParser;
+
+use super::quotes;
+
+/// Bytes of message text fetched for a preview. Multipart messages spend some of them
+/// on part headers, and base64 on its overhead.
+pub const PREVIEW_BYTES: u32 = 1024;
+
+/// The header fields a preview needs, for `BODY.PEEK[HEADER.FIELDS (...)]`
+pub const PREVIEW_HEADERS: &str = "CONTENT-TYPE CONTENT-TRANSFER-ENCODING";
+
+/// Snippet of up to `max_chars` from `headers` (as fetched, ending in a blank line) and
+/// the first bytes of the message text. Empty if nothing readable comes out.
+pub fn snippet(headers: &[u8], text: &[u8], max_chars: usize) -> String {
+    let mut raw = headers.to_vec();
+    if !raw.ends_with(b"\r\n\r\n") && !raw.ends_with(b"\n\n") {
+        raw.extend_from_slice(b"\r\n");
+    }
+    raw.extend_from_slice(text);
+
+    let Some(message) = MessageParser::default().parse(&raw) else {
+        return String::new();
+    };
+    let body = message.body_text(0).unwrap_or_default();
+    quotes::snippet(&quotes::clean_body(&body), max_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text() {
+        let headers = b"Content-Type: text/plain; charset=utf-8\r\n\r\n";
+        let text = b"Hi Sam,\r\n\r\nThe  draft is\r\nattached.\r\n";
+        assert_eq!(
+            snippet(headers, text, 200),
+            "Hi Sam, The draft is attached."
+        );
+        assert_eq!(snippet(headers, text, 6), "Hi Sam");
+        assert_eq!(snippet(b"", b"", 200), "");
+    }
+
+    #[test]
+    fn test_encoded_and_cut_short() {
+        let headers = b"Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\r\n";
+        assert_eq!(
+            snippet(headers, b"Gr=C3=BC=C3=9Fe aus K=C3=B6ln, bis mor", 200),
+            "Grüße aus Köln, bis mor"
+        );
+
+        // Cut inside the second part of an alternative, which is never reached
+        let headers = b"Content-Type: multipart/alternative; boundary=\"b1\"\r\n\r\n";
+        let text = b"--b1\r\n\
+            Content-Type: text/plain; charset=utf-8\r\n\
+            Content-Transfer-Encoding: base64\r\n\r\n\
+            TWVldGluZyBtb3ZlZCB0byAz\r\n\
+            --b1\r\n\
+            Content-Type: text/html\r\n\r\n<p>Meet";
+        assert_eq!(snippet(headers, text, 200), "Meeting moved to 3");
+    }
+
+    #[test]
+    fn test_html_only() {
+        let headers = b"Content-Type: text/html; charset=utf-8\r\n\r\n";
+        let text = b"<html><body><p>Your <b>order</b> has shipped</p><p>Track";
+        assert_eq!(snippet(headers, text, 200), "Your order has shipped Track");
+    }
+}
@@ -0,0 +1,301 @@
+//! Personal priority scoring
+//!
+//! The model (or the starred flag when no model is loaded) gives a first guess; how the
+//! user treats the sender and the corrections they made decide the rest. The guess and
+//! the sender's history are combined with per-account weights through a logistic curve,
+//! and the weights are refit from the user's corrections (see `retune`). A sender the
+//! user corrected before is pulled towards the priority they picked, and an email the
+//! user corrected keeps that priority for good.
+
+use serde::{Deserialize, Serialize};
+
+/// Score at or above which an email is HIGH priority
+pub const HIGH_THRESHOLD: f64 = 0.7;
+
+/// Score at or below which an email is LOW priority
+pub const LOW_THRESHOLD: f64 = 0.3;
+
+/// Corrections needed before the weights are refit; fewer would mostly fit noise
+pub const MIN_CORRECTIONS: usize = 5;
+
+/// How many corrections a sender's history counts for against the scored guess
+const OVERRIDE_PRIOR: f64 = 0.5;
+
+const RETUNE_ITERATIONS: usize = 300;
+const RETUNE_RATE: f64 = 1.0;
+
+/// Pull of refit weights back towards the defaults, so a handful of corrections can't
+/// swing them arbitrarily
+const RETUNE_REGULARIZATION: f64 = 0.05;
+
+/// Score of a HIGH, MEDIUM or LOW label
+pub fn label_score(label: &str) -> f64 {
+    match label {
+        "HIGH" => 0.85,
+        "LOW" => 0.2,
+        _ => 0.5,
+    }
+}
+
+/// Label for a score
+pub fn label_for(score: f64) -> &'static str {
+    if score >= HIGH_THRESHOLD {
+        "HIGH"
+    } else if score <= LOW_THRESHOLD {
+        "LOW"
+    } else {
+        "MEDIUM"
+    }
+}
+
+/// What the user did with earlier mail from a sender
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SenderHistory {
+    /// Emails received from the sender
+    pub received: i64,
+    /// Of those, how many were read
+    pub read: i64,
+    /// Of those, how many the user answered
+    pub replied: i64,
+    /// Corrections the user made to the sender's emails
+    pub overrides: i64,
+    /// Average score of the corrected priorities
+    pub override_score: f64,
+}
+
+impl SenderHistory {
+    /// Share of the sender's mail that was read, 0.5 for an unknown sender
+    fn read_ratio(&self) -> f64 {
+        (self.read as f64 + 1.0) / (self.received as f64 + 2.0)
+    }
+
+    /// Share of the sender's mail that was answered, close to 0 for an unknown sender
+    fn reply_ratio(&self) -> f64 {
+        self.replied as f64 / (self.received as f64 + 2.0)
+    }
+}
+
+/// Inputs to the scoring curve for one email
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriorityFeatures {
+    /// Score of the model's label, 0.5 when no model was loaded
+    pub model_score: f64,
+    pub starred: bool,
+    pub read_ratio: f64,
+    pub reply_ratio: f64,
+}
+
+impl PriorityFeatures {
+    pub fn new(model_score: f64, starred: bool, sender: &SenderHistory) -> Self {
+        Self {
+            model_score,
+            starred,
+            read_ratio: sender.read_ratio(),
+            reply_ratio: sender.reply_ratio(),
+        }
+    }
+
+    /// Centered so that an unstarred email from an unknown sender with a MEDIUM guess
+    /// contributes nothing
+    fn values(&self) -> [f64; 5] {
+        [
+            1.0,
+            self.model_score - 0.5,
+            if self.starred { 1.0 } else { 0.0 },
+            self.read_ratio - 0.5,
+            self.reply_ratio,
+        ]
+    }
+}
+
+/// Weights of the scoring curve, refit per account from the user's corrections
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PriorityWeights {
+    pub bias: f64,
+    pub model: f64,
+    pub starred: f64,
+    pub read: f64,
+    pub reply: f64,
+}
+
+impl Default for PriorityWeights {
+    /// Reproduce the model's labels for an unknown sender, and lift starred mail by
+    /// about one level
+    fn default() -> Self {
+        Self {
+            bias: 0.0,
+            model: 5.0,
+            starred: 1.0,
+            read: 1.0,
+            reply: 2.0,
+        }
+    }
+}
+
+impl PriorityWeights {
+    fn values(&self) -> [f64; 5] {
+        [self.bias, self.model, self.starred, self.read, self.reply]
+    }
+
+    fn from_values(values: [f64; 5]) -> Self {
+        let [bias, model, starred, read, reply] = values;
+        Self {
+            bias,
+            model,
+            starred,
+            read,
+            reply,
+        }
+    }
+
+    /// Score between 0 and 1
+    pub fn score(&self, features: &PriorityFeatures) -> f64 {
+        let z: f64 = self
+            .values()
+            .iter()
+            .zip(features.values())
+            .map(|(w, x)| w * x)
+            .sum();
+        1.0 / (1.0 + (-z).exp())
+    }
+}
+
+/// A priority the user set by hand, with what the email looked like at the time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    pub features: PriorityFeatures,
+    pub priority: String,
+}
+
+/// Priority label and score of an email. `own_override` is the priority the user set
+/// on this very email, if any.
+pub fn classify(
+    features: &PriorityFeatures,
+    weights: &PriorityWeights,
+    sender: &SenderHistory,
+    own_override: Option<&str>,
+) -> (String, f64) {
+    if let Some(priority) = own_override {
+        return (priority.to_string(), label_score(priority));
+    }
+
+    let mut score = weights.score(features);
+    if sender.overrides > 0 {
+        let overrides = sender.overrides as f64;
+        score = (score * OVERRIDE_PRIOR + sender.override_score * overrides)
+            / (OVERRIDE_PRIOR + overrides);
+    }
+    (label_for(score).to_string(), score)
+}
+
+/// Refit `current` to the user's corrections by gradient descent on the log loss.
+/// Below `MIN_CORRECTIONS` the weights are kept as they are.
+pub fn retune(current: &PriorityWeights, corrections: &[Correction]) -> PriorityWeights {
+    if corrections.len() < MIN_CORRECTIONS {
+        return *current;
+    }
+
+    let defaults = PriorityWeights::default().values();
+    let mut weights = current.values();
+    let n = corrections.len() as f64;
+    for _ in 0..RETUNE_ITERATIONS {
+        let model = PriorityWeights::from_values(weights);
+        let mut gradient: [f64; 5] =
+            std::array::from_fn(|i| RETUNE_REGULARIZATION * (weights[i] - defaults[i]));
+        for correction in corrections {
+            let error = model.score(&correction.features) - label_score(&correction.priority);
+            for (g, x) in gradient.iter_mut().zip(correction.features.values()) {
+                *g += error * x / n;
+            }
+        }
+        for (w, g) in weights.iter_mut().zip(gradient) {
+            *w -= RETUNE_RATE * g;
+        }
+    }
+    PriorityWeights::from_values(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown_sender(model_score: f64, starred: bool) -> PriorityFeatures {
+        PriorityFeatures::new(model_score, starred, &SenderHistory::default())
+    }
+
+    #[test]
+    fn test_default_weights_follow_model() {
+        let weights = PriorityWeights::default();
+        let sender = SenderHistory::default();
+        for label in ["HIGH", "MEDIUM", "LOW"] {
+            let features = unknown_sender(label_score(label), false);
+            assert_eq!(classify(&features, &weights, &sender, None).0, label);
+        }
+        let starred = unknown_sender(0.5, true);
+        assert_eq!(classify(&starred, &weights, &sender, None).0, "HIGH");
+    }
+
+    #[test]
+    fn test_sender_history() {
+        let weights = PriorityWeights::default();
+        let answered = SenderHistory {
+            received: 20,
+            read: 20,
+            replied: 15,
+            ..Default::default()
+        };
+        let ignored = SenderHistory {
+            received: 20,
+            read: 1,
+            ..Default::default()
+        };
+        let (_, answered_score) = classify(
+            &PriorityFeatures::new(0.5, false, &answered),
+            &weights,
+            &answered,
+            None,
+        );
+        let (_, ignored_score) = classify(
+            &PriorityFeatures::new(0.5, false, &ignored),
+            &weights,
+            &ignored,
+            None,
+        );
+        assert!(answered_score >= HIGH_THRESHOLD);
+        assert!(ignored_score < 0.5);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let weights = PriorityWeights::default();
+        let features = unknown_sender(0.5, false);
+        let corrected = SenderHistory {
+            overrides: 1,
+            override_score: label_score("HIGH"),
+            ..Default::default()
+        };
+        assert_eq!(classify(&features, &weights, &corrected, None).0, "HIGH");
+        assert_eq!(
+            classify(&features, &weights, &corrected, Some("LOW")),
+            ("LOW".to_string(), 0.2)
+        );
+    }
+
+    #[test]
+    fn test_retune() {
+        let weights = PriorityWeights::default();
+        let starred_low = Correction {
+            features: unknown_sender(0.5, true),
+            priority: "LOW".to_string(),
+        };
+        assert_eq!(retune(&weights, std::slice::from_ref(&starred_low)), weights);
+
+        // The user keeps saying starred mail isn't important
+        let corrections = vec![starred_low; MIN_CORRECTIONS];
+        let tuned = retune(&weights, &corrections);
+        assert!(tuned.starred < weights.starred);
+        assert!(
+            tuned.score(&unknown_sender(0.5, true)) < weights.score(&unknown_sender(0.5, true))
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use super::types::{Email, EmailListItem, Folder};
+use super::types::{Email, EmailListItem, Folder, Label};
+use crate::db::search_query::SearchQuery;
 
 /// IMAP flag types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +37,15 @@ pub trait EmailProvider: Send + Sync {
         offset: u32,
     ) -> Result<Vec<EmailListItem>>;
 
+    /// Search a folder on the server, newest matches first, including messages that
+    /// haven't been fetched into the local cache
+    async fn search_messages(
+        &self,
+        folder: &str,
+        criteria: &SearchQuery,
+        max_results: u32,
+    ) -> Result<Vec<EmailListItem>>;
+
     /// Get a single message by UID
     async fn get_message(&self, folder: &str, uid: u32) -> Result<Email>;
 
@@ -55,6 +65,10 @@ pub trait EmailProvider: Send + Sync {
     async fn set_flags(&self, folder: &str, uid: u32, flags: &[ImapFlag], add: bool)
         -> Result<()>;
 
+    /// Add or remove user labels: IMAP keywords, or Gmail labels on Gmail
+    async fn set_labels(&self, folder: &str, uid: u32, labels: &[Label], add: bool)
+        -> Result<()>;
+
     /// Move a message to another folder
     async fn move_message(&self, from_folder: &str, uid: u32, to_folder: &str) -> Result<()>;
 
@@ -63,4 +77,13 @@ pub trait EmailProvider: Send + Sync {
 
     /// List all folders/mailboxes
     async fn list_folders(&self) -> Result<Vec<Folder>>;
+
+    /// Create a folder (a label on Gmail, which maps IMAP folders to labels)
+    async fn create_folder(&self, name: &str) -> Result<()>;
+
+    /// Rename a folder, keeping its messages
+    async fn rename_folder(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Delete a folder with the messages in it
+    async fn delete_folder(&self, name: &str) -> Result<()>;
 }
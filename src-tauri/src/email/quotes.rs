@@ -0,0 +1,131 @@
+//! Quoted history and signatures
+//!
+//! Replies usually carry the thread they answer below the new text, and many messages
+//! end in a signature or a "Sent from my phone" footer. `clean_body` keeps only what was
+//! written for the message itself; snippets, summaries and embeddings are made from it,
+//! while the full body is still what the viewer shows.
+
+/// Footers mail apps add in place of a signature
+const CLIENT_FOOTERS: &[&str] = &[
+    "Sent from my iPhone",
+    "Sent from my iPad",
+    "Sent from my Android",
+    "Sent from my Galaxy",
+    "Sent from my mobile",
+    "Sent from Outlook",
+    "Sent from Mail for Windows",
+    "Sent from Yahoo Mail",
+    "Get Outlook for",
+];
+
+/// Whether `line` starts the quoted history of a reply. `next` is the line after it, for
+/// headers that clients wrap over two lines.
+fn starts_quote(line: &str, next: Option<&str>) -> bool {
+    // "On <date>, <name> wrote:"
+    if line.starts_with("On ")
+        && (line.ends_with("wrote:") || next.is_some_and(|next| next.ends_with("wrote:")))
+    {
+        return true;
+    }
+    // Outlook's separators and its "From: / Sent:" header block
+    line.starts_with("-----Original Message-----")
+        || line.starts_with("________________________________")
+        || (line.starts_with("From:")
+            && next.is_some_and(|next| next.starts_with("Sent:") || next.starts_with("Date:")))
+}
+
+/// The body without quoted replies: `>` lines and everything from a reply header on
+pub fn strip_quoted(body: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut kept = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if starts_quote(trimmed, lines.get(i + 1).map(|next| next.trim())) {
+            break;
+        }
+        if !trimmed.starts_with('>') {
+            kept.push(*line);
+        }
+    }
+    kept.join("\n")
+}
+
+/// The body without its signature: everything from the `-- ` separator or a mail app's
+/// footer on
+pub fn strip_signature(body: &str) -> String {
+    let mut kept = Vec::new();
+    for line in body.lines() {
+        if line.trim_end() == "--" || CLIENT_FOOTERS.iter().any(|f| line.trim().starts_with(f)) {
+            break;
+        }
+        kept.push(line);
+    }
+    kept.join("\n")
+}
+
+/// The text written for this message, without quoted replies or signature. When nothing
+/// would be left, e.g. for a message that only quotes another, the whole body is kept.
+pub fn clean_body(body: &str) -> String {
+    let clean = strip_signature(&strip_quoted(body));
+    let clean = clean.trim();
+    if clean.is_empty() {
+        body.trim().to_string()
+    } else {
+        clean.to_string()
+    }
+}
+
+/// Up to `max_chars` of `text` on one line, for list previews
+pub fn snippet(text: &str, max_chars: usize) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(max_chars)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_quoted() {
+        let body = "Works for me.\n\nOn Tue, 3 Mar 2026 at 10:02, Sam Lee <sam@x.com>\nwrote:\n> Thursday at 3?\n> Sam";
+        assert_eq!(strip_quoted(body).trim(), "Works for me.");
+
+        let outlook = "Approved.\n\nFrom: Dana <dana@x.com>\nSent: Monday, March 2, 2026 9:14 AM\nSubject: Budget";
+        assert_eq!(strip_quoted(outlook).trim(), "Approved.");
+
+        let inline = "> Are we still on?\nYes, see you at 3.";
+        assert_eq!(strip_quoted(inline), "Yes, see you at 3.");
+
+        let prose = "On Monday the office is closed.\nFrom: the facilities team";
+        assert_eq!(strip_quoted(prose), prose);
+    }
+
+    #[test]
+    fn test_strip_signature() {
+        let body = "See attached.\n\n-- \nPriya Patel\nNorthwind";
+        assert_eq!(strip_signature(body).trim(), "See attached.");
+        assert_eq!(
+            strip_signature("On my way\n\nSent from my iPhone").trim(),
+            "On my way"
+        );
+        assert_eq!(
+            strip_signature("Prices -- as discussed -- are final."),
+            "Prices -- as discussed -- are final."
+        );
+    }
+
+    #[test]
+    fn test_clean_body() {
+        let body = "Thanks!\n\n--\nSam\n\nOn Mon, Sam wrote:\n> Draft attached";
+        assert_eq!(clean_body(body), "Thanks!");
+        assert_eq!(
+            clean_body("> only a quote\n> nothing new"),
+            "> only a quote\n> nothing new"
+        );
+        assert_eq!(snippet("Hello\r\n\n  there,\tall", 9), "Hello the");
+    }
+}
@@ -0,0 +1,260 @@
+//! Read receipts (MDN, RFC 8098) and delivery status notifications (DSN, RFC 3464)
+//!
+//! Both are `multipart/report` messages whose machine-readable part is a list of
+//! header-style fields. A DSN is parsed to find which recipients of a sent email bounced;
+//! an MDN is built when the user agrees to send a read receipt. Some MTAs (Exim among
+//! them) bounce with a plain message instead, naming the failed recipients in an
+//! `X-Failed-Recipients` header and quoting the original below the explanation.
+
+use serde::{Deserialize, Serialize};
+
+/// Header asking the recipient to send a read receipt to the given address
+pub const RECEIPT_REQUEST_HEADER: &str = "Disposition-Notification-To";
+
+/// Header of non-DSN bounces listing the addresses delivery failed for
+pub const FAILED_RECIPIENTS_HEADER: &str = "X-Failed-Recipients";
+
+/// Recipients of a sent email that the mail system gave up delivering to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryFailure {
+    /// Message-ID of the sent email, without angle brackets
+    pub message_id: String,
+    pub recipients: Vec<String>,
+    /// Diagnostic given for the first failed recipient, e.g. "550 5.1.1 User unknown"
+    pub reason: Option<String>,
+}
+
+/// Address a read receipt goes to, from a `Disposition-Notification-To` value
+pub fn receipt_address(header: &str) -> Option<String> {
+    let header = header.trim();
+    let address = match (header.rfind('<'), header.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &header[start + 1..end],
+        _ => header,
+    }
+    .trim();
+    (address.contains('@') && !address.contains(char::is_whitespace)).then(|| address.to_string())
+}
+
+/// Failed deliveries in a DSN, given the text of its `message/delivery-status` part and
+/// the returned headers (or message) of the original. `None` when nothing failed or the
+/// original can't be identified.
+pub fn parse_delivery_failure(report: &str, original: &str) -> Option<DeliveryFailure> {
+    let message_id = header_value(original, "Message-ID")?
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string();
+    if message_id.is_empty() {
+        return None;
+    }
+
+    let mut recipients = Vec::new();
+    let mut reason = None;
+    // The first block describes the reporting MTA, the rest one recipient each
+    for block in field_blocks(report).iter().skip(1) {
+        let field = |name: &str| {
+            block
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        if !field("Action").is_some_and(|action| action.trim().eq_ignore_ascii_case("failed")) {
+            continue;
+        }
+        if let Some(recipient) = field("Final-Recipient").or_else(|| field("Original-Recipient")) {
+            recipients.push(without_type(recipient).to_string());
+        }
+        if reason.is_none() {
+            reason = field("Diagnostic-Code")
+                .map(without_type)
+                .or_else(|| field("Status").map(str::trim))
+                .map(str::to_string);
+        }
+    }
+
+    (!recipients.is_empty()).then_some(DeliveryFailure {
+        message_id,
+        recipients,
+        reason,
+    })
+}
+
+/// Failed delivery described by an `X-Failed-Recipients` bounce, given the header value
+/// and the bounce text quoting the original message
+pub fn parse_failed_recipients(header: &str, bounce_text: &str) -> Option<DeliveryFailure> {
+    let recipients: Vec<String> = header
+        .split(',')
+        .map(str::trim)
+        .filter(|address| address.contains('@'))
+        .map(str::to_string)
+        .collect();
+    if recipients.is_empty() {
+        return None;
+    }
+
+    // The quoted original is the only part of the text with header lines
+    let message_id = bounce_text
+        .split("\n\n")
+        .chain(bounce_text.split("\r\n\r\n"))
+        .find_map(|section| header_value(section, "Message-ID"))?;
+    let message_id = message_id
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string();
+
+    (!message_id.is_empty()).then_some(DeliveryFailure {
+        message_id,
+        recipients,
+        reason: None,
+    })
+}
+
+/// The `message/disposition-notification` part of a read receipt for a message that
+/// `final_recipient` displayed
+pub fn disposition_report(
+    reporting_ua: &str,
+    final_recipient: &str,
+    original_message_id: &str,
+) -> String {
+    format!(
+        "Reporting-UA: {}\r\n\
+         Final-Recipient: rfc822; {}\r\n\
+         Original-Message-ID: <{}>\r\n\
+         Disposition: manual-action/MDN-sent-manually; displayed\r\n",
+        reporting_ua, final_recipient, original_message_id
+    )
+}
+
+/// Value of a header in a header section, unfolded; the section ends at the first empty
+/// line
+fn header_value(headers: &str, name: &str) -> Option<String> {
+    field_blocks(headers)
+        .into_iter()
+        .next()?
+        .into_iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+/// Groups of `Name: value` fields separated by empty lines, with folded lines joined
+fn field_blocks(text: &str) -> Vec<Vec<(String, String)>> {
+    let mut blocks = Vec::new();
+    let mut block: Vec<(String, String)> = Vec::new();
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                blocks.push(std::mem::take(&mut block));
+            }
+        } else if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = block.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            block.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// Value of a typed field such as `rfc822; bob@example.org` without the type
+fn without_type(value: &str) -> &str {
+    value.split_once(';').map_or(value, |(_, rest)| rest).trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "Reporting-MTA: dns; mx.example.com\r\n\
+        Arrival-Date: Mon, 5 Oct 2026 10:00:00 +0000\r\n\
+        \r\n\
+        Final-Recipient: rfc822; bob@example.org\r\n\
+        Action: failed\r\n\
+        Status: 5.1.1\r\n\
+        Diagnostic-Code: smtp; 550 5.1.1 <bob@example.org>:\r\n\
+        \x20recipient address rejected: User unknown\r\n\
+        \r\n\
+        Final-Recipient: rfc822; carol@example.org\r\n\
+        Action: delivered\r\n\
+        Status: 2.0.0\r\n";
+
+    const ORIGINAL: &str = "From: ada@example.com\r\n\
+        Message-ID:\r\n\
+        \x20<abc123@example.com>\r\n\
+        Subject: Hello\r\n\
+        \r\n\
+        Message-ID: <not-a-header@example.com>\r\n";
+
+    #[test]
+    fn test_parse_delivery_failure() {
+        let failure = parse_delivery_failure(REPORT, ORIGINAL).unwrap();
+        assert_eq!(failure.message_id, "abc123@example.com");
+        assert_eq!(failure.recipients, vec!["bob@example.org"]);
+        assert_eq!(
+            failure.reason.as_deref(),
+            Some("550 5.1.1 <bob@example.org>: recipient address rejected: User unknown")
+        );
+
+        let delivered = REPORT.replace("Action: failed", "Action: delayed");
+        assert_eq!(parse_delivery_failure(&delivered, ORIGINAL), None);
+        assert_eq!(parse_delivery_failure(REPORT, "Subject: Hello\r\n"), None);
+    }
+
+    #[test]
+    fn test_parse_failed_recipients() {
+        let bounce = "This message was created automatically by mail delivery software.\n\
+            \n\
+            A message that you sent could not be delivered to one or more of its\n\
+            recipients. This is a permanent error.\n\
+            \n\
+            ------ This is a copy of the message, including all the headers. ------\n\
+            \n\
+            From: ada@example.com\n\
+            Message-ID: <abc123@example.com>\n\
+            Subject: Hello\n\
+            \n\
+            Hi Bob\n";
+        let failure =
+            parse_failed_recipients("bob@example.org, carol@example.org", bounce).unwrap();
+        assert_eq!(failure.message_id, "abc123@example.com");
+        assert_eq!(
+            failure.recipients,
+            vec!["bob@example.org", "carol@example.org"]
+        );
+
+        assert_eq!(parse_failed_recipients("", bounce), None);
+        assert_eq!(
+            parse_failed_recipients("bob@example.org", "Delivery failed.\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_receipt_address() {
+        assert_eq!(
+            receipt_address("Ada Lovelace <ada@example.com>").as_deref(),
+            Some("ada@example.com")
+        );
+        assert_eq!(
+            receipt_address(" ada@example.com ").as_deref(),
+            Some("ada@example.com")
+        );
+        assert_eq!(receipt_address("undisclosed"), None);
+    }
+
+    #[test]
+    fn test_disposition_report() {
+        let report = disposition_report("Inboxed", "bob@example.org", "abc123@example.com");
+        let fields = &field_blocks(&report)[0];
+        assert!(fields.contains(&(
+            "Original-Message-ID".to_string(),
+            "<abc123@example.com>".to_string()
+        )));
+        assert!(report.ends_with("displayed\r\n"));
+    }
+}
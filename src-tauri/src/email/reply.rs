@@ -0,0 +1,170 @@
+//! Reply-needed heuristics
+//!
+//! Decides whether an email is waiting on an answer from the recipient: it comes from a
+//! person rather than an automated sender, and its new text (ignoring quoted history)
+//! asks a question or requests a response. Used directly when no model is loaded and as a
+//! cheap pre-filter before asking the LLM.
+
+use super::quotes::strip_quoted;
+
+/// Local parts of addresses that never expect an answer
+const AUTOMATED_LOCAL_PARTS: &[&str] = &[
+    "noreply",
+    "no-reply",
+    "no_reply",
+    "donotreply",
+    "do-not-reply",
+    "do_not_reply",
+    "notifications",
+    "notification",
+    "notify",
+    "mailer-daemon",
+    "postmaster",
+    "bounce",
+    "newsletter",
+    "news",
+    "alerts",
+    "updates",
+    "marketing",
+];
+
+const REQUEST_PHRASES: &[&str] = &[
+    "let me know",
+    "please confirm",
+    "please advise",
+    "please reply",
+    "please respond",
+    "get back to me",
+    "can you",
+    "could you",
+    "would you",
+    "will you",
+    "are you able",
+    "do you have",
+    "what do you think",
+    "your thoughts",
+    "thoughts?",
+    "waiting for your",
+    "looking forward to your reply",
+    "looking forward to hearing",
+    "awaiting your",
+];
+
+/// Whether the address belongs to a bulk or automated sender
+pub fn is_automated_sender(from_email: &str) -> bool {
+    let address = from_email.trim().trim_matches(['<', '>']).to_lowercase();
+    let local = address.split('@').next().unwrap_or("");
+    local.contains("noreply")
+        || local.contains("no-reply")
+        || AUTOMATED_LOCAL_PARTS
+            .iter()
+            .any(|part| local == *part || local.starts_with(&format!("{part}+")))
+}
+
+/// The part of a plain-text body written for this message, without quoted replies
+pub fn new_text(body: &str) -> String {
+    strip_quoted(body)
+}
+
+/// Whether the message asks the recipient something
+pub fn asks_for_reply(subject: &str, body_text: &str) -> bool {
+    // Links often carry query strings, which aren't questions
+    let text = new_text(body_text)
+        .split_whitespace()
+        .filter(|word| !word.contains("://") && !word.starts_with("www."))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    let subject = subject.to_lowercase();
+    // A reply's subject carries the original question, not a new one
+    let subject_asks = !subject.starts_with("re:") && subject.contains('?');
+    text.contains('?') || subject_asks || REQUEST_PHRASES.iter().any(|phrase| text.contains(phrase))
+}
+
+/// Heuristic verdict used when no model is loaded
+pub fn needs_reply(from_email: &str, subject: &str, body_text: &str) -> bool {
+    !is_automated_sender(from_email) && asks_for_reply(subject, body_text)
+}
+
+/// Subject for a message continuing the thread of `subject`
+pub fn reply_subject(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject.to_lowercase().starts_with("re:") {
+        subject.to_string()
+    } else {
+        format!("Re: {subject}")
+    }
+}
+
+/// Follow-up body used when no model is available to draft one
+pub fn followup_template(subject: &str) -> String {
+    let subject = subject.trim();
+    if subject.is_empty() {
+        "Hi,\n\nJust following up on my previous email. Have you had a chance to look at it?\n\nThanks".to_string()
+    } else {
+        format!("Hi,\n\nJust following up on my email about \"{subject}\". Have you had a chance to look at it?\n\nThanks")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_automated_senders() {
+        assert!(is_automated_sender("noreply@github.com"));
+        assert!(is_automated_sender("<No-Reply@accounts.example.com>"));
+        assert!(is_automated_sender("notifications+abc@service.io"));
+        assert!(is_automated_sender("shop-noreply@store.example"));
+        assert!(!is_automated_sender("priya@northwind.example"));
+        assert!(!is_automated_sender("newsroom-editor@paper.example"));
+    }
+
+    #[test]
+    fn test_quoted_text_is_ignored() {
+        let body = "Sounds good, thanks.\n\nOn Tue, 3 Mar 2026, Sam <sam@x.com> wrote:\n> Can you send the deck?";
+        assert_eq!(new_text(body).trim(), "Sounds good, thanks.");
+        assert!(!asks_for_reply("Re: Deck", body));
+
+        let inline = "> Are we still on?\nYes, see you at 3.";
+        assert!(!asks_for_reply("Re: Sync", inline));
+        assert!(!asks_for_reply("Re: Lunch tomorrow?", "Works for me."));
+    }
+
+    #[test]
+    fn test_needs_reply() {
+        assert!(needs_reply(
+            "sam@x.com",
+            "Deck",
+            "Hi, could you send me the Q3 deck before Friday"
+        ));
+        assert!(needs_reply(
+            "sam@x.com",
+            "Lunch tomorrow?",
+            "Free around noon."
+        ));
+        assert!(!needs_reply(
+            "noreply@bank.example",
+            "Your statement",
+            "Questions? Visit our help center."
+        ));
+        assert!(!needs_reply(
+            "sam@x.com",
+            "Slides",
+            "Uploaded here: https://drive.example/view?id=42"
+        ));
+        assert!(!needs_reply(
+            "sam@x.com",
+            "Notes",
+            "Here are the notes from today."
+        ));
+    }
+
+    #[test]
+    fn test_followup_helpers() {
+        assert_eq!(reply_subject("Q3 deck"), "Re: Q3 deck");
+        assert_eq!(reply_subject(" RE: Q3 deck"), "RE: Q3 deck");
+        assert!(followup_template("Q3 deck").contains("\"Q3 deck\""));
+        assert!(!followup_template("  ").contains("\"\""));
+    }
+}
@@ -0,0 +1,127 @@
+//! HTML sanitization for email bodies
+//!
+//! Email HTML is untrusted: it can carry scripts, inline event handlers and remote
+//! images used as read trackers. Bodies are cleaned here before they reach the webview.
+//! Remote images are blocked unless the caller opts in (per sender); detected
+//! trackers are blocked regardless.
+
+use super::trackers::{detect_trackers, is_tracker_url};
+use ammonia::Builder;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Presentational attributes commonly used by email templates
+const ALLOWED_ATTRIBUTES: &[&str] = &[
+    "style", "class", "align", "valign", "width", "height", "bgcolor", "color", "border",
+    "cellpadding", "cellspacing", "dir",
+];
+
+/// Legacy layout tags still common in email HTML
+const EXTRA_TAGS: &[&str] = &["center", "font"];
+
+/// Attributes that load remote resources when rendered
+const RESOURCE_ATTRIBUTES: &[&str] = &["src", "background", "srcset"];
+
+#[derive(Debug, Clone)]
+pub struct SanitizedHtml {
+    pub html: String,
+    /// Number of remote resources removed because remote loading was not allowed
+    pub blocked_remote: usize,
+}
+
+fn is_remote_url(value: &str) -> bool {
+    let lower = value.trim().to_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("//")
+}
+
+/// Clean an HTML body: drops scripts, event handlers, unsafe URL schemes and tracking
+/// images, and strips other remote image sources unless `allow_remote` is set.
+pub fn sanitize_html(html: &str, allow_remote: bool) -> SanitizedHtml {
+    let blocked = Arc::new(AtomicUsize::new(0));
+    let counter = blocked.clone();
+    let tracker_urls: HashSet<String> = detect_trackers(html).into_iter().map(|t| t.url).collect();
+
+    let mut builder = Builder::default();
+    builder
+        .add_tags(EXTRA_TAGS)
+        .add_generic_attributes(ALLOWED_ATTRIBUTES)
+        .add_tag_attributes("font", &["face", "size"])
+        .add_tag_attributes("td", &["background"])
+        .add_tag_attributes("table", &["background"])
+        .add_url_schemes(&["data", "cid"])
+        .link_rel(Some("noopener noreferrer"))
+        .attribute_filter(move |_element, attribute, value| {
+            if RESOURCE_ATTRIBUTES.contains(&attribute)
+                && (tracker_urls.contains(value) || is_tracker_url(value))
+            {
+                counter.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+
+            if allow_remote {
+                return Some(Cow::Borrowed(value));
+            }
+
+            if RESOURCE_ATTRIBUTES.contains(&attribute) && is_remote_url(value) {
+                counter.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+
+            // Inline styles can also pull remote images via url(...)
+            if attribute == "style" && value.to_lowercase().contains("url(") {
+                counter.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+
+            Some(Cow::Borrowed(value))
+        });
+
+    let html = builder.clean(html).to_string();
+
+    SanitizedHtml {
+        html,
+        blocked_remote: blocked.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_scripts_and_handlers() {
+        let input = r#"<p onclick="steal()">Hi</p><script>alert(1)</script><a href="javascript:x()">x</a>"#;
+        let result = sanitize_html(input, true);
+
+        assert!(!result.html.contains("script"));
+        assert!(!result.html.contains("onclick"));
+        assert!(!result.html.contains("javascript:"));
+        assert!(result.html.contains("Hi"));
+    }
+
+    #[test]
+    fn test_blocks_remote_images() {
+        let input = r#"<img src="https://tracker.example.com/p.gif"><img src="data:image/png;base64,AA==">"#;
+
+        let blocked = sanitize_html(input, false);
+        assert!(!blocked.html.contains("tracker.example.com"));
+        assert!(blocked.html.contains("data:image/png;base64,AA=="));
+        assert_eq!(blocked.blocked_remote, 1);
+
+        let allowed = sanitize_html(input, true);
+        assert!(allowed.html.contains("tracker.example.com"));
+        assert_eq!(allowed.blocked_remote, 0);
+    }
+
+    #[test]
+    fn test_blocks_trackers_when_remote_allowed() {
+        let input = r#"<img src="https://example.com/logo.png"><img src="https://example.com/o.gif" width="1" height="1">"#;
+        let result = sanitize_html(input, true);
+
+        assert!(result.html.contains("logo.png"));
+        assert!(!result.html.contains("o.gif"));
+        assert_eq!(result.blocked_remote, 1);
+    }
+}
@@ -15,6 +15,10 @@ pub enum ProviderType {
     Gmail,
     Outlook,
     Yahoo,
+    /// ProtonMail through the local Bridge app, which serves a self-signed certificate
+    ProtonBridge,
+    /// Download-only POP3 server; its host and port live in the IMAP fields
+    Pop3,
     Custom,
 }
 
@@ -24,6 +28,8 @@ impl ProviderType {
             ProviderType::Gmail => "gmail",
             ProviderType::Outlook => "outlook",
             ProviderType::Yahoo => "yahoo",
+            ProviderType::ProtonBridge => "proton_bridge",
+            ProviderType::Pop3 => "pop3",
             ProviderType::Custom => "custom",
         }
     }
@@ -33,6 +39,8 @@ impl ProviderType {
             "gmail" => ProviderType::Gmail,
             "outlook" | "microsoft" | "hotmail" => ProviderType::Outlook,
             "yahoo" => ProviderType::Yahoo,
+            "proton_bridge" | "proton" | "protonmail" => ProviderType::ProtonBridge,
+            "pop3" | "pop" => ProviderType::Pop3,
             _ => ProviderType::Custom,
         }
     }
@@ -46,8 +54,25 @@ pub struct ServerConfig {
     pub smtp_host: String,
     pub smtp_port: u16,
     pub use_tls: bool,
+    /// Pinned SHA-256 fingerprint of the server certificate (see `tls::normalize_fingerprint`);
+    /// when set, only that certificate is accepted, self-signed or not
+    #[serde(default)]
+    pub cert_fingerprint: Option<String>,
 }
 
+/// IMAP on the plain-text ports is upgraded with STARTTLS; elsewhere TLS is implicit
+pub fn imap_uses_starttls(port: u16) -> bool {
+    matches!(port, 143 | 1143)
+}
+
+/// SMTP on 465 uses implicit TLS; other ports are upgraded with STARTTLS
+pub fn smtp_uses_implicit_tls(port: u16) -> bool {
+    port == 465
+}
+
+/// POP3 over TLS (RFC 8314)
+pub const POP3_TLS_PORT: u16 = 995;
+
 /// Well-known server presets
 pub fn get_server_preset(provider: &ProviderType) -> Option<ServerConfig> {
     match provider {
@@ -57,6 +82,7 @@ pub fn get_server_preset(provider: &ProviderType) -> Option<ServerConfig> {
             smtp_host: "smtp.gmail.com".to_string(),
             smtp_port: 465,
             use_tls: true,
+            cert_fingerprint: None,
         }),
         ProviderType::Outlook => Some(ServerConfig {
             imap_host: "outlook.office365.com".to_string(),
@@ -64,6 +90,7 @@ pub fn get_server_preset(provider: &ProviderType) -> Option<ServerConfig> {
             smtp_host: "smtp.office365.com".to_string(),
             smtp_port: 587,
             use_tls: true,
+            cert_fingerprint: None,
         }),
         ProviderType::Yahoo => Some(ServerConfig {
             imap_host: "imap.mail.yahoo.com".to_string(),
@@ -71,8 +98,19 @@ pub fn get_server_preset(provider: &ProviderType) -> Option<ServerConfig> {
             smtp_host: "smtp.mail.yahoo.com".to_string(),
             smtp_port: 465,
             use_tls: true,
+            cert_fingerprint: None,
+        }),
+        // Bridge's defaults: STARTTLS on both ports, with its own certificate to pin
+        ProviderType::ProtonBridge => Some(ServerConfig {
+            imap_host: "127.0.0.1".to_string(),
+            imap_port: 1143,
+            smtp_host: "127.0.0.1".to_string(),
+            smtp_port: 1025,
+            use_tls: true,
+            cert_fingerprint: None,
         }),
-        ProviderType::Custom => None,
+        // POP3 servers are per-provider; only the port has a default
+        ProviderType::Pop3 | ProviderType::Custom => None,
     }
 }
 
@@ -88,6 +126,7 @@ pub fn detect_provider(email: &str) -> ProviderType {
         "gmail.com" | "googlemail.com" => ProviderType::Gmail,
         "outlook.com" | "hotmail.com" | "live.com" | "msn.com" => ProviderType::Outlook,
         "yahoo.com" | "ymail.com" | "rocketmail.com" => ProviderType::Yahoo,
+        "proton.me" | "protonmail.com" | "protonmail.ch" | "pm.me" => ProviderType::ProtonBridge,
         _ => ProviderType::Custom,
     }
 }
@@ -96,7 +135,10 @@ pub fn detect_provider(email: &str) -> ProviderType {
 pub fn default_auth_type(provider: &ProviderType) -> AuthType {
     match provider {
         ProviderType::Gmail | ProviderType::Outlook => AuthType::OAuth2,
-        ProviderType::Yahoo | ProviderType::Custom => AuthType::Password,
+        ProviderType::Yahoo
+        | ProviderType::ProtonBridge
+        | ProviderType::Pop3
+        | ProviderType::Custom => AuthType::Password,
     }
 }
 
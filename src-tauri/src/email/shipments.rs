@@ -0,0 +1,429 @@
+//! Package tracking numbers in shipping confirmations
+//!
+//! An email counts as a shipping notice when its wording says so or when it links to a
+//! carrier's tracking pages. Tracking numbers are then picked out of the text and those
+//! links by the shape each carrier uses. Numbers made of digits alone (FedEx, DHL) look
+//! like order and invoice numbers, so they are only taken when the email names the
+//! carrier. The delivery status comes from the same wording, or from the carrier's
+//! tracking page when the user lets the app check it.
+
+use serde::{Deserialize, Serialize};
+
+use super::links::{strip_tags, EmailLink};
+
+/// Most tracking numbers kept per email
+const MAX_SHIPMENTS: usize = 10;
+
+/// Wording of shipping confirmations and delivery updates
+const SHIPPING_PHRASES: &[&str] = &[
+    "tracking number",
+    "tracking #",
+    "tracking no",
+    "track your",
+    "has shipped",
+    "have shipped",
+    "was shipped",
+    "been shipped",
+    "been dispatched",
+    "has dispatched",
+    "out for delivery",
+    "in transit",
+    "on its way",
+    "shipment",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Carrier {
+    Ups,
+    Fedex,
+    Usps,
+    Dhl,
+    Amazon,
+    RoyalMail,
+}
+
+impl Carrier {
+    const ALL: [Carrier; 6] = [
+        Carrier::Ups,
+        Carrier::Fedex,
+        Carrier::Usps,
+        Carrier::Dhl,
+        Carrier::Amazon,
+        Carrier::RoyalMail,
+    ];
+
+    /// Identifier stored in the database
+    pub fn id(self) -> &'static str {
+        match self {
+            Carrier::Ups => "ups",
+            Carrier::Fedex => "fedex",
+            Carrier::Usps => "usps",
+            Carrier::Dhl => "dhl",
+            Carrier::Amazon => "amazon",
+            Carrier::RoyalMail => "royal_mail",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|carrier| carrier.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Carrier::Ups => "UPS",
+            Carrier::Fedex => "FedEx",
+            Carrier::Usps => "USPS",
+            Carrier::Dhl => "DHL",
+            Carrier::Amazon => "Amazon",
+            Carrier::RoyalMail => "Royal Mail",
+        }
+    }
+
+    /// Page showing the status of a tracking number
+    pub fn tracking_url(self, tracking_number: &str) -> String {
+        match self {
+            Carrier::Ups => format!("https://www.ups.com/track?tracknum={}", tracking_number),
+            Carrier::Fedex => format!(
+                "https://www.fedex.com/fedextrack/?trknbr={}",
+                tracking_number
+            ),
+            Carrier::Usps => format!(
+                "https://tools.usps.com/go/TrackConfirmAction?tLabels={}",
+                tracking_number
+            ),
+            Carrier::Dhl => format!(
+                "https://www.dhl.com/global-en/home/tracking.html?tracking-id={}",
+                tracking_number
+            ),
+            Carrier::Amazon => format!("https://track.amazon.com/tracking/{}", tracking_number),
+            Carrier::RoyalMail => format!(
+                "https://www.royalmail.com/track-your-item#/tracking-results/{}",
+                tracking_number
+            ),
+        }
+    }
+
+    /// Whether a link host belongs to the carrier's tracking site
+    fn owns_domain(self, host: &str) -> bool {
+        let domains: &[&str] = match self {
+            Carrier::Ups => &["ups.com"],
+            Carrier::Fedex => &["fedex.com"],
+            Carrier::Usps => &["usps.com"],
+            Carrier::Dhl => &["dhl.com", "dhl.de"],
+            Carrier::Amazon => &["track.amazon.com"],
+            Carrier::RoyalMail => &["royalmail.com"],
+        };
+        domains
+            .iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+    }
+}
+
+/// Where a package is, from the first shipping notice to delivery
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShipmentStatus {
+    Shipped,
+    InTransit,
+    OutForDelivery,
+    Delivered,
+    /// Delayed, a failed delivery attempt, or sent back
+    Exception,
+}
+
+impl ShipmentStatus {
+    const ALL: [ShipmentStatus; 5] = [
+        ShipmentStatus::Shipped,
+        ShipmentStatus::InTransit,
+        ShipmentStatus::OutForDelivery,
+        ShipmentStatus::Delivered,
+        ShipmentStatus::Exception,
+    ];
+
+    /// Identifier stored in the database
+    pub fn id(self) -> &'static str {
+        match self {
+            ShipmentStatus::Shipped => "shipped",
+            ShipmentStatus::InTransit => "in_transit",
+            ShipmentStatus::OutForDelivery => "out_for_delivery",
+            ShipmentStatus::Delivered => "delivered",
+            ShipmentStatus::Exception => "exception",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|status| status.id() == id)
+    }
+}
+
+/// A tracking number found in an email
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedShipment {
+    pub carrier: Carrier,
+    pub tracking_number: String,
+    pub status: ShipmentStatus,
+}
+
+/// Tracking numbers in a shipping notice, or none when the email isn't one
+pub fn detect(
+    subject: &str,
+    body_plain: Option<&str>,
+    body_html: Option<&str>,
+    links: &[EmailLink],
+) -> Vec<DetectedShipment> {
+    let body = match (body_plain, body_html) {
+        (Some(plain), _) if !plain.trim().is_empty() => plain.to_string(),
+        (_, Some(html)) => strip_tags(html),
+        _ => String::new(),
+    };
+    let text = format!("{}\n{}", subject, body).to_lowercase();
+    let tracking_links: Vec<&EmailLink> = links
+        .iter()
+        .filter(|link| Carrier::ALL.iter().any(|c| c.owns_domain(&link.domain)))
+        .collect();
+    if tracking_links.is_empty() && !SHIPPING_PHRASES.iter().any(|p| text.contains(p)) {
+        return Vec::new();
+    }
+
+    let status = status_from_text(&text).unwrap_or(ShipmentStatus::Shipped);
+    let names_fedex = text.contains("fedex");
+    let names_dhl = text.contains("dhl");
+    let mut shipments: Vec<DetectedShipment> = Vec::new();
+    let candidates = tokens(&text).chain(tracking_links.iter().flat_map(|link| tokens(&link.url)));
+    for token in candidates {
+        let token = token.to_ascii_uppercase();
+        let Some(carrier) = carrier_of(&token, names_fedex, names_dhl) else {
+            continue;
+        };
+        if shipments.len() < MAX_SHIPMENTS && !shipments.iter().any(|s| s.tracking_number == token)
+        {
+            shipments.push(DetectedShipment {
+                carrier,
+                tracking_number: token,
+                status,
+            });
+        }
+    }
+    shipments
+}
+
+/// Runs of letters and digits long enough to be a tracking number
+fn tokens(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|token| token.len() >= 10 && token.len() <= 22)
+}
+
+/// Carrier whose tracking numbers look like `token` (upper-cased)
+fn carrier_of(token: &str, names_fedex: bool, names_dhl: bool) -> Option<Carrier> {
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    let letters = |s: &str| s.bytes().all(|b| b.is_ascii_uppercase());
+    // Two letters, nine digits and the issuing country: the UPU S10 format
+    let s10 = |country: &str| {
+        token.len() == 13
+            && letters(&token[..2])
+            && digits(&token[2..11])
+            && &token[11..] == country
+    };
+
+    if token.len() == 18 && token.starts_with("1Z") {
+        Some(Carrier::Ups)
+    } else if token.len() == 15 && token.starts_with("TBA") && digits(&token[3..]) {
+        Some(Carrier::Amazon)
+    } else if (20..=22).contains(&token.len()) && token.starts_with('9') && digits(token)
+        || s10("US")
+    {
+        Some(Carrier::Usps)
+    } else if s10("GB") {
+        Some(Carrier::RoyalMail)
+    } else if names_dhl && token.starts_with("JJD") && digits(&token[3..]) {
+        Some(Carrier::Dhl)
+    } else if names_fedex && matches!(token.len(), 12 | 15) && digits(token) {
+        Some(Carrier::Fedex)
+    } else if names_dhl && token.len() == 10 && digits(token) {
+        Some(Carrier::Dhl)
+    } else {
+        None
+    }
+}
+
+/// Delivery status described by lower-cased text, the furthest along when it mentions
+/// several
+pub fn status_from_text(text: &str) -> Option<ShipmentStatus> {
+    let delivered = text.match_indices("delivered").any(|(start, _)| {
+        let before = &text[..start];
+        // Not "undelivered", nor a promise that it will be delivered
+        !before.ends_with(|c: char| c.is_alphabetic())
+            && !before.ends_with("be ")
+            && !before.ends_with("not ")
+    });
+    let has = |phrases: &[&str]| phrases.iter().any(|p| text.contains(p));
+
+    if delivered {
+        Some(ShipmentStatus::Delivered)
+    } else if has(&[
+        "delivery exception",
+        "delivery attempt",
+        "could not be delivered",
+        "unable to deliver",
+        "returned to sender",
+        "delayed",
+    ]) {
+        Some(ShipmentStatus::Exception)
+    } else if text.contains("out for delivery") {
+        Some(ShipmentStatus::OutForDelivery)
+    } else if has(&[
+        "in transit",
+        "on its way",
+        "on the way",
+        "departed",
+        "arrived at",
+    ]) {
+        Some(ShipmentStatus::InTransit)
+    } else if has(&["shipped", "dispatched"]) {
+        Some(ShipmentStatus::Shipped)
+    } else {
+        None
+    }
+}
+
+/// Delivery status shown on a carrier's tracking page
+pub fn status_from_page(html: &str) -> Option<ShipmentStatus> {
+    let mut html = html.to_lowercase();
+    for tag in ["script", "style"] {
+        html = drop_elements(&html, tag);
+    }
+    let text = strip_tags(&html)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    status_from_text(&text)
+}
+
+/// Lower-cased `html` without the `<tag>` elements and their content
+fn drop_elements(html: &str, tag: &str) -> String {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut kept = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(&open) {
+        kept.push_str(&rest[..start]);
+        rest = match rest[start..].find(&close) {
+            Some(end) => &rest[start + end + close.len()..],
+            None => "",
+        };
+    }
+    kept.push_str(rest);
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, domain: &str) -> EmailLink {
+        EmailLink {
+            url: url.to_string(),
+            text: None,
+            domain: domain.to_string(),
+            is_tracker: false,
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        let plain = "Good news! Your order #112-4455667-1234567 has shipped.\n\
+                     UPS tracking number: 1Z999AA10123456784\n\
+                     USPS: 9400 1000 0000 0000 0000 00 or 9400100000000000000000";
+        let shipments = detect("Your order has shipped", Some(plain), None, &[]);
+        assert_eq!(
+            shipments,
+            vec![
+                DetectedShipment {
+                    carrier: Carrier::Ups,
+                    tracking_number: "1Z999AA10123456784".to_string(),
+                    status: ShipmentStatus::Shipped,
+                },
+                DetectedShipment {
+                    carrier: Carrier::Usps,
+                    tracking_number: "9400100000000000000000".to_string(),
+                    status: ShipmentStatus::Shipped,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detect_needs_shipping_notice() {
+        // A 1Z-shaped token in an unrelated email
+        assert!(detect("Invoice", Some("Ref 1Z999AA10123456784"), None, &[]).is_empty());
+
+        // A digits-only number needs the carrier named
+        let plain = "Your shipment is on its way. Tracking number: 123456789012";
+        assert!(detect("Shipped", Some(plain), None, &[]).is_empty());
+        let shipments = detect("Shipped via FedEx", Some(plain), None, &[]);
+        assert_eq!(shipments[0].carrier, Carrier::Fedex);
+        assert_eq!(shipments[0].status, ShipmentStatus::InTransit);
+    }
+
+    #[test]
+    fn test_detect_from_links() {
+        let html = r#"<p>Your parcel is <b>out for delivery</b> today.</p>
+            <a href="https://www.royalmail.com/track-your-item#/tracking-results/AB123456789GB">Track</a>"#;
+        let links = [link(
+            "https://www.royalmail.com/track-your-item#/tracking-results/AB123456789GB",
+            "www.royalmail.com",
+        )];
+        let shipments = detect("Delivery update", None, Some(html), &links);
+        assert_eq!(shipments.len(), 1);
+        assert_eq!(shipments[0].carrier, Carrier::RoyalMail);
+        assert_eq!(shipments[0].tracking_number, "AB123456789GB");
+        assert_eq!(shipments[0].status, ShipmentStatus::OutForDelivery);
+        assert_eq!(
+            Carrier::RoyalMail.tracking_url("AB123456789GB"),
+            links[0].url
+        );
+    }
+
+    #[test]
+    fn test_status_from_text() {
+        assert_eq!(
+            status_from_text("your package was delivered at 2:14 pm"),
+            Some(ShipmentStatus::Delivered)
+        );
+        assert_eq!(
+            status_from_text("shipped! it will be delivered on friday"),
+            Some(ShipmentStatus::Shipped)
+        );
+        assert_eq!(
+            status_from_text("your package could not be delivered"),
+            Some(ShipmentStatus::Exception)
+        );
+        assert_eq!(
+            status_from_text("out for delivery, expected to be delivered today"),
+            Some(ShipmentStatus::OutForDelivery)
+        );
+        assert_eq!(status_from_text("undelivered mail"), None);
+    }
+
+    #[test]
+    fn test_status_from_page() {
+        let html = r#"<html><script>var s = "Delivered";</script>
+            <div class="status">Out for Delivery</div></html>"#;
+        assert_eq!(status_from_page(html), Some(ShipmentStatus::OutForDelivery));
+    }
+
+    #[test]
+    fn test_ids() {
+        for carrier in Carrier::ALL {
+            assert_eq!(Carrier::from_id(carrier.id()), Some(carrier));
+        }
+        for status in ShipmentStatus::ALL {
+            assert_eq!(ShipmentStatus::from_id(status.id()), Some(status));
+        }
+        assert_eq!(
+            serde_json::to_string(&ShipmentStatus::OutForDelivery).unwrap(),
+            "\"out_for_delivery\""
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! S/MIME signature verification
+//!
+//! Verifies `multipart/signed` messages with an `application/pkcs7-signature` part
+//! (and opaque `application/pkcs7-mime; smime-type=signed-data`) and reports who
+//! signed the message and whether their certificate chains to a trusted root.
+
+use anyhow::Result;
+use mail_parser::MimeHeaders;
+use openssl::nid::Nid;
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509NameRef, X509Ref, X509};
+use serde::{Deserialize, Serialize};
+
+use super::crypto::{canonicalize, child_ids, protocol_is};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SmimeStatus {
+    /// The signature matches the signed content
+    pub signature_valid: bool,
+    /// The signer certificate chains to a root in the system trust store
+    pub certificate_trusted: bool,
+    pub signer_name: Option<String>,
+    pub signer_email: Option<String>,
+    pub issuer: Option<String>,
+    /// Certificate expiry as reported by OpenSSL (e.g. "Jan  1 00:00:00 2030 GMT")
+    pub valid_until: Option<String>,
+    pub error: Option<String>,
+}
+
+fn name_entry(name: &X509NameRef, nid: Nid) -> Option<String> {
+    name.entries_by_nid(nid)
+        .next()
+        .and_then(|entry| entry.data().to_string().ok())
+}
+
+fn signer_details(status: &mut SmimeStatus, cert: &X509Ref) {
+    status.signer_name = name_entry(cert.subject_name(), Nid::COMMONNAME);
+    status.signer_email = cert
+        .subject_alt_names()
+        .and_then(|names| names.iter().find_map(|n| n.email().map(|e| e.to_string())))
+        .or_else(|| name_entry(cert.subject_name(), Nid::PKCS9_EMAILADDRESS));
+    status.issuer = name_entry(cert.issuer_name(), Nid::COMMONNAME)
+        .or_else(|| name_entry(cert.issuer_name(), Nid::ORGANIZATIONNAME));
+    status.valid_until = Some(cert.not_after().to_string());
+}
+
+/// Verify a PKCS#7 signature. `content` is the canonicalized signed entity for detached
+/// signatures, or None for opaque signed-data (content embedded in the signature).
+fn verify_pkcs7(signature: &[u8], content: Option<&[u8]>) -> Result<SmimeStatus> {
+    let pkcs7 = Pkcs7::from_der(signature).or_else(|_| Pkcs7::from_pem(signature))?;
+    let no_certs = Stack::<X509>::new()?;
+    let mut status = SmimeStatus::default();
+
+    // Signature integrity only (ignore the chain)
+    let empty_store = X509StoreBuilder::new()?.build();
+    match pkcs7.verify(
+        &no_certs,
+        &empty_store,
+        content,
+        None,
+        Pkcs7Flags::NOVERIFY | Pkcs7Flags::BINARY,
+    ) {
+        Ok(()) => status.signature_valid = true,
+        Err(e) => status.error = Some(e.to_string()),
+    }
+
+    // Chain to the system trust store
+    if status.signature_valid {
+        let mut trusted = X509StoreBuilder::new()?;
+        trusted.set_default_paths()?;
+        let trusted = trusted.build();
+        match pkcs7.verify(&no_certs, &trusted, content, None, Pkcs7Flags::BINARY) {
+            Ok(()) => status.certificate_trusted = true,
+            Err(e) => status.error = Some(format!("Untrusted certificate: {}", e)),
+        }
+    }
+
+    if let Some(cert) = pkcs7.signers(&no_certs, Pkcs7Flags::empty())?.iter().next() {
+        signer_details(&mut status, cert);
+    }
+
+    Ok(status)
+}
+
+fn is_pkcs7_signed_data(part: &mail_parser::MessagePart<'_>) -> bool {
+    part.content_type()
+        .map(|ct| {
+            let subtype = ct.subtype().unwrap_or("").to_lowercase();
+            ct.ctype().eq_ignore_ascii_case("application")
+                && (subtype == "pkcs7-mime" || subtype == "x-pkcs7-mime")
+                && ct
+                    .attribute("smime-type")
+                    .map(|t| t.eq_ignore_ascii_case("signed-data"))
+                    .unwrap_or(false)
+        })
+        .unwrap_or(false)
+}
+
+/// Verify an S/MIME signed message. Returns None for messages without an S/MIME signature.
+pub fn verify_message(parsed: &mail_parser::Message<'_>) -> Option<SmimeStatus> {
+    let root = parsed.parts.first()?;
+
+    let result = if protocol_is(root, "signed", "application/pkcs7-signature")
+        || protocol_is(root, "signed", "application/x-pkcs7-signature")
+    {
+        let ids = child_ids(root);
+        let signed_part = ids.first().and_then(|id| parsed.parts.get(*id))?;
+        let signature = ids.get(1).and_then(|id| parsed.parts.get(*id))?;
+        let signed_data = parsed
+            .raw_message()
+            .get(signed_part.offset_header..signed_part.offset_end)?;
+
+        verify_pkcs7(signature.contents(), Some(&canonicalize(signed_data)))
+    } else if is_pkcs7_signed_data(root) {
+        verify_pkcs7(root.contents(), None)
+    } else {
+        return None;
+    };
+
+    Some(result.unwrap_or_else(|e| SmimeStatus {
+        error: Some(e.to_string()),
+        ..Default::default()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::X509NameBuilder;
+
+    fn self_signed() -> (X509, PKey<openssl::pkey::Private>) {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "Alice Example").unwrap();
+        name.append_entry_by_nid(Nid::PKCS9_EMAILADDRESS, "alice@example.com").unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    #[test]
+    fn test_detached_signature() {
+        let (cert, key) = self_signed();
+        let content = canonicalize(b"Content-Type: text/plain\n\nhello\n");
+        let certs = Stack::new().unwrap();
+        let signature = Pkcs7::sign(
+            &cert,
+            &key,
+            &certs,
+            &content,
+            Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY,
+        )
+        .unwrap()
+        .to_der()
+        .unwrap();
+
+        let good = verify_pkcs7(&signature, Some(&content)).unwrap();
+        assert!(good.signature_valid);
+        assert!(!good.certificate_trusted); // self-signed
+        assert_eq!(good.signer_name.as_deref(), Some("Alice Example"));
+        assert_eq!(good.signer_email.as_deref(), Some("alice@example.com"));
+
+        let bad = verify_pkcs7(&signature, Some(b"tampered")).unwrap();
+        assert!(!bad.signature_valid);
+    }
+}
@@ -0,0 +1,335 @@
+//! Which folder is the Sent, Trash, Drafts… folder of an account
+//!
+//! Servers that support SPECIAL-USE (RFC 6154), or Gmail's older XLIST, mark these
+//! folders with LIST attributes such as `\Sent` and `\Junk`, which is what counts first.
+//! Without them the folder's name is looked up in a table of the names common mail
+//! servers and clients use in several languages, then checked for English words as
+//! before. Users can override the result per folder; overrides are stored by account.
+
+use std::collections::HashMap;
+
+use super::types::{Folder, SpecialFolder};
+
+/// Localized folder names, lowercase, by the special folder they name
+const LOCALIZED_NAMES: &[(SpecialFolder, &[&str])] = &[
+    (
+        SpecialFolder::Sent,
+        &[
+            "sent",
+            "sent mail",
+            "sent items",
+            "sent messages",
+            "gesendet",
+            "gesendete objekte",
+            "gesendete elemente",
+            "envoyés",
+            "éléments envoyés",
+            "messages envoyés",
+            "enviados",
+            "elementos enviados",
+            "itens enviados",
+            "posta inviata",
+            "inviati",
+            "verzonden",
+            "verzonden items",
+            "skickat",
+            "skickade objekt",
+            "sendt",
+            "sendte elementer",
+            "wysłane",
+            "отправленные",
+            "送信済み",
+            "已发送",
+        ],
+    ),
+    (
+        SpecialFolder::Trash,
+        &[
+            "trash",
+            "bin",
+            "deleted",
+            "deleted items",
+            "deleted messages",
+            "papierkorb",
+            "gelöschte elemente",
+            "gelöschte objekte",
+            "corbeille",
+            "éléments supprimés",
+            "papelera",
+            "elementos eliminados",
+            "lixeira",
+            "itens excluídos",
+            "cestino",
+            "posta eliminata",
+            "prullenbak",
+            "verwijderde items",
+            "papperskorgen",
+            "borttaget",
+            "slettet",
+            "kosz",
+            "корзина",
+            "ゴミ箱",
+            "已删除",
+        ],
+    ),
+    (
+        SpecialFolder::Drafts,
+        &[
+            "drafts",
+            "draft",
+            "entwürfe",
+            "brouillons",
+            "borradores",
+            "rascunhos",
+            "bozze",
+            "concepten",
+            "utkast",
+            "kladder",
+            "wersje robocze",
+            "черновики",
+            "下書き",
+            "草稿",
+        ],
+    ),
+    (
+        SpecialFolder::Spam,
+        &[
+            "spam",
+            "junk",
+            "junk e-mail",
+            "junk email",
+            "bulk mail",
+            "junk-e-mail",
+            "courrier indésirable",
+            "correo no deseado",
+            "lixo eletrônico",
+            "posta indesiderata",
+            "ongewenste e-mail",
+            "skräppost",
+            "søppelpost",
+            "спам",
+            "迷惑メール",
+            "垃圾邮件",
+        ],
+    ),
+    (
+        SpecialFolder::Archive,
+        &[
+            "archive",
+            "archives",
+            "all mail",
+            "archiv",
+            "alle nachrichten",
+            "tous les messages",
+            "archivo",
+            "arquivo",
+            "archivio",
+            "archief",
+            "arkiv",
+            "archiwum",
+            "архив",
+            "アーカイブ",
+            "归档",
+        ],
+    ),
+    (
+        SpecialFolder::Starred,
+        &[
+            "starred",
+            "flagged",
+            "markiert",
+            "suivis",
+            "destacados",
+            "speciali",
+        ],
+    ),
+];
+
+/// The special folder a folder is, from its LIST attributes (as sent, e.g. `\Sent`)
+/// and its decoded path
+pub fn detect(path: &str, delimiter: Option<&str>, attributes: &[String]) -> Option<SpecialFolder> {
+    if path.eq_ignore_ascii_case("INBOX") {
+        return Some(SpecialFolder::Inbox);
+    }
+    attributes
+        .iter()
+        .find_map(|attribute| from_attribute(attribute))
+        .or_else(|| from_name(last_part(path, delimiter)))
+}
+
+/// SPECIAL-USE attributes, and the XLIST ones Gmail still sends
+pub fn from_attribute(attribute: &str) -> Option<SpecialFolder> {
+    let name = attribute.trim_start_matches('\\').to_lowercase();
+    match name.as_str() {
+        "inbox" => Some(SpecialFolder::Inbox),
+        "sent" => Some(SpecialFolder::Sent),
+        "trash" => Some(SpecialFolder::Trash),
+        "drafts" => Some(SpecialFolder::Drafts),
+        "junk" | "spam" => Some(SpecialFolder::Spam),
+        "archive" | "all" | "allmail" => Some(SpecialFolder::Archive),
+        "flagged" | "starred" => Some(SpecialFolder::Starred),
+        _ => None,
+    }
+}
+
+/// The special folder a folder name (without its parents) stands for
+fn from_name(name: &str) -> Option<SpecialFolder> {
+    let lower = name.trim().to_lowercase();
+    let localized = LOCALIZED_NAMES
+        .iter()
+        .find(|(_, names)| names.contains(&lower.as_str()))
+        .map(|(special, _)| special.clone());
+    localized.or_else(|| {
+        if lower.contains("sent") {
+            Some(SpecialFolder::Sent)
+        } else if lower.contains("trash") || lower.contains("deleted") {
+            Some(SpecialFolder::Trash)
+        } else if lower.contains("draft") {
+            Some(SpecialFolder::Drafts)
+        } else if lower.contains("spam") || lower.contains("junk") {
+            Some(SpecialFolder::Spam)
+        } else if lower.contains("archive") {
+            Some(SpecialFolder::Archive)
+        } else {
+            None
+        }
+    })
+}
+
+fn last_part<'a>(path: &'a str, delimiter: Option<&str>) -> &'a str {
+    match delimiter.filter(|delimiter| !delimiter.is_empty()) {
+        Some(delimiter) => path.rsplit(delimiter).next().unwrap_or(path),
+        None => path,
+    }
+}
+
+/// Apply an account's overrides, keyed by folder name, to its folders. A folder given a
+/// role takes it over from whichever folder was detected with it; `None` marks a folder
+/// as not special.
+pub fn apply_overrides(folders: &mut [Folder], overrides: &HashMap<String, Option<SpecialFolder>>) {
+    for (name, role) in overrides {
+        if !folders.iter().any(|folder| &folder.name == name) {
+            continue;
+        }
+        for folder in folders.iter_mut() {
+            if &folder.name == name {
+                folder.special = role.clone();
+            } else if role.is_some()
+                && folder.special == *role
+                && !overrides.contains_key(&folder.name)
+            {
+                folder.special = None;
+            }
+        }
+    }
+}
+
+/// Stored form of a role
+pub fn role_key(role: &SpecialFolder) -> &'static str {
+    match role {
+        SpecialFolder::Inbox => "inbox",
+        SpecialFolder::Sent => "sent",
+        SpecialFolder::Trash => "trash",
+        SpecialFolder::Drafts => "drafts",
+        SpecialFolder::Spam => "spam",
+        SpecialFolder::Archive => "archive",
+        SpecialFolder::Starred => "starred",
+    }
+}
+
+pub fn parse_role_key(key: &str) -> Option<SpecialFolder> {
+    from_attribute(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(name: &str, special: Option<SpecialFolder>) -> Folder {
+        Folder {
+            name: name.to_string(),
+            path: name.to_string(),
+            display_name: name.to_string(),
+            special,
+            delimiter: Some("/".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_attributes_come_first() {
+        let attributes = vec!["\\HasNoChildren".to_string(), "\\Trash".to_string()];
+        assert_eq!(
+            detect("Sent Stuff", Some("/"), &attributes),
+            Some(SpecialFolder::Trash)
+        );
+        assert_eq!(
+            detect(
+                "[Gmail]/Alle Nachrichten",
+                Some("/"),
+                &["\\All".to_string()]
+            ),
+            Some(SpecialFolder::Archive)
+        );
+        assert_eq!(detect("inbox", None, &[]), Some(SpecialFolder::Inbox));
+    }
+
+    #[test]
+    fn test_localized_names() {
+        assert_eq!(
+            detect("Papierkorb", Some("/"), &[]),
+            Some(SpecialFolder::Trash)
+        );
+        assert_eq!(
+            detect("INBOX.Envoyés", Some("."), &[]),
+            Some(SpecialFolder::Sent)
+        );
+        assert_eq!(detect("Entwürfe", None, &[]), Some(SpecialFolder::Drafts));
+        assert_eq!(
+            detect("Correo no deseado", None, &[]),
+            Some(SpecialFolder::Spam)
+        );
+        assert_eq!(
+            detect("[Gmail]/Sent Mail", Some("/"), &[]),
+            Some(SpecialFolder::Sent)
+        );
+        assert_eq!(detect("Sent/Project X", Some("/"), &[]), None);
+        assert_eq!(detect("Receipts", Some("/"), &[]), None);
+    }
+
+    #[test]
+    fn test_overrides() {
+        let mut folders = vec![
+            folder("INBOX", Some(SpecialFolder::Inbox)),
+            folder("Trash", Some(SpecialFolder::Trash)),
+            folder("Bin", None),
+            folder("Sent", Some(SpecialFolder::Sent)),
+        ];
+        let overrides = HashMap::from([
+            ("Bin".to_string(), Some(SpecialFolder::Trash)),
+            ("Sent".to_string(), None),
+            ("Gone".to_string(), Some(SpecialFolder::Drafts)),
+        ]);
+        apply_overrides(&mut folders, &overrides);
+        let specials: Vec<_> = folders.iter().map(|f| f.special.clone()).collect();
+        assert_eq!(
+            specials,
+            vec![
+                Some(SpecialFolder::Inbox),
+                None,
+                Some(SpecialFolder::Trash),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_role_keys() {
+        for role in [
+            SpecialFolder::Sent,
+            SpecialFolder::Spam,
+            SpecialFolder::Starred,
+        ] {
+            assert_eq!(parse_role_key(role_key(&role)), Some(role));
+        }
+    }
+}
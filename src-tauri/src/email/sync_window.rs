@@ -0,0 +1,151 @@
+//! Which folders of an account are synced, and how far back
+//!
+//! Each account has its own settings. The IDLE monitors watch only the chosen folders,
+//! and fetching caches only the newest messages inside the window. Folders and mail
+//! outside it can still be listed from the server when opened; they just aren't stored.
+//!
+//! An account can also have its folders checked every few seconds instead of holding
+//! IDLE connections open, for servers like Gmail that drop idling connections often.
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Folders watched for new mail unless the account picks its own
+pub const DEFAULT_SYNC_FOLDERS: &[&str] = &["INBOX", "Sent", "Drafts", "Trash", "Spam"];
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Shortest interval between fast-polling checks, to stay clear of server rate limits
+pub const MIN_FAST_POLL_SECS: u32 = 5;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountSyncSettings {
+    /// Folders kept in sync; `DEFAULT_SYNC_FOLDERS` are watched and any opened folder is
+    /// cached when unset
+    #[serde(default)]
+    pub folders: Option<Vec<String>>,
+    /// Only mail from the last this many days is synced
+    #[serde(default)]
+    pub days_back: Option<u32>,
+    /// At most this many of the newest messages of each folder are synced
+    #[serde(default)]
+    pub max_messages: Option<u32>,
+    /// Check the watched folders for new mail every this many seconds instead of
+    /// waiting in IDLE
+    #[serde(default)]
+    pub fast_poll_secs: Option<u32>,
+}
+
+/// How the monitor of a folder notices new mail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// Wait in IMAP IDLE for the server to report changes
+    Idle,
+    /// Ask the server for the folder's status at this interval
+    Poll(Duration),
+}
+
+impl AccountSyncSettings {
+    /// Whether mail in `folder` is cached
+    pub fn syncs_folder(&self, folder: &str) -> bool {
+        self.folders.as_ref().is_none_or(|folders| {
+            folders
+                .iter()
+                .any(|synced| synced.eq_ignore_ascii_case(folder))
+        })
+    }
+
+    /// Folders the IDLE monitors watch
+    pub fn watched_folders(&self) -> Vec<String> {
+        match &self.folders {
+            Some(folders) => folders.clone(),
+            None => DEFAULT_SYNC_FOLDERS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    pub fn watch_mode(&self) -> WatchMode {
+        match self.fast_poll_secs {
+            Some(secs) => WatchMode::Poll(Duration::from_secs(secs.max(MIN_FAST_POLL_SECS) as u64)),
+            None => WatchMode::Idle,
+        }
+    }
+
+    /// How many messages to fetch when `requested` are asked for
+    pub fn limit(&self, requested: u32) -> u32 {
+        self.max_messages
+            .map_or(requested, |max| requested.min(max))
+    }
+
+    /// Whether a message dated `date` (RFC 3339) falls inside the window at Unix time
+    /// `now`. Messages without a readable date are kept.
+    pub fn in_window(&self, date: &str, now: i64) -> bool {
+        let Some(days) = self.days_back else {
+            return true;
+        };
+        match DateTime::parse_from_rfc3339(date) {
+            Ok(date) => date.timestamp() >= now - days as i64 * SECONDS_PER_DAY,
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folders() {
+        let all = AccountSyncSettings::default();
+        assert!(all.syncs_folder("Projects/2026"));
+        assert_eq!(all.watched_folders().len(), DEFAULT_SYNC_FOLDERS.len());
+
+        let inbox_only = AccountSyncSettings {
+            folders: Some(vec!["INBOX".to_string()]),
+            ..Default::default()
+        };
+        assert!(inbox_only.syncs_folder("inbox"));
+        assert!(!inbox_only.syncs_folder("Trash"));
+        assert_eq!(inbox_only.watched_folders(), vec!["INBOX"]);
+    }
+
+    #[test]
+    fn test_watch_mode() {
+        assert_eq!(AccountSyncSettings::default().watch_mode(), WatchMode::Idle);
+
+        let fast = AccountSyncSettings {
+            fast_poll_secs: Some(15),
+            ..Default::default()
+        };
+        assert_eq!(fast.watch_mode(), WatchMode::Poll(Duration::from_secs(15)));
+
+        let too_fast = AccountSyncSettings {
+            fast_poll_secs: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            too_fast.watch_mode(),
+            WatchMode::Poll(Duration::from_secs(MIN_FAST_POLL_SECS as u64))
+        );
+    }
+
+    #[test]
+    fn test_window() {
+        let now = DateTime::parse_from_rfc3339("2026-03-10T12:00:00+00:00")
+            .unwrap()
+            .timestamp();
+        let month = AccountSyncSettings {
+            days_back: Some(30),
+            max_messages: Some(200),
+            ..Default::default()
+        };
+        assert!(month.in_window("2026-03-01T08:30:00+01:00", now));
+        assert!(!month.in_window("2026-01-15T08:30:00+00:00", now));
+        assert!(month.in_window("", now));
+        assert!(AccountSyncSettings::default().in_window("1999-01-01T00:00:00Z", now));
+
+        assert_eq!(month.limit(50), 50);
+        assert_eq!(month.limit(500), 200);
+        assert_eq!(AccountSyncSettings::default().limit(500), 500);
+    }
+}
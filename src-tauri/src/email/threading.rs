@@ -0,0 +1,151 @@
+//! Grouping messages into conversations
+//!
+//! Threading follows JWZ's algorithm, kept up to date as messages arrive instead of
+//! rebuilt from scratch. Every Message-ID a message mentions (its own, the whole
+//! References chain and In-Reply-To) is linked to the message's thread, which makes the
+//! stored links a union-find forest: a message that mentions IDs of two threads joins
+//! them, and the thread nearest the root of its chain absorbs the others. Referenced
+//! messages that aren't cached yet still carry their thread, so replies synced before
+//! the message they answer end up together.
+//!
+//! A reply that mentions no known ID, as sent by clients that drop References and
+//! In-Reply-To, joins a recent thread with the same subject once reply prefixes and
+//! mailing-list tags are stripped.
+
+/// How far apart in time a reply without references and the thread it is matched to
+/// by subject can be
+pub const SUBJECT_MATCH_WINDOW_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Prefixes mail clients put before the subject of replies and forwards, lowercase
+const REPLY_PREFIXES: &[&str] = &[
+    "re", "fw", "fwd", "aw", "wg", "sv", "vs", "antw", "tr", "rif", "res", "odp", "enc",
+];
+
+/// The Message-IDs a message answers, root first: its References chain, then its
+/// In-Reply-To when the chain doesn't end there. Angle brackets are removed, and IDs
+/// are listed once.
+pub fn reference_chain(references: &[&str], in_reply_to: &[&str]) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    for id in references.iter().chain(in_reply_to) {
+        let id = id.trim().trim_matches(|c| c == '<' || c == '>').trim();
+        if !id.is_empty() && !chain.iter().any(|known| known == id) {
+            chain.push(id.to_string());
+        }
+    }
+    chain
+}
+
+/// Strip reply and forward prefixes (`Re:`, `AW:`, `Fwd[2]:`) and mailing-list tags
+/// (`[team]`) from the start of a subject. Returns the rest and whether a reply or
+/// forward prefix was found.
+fn strip_prefixes(subject: &str) -> (&str, bool) {
+    let mut rest = subject.trim();
+    let mut replied = false;
+    loop {
+        if let Some(tagged) = rest.strip_prefix('[') {
+            match tagged.find(']') {
+                Some(end) => {
+                    rest = tagged[end + 1..].trim_start();
+                    continue;
+                }
+                None => break,
+            }
+        }
+        let Some(colon) = rest.find(':') else {
+            break;
+        };
+        // A counter some clients add, as in `Re[2]:` or `Re(2):`
+        let prefix = rest[..colon]
+            .trim_end_matches(|c: char| c.is_ascii_digit() || matches!(c, '[' | ']' | '(' | ')'))
+            .trim();
+        if !REPLY_PREFIXES
+            .iter()
+            .any(|known| prefix.eq_ignore_ascii_case(known))
+        {
+            break;
+        }
+        rest = rest[colon + 1..].trim_start();
+        replied = true;
+    }
+    (rest, replied)
+}
+
+/// The subject threads are matched on: without reply prefixes or list tags, in
+/// lowercase with single spaces
+pub fn subject_key(subject: &str) -> String {
+    let (rest, _) = strip_prefixes(subject);
+    rest.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Whether a subject has a reply or forward prefix
+pub fn is_reply_subject(subject: &str) -> bool {
+    strip_prefixes(subject).1
+}
+
+/// The address in a recipient as listed on an email (`Name <address>` or just the
+/// address), lowercase
+pub fn address_of(recipient: &str) -> String {
+    let address = match (recipient.rfind('<'), recipient.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &recipient[start + 1..end],
+        _ => recipient,
+    };
+    address.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_chain() {
+        assert_eq!(
+            reference_chain(&["<a@x>", "b@x", "<c@x>"], &["<c@x>"]),
+            vec!["a@x", "b@x", "c@x"]
+        );
+        // In-Reply-To missing from a truncated chain comes last
+        assert_eq!(reference_chain(&["a@x"], &["d@x"]), vec!["a@x", "d@x"]);
+        assert_eq!(reference_chain(&[], &["<d@x>"]), vec!["d@x"]);
+        assert!(reference_chain(&[" <> "], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_subject_key() {
+        assert_eq!(subject_key("Quarterly  report"), "quarterly report");
+        assert_eq!(subject_key("RE: Fwd: Quarterly report"), "quarterly report");
+        assert_eq!(
+            subject_key("[team] Re: quarterly report"),
+            "quarterly report"
+        );
+        assert_eq!(
+            subject_key("AW: [team] Quarterly report"),
+            "quarterly report"
+        );
+        assert_eq!(subject_key("Re[2]: Quarterly report"), "quarterly report");
+        // A colon that isn't a prefix stays
+        assert_eq!(subject_key("Re: Agenda: Monday"), "agenda: monday");
+        assert_eq!(subject_key("Reminder: Monday"), "reminder: monday");
+    }
+
+    #[test]
+    fn test_address_of() {
+        assert_eq!(address_of("Sam Lee <Sam@Example.com>"), "sam@example.com");
+        assert_eq!(address_of(" sam@example.com "), "sam@example.com");
+        assert_eq!(
+            address_of("\"Lee, Sam\" <sam@example.com>"),
+            "sam@example.com"
+        );
+    }
+
+    #[test]
+    fn test_is_reply_subject() {
+        assert!(is_reply_subject("Re: Quarterly report"));
+        assert!(is_reply_subject("[team] Fwd: Quarterly report"));
+        assert!(is_reply_subject("sv: Kvartalsrapport"));
+        assert!(!is_reply_subject("Quarterly report"));
+        assert!(!is_reply_subject("[team] Quarterly report"));
+        assert!(!is_reply_subject("Reminder: Monday"));
+    }
+}
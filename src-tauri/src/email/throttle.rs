@@ -0,0 +1,221 @@
+//! Pacing and retrying of server commands
+//!
+//! Gmail answers clients that send commands too quickly with `[THROTTLED]` or
+//! `[UNAVAILABLE]` and drops the connections of ones over their bandwidth limit; other
+//! servers occasionally reset a connection mid-sync. A `RateLimiter` spaces out the
+//! commands sent for one account, and `retry` runs an operation again after such
+//! transient failures, waiting twice as long each time.
+
+use anyhow::Result;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Markers of server responses that ask the client to slow down or come back later
+const THROTTLE_MARKERS: &[&str] = &[
+    "[throttled]",
+    "[unavailable]",
+    "[limit]",
+    "too many simultaneous connections",
+    "bandwidth limits",
+    "try again later",
+];
+
+/// Markers of a connection that broke and has to be opened again
+const CONNECTION_MARKERS: &[&str] = &[
+    "connection reset",
+    "connection aborted",
+    "connection closed",
+    "broken pipe",
+    "timed out",
+    "unexpected eof",
+    "no imap session",
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Tries in total, the first one included
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// For IMAP commands: up to about a minute of waiting in all
+    pub const IMAP: RetryPolicy = RetryPolicy {
+        max_attempts: 5,
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(30),
+    };
+
+    /// Wait after the given failed attempt (1-based): the base delay doubled per attempt,
+    /// capped, minus up to a quarter so clients that failed together don't retry together
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay);
+        let jitter = rand::thread_rng().gen_range(0.0..0.25);
+        exponential.mul_f64(1.0 - jitter)
+    }
+}
+
+/// Whether an error is worth retrying: the server throttled us or the connection broke
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    is_throttled(error) || is_connection_error(error)
+}
+
+pub fn is_throttled(error: &anyhow::Error) -> bool {
+    matches_any(error, THROTTLE_MARKERS)
+}
+
+/// Whether the connection the error happened on can't be used any more
+pub fn is_connection_error(error: &anyhow::Error) -> bool {
+    matches_any(error, CONNECTION_MARKERS)
+}
+
+fn matches_any(error: &anyhow::Error, markers: &[&str]) -> bool {
+    error.chain().any(|cause| {
+        let message = cause.to_string().to_lowercase();
+        markers.iter().any(|marker| message.contains(marker))
+    })
+}
+
+/// Run `operation` until it succeeds, fails for good or runs out of attempts
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && is_transient(&e) => {
+                let delay = policy.delay(attempt);
+                warn!(
+                    "{} failed (attempt {}), retrying in {:?}: {:#}",
+                    what, attempt, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Lets commands through no faster than a set rate. Shared by everything that talks to
+/// one account's server.
+pub struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for this command's turn
+    pub async fn acquire(&self) {
+        let at = {
+            let mut next = self.next.lock().await;
+            let at = (*next).max(Instant::now());
+            *next = at + self.interval;
+            at
+        };
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const FAST: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(4),
+    };
+
+    #[test]
+    fn test_delay_doubles_and_is_capped() {
+        let policy = RetryPolicy::IMAP;
+        for (attempt, full) in [(1, 2), (2, 4), (3, 8), (4, 16), (5, 30), (40, 30)] {
+            let delay = policy.delay(attempt);
+            let full = Duration::from_secs(full);
+            assert!(delay <= full && delay >= full.mul_f64(0.75), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient(&anyhow!(
+            "a1 NO [THROTTLED] Request throttled. Try again later."
+        )));
+        assert!(is_transient(
+            &anyhow!("Connection reset by peer").context("Failed to fetch messages")
+        ));
+        assert!(is_connection_error(&anyhow!("Broken pipe (os error 32)")));
+        assert!(!is_connection_error(&anyhow!("[THROTTLED]")));
+        assert!(!is_transient(&anyhow!(
+            "a1 NO [AUTHENTICATIONFAILED] Invalid credentials"
+        )));
+        assert!(!is_transient(&anyhow!("Message not found")));
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let calls = AtomicU32::new(0);
+        let result = retry(&FAST, "fetch", || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(anyhow!("[UNAVAILABLE] Server busy"))
+            } else {
+                Ok(7)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 7);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = retry(&FAST, "fetch", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("[THROTTLED]"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), FAST.max_attempts);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<()> = retry(&FAST, "fetch", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("Invalid credentials"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_commands() {
+        let limiter = RateLimiter::new(100);
+        let start = Instant::now();
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,170 @@
+//! TLS connections with optional certificate pinning
+//!
+//! Local bridges such as ProtonMail Bridge serve a self-signed certificate that the system
+//! trust store rejects. An account can pin that certificate's SHA-256 fingerprint instead:
+//! chain and hostname checks are then skipped, and the connection is only used when the
+//! server presents exactly the pinned certificate.
+
+use anyhow::{bail, Context, Result};
+use async_native_tls::TlsConnector;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+
+/// TLS stream over a tokio TCP connection
+pub type TlsStream = async_native_tls::TlsStream<Compat<TcpStream>>;
+
+/// Plain-text dialog that upgrades a connection before the handshake
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Starttls {
+    Imap,
+    Smtp,
+}
+
+/// SHA-256 fingerprint of a DER certificate, as upper-case hex pairs separated by colons
+/// (the format `openssl x509 -fingerprint -sha256` prints)
+pub fn fingerprint_of(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Canonical form of a fingerprint typed or pasted by the user
+pub fn normalize_fingerprint(input: &str) -> Result<String, String> {
+    let hex: String = input
+        .trim()
+        .trim_start_matches("SHA256:")
+        .trim_start_matches("sha256:")
+        .chars()
+        .filter(|c| !matches!(c, ':' | ' ' | '-'))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("Certificate fingerprint must be a SHA-256 hash (64 hex digits)".to_string());
+    }
+    let pairs: Vec<&str> = (0..hex.len()).step_by(2).map(|i| &hex[i..i + 2]).collect();
+    Ok(pairs.join(":"))
+}
+
+/// Run the STARTTLS dialog of `protocol` on a fresh connection
+pub async fn starttls(tcp: TcpStream, protocol: Starttls) -> Result<TcpStream> {
+    let mut stream = BufReader::new(tcp);
+    match protocol {
+        Starttls::Imap => {
+            read_line(&mut stream).await?; // greeting
+            stream.write_all(b"a0 STARTTLS\r\n").await?;
+            loop {
+                let line = read_line(&mut stream).await?;
+                if let Some(status) = line.strip_prefix("a0 ") {
+                    if !status.starts_with("OK") {
+                        bail!("STARTTLS refused: {}", status.trim());
+                    }
+                    break;
+                }
+            }
+        }
+        Starttls::Smtp => {
+            read_smtp_reply(&mut stream, "220").await?;
+            stream.write_all(b"EHLO localhost\r\n").await?;
+            read_smtp_reply(&mut stream, "250").await?;
+            stream.write_all(b"STARTTLS\r\n").await?;
+            read_smtp_reply(&mut stream, "220").await?;
+        }
+    }
+    // The server waits for the handshake, so nothing is left in the buffer
+    Ok(stream.into_inner())
+}
+
+/// TLS handshake over `tcp`. Without a pinned fingerprint the certificate is verified
+/// against the system roots as usual; with one, it must match the pin exactly.
+pub async fn handshake(tcp: TcpStream, host: &str, pinned: Option<&str>) -> Result<TlsStream> {
+    let stream = connector(pinned.is_some())
+        .connect(host, tcp.compat())
+        .await
+        .context("TLS handshake failed")?;
+
+    if let Some(expected) = pinned {
+        let actual = fingerprint_of(&peer_certificate(&stream)?);
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!(
+                "Server certificate ({}) doesn't match the pinned fingerprint",
+                actual
+            );
+        }
+    }
+    Ok(stream)
+}
+
+/// Certificate a server presents, whether or not it's trusted, so the user can decide
+/// to pin it
+pub async fn fetch_certificate(tcp: TcpStream, host: &str) -> Result<Vec<u8>> {
+    let stream = connector(true)
+        .connect(host, tcp.compat())
+        .await
+        .context("TLS handshake failed")?;
+    peer_certificate(&stream)
+}
+
+/// DER encoding of the peer's certificate
+pub fn peer_certificate(stream: &TlsStream) -> Result<Vec<u8>> {
+    let certificate = stream
+        .peer_certificate()?
+        .context("Server sent no certificate")?;
+    Ok(certificate.to_der()?)
+}
+
+fn connector(accept_any: bool) -> TlsConnector {
+    TlsConnector::new()
+        .danger_accept_invalid_certs(accept_any)
+        .danger_accept_invalid_hostnames(accept_any)
+}
+
+async fn read_line(stream: &mut BufReader<TcpStream>) -> Result<String> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        bail!("Server closed the connection");
+    }
+    Ok(line)
+}
+
+/// Read a (possibly multi-line) SMTP reply and check its code
+async fn read_smtp_reply(stream: &mut BufReader<TcpStream>, code: &str) -> Result<()> {
+    loop {
+        let line = read_line(stream).await?;
+        if !line.starts_with(code) {
+            bail!("Unexpected SMTP reply: {}", line.trim());
+        }
+        // "250-..." continues, "250 ..." ends the reply
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        let canonical = normalize_fingerprint(&"ab".repeat(32)).unwrap();
+        assert_eq!(canonical, ["AB"; 32].join(":"));
+        assert_eq!(normalize_fingerprint(&canonical).unwrap(), canonical);
+        assert_eq!(
+            normalize_fingerprint(&format!("sha256:{}", "ab ".repeat(32))).unwrap(),
+            canonical
+        );
+        assert!(normalize_fingerprint("AB:CD").is_err());
+        assert!(normalize_fingerprint(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_of() {
+        let fingerprint = fingerprint_of(b"certificate");
+        assert_eq!(normalize_fingerprint(&fingerprint).unwrap(), fingerprint);
+        assert_ne!(fingerprint, fingerprint_of(b"other certificate"));
+    }
+}
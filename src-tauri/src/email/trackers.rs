@@ -0,0 +1,228 @@
+//! Tracking pixel and tracker-domain detection
+//!
+//! Scans `<img>` tags in an HTML body for invisible 1x1 pixels and for images served
+//! from known email-tracking services. Results are stored with the email's insights
+//! and used by the sanitizer to drop those URLs even when remote images are allowed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Domains of email open-tracking services (matched as host suffixes)
+const TRACKER_DOMAINS: &[&str] = &[
+    "mailtrack.io",
+    "yesware.com",
+    "track.hubspot.com",
+    "list-manage.com",
+    "sendgrid.net",
+    "mandrillapp.com",
+    "exct.net",
+    "returnpath.net",
+    "bananatag.com",
+    "mailfoogae.appspot.com",
+    "mixmax.com",
+    "superhuman.com",
+    "getnotify.com",
+    "mailstat.us",
+    "cirrusinsight.com",
+    "emltrk.com",
+    "convertkit-mail.com",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackerKind {
+    /// Invisible 1x1 (or hidden) image
+    Pixel,
+    /// Image served by a known tracking service
+    KnownDomain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackerInfo {
+    pub url: String,
+    pub domain: String,
+    pub kind: TrackerKind,
+}
+
+/// Host of an absolute http(s) or protocol-relative URL
+pub(super) fn url_host(src: &str) -> Option<String> {
+    let src = src.trim();
+    let absolute = if src.starts_with("//") {
+        format!("https:{}", src)
+    } else {
+        src.to_string()
+    };
+    let parsed = url::Url::parse(&absolute).ok()?;
+    match parsed.scheme() {
+        "http" | "https" => parsed.host_str().map(|h| h.to_lowercase()),
+        _ => None,
+    }
+}
+
+fn is_tracker_domain(host: &str) -> bool {
+    TRACKER_DOMAINS
+        .iter()
+        .any(|d| host == *d || host.ends_with(&format!(".{}", d)))
+}
+
+/// Whether a URL points at a known tracking service
+pub fn is_tracker_url(url: &str) -> bool {
+    url_host(url).map(|h| is_tracker_domain(&h)).unwrap_or(false)
+}
+
+/// Parse the attributes of every `<img>` tag (names lowercased, `&amp;` decoded)
+fn img_tags(html: &str) -> Vec<HashMap<String, String>> {
+    let lower = html.to_ascii_lowercase();
+    let mut tags = Vec::new();
+    let mut pos = 0;
+
+    while let Some(offset) = lower[pos..].find("<img") {
+        let start = pos + offset + 4;
+        let end = lower[start..].find('>').map(|e| start + e).unwrap_or(html.len());
+        tags.push(parse_attributes(&html[start..end]));
+        pos = end;
+    }
+
+    tags
+}
+
+pub(super) fn parse_attributes(input: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        while i < chars.len() && (chars[i].is_whitespace() || chars[i] == '/') {
+            i += 1;
+        }
+        let name_start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '=' && chars[i] != '/' {
+            i += 1;
+        }
+        let name: String = chars[name_start..i].iter().collect::<String>().to_lowercase();
+        while i < chars.len() && chars[i].is_whitespace() {
+            i += 1;
+        }
+
+        let mut value = String::new();
+        if i < chars.len() && chars[i] == '=' {
+            i += 1;
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            if i < chars.len() && (chars[i] == '"' || chars[i] == '\'') {
+                let quote = chars[i];
+                i += 1;
+                let value_start = i;
+                while i < chars.len() && chars[i] != quote {
+                    i += 1;
+                }
+                value = chars[value_start..i].iter().collect();
+                i += 1;
+            } else {
+                let value_start = i;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                value = chars[value_start..i].iter().collect();
+            }
+        }
+
+        if !name.is_empty() {
+            attrs.insert(name, value.replace("&amp;", "&"));
+        }
+    }
+
+    attrs
+}
+
+fn is_tiny_dimension(value: &str) -> bool {
+    matches!(value.trim().trim_end_matches("px").trim(), "0" | "1")
+}
+
+fn is_pixel(attrs: &HashMap<String, String>) -> bool {
+    let tiny_attrs = matches!(
+        (attrs.get("width"), attrs.get("height")),
+        (Some(w), Some(h)) if is_tiny_dimension(w) && is_tiny_dimension(h)
+    );
+
+    let style = attrs
+        .get("style")
+        .map(|s| s.to_lowercase().replace(' ', ""))
+        .unwrap_or_default();
+    let tiny_style = (style.contains("width:1px") || style.contains("width:0"))
+        && (style.contains("height:1px") || style.contains("height:0"));
+    let hidden = style.contains("display:none") || style.contains("visibility:hidden");
+
+    tiny_attrs || tiny_style || hidden
+}
+
+/// Find tracking pixels and tracker-domain images in an HTML body
+pub fn detect_trackers(html: &str) -> Vec<TrackerInfo> {
+    let mut trackers: Vec<TrackerInfo> = Vec::new();
+
+    for attrs in img_tags(html) {
+        let Some(src) = attrs.get("src") else {
+            continue;
+        };
+        let Some(domain) = url_host(src) else {
+            continue;
+        };
+
+        let kind = if is_tracker_domain(&domain) {
+            TrackerKind::KnownDomain
+        } else if is_pixel(&attrs) {
+            TrackerKind::Pixel
+        } else {
+            continue;
+        };
+
+        if !trackers.iter().any(|t| t.url == *src) {
+            trackers.push(TrackerInfo {
+                url: src.clone(),
+                domain,
+                kind,
+            });
+        }
+    }
+
+    trackers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_pixel() {
+        let html = r#"<p>Hi</p><img src="https://example.com/o.gif?id=1&amp;u=2" width="1" height="1">"#;
+        let trackers = detect_trackers(html);
+
+        assert_eq!(trackers.len(), 1);
+        assert_eq!(trackers[0].kind, TrackerKind::Pixel);
+        assert_eq!(trackers[0].url, "https://example.com/o.gif?id=1&u=2");
+        assert_eq!(trackers[0].domain, "example.com");
+    }
+
+    #[test]
+    fn test_detects_known_domain() {
+        let html = r#"<IMG SRC='https://u123.ct.sendgrid.net/wf/open?upn=abc'><img src="https://example.com/logo.png" width="120">"#;
+        let trackers = detect_trackers(html);
+
+        assert_eq!(trackers.len(), 1);
+        assert_eq!(trackers[0].kind, TrackerKind::KnownDomain);
+        assert_eq!(trackers[0].domain, "u123.ct.sendgrid.net");
+    }
+
+    #[test]
+    fn test_ignores_inline_and_regular_images() {
+        let html = r#"<img src="data:image/png;base64,AA==" width="1" height="1"><img src="https://example.com/banner.jpg">"#;
+        assert!(detect_trackers(html).is_empty());
+    }
+
+    #[test]
+    fn test_is_tracker_url() {
+        assert!(is_tracker_url("https://mailtrack.io/trace/mail/abc.png"));
+        assert!(!is_tracker_url("https://notmailtrack.io/x.png"));
+    }
+}
@@ -1,5 +1,14 @@
 use serde::{Deserialize, Serialize};
 
+use super::attachments::AttachmentKind;
+
+use super::crypto::CryptoStatus;
+use super::inline::InlinePart;
+use super::links::EmailLink;
+use super::receipts::DeliveryFailure;
+use super::shipments::DetectedShipment;
+use super::smime::SmimeStatus;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Email {
     pub id: String,
@@ -13,6 +22,10 @@ pub struct Email {
     pub snippet: String,
     pub body_html: Option<String>,
     pub body_plain: Option<String>,
+    /// `body_plain` without quoted replies or signature, used for snippets, summaries and
+    /// embeddings
+    #[serde(default)]
+    pub body_clean: Option<String>,
     pub labels: Vec<String>,
     pub is_read: bool,
     pub is_starred: bool,
@@ -22,6 +35,87 @@ pub struct Email {
     pub uid: u32,
     pub folder: String,
     pub message_id: String,
+    /// Message-IDs this message answers, root first (see `threading::reference_chain`).
+    /// Only known for freshly parsed emails.
+    #[serde(default)]
+    pub references: Vec<String>,
+    /// Reply-To address when it was set on the message
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Raw Authentication-Results header (SPF/DKIM/DMARC verdicts from the receiving server)
+    #[serde(default)]
+    pub auth_results: Option<String>,
+    /// Inline `cid:` parts extracted during parsing (cached locally, never serialized)
+    #[serde(skip)]
+    pub inline_parts: Vec<InlinePart>,
+    /// Remote resources stripped from `body_html` by the sanitizer (0 when not sanitized)
+    #[serde(default)]
+    pub remote_images_blocked: usize,
+    /// OpenPGP status for encrypted/signed messages (None for ordinary mail)
+    #[serde(default)]
+    pub crypto: Option<CryptoStatus>,
+    /// S/MIME signature status (None when the message is not S/MIME signed)
+    #[serde(default)]
+    pub smime: Option<SmimeStatus>,
+    /// Where the sender asked for a read receipt to go, until the user sends or declines it
+    #[serde(default)]
+    pub read_receipt_to: Option<String>,
+    /// Bounce of an email the user sent, when this message is a delivery status report
+    #[serde(default)]
+    pub delivery_failure: Option<DeliveryFailure>,
+    /// URLs in the body, extracted during parsing (emails read from the cache have them
+    /// in `email_links` instead)
+    #[serde(default)]
+    pub links: Vec<EmailLink>,
+    /// Tracking numbers when the message is a shipping notice, found during parsing
+    /// (stored in `shipments`)
+    #[serde(default)]
+    pub shipments: Vec<DetectedShipment>,
+    /// Files attached to the message (inline `cid:` images aren't listed)
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+impl Email {
+    /// The text to summarize, classify or embed: the body without quoted replies or
+    /// signature, or the HTML body when there is no plain-text one
+    pub fn ai_text(&self) -> &str {
+        self.body_clean
+            .as_deref()
+            .or(self.body_plain.as_deref())
+            .or(self.body_html.as_deref())
+            .unwrap_or("")
+    }
+}
+
+/// A file attached to an email
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Position among the message's attachments
+    pub position: u32,
+    pub filename: String,
+    pub content_type: String,
+    pub kind: AttachmentKind,
+    /// Decoded size in bytes
+    pub size: u64,
+    /// Downloaded copy in the media cache
+    #[serde(default)]
+    pub local_path: Option<String>,
+    /// Decoded contents, only present on freshly parsed emails
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// A user-defined label. Emails carry its `keyword` in `labels`, which is also the IMAP
+/// keyword it is stored as on the server (Gmail uses `name` as the label instead).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub name: String,
+    pub keyword: String,
+    pub color: Option<String>,
+    /// Cached emails carrying the label (0 when not counted)
+    #[serde(default)]
+    pub email_count: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,9 +135,12 @@ pub struct EmailListItem {
 /// Represents an IMAP folder/mailbox
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Folder {
-    /// Full folder path (e.g., "[Gmail]/Sent Mail")
+    /// Full folder path as the server names it, in modified UTF-7 (e.g.,
+    /// "[Gmail]/Entw&APw-rfe"); commands take this name
     pub name: String,
-    /// Display name (e.g., "Sent Mail")
+    /// Full folder path decoded for display (e.g., "[Gmail]/Entwürfe")
+    pub path: String,
+    /// Display name, the last part of the decoded path (e.g., "Entwürfe")
     pub display_name: String,
     /// Special folder type, if detected
     pub special: Option<SpecialFolder>,
@@ -51,6 +148,16 @@ pub struct Folder {
     pub delimiter: Option<String>,
 }
 
+/// Message counts of a folder, from IMAP STATUS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderStatus {
+    pub folder: String,
+    pub total_count: u32,
+    pub unread_count: u32,
+    /// Messages that arrived since the folder was last selected by any client
+    pub recent_count: u32,
+}
+
 /// Well-known special folder types (RFC 6154)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SpecialFolder {
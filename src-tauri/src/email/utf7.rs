@@ -0,0 +1,175 @@
+//! Modified UTF-7 mailbox names (RFC 3501 §5.1.3)
+//!
+//! IMAP servers name folders in a variant of UTF-7: printable ASCII stands for itself,
+//! except `&`, which is written `&-`, and everything else is UTF-16 in base64 between `&`
+//! and `-`, with `,` in place of `/`. `Entwürfe` goes over the wire as `Entw&APw-rfe`.
+//! Folders keep their wire names for commands; the decoded names are for display.
+
+use std::borrow::Cow;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,";
+
+/// The readable form of a wire name, or `None` if `name` isn't valid modified UTF-7
+pub fn decode(name: &str) -> Option<String> {
+    if !name.is_ascii() {
+        return None;
+    }
+    let mut decoded = String::with_capacity(name.len());
+    let mut rest = name;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let shifted = &rest[start + 1..];
+        let end = shifted.find('-')?;
+        let encoded = &shifted[..end];
+        if encoded.is_empty() {
+            decoded.push('&');
+        } else {
+            let bytes = base64_decode(encoded)?;
+            if bytes.len() % 2 != 0 {
+                return None;
+            }
+            let units: Vec<u16> = bytes
+                .chunks(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            decoded.push_str(&String::from_utf16(&units).ok()?);
+        }
+        rest = &shifted[end + 1..];
+    }
+    decoded.push_str(rest);
+    Some(decoded)
+}
+
+/// Decode a wire name, showing it as it is when it isn't valid modified UTF-7
+pub fn decode_lossy(name: &str) -> String {
+    decode(name).unwrap_or_else(|| name.to_string())
+}
+
+/// The wire form of a readable name
+pub fn encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    let mut pending: Vec<u16> = Vec::new();
+    for c in name.chars() {
+        if (' '..='~').contains(&c) {
+            flush(&mut encoded, &mut pending);
+            if c == '&' {
+                encoded.push_str("&-");
+            } else {
+                encoded.push(c);
+            }
+        } else {
+            let mut buf = [0; 2];
+            pending.extend_from_slice(c.encode_utf16(&mut buf));
+        }
+    }
+    flush(&mut encoded, &mut pending);
+    encoded
+}
+
+/// `name` as sent to the server. Names that are already valid modified UTF-7, like the
+/// ones folder listings return, pass unchanged; anything else, such as a name typed with
+/// accents, is encoded.
+pub fn wire_name(name: &str) -> Cow<'_, str> {
+    if decode(name).is_some() {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(encode(name))
+    }
+}
+
+/// Write `pending` UTF-16 units as one `&…-` run
+fn flush(encoded: &mut String, pending: &mut Vec<u16>) {
+    if pending.is_empty() {
+        return;
+    }
+    let bytes: Vec<u8> = pending.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+    encoded.push('&');
+    encoded.push_str(&base64_encode(&bytes));
+    encoded.push('-');
+    pending.clear();
+}
+
+/// Base64 with the modified alphabet and without padding
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let mut bits = 0u32;
+    let mut len = 0;
+    for &byte in bytes {
+        bits = (bits << 8) | u32::from(byte);
+        len += 8;
+        while len >= 6 {
+            len -= 6;
+            out.push(ALPHABET[((bits >> len) & 0x3f) as usize] as char);
+        }
+    }
+    if len > 0 {
+        out.push(ALPHABET[((bits << (6 - len)) & 0x3f) as usize] as char);
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut len = 0;
+    for c in text.bytes() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+        bits = (bits << 6) | value;
+        len += 6;
+        if len >= 8 {
+            len -= 8;
+            out.push((bits >> len) as u8);
+        }
+    }
+    // Leftover bits are padding and must be zero
+    if bits & ((1 << len) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("INBOX").unwrap(), "INBOX");
+        assert_eq!(decode("Entw&APw-rfe").unwrap(), "Entwürfe");
+        assert_eq!(
+            decode("~peter/mail/&U,BTFw-/&ZeVnLIqe-").unwrap(),
+            "~peter/mail/台北/日本語"
+        );
+        assert_eq!(decode("R&-D").unwrap(), "R&D");
+        assert_eq!(decode("&2D3eAA-").unwrap(), "😀");
+        assert!(decode("R&D").is_none());
+        assert!(decode("&Jjo").is_none());
+        assert!(decode("Entwürfe").is_none());
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        for name in [
+            "INBOX",
+            "Entwürfe",
+            "~peter/mail/台北/日本語",
+            "R&D",
+            "Fotos 😀",
+            "Élément",
+        ] {
+            let encoded = encode(name);
+            assert!(encoded.is_ascii());
+            assert_eq!(decode(&encoded).unwrap(), name);
+        }
+        assert_eq!(encode("Entwürfe"), "Entw&APw-rfe");
+        assert_eq!(encode("台北"), "&U,BTFw-");
+    }
+
+    #[test]
+    fn test_wire_name() {
+        assert_eq!(wire_name("Entw&APw-rfe"), "Entw&APw-rfe");
+        assert_eq!(wire_name("Entwürfe"), "Entw&APw-rfe");
+        assert_eq!(wire_name("R&D"), "R&-D");
+        assert_eq!(wire_name("[Gmail]/Sent Mail"), "[Gmail]/Sent Mail");
+    }
+}
@@ -0,0 +1,374 @@
+//! Long-running background jobs
+//!
+//! A `JobControl` is shared between the loop doing the work and the commands that steer it.
+//! The loop calls `checkpoint()` between items: it waits there while the job is paused and
+//! stops once it has been cancelled.
+//!
+//! The `JobManager` (managed Tauri state) keeps a registry of every job — indexing,
+//! embedding, model downloads, sync — with its kind, progress and control handle, so the
+//! frontend can list and cancel them in one place.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Finished jobs kept in the registry so the UI can show their outcome
+const MAX_FINISHED_JOBS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Idle,
+    Running,
+    Paused,
+    Cancelled,
+}
+
+pub struct JobControl {
+    state: Mutex<JobState>,
+    changed: Notify,
+}
+
+impl Default for JobControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobControl {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(JobState::Idle),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn state(&self) -> JobState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Claim the job for a new run; false if a run is already active
+    pub fn start(&self) -> bool {
+        self.transition(|state| match state {
+            JobState::Idle | JobState::Cancelled => Some(JobState::Running),
+            _ => None,
+        })
+    }
+
+    /// Mark the run as over (whether it completed or was cancelled)
+    pub fn finish(&self) {
+        *self.state.lock().unwrap() = JobState::Idle;
+        self.changed.notify_waiters();
+    }
+
+    pub fn pause(&self) -> bool {
+        self.transition(|state| (state == JobState::Running).then_some(JobState::Paused))
+    }
+
+    pub fn resume(&self) -> bool {
+        self.transition(|state| (state == JobState::Paused).then_some(JobState::Running))
+    }
+
+    pub fn cancel(&self) -> bool {
+        self.transition(|state| match state {
+            JobState::Running | JobState::Paused => Some(JobState::Cancelled),
+            _ => None,
+        })
+    }
+
+    /// Call between items. Waits while paused; returns false once the job is cancelled.
+    pub async fn checkpoint(&self) -> bool {
+        loop {
+            // Register for wakeups before reading the state so a resume can't be missed
+            let notified = self.changed.notified();
+            match self.state() {
+                JobState::Paused => notified.await,
+                JobState::Cancelled => return false,
+                JobState::Running | JobState::Idle => return true,
+            }
+        }
+    }
+
+    fn transition(&self, next: impl FnOnce(JobState) -> Option<JobState>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match next(*state) {
+            Some(new_state) => {
+                *state = new_state;
+                drop(state);
+                self.changed.notify_waiters();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Indexing,
+    Reindex,
+    Reclassify,
+    Embedding,
+    ModelDownload,
+    Sync,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// Snapshot of a job, as sent in `job:update` events and returned by `list_jobs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// Percent complete (0-100)
+    pub progress: f32,
+    pub processed: Option<i64>,
+    pub total: Option<i64>,
+    pub message: Option<String>,
+    /// Whether `cancel_job` can stop it
+    pub cancellable: bool,
+    pub started_at: i64,
+    pub updated_at: i64,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    control: Option<Arc<JobControl>>,
+}
+
+impl JobEntry {
+    /// The stored info with the running/paused status read from the control
+    fn snapshot(&self) -> JobInfo {
+        let mut info = self.info.clone();
+        if !info.status.is_finished() {
+            if let Some(control) = &self.control {
+                info.status = match control.state() {
+                    JobState::Paused => JobStatus::Paused,
+                    _ => JobStatus::Running,
+                };
+            }
+        }
+        info
+    }
+}
+
+/// Registry of background jobs
+#[derive(Default)]
+pub struct JobManager {
+    jobs: Mutex<HashMap<String, JobEntry>>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a running job. Jobs without a control can't be paused or cancelled.
+    pub fn register(&self, kind: JobKind, control: Option<Arc<JobControl>>) -> JobInfo {
+        let now = chrono::Utc::now().timestamp();
+        let info = JobInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            status: JobStatus::Running,
+            progress: 0.0,
+            processed: None,
+            total: None,
+            message: None,
+            cancellable: control.is_some(),
+            started_at: now,
+            updated_at: now,
+        };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.insert(info.id.clone(), JobEntry { info: info.clone(), control });
+        prune_finished(&mut jobs);
+        info
+    }
+
+    /// Record progress as `processed` out of `total` items
+    pub fn set_progress(&self, id: &str, processed: i64, total: i64) -> Option<JobInfo> {
+        self.update(id, |info| {
+            info.processed = Some(processed);
+            info.total = Some(total);
+            info.progress = if total > 0 {
+                (processed as f32 / total as f32 * 100.0).min(100.0)
+            } else {
+                0.0
+            };
+        })
+    }
+
+    /// Record progress as a percentage (for jobs without countable items)
+    pub fn set_percent(&self, id: &str, progress: f32) -> Option<JobInfo> {
+        self.update(id, |info| info.progress = progress.clamp(0.0, 100.0))
+    }
+
+    pub fn set_message(&self, id: &str, message: impl Into<String>) -> Option<JobInfo> {
+        let message = message.into();
+        self.update(id, |info| info.message = Some(message))
+    }
+
+    /// Mark the job finished with `status` (`Completed`, `Failed` or `Cancelled`)
+    pub fn finish(&self, id: &str, status: JobStatus, message: Option<String>) -> Option<JobInfo> {
+        self.update(id, |info| {
+            info.status = status;
+            if status == JobStatus::Completed {
+                info.progress = 100.0;
+            }
+            if message.is_some() {
+                info.message = message;
+            }
+        })
+    }
+
+    /// Cancel a running job; false if it's unknown, finished or not cancellable
+    pub fn cancel(&self, id: &str) -> bool {
+        let jobs = self.jobs.lock().unwrap();
+        match jobs.get(id) {
+            Some(entry) if !entry.info.status.is_finished() => {
+                entry.control.as_ref().is_some_and(|control| control.cancel())
+            }
+            _ => false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobInfo> {
+        self.jobs.lock().unwrap().get(id).map(JobEntry::snapshot)
+    }
+
+    /// All known jobs, newest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut list: Vec<JobInfo> = jobs.values().map(JobEntry::snapshot).collect();
+        list.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| a.id.cmp(&b.id)));
+        list
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut JobInfo)) -> Option<JobInfo> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let entry = jobs.get_mut(id)?;
+        f(&mut entry.info);
+        entry.info.updated_at = chrono::Utc::now().timestamp();
+        Some(entry.snapshot())
+    }
+}
+
+/// Drop the oldest finished jobs beyond `MAX_FINISHED_JOBS`
+fn prune_finished(jobs: &mut HashMap<String, JobEntry>) {
+    let mut finished: Vec<(i64, String)> = jobs
+        .values()
+        .filter(|entry| entry.info.status.is_finished())
+        .map(|entry| (entry.info.updated_at, entry.info.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+
+    finished.sort();
+    let excess = finished.len() - MAX_FINISHED_JOBS;
+    for (_, id) in finished.into_iter().take(excess) {
+        jobs.remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_transitions() {
+        let control = JobControl::new();
+        assert!(!control.pause());
+        assert!(control.start());
+        assert!(!control.start());
+        assert!(control.pause());
+        assert_eq!(control.state(), JobState::Paused);
+        assert!(control.resume());
+        assert!(control.cancel());
+        assert!(!control.resume());
+        assert!(control.start());
+        control.finish();
+        assert_eq!(control.state(), JobState::Idle);
+    }
+
+    #[test]
+    fn test_manager_tracks_progress_and_cancel() {
+        let manager = JobManager::new();
+        let control = Arc::new(JobControl::new());
+        control.start();
+
+        let job = manager.register(JobKind::Embedding, Some(control.clone()));
+        let info = manager.set_progress(&job.id, 5, 20).unwrap();
+        assert_eq!(info.progress, 25.0);
+
+        control.pause();
+        assert_eq!(manager.get(&job.id).unwrap().status, JobStatus::Paused);
+
+        assert!(manager.cancel(&job.id));
+        assert_eq!(control.state(), JobState::Cancelled);
+        manager.finish(&job.id, JobStatus::Cancelled, None);
+        assert!(!manager.cancel(&job.id));
+
+        let download = manager.register(JobKind::ModelDownload, None);
+        assert!(!manager.cancel(&download.id));
+        assert_eq!(manager.list().len(), 2);
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned() {
+        let manager = JobManager::new();
+        for _ in 0..MAX_FINISHED_JOBS + 5 {
+            let job = manager.register(JobKind::Sync, None);
+            manager.finish(&job.id, JobStatus::Completed, None);
+        }
+        let running = manager.register(JobKind::Indexing, None);
+
+        let jobs = manager.list();
+        assert_eq!(jobs.len(), MAX_FINISHED_JOBS + 1);
+        assert!(jobs.iter().any(|job| job.id == running.id));
+    }
+
+    #[test]
+    fn test_checkpoint_waits_while_paused() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let control = Arc::new(JobControl::new());
+            control.start();
+            assert!(control.checkpoint().await);
+
+            control.pause();
+            let waiter = tokio::spawn({
+                let control = control.clone();
+                async move { control.checkpoint().await }
+            });
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            assert!(!waiter.is_finished());
+
+            control.cancel();
+            assert!(!waiter.await.unwrap());
+        });
+    }
+}
@@ -2,12 +2,18 @@ mod auth;
 mod commands;
 mod db;
 mod email;
+mod jobs;
 mod llm;
+mod logging;
+mod paths;
+mod security;
+mod settings;
 
 use commands::account::AccountManager;
-use directories::ProjectDirs;
 use email::idle::IdleManager;
+use jobs::JobManager;
 use std::sync::{Arc, Mutex};
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -15,38 +21,74 @@ pub fn run() {
     let _ = dotenvy::dotenv();
 
     // Initialize database
-    let project_dirs =
-        ProjectDirs::from("com", "inboxed", "inboxed").expect("Failed to get project directory");
-    let data_dir = project_dirs.data_dir();
-    std::fs::create_dir_all(data_dir).expect("Failed to create data directory");
-    let db_path = data_dir.join("emails.db");
+    let data_dir = paths::data_dir().expect("Failed to get data directory");
+    std::fs::create_dir_all(&data_dir).expect("Failed to create data directory");
+    logging::init(&data_dir);
+    let db_path = data_dir.join(paths::EMAIL_DB_FILE);
     let database = db::EmailDatabase::new(db_path).expect("Failed to initialize database");
+    settings::init(&database);
+    security::init();
     let db_state = Arc::new(Mutex::new(Some(database)));
+    let vector_db_state = commands::VectorDbState::new(data_dir.join(commands::VECTOR_DB_FILE));
 
-    // Initialize account manager and IDLE manager
+    // Initialize account manager, IDLE manager and background job registry
     let account_manager = AccountManager::new();
     let idle_manager = IdleManager::new();
+    let job_manager = JobManager::new();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .manage(db_state)
+        .manage(vector_db_state)
         .manage(account_manager)
         .manage(idle_manager)
+        .manage(job_manager)
+        .setup(|app| {
+            // Avatars and inline images are shown from the media cache, which moves with
+            // the profile, portable mode and INBOXED_DATA_DIR
+            app.asset_protocol_scope()
+                .allow_directory(commands::cache::get_media_cache_dir()?, true)?;
+            commands::spawn_model_auto_unload();
+            commands::spawn_reply_reminders(app.handle().clone());
+            commands::spawn_ai_warmup(app.handle().clone());
+            commands::spawn_orphan_sweep(app.handle().clone());
+            commands::spawn_auto_lock(app.handle().clone());
+            commands::spawn_imap_keepalive(app.handle().clone());
+            commands::spawn_status_watchdog(app.handle().clone());
+            commands::spawn_db_maintenance(app.handle().clone());
+            commands::spawn_cache_events(app.handle().clone());
+            commands::spawn_priority_retune(app.handle().clone());
+            commands::spawn_attachment_ocr(app.handle().clone());
+            commands::spawn_shipment_tracking(app.handle().clone());
+            #[cfg(mobile)]
+            app.handle().plugin(tauri_plugin_biometric::init())?;
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Auth commands
             commands::check_auth_status,
             commands::start_auth,
             commands::complete_auth,
+            commands::start_device_auth,
+            commands::complete_device_auth,
             commands::refresh_token,
             commands::sign_out,
             commands::get_access_token,
+            commands::get_account_auth_status,
+            commands::reauthenticate_account,
             // Account commands
             commands::add_account,
             commands::remove_account,
             commands::list_accounts,
+            commands::update_account,
             commands::set_active_account,
             commands::connect_account,
+            commands::get_server_certificate_fingerprint,
+            commands::set_account_cert_fingerprint,
+            commands::get_account_capabilities,
+            commands::list_identities,
+            commands::add_identity,
             // Email commands
             commands::fetch_emails,
             commands::get_email,
@@ -55,18 +97,38 @@ pub fn run() {
             commands::star_email,
             commands::trash_email,
             commands::archive_email,
+            commands::move_email,
+            commands::search_server,
+            commands::list_folders,
+            commands::create_folder,
+            commands::rename_folder,
+            commands::delete_folder,
+            commands::set_folder_role,
+            commands::reset_folder_role,
             commands::start_idle_monitoring,
             commands::stop_idle_monitoring,
             commands::get_folder_stats,
+            commands::get_folder_status,
+            commands::set_remote_images_allowed,
+            commands::get_remote_images_allowed,
+            // Triage commands
+            commands::start_triage_session,
+            commands::triage_action,
+            commands::end_triage_session,
             // AI commands
             commands::check_model_status,
             commands::is_model_loading,
             commands::download_model,
             commands::download_model_by_id,
+            commands::add_custom_model,
             commands::init_ai,
             commands::init_ai_fallback,
             commands::summarize_email,
             commands::summarize_email_stream,
+            commands::compose_with_ai,
+            commands::rewrite_text,
+            commands::translate_email,
+            commands::cancel_ai_task,
             commands::get_email_insights,
             commands::classify_priority,
             commands::get_model_info,
@@ -75,42 +137,174 @@ pub fn run() {
             commands::get_downloaded_models,
             commands::delete_model,
             commands::activate_model,
+            commands::unload_model,
+            commands::get_memory_usage,
+            commands::benchmark_model,
+            commands::get_model_benchmarks,
+            commands::get_prompt_template,
+            commands::set_prompt_template,
             commands::get_active_model_id,
+            commands::get_model_fallback,
+            commands::get_compute_capabilities,
+            commands::get_compute_settings,
+            commands::save_compute_settings,
+            commands::get_llm_settings,
+            commands::set_llm_settings,
+            // Dictation commands
+            commands::get_available_speech_models,
+            commands::get_downloaded_speech_models,
+            commands::download_speech_model,
+            commands::delete_speech_model,
+            commands::transcribe_audio,
             // Database commands
             commands::init_database,
             commands::get_smart_inbox,
             commands::get_emails_by_category,
+            commands::get_emails_by_sentiment,
+            commands::get_categories,
+            commands::add_category,
+            commands::update_category,
+            commands::delete_category,
+            commands::reclassify_emails,
             commands::get_indexing_status,
             commands::reset_indexing_status,
+            commands::force_reset_all_statuses,
+            commands::get_email_trackers,
+            commands::get_email_copies,
+            commands::get_thread_summary,
+            commands::get_email_links,
+            commands::get_shared_links,
+            commands::get_link_preview,
+            commands::list_all_attachments,
+            commands::download_attachment,
+            commands::get_attachment_text,
+            commands::get_shipments,
+            commands::get_suspicious_emails,
+            commands::get_upcoming_deadlines,
+            commands::get_sender_stats,
+            commands::get_inbox_analytics,
+            commands::get_awaiting_reply,
+            commands::set_needs_reply,
+            commands::get_reply_reminder_settings,
+            commands::set_reply_reminder_settings,
+            commands::get_notification_settings,
+            commands::set_notification_settings,
+            commands::get_sync_settings,
+            commands::set_sync_settings,
+            commands::get_pending_followups,
+            commands::dismiss_followup,
+            commands::draft_followup,
+            commands::extract_meeting_proposals,
+            commands::draft_scheduling_reply,
+            commands::send_read_receipt,
+            commands::decline_read_receipt,
+            commands::get_bounced_messages,
+            commands::check_recipients,
+            commands::clear_bounced_address,
+            commands::list_labels,
+            commands::create_label,
+            commands::delete_label,
+            commands::apply_label,
+            commands::remove_label,
+            commands::get_emails_by_label,
+            commands::get_inbox_tabs,
+            commands::get_inbox_tab_emails,
+            commands::pin_email,
+            commands::get_inbox_sections,
+            commands::get_inbox_counts,
+            commands::set_email_priority_override,
+            commands::create_inbox_section,
+            commands::update_inbox_section,
+            commands::delete_inbox_section,
+            commands::reorder_inbox_sections,
+            commands::get_inbox_section_emails,
             commands::start_email_indexing,
+            commands::get_account_indexing_status,
+            commands::pause_indexing,
+            commands::resume_indexing,
+            commands::cancel_indexing,
+            commands::reindex_cached_emails,
+            commands::get_reindex_status,
             commands::search_smart_emails,
+            commands::save_search,
+            commands::list_saved_searches,
+            commands::run_saved_search,
+            commands::delete_saved_search,
             commands::get_emails_by_account_and_category,
             commands::chat_query,
+            commands::chat_query_stream,
+            commands::cancel_chat_generation,
+            commands::create_chat_session,
+            commands::list_chat_sessions,
+            commands::get_chat_messages,
+            commands::rename_chat_session,
+            commands::delete_chat_session,
+            commands::send_chat_message,
+            // App lock commands
+            commands::get_app_lock_status,
+            commands::set_app_lock,
+            commands::disable_app_lock,
+            commands::lock_app,
+            commands::unlock_app,
+            // Settings commands
+            commands::get_settings,
+            commands::update_settings,
+            commands::export_app_config,
+            commands::import_app_config,
+            // Backup commands
+            commands::create_backup,
+            commands::restore_backup,
             // Cache commands
             commands::get_storage_info,
             commands::get_cache_settings,
             commands::save_cache_settings,
             commands::clear_email_cache,
+            commands::run_db_maintenance,
             commands::clear_media_cache,
             commands::clear_all_caches,
             commands::cache_media_asset,
             commands::get_cached_media_asset,
+            commands::get_inline_assets,
+            commands::get_sender_avatar,
             commands::get_cached_emails_count,
             commands::has_cached_emails,
             commands::clear_all_app_data,
             commands::clear_ai_models,
+            // Diagnostics commands
+            commands::get_recent_logs,
+            commands::export_diagnostics,
+            commands::run_diagnostics,
+            // PGP commands
+            commands::import_pgp_key,
+            commands::export_pgp_key,
+            commands::list_pgp_keys,
+            commands::delete_pgp_key,
             // RAG commands
             commands::init_rag,
             commands::is_rag_ready,
             commands::is_embedding_model_downloaded,
+            commands::get_available_embedding_models,
+            commands::set_embedding_model,
+            commands::unload_embedding_model,
             commands::get_embedding_status,
             commands::embed_email,
             commands::embed_all_emails,
+            commands::pause_embedding,
+            commands::resume_embedding,
+            commands::cancel_embedding,
+            commands::reembed_all,
+            commands::get_embedding_models,
             commands::search_emails_semantic,
             commands::find_similar_emails,
             commands::get_embedded_count,
             commands::clear_embeddings,
             commands::chat_with_context,
+            commands::chat_with_context_stream,
+            commands::download_reranker,
+            commands::is_reranker_downloaded,
+            // Job commands
+            commands::list_jobs,
+            commands::cancel_job,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,190 @@
+//! Dictation audio
+//!
+//! The composer records dictation as a WAV file. Whisper models take mono samples at
+//! 16 kHz, so recordings are mixed down and resampled before the model turns them into a
+//! log-mel spectrogram with the filterbank from `mel_filters`.
+
+use anyhow::{bail, Result};
+use hound::{SampleFormat, WavReader};
+use std::io::Cursor;
+
+/// Sample rate Whisper models are trained on
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Longest recording transcribed, in seconds
+pub const MAX_DURATION_SECS: u32 = 600;
+
+/// Decode a WAV file into mono samples at `SAMPLE_RATE`, scaled to -1.0..=1.0
+pub fn decode_wav(bytes: &[u8]) -> Result<Vec<f32>> {
+    let mut reader = WavReader::new(Cursor::new(bytes))?;
+    let spec = reader.spec();
+    if spec.channels == 0 || spec.sample_rate == 0 || reader.duration() == 0 {
+        bail!("The recording has no audio");
+    }
+    if reader.duration() / spec.sample_rate > MAX_DURATION_SECS {
+        bail!(
+            "Recordings over {} minutes can't be transcribed",
+            MAX_DURATION_SECS / 60
+        );
+    }
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+        SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+    let mono = mix_down(&samples, spec.channels as usize);
+    Ok(resample(&mono, spec.sample_rate, SAMPLE_RATE))
+}
+
+/// Average interleaved `channels` into one
+fn mix_down(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels == 1 {
+        return samples.to_vec();
+    }
+    samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect()
+}
+
+/// Linearly interpolate `samples` recorded at `from` Hz to `to` Hz
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from as f64 / to as f64;
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            current + (next - current) * fraction
+        })
+        .collect()
+}
+
+/// Hz per mel below 1 kHz, where the Slaney mel scale is linear
+const MEL_LINEAR_STEP: f64 = 200.0 / 3.0;
+
+/// The mel where the scale turns logarithmic, at 1 kHz
+const MEL_LOG_START: f64 = 1000.0 / MEL_LINEAR_STEP;
+
+/// Growth in ln(Hz) per mel above 1 kHz
+fn mel_log_step() -> f64 {
+    6.4f64.ln() / 27.0
+}
+
+/// The Slaney mel scale librosa and Whisper use
+fn hz_to_mel(hz: f64) -> f64 {
+    if hz < 1000.0 {
+        hz / MEL_LINEAR_STEP
+    } else {
+        MEL_LOG_START + (hz / 1000.0).ln() / mel_log_step()
+    }
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    if mel < MEL_LOG_START {
+        mel * MEL_LINEAR_STEP
+    } else {
+        1000.0 * (mel_log_step() * (mel - MEL_LOG_START)).exp()
+    }
+}
+
+/// The `n_mels` × (`n_fft` / 2 + 1) mel filterbank, row by row, with area-normalized
+/// triangles spanning 0 Hz to half of `sample_rate`. This is what Whisper ships as
+/// `mel_filters.npz`.
+pub fn mel_filters(n_mels: usize, n_fft: usize, sample_rate: u32) -> Vec<f32> {
+    let bins = n_fft / 2 + 1;
+    let nyquist = sample_rate as f64 / 2.0;
+    let bin_hz: Vec<f64> = (0..bins)
+        .map(|k| k as f64 * nyquist / (bins - 1) as f64)
+        .collect();
+    let max_mel = hz_to_mel(nyquist);
+    let edges: Vec<f64> = (0..n_mels + 2)
+        .map(|i| mel_to_hz(max_mel * i as f64 / (n_mels + 1) as f64))
+        .collect();
+
+    let mut filters = vec![0.0f32; n_mels * bins];
+    for m in 0..n_mels {
+        let (lower, center, upper) = (edges[m], edges[m + 1], edges[m + 2]);
+        let norm = 2.0 / (upper - lower);
+        for (k, hz) in bin_hz.iter().enumerate() {
+            let rising = (hz - lower) / (center - lower);
+            let falling = (upper - hz) / (upper - center);
+            let weight = rising.min(falling).max(0.0);
+            filters[m * bins + k] = (weight * norm) as f32;
+        }
+    }
+    filters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{WavSpec, WavWriter};
+
+    fn wav(spec: WavSpec, samples: &[i16]) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+        for sample in samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_decode_wav() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48_000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        // A second of stereo where the channels cancel out, then match at half scale
+        let mut samples = Vec::new();
+        for i in 0..48_000 {
+            if i < 24_000 {
+                samples.extend([i16::MAX, -i16::MAX]);
+            } else {
+                samples.extend([16_384, 16_384]);
+            }
+        }
+        let decoded = decode_wav(&wav(spec, &samples)).unwrap();
+        assert_eq!(decoded.len(), 16_000);
+        assert!(decoded[..7_999].iter().all(|sample| sample.abs() < 1e-6));
+        assert!(decoded[8_000..]
+            .iter()
+            .all(|sample| (sample - 0.5).abs() < 1e-6));
+
+        let empty = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            ..spec
+        };
+        assert!(decode_wav(&wav(empty, &[])).is_err());
+        assert!(decode_wav(b"not a wav file").is_err());
+    }
+
+    #[test]
+    fn test_mel_filters() {
+        let filters = mel_filters(80, 400, SAMPLE_RATE);
+        assert_eq!(filters.len(), 80 * 201);
+        // Matches the first filter in Whisper's mel_filters.npz
+        assert!((filters[1] - 0.024_862_59).abs() < 1e-6);
+        assert!(filters.iter().all(|weight| *weight >= 0.0));
+        // Every filter covers at least one frequency bin
+        assert!(filters
+            .chunks(201)
+            .all(|filter| filter.iter().any(|weight| *weight > 0.0)));
+    }
+}
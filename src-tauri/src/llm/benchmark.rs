@@ -0,0 +1,190 @@
+//! Model benchmarking
+//!
+//! Loads a model on its own, summarizes a fixed sample email and measures load time,
+//! prompt processing and generation speed, and how much memory the process gained while
+//! doing so. The numbers replace the catalog's hardcoded speed estimates in the model picker.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::compute::process_rss_bytes;
+use super::engine::{GenerationParams, LlmEngine, LlmSettings};
+use super::prompt_format::{self, PromptFormat};
+
+/// Tokens generated per run; long enough for a stable rate, short enough to finish quickly
+const BENCHMARK_MAX_TOKENS: u32 = 128;
+
+/// How often memory is sampled while the benchmark runs
+const MEMORY_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+const BENCHMARK_SYSTEM: &str =
+    "You are a helpful email assistant. Summarize this email in 2-3 sentences, covering the main points and any required actions.";
+
+const BENCHMARK_EMAIL: &str = "Summarize this email:
+
+From: Priya Raman <priya@northwind.example>
+Subject: Q3 planning offsite - agenda and logistics
+
+Hi team,
+
+Thanks everyone for the input on the Q3 planning offsite. We've locked in Thursday the 14th \
+and Friday the 15th at the Harbor View conference center, 9am to 5pm both days. Lunch and \
+coffee are covered; please let Marcus know about dietary restrictions by Monday.
+
+Day one covers the roadmap review: each squad lead has 20 minutes to present what shipped, \
+what slipped and why, and their top three bets for next quarter. Please send slides to me by \
+Wednesday noon so I can merge them into one deck. Day two is for cross-team dependencies and \
+the hiring plan. Finance will join after lunch to walk through the revised budget, which is \
+about 8% lower than we asked for, so come ready to prioritize.
+
+Parking is validated at the front desk. If you're remote, the dial-in link will be on the \
+calendar invite. Reply to this thread with any agenda items I've missed.
+
+Thanks,
+Priya";
+
+/// Measured performance of a model on this machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelBenchmark {
+    pub model_id: String,
+    /// Time to load the weights and create the context
+    pub load_time_ms: u64,
+    pub prompt_tokens: usize,
+    pub prompt_tokens_per_sec: f32,
+    pub generated_tokens: usize,
+    pub tokens_per_sec: f32,
+    /// Growth of the process's resident memory over the run, when the platform reports it
+    pub peak_memory_bytes: Option<u64>,
+    /// Unix timestamp of the run
+    pub measured_at: i64,
+}
+
+/// Load the model at `model_path` and time a summarization of the sample email
+pub fn run_benchmark(
+    model_id: &str,
+    model_path: &Path,
+    settings: LlmSettings,
+) -> Result<ModelBenchmark> {
+    let memory = MemorySampler::start();
+
+    let load_start = Instant::now();
+    let engine = LlmEngine::new(model_path, settings)?;
+    let load_time = load_start.elapsed();
+
+    let template = prompt_format::override_for(model_path)
+        .unwrap_or_else(|| PromptFormat::detect_file(model_path).template());
+    let prompt = template.format(BENCHMARK_SYSTEM, BENCHMARK_EMAIL);
+    let prompt_tokens = engine.token_count(&prompt)?;
+
+    let params = GenerationParams {
+        max_tokens: BENCHMARK_MAX_TOKENS,
+        temperature: engine.settings().temperature,
+        top_p: engine.settings().top_p,
+        stop_sequences: template.stop,
+        ..Default::default()
+    };
+
+    let generation_start = Instant::now();
+    let mut first_token: Option<Duration> = None;
+    let mut generated_tokens = 0;
+    engine.generate_stream(&prompt, &params, |_| {
+        first_token.get_or_insert_with(|| generation_start.elapsed());
+        generated_tokens += 1;
+    })?;
+    let total = generation_start.elapsed();
+
+    // Sample while the model is still loaded
+    let peak_memory_bytes = memory.stop();
+    drop(engine);
+    let first_token = first_token.ok_or_else(|| anyhow!("The model produced no output"))?;
+
+    Ok(ModelBenchmark {
+        model_id: model_id.to_string(),
+        load_time_ms: load_time.as_millis() as u64,
+        prompt_tokens,
+        prompt_tokens_per_sec: rate(prompt_tokens, first_token),
+        generated_tokens,
+        // The first token's time is prompt processing, so it's left out of the rate
+        tokens_per_sec: rate(
+            generated_tokens.saturating_sub(1),
+            total.saturating_sub(first_token),
+        ),
+        peak_memory_bytes,
+        measured_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Items per second, or 0 when nothing was timed
+fn rate(count: usize, elapsed: Duration) -> f32 {
+    let secs = elapsed.as_secs_f32();
+    if count == 0 || secs <= 0.0 {
+        0.0
+    } else {
+        count as f32 / secs
+    }
+}
+
+/// Tracks the highest resident memory seen on a background thread
+struct MemorySampler {
+    baseline: Option<u64>,
+    peak: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl MemorySampler {
+    fn start() -> Self {
+        let baseline = process_rss_bytes();
+        let peak = Arc::new(AtomicU64::new(baseline.unwrap_or(0)));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = baseline.is_some().then(|| {
+            let peak = peak.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Some(rss) = process_rss_bytes() {
+                        peak.fetch_max(rss, Ordering::Relaxed);
+                    }
+                    std::thread::sleep(MEMORY_SAMPLE_INTERVAL);
+                }
+            })
+        });
+
+        Self {
+            baseline,
+            peak,
+            stop,
+            handle,
+        }
+    }
+
+    /// Stop sampling and return the growth over the baseline
+    fn stop(mut self) -> Option<u64> {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        let baseline = self.baseline?;
+        if let Some(rss) = process_rss_bytes() {
+            self.peak.fetch_max(rss, Ordering::Relaxed);
+        }
+        Some(self.peak.load(Ordering::Relaxed).saturating_sub(baseline))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate() {
+        assert_eq!(rate(100, Duration::from_secs(4)), 25.0);
+        assert_eq!(rate(0, Duration::from_secs(4)), 0.0);
+        assert_eq!(rate(10, Duration::ZERO), 0.0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Compute device preferences and capability reporting
+//!
+//! Both inference backends pick a device when a model is loaded: llama.cpp offloads layers
+//! to the GPU, and Candle tries Metal before the CPU. `ComputeSettings` (the `compute`
+//! section of the app settings) lets users on weak GPUs force the CPU; the offloaded layer
+//! count itself is part of `LlmSettings`. Changes apply the next time a model is loaded.
+//! It also holds the idle timeout after which loaded models are freed.
+
+use candle_core::Device;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevicePreference {
+    /// Use the GPU when one is available, otherwise the CPU
+    #[default]
+    Auto,
+    /// Never use the GPU
+    Cpu,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ComputeSettings {
+    pub device: DevicePreference,
+    /// Free the LLM and embedding model after this many minutes without use; `None` keeps
+    /// them loaded. Freed models reload the next time they're needed.
+    pub idle_unload_minutes: Option<u32>,
+}
+
+impl ComputeSettings {
+    /// Whether models may be placed on a GPU
+    pub fn allow_gpu(&self) -> bool {
+        self.device != DevicePreference::Cpu
+    }
+
+    /// How long a model may sit unused before it is freed
+    pub fn idle_unload_after(&self) -> Option<Duration> {
+        self.idle_unload_minutes
+            .filter(|&minutes| minutes > 0)
+            .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+    }
+}
+
+/// What the machine offers for local inference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComputeCapabilities {
+    pub metal: bool,
+    pub cuda: bool,
+    /// Whether the llama.cpp build can offload layers to a GPU
+    pub llm_gpu_offload: bool,
+    pub cpu_cores: usize,
+    /// Physical memory, when the platform reports it
+    pub total_memory_bytes: Option<u64>,
+    pub os: String,
+    pub arch: String,
+}
+
+impl ComputeCapabilities {
+    pub fn detect() -> Self {
+        Self {
+            metal: Device::new_metal(0).is_ok(),
+            cuda: Device::new_cuda(0).is_ok(),
+            llm_gpu_offload: super::engine::gpu_offload_supported(),
+            cpu_cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            total_memory_bytes: total_memory_bytes(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn total_memory_bytes() -> Option<u64> {
+    let output = std::process::Command::new("sysctl")
+        .args(["-n", "hw.memsize"])
+        .output()
+        .ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn total_memory_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    parse_meminfo_total(&meminfo)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn total_memory_bytes() -> Option<u64> {
+    None
+}
+
+/// Resident memory of this process, when the platform reports it
+#[cfg(target_os = "linux")]
+pub fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    parse_kb_field(&status, "VmRSS:")
+}
+
+#[cfg(target_os = "macos")]
+pub fn process_rss_bytes() -> Option<u64> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+        .output()
+        .ok()?;
+    let kb: u64 = String::from_utf8(output.stdout).ok()?.trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn process_rss_bytes() -> Option<u64> {
+    None
+}
+
+/// `MemTotal:  16318480 kB` -> bytes
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_meminfo_total(meminfo: &str) -> Option<u64> {
+    parse_kb_field(meminfo, "MemTotal:")
+}
+
+/// Value of a `Name:  123 kB` line from a `/proc` file, in bytes
+#[cfg_attr(not(any(target_os = "linux", test)), allow(dead_code))]
+fn parse_kb_field(content: &str, field: &str) -> Option<u64> {
+    let line = content.lines().find(|l| l.starts_with(field))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_parse() {
+        let settings: ComputeSettings = serde_json::from_str(r#"{"device":"cpu"}"#).unwrap();
+        assert_eq!(settings.device, DevicePreference::Cpu);
+        assert!(!settings.allow_gpu());
+
+        // Unknown fields (e.g. from older versions) are ignored and missing ones default
+        let settings: ComputeSettings = serde_json::from_str(r#"{"max_gpu_layers":12}"#).unwrap();
+        assert!(settings.allow_gpu());
+        assert_eq!(settings.idle_unload_after(), None);
+
+        let settings: ComputeSettings =
+            serde_json::from_str(r#"{"idle_unload_minutes":15}"#).unwrap();
+        assert_eq!(settings.idle_unload_after(), Some(Duration::from_secs(900)));
+    }
+
+    #[test]
+    fn test_parse_meminfo_total() {
+        let meminfo = "MemTotal:       16318480 kB\nMemFree:         1000 kB\n";
+        assert_eq!(parse_meminfo_total(meminfo), Some(16318480 * 1024));
+        assert_eq!(parse_meminfo_total("MemFree: 10 kB"), None);
+
+        let status = "Name:\tinboxed\nVmPeak:\t  900000 kB\nVmRSS:\t  512000 kB\n";
+        assert_eq!(parse_kb_field(status, "VmRSS:"), Some(512000 * 1024));
+    }
+}
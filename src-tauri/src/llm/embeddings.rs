@@ -7,13 +7,104 @@ use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config, DTYPE};
 use hf_hub::{Repo, RepoType};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokenizers::Tokenizer;
+use tracing::{debug, info, warn};
+
+use crate::settings;
 
 /// Default embedding model - small and fast
 pub const DEFAULT_EMBEDDING_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+/// Dimensions of the default model, used when a model's config doesn't say
 pub const EMBEDDING_DIMENSIONS: usize = 384;
 
+/// How token embeddings are combined into one vector per text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pooling {
+    /// Attention-masked mean over all tokens
+    Mean,
+    /// The `[CLS]` token's embedding
+    Cls,
+}
+
+/// Embedding model option for users to choose from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelOption {
+    /// Hugging Face repo id, also stored as `embedding_model` on each vector
+    pub id: String,
+    pub name: String,
+    pub dimensions: usize,
+    pub size_mb: u32,
+    pub description: String,
+    pub multilingual: bool,
+    pub pooling: Pooling,
+    /// Prepended to search queries
+    pub query_prefix: Option<String>,
+    /// Prepended to email text
+    pub passage_prefix: Option<String>,
+}
+
+/// Supported embedding models (all BERT-architecture, so `BertModel` can load them)
+pub fn get_available_embedding_models() -> Vec<EmbeddingModelOption> {
+    vec![
+        EmbeddingModelOption {
+            id: DEFAULT_EMBEDDING_MODEL.to_string(),
+            name: "MiniLM L6 (Recommended)".to_string(),
+            dimensions: 384,
+            size_mb: 90,
+            description: "Small and fast. Good for English email.".to_string(),
+            multilingual: false,
+            pooling: Pooling::Mean,
+            query_prefix: None,
+            passage_prefix: None,
+        },
+        EmbeddingModelOption {
+            id: "BAAI/bge-small-en-v1.5".to_string(),
+            name: "BGE Small".to_string(),
+            dimensions: 384,
+            size_mb: 133,
+            description: "Same size class, better retrieval quality for English.".to_string(),
+            multilingual: false,
+            pooling: Pooling::Cls,
+            query_prefix: Some("Represent this sentence for searching relevant passages: ".to_string()),
+            passage_prefix: None,
+        },
+        EmbeddingModelOption {
+            id: "BAAI/bge-base-en-v1.5".to_string(),
+            name: "BGE Base".to_string(),
+            dimensions: 768,
+            size_mb: 438,
+            description: "Larger and more accurate, slower to index.".to_string(),
+            multilingual: false,
+            pooling: Pooling::Cls,
+            query_prefix: Some("Represent this sentence for searching relevant passages: ".to_string()),
+            passage_prefix: None,
+        },
+        EmbeddingModelOption {
+            id: "intfloat/multilingual-e5-small".to_string(),
+            name: "Multilingual E5 Small".to_string(),
+            dimensions: 384,
+            size_mb: 471,
+            description: "Searches across 90+ languages.".to_string(),
+            multilingual: true,
+            pooling: Pooling::Mean,
+            query_prefix: Some("query: ".to_string()),
+            passage_prefix: Some("passage: ".to_string()),
+        },
+    ]
+}
+
+/// Look up a supported embedding model by id
+pub fn find_embedding_model(model_id: &str) -> Option<EmbeddingModelOption> {
+    get_available_embedding_models()
+        .into_iter()
+        .find(|m| m.id == model_id)
+}
+
 /// Files needed for the embedding model
 const MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
 
@@ -23,17 +114,34 @@ pub struct EmbeddingEngine {
     tokenizer: Tokenizer,
     device: Device,
     model_id: String,
+    dimensions: usize,
+    pooling: Pooling,
+    query_prefix: Option<String>,
+    passage_prefix: Option<String>,
+    /// Size of the weights file, as an estimate of the model's memory
+    weights_bytes: u64,
+    /// Unix time (seconds) of the last encode
+    last_used: AtomicU64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
 }
 
 /// Get the custom cache directory for embedding model files
 fn get_custom_cache_dir(model_id: &str) -> Result<PathBuf> {
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
-    let cache_dir = PathBuf::from(home)
-        .join(".cache")
-        .join("inboxed")
+    // Portable installs keep everything under their data directory
+    let base = if crate::paths::is_portable() {
+        crate::paths::models_dir()?
+    } else {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+        PathBuf::from(home).join(".cache").join("inboxed")
+    };
+    Ok(base
         .join("embedding_models")
-        .join(model_id.replace('/', "--"));
-    Ok(cache_dir)
+        .join(model_id.replace('/', "--")))
 }
 
 /// Check if model files exist in custom cache
@@ -64,6 +172,11 @@ fn check_hf_cache(model_id: &str) -> Option<(PathBuf, PathBuf, PathBuf)> {
     }
 }
 
+/// Paths of a model's config, tokenizer and weights if they're in either cache
+pub(super) fn cached_model_files(model_id: &str) -> Option<(PathBuf, PathBuf, PathBuf)> {
+    check_hf_cache(model_id).or_else(|| check_custom_cache(model_id))
+}
+
 /// Download embedding model files directly via HTTP from HuggingFace CDN.
 /// Falls back from hf-hub API to direct HTTP download.
 pub async fn download_embedding_model(
@@ -73,22 +186,22 @@ pub async fn download_embedding_model(
 
     // 1. Check hf-hub cache
     if let Some(paths) = check_hf_cache(model_id) {
-        eprintln!("Using hf-hub cached embedding model");
+        info!("Using hf-hub cached embedding model");
         return Ok(paths);
     }
 
     // 2. Check custom cache
     if let Some(paths) = check_custom_cache(model_id) {
-        eprintln!("Using custom cached embedding model");
+        info!("Using custom cached embedding model");
         return Ok(paths);
     }
 
     // 3. Try hf-hub API download first
-    eprintln!("Attempting hf-hub API download for embedding model...");
+    info!("Attempting hf-hub API download for embedding model...");
     match try_hf_hub_download(model_id) {
         Ok(paths) => return Ok(paths),
         Err(e) => {
-            eprintln!(
+            warn!(
                 "hf-hub download failed ({}), falling back to direct HTTP...",
                 e
             );
@@ -96,7 +209,7 @@ pub async fn download_embedding_model(
     }
 
     // 4. Direct HTTP download from HuggingFace CDN
-    eprintln!("Downloading embedding model via direct HTTP...");
+    info!("Downloading embedding model via direct HTTP...");
     let cache_dir = get_custom_cache_dir(model_id)?;
     std::fs::create_dir_all(&cache_dir)
         .with_context(|| format!("Failed to create cache dir: {}", cache_dir.display()))?;
@@ -107,12 +220,12 @@ pub async fn download_embedding_model(
     for filename in &MODEL_FILES {
         let dest = cache_dir.join(filename);
         if dest.exists() {
-            eprintln!("  {} already downloaded", filename);
+            debug!("{} already downloaded", filename);
             continue;
         }
 
         let url = format!("{}/{}", base_url, filename);
-        eprintln!("  Downloading {}...", filename);
+        info!("Downloading {}...", filename);
 
         let response = client
             .get(&url)
@@ -138,10 +251,14 @@ pub async fn download_embedding_model(
         std::fs::write(&dest, &bytes)
             .with_context(|| format!("Failed to write {}", dest.display()))?;
 
-        eprintln!("  Downloaded {} ({:.2} MB)", filename, bytes.len() as f64 / 1_048_576.0);
+        info!(
+            "Downloaded {} ({:.2} MB)",
+            filename,
+            bytes.len() as f64 / 1_048_576.0
+        );
     }
 
-    eprintln!("Embedding model download complete");
+    info!("Embedding model download complete");
     Ok((
         cache_dir.join("config.json"),
         cache_dir.join("tokenizer.json"),
@@ -162,6 +279,7 @@ fn try_hf_hub_download(model_id: &str) -> Result<(PathBuf, PathBuf, PathBuf)> {
 
 impl EmbeddingEngine {
     /// Create a new embedding engine from pre-downloaded file paths
+    #[tracing::instrument(skip_all, fields(model = %model_id))]
     pub fn from_paths(
         model_id: &str,
         config_path: &Path,
@@ -170,15 +288,30 @@ impl EmbeddingEngine {
     ) -> Result<Self> {
         let config_str = std::fs::read_to_string(config_path)?;
         let config: Config = serde_json::from_str(&config_str)?;
+        // Models outside the registry get mean pooling and no prefixes
+        let option = find_embedding_model(model_id);
+        let dimensions = serde_json::from_str::<serde_json::Value>(&config_str)?["hidden_size"]
+            .as_u64()
+            .map(|size| size as usize)
+            .or(option.as_ref().map(|o| o.dimensions))
+            .unwrap_or(EMBEDDING_DIMENSIONS);
+        let pooling = option.as_ref().map_or(Pooling::Mean, |o| o.pooling);
+        let query_prefix = option.as_ref().and_then(|o| o.query_prefix.clone());
+        let passage_prefix = option.and_then(|o| o.passage_prefix);
+        let weights_bytes = std::fs::metadata(weights_path).map_or(0, |m| m.len());
 
         let tokenizer = Tokenizer::from_file(tokenizer_path)
             .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
 
-        // Try Metal first, fall back to CPU if forward pass fails
-        let metal_device = Device::new_metal(0).ok();
+        // Try Metal first (unless the user forced the CPU), fall back to CPU if forward pass fails
+        let metal_device = if settings::current().compute.allow_gpu() {
+            Device::new_metal(0).ok()
+        } else {
+            None
+        };
 
         if let Some(ref device) = metal_device {
-            eprintln!("[RAG] Attempting Metal GPU for embedding model '{}'", model_id);
+            info!("Attempting Metal GPU for embedding model '{}'", model_id);
             // Use F32 for Metal — upstream BERT hardcodes F32 attention masks (candle-transformers bert.rs:508)
             // which causes dtype mismatch with F16 weights. Metal kernels support F32 natively.
             let vb = unsafe {
@@ -190,36 +323,48 @@ impl EmbeddingEngine {
             let test_input = Tensor::zeros((1, 1), candle_core::DType::U32, device)?;
             match model.forward(&test_input, &test_input, None) {
                 Ok(_) => {
-                    eprintln!("[RAG] Metal GPU works, using Metal for embeddings (F32)");
+                    info!("Metal GPU works, using Metal for embeddings (F32)");
                     return Ok(Self {
                         model,
                         tokenizer,
                         device: device.clone(),
                         model_id: model_id.to_string(),
+                        dimensions,
+                        pooling,
+                        query_prefix,
+                        passage_prefix,
+                        weights_bytes,
+                        last_used: AtomicU64::new(now_secs()),
                     });
                 }
                 Err(e) => {
-                    eprintln!("[RAG] Metal forward pass failed ({}), falling back to CPU", e);
+                    warn!("Metal forward pass failed ({}), falling back to CPU", e);
                 }
             }
         }
 
         // CPU fallback
         let device = Device::Cpu;
-        eprintln!("[RAG] Loading embedding model '{}' on CPU", model_id);
+        info!("Loading embedding model '{}' on CPU", model_id);
 
         let vb = unsafe {
             VarBuilder::from_mmaped_safetensors(&[weights_path.to_path_buf()], DTYPE, &device)?
         };
         let model = BertModel::load(vb, &config)?;
 
-        eprintln!("[RAG] Embedding model loaded successfully on CPU");
+        info!("Embedding model loaded successfully on CPU");
 
         Ok(Self {
             model,
             tokenizer,
             device,
             model_id: model_id.to_string(),
+            dimensions,
+            pooling,
+            query_prefix,
+            passage_prefix,
+            weights_bytes,
+            last_used: AtomicU64::new(now_secs()),
         })
     }
 
@@ -228,8 +373,7 @@ impl EmbeddingEngine {
         let model_id = model_id.unwrap_or(DEFAULT_EMBEDDING_MODEL);
 
         // Try hf-hub cache, then custom cache
-        let (config_path, tokenizer_path, weights_path) = check_hf_cache(model_id)
-            .or_else(|| check_custom_cache(model_id))
+        let (config_path, tokenizer_path, weights_path) = cached_model_files(model_id)
             .ok_or_else(|| {
                 anyhow!(
                     "Embedding model not found in cache. Use init_rag to download it first."
@@ -248,11 +392,35 @@ impl EmbeddingEngine {
             .ok_or_else(|| anyhow!("No embedding generated"))
     }
 
+    /// Generate the embedding for a search query (uses the model's query prefix, if any)
+    pub fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let text = match &self.query_prefix {
+            Some(prefix) => format!("{}{}", prefix, query),
+            None => query.to_string(),
+        };
+        self.encode(&[&text])?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No embedding generated"))
+    }
+
     /// Generate embeddings for multiple texts
     pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        match &self.passage_prefix {
+            Some(prefix) => {
+                let prefixed: Vec<String> = texts.iter().map(|t| format!("{}{}", prefix, t)).collect();
+                let refs: Vec<&str> = prefixed.iter().map(String::as_str).collect();
+                self.encode(&refs)
+            }
+            None => self.encode(texts),
+        }
+    }
+
+    fn encode(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
         if texts.is_empty() {
             return Ok(vec![]);
         }
+        self.last_used.store(now_secs(), Ordering::Relaxed);
 
         // Tokenize all texts
         let encodings = self
@@ -310,39 +478,45 @@ impl EmbeddingEngine {
             .model
             .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
 
-        // Mean pooling over sequence length (with attention mask)
-        let attention_mask_expanded = attention_mask
-            .unsqueeze(2)?
-            .to_dtype(embeddings.dtype())?
-            .broadcast_as(embeddings.shape())?;
-
-        let masked_embeddings = embeddings.mul(&attention_mask_expanded)?;
-        let sum_embeddings = masked_embeddings.sum(1)?;
-
-        // Create attention sum for division
-        let attention_sum = Tensor::from_vec(
-            attention_mask_vec
-                .iter()
-                .map(|&x| x as f32)
-                .collect::<Vec<_>>(),
-            (batch_size, max_len),
-            &self.device,
-        )?
-        .sum(1)?
-        .unsqueeze(1)?
-        .to_dtype(embeddings.dtype())?
-        .broadcast_as(sum_embeddings.shape())?;
-
-        let mean_embeddings = sum_embeddings.div(&attention_sum)?;
+        let pooled = match self.pooling {
+            Pooling::Mean => {
+                // Mean pooling over sequence length (with attention mask)
+                let attention_mask_expanded = attention_mask
+                    .unsqueeze(2)?
+                    .to_dtype(embeddings.dtype())?
+                    .broadcast_as(embeddings.shape())?;
+
+                let masked_embeddings = embeddings.mul(&attention_mask_expanded)?;
+                let sum_embeddings = masked_embeddings.sum(1)?;
+
+                // Create attention sum for division
+                let attention_sum = Tensor::from_vec(
+                    attention_mask_vec
+                        .iter()
+                        .map(|&x| x as f32)
+                        .collect::<Vec<_>>(),
+                    (batch_size, max_len),
+                    &self.device,
+                )?
+                .sum(1)?
+                .unsqueeze(1)?
+                .to_dtype(embeddings.dtype())?
+                .broadcast_as(sum_embeddings.shape())?;
+
+                sum_embeddings.div(&attention_sum)?
+            }
+            // First token of every sequence
+            Pooling::Cls => embeddings.narrow(1, 0, 1)?.squeeze(1)?,
+        };
 
         // Normalize embeddings (L2 normalization)
-        let norms = mean_embeddings
+        let norms = pooled
             .sqr()?
             .sum(1)?
             .sqrt()?
             .unsqueeze(1)?
-            .broadcast_as(mean_embeddings.shape())?;
-        let normalized = mean_embeddings.div(&norms)?;
+            .broadcast_as(pooled.shape())?;
+        let normalized = pooled.div(&norms)?;
 
         // Convert to Vec<Vec<f32>>
         let normalized_cpu = normalized.to_device(&Device::Cpu)?.to_dtype(DType::F32)?;
@@ -373,14 +547,23 @@ impl EmbeddingEngine {
 
     /// Get embedding dimensions
     pub fn dimensions(&self) -> usize {
-        EMBEDDING_DIMENSIONS
+        self.dimensions
+    }
+
+    /// Approximate memory held by the model weights
+    pub fn weights_bytes(&self) -> u64 {
+        self.weights_bytes
+    }
+
+    /// Time since the engine last encoded anything
+    pub fn idle_for(&self) -> Duration {
+        Duration::from_secs(now_secs().saturating_sub(self.last_used.load(Ordering::Relaxed)))
     }
 }
 
 /// Check if the embedding model is downloaded (local cache only, no network)
 pub fn is_model_downloaded(model_id: Option<&str>) -> bool {
-    let model_id = model_id.unwrap_or(DEFAULT_EMBEDDING_MODEL);
-    check_hf_cache(model_id).is_some() || check_custom_cache(model_id).is_some()
+    cached_model_files(model_id.unwrap_or(DEFAULT_EMBEDDING_MODEL)).is_some()
 }
 
 /// Get the cache path for embedding models
@@ -398,6 +581,14 @@ mod tests {
         assert_eq!(EMBEDDING_DIMENSIONS, 384);
     }
 
+    #[test]
+    fn test_embedding_model_registry() {
+        let default = find_embedding_model(DEFAULT_EMBEDDING_MODEL).unwrap();
+        assert_eq!(default.dimensions, EMBEDDING_DIMENSIONS);
+        assert_eq!(find_embedding_model("BAAI/bge-base-en-v1.5").unwrap().dimensions, 768);
+        assert!(find_embedding_model("unknown/model").is_none());
+    }
+
     // Integration test - requires model download
     #[test]
     #[ignore]
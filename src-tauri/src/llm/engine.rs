@@ -1,13 +1,21 @@
 use anyhow::{anyhow, Result};
 use llama_cpp_2::context::params::LlamaContextParams;
+use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::llama_backend::LlamaBackend;
 use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::params::LlamaModelParams;
 use llama_cpp_2::model::LlamaModel;
 use llama_cpp_2::sampling::LlamaSampler;
+use llama_cpp_2::token::LlamaToken;
+use serde::{Deserialize, Serialize};
 use std::num::NonZeroU32;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Once};
+use tracing::{debug, info};
+
+use super::compute::ComputeSettings;
+use super::summarizer::INDEXING_CACHE_KEYS;
 
 /// Default generation parameters
 const DEFAULT_MAX_TOKENS: u32 = 256;
@@ -15,6 +23,15 @@ const DEFAULT_TEMPERATURE: f32 = 0.7;
 const DEFAULT_TOP_P: f32 = 0.9;
 const DEFAULT_CONTEXT_SIZE: u32 = 4096;
 const DEFAULT_BATCH_SIZE: u32 = 512;
+/// Layers offloaded when there's no limit; more than any supported model has
+const ALL_GPU_LAYERS: u32 = 100;
+
+/// Idle contexts kept alive for prompt-prefix reuse (each holds a full KV cache). Indexing
+/// runs every prompt in `INDEXING_CACHE_KEYS` on each email, so the pool holds all of them
+/// plus a chat and a compose context; any fewer and each pass evicts the next one's prefix.
+const MAX_CACHED_CONTEXTS: usize = INDEXING_CACHE_KEYS.len() + 2;
+/// Cache key used when a generation doesn't set one
+const DEFAULT_CACHE_KEY: &str = "default";
 
 /// Global singleton for the LlamaBackend (can only be initialized once per process)
 static BACKEND_INIT: Once = Once::new();
@@ -44,10 +61,155 @@ fn get_backend() -> Result<Arc<LlamaBackend>> {
         .ok_or_else(|| anyhow!("LlamaBackend not initialized"))
 }
 
+/// Whether this llama.cpp build can offload layers to a GPU
+pub fn gpu_offload_supported() -> bool {
+    get_backend().is_ok_and(|backend| backend.supports_gpu_offload())
+}
+
+/// Runtime parameters applied when a model is loaded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmSettings {
+    /// Context window in tokens (prompt + generated)
+    pub n_ctx: u32,
+    /// Tokens decoded per batch while processing the prompt
+    pub n_batch: u32,
+    /// CPU threads for generation (`None` uses llama.cpp's default)
+    pub n_threads: Option<u32>,
+    /// Layers offloaded to the GPU (`None` offloads all of them). Forced to 0 when the
+    /// compute device preference is CPU.
+    pub gpu_layers: Option<u32>,
+    /// Sampling temperature for free-form writing (compose and chat). Summaries, rewrites and
+    /// classification keep their own low temperatures so results stay consistent.
+    pub temperature: f32,
+    /// Nucleus sampling cutoff for free-form writing
+    pub top_p: f32,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            n_ctx: DEFAULT_CONTEXT_SIZE,
+            n_batch: DEFAULT_BATCH_SIZE,
+            n_threads: None,
+            gpu_layers: None,
+            temperature: DEFAULT_TEMPERATURE,
+            top_p: DEFAULT_TOP_P,
+        }
+    }
+}
+
+impl LlmSettings {
+    pub fn validate(&self) -> Result<()> {
+        if !(512..=32768).contains(&self.n_ctx) {
+            return Err(anyhow!("Context size must be between 512 and 32768 tokens"));
+        }
+        if self.n_batch < 32 || self.n_batch > self.n_ctx {
+            return Err(anyhow!("Batch size must be between 32 and the context size"));
+        }
+        if self.n_threads == Some(0) {
+            return Err(anyhow!("Thread count must be at least 1"));
+        }
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(anyhow!("Temperature must be between 0 and 2"));
+        }
+        if !(self.top_p > 0.0 && self.top_p <= 1.0) {
+            return Err(anyhow!("Top-p must be greater than 0 and at most 1"));
+        }
+        Ok(())
+    }
+
+    /// Layers to offload, after applying the compute device preference
+    pub fn effective_gpu_layers(&self, compute: &ComputeSettings) -> u32 {
+        if compute.allow_gpu() {
+            self.gpu_layers.unwrap_or(ALL_GPU_LAYERS)
+        } else {
+            0
+        }
+    }
+}
+
+/// A context kept alive between generations, with the tokens currently in its KV cache
+struct CachedContext {
+    /// Borrows `LlmEngine::model`; see `LlmEngine::checkout_context`
+    ctx: LlamaContext<'static>,
+    tokens: Vec<LlamaToken>,
+}
+
+/// Idle values by cache key, least recently used first
+struct ContextPool<T> {
+    entries: Vec<(String, T)>,
+    capacity: usize,
+}
+
+impl<T> ContextPool<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Remove and return the value for `key`
+    fn take(&mut self, key: &str) -> Option<T> {
+        let idx = self.entries.iter().position(|(k, _)| k == key)?;
+        Some(self.entries.remove(idx).1)
+    }
+
+    /// Add a value as the most recently used, evicting the least recently used one when full
+    fn put(&mut self, key: String, value: T) {
+        self.entries.push((key, value));
+        if self.entries.len() > self.capacity {
+            self.entries.remove(0);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Number of leading tokens `cached` and `tokens` have in common
+fn shared_prefix<T: PartialEq>(cached: &[T], tokens: &[T]) -> usize {
+    cached
+        .iter()
+        .zip(tokens)
+        .take_while(|(cached_token, token)| cached_token == token)
+        .count()
+}
+
 /// LLM Engine for text generation with Metal acceleration
 pub struct LlmEngine {
+    /// Idle contexts by cache key, least recently used first. Declared before `model` so
+    /// the contexts are dropped before the model they borrow.
+    contexts: Mutex<ContextPool<CachedContext>>,
     model: Arc<LlamaModel>,
     backend: Arc<LlamaBackend>,
+    settings: LlmSettings,
+}
+
+/// Cooperative cancellation flag shared between a generation and whoever may stop it.
+/// Clones share the same flag; the engine checks it before every token.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Whether both tokens share the same flag
+    pub fn same_as(&self, other: &CancellationToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
 }
 
 /// Generation parameters
@@ -57,6 +219,11 @@ pub struct GenerationParams {
     pub temperature: f32,
     pub top_p: f32,
     pub stop_sequences: Vec<String>,
+    /// When set to true, generation stops after the current token and returns what it has
+    pub cancel: Option<CancellationToken>,
+    /// Generations with the same key share a context and skip re-decoding the prompt prefix
+    /// they have in common (e.g. the system prompt). Use one key per prompt template.
+    pub cache_key: Option<String>,
 }
 
 impl Default for GenerationParams {
@@ -66,52 +233,120 @@ impl Default for GenerationParams {
             temperature: DEFAULT_TEMPERATURE,
             top_p: DEFAULT_TOP_P,
             stop_sequences: vec![],
+            cancel: None,
+            cache_key: None,
         }
     }
 }
 
 impl LlmEngine {
     /// Create a new LlmEngine by loading a model from the given path
-    /// Automatically uses Metal acceleration on macOS
-    pub fn new(model_path: &Path) -> Result<Self> {
+    /// Offloads layers to the GPU (Metal on macOS) as allowed by `settings` and `ComputeSettings`
+    pub fn new(model_path: &Path, settings: LlmSettings) -> Result<Self> {
         // Get the singleton backend (initialized once per process)
         let backend = get_backend()?;
 
         // Configure model parameters
         // Metal/GPU acceleration is enabled by default on macOS but we need to explicitly offload layers
-        let model_params = LlamaModelParams::default()
-            .with_n_gpu_layers(100); // Offload all layers to GPU
+        let gpu_layers = settings.effective_gpu_layers(&crate::settings::current().compute);
+        info!("Offloading up to {} layers to the GPU", gpu_layers);
+        let model_params = LlamaModelParams::default().with_n_gpu_layers(gpu_layers);
 
         // Load the model
         let model = LlamaModel::load_from_file(&backend, model_path, &model_params)
             .map_err(|e| anyhow!("Failed to load model: {:?}", e))?;
 
         Ok(Self {
+            contexts: Mutex::new(ContextPool::new(MAX_CACHED_CONTEXTS)),
             model: Arc::new(model),
             backend,
+            settings,
         })
     }
 
+    /// The runtime parameters this engine was loaded with
+    pub fn settings(&self) -> &LlmSettings {
+        &self.settings
+    }
+
+    /// Take the idle context for `key`, or create a new one
+    fn checkout_context(&self, key: &str) -> Result<CachedContext> {
+        if let Some(cached) = self.contexts.lock().unwrap().take(key) {
+            return Ok(cached);
+        }
+
+        // Create context with explicit n_batch to prevent decode assertion failures
+        let mut ctx_params = LlamaContextParams::default()
+            .with_n_ctx(NonZeroU32::new(self.settings.n_ctx))
+            .with_n_batch(self.settings.n_batch);
+        if let Some(threads) = self.settings.n_threads {
+            ctx_params = ctx_params
+                .with_n_threads(threads as i32)
+                .with_n_threads_batch(threads as i32);
+        }
+
+        let ctx = self
+            .model
+            .new_context(&self.backend, ctx_params)
+            .map_err(|e| anyhow!("Failed to create context: {:?}", e))?;
+
+        // SAFETY: the context borrows `self.model`, which is owned by this engine. Contexts
+        // never leave the engine: they live either in `self.contexts` (dropped before `model`,
+        // see field order) or on the stack of a `&self` method.
+        let ctx = unsafe { std::mem::transmute::<LlamaContext<'_>, LlamaContext<'static>>(ctx) };
+
+        Ok(CachedContext {
+            ctx,
+            tokens: Vec::new(),
+        })
+    }
+
+    /// Return a context to the pool, evicting the least recently used one when full
+    fn checkin_context(&self, key: String, cached: CachedContext) {
+        self.contexts.lock().unwrap().put(key, cached);
+    }
+
+    /// Drop all cached contexts (frees their KV caches)
+    pub fn clear_context_cache(&self) {
+        self.contexts.lock().unwrap().clear();
+    }
+
     /// Generate text with streaming callback
     pub fn generate_stream<F>(
         &self,
         prompt: &str,
         params: &GenerationParams,
-        mut on_token: F,
+        on_token: F,
     ) -> Result<String>
     where
         F: FnMut(&str),
     {
-        // Create context with explicit n_batch to prevent decode assertion failures
-        let ctx_params = LlamaContextParams::default()
-            .with_n_ctx(NonZeroU32::new(DEFAULT_CONTEXT_SIZE))
-            .with_n_batch(DEFAULT_BATCH_SIZE);
+        let key = params
+            .cache_key
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CACHE_KEY.to_string());
+        let mut cached = self.checkout_context(&key)?;
 
-        let mut ctx = self
-            .model
-            .new_context(&self.backend, ctx_params)
-            .map_err(|e| anyhow!("Failed to create context: {:?}", e))?;
+        let result = self.generate_with_context(&mut cached, prompt, params, on_token);
+
+        // A failed decode leaves the KV cache in an unknown state; only keep good contexts
+        if result.is_ok() {
+            self.checkin_context(key, cached);
+        }
+        result
+    }
 
+    #[tracing::instrument(name = "generate", skip_all)]
+    fn generate_with_context<F>(
+        &self,
+        cached: &mut CachedContext,
+        prompt: &str,
+        params: &GenerationParams,
+        mut on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
         // Tokenize the prompt
         let tokens = self
             .model
@@ -119,10 +354,10 @@ impl LlmEngine {
             .map_err(|e| anyhow!("Failed to tokenize: {:?}", e))?;
 
         // Ensure prompt tokens fit within context window (leave room for generation)
-        let max_prompt_tokens = (DEFAULT_CONTEXT_SIZE as usize).saturating_sub(params.max_tokens as usize + 16);
+        let max_prompt_tokens = (self.settings.n_ctx as usize).saturating_sub(params.max_tokens as usize + 16);
         let tokens = if tokens.len() > max_prompt_tokens {
-            println!(
-                "[AI] Prompt too long ({} tokens), truncating to {} tokens",
+            info!(
+                "Prompt too long ({} tokens), truncating to {} tokens",
                 tokens.len(),
                 max_prompt_tokens
             );
@@ -135,27 +370,56 @@ impl LlmEngine {
             return Err(anyhow!("Prompt produced no tokens"));
         }
 
-        // Process prompt in chunks of batch_size to avoid exceeding n_batch
-        let batch_size = DEFAULT_BATCH_SIZE as usize;
-        let num_chunks = (tokens.len() + batch_size - 1) / batch_size;
+        // Reuse the prefix already in the KV cache. At least the last prompt token is always
+        // decoded again so sampling starts from fresh logits.
+        let mut reused = shared_prefix(&cached.tokens, &tokens).min(tokens.len() - 1);
+
+        if reused < cached.tokens.len() {
+            // Drop cached positions past the shared prefix. Models with recurrent state can't
+            // remove a partial range, so start over with an empty cache.
+            let removed = cached
+                .ctx
+                .clear_kv_cache_seq(Some(0), Some(reused as u32), None)
+                .unwrap_or(false);
+            if !removed {
+                cached.ctx.clear_kv_cache();
+                reused = 0;
+            }
+        }
+        cached.tokens.truncate(reused);
+
+        if reused > 0 {
+            debug!(
+                "Reusing {} of {} prompt tokens from cache",
+                reused,
+                tokens.len()
+            );
+        }
 
-        for chunk_idx in 0..num_chunks {
-            let start = chunk_idx * batch_size;
-            let end = (start + batch_size).min(tokens.len());
+        // Process the rest of the prompt in chunks of batch_size to avoid exceeding n_batch
+        let batch_size = self.settings.n_batch as usize;
+        let pending = &tokens[reused..];
+        let num_chunks = pending.len().div_ceil(batch_size);
+
+        for (chunk_idx, chunk) in pending.chunks(batch_size).enumerate() {
+            let start = reused + chunk_idx * batch_size;
             let is_last_chunk = chunk_idx == num_chunks - 1;
 
             let mut batch = LlamaBatch::new(batch_size, 1);
 
-            for (i, token) in tokens[start..end].iter().enumerate() {
+            for (i, token) in chunk.iter().enumerate() {
                 let pos = (start + i) as i32;
-                let is_last = is_last_chunk && i == (end - start - 1);
+                let is_last = is_last_chunk && i == chunk.len() - 1;
                 batch
                     .add(*token, pos, &[0], is_last)
                     .map_err(|e| anyhow!("Failed to add token to batch: {:?}", e))?;
             }
 
-            ctx.decode(&mut batch)
+            cached
+                .ctx
+                .decode(&mut batch)
                 .map_err(|e| anyhow!("Failed to decode prompt chunk {}/{}: {:?}", chunk_idx + 1, num_chunks, e))?;
+            cached.tokens.extend_from_slice(chunk);
         }
 
         // Create sampler chain with temperature and top_p
@@ -169,7 +433,7 @@ impl LlmEngine {
         // Generate tokens
         let mut output = String::new();
         let mut n_cur = tokens.len();
-        let max_ctx = DEFAULT_CONTEXT_SIZE as usize;
+        let max_ctx = self.settings.n_ctx as usize;
         let mut batch = LlamaBatch::new(1, 1);
 
         for _ in 0..params.max_tokens {
@@ -178,8 +442,14 @@ impl LlmEngine {
                 break;
             }
 
+            // Stop if the caller cancelled generation
+            if params.cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+                info!("Generation cancelled after {} tokens", n_cur - tokens.len());
+                break;
+            }
+
             // Sample the next token from the last logit position
-            let new_token = sampler.sample(&ctx, -1);
+            let new_token = sampler.sample(&cached.ctx, -1);
 
             // Accept the token
             sampler.accept(new_token);
@@ -218,8 +488,11 @@ impl LlmEngine {
             n_cur += 1;
 
             // Decode next token
-            ctx.decode(&mut batch)
+            cached
+                .ctx
+                .decode(&mut batch)
                 .map_err(|e| anyhow!("Failed to decode: {:?}", e))?;
+            cached.tokens.push(new_token);
         }
 
         Ok(output.trim().to_string())
@@ -230,6 +503,14 @@ impl LlmEngine {
         self.generate_stream(prompt, params, |_| {})
     }
 
+    /// Number of tokens `text` encodes to (with the BOS token, as prompts are)
+    pub fn token_count(&self, text: &str) -> Result<usize> {
+        self.model
+            .str_to_token(text, llama_cpp_2::model::AddBos::Always)
+            .map(|tokens| tokens.len())
+            .map_err(|e| anyhow!("Failed to tokenize: {:?}", e))
+    }
+
     /// Generate with default parameters
     pub fn generate_simple(&self, prompt: &str) -> Result<String> {
         self.generate(prompt, &GenerationParams::default())
@@ -243,11 +524,86 @@ unsafe impl Sync for LlmEngine {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::compute::DevicePreference;
 
     #[test]
     fn test_generation_params_default() {
         let params = GenerationParams::default();
         assert_eq!(params.max_tokens, DEFAULT_MAX_TOKENS);
         assert!((params.temperature - DEFAULT_TEMPERATURE).abs() < f32::EPSILON);
+        assert!(params.cancel.is_none());
+    }
+
+    #[test]
+    fn test_llm_settings_validation() {
+        let settings = LlmSettings::default();
+        assert!(settings.validate().is_ok());
+
+        let too_big_batch = LlmSettings {
+            n_batch: settings.n_ctx * 2,
+            ..settings.clone()
+        };
+        assert!(too_big_batch.validate().is_err());
+
+        let no_threads = LlmSettings {
+            n_threads: Some(0),
+            ..settings.clone()
+        };
+        assert!(no_threads.validate().is_err());
+    }
+
+    #[test]
+    fn test_effective_gpu_layers() {
+        let settings = LlmSettings {
+            gpu_layers: Some(8),
+            ..Default::default()
+        };
+        let mut compute = ComputeSettings::default();
+        assert_eq!(settings.effective_gpu_layers(&compute), 8);
+        assert_eq!(LlmSettings::default().effective_gpu_layers(&compute), ALL_GPU_LAYERS);
+
+        compute.device = DevicePreference::Cpu;
+        assert_eq!(settings.effective_gpu_layers(&compute), 0);
+    }
+
+    #[test]
+    fn test_indexing_batch_reuses_prompt_prefixes() {
+        const SYSTEM_TOKENS: u32 = 50;
+        // Each pass has its own system prompt, followed by the email
+        let prompt = |pass: u32, email: u32| -> Vec<u32> {
+            let mut tokens: Vec<u32> = (0..SYSTEM_TOKENS).map(|t| pass * 1000 + t).collect();
+            tokens.extend([100_000 + email, 200_000 + email]);
+            tokens
+        };
+
+        let mut pool: ContextPool<Vec<u32>> = ContextPool::new(MAX_CACHED_CONTEXTS);
+        for email in 0..3 {
+            for (pass, key) in INDEXING_CACHE_KEYS.iter().enumerate() {
+                let tokens = prompt(pass as u32, email);
+                let cached = pool.take(key).unwrap_or_default();
+                let reused = shared_prefix(&cached, &tokens);
+                if email == 0 {
+                    assert_eq!(reused, 0, "{}", key);
+                } else {
+                    assert_eq!(reused, SYSTEM_TOKENS as usize, "email {} {}", email, key);
+                }
+                pool.put(key.to_string(), tokens);
+            }
+            // A chat and a compose between emails don't push indexing prompts out
+            pool.put("chat:1".to_string(), vec![1, 2, 3]);
+            pool.put("compose".to_string(), vec![4, 5, 6]);
+        }
+    }
+
+    #[test]
+    fn test_cancellation_token_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+        assert!(token.same_as(&clone));
+        assert!(!token.same_as(&CancellationToken::new()));
     }
 }
@@ -0,0 +1,210 @@
+//! Minimal GGUF metadata reader
+//!
+//! Reads the key/value header of a GGUF file without loading the model, so imported files
+//! can be validated and their prompt format detected. Only string values are kept; every
+//! other value (including the large tokenizer arrays) is skipped.
+
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Longest string accepted from the header; guards against corrupt length fields
+const MAX_STRING_LEN: u64 = 16 * 1024 * 1024;
+
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+
+/// String metadata from a GGUF header
+#[derive(Debug, Clone, Default)]
+pub struct GgufMetadata {
+    pub version: u32,
+    strings: HashMap<String, String>,
+}
+
+impl GgufMetadata {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    /// `general.architecture`, e.g. `llama`, `qwen2`, `lfm2`
+    pub fn architecture(&self) -> Option<&str> {
+        self.get("general.architecture")
+    }
+
+    /// `general.name`, the model's display name if the file sets one
+    pub fn name(&self) -> Option<&str> {
+        self.get("general.name")
+    }
+
+    /// The Jinja chat template embedded by the converter
+    pub fn chat_template(&self) -> Option<&str> {
+        self.get("tokenizer.chat_template")
+    }
+}
+
+/// Read the metadata of a GGUF file, failing if it isn't one
+pub fn read_metadata(path: &Path) -> Result<GgufMetadata> {
+    let file = std::fs::File::open(path)?;
+    read_metadata_from(&mut BufReader::new(file))
+}
+
+pub fn read_metadata_from<R: Read + Seek>(reader: &mut R) -> Result<GgufMetadata> {
+    let mut magic = [0u8; 4];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| anyhow!("File is too small to be a GGUF model"))?;
+    if &magic != GGUF_MAGIC {
+        bail!("Not a GGUF file");
+    }
+
+    let version = read_u32(reader)?;
+    if version < 2 {
+        bail!("Unsupported GGUF version {}", version);
+    }
+    let _tensor_count = read_u64(reader)?;
+    let kv_count = read_u64(reader)?;
+
+    let mut strings = HashMap::new();
+    for _ in 0..kv_count {
+        let key = read_string(reader)?;
+        match read_u32(reader)? {
+            TYPE_STRING => {
+                strings.insert(key, read_string(reader)?);
+            }
+            value_type => skip_value(reader, value_type)?,
+        }
+    }
+
+    Ok(GgufMetadata { version, strings })
+}
+
+/// Byte size of a fixed-size value type
+fn scalar_size(value_type: u32) -> Result<u64> {
+    Ok(match value_type {
+        0 | 1 | 7 => 1, // u8, i8, bool
+        2 | 3 => 2,     // u16, i16
+        4..=6 => 4,     // u32, i32, f32
+        10..=12 => 8,   // u64, i64, f64
+        other => bail!("Unknown GGUF value type {}", other),
+    })
+}
+
+fn skip_value<R: Read + Seek>(reader: &mut R, value_type: u32) -> Result<()> {
+    match value_type {
+        TYPE_STRING => {
+            let len = read_u64(reader)?;
+            skip(reader, len)
+        }
+        TYPE_ARRAY => {
+            let item_type = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            if item_type == TYPE_STRING || item_type == TYPE_ARRAY {
+                for _ in 0..len {
+                    skip_value(reader, item_type)?;
+                }
+                Ok(())
+            } else {
+                let size = scalar_size(item_type)?
+                    .checked_mul(len)
+                    .ok_or_else(|| anyhow!("Corrupt GGUF array length"))?;
+                skip(reader, size)
+            }
+        }
+        other => skip(reader, scalar_size(other)?),
+    }
+}
+
+fn skip<R: Seek>(reader: &mut R, bytes: u64) -> Result<()> {
+    let offset = i64::try_from(bytes).map_err(|_| anyhow!("Corrupt GGUF length"))?;
+    reader.seek(SeekFrom::Current(offset))?;
+    Ok(())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING_LEN {
+        bail!("Corrupt GGUF string length {}", len);
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn push_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend_from_slice(&(s.len() as u64).to_le_bytes());
+        buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn header(kv_count: u64) -> Vec<u8> {
+        let mut buf = GGUF_MAGIC.to_vec();
+        buf.extend_from_slice(&3u32.to_le_bytes());
+        buf.extend_from_slice(&0u64.to_le_bytes());
+        buf.extend_from_slice(&kv_count.to_le_bytes());
+        buf
+    }
+
+    #[test]
+    fn test_reads_strings_and_skips_other_values() {
+        let mut buf = header(4);
+
+        push_string(&mut buf, "general.architecture");
+        buf.extend_from_slice(&TYPE_STRING.to_le_bytes());
+        push_string(&mut buf, "qwen2");
+
+        push_string(&mut buf, "qwen2.context_length");
+        buf.extend_from_slice(&4u32.to_le_bytes());
+        buf.extend_from_slice(&32768u32.to_le_bytes());
+
+        push_string(&mut buf, "tokenizer.ggml.tokens");
+        buf.extend_from_slice(&TYPE_ARRAY.to_le_bytes());
+        buf.extend_from_slice(&TYPE_STRING.to_le_bytes());
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        push_string(&mut buf, "<|im_start|>");
+        push_string(&mut buf, "hello");
+
+        push_string(&mut buf, "tokenizer.chat_template");
+        buf.extend_from_slice(&TYPE_STRING.to_le_bytes());
+        push_string(&mut buf, "{% for m in messages %}<|im_start|>{% endfor %}");
+
+        let metadata = read_metadata_from(&mut Cursor::new(buf)).unwrap();
+        assert_eq!(metadata.version, 3);
+        assert_eq!(metadata.architecture(), Some("qwen2"));
+        assert_eq!(
+            metadata.chat_template(),
+            Some("{% for m in messages %}<|im_start|>{% endfor %}")
+        );
+        assert_eq!(metadata.get("tokenizer.ggml.tokens"), None);
+        assert_eq!(metadata.name(), None);
+    }
+
+    #[test]
+    fn test_rejects_non_gguf() {
+        assert!(read_metadata_from(&mut Cursor::new(b"PK\x03\x04rest".to_vec())).is_err());
+        assert!(read_metadata_from(&mut Cursor::new(b"GG".to_vec())).is_err());
+
+        // Truncated header
+        let mut buf = header(1);
+        push_string(&mut buf, "general.name");
+        assert!(read_metadata_from(&mut Cursor::new(buf)).is_err());
+    }
+}
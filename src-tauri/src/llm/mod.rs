@@ -1,14 +1,26 @@
+pub mod audio;
+pub mod benchmark;
+pub mod compute;
 pub mod embeddings;
 pub mod engine;
+pub mod gguf;
 pub mod model_manager;
+pub mod prompt_format;
 pub mod rag;
+pub mod reranker;
 pub mod summarizer;
+pub mod whisper;
+pub mod worker;
 
+pub use benchmark::ModelBenchmark;
 pub use embeddings::EmbeddingEngine;
-pub use engine::{GenerationParams, LlmEngine};
+pub use engine::{CancellationToken, GenerationParams, LlmEngine, LlmSettings};
 pub use model_manager::{
     get_available_models, ModelManager, ModelOption, ModelStatus, DEFAULT_MODEL_FILE,
     DEFAULT_MODEL_REPO,
 };
+pub use prompt_format::{PromptFormat, PromptTemplate};
 pub use rag::RagEngine;
 pub use summarizer::Summarizer;
+pub use whisper::SpeechEngine;
+pub use worker::{LlmWorker, Priority};
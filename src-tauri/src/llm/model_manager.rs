@@ -1,10 +1,17 @@
-use anyhow::{anyhow, Result};
-use directories::ProjectDirs;
-use hf_hub::api::sync::Api;
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::{CONTENT_RANGE, RANGE, USER_AGENT};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use super::gguf;
+use super::prompt_format::{self, PromptFormat, PromptTemplate};
+use super::whisper::{find_speech_model, SPEECH_MODEL_FILES};
 
 /// Available model options for users to choose from
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +24,12 @@ pub struct ModelOption {
     pub description: String,
     pub min_ram_gb: u32,
     pub tokens_per_sec: String,
+    /// Imported by the user rather than part of the built-in catalog
+    #[serde(default)]
+    pub custom: bool,
+    /// Detected when a custom model is imported
+    #[serde(default)]
+    pub prompt_format: Option<PromptFormat>,
 }
 
 /// Get available models based on system specs
@@ -31,6 +44,8 @@ pub fn get_available_models() -> Vec<ModelOption> {
             description: "Fastest, most efficient. Great for email tasks.".to_string(),
             min_ram_gb: 2,
             tokens_per_sec: "200+ tok/s".to_string(),
+            custom: false,
+            prompt_format: None,
         },
         ModelOption {
             id: "lfm2.5-1.2b-q8".to_string(),
@@ -41,6 +56,8 @@ pub fn get_available_models() -> Vec<ModelOption> {
             description: "Higher quality, still very fast.".to_string(),
             min_ram_gb: 4,
             tokens_per_sec: "150+ tok/s".to_string(),
+            custom: false,
+            prompt_format: None,
         },
         ModelOption {
             id: "qwen2.5-3b-q4".to_string(),
@@ -51,6 +68,8 @@ pub fn get_available_models() -> Vec<ModelOption> {
             description: "Larger model, better reasoning.".to_string(),
             min_ram_gb: 8,
             tokens_per_sec: "70-90 tok/s".to_string(),
+            custom: false,
+            prompt_format: None,
         },
     ]
 }
@@ -59,6 +78,25 @@ pub fn get_available_models() -> Vec<ModelOption> {
 pub const DEFAULT_MODEL_REPO: &str = "LiquidAI/LFM2.5-1.2B-Instruct-GGUF";
 pub const DEFAULT_MODEL_FILE: &str = "LFM2.5-1.2B-Instruct-Q4_K_M.gguf";
 
+/// Suffix of the in-progress download; it is kept on failure so the next attempt resumes
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Custom models imported by the user, stored in the models directory
+const REGISTRY_FILE: &str = "registry.json";
+
+/// Subdirectory of the models directory speech models are kept in, one directory each
+const SPEECH_MODELS_DIR: &str = "whisper";
+
+/// Minimum progress change (in percent) between two progress callbacks
+const PROGRESS_STEP: f32 = 0.1;
+
+/// Size and SHA-256 HuggingFace reports for an LFS file, used to verify the download
+#[derive(Debug, Default)]
+struct RemoteFileInfo {
+    size: Option<u64>,
+    sha256: Option<String>,
+}
+
 /// Model download status
 #[derive(Debug, Clone, PartialEq)]
 pub enum ModelStatus {
@@ -77,6 +115,7 @@ impl Default for ModelStatus {
 }
 
 /// Manages model downloading and caching
+#[derive(Clone)]
 pub struct ModelManager {
     models_dir: PathBuf,
     status: Arc<RwLock<ModelStatus>>,
@@ -98,14 +137,7 @@ impl ModelManager {
 
     /// Get the models directory path
     fn get_models_dir() -> Result<PathBuf> {
-        if let Some(proj_dirs) = ProjectDirs::from("com", "inboxed", "inboxed") {
-            let data_dir = proj_dirs.data_dir();
-            Ok(data_dir.join("models"))
-        } else {
-            // Fallback to home directory
-            let home = std::env::var("HOME").map_err(|_| anyhow!("HOME not set"))?;
-            Ok(PathBuf::from(home).join(".inboxed").join("models"))
-        }
+        crate::paths::models_dir()
     }
 
     /// Get the full path to a model file
@@ -129,16 +161,38 @@ impl ModelManager {
         *self.status.write().await = status;
     }
 
+    /// Path of the partially downloaded file for a model
+    fn get_partial_path(&self, model_file: &str) -> PathBuf {
+        self.models_dir
+            .join(format!("{}{}", model_file, PARTIAL_SUFFIX))
+    }
+
     /// Download a model from HuggingFace
     /// Returns the path to the downloaded model file
-    pub fn download_model<F>(
+    ///
+    /// Streams into `<file>.part`, resuming from its current size with a byte-range request,
+    /// and reports progress in percent as bytes arrive. The finished file is checked against
+    /// the size and SHA-256 HuggingFace publishes before it is moved into place.
+    pub async fn download_model<F>(
         &self,
         repo_id: &str,
         filename: &str,
         on_progress: F,
     ) -> Result<PathBuf>
     where
-        F: Fn(f32) + Send + 'static,
+        F: Fn(f32) + Send + Sync + 'static,
+    {
+        let url = format!(
+            "https://huggingface.co/{}/resolve/main/{}",
+            repo_id, filename
+        );
+        self.download_file(&url, filename, on_progress).await
+    }
+
+    /// Download `url` into the models directory as `filename` (see `download_model`)
+    async fn download_file<F>(&self, url: &str, filename: &str, on_progress: F) -> Result<PathBuf>
+    where
+        F: Fn(f32) + Send + Sync + 'static,
     {
         let target_path = self.get_model_path(filename);
 
@@ -148,35 +202,50 @@ impl ModelManager {
             return Ok(target_path);
         }
 
-        // Download from HuggingFace
-        let api = Api::new()?;
-        let repo = api.model(repo_id.to_string());
-
-        // Note: hf-hub doesn't provide progress callbacks directly,
-        // so we report progress at key stages
-        on_progress(0.0);
-
-        // Download the file (this blocks until complete)
-        let downloaded_path = repo.get(filename)?;
-
-        on_progress(90.0);
+        let partial_path = self.get_partial_path(filename);
+        let remote = fetch_remote_info(url).await.unwrap_or_else(|e| {
+            warn!(
+                "Could not read file info for {} ({}), skipping hash check",
+                filename, e
+            );
+            RemoteFileInfo::default()
+        });
+
+        let downloaded = match tokio::fs::metadata(&partial_path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        // A partial file at least as large as the remote one is either complete or bogus;
+        // verification below sorts out which
+        if !matches!(remote.size, Some(size) if downloaded >= size) {
+            stream_to_file(url, &partial_path, downloaded, remote.size, &on_progress)
+                .await
+                .with_context(|| format!("Failed to download {}", filename))?;
+        }
 
-        // Copy to our models directory if not already there
-        if downloaded_path != target_path {
-            std::fs::copy(&downloaded_path, &target_path)?;
+        if let Err(e) = verify_download(&partial_path, &remote).await {
+            // A corrupt partial file would poison every future resume
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(e);
         }
 
+        tokio::fs::rename(&partial_path, &target_path)
+            .await
+            .with_context(|| format!("Failed to move {} into place", filename))?;
+
         on_progress(100.0);
 
         Ok(target_path)
     }
 
     /// Download the default model
-    pub fn download_default_model<F>(&self, on_progress: F) -> Result<PathBuf>
+    pub async fn download_default_model<F>(&self, on_progress: F) -> Result<PathBuf>
     where
-        F: Fn(f32) + Send + 'static,
+        F: Fn(f32) + Send + Sync + 'static,
     {
         self.download_model(DEFAULT_MODEL_REPO, DEFAULT_MODEL_FILE, on_progress)
+            .await
     }
 
     /// Get path to default model (if it exists)
@@ -199,9 +268,140 @@ impl ModelManager {
         &self.models_dir
     }
 
+    /// The built-in catalog followed by imported custom models
+    pub fn models(&self) -> Vec<ModelOption> {
+        let mut models = get_available_models();
+        models.extend(self.load_custom_models());
+        models
+    }
+
+    fn registry_path(&self) -> PathBuf {
+        self.models_dir.join(REGISTRY_FILE)
+    }
+
+    /// Custom models from the registry; an unreadable registry counts as empty
+    fn load_custom_models(&self) -> Vec<ModelOption> {
+        std::fs::read_to_string(self.registry_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_custom_models(&self, models: &[ModelOption]) -> Result<()> {
+        std::fs::write(self.registry_path(), serde_json::to_string_pretty(models)?)?;
+        Ok(())
+    }
+
+    /// Import a GGUF file from a local path or an http(s) URL and register it as a custom
+    /// model. The file is copied or downloaded into the models directory, checked to be a
+    /// valid GGUF, and its prompt format detected from the embedded chat template.
+    pub async fn add_custom_model<F>(
+        &self,
+        path_or_url: &str,
+        name: Option<&str>,
+        on_progress: F,
+    ) -> Result<ModelOption>
+    where
+        F: Fn(f32) + Send + Sync + 'static,
+    {
+        let source = path_or_url.trim();
+        let is_url = source.starts_with("https://") || source.starts_with("http://");
+
+        let filename = if is_url {
+            source
+                .split(['?', '#'])
+                .next()
+                .and_then(|url| url.rsplit('/').next())
+                .map(str::to_string)
+        } else {
+            Path::new(source)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(str::to_string)
+        }
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| anyhow!("Could not determine a file name from {}", source))?;
+
+        if !filename.to_lowercase().ends_with(".gguf") {
+            return Err(anyhow!("Only .gguf model files are supported"));
+        }
+        if self.get_model_path(&filename).exists()
+            || self.models().iter().any(|m| m.filename == filename)
+        {
+            return Err(anyhow!("A model file named {} already exists", filename));
+        }
+
+        let target_path = if is_url {
+            self.download_file(source, &filename, on_progress).await?
+        } else {
+            let source_path = PathBuf::from(source);
+            let check_path = source_path.clone();
+            tokio::task::spawn_blocking(move || gguf::read_metadata(&check_path))
+                .await?
+                .with_context(|| format!("{} is not a valid GGUF model", source))?;
+
+            // Copy next to the destination first so a failed copy never looks complete
+            let partial_path = self.get_partial_path(&filename);
+            on_progress(0.0);
+            tokio::fs::copy(&source_path, &partial_path)
+                .await
+                .with_context(|| format!("Failed to copy {}", source))?;
+            let target_path = self.get_model_path(&filename);
+            tokio::fs::rename(&partial_path, &target_path).await?;
+            on_progress(100.0);
+            target_path
+        };
+
+        let metadata_path = target_path.clone();
+        let metadata =
+            match tokio::task::spawn_blocking(move || gguf::read_metadata(&metadata_path)).await? {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&target_path).await;
+                    return Err(e.context(format!("{} is not a valid GGUF model", filename)));
+                }
+            };
+
+        let size_mb = (tokio::fs::metadata(&target_path).await?.len() / (1024 * 1024)) as u32;
+        let display_name = name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .or(metadata.name())
+            .unwrap_or_else(|| filename.trim_end_matches(".gguf"))
+            .to_string();
+
+        let mut custom_models = self.load_custom_models();
+        let existing_ids: Vec<String> = self.models().into_iter().map(|m| m.id).collect();
+        let model = ModelOption {
+            id: unique_model_id(&display_name, &existing_ids),
+            name: display_name,
+            repo: if is_url {
+                source.to_string()
+            } else {
+                String::new()
+            },
+            filename: filename.clone(),
+            size_mb,
+            description: match metadata.architecture() {
+                Some(arch) => format!("Custom model ({})", arch),
+                None => "Custom model".to_string(),
+            },
+            // Weights plus KV cache and runtime overhead
+            min_ram_gb: (size_mb as f32 * 1.5 / 1024.0).ceil().max(1.0) as u32,
+            tokens_per_sec: "Unknown".to_string(),
+            custom: true,
+            prompt_format: Some(PromptFormat::detect(Some(&metadata), &filename)),
+        };
+
+        custom_models.push(model.clone());
+        self.save_custom_models(&custom_models)?;
+
+        Ok(model)
+    }
+
     /// Find any downloaded model (checks all known models)
     pub fn find_any_downloaded_model(&self) -> Option<(ModelOption, PathBuf)> {
-        for model in get_available_models() {
+        for model in self.models() {
             let path = self.get_model_path(&model.filename);
             if path.exists() && path.is_file() {
                 return Some((model, path));
@@ -212,14 +412,49 @@ impl ModelManager {
 
     /// Get model by ID
     pub fn get_model_by_id(&self, model_id: &str) -> Option<ModelOption> {
-        get_available_models()
-            .into_iter()
-            .find(|m| m.id == model_id)
+        self.models().into_iter().find(|m| m.id == model_id)
+    }
+
+    /// Prompt format of a model: recorded at import for custom models, otherwise detected
+    /// from the downloaded file, or from the file name before it's downloaded
+    pub fn prompt_format(&self, model: &ModelOption) -> PromptFormat {
+        model.prompt_format.unwrap_or_else(|| {
+            let path = self.get_model_path(&model.filename);
+            if path.is_file() {
+                PromptFormat::detect_file(&path)
+            } else {
+                PromptFormat::detect(None, &model.filename)
+            }
+        })
+    }
+
+    /// The user's prompt template for a model, if they set one
+    pub fn prompt_template_override(&self, model: &ModelOption) -> Option<PromptTemplate> {
+        prompt_format::load_overrides(&self.models_dir).remove(&model.filename)
+    }
+
+    /// Set the user's prompt template for a model, or clear it with `None`
+    pub fn set_prompt_template_override(
+        &self,
+        model: &ModelOption,
+        template: Option<PromptTemplate>,
+    ) -> Result<()> {
+        let mut overrides = prompt_format::load_overrides(&self.models_dir);
+        match template {
+            Some(template) => {
+                template.validate()?;
+                overrides.insert(model.filename.clone(), template);
+            }
+            None => {
+                overrides.remove(&model.filename);
+            }
+        }
+        prompt_format::save_overrides(&self.models_dir, &overrides)
     }
 
     /// Get list of all downloaded models
     pub fn get_downloaded_models(&self) -> Vec<ModelOption> {
-        get_available_models()
+        self.models()
             .into_iter()
             .filter(|model| {
                 let path = self.get_model_path(&model.filename);
@@ -236,6 +471,17 @@ impl ModelManager {
 
         let path = self.get_model_path(&model.filename);
 
+        // Drop any interrupted download along with the model
+        let _ = std::fs::remove_file(self.get_partial_path(&model.filename));
+
+        // Custom models only exist through their file, so forget them as well
+        if model.custom {
+            let mut custom_models = self.load_custom_models();
+            custom_models.retain(|m| m.id != model.id);
+            self.save_custom_models(&custom_models)?;
+            self.set_prompt_template_override(&model, None)?;
+        }
+
         if path.exists() {
             std::fs::remove_file(&path)?;
             Ok(())
@@ -245,16 +491,247 @@ impl ModelManager {
     }
 
     /// Download a specific model by ID
-    pub fn download_model_by_id<F>(&self, model_id: &str, on_progress: F) -> Result<PathBuf>
+    pub async fn download_model_by_id<F>(&self, model_id: &str, on_progress: F) -> Result<PathBuf>
     where
-        F: Fn(f32) + Send + 'static,
+        F: Fn(f32) + Send + Sync + 'static,
     {
         let model = self
             .get_model_by_id(model_id)
             .ok_or_else(|| anyhow!("Unknown model: {}", model_id))?;
 
+        if model.custom {
+            if self.is_model_downloaded(&model.filename) {
+                on_progress(100.0);
+                return Ok(self.get_model_path(&model.filename));
+            }
+            return Err(anyhow!(
+                "Custom model {} is missing; import it again",
+                model.name
+            ));
+        }
+
         self.download_model(&model.repo, &model.filename, on_progress)
+            .await
+    }
+
+    /// Directory holding a speech model's files
+    pub fn speech_model_dir(&self, model_id: &str) -> PathBuf {
+        self.models_dir.join(SPEECH_MODELS_DIR).join(model_id)
+    }
+
+    /// Check if all of a speech model's files are downloaded
+    pub fn is_speech_model_downloaded(&self, model_id: &str) -> bool {
+        let dir = self.speech_model_dir(model_id);
+        SPEECH_MODEL_FILES
+            .iter()
+            .all(|file| dir.join(file).is_file())
+    }
+
+    /// Download a speech model's files (see `download_model`). Progress covers all of
+    /// them. Returns the directory they're in.
+    pub async fn download_speech_model<F>(&self, model_id: &str, on_progress: F) -> Result<PathBuf>
+    where
+        F: Fn(f32) + Send + Sync + 'static,
+    {
+        let model = find_speech_model(model_id)
+            .ok_or_else(|| anyhow!("Unknown speech model: {}", model_id))?;
+        let dir = self.speech_model_dir(model_id);
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let on_progress = Arc::new(on_progress);
+        let files = SPEECH_MODEL_FILES.len() as f32;
+        for (i, file) in SPEECH_MODEL_FILES.iter().enumerate() {
+            let url = format!(
+                "https://huggingface.co/{}/resolve/main/{}",
+                model.repo, file
+            );
+            let filename = format!("{}/{}/{}", SPEECH_MODELS_DIR, model_id, file);
+            let on_progress = on_progress.clone();
+            self.download_file(&url, &filename, move |progress| {
+                on_progress((i as f32 * 100.0 + progress) / files)
+            })
+            .await?;
+        }
+        Ok(dir)
+    }
+
+    /// The first speech model that is fully downloaded
+    pub fn find_downloaded_speech_model(&self) -> Option<String> {
+        super::whisper::get_available_speech_models()
+            .into_iter()
+            .map(|model| model.id)
+            .find(|id| self.is_speech_model_downloaded(id))
+    }
+
+    /// Delete a speech model's files, including interrupted downloads
+    pub fn delete_speech_model(&self, model_id: &str) -> Result<()> {
+        if find_speech_model(model_id).is_none() {
+            return Err(anyhow!("Unknown speech model: {}", model_id));
+        }
+        let dir = self.speech_model_dir(model_id);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+            Ok(())
+        } else {
+            Err(anyhow!("Speech model not downloaded: {}", model_id))
+        }
+    }
+}
+
+/// `custom-<slug of name>`, with a numeric suffix if that id is taken
+fn unique_model_id(name: &str, existing: &[String]) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let base = if slug.is_empty() {
+        "custom".to_string()
+    } else {
+        format!("custom-{}", slug)
+    };
+
+    let mut id = base.clone();
+    let mut n = 2;
+    while existing.contains(&id) {
+        id = format!("{}-{}", base, n);
+        n += 1;
+    }
+    id
+}
+
+/// Look up the expected size and hash of a HuggingFace file.
+/// `resolve/` URLs redirect to the CDN; the redirect itself carries `X-Linked-Size` and
+/// `X-Linked-Etag` (the SHA-256 of LFS files), so it is read without following it.
+async fn fetch_remote_info(url: &str) -> Result<RemoteFileInfo> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let response = client
+        .head(url)
+        .header(USER_AGENT, "inboxed-email-client/0.1")
+        .send()
+        .await?;
+
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+
+    Ok(RemoteFileInfo {
+        // Non-LFS files are served directly, so their own length is the file size
+        size: header("x-linked-size")
+            .or_else(|| header("content-length").filter(|_| response.status().is_success()))
+            .and_then(|size| size.parse().ok()),
+        sha256: header("x-linked-etag")
+            .as_deref()
+            .and_then(parse_sha256_etag),
+    })
+}
+
+/// Append the remote file to `path` from byte `offset` on, reporting progress in percent.
+/// Falls back to a full download if the server ignores the range request.
+async fn stream_to_file<F: Fn(f32)>(
+    url: &str,
+    path: &Path,
+    offset: u64,
+    expected_size: Option<u64>,
+    on_progress: &F,
+) -> Result<()> {
+    let mut request = reqwest::Client::new()
+        .get(url)
+        .header(USER_AGENT, "inboxed-email-client/0.1");
+    if offset > 0 {
+        info!("Resuming download from {} bytes", offset);
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+
+    let mut response = request.send().await?;
+
+    // Nothing left to fetch; verification decides whether the file is usable
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(());
+    }
+    if !response.status().is_success() {
+        return Err(anyhow!("HTTP {}", response.status()));
     }
+
+    let resumed = response.status() == StatusCode::PARTIAL_CONTENT
+        && response.headers().contains_key(CONTENT_RANGE);
+    let mut downloaded = if resumed { offset } else { 0 };
+    let total = expected_size.or_else(|| response.content_length().map(|len| len + downloaded));
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut last_reported = -PROGRESS_STEP;
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk).await?;
+        downloaded += chunk.len() as u64;
+
+        if let Some(total) = total.filter(|&t| t > 0) {
+            let progress = (downloaded as f32 / total as f32 * 100.0).min(99.9);
+            if progress - last_reported >= PROGRESS_STEP {
+                on_progress(progress);
+                last_reported = progress;
+            }
+        }
+    }
+    file.flush().await?;
+
+    Ok(())
+}
+
+/// `"<64 hex chars>"` (optionally weak, `W/"..."`) -> lowercase hex digest
+fn parse_sha256_etag(etag: &str) -> Option<String> {
+    let value = etag.trim().trim_start_matches("W/").trim_matches('"');
+    (value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| value.to_ascii_lowercase())
+}
+
+/// Check a finished download against the expected size and hash
+async fn verify_download(path: &Path, remote: &RemoteFileInfo) -> Result<()> {
+    let size = tokio::fs::metadata(path).await?.len();
+    if let Some(expected) = remote.size {
+        if size != expected {
+            return Err(anyhow!(
+                "Downloaded file is {} bytes, expected {}",
+                size,
+                expected
+            ));
+        }
+    }
+
+    if let Some(expected) = &remote.sha256 {
+        let path = path.to_path_buf();
+        let actual = tokio::task::spawn_blocking(move || -> Result<String> {
+            let mut file = std::fs::File::open(&path)?;
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut file, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        })
+        .await??;
+
+        if &actual != expected {
+            return Err(anyhow!(
+                "Checksum mismatch: got {}, expected {}",
+                actual,
+                expected
+            ));
+        }
+    }
+
+    Ok(())
 }
 
 impl Default for ModelManager {
@@ -279,4 +756,36 @@ mod tests {
         let path = manager.get_model_path("test.gguf");
         assert!(path.ends_with("test.gguf"));
     }
+
+    #[test]
+    fn test_unique_model_id() {
+        let existing = vec!["custom-llama-3.2-3b".to_string()];
+        assert_eq!(
+            unique_model_id("Phi 3 Mini (Q4)", &existing),
+            "custom-phi-3-mini-q4"
+        );
+        assert_eq!(
+            unique_model_id("Llama 3.2 3B", &existing),
+            "custom-llama-3.2-3b-2"
+        );
+        assert_eq!(unique_model_id("???", &existing), "custom");
+    }
+
+    #[test]
+    fn test_parse_sha256_etag() {
+        let hash = "3f1a0c2b9d8e7f6a5b4c3d2e1f0a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a";
+        assert_eq!(
+            parse_sha256_etag(&format!("\"{}\"", hash)).as_deref(),
+            Some(hash)
+        );
+        assert_eq!(
+            parse_sha256_etag(&format!("W/\"{}\"", hash.to_uppercase())).as_deref(),
+            Some(hash)
+        );
+        // Git blob etags (non-LFS files) are SHA-1 and can't be checked this way
+        assert_eq!(
+            parse_sha256_etag("\"a94a8fe5ccb19ba61c4c0873d391e987982fbbd3\""),
+            None
+        );
+    }
 }
@@ -0,0 +1,393 @@
+//! Chat prompt formats
+//!
+//! Each model family expects its own turn markers. The format is detected from the chat
+//! template embedded in the GGUF file, then from its architecture, and finally from the
+//! file name, falling back to ChatML which most small instruct models understand.
+//! Users can override the detected format per model with a `PromptTemplate`.
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::gguf::{self, GgufMetadata};
+
+/// Stands for a turn's text in a `PromptTemplate`
+const CONTENT_PLACEHOLDER: &str = "{content}";
+
+/// User template overrides, keyed by model file name
+const OVERRIDES_FILE: &str = "prompt_templates.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptFormat {
+    /// ChatML prefixed with `<|startoftext|>` (LiquidAI LFM2.5)
+    #[default]
+    Lfm,
+    /// `<|im_start|>role ... <|im_end|>` (Qwen and most fine-tunes)
+    ChatMl,
+    /// `<|start_header_id|>role<|end_header_id|> ... <|eot_id|>`
+    Llama3,
+    /// `<start_of_turn>user ... <end_of_turn>`; no system role
+    Gemma,
+    /// `[INST] ... [/INST]`; no system role
+    Mistral,
+    /// `<|user|> ... <|end|>`
+    Phi3,
+}
+
+impl PromptFormat {
+    /// Best guess from the file's metadata, with the file name as a last resort
+    pub fn detect(metadata: Option<&GgufMetadata>, filename: &str) -> Self {
+        metadata
+            .and_then(|m| m.chat_template().and_then(Self::from_template))
+            .or_else(|| metadata.and_then(|m| m.architecture().and_then(Self::from_architecture)))
+            .unwrap_or_else(|| Self::from_filename(filename))
+    }
+
+    fn from_template(template: &str) -> Option<Self> {
+        if template.contains("<|start_header_id|>") {
+            Some(Self::Llama3)
+        } else if template.contains("<start_of_turn>") {
+            Some(Self::Gemma)
+        } else if template.contains("<|im_start|>") {
+            if template.contains("<|startoftext|>") {
+                Some(Self::Lfm)
+            } else {
+                Some(Self::ChatMl)
+            }
+        } else if template.contains("<|user|>") && template.contains("<|end|>") {
+            Some(Self::Phi3)
+        } else if template.contains("[INST]") {
+            Some(Self::Mistral)
+        } else {
+            None
+        }
+    }
+
+    fn from_architecture(architecture: &str) -> Option<Self> {
+        match architecture {
+            "lfm2" => Some(Self::Lfm),
+            "qwen2" | "qwen3" | "qwen2moe" | "qwen3moe" => Some(Self::ChatMl),
+            "gemma" | "gemma2" | "gemma3" => Some(Self::Gemma),
+            "phi3" => Some(Self::Phi3),
+            // `llama` also covers Mistral and many others, so it isn't conclusive
+            _ => None,
+        }
+    }
+
+    fn from_filename(filename: &str) -> Self {
+        let filename = filename.to_lowercase();
+        if filename.contains("lfm") {
+            Self::Lfm
+        } else if filename.contains("llama-3") || filename.contains("llama3") {
+            Self::Llama3
+        } else if filename.contains("gemma") {
+            Self::Gemma
+        } else if filename.contains("mistral") {
+            Self::Mistral
+        } else if filename.contains("phi-3") || filename.contains("phi3") {
+            Self::Phi3
+        } else {
+            Self::ChatMl
+        }
+    }
+
+    /// Detect the format of a model file from its metadata and name
+    pub fn detect_file(model_path: &Path) -> Self {
+        let filename = model_path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("");
+        let metadata = gguf::read_metadata(model_path).ok();
+        Self::detect(metadata.as_ref(), filename)
+    }
+
+    /// The format as placeholder strings, the starting point for a user override
+    pub fn template(self) -> PromptTemplate {
+        let end_of_turn = match self {
+            Self::Lfm | Self::ChatMl => "<|im_end|>",
+            Self::Llama3 => "<|eot_id|>",
+            Self::Gemma => "<end_of_turn>",
+            Self::Mistral => "</s>",
+            Self::Phi3 => "<|end|>",
+        };
+        let (prefix, system, user, assistant, generation_prompt) = match self {
+            Self::Lfm | Self::ChatMl => (
+                if self == Self::Lfm {
+                    "<|startoftext|>"
+                } else {
+                    ""
+                },
+                "<|im_start|>system\n{content}<|im_end|>\n",
+                "<|im_start|>user\n{content}<|im_end|>\n",
+                "<|im_start|>assistant\n{content}<|im_end|>\n",
+                "<|im_start|>assistant\n",
+            ),
+            Self::Llama3 => (
+                "<|begin_of_text|>",
+                "<|start_header_id|>system<|end_header_id|>\n\n{content}<|eot_id|>",
+                "<|start_header_id|>user<|end_header_id|>\n\n{content}<|eot_id|>",
+                "<|start_header_id|>assistant<|end_header_id|>\n\n{content}<|eot_id|>",
+                "<|start_header_id|>assistant<|end_header_id|>\n\n",
+            ),
+            Self::Phi3 => (
+                "",
+                "<|system|>\n{content}<|end|>\n",
+                "<|user|>\n{content}<|end|>\n",
+                "<|assistant|>\n{content}<|end|>\n",
+                "<|assistant|>\n",
+            ),
+            Self::Gemma => (
+                "",
+                "",
+                "<start_of_turn>user\n{content}<end_of_turn>\n",
+                "<start_of_turn>model\n{content}<end_of_turn>\n",
+                "<start_of_turn>model\n",
+            ),
+            Self::Mistral => ("", "", "[INST] {content} [/INST]", " {content}</s>", ""),
+        };
+        PromptTemplate {
+            prefix: prefix.to_string(),
+            system: system.to_string(),
+            user: user.to_string(),
+            assistant: assistant.to_string(),
+            generation_prompt: generation_prompt.to_string(),
+            stop: vec![
+                end_of_turn.to_string(),
+                "<|endoftext|>".to_string(),
+                "\n\n\n".to_string(),
+            ],
+        }
+    }
+
+    /// Prompt for a single exchange
+    pub fn format(self, system: &str, user: &str) -> String {
+        self.template().format(system, user)
+    }
+
+    /// Multi-turn prompt; `history` is `(role, content)` with role "user" or "assistant"
+    pub fn format_chat(self, system: &str, history: &[(String, String)], user: &str) -> String {
+        self.template().format_chat(system, history, user)
+    }
+
+    /// Sequences that end the assistant's turn
+    pub fn stop_sequences(self) -> Vec<String> {
+        self.template().stop
+    }
+}
+
+/// A prompt layout written as plain strings, where `{content}` stands for a turn's text.
+/// Users can set one per model to support formats the detection doesn't know.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    /// Emitted once at the start, e.g. a beginning-of-text marker
+    #[serde(default)]
+    pub prefix: String,
+    /// Wraps the system prompt. Leave empty for models without a system role; the system
+    /// prompt is then prepended to the first user turn.
+    #[serde(default)]
+    pub system: String,
+    pub user: String,
+    pub assistant: String,
+    /// Appended after the last user turn to start the model's reply
+    #[serde(default)]
+    pub generation_prompt: String,
+    /// Sequences that end the model's reply
+    #[serde(default)]
+    pub stop: Vec<String>,
+}
+
+impl PromptTemplate {
+    /// Check that every turn template has a place for its content
+    pub fn validate(&self) -> Result<()> {
+        for (field, template) in [
+            ("system", &self.system),
+            ("user", &self.user),
+            ("assistant", &self.assistant),
+        ] {
+            let optional = field == "system" && template.is_empty();
+            if !optional && !template.contains(CONTENT_PLACEHOLDER) {
+                bail!(
+                    "The {} template must contain {}",
+                    field,
+                    CONTENT_PLACEHOLDER
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn format(&self, system: &str, user: &str) -> String {
+        self.format_chat(system, &[], user)
+    }
+
+    /// Multi-turn prompt; `history` is `(role, content)` with role "user" or "assistant"
+    pub fn format_chat(&self, system: &str, history: &[(String, String)], user: &str) -> String {
+        let mut prompt = self.prefix.clone();
+        // Without a system template the system prompt rides along with the first user turn
+        let mut pending_system = None;
+        if !system.is_empty() {
+            if self.system.is_empty() {
+                pending_system = Some(system);
+            } else {
+                prompt.push_str(&fill(&self.system, system));
+            }
+        }
+
+        let turns = history
+            .iter()
+            .map(|(role, content)| (role.as_str(), content.as_str()))
+            .chain(std::iter::once(("user", user)));
+        for (role, content) in turns {
+            if role == "assistant" {
+                prompt.push_str(&fill(&self.assistant, content));
+            } else if let Some(system) = pending_system.take() {
+                prompt.push_str(&fill(&self.user, &format!("{system}\n\n{content}")));
+            } else {
+                prompt.push_str(&fill(&self.user, content));
+            }
+        }
+        prompt.push_str(&self.generation_prompt);
+        prompt
+    }
+}
+
+fn fill(template: &str, content: &str) -> String {
+    template.replace(CONTENT_PLACEHOLDER, content)
+}
+
+/// User templates by model file name, stored as JSON in the models directory
+pub fn load_overrides(models_dir: &Path) -> BTreeMap<String, PromptTemplate> {
+    std::fs::read_to_string(models_dir.join(OVERRIDES_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_overrides(
+    models_dir: &Path,
+    overrides: &BTreeMap<String, PromptTemplate>,
+) -> Result<()> {
+    std::fs::write(
+        models_dir.join(OVERRIDES_FILE),
+        serde_json::to_string_pretty(overrides)?,
+    )?;
+    Ok(())
+}
+
+/// The user's template for the model at `model_path`, if they set one
+pub fn override_for(model_path: &Path) -> Option<PromptTemplate> {
+    let models_dir = model_path.parent()?;
+    let filename = model_path.file_name()?.to_str()?;
+    load_overrides(models_dir).remove(filename)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from_template_and_filename() {
+        assert_eq!(
+            PromptFormat::from_template("{{ '<|start_header_id|>' + message['role'] }}"),
+            Some(PromptFormat::Llama3)
+        );
+        assert_eq!(
+            PromptFormat::from_template("{{'<|startoftext|>'}}{{'<|im_start|>' + role}}"),
+            Some(PromptFormat::Lfm)
+        );
+        assert_eq!(
+            PromptFormat::from_template("{{ bos_token }}[INST] {{ message['content'] }} [/INST]"),
+            Some(PromptFormat::Mistral)
+        );
+        assert_eq!(PromptFormat::from_template("{{ messages }}"), None);
+
+        assert_eq!(
+            PromptFormat::detect(None, "LFM2.5-1.2B-Instruct-Q4_K_M.gguf"),
+            PromptFormat::Lfm
+        );
+        assert_eq!(
+            PromptFormat::detect(None, "Meta-Llama-3.1-8B.Q4.gguf"),
+            PromptFormat::Llama3
+        );
+        assert_eq!(
+            PromptFormat::detect(None, "gemma-2-2b-it.gguf"),
+            PromptFormat::Gemma
+        );
+        assert_eq!(
+            PromptFormat::detect(None, "some-model.gguf"),
+            PromptFormat::ChatMl
+        );
+    }
+
+    #[test]
+    fn test_format_chat() {
+        let history = vec![
+            ("user".to_string(), "Hi".to_string()),
+            ("assistant".to_string(), "Hello!".to_string()),
+        ];
+
+        assert_eq!(
+            PromptFormat::ChatMl.format("Be brief.", "Thanks"),
+            "<|im_start|>system\nBe brief.<|im_end|>\n<|im_start|>user\nThanks<|im_end|>\n<|im_start|>assistant\n"
+        );
+        assert_eq!(
+            PromptFormat::Gemma.format_chat("Be brief.", &history, "Thanks"),
+            "<start_of_turn>user\nBe brief.\n\nHi<end_of_turn>\n<start_of_turn>model\nHello!<end_of_turn>\n\
+             <start_of_turn>user\nThanks<end_of_turn>\n<start_of_turn>model\n"
+        );
+        assert_eq!(
+            PromptFormat::Mistral.format_chat("Be brief.", &history, "Thanks"),
+            "[INST] Be brief.\n\nHi [/INST] Hello!</s>[INST] Thanks [/INST]"
+        );
+        assert_eq!(PromptFormat::Llama3.stop_sequences()[0], "<|eot_id|>");
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let template = PromptTemplate {
+            prefix: "<s>".to_string(),
+            system: String::new(),
+            user: "### User:\n{content}\n".to_string(),
+            assistant: "### Assistant:\n{content}\n".to_string(),
+            generation_prompt: "### Assistant:\n".to_string(),
+            stop: vec!["### User:".to_string()],
+        };
+        assert!(template.validate().is_ok());
+        assert_eq!(
+            template.format_chat(
+                "Be brief.",
+                &[
+                    ("user".to_string(), "Hi".to_string()),
+                    ("assistant".to_string(), "Hello!".to_string()),
+                ],
+                "Thanks"
+            ),
+            "<s>### User:\nBe brief.\n\nHi\n### Assistant:\nHello!\n### User:\nThanks\n### Assistant:\n"
+        );
+
+        let missing_content = PromptTemplate {
+            user: "### User:\n".to_string(),
+            ..template
+        };
+        assert!(missing_content.validate().is_err());
+    }
+
+    #[test]
+    fn test_overrides_round_trip() {
+        let dir = std::env::temp_dir().join(format!("prompt-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut overrides = BTreeMap::new();
+        overrides.insert("model.gguf".to_string(), PromptFormat::Phi3.template());
+        save_overrides(&dir, &overrides).unwrap();
+
+        assert_eq!(
+            override_for(&dir.join("model.gguf")),
+            Some(PromptFormat::Phi3.template())
+        );
+        assert_eq!(override_for(&dir.join("other.gguf")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,6 +3,7 @@
 //! Combines embedding-based retrieval with LLM generation for contextual responses.
 
 use anyhow::{anyhow, Result};
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use super::embeddings::EmbeddingEngine;
@@ -19,12 +20,13 @@ pub struct RetrievedContext {
     pub similarity: f32,
 }
 
-/// Category descriptions for zero-shot classification via embeddings
-const CATEGORY_DESCRIPTIONS: &[(&str, &str)] = &[
-    ("promotions", "Marketing email with sales promotions, discount offers, coupon codes, limited time deals, shopping advertisements, commercial offers"),
-    ("newsletters", "Newsletter digest with editorial content, weekly updates, curated news roundup, blog posts, industry insights, recurring content publication"),
-    ("subscriptions", "Automated service notification, account alert, billing receipt, shipping update, password reset, order confirmation, system notification, GitHub notification, CI/CD alert"),
-    ("general", "Personal or work email conversation, direct message, meeting discussion, project collaboration, question from a colleague, professional correspondence"),
+/// Built-in categories `(id, name, description)`, seeded into the database on first run.
+/// Descriptions double as reference texts for zero-shot classification via embeddings.
+pub const DEFAULT_CATEGORIES: &[(&str, &str, &str)] = &[
+    ("promotions", "Promotions", "Marketing email with sales promotions, discount offers, coupon codes, limited time deals, shopping advertisements, commercial offers"),
+    ("newsletters", "Newsletters", "Newsletter digest with editorial content, weekly updates, curated news roundup, blog posts, industry insights, recurring content publication"),
+    ("subscriptions", "Subscriptions", "Automated service notification, account alert, billing receipt, shipping update, password reset, order confirmation, system notification, GitHub notification, CI/CD alert"),
+    ("general", "General", "Personal or work email conversation, direct message, meeting discussion, project collaboration, question from a colleague, professional correspondence"),
 ];
 
 /// RAG Engine combining retrieval and generation
@@ -55,6 +57,11 @@ impl RagEngine {
         self.embedding_engine.is_some() && self.vector_db.is_some()
     }
 
+    /// Id of the model that produces this engine's embeddings
+    pub fn embedding_model(&self) -> Option<&str> {
+        self.embedding_engine.as_ref().map(|engine| engine.model_id())
+    }
+
     /// Generate embedding for text
     pub fn embed_text(&self, text: &str) -> Result<Vec<f32>> {
         let engine = self
@@ -91,12 +98,15 @@ impl RagEngine {
         Ok(())
     }
 
-    /// Search for similar emails
+    /// Search for similar emails. `candidates` limits the search to emails that passed
+    /// metadata filters (sender, dates, folder, account), looked up beforehand with
+    /// `EmailDatabase::get_filtered_email_ids`.
     pub fn search_similar(
         &self,
         query: &str,
         top_k: usize,
         exclude_email_id: Option<&str>,
+        candidates: Option<&HashSet<String>>,
     ) -> Result<Vec<SimilarEmail>> {
         let engine = self
             .embedding_engine
@@ -108,10 +118,16 @@ impl RagEngine {
             .ok_or_else(|| anyhow!("Vector database not initialized"))?;
 
         // Generate query embedding
-        let query_embedding = engine.embed(query)?;
+        let query_embedding = engine.embed_query(query)?;
 
-        // Search in vector database
-        let similar = vector_db.search_similar(&query_embedding, top_k, exclude_email_id)?;
+        // Search in vector database, among vectors from the same model
+        let similar = vector_db.search_similar(
+            &query_embedding,
+            engine.model_id(),
+            top_k,
+            exclude_email_id,
+            candidates,
+        )?;
 
         Ok(similar)
     }
@@ -162,17 +178,18 @@ impl RagEngine {
         summarizer.chat(&prompt, Some(&context_str))
     }
 
-    /// Compute and cache reference embeddings for category classification
-    pub fn init_category_embeddings(&mut self) -> Result<()> {
+    /// Compute and cache reference embeddings for category classification.
+    /// `categories` are `(id, description)` pairs; call again when the taxonomy changes.
+    pub fn init_category_embeddings(&mut self, categories: &[(String, String)]) -> Result<()> {
         let engine = self
             .embedding_engine
             .as_ref()
             .ok_or_else(|| anyhow!("Embedding engine not initialized"))?;
 
         let mut embeddings = Vec::new();
-        for (category, description) in CATEGORY_DESCRIPTIONS {
+        for (category, description) in categories {
             let embedding = engine.embed(description)?;
-            embeddings.push((category.to_string(), embedding));
+            embeddings.push((category.clone(), embedding));
         }
 
         self.category_embeddings = Some(embeddings);
@@ -0,0 +1,225 @@
+//! Cross-encoder reranking for RAG retrieval
+//!
+//! Vector search compares a question with an email through two embeddings computed
+//! apart, which is fast enough for a whole mailbox but blurs fine distinctions. A
+//! cross-encoder reads the question and the email together and scores how well the email
+//! answers it: too slow for a whole mailbox, cheap for the few dozen best vector hits.
+//! RAG chat uses it, when enabled in the AI settings, to reorder those hits before
+//! picking the ones the model gets to read.
+
+use anyhow::{anyhow, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{linear, Linear, Module, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config};
+use std::path::{Path, PathBuf};
+use tokenizers::{Tokenizer, TruncationParams, TruncationStrategy};
+use tracing::{info, warn};
+
+use super::embeddings;
+use crate::settings;
+
+/// MiniLM cross-encoder trained on MS MARCO passage ranking (~90 MB)
+pub const DEFAULT_RERANKER_MODEL: &str = "cross-encoder/ms-marco-MiniLM-L-6-v2";
+
+/// Question and email pairs scored per forward pass
+const BATCH_SIZE: usize = 16;
+
+/// Longest pair read, in tokens; the email is cut to fit (BERT's position limit)
+const MAX_TOKENS: usize = 512;
+
+/// Scores how relevant passages are to a query
+pub struct Reranker {
+    model: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl Reranker {
+    /// Load the reranker from the model cache; fails if it hasn't been downloaded
+    pub fn new() -> Result<Self> {
+        let (config, tokenizer, weights) =
+            embeddings::cached_model_files(DEFAULT_RERANKER_MODEL)
+                .ok_or_else(|| anyhow!("Reranker model not downloaded"))?;
+        Self::from_paths(&config, &tokenizer, &weights)
+    }
+
+    pub fn from_paths(
+        config_path: &Path,
+        tokenizer_path: &Path,
+        weights_path: &Path,
+    ) -> Result<Self> {
+        let config: Config = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                strategy: TruncationStrategy::OnlySecond,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer: {}", e))?;
+
+        // Metal when allowed and working, as for embeddings; F32 throughout since the
+        // BERT attention mask is F32
+        let metal = if settings::current().compute.allow_gpu() {
+            Device::new_metal(0).ok()
+        } else {
+            None
+        };
+        if let Some(device) = metal {
+            match Self::load(&config, weights_path, &device) {
+                Ok((model, pooler, classifier)) => {
+                    let test_input = Tensor::zeros((1, 1), DType::U32, &device)?;
+                    match model.forward(&test_input, &test_input, None) {
+                        Ok(_) => {
+                            info!("Reranker loaded on Metal");
+                            return Ok(Self {
+                                model,
+                                pooler,
+                                classifier,
+                                tokenizer,
+                                device,
+                            });
+                        }
+                        Err(e) => warn!("Metal forward pass failed ({}), reranking on CPU", e),
+                    }
+                }
+                Err(e) => warn!("Failed to load reranker on Metal ({}), using CPU", e),
+            }
+        }
+
+        let device = Device::Cpu;
+        let (model, pooler, classifier) = Self::load(&config, weights_path, &device)?;
+        info!("Reranker loaded on CPU");
+        Ok(Self {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// The encoder, the pooler over its `[CLS]` token and the one-logit relevance head of
+    /// a `BertForSequenceClassification` checkpoint
+    fn load(
+        config: &Config,
+        weights_path: &Path,
+        device: &Device,
+    ) -> Result<(BertModel, Linear, Linear)> {
+        let weights: [PathBuf; 1] = [weights_path.to_path_buf()];
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&weights, DType::F32, device)? };
+        // Falls back to the `bert.` prefix the classification checkpoint uses
+        let model = BertModel::load(vb.clone(), config)?;
+        let pooler = linear(
+            config.hidden_size,
+            config.hidden_size,
+            vb.pp("bert.pooler.dense"),
+        )?;
+        let classifier = linear(config.hidden_size, 1, vb.pp("classifier"))?;
+        Ok((model, pooler, classifier))
+    }
+
+    /// Relevance of each passage to `query`, in passage order. Scores are logits: higher
+    /// is more relevant, and only their order means anything (see `relevance`).
+    pub fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>> {
+        let mut scores = Vec::with_capacity(passages.len());
+        for batch in passages.chunks(BATCH_SIZE) {
+            scores.extend(self.score_batch(query, batch)?);
+        }
+        Ok(scores)
+    }
+
+    fn score_batch(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>> {
+        let pairs: Vec<(&str, &str)> = passages.iter().map(|passage| (query, *passage)).collect();
+        let encodings = self
+            .tokenizer
+            .encode_batch(pairs, true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+
+        let max_len = encodings
+            .iter()
+            .map(|e| e.get_ids().len())
+            .max()
+            .unwrap_or(0);
+        let mut input_ids = Vec::with_capacity(encodings.len() * max_len);
+        let mut type_ids = Vec::with_capacity(encodings.len() * max_len);
+        let mut attention_mask = Vec::with_capacity(encodings.len() * max_len);
+        // Pad every sequence to the longest in the batch
+        let padded = |values: &[u32]| {
+            let mut values = values.to_vec();
+            values.resize(max_len, 0);
+            values
+        };
+        for encoding in &encodings {
+            input_ids.extend(padded(encoding.get_ids()));
+            type_ids.extend(padded(encoding.get_type_ids()));
+            attention_mask.extend(padded(encoding.get_attention_mask()));
+        }
+
+        let shape = (encodings.len(), max_len);
+        let input_ids = Tensor::from_vec(input_ids, shape, &self.device)?;
+        let type_ids = Tensor::from_vec(type_ids, shape, &self.device)?;
+        let attention_mask = Tensor::from_vec(attention_mask, shape, &self.device)?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &type_ids, Some(&attention_mask))?;
+        let cls = hidden.narrow(1, 0, 1)?.squeeze(1)?;
+        let pooled = self.pooler.forward(&cls)?.tanh()?;
+        let logits = self.classifier.forward(&pooled)?.squeeze(1)?;
+        Ok(logits.to_device(&Device::Cpu)?.to_vec1::<f32>()?)
+    }
+}
+
+/// A reranker logit as a 0–1 relevance
+pub fn relevance(score: f32) -> f32 {
+    1.0 / (1.0 + (-score).exp())
+}
+
+/// Indices of the `top_k` best scores, best first; ties keep their original order
+pub fn top_indices(scores: &[f32], top_k: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+    order.truncate(top_k);
+    order
+}
+
+/// Check if the reranker model is downloaded (local cache only, no network)
+pub fn is_model_downloaded() -> bool {
+    embeddings::cached_model_files(DEFAULT_RERANKER_MODEL).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_indices() {
+        assert_eq!(top_indices(&[0.1, 2.5, -1.0, 2.5, 0.3], 3), vec![1, 3, 4]);
+        assert_eq!(top_indices(&[1.0], 5), vec![0]);
+        assert!(top_indices(&[], 5).is_empty());
+        assert!((relevance(0.0) - 0.5).abs() < 1e-6);
+        assert!(relevance(8.0) > 0.99 && relevance(-8.0) < 0.01);
+    }
+
+    // Integration test - requires model download
+    #[test]
+    #[ignore]
+    fn test_rerank_order() {
+        let reranker = Reranker::new().unwrap();
+        let scores = reranker
+            .score(
+                "When is the dentist appointment?",
+                &[
+                    "Your weekly newsletter: ten recipes for autumn",
+                    "Reminder: your dental checkup is on Tuesday at 9:30",
+                ],
+            )
+            .unwrap();
+        assert_eq!(top_indices(&scores, 1), vec![1]);
+    }
+}
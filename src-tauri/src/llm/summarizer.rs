@@ -1,22 +1,32 @@
 use anyhow::Result;
 use std::path::Path;
 use std::sync::Arc;
+use tracing::debug;
 
-use super::engine::{GenerationParams, LlmEngine};
+use super::engine::{CancellationToken, GenerationParams, LlmEngine, LlmSettings};
+use super::prompt_format::{self, PromptFormat, PromptTemplate};
+use crate::email::{meetings, reply};
+
+/// Most of an email body translated; the rest is left out
+const MAX_TRANSLATION_CHARS: usize = 12000;
+
+/// Paragraphs are translated in runs of about this many characters
+const TRANSLATION_CHUNK_CHARS: usize = 1500;
+
+/// Engine cache keys of the prompts indexing runs on every email
+pub(crate) const INDEXING_CACHE_KEYS: [&str; 6] = [
+    "summary",
+    "priority",
+    "category",
+    "deadline",
+    "sentiment",
+    "needs_reply",
+];
 
 /// AI-powered email summarizer using local LLM
 pub struct Summarizer {
     engine: Option<Arc<LlmEngine>>,
-    model_type: ModelType,
-}
-
-/// Different model types require different prompt formats
-#[derive(Debug, Clone, Copy, Default)]
-pub enum ModelType {
-    #[default]
-    LFM25,      // LiquidAI LFM2.5 series
-    Qwen25,     // Qwen 2.5 series
-    Unknown,    // Generic ChatML
+    prompt_template: PromptTemplate,
 }
 
 impl Summarizer {
@@ -25,28 +35,25 @@ impl Summarizer {
     pub fn new() -> Result<Self> {
         Ok(Self {
             engine: None,
-            model_type: ModelType::default(),
+            prompt_template: PromptFormat::default().template(),
         })
     }
 
     /// Load an LLM model from the given path
-    pub fn load_model(&mut self, model_path: &Path) -> Result<()> {
-        let engine = LlmEngine::new(model_path)?;
+    pub fn load_model(&mut self, model_path: &Path, settings: LlmSettings) -> Result<()> {
+        let engine = LlmEngine::new(model_path, settings)?;
         self.engine = Some(Arc::new(engine));
 
-        // Detect model type from filename
-        let filename = model_path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        self.model_type = if filename.contains("lfm") {
-            ModelType::LFM25
-        } else if filename.contains("qwen") {
-            ModelType::Qwen25
-        } else {
-            ModelType::Unknown
+        self.prompt_template = match prompt_format::override_for(model_path) {
+            Some(template) => {
+                debug!("Using custom prompt template");
+                template
+            }
+            None => {
+                let format = PromptFormat::detect_file(model_path);
+                debug!("Using {:?} prompt format", format);
+                format.template()
+            }
         };
 
         Ok(())
@@ -62,37 +69,33 @@ impl Summarizer {
         self.engine.clone()
     }
 
-    /// Format a prompt for the loaded model type
+    /// Format a prompt for the loaded model's prompt format
     fn format_prompt(&self, system: &str, user: &str) -> String {
-        match self.model_type {
-            ModelType::LFM25 => {
-                // LFM2.5 uses <|startoftext|> and ChatML-like format
-                format!(
-                    "<|startoftext|><|im_start|>system\n{system}<|im_end|>\n<|im_start|>user\n{user}<|im_end|>\n<|im_start|>assistant\n"
-                )
-            }
-            ModelType::Qwen25 => {
-                // Qwen 2.5 uses standard ChatML
-                format!(
-                    "<|im_start|>system\n{system}<|im_end|>\n<|im_start|>user\n{user}<|im_end|>\n<|im_start|>assistant\n"
-                )
-            }
-            ModelType::Unknown => {
-                // Generic ChatML format
-                format!(
-                    "<|im_start|>system\n{system}<|im_end|>\n<|im_start|>user\n{user}<|im_end|>\n<|im_start|>assistant\n"
-                )
+        self.prompt_template.format(system, user)
+    }
+
+    /// Format a multi-turn prompt; `history` is `(role, content)` with role "user" or "assistant"
+    fn format_chat_prompt(&self, system: &str, history: &[(String, String)], user: &str) -> String {
+        self.prompt_template.format_chat(system, history, user)
+    }
+
+    /// Most recent turns that fit in `max_chars`, oldest first
+    fn truncate_history(history: &[(String, String)], max_chars: usize) -> &[(String, String)] {
+        let mut used = 0;
+        let mut start = history.len();
+        for (idx, (_, content)) in history.iter().enumerate().rev() {
+            used += content.len();
+            if used > max_chars {
+                break;
             }
+            start = idx;
         }
+        &history[start..]
     }
 
     /// Get stop sequences for the model
     fn get_stop_sequences(&self) -> Vec<String> {
-        vec![
-            "<|im_end|>".to_string(),
-            "<|endoftext|>".to_string(),
-            "\n\n\n".to_string(),
-        ]
+        self.prompt_template.stop.clone()
     }
 
     /// Determine summary parameters based on email length
@@ -107,12 +110,26 @@ impl Summarizer {
         }
     }
 
-    /// Summarize email content using LLM
+    /// Summary instructions, asking for `language` (an English language name) when given
+    fn summary_system_prompt(instruction: &str, language: Option<&str>) -> String {
+        let mut system = format!(
+            "You are a helpful email assistant. {} Do not miss any important information.",
+            instruction
+        );
+        if let Some(language) = language {
+            system.push_str(&format!(" Write the summary in {}.", language));
+        }
+        system
+    }
+
+    /// Summarize email content using LLM, in `language` (an English language name) when
+    /// given
     pub fn summarize_email(
         &self,
         subject: &str,
         from: &str,
         body: &str,
+        language: Option<&str>,
     ) -> Result<String> {
         let body_text = Self::strip_html(body);
         let word_count = body_text.split_whitespace().count();
@@ -124,10 +141,7 @@ impl Summarizer {
         if let Some(engine) = &self.engine {
             let (max_tokens, instruction) = Self::get_summary_params(word_count);
 
-            let system = format!(
-                "You are a helpful email assistant. {} Do not miss any important information.",
-                instruction
-            );
+            let system = Self::summary_system_prompt(instruction, language);
             let user = format!(
                 "Summarize this email:\n\nFrom: {from}\nSubject: {subject}\n\n{body_preview}"
             );
@@ -138,6 +152,7 @@ impl Summarizer {
                 max_tokens,
                 temperature: 0.3,
                 stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("summary".to_string()),
                 ..Default::default()
             };
 
@@ -154,6 +169,8 @@ impl Summarizer {
         subject: &str,
         from: &str,
         body: &str,
+        language: Option<&str>,
+        cancel: Option<CancellationToken>,
         on_token: F,
     ) -> Result<String>
     where
@@ -169,10 +186,7 @@ impl Summarizer {
         if let Some(engine) = &self.engine {
             let (max_tokens, instruction) = Self::get_summary_params(word_count);
 
-            let system = format!(
-                "You are a helpful email assistant. {} Do not miss any important information.",
-                instruction
-            );
+            let system = Self::summary_system_prompt(instruction, language);
             let user = format!(
                 "Summarize this email:\n\nFrom: {from}\nSubject: {subject}\n\n{body_preview}"
             );
@@ -183,6 +197,8 @@ impl Summarizer {
                 max_tokens,
                 temperature: 0.3,
                 stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("summary".to_string()),
+                cancel,
                 ..Default::default()
             };
 
@@ -209,6 +225,7 @@ impl Summarizer {
                 max_tokens: 150,
                 temperature: 0.3,
                 stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("insights".to_string()),
                 ..Default::default()
             };
 
@@ -257,6 +274,7 @@ impl Summarizer {
                 max_tokens: 10,
                 temperature: 0.1,
                 stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("priority".to_string()),
                 ..Default::default()
             };
 
@@ -283,8 +301,491 @@ impl Summarizer {
         }
     }
 
+    /// Classify an email into one of the configured categories using LLM.
+    /// `categories` are `(id, name, description)`; returns the chosen id, or None when
+    /// no model is loaded or the answer matches no category.
+    pub fn classify_category(
+        &self,
+        subject: &str,
+        from: &str,
+        body: &str,
+        categories: &[(String, String, String)],
+    ) -> Result<Option<String>> {
+        let body_text = Self::strip_html(body);
+        let body_preview = Self::truncate_text(&body_text, 1000);
+
+        if let Some(engine) = &self.engine {
+            let options = categories
+                .iter()
+                .map(|(id, name, description)| format!("{id}: {name} — {description}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let system = format!(
+                "You are an email classifier. Respond with exactly one category id from this list and nothing else:\n\n{options}"
+            );
+            let user = format!("Classify this email:\n\nFrom: {from}\nSubject: {subject}\n\n{body_preview}");
+
+            let prompt = self.format_prompt(&system, &user);
+
+            let params = GenerationParams {
+                max_tokens: 10,
+                temperature: 0.1,
+                stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("category".to_string()),
+                ..Default::default()
+            };
+
+            let response = engine.generate(&prompt, &params)?.trim().to_lowercase();
+
+            // Exact id first, then the longest id or name mentioned in the answer
+            let exact = categories.iter().find(|(id, _, _)| response == *id);
+            let mentioned = categories
+                .iter()
+                .filter(|(id, name, _)| response.contains(id.as_str()) || response.contains(&name.to_lowercase()))
+                .max_by_key(|(id, _, _)| id.len());
+            Ok(exact.or(mentioned).map(|(id, _, _)| id.clone()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Extract the main deadline from an email using LLM.
+    /// `sent_at` is the email's send time ("YYYY-MM-DD HH:MM, Weekday") used to resolve
+    /// relative dates. Returns the raw "YYYY-MM-DD HH:MM" answer, or None if there is no deadline.
+    pub fn extract_deadline(&self, subject: &str, body: &str, sent_at: &str) -> Result<Option<String>> {
+        let body_text = Self::strip_html(body);
+        let body_preview = Self::truncate_text(&body_text, 1500);
+
+        if let Some(engine) = &self.engine {
+            let system = "You extract deadlines from emails. If the email states a date or time by which something must be done, \
+                respond with exactly that date and time in the format YYYY-MM-DD HH:MM (use 23:59 if no time is given). \
+                Resolve relative dates like \"Friday\" or \"tomorrow\" against the date the email was sent. \
+                If there is no deadline, respond with exactly NONE.";
+            let user = format!("Sent: {sent_at}\nSubject: {subject}\n\n{body_preview}");
+
+            let prompt = self.format_prompt(system, &user);
+
+            let params = GenerationParams {
+                max_tokens: 20,
+                temperature: 0.1,
+                stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("deadline".to_string()),
+                ..Default::default()
+            };
+
+            let response = engine.generate(&prompt, &params)?;
+            let answer = response.trim();
+            if answer.is_empty() || answer.to_uppercase().contains("NONE") {
+                Ok(None)
+            } else {
+                Ok(Some(answer.to_string()))
+            }
+        } else {
+            // No model loaded — caller falls back to rule-based parsing
+            Ok(None)
+        }
+    }
+
+    /// List the meeting times an email proposes, one "YYYY-MM-DD HH:MM" or
+    /// "YYYY-MM-DD HH:MM-HH:MM" per line. `sent_at` is formatted as for `extract_deadline`.
+    /// Returns no lines when there are none or no model is loaded.
+    pub fn extract_meeting_slots(
+        &self,
+        subject: &str,
+        body: &str,
+        sent_at: &str,
+    ) -> Result<Vec<String>> {
+        let body_text = Self::strip_html(body);
+        let body_preview = Self::truncate_text(&body_text, 1500);
+
+        let Some(engine) = &self.engine else {
+            // No model loaded — caller falls back to rule-based parsing
+            return Ok(Vec::new());
+        };
+
+        let system = "You extract proposed meeting times from emails. List every date and time the sender suggests for a meeting or call, \
+            one per line, as YYYY-MM-DD HH:MM, followed by -HH:MM when the end time is given. \
+            Resolve relative dates like \"Tuesday\" or \"tomorrow\" against the date the email was sent. \
+            Leave out deadlines and times without a day. If no meeting time is proposed, respond with exactly NONE.";
+        let user = format!("Sent: {sent_at}\nSubject: {subject}\n\n{body_preview}");
+
+        let prompt = self.format_prompt(system, &user);
+
+        let params = GenerationParams {
+            max_tokens: 120,
+            temperature: 0.1,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("meeting_slots".to_string()),
+            ..Default::default()
+        };
+
+        let response = engine.generate(&prompt, &params)?;
+        if response.to_uppercase().contains("NONE") {
+            return Ok(Vec::new());
+        }
+        Ok(response
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Classify the sender's tone as positive, neutral, negative or frustrated.
+    /// Falls back to keyword matching when no model is loaded.
+    pub fn classify_sentiment(&self, subject: &str, body: &str) -> Result<String> {
+        let body_text = Self::strip_html(body);
+        let body_preview = Self::truncate_text(&body_text, 1000);
+
+        if let Some(engine) = &self.engine {
+            let system = "You classify the tone of emails. Respond with exactly one word: POSITIVE, NEUTRAL, NEGATIVE, or FRUSTRATED.\n\n\
+                POSITIVE: Thanks, praise, good news, enthusiasm.\n\
+                NEUTRAL: Factual, informational, routine requests, automated messages.\n\
+                NEGATIVE: Disappointment, complaints, bad news, dissatisfaction.\n\
+                FRUSTRATED: Anger, repeated unresolved complaints, threats to cancel or escalate.";
+            let user = format!("Classify the tone of this email:\n\nSubject: {subject}\n\n{body_preview}");
+
+            let prompt = self.format_prompt(system, &user);
+
+            let params = GenerationParams {
+                max_tokens: 10,
+                temperature: 0.1,
+                stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("sentiment".to_string()),
+                ..Default::default()
+            };
+
+            let response = engine.generate(&prompt, &params)?.to_uppercase();
+
+            // Check FRUSTRATED before NEGATIVE/POSITIVE so partial answers map sensibly
+            let sentiment = ["FRUSTRATED", "NEGATIVE", "POSITIVE", "NEUTRAL"]
+                .into_iter()
+                .find(|label| response.contains(label))
+                .unwrap_or("NEUTRAL");
+            Ok(sentiment.to_lowercase())
+        } else {
+            Ok(Self::simple_sentiment(subject, &body_text))
+        }
+    }
+
+    /// Decide whether the email asks the recipient a question or for a response.
+    /// Automated senders and messages with no question or request are ruled out by the
+    /// heuristics first; the LLM confirms the rest, or the heuristics decide alone when no
+    /// model is loaded.
+    pub fn detect_needs_reply(&self, subject: &str, from_email: &str, body: &str) -> Result<bool> {
+        let body_text = Self::strip_html(body);
+        if !reply::needs_reply(from_email, subject, &body_text) {
+            return Ok(false);
+        }
+
+        let Some(engine) = &self.engine else {
+            return Ok(true);
+        };
+
+        let body_preview = Self::truncate_text(&reply::new_text(&body_text), 1000);
+        let system = "You decide whether an email needs a reply from its recipient. Respond with exactly one word: YES or NO.\n\n\
+            YES: The sender asks the recipient a direct question, requests a decision, confirmation or information, or explicitly waits for an answer.\n\
+            NO: Rhetorical questions, FYI updates, thank-you notes, announcements, or questions the recipient is not expected to answer.";
+        let user = format!("Does this email need a reply?\n\nFrom: {from_email}\nSubject: {subject}\n\n{body_preview}");
+
+        let prompt = self.format_prompt(system, &user);
+
+        let params = GenerationParams {
+            max_tokens: 5,
+            temperature: 0.1,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("needs_reply".to_string()),
+            ..Default::default()
+        };
+
+        let response = engine.generate(&prompt, &params)?.to_uppercase();
+        Ok(!response.contains("NO"))
+    }
+
+    /// Draft an email from the user's instructions (e.g. bullet points), streaming tokens.
+    /// `context` is the message being replied to, if any.
+    pub fn compose_email_stream<F>(
+        &self,
+        instructions: &str,
+        context: Option<&str>,
+        cancel: Option<CancellationToken>,
+        on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No AI model loaded"))?;
+
+        let system = "You are an email writing assistant. Write a complete, ready-to-send email body from the user's instructions. \
+            Expand bullet points into clear prose, keep a natural professional tone, and do not invent facts, names or dates. \
+            Output only the email body, without a subject line or commentary.";
+        let user = match context {
+            Some(context) => {
+                let context_text = Self::truncate_text(&Self::strip_html(context), 2000);
+                format!("Email being replied to:\n\n{context_text}\n\nWrite a reply based on these instructions:\n{instructions}")
+            }
+            None => format!("Write an email based on these instructions:\n{instructions}"),
+        };
+
+        let prompt = self.format_prompt(system, &user);
+
+        let params = GenerationParams {
+            max_tokens: 400,
+            temperature: engine.settings().temperature,
+            top_p: engine.settings().top_p,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("compose".to_string()),
+            cancel,
+            ..Default::default()
+        };
+
+        engine.generate_stream(&prompt, &params, on_token)
+    }
+
+    /// Draft a short follow-up ("nudge") to an email that got no answer, streaming tokens.
+    /// Falls back to a fixed template when no model is loaded.
+    pub fn draft_followup_stream<F>(
+        &self,
+        subject: &str,
+        sent_body: &str,
+        days_waiting: i64,
+        cancel: Option<CancellationToken>,
+        on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        let Some(engine) = &self.engine else {
+            return Ok(reply::followup_template(subject));
+        };
+
+        let system = "You are an email writing assistant. Write a brief, polite follow-up to an email the user sent that has not been answered. \
+            Refer to the original request in a sentence, ask for an update without sounding pushy, and keep it under 80 words. \
+            Do not invent facts, names or dates. Output only the email body, without a subject line or commentary.";
+        let original = Self::truncate_text(&Self::strip_html(sent_body), 1500);
+        let user = format!(
+            "Original email, sent {days_waiting} days ago with no reply:\n\nSubject: {subject}\n\n{original}\n\nWrite the follow-up."
+        );
+
+        let prompt = self.format_prompt(system, &user);
+
+        let params = GenerationParams {
+            max_tokens: 200,
+            temperature: engine.settings().temperature,
+            top_p: engine.settings().top_p,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("compose".to_string()),
+            cancel,
+            ..Default::default()
+        };
+
+        engine.generate_stream(&prompt, &params, on_token)
+    }
+
+    /// Draft a reply accepting one of the meeting times an email proposed, streaming
+    /// tokens. Falls back to a fixed template when no model is loaded.
+    pub fn draft_scheduling_reply_stream<F>(
+        &self,
+        subject: &str,
+        body: &str,
+        slot: &str,
+        cancel: Option<CancellationToken>,
+        on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        let Some(engine) = &self.engine else {
+            return Ok(meetings::scheduling_template(slot));
+        };
+
+        let system = "You are an email writing assistant. Write a brief, friendly reply to a meeting request that confirms the time the user picked. \
+            State the chosen day and time exactly as given, answer any other question in the email only if it is about the meeting, and keep it under 80 words. \
+            Do not invent facts, names, places or links. Output only the email body, without a subject line or commentary.";
+        let original = Self::truncate_text(&Self::strip_html(body), 1500);
+        let user = format!("Meeting request:\n\nSubject: {subject}\n\n{original}\n\nThe user picked: {slot}\n\nWrite the reply.");
+
+        let prompt = self.format_prompt(system, &user);
+
+        let params = GenerationParams {
+            max_tokens: 200,
+            temperature: engine.settings().temperature,
+            top_p: engine.settings().top_p,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("compose".to_string()),
+            cancel,
+            ..Default::default()
+        };
+
+        engine.generate_stream(&prompt, &params, on_token)
+    }
+
+    /// Instruction for a rewrite mode; unknown values are treated as a tone name
+    fn rewrite_instruction(tone: &str) -> String {
+        match tone.trim().to_lowercase().as_str() {
+            "grammar" | "fix" | "proofread" => {
+                "Fix spelling, grammar and punctuation. Keep the wording and tone otherwise unchanged.".to_string()
+            }
+            "shorter" | "shorten" | "concise" => {
+                "Make the text shorter and more concise while keeping every important point.".to_string()
+            }
+            "longer" | "expand" => "Expand the text with more detail and clearer explanations, without inventing facts.".to_string(),
+            "formal" | "professional" => "Rewrite the text in a formal, professional tone.".to_string(),
+            "friendly" | "casual" => "Rewrite the text in a warm, friendly and casual tone.".to_string(),
+            "polite" => "Rewrite the text to be more polite and diplomatic.".to_string(),
+            "direct" | "assertive" => "Rewrite the text to be clear, direct and assertive.".to_string(),
+            other => format!("Rewrite the text in a {} tone.", other),
+        }
+    }
+
+    /// Rewrite a piece of text (fix grammar, shorten, change tone), streaming tokens
+    pub fn rewrite_text_stream<F>(
+        &self,
+        text: &str,
+        tone: &str,
+        cancel: Option<CancellationToken>,
+        on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No AI model loaded"))?;
+
+        let system = format!(
+            "You are an email editing assistant. {} Preserve the meaning and any names, numbers and dates. \
+            Output only the rewritten text, without commentary.",
+            Self::rewrite_instruction(tone)
+        );
+        let text = Self::truncate_text(text, 4000);
+        let user = format!("Rewrite this text:\n\n{text}");
+
+        let prompt = self.format_prompt(&system, &user);
+
+        // Leave room for expansion relative to the input length
+        let word_count = text.split_whitespace().count() as u32;
+        let params = GenerationParams {
+            max_tokens: (word_count * 2 + 64).min(800),
+            temperature: 0.4,
+            stop_sequences: self.get_stop_sequences(),
+            cache_key: Some("rewrite".to_string()),
+            cancel,
+            ..Default::default()
+        };
+
+        engine.generate_stream(&prompt, &params, on_token)
+    }
+
+    /// Translate an email body into `language` (an English language name), streaming
+    /// tokens. Long bodies are translated a few paragraphs at a time, so cancelling stops
+    /// after the current run.
+    pub fn translate_stream<F>(
+        &self,
+        body: &str,
+        language: &str,
+        cancel: Option<CancellationToken>,
+        mut on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        let engine = self
+            .engine
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No AI model loaded"))?;
+
+        let system = format!(
+            "You are a translator. Translate the user's email text into {language}. Keep the meaning, tone, names, \
+            numbers, dates and paragraph breaks. Output only the translation, without commentary."
+        );
+        let body = Self::truncate_text(body, MAX_TRANSLATION_CHARS);
+
+        let mut translated = Vec::new();
+        for chunk in Self::paragraph_chunks(&body, TRANSLATION_CHUNK_CHARS) {
+            if cancel.as_ref().is_some_and(CancellationToken::is_cancelled) {
+                break;
+            }
+            if !translated.is_empty() {
+                on_token("\n\n");
+            }
+            let prompt = self.format_prompt(&system, &chunk);
+            let params = GenerationParams {
+                // Room for languages that take more tokens than the original
+                max_tokens: (chunk.chars().count() as u32 / 2 + 64).min(1200),
+                temperature: 0.2,
+                stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("translate".to_string()),
+                cancel: cancel.clone(),
+                ..Default::default()
+            };
+            let part = engine.generate_stream(&prompt, &params, &mut on_token)?;
+            translated.push(part.trim().to_string());
+        }
+
+        Ok(translated.join("\n\n"))
+    }
+
+    /// Continue a multi-turn conversation with streaming output (pass an empty history for a
+    /// single turn). Older turns are dropped when the history exceeds the prompt budget.
+    ///
+    /// Each chat session keeps its own engine context. The system prompt never changes and the
+    /// retrieved email context goes in the new user turn, after the history, so the cached
+    /// prefix covers the whole conversation so far.
+    pub fn chat_stream<F>(
+        &self,
+        session_id: Option<&str>,
+        history: &[(String, String)],
+        user_message: &str,
+        email_context: Option<&str>,
+        cancel: Option<CancellationToken>,
+        on_token: F,
+    ) -> Result<String>
+    where
+        F: FnMut(&str),
+    {
+        if let Some(engine) = &self.engine {
+            let system = "You are an intelligent email assistant for Inboxed. Help users understand their emails. \
+                Be concise and conversational. When email context is given, only reference information from it.";
+            let user = match email_context {
+                Some(ctx) => format!(
+                    "Email context:\n{}\n\n{}",
+                    Self::truncate_text(ctx, 2000),
+                    user_message
+                ),
+                None => user_message.to_string(),
+            };
+
+            let cache_key = match session_id {
+                Some(id) => format!("chat:{}", id),
+                None => "chat".to_string(),
+            };
+
+            let history = Self::truncate_history(history, 3000);
+            let prompt = self.format_chat_prompt(system, history, &user);
+            let params = GenerationParams {
+                max_tokens: 300,
+                temperature: engine.settings().temperature,
+                top_p: engine.settings().top_p,
+                stop_sequences: self.get_stop_sequences(),
+                cache_key: Some(cache_key),
+                cancel,
+                ..Default::default()
+            };
+
+            engine.generate_stream(&prompt, &params, on_token)
+        } else {
+            // Fallback when no model loaded
+            Ok(Self::fallback_chat_response(email_context))
+        }
+    }
+
     /// Strip HTML tags from content
-    fn strip_html(html: &str) -> String {
+    pub(crate) fn strip_html(html: &str) -> String {
         let result = html
             .replace("<br>", "\n")
             .replace("<br/>", "\n")
@@ -325,6 +826,22 @@ impl Summarizer {
         }
     }
 
+    /// `text` split at blank lines into runs of paragraphs of up to `max_chars` (a longer
+    /// paragraph makes a run of its own)
+    fn paragraph_chunks(text: &str, max_chars: usize) -> Vec<String> {
+        let mut chunks: Vec<String> = Vec::new();
+        for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+            match chunks.last_mut() {
+                Some(chunk) if chunk.len() + paragraph.len() + 2 <= max_chars => {
+                    chunk.push_str("\n\n");
+                    chunk.push_str(paragraph);
+                }
+                _ => chunks.push(paragraph.to_string()),
+            }
+        }
+        chunks
+    }
+
     /// Simple fallback summary (used when no LLM is loaded)
     fn simple_summary(subject: &str, from: &str, body_text: &str, word_count: usize) -> Result<String> {
         let words: Vec<&str> = body_text.split_whitespace().collect();
@@ -397,6 +914,36 @@ impl Summarizer {
         Ok(insights)
     }
 
+    /// Simple fallback sentiment (keyword-based)
+    pub fn simple_sentiment(subject: &str, body_text: &str) -> String {
+        let combined = format!("{} {}", subject, body_text).to_lowercase();
+        let count = |words: &[&str]| words.iter().filter(|w| combined.contains(*w)).count();
+
+        let frustrated = count(&[
+            "unacceptable", "ridiculous", "still waiting", "still not", "third time",
+            "again and again", "cancel my", "fed up", "furious", "escalate", "worst",
+        ]);
+        let negative = count(&[
+            "disappointed", "unfortunately", "complaint", "problem", "issue", "not working",
+            "broken", "refund", "unhappy", "sorry", "failed", "error",
+        ]);
+        let positive = count(&[
+            "thank you", "thanks", "great", "awesome", "appreciate", "congratulations",
+            "excellent", "love", "happy to", "well done",
+        ]);
+
+        let sentiment = if frustrated > 0 && frustrated + negative >= 2 {
+            "frustrated"
+        } else if negative > positive {
+            "negative"
+        } else if positive > negative {
+            "positive"
+        } else {
+            "neutral"
+        };
+        sentiment.to_string()
+    }
+
     /// Generate a conversational chat response
     pub fn chat(
         &self,
@@ -418,8 +965,10 @@ impl Summarizer {
             let prompt = self.format_prompt(system, &user);
             let params = GenerationParams {
                 max_tokens: 300,
-                temperature: 0.7,
+                temperature: engine.settings().temperature,
+                top_p: engine.settings().top_p,
                 stop_sequences: self.get_stop_sequences(),
+                cache_key: Some("chat".to_string()),
                 ..Default::default()
             };
 
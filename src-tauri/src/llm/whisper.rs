@@ -0,0 +1,291 @@
+//! Speech-to-text engine using Candle's Whisper
+//!
+//! Transcribes dictation from the composer on this machine. Whisper models are
+//! downloaded by `ModelManager` into `models/whisper/<id>/`; English-only models are
+//! smaller and more accurate for English, multilingual ones detect the spoken language.
+
+use anyhow::{anyhow, Result};
+use candle_core::{Device, IndexOp, Tensor};
+use candle_nn::ops::{log_softmax, softmax};
+use candle_nn::VarBuilder;
+use candle_transformers::models::whisper::{
+    self, audio::pcm_to_mel, model::Whisper, Config, EOT_TOKEN, HOP_LENGTH, LOGPROB_THRESHOLD,
+    NO_SPEECH_THRESHOLD, NO_SPEECH_TOKENS, NO_TIMESTAMPS_TOKEN, N_FFT, N_FRAMES, SOT_TOKEN,
+    TRANSCRIBE_TOKEN, TRANSLATE_TOKEN,
+};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use tokenizers::Tokenizer;
+use tracing::info;
+
+use super::audio::{self, SAMPLE_RATE};
+use crate::settings;
+
+/// Speech model downloaded when the user first dictates
+pub const DEFAULT_SPEECH_MODEL: &str = "whisper-base.en";
+
+/// Files each speech model needs, downloaded from its Hugging Face repo
+pub const SPEECH_MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Speech model option for users to choose from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechModelOption {
+    pub id: String,
+    pub name: String,
+    pub repo: String,
+    pub size_mb: u32,
+    pub description: String,
+    pub multilingual: bool,
+}
+
+/// Supported speech models
+pub fn get_available_speech_models() -> Vec<SpeechModelOption> {
+    vec![
+        SpeechModelOption {
+            id: "whisper-tiny.en".to_string(),
+            name: "Whisper Tiny (English)".to_string(),
+            repo: "openai/whisper-tiny.en".to_string(),
+            size_mb: 151,
+            description: "Fastest. Fine for short, clearly spoken notes.".to_string(),
+            multilingual: false,
+        },
+        SpeechModelOption {
+            id: DEFAULT_SPEECH_MODEL.to_string(),
+            name: "Whisper Base (English, Recommended)".to_string(),
+            repo: "openai/whisper-base.en".to_string(),
+            size_mb: 290,
+            description: "Good accuracy for English at real-time speed.".to_string(),
+            multilingual: false,
+        },
+        SpeechModelOption {
+            id: "whisper-base".to_string(),
+            name: "Whisper Base (Multilingual)".to_string(),
+            repo: "openai/whisper-base".to_string(),
+            size_mb: 290,
+            description: "Detects and transcribes 99 languages.".to_string(),
+            multilingual: true,
+        },
+        SpeechModelOption {
+            id: "whisper-small.en".to_string(),
+            name: "Whisper Small (English)".to_string(),
+            repo: "openai/whisper-small.en".to_string(),
+            size_mb: 967,
+            description: "Most accurate, several times slower than Base.".to_string(),
+            multilingual: false,
+        },
+    ]
+}
+
+/// Look up a supported speech model by id
+pub fn find_speech_model(model_id: &str) -> Option<SpeechModelOption> {
+    get_available_speech_models()
+        .into_iter()
+        .find(|m| m.id == model_id)
+}
+
+/// Whisper model with its tokenizer, ready to transcribe
+pub struct SpeechEngine {
+    model: Whisper,
+    tokenizer: Tokenizer,
+    device: Device,
+    model_id: String,
+    mel_filters: Vec<f32>,
+    /// Added to the logits: 0 for allowed tokens, -inf for the config's suppressed
+    /// tokens and the timestamp tokens
+    suppress: Tensor,
+    sot_token: u32,
+    eot_token: u32,
+    transcribe_token: u32,
+    no_timestamps_token: u32,
+    no_speech_token: Option<u32>,
+    /// Language tokens of multilingual models, which sit between SOT and translate
+    language_tokens: Option<Range<u32>>,
+}
+
+impl SpeechEngine {
+    /// Load the speech model whose files are in `dir`
+    #[tracing::instrument(skip_all, fields(model = %model_id))]
+    pub fn load(model_id: &str, dir: &Path) -> Result<Self> {
+        let config: Config =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("config.json"))?)?;
+        let tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+        let token = |name: &str| {
+            tokenizer
+                .token_to_id(name)
+                .ok_or_else(|| anyhow!("Tokenizer has no {} token", name))
+        };
+        let sot_token = token(SOT_TOKEN)?;
+        let eot_token = token(EOT_TOKEN)?;
+        let transcribe_token = token(TRANSCRIBE_TOKEN)?;
+        let no_timestamps_token = token(NO_TIMESTAMPS_TOKEN)?;
+        let no_speech_token = NO_SPEECH_TOKENS
+            .iter()
+            .find_map(|name| tokenizer.token_to_id(name));
+        let multilingual = find_speech_model(model_id).is_some_and(|option| option.multilingual);
+        let language_tokens = if multilingual {
+            Some(sot_token + 1..token(TRANSLATE_TOKEN)?)
+        } else {
+            None
+        };
+
+        // The GPU unless the user forced the CPU
+        let device = if settings::current().compute.allow_gpu() {
+            Device::new_metal(0).unwrap_or(Device::Cpu)
+        } else {
+            Device::Cpu
+        };
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(
+                &[dir.join("model.safetensors")],
+                whisper::DTYPE,
+                &device,
+            )?
+        };
+        let model = Whisper::load(&vb, config.clone())?;
+
+        let suppress: Vec<f32> = (0..config.vocab_size as u32)
+            .map(|id| {
+                if config.suppress_tokens.contains(&id) || id > no_timestamps_token {
+                    f32::NEG_INFINITY
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let suppress = Tensor::new(suppress.as_slice(), &device)?;
+        let mel_filters = audio::mel_filters(config.num_mel_bins, N_FFT, SAMPLE_RATE);
+
+        info!("Speech model loaded on {:?}", device);
+        Ok(Self {
+            model,
+            tokenizer,
+            device,
+            model_id: model_id.to_string(),
+            mel_filters,
+            suppress,
+            sot_token,
+            eot_token,
+            transcribe_token,
+            no_timestamps_token,
+            no_speech_token,
+            language_tokens,
+        })
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// Transcribe mono samples at `audio::SAMPLE_RATE`. Audio is decoded in 30-second
+    /// windows, the length Whisper is trained on, and silent windows are skipped.
+    pub fn transcribe(&mut self, samples: &[f32]) -> Result<String> {
+        let n_mels = self.model.config.num_mel_bins;
+        let mel = pcm_to_mel(&self.model.config, samples, &self.mel_filters);
+        let frames = mel.len() / n_mels;
+        let mel = Tensor::from_vec(mel, (1, n_mels, frames), &self.device)?;
+
+        // The spectrogram is padded with silence; only windows starting in the recording
+        // are decoded
+        let content_frames = samples.len().div_ceil(HOP_LENGTH);
+        let mut text = Vec::new();
+        let mut seek = 0;
+        while seek < content_frames {
+            let window = mel.narrow(2, seek, N_FRAMES.min(frames - seek))?;
+            let segment = self.decode_window(&window)?;
+            if !segment.is_empty() {
+                text.push(segment);
+            }
+            seek += N_FRAMES;
+        }
+        Ok(text.join(" "))
+    }
+
+    /// Greedily decode one window of the spectrogram
+    fn decode_window(&mut self, mel: &Tensor) -> Result<String> {
+        let audio_features = self.model.encoder.forward(mel, true)?;
+
+        let mut tokens = vec![self.sot_token];
+        if let Some(languages) = self.language_tokens.clone() {
+            let language = self.detect_language(&audio_features, languages)?;
+            tokens.extend([language, self.transcribe_token]);
+        }
+        tokens.push(self.no_timestamps_token);
+        let prompt_len = tokens.len();
+
+        let max_tokens = self.model.config.max_target_positions;
+        let mut no_speech_prob = 0.0;
+        let mut sum_logprob = 0.0;
+        for i in 0..max_tokens / 2 {
+            let input = Tensor::new(tokens.as_slice(), &self.device)?.unsqueeze(0)?;
+            let ys = self
+                .model
+                .decoder
+                .forward(&input, &audio_features, i == 0)?;
+
+            // How likely the window is to be silence, judged from the SOT position
+            if i == 0 {
+                if let Some(no_speech_token) = self.no_speech_token {
+                    let logits = self
+                        .model
+                        .decoder
+                        .final_linear(&ys.i((..1, ..1))?)?
+                        .i(0)?
+                        .i(0)?;
+                    no_speech_prob = softmax(&logits, 0)?
+                        .i(no_speech_token as usize)?
+                        .to_scalar::<f32>()? as f64;
+                }
+            }
+
+            let (_, seq_len, _) = ys.dims3()?;
+            let logits = self
+                .model
+                .decoder
+                .final_linear(&ys.i((..1, seq_len - 1..))?)?
+                .i(0)?
+                .i(0)?
+                .broadcast_add(&self.suppress)?;
+            let next = logits.argmax(0)?.to_scalar::<u32>()?;
+            if next == self.eot_token {
+                break;
+            }
+            sum_logprob += log_softmax(&logits, 0)?
+                .i(next as usize)?
+                .to_scalar::<f32>()? as f64;
+            tokens.push(next);
+            if tokens.len() >= max_tokens {
+                break;
+            }
+        }
+
+        let generated = &tokens[prompt_len..];
+        let avg_logprob = sum_logprob / generated.len().max(1) as f64;
+        if no_speech_prob > NO_SPEECH_THRESHOLD && avg_logprob < LOGPROB_THRESHOLD {
+            return Ok(String::new());
+        }
+        let text = self
+            .tokenizer
+            .decode(generated, true)
+            .map_err(|e| anyhow!("Failed to decode transcript: {}", e))?;
+        Ok(text.trim().to_string())
+    }
+
+    /// The most likely language token after SOT
+    fn detect_language(&mut self, audio_features: &Tensor, languages: Range<u32>) -> Result<u32> {
+        let input = Tensor::new(&[self.sot_token], &self.device)?.unsqueeze(0)?;
+        let ys = self.model.decoder.forward(&input, audio_features, true)?;
+        let logits = self
+            .model
+            .decoder
+            .final_linear(&ys.i((..1, ..1))?)?
+            .i(0)?
+            .i(0)?;
+        let best = logits
+            .narrow(0, languages.start as usize, languages.len())?
+            .argmax(0)?
+            .to_scalar::<u32>()?;
+        Ok(languages.start + best)
+    }
+}
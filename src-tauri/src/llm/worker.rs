@@ -0,0 +1,182 @@
+//! Dedicated LLM worker thread
+//!
+//! The summarizer (and the model it holds) is owned by a single worker thread instead of a
+//! global mutex. Callers submit jobs to one of two bounded queues: interactive work (chat,
+//! compose, on-demand summaries) always runs before queued background work (indexing).
+//! A full queue applies backpressure — submitting waits until there is room.
+//! The worker remembers when its last job finished so an idle model can be freed.
+
+use anyhow::{anyhow, Result};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info};
+
+use super::summarizer::Summarizer;
+
+/// Pending interactive jobs before submitters wait
+const INTERACTIVE_QUEUE_SIZE: usize = 16;
+/// Pending background jobs before submitters wait (keeps indexing from flooding the queue)
+const BACKGROUND_QUEUE_SIZE: usize = 4;
+
+/// Scheduling class of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// User is waiting on the result (chat, compose, summaries)
+    Interactive,
+    /// Batch work such as indexing; runs only when no interactive job is queued
+    Background,
+}
+
+type Job = Box<dyn FnOnce(&mut Option<Summarizer>) + Send>;
+
+/// Handle to the LLM worker thread
+pub struct LlmWorker {
+    interactive: mpsc::Sender<Job>,
+    background: mpsc::Sender<Job>,
+    initialized: Arc<AtomicBool>,
+    model_loaded: Arc<AtomicBool>,
+    /// Unix time (seconds) the last job finished
+    last_used: Arc<AtomicU64>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn idle_since(last_used: &AtomicU64) -> Duration {
+    Duration::from_secs(now_secs().saturating_sub(last_used.load(Ordering::Relaxed)))
+}
+
+impl LlmWorker {
+    /// Start the worker thread with no summarizer installed
+    pub fn spawn() -> Result<Self> {
+        let (interactive, mut interactive_rx) = mpsc::channel::<Job>(INTERACTIVE_QUEUE_SIZE);
+        let (background, mut background_rx) = mpsc::channel::<Job>(BACKGROUND_QUEUE_SIZE);
+        let initialized = Arc::new(AtomicBool::new(false));
+        let model_loaded = Arc::new(AtomicBool::new(false));
+        let last_used = Arc::new(AtomicU64::new(now_secs()));
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        let initialized_flag = initialized.clone();
+        let model_loaded_flag = model_loaded.clone();
+        let last_used_time = last_used.clone();
+
+        std::thread::Builder::new()
+            .name("llm-worker".to_string())
+            .spawn(move || {
+                let mut summarizer: Option<Summarizer> = None;
+
+                runtime.block_on(async {
+                    loop {
+                        // `biased` polls the interactive queue first
+                        let job = tokio::select! {
+                            biased;
+                            Some(job) = interactive_rx.recv() => job,
+                            Some(job) = background_rx.recv() => job,
+                            else => break,
+                        };
+
+                        // A panicking job drops its result channel; keep the worker alive
+                        if panic::catch_unwind(AssertUnwindSafe(|| job(&mut summarizer))).is_err() {
+                            error!("LLM worker job panicked");
+                        }
+
+                        initialized_flag.store(summarizer.is_some(), Ordering::Relaxed);
+                        model_loaded_flag.store(
+                            summarizer.as_ref().is_some_and(Summarizer::is_model_loaded),
+                            Ordering::Relaxed,
+                        );
+                        last_used_time.store(now_secs(), Ordering::Relaxed);
+                    }
+                });
+
+                info!("LLM worker stopped");
+            })?;
+
+        Ok(Self {
+            interactive,
+            background,
+            initialized,
+            model_loaded,
+            last_used,
+        })
+    }
+
+    /// Run `f` on the worker thread with access to the summarizer slot and wait for its result
+    pub async fn run<T, F>(&self, priority: Priority, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Option<Summarizer>) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move |summarizer| {
+            let _ = tx.send(f(summarizer));
+        });
+
+        let queue = match priority {
+            Priority::Interactive => &self.interactive,
+            Priority::Background => &self.background,
+        };
+        queue
+            .send(job)
+            .await
+            .map_err(|_| anyhow!("LLM worker is not running"))?;
+
+        rx.await.map_err(|_| anyhow!("LLM job failed"))
+    }
+
+    /// Install (or remove) the summarizer
+    pub async fn set_summarizer(&self, summarizer: Option<Summarizer>) -> Result<()> {
+        self.run(Priority::Interactive, move |slot| *slot = summarizer)
+            .await
+    }
+
+    /// Drop the loaded model to free its memory, keeping a model-less summarizer installed
+    /// so keyword fallbacks keep working. Returns whether a model was loaded.
+    pub async fn unload(&self) -> Result<bool> {
+        self.run(Priority::Interactive, |slot| {
+            let loaded = slot.as_ref().is_some_and(Summarizer::is_model_loaded);
+            if loaded {
+                *slot = Summarizer::new().ok();
+            }
+            loaded
+        })
+        .await
+    }
+
+    /// Like `unload`, but only if no job has finished within `timeout`. The check runs on
+    /// the worker, after anything queued ahead of it, so a model in use is never dropped.
+    pub async fn unload_if_idle(&self, timeout: Duration) -> Result<bool> {
+        let last_used = self.last_used.clone();
+        self.run(Priority::Background, move |slot| {
+            let loaded = slot.as_ref().is_some_and(Summarizer::is_model_loaded);
+            if loaded && idle_since(&last_used) >= timeout {
+                *slot = Summarizer::new().ok();
+                true
+            } else {
+                false
+            }
+        })
+        .await
+    }
+
+    /// Time since the last job finished
+    pub fn idle_for(&self) -> Duration {
+        idle_since(&self.last_used)
+    }
+
+    /// Whether a summarizer is installed (with or without a model)
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Relaxed)
+    }
+
+    /// Whether the installed summarizer has a model loaded; doesn't wait for queued jobs
+    pub fn is_model_loaded(&self) -> bool {
+        self.model_loaded.load(Ordering::Relaxed)
+    }
+}
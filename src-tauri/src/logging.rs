@@ -0,0 +1,174 @@
+//! Logging
+//!
+//! Everything logs through `tracing`. Events go to stderr and to a daily log file under
+//! `<data dir>/logs`, of which the last week is kept. `RUST_LOG` overrides the default
+//! filter. The log files are what `get_recent_logs` shows and what `export_diagnostics`
+//! bundles for bug reports, with email addresses masked.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+/// Directory of the log files, inside the data directory
+pub const LOG_DIR: &str = "logs";
+
+const LOG_FILE_PREFIX: &str = "inboxed";
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Daily log files kept
+const MAX_LOG_FILES: usize = 7;
+
+/// Our own info messages and up, and only warnings from dependencies
+const DEFAULT_FILTER: &str = "warn,inboxed_lib=info";
+
+/// Keeps the background writer flushing to the log file while the app runs
+static FILE_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// Install the global subscriber. If the log directory can't be used, logs only go to
+/// stderr.
+pub fn init(data_dir: &Path) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(data_dir.join(LOG_DIR));
+    let (file_layer, file_error) = match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = FILE_GUARD.set(guard);
+            (
+                Some(fmt::layer().with_ansi(false).with_writer(writer)),
+                None,
+            )
+        }
+        Err(e) => (None, Some(e)),
+    };
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .try_init();
+    if let Some(e) = file_error {
+        tracing::warn!("Failed to open the log file, logging to stderr only: {}", e);
+    }
+}
+
+/// Log files in `data_dir`, oldest first
+pub fn log_files(data_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(data_dir.join(LOG_DIR)) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX)
+                })
+        })
+        .collect();
+    // Names carry the date, so they sort by age
+    files.sort();
+    files
+}
+
+/// The last `max_lines` lines logged, oldest first
+pub fn recent_logs(data_dir: &Path, max_lines: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for file in log_files(data_dir).iter().rev() {
+        let Ok(content) = fs::read_to_string(file) else {
+            continue;
+        };
+        let needed = max_lines - lines.len();
+        let file_lines: Vec<&str> = content.lines().collect();
+        let start = file_lines.len().saturating_sub(needed);
+        // Collected newest file first, so older lines go in front
+        lines.splice(
+            0..0,
+            file_lines[start..].iter().map(|line| line.to_string()),
+        );
+        if lines.len() >= max_lines {
+            break;
+        }
+    }
+    lines
+}
+
+/// Mask the local part of email addresses, keeping its first character and the domain
+/// (`ada@example.com` becomes `a***@example.com`)
+pub fn mask_emails(text: &str) -> String {
+    let is_local = |c: char| c.is_alphanumeric() || "._%+-".contains(c);
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(at) = rest.find('@') {
+        let local_start = rest[..at]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| is_local(c))
+            .last()
+            .map_or(at, |(i, _)| i);
+        let has_domain = rest[at + 1..]
+            .split(|c: char| !(c.is_alphanumeric() || c == '.' || c == '-'))
+            .next()
+            .is_some_and(|domain| domain.contains('.'));
+
+        masked.push_str(&rest[..local_start]);
+        match rest[local_start..at].chars().next() {
+            Some(first) if has_domain => {
+                masked.push(first);
+                masked.push_str("***@");
+            }
+            _ => masked.push_str(&rest[local_start..=at]),
+        }
+        rest = &rest[at + 1..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_emails() {
+        assert_eq!(
+            mask_emails("Failed to cache ada.lovelace@example.com:INBOX:42: timeout"),
+            "Failed to cache a***@example.com:INBOX:42: timeout"
+        );
+        assert_eq!(
+            mask_emails("to bob@mail.example.org, carol+news@example.com"),
+            "to b***@mail.example.org, c***@example.com"
+        );
+        assert_eq!(
+            mask_emails("user@localhost and @mention"),
+            "user@localhost and @mention"
+        );
+    }
+
+    #[test]
+    fn test_recent_logs() {
+        let data_dir = std::env::temp_dir().join(format!("inboxed-logs-{}", std::process::id()));
+        let log_dir = data_dir.join(LOG_DIR);
+        fs::create_dir_all(&log_dir).unwrap();
+        fs::write(log_dir.join("inboxed.2026-01-01.log"), "a\nb\nc\n").unwrap();
+        fs::write(log_dir.join("inboxed.2026-01-02.log"), "d\ne\n").unwrap();
+        fs::write(log_dir.join("notes.txt"), "x\n").unwrap();
+
+        assert_eq!(recent_logs(&data_dir, 3), vec!["c", "d", "e"]);
+        assert_eq!(recent_logs(&data_dir, 10).len(), 5);
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! Where the app keeps its files
+//!
+//! Everything lives under one root: the platform's app data directory, a `data`
+//! directory next to the executable when a `portable` marker file sits beside it, or
+//! the directory named by `INBOXED_DATA_DIR`, which wins over both and is what tests use
+//! to stay isolated. A named profile (`--profile work` or `INBOXED_PROFILE=work`) keeps
+//! its database, caches, settings and tokens in `profiles/<name>` under the root and
+//! its secrets under its own keychain service, so profiles never see each other's mail.
+//! Downloaded models are large and identical for every profile, so they stay shared.
+
+use anyhow::{anyhow, Result};
+use directories::ProjectDirs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Environment variable that overrides the root data directory
+pub const DATA_DIR_ENV: &str = "INBOXED_DATA_DIR";
+
+/// Environment variable that selects a profile
+pub const PROFILE_ENV: &str = "INBOXED_PROFILE";
+
+/// Command-line flag that selects a profile, winning over `INBOXED_PROFILE`
+const PROFILE_ARG: &str = "--profile";
+
+/// File next to the executable that turns on portable mode
+const PORTABLE_MARKER: &str = "portable";
+
+/// The email database, in the profile directory
+pub const EMAIL_DB_FILE: &str = "emails.db";
+
+struct DataPaths {
+    root: PathBuf,
+    profile: Option<String>,
+    portable: bool,
+}
+
+static PATHS: OnceLock<Result<DataPaths, String>> = OnceLock::new();
+
+fn resolve() -> Result<&'static DataPaths> {
+    PATHS
+        .get_or_init(|| {
+            let profile = profile_arg(std::env::args())
+                .or_else(|| std::env::var(PROFILE_ENV).ok())
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty());
+            if let Some(name) = &profile {
+                if !is_valid_profile_name(name) {
+                    return Err(format!(
+                        "Invalid profile name '{}': use letters, digits, '-' and '_'",
+                        name
+                    ));
+                }
+            }
+
+            if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+                return Ok(DataPaths {
+                    root: PathBuf::from(dir),
+                    profile,
+                    portable: true,
+                });
+            }
+            if let Some(dir) = portable_root() {
+                return Ok(DataPaths {
+                    root: dir,
+                    profile,
+                    portable: true,
+                });
+            }
+            let project_dirs = ProjectDirs::from("com", "inboxed", "inboxed")
+                .ok_or("Failed to get project directory")?;
+            Ok(DataPaths {
+                root: project_dirs.data_dir().to_path_buf(),
+                profile,
+                portable: false,
+            })
+        })
+        .as_ref()
+        .map_err(|e| anyhow!("{}", e))
+}
+
+/// `data` next to the executable when the portable marker is there
+fn portable_root() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let dir = exe.parent()?;
+    dir.join(PORTABLE_MARKER).exists().then(|| dir.join("data"))
+}
+
+/// The value of `--profile <name>` or `--profile=<name>` among the arguments
+fn profile_arg(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == PROFILE_ARG {
+            return args.next();
+        }
+        if let Some(name) = arg
+            .strip_prefix(PROFILE_ARG)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Profile names become directory names and keychain service suffixes
+fn is_valid_profile_name(name: &str) -> bool {
+    name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The directory of `profile` under `root`; the default profile uses the root itself,
+/// which is where data lived before profiles existed
+fn profile_dir(root: &Path, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => root.join("profiles").join(name),
+        None => root.to_path_buf(),
+    }
+}
+
+/// The keychain service for `base` in `profile`
+fn profile_service(base: &str, profile: Option<&str>) -> String {
+    match profile {
+        Some(name) => format!("{}.{}", base, name),
+        None => base.to_string(),
+    }
+}
+
+/// The data directory of the active profile
+pub fn data_dir() -> Result<PathBuf> {
+    let paths = resolve()?;
+    Ok(profile_dir(&paths.root, paths.profile.as_deref()))
+}
+
+/// The email database of the active profile
+pub fn email_db_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(EMAIL_DB_FILE))
+}
+
+/// Downloaded models, shared by every profile
+pub fn models_dir() -> Result<PathBuf> {
+    Ok(resolve()?.root.join("models"))
+}
+
+/// The active profile, `None` for the default one
+pub fn profile() -> Option<&'static str> {
+    resolve().ok().and_then(|paths| paths.profile.as_deref())
+}
+
+/// Whether the data directory was chosen by `INBOXED_DATA_DIR` or portable mode
+pub fn is_portable() -> bool {
+    resolve().map(|paths| paths.portable).unwrap_or(false)
+}
+
+/// The keychain service name for `base` in the active profile
+pub fn keychain_service(base: &str) -> String {
+    profile_service(base, profile())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_profile_arg() {
+        assert_eq!(
+            profile_arg(args(&["inboxed", "--profile", "work"])).as_deref(),
+            Some("work")
+        );
+        assert_eq!(
+            profile_arg(args(&["inboxed", "--profile=personal"])).as_deref(),
+            Some("personal")
+        );
+        assert_eq!(profile_arg(args(&["inboxed", "--profiles"])), None);
+        assert_eq!(profile_arg(args(&["inboxed", "--profile"])), None);
+        assert_eq!(profile_arg(args(&["inboxed"])), None);
+    }
+
+    #[test]
+    fn test_profile_name() {
+        assert!(is_valid_profile_name("work"));
+        assert!(is_valid_profile_name("client_2-eu"));
+        assert!(!is_valid_profile_name("../work"));
+        assert!(!is_valid_profile_name("my work"));
+        assert!(!is_valid_profile_name(&"a".repeat(65)));
+    }
+
+    #[test]
+    fn test_profile_dir() {
+        let root = Path::new("/data/inboxed");
+        assert_eq!(profile_dir(root, None), root);
+        assert_eq!(
+            profile_dir(root, Some("work")),
+            Path::new("/data/inboxed/profiles/work")
+        );
+        assert_eq!(profile_service("com.inboxed.app", None), "com.inboxed.app");
+        assert_eq!(
+            profile_service("com.inboxed.app", Some("work")),
+            "com.inboxed.app.work"
+        );
+    }
+}
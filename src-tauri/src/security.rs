@@ -0,0 +1,407 @@
+//! App lock
+//!
+//! With the lock set up, commands that return email content refuse to answer until the
+//! app is unlocked with the passphrase, or with the OS biometric prompt on platforms that
+//! have one. The app starts locked and locks itself again after a stretch without any of
+//! those commands. The passphrase is kept only as an Argon2 hash, in `app_lock.json` in
+//! the data directory, apart from the settings so `update_settings` can't turn the lock
+//! off. A lock file that exists but can't be read keeps the app locked rather than
+//! leaving it open: the passphrase is recovered from it when possible, and unlocking
+//! with it writes the file out again.
+//!
+//! The lock keeps mail off the screen of an unattended app; it doesn't encrypt the mail
+//! stored on disk.
+
+use anyhow::{anyhow, bail, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const LOCK_FILE: &str = "app_lock.json";
+
+/// Minutes without activity before the app locks itself unless set otherwise
+pub const DEFAULT_AUTO_LOCK_MINUTES: u32 = 5;
+
+const MIN_PASSPHRASE_CHARS: usize = 6;
+
+/// Whether this platform has a biometric prompt to unlock with
+pub const BIOMETRIC_AVAILABLE: bool = cfg!(mobile);
+
+static LOCK: Mutex<LockState> = Mutex::new(LockState {
+    config: None,
+    damaged: None,
+    locked: false,
+    last_activity: None,
+});
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppLockConfig {
+    /// Argon2 hash of the passphrase as a PHC string
+    pub passphrase_hash: String,
+    /// Also unlock with the OS biometric prompt
+    #[serde(default)]
+    pub biometric: bool,
+    /// Lock after this many minutes without activity; 0 locks only when asked to
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u32,
+}
+
+fn default_auto_lock_minutes() -> u32 {
+    DEFAULT_AUTO_LOCK_MINUTES
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppLockStatus {
+    pub enabled: bool,
+    pub locked: bool,
+    pub biometric: bool,
+    pub biometric_available: bool,
+    pub auto_lock_minutes: u32,
+    /// Why the lock file couldn't be read, while it still needs repairing
+    pub damaged: Option<String>,
+}
+
+struct LockState {
+    config: Option<AppLockConfig>,
+    /// Why the lock file couldn't be read. Until the passphrase repairs it the app
+    /// stays locked, even with no passphrase recovered from it to unlock with.
+    damaged: Option<String>,
+    locked: bool,
+    last_activity: Option<Instant>,
+}
+
+impl LockState {
+    /// Whether the app is locked at `now`, locking it first if it has been idle too long
+    fn check(&mut self, now: Instant) -> bool {
+        let Some(config) = &self.config else {
+            self.locked = self.damaged.is_some();
+            return self.locked;
+        };
+        if !self.locked && config.auto_lock_minutes > 0 {
+            let timeout = Duration::from_secs(u64::from(config.auto_lock_minutes) * 60);
+            let idle = self
+                .last_activity
+                .map_or(Duration::ZERO, |at| now.saturating_duration_since(at));
+            if idle >= timeout {
+                self.locked = true;
+            }
+        }
+        self.locked
+    }
+
+    fn unlock(&mut self, now: Instant) {
+        self.locked = false;
+        self.last_activity = Some(now);
+    }
+
+    /// Whether there is a lock to lock the app with
+    fn enabled(&self) -> bool {
+        self.config.is_some() || self.damaged.is_some()
+    }
+
+    fn status(&self) -> AppLockStatus {
+        AppLockStatus {
+            enabled: self.enabled(),
+            locked: self.enabled() && self.locked,
+            biometric: self.config.as_ref().is_some_and(|c| c.biometric),
+            biometric_available: BIOMETRIC_AVAILABLE,
+            auto_lock_minutes: self
+                .config
+                .as_ref()
+                .map_or(DEFAULT_AUTO_LOCK_MINUTES, |c| c.auto_lock_minutes),
+            damaged: self.damaged.clone(),
+        }
+    }
+
+    /// Fails while the lock file is damaged with no passphrase recovered from it
+    fn usable_config(&self) -> Result<Option<&AppLockConfig>> {
+        match (&self.config, &self.damaged) {
+            (None, Some(error)) => bail!(
+                "The app lock file is damaged ({}) and no passphrase could be recovered from \
+                 it. Remove {} to turn the lock off.",
+                error,
+                LOCK_FILE
+            ),
+            (config, _) => Ok(config.as_ref()),
+        }
+    }
+}
+
+/// Load the lock at startup; the app starts locked when it's set up. Only a missing
+/// lock file means there is no lock: one that can't be read locks the app too.
+pub fn init() {
+    let (config, damaged) = match read_config() {
+        Ok(config) => (config, None),
+        Err(e) => {
+            warn!("Failed to read the app lock, starting locked: {}", e);
+            let recovered = lock_file()
+                .and_then(|path| Ok(fs::read(path)?))
+                .ok()
+                .and_then(|raw| recover_config(&String::from_utf8_lossy(&raw)));
+            (recovered, Some(e.to_string()))
+        }
+    };
+    let mut state = LOCK.lock().unwrap();
+    state.config = config;
+    state.damaged = damaged;
+    state.locked = state.enabled();
+}
+
+pub fn status() -> AppLockStatus {
+    let mut state = LOCK.lock().unwrap();
+    state.check(Instant::now());
+    state.status()
+}
+
+/// Fails while the app is locked; otherwise counts as activity for the auto-lock.
+/// Called first by every command that returns email content.
+pub fn ensure_unlocked() -> Result<(), String> {
+    let mut state = LOCK.lock().unwrap();
+    let now = Instant::now();
+    if state.check(now) {
+        return Err("Inboxed is locked".to_string());
+    }
+    state.last_activity = Some(now);
+    Ok(())
+}
+
+/// Lock the app now; false if the lock isn't set up
+pub fn lock() -> bool {
+    let mut state = LOCK.lock().unwrap();
+    state.locked = state.enabled();
+    state.locked
+}
+
+/// Lock the app if it has been idle past its timeout. True if that locked it.
+pub fn lock_if_idle() -> bool {
+    let mut state = LOCK.lock().unwrap();
+    let was_locked = state.locked;
+    state.check(Instant::now()) && !was_locked
+}
+
+/// Unlock with the passphrase, writing a damaged lock file out again with it
+pub fn unlock_with_passphrase(passphrase: &str) -> Result<()> {
+    let mut state = LOCK.lock().unwrap();
+    let Some(config) = state.usable_config()? else {
+        return Ok(());
+    };
+    if !verify_passphrase(passphrase, &config.passphrase_hash)? {
+        bail!("Wrong passphrase");
+    }
+    if state.damaged.is_some() {
+        write_config(Some(config))?;
+        state.damaged = None;
+    }
+    state.unlock(Instant::now());
+    Ok(())
+}
+
+/// Unlock after the OS biometric prompt succeeded
+pub fn unlock_with_biometric() -> Result<()> {
+    let mut state = LOCK.lock().unwrap();
+    if state.damaged.is_some() {
+        bail!("Enter the passphrase");
+    }
+    match &state.config {
+        Some(config) if !config.biometric => bail!("Biometric unlock is turned off"),
+        _ => {}
+    }
+    state.unlock(Instant::now());
+    Ok(())
+}
+
+/// Set up the lock, or change it when it is already set up, which takes the current
+/// passphrase. The app stays unlocked.
+pub fn configure(
+    passphrase: &str,
+    current_passphrase: Option<&str>,
+    biometric: bool,
+    auto_lock_minutes: u32,
+) -> Result<AppLockStatus> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        bail!(
+            "The passphrase must be at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        );
+    }
+    if biometric && !BIOMETRIC_AVAILABLE {
+        bail!("Biometric unlock isn't available on this platform");
+    }
+
+    let mut state = LOCK.lock().unwrap();
+    if let Some(config) = state.usable_config()? {
+        let current = current_passphrase.ok_or_else(|| anyhow!("Enter the current passphrase"))?;
+        if !verify_passphrase(current, &config.passphrase_hash)? {
+            bail!("Wrong passphrase");
+        }
+    }
+
+    let config = AppLockConfig {
+        passphrase_hash: hash_passphrase(passphrase)?,
+        biometric,
+        auto_lock_minutes,
+    };
+    write_config(Some(&config))?;
+    state.config = Some(config);
+    state.damaged = None;
+    state.unlock(Instant::now());
+    Ok(state.status())
+}
+
+/// Turn the lock off, which takes the passphrase
+pub fn disable(passphrase: &str) -> Result<()> {
+    let mut state = LOCK.lock().unwrap();
+    let Some(config) = state.usable_config()? else {
+        return Ok(());
+    };
+    if !verify_passphrase(passphrase, &config.passphrase_hash)? {
+        bail!("Wrong passphrase");
+    }
+    write_config(None)?;
+    state.config = None;
+    state.damaged = None;
+    state.locked = false;
+    Ok(())
+}
+
+fn hash_passphrase(passphrase: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Failed to hash the passphrase: {}", e))?;
+    Ok(hash.to_string())
+}
+
+fn verify_passphrase(passphrase: &str, hash: &str) -> Result<bool> {
+    let hash = PasswordHash::new(hash)
+        .map_err(|e| anyhow!("The stored passphrase is unreadable: {}", e))?;
+    Ok(Argon2::default()
+        .verify_password(passphrase.as_bytes(), &hash)
+        .is_ok())
+}
+
+fn lock_file() -> Result<PathBuf> {
+    Ok(crate::paths::data_dir()?.join(LOCK_FILE))
+}
+
+fn read_config() -> Result<Option<AppLockConfig>> {
+    let path = lock_file()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// A lock with the passphrase hash found in an unreadable lock file, if one is intact
+fn recover_config(raw: &str) -> Option<AppLockConfig> {
+    let start = raw.find("$argon2")?;
+    let hash = raw[start..]
+        .split(|c: char| c == '"' || c.is_whitespace())
+        .next()?;
+    PasswordHash::new(hash).ok()?.hash?;
+    Some(AppLockConfig {
+        passphrase_hash: hash.to_string(),
+        biometric: false,
+        auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
+    })
+}
+
+fn write_config(config: Option<&AppLockConfig>) -> Result<()> {
+    let path = lock_file()?;
+    match config {
+        Some(config) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, serde_json::to_string_pretty(config)?)?;
+        }
+        None if path.exists() => fs::remove_file(path)?,
+        None => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(auto_lock_minutes: u32) -> LockState {
+        LockState {
+            config: Some(AppLockConfig {
+                passphrase_hash: String::new(),
+                biometric: false,
+                auto_lock_minutes,
+            }),
+            damaged: None,
+            locked: false,
+            last_activity: Some(Instant::now()),
+        }
+    }
+
+    #[test]
+    fn test_passphrase_hash_round_trip() {
+        let hash = hash_passphrase("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_passphrase("correct horse", &hash).unwrap());
+        assert!(!verify_passphrase("wrong horse", &hash).unwrap());
+        assert_ne!(hash, hash_passphrase("correct horse").unwrap());
+    }
+
+    #[test]
+    fn test_auto_lock_after_idle_timeout() {
+        let mut lock = state(5);
+        let start = lock.last_activity.unwrap();
+        assert!(!lock.check(start + Duration::from_secs(4 * 60)));
+        assert!(lock.check(start + Duration::from_secs(5 * 60)));
+
+        lock.unlock(start + Duration::from_secs(6 * 60));
+        assert!(!lock.check(start + Duration::from_secs(7 * 60)));
+    }
+
+    #[test]
+    fn test_no_auto_lock_when_timeout_is_zero() {
+        let mut lock = state(0);
+        let start = lock.last_activity.unwrap();
+        assert!(!lock.check(start + Duration::from_secs(24 * 3600)));
+    }
+
+    #[test]
+    fn test_never_locked_without_config() {
+        let mut lock = state(5);
+        lock.config = None;
+        lock.locked = true;
+        assert!(!lock.check(Instant::now()));
+        assert!(!lock.status().locked);
+    }
+
+    #[test]
+    fn test_damaged_lock_file_stays_locked() {
+        let mut lock = state(0);
+        lock.config = None;
+        lock.damaged = Some("expected value at line 1".to_string());
+        assert!(lock.check(Instant::now()));
+        assert!(lock.status().enabled && lock.status().locked);
+        assert!(lock.usable_config().is_err());
+    }
+
+    #[test]
+    fn test_recover_passphrase_from_damaged_lock_file() {
+        let hash = hash_passphrase("correct horse").unwrap();
+        let raw = format!(
+            "{{\n  \"passphrase_hash\": \"{}\",\n  \"biometric\": tru",
+            hash
+        );
+        let config = recover_config(&raw).unwrap();
+        assert!(verify_passphrase("correct horse", &config.passphrase_hash).unwrap());
+        assert!(!config.biometric);
+
+        assert!(recover_config("{\"passphrase_hash\": \"$argon2id$v=19$m=1").is_none());
+        assert!(recover_config("\0\0\0").is_none());
+    }
+}
@@ -0,0 +1,399 @@
+//! App settings
+//!
+//! Settings are kept in one `settings.json` in the data directory, grouped by subsystem,
+//! and held in memory once read so any part of the app can call `current()`. The
+//! frontend reads and patches them with `get_settings` and `update_settings` and hears
+//! about every change through a `settings:changed` event. When there is no settings file
+//! yet, it is created from the older `cache_settings.json` and `compute_settings.json`
+//! and the LLM parameters saved in the database. Credentials stay in the system keychain,
+//! and per-account settings such as notifications stay in the database.
+
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+use tracing::warn;
+
+use crate::db::EmailDatabase;
+use crate::email::attachments::DEFAULT_AUTO_DOWNLOAD_MB;
+use crate::email::{language, ocr};
+use crate::llm::compute::ComputeSettings;
+use crate::llm::LlmSettings;
+use crate::paths;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Files the settings used to be spread across, read once to create `SETTINGS_FILE`
+const LEGACY_CACHE_FILE: &str = "cache_settings.json";
+const LEGACY_COMPUTE_FILE: &str = "compute_settings.json";
+
+/// Minutes between inbox refreshes alongside IDLE unless the settings say otherwise
+pub const DEFAULT_POLLING_INTERVAL_MINUTES: u32 = 10;
+
+/// Days between database maintenance runs unless the settings say otherwise
+pub const DEFAULT_MAINTENANCE_INTERVAL_DAYS: u32 = 7;
+
+static SETTINGS: RwLock<Option<AppSettings>> = RwLock::new(None);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub cache: CacheSettings,
+    pub sync: SyncSettings,
+    pub compute: ComputeSettings,
+    pub llm: LlmSettings,
+    pub ai: AiSettings,
+    pub auth: AuthSettings,
+    pub privacy: PrivacySettings,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub cache_enabled: bool,
+    pub auto_sync_on_start: bool,
+    pub cache_media_assets: bool,
+    pub max_cache_age_days: u32,
+    /// Attachments up to this many MB are downloaded while syncing; 0 downloads them
+    /// only when opened
+    pub auto_download_attachments_mb: u32,
+    /// Show a message cached in several folders or accounts once in the smart inbox and
+    /// search results
+    pub collapse_duplicates: bool,
+    /// Days between automatic database maintenance runs; 0 runs it only on request
+    pub maintenance_interval_days: u32,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            cache_enabled: true,
+            auto_sync_on_start: false,
+            cache_media_assets: true,
+            max_cache_age_days: 30,
+            auto_download_attachments_mb: DEFAULT_AUTO_DOWNLOAD_MB,
+            collapse_duplicates: true,
+            maintenance_interval_days: DEFAULT_MAINTENANCE_INTERVAL_DAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// How often the inbox is refreshed in case IDLE missed something
+    pub polling_interval_minutes: u32,
+}
+
+impl Default for SyncSettings {
+    fn default() -> Self {
+        Self {
+            polling_interval_minutes: DEFAULT_POLLING_INTERVAL_MINUTES,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AiSettings {
+    /// Model loaded by `init_ai`; the first downloaded model when unset or deleted
+    pub model_id: Option<String>,
+    /// Load the model and embedding engine in the background when the app starts, if
+    /// they're downloaded
+    pub load_on_startup: bool,
+    /// Language summaries are written in, by ISO 639-3 code or name; each email's own
+    /// language when unset
+    pub summary_language: Option<String>,
+    /// Speech model used for dictation; the first downloaded one when unset or deleted
+    pub speech_model_id: Option<String>,
+    /// Read the text in downloaded image and scanned PDF attachments with Tesseract, so
+    /// receipts and scanned letters can be searched and summarized. Off by default
+    /// because large scans take a lot of CPU.
+    pub ocr_enabled: bool,
+    /// Tesseract languages to read, such as `eng` or `eng+deu`
+    pub ocr_languages: String,
+    /// Tesseract executable; looked up on the PATH when unset. Belongs to this machine
+    /// like the compute settings, so it isn't exported or imported with the config.
+    pub tesseract_path: Option<String>,
+    /// Reorder the best semantic matches for a chat question with a cross-encoder before
+    /// choosing the emails the model reads. Off by default: it needs a second model and
+    /// adds a moment to each answer.
+    pub rerank_results: bool,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            model_id: None,
+            load_on_startup: true,
+            summary_language: None,
+            speech_model_id: None,
+            ocr_enabled: false,
+            ocr_languages: ocr::DEFAULT_LANGUAGES.to_string(),
+            tesseract_path: None,
+            rerank_results: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthSettings {
+    /// Port the OAuth sign-in redirect is received on; 0 picks a free port each time.
+    /// Set it when the provider app only allows a fixed redirect URI.
+    pub oauth_callback_port: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Look up tracked packages on the carriers' websites, which tells the carrier the
+    /// tracking number and this computer's address
+    pub check_shipment_status: bool,
+    /// Show a sender's domain logo (BIMI logo or favicon) when they have no Gravatar,
+    /// which fetches from servers the sender controls and so tells them the email was
+    /// seen
+    pub fetch_domain_logos: bool,
+}
+
+impl AppSettings {
+    pub fn validate(&self) -> Result<()> {
+        self.llm.validate()?;
+        if self.sync.polling_interval_minutes == 0 {
+            bail!("The polling interval must be at least one minute");
+        }
+        if let Some(language) = &self.ai.summary_language {
+            if language::parse(language).is_none() {
+                bail!("Unknown summary language: {}", language);
+            }
+        }
+        if !ocr::is_valid_languages(&self.ai.ocr_languages) {
+            bail!(
+                "OCR languages must be Tesseract codes joined by '+', such as eng+deu: {}",
+                self.ai.ocr_languages
+            );
+        }
+        if let Some(path) = &self.ai.tesseract_path {
+            if !ocr::is_valid_tesseract_path(path) {
+                bail!(
+                    "The Tesseract path must be an existing file named tesseract: {}",
+                    path
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// These settings with `patch` merged in
+    pub fn patched(&self, patch: Value) -> Result<Self> {
+        let mut value = serde_json::to_value(self)?;
+        merge_json(&mut value, patch);
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// Merge `patch` into `target`: objects are merged key by key, and any other value
+/// replaces what was there
+pub fn merge_json(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+/// Load the settings at startup, creating the settings file from the older settings
+/// files and the database if there isn't one yet
+pub fn init(database: &EmailDatabase) {
+    let settings = match read_settings() {
+        Ok(Some(settings)) => settings,
+        Ok(None) => {
+            let settings = import_legacy(Some(database));
+            if let Err(e) = write_settings(&settings) {
+                warn!("Failed to save settings: {}", e);
+            }
+            settings
+        }
+        Err(e) => {
+            warn!("Failed to read settings, using the defaults: {}", e);
+            AppSettings::default()
+        }
+    };
+    *SETTINGS.write().unwrap() = Some(settings);
+}
+
+/// The current settings
+pub fn current() -> AppSettings {
+    if let Some(settings) = SETTINGS.read().unwrap().as_ref() {
+        return settings.clone();
+    }
+    let settings = load_without_database();
+    SETTINGS.write().unwrap().get_or_insert(settings).clone()
+}
+
+/// Change the settings and save them. Returns the new settings.
+pub fn update(change: impl FnOnce(&mut AppSettings)) -> Result<AppSettings> {
+    modify(|settings| {
+        change(settings);
+        Ok(())
+    })
+}
+
+/// Merge a partial settings object into the settings and save them. Returns the new
+/// settings.
+pub fn apply_patch(patch: Value) -> Result<AppSettings> {
+    modify(|settings| {
+        *settings = settings.patched(patch)?;
+        Ok(())
+    })
+}
+
+/// Go back to the default settings, removing the older settings files so they aren't
+/// imported again
+pub fn reset() -> Result<AppSettings> {
+    modify(|settings| {
+        let dir = paths::data_dir()?;
+        for file in [LEGACY_CACHE_FILE, LEGACY_COMPUTE_FILE] {
+            let path = dir.join(file);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+        *settings = AppSettings::default();
+        Ok(())
+    })
+}
+
+fn modify(change: impl FnOnce(&mut AppSettings) -> Result<()>) -> Result<AppSettings> {
+    let mut guard = SETTINGS.write().unwrap();
+    let mut settings = guard.clone().unwrap_or_else(load_without_database);
+    change(&mut settings)?;
+    settings.validate()?;
+    write_settings(&settings)?;
+    *guard = Some(settings.clone());
+    Ok(settings)
+}
+
+fn load_without_database() -> AppSettings {
+    read_settings()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| import_legacy(None))
+}
+
+/// The saved settings, or `None` when there is no settings file
+fn read_settings() -> Result<Option<AppSettings>> {
+    let path = paths::data_dir()?.join(SETTINGS_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+fn write_settings(settings: &AppSettings) -> Result<()> {
+    let dir = paths::data_dir()?;
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join(SETTINGS_FILE),
+        serde_json::to_string_pretty(settings)?,
+    )?;
+    Ok(())
+}
+
+/// Settings from the files and table they were kept in before `SETTINGS_FILE`
+fn import_legacy(database: Option<&EmailDatabase>) -> AppSettings {
+    let mut settings = AppSettings::default();
+    if let Ok(dir) = paths::data_dir() {
+        if let Some(cache) = read_json(&dir.join(LEGACY_CACHE_FILE)) {
+            settings.cache = cache;
+        }
+        if let Some(compute) = read_json(&dir.join(LEGACY_COMPUTE_FILE)) {
+            settings.compute = compute;
+        }
+    }
+    if let Some(llm) = database.and_then(|database| database.get_llm_settings().ok()) {
+        settings.llm = llm;
+    }
+    settings
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_json() {
+        let mut target = json!({
+            "cache": {"cache_enabled": true, "max_cache_age_days": 30},
+            "ai": {"model_id": "a"},
+        });
+        merge_json(
+            &mut target,
+            json!({"cache": {"max_cache_age_days": 7}, "ai": {"model_id": null}, "new": 1}),
+        );
+        assert_eq!(
+            target,
+            json!({
+                "cache": {"cache_enabled": true, "max_cache_age_days": 7},
+                "ai": {"model_id": null},
+                "new": 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_patched() {
+        let settings = AppSettings::default()
+            .patched(json!({
+                "sync": {"polling_interval_minutes": 5},
+                "ai": {"model_id": "qwen"},
+            }))
+            .unwrap();
+        assert_eq!(settings.sync.polling_interval_minutes, 5);
+        assert_eq!(settings.ai.model_id.as_deref(), Some("qwen"));
+        assert_eq!(settings.cache, CacheSettings::default());
+
+        assert!(AppSettings::default()
+            .patched(json!({"cache": {"max_cache_age_days": "soon"}}))
+            .is_err());
+        let no_polling = AppSettings::default()
+            .patched(json!({"sync": {"polling_interval_minutes": 0}}))
+            .unwrap();
+        assert!(no_polling.validate().is_err());
+        let klingon = AppSettings::default()
+            .patched(json!({"ai": {"summary_language": "Klingon"}}))
+            .unwrap();
+        assert!(klingon.validate().is_err());
+        let shell = AppSettings::default()
+            .patched(json!({"ai": {"ocr_languages": "eng; rm -rf"}}))
+            .unwrap();
+        assert!(shell.validate().is_err());
+    }
+
+    #[test]
+    fn test_legacy_cache_settings() {
+        let legacy = r#"{"cache_enabled": false, "auto_sync_on_start": true,
+            "cache_media_assets": true, "max_cache_age_days": 14}"#;
+        let cache: CacheSettings = serde_json::from_str(legacy).unwrap();
+        assert!(!cache.cache_enabled);
+        assert_eq!(cache.max_cache_age_days, 14);
+        assert_eq!(cache.auto_download_attachments_mb, DEFAULT_AUTO_DOWNLOAD_MB);
+    }
+}
@@ -1,6 +1,6 @@
 import { useEffect, useState } from 'react'
 import Sidebar from './components/Sidebar'
-import { LoginScreen } from './components/Auth'
+import { LockScreen, LoginScreen } from './components/Auth'
 import EmailList from './components/EmailList'
 import EmailViewer from './components/EmailViewer'
 import { ComposeModal } from './components/Compose'
@@ -10,6 +10,8 @@ import { SmartInbox } from './components/SmartInbox'
 import { useAuthStore } from './stores/authStore'
 import { useAiStore } from './stores/aiStore'
 import { useEmailStore } from './stores/emailStore'
+import { useLockStore } from './stores/lockStore'
+import { useRagStore } from './stores/ragStore'
 
 type AppState = 'loading' | 'login' | 'setup' | 'ready'
 type ViewMode = 'smart' | 'classic'
@@ -33,11 +35,29 @@ function App() {
   const { authenticated, loading: authLoading, checkAuth } = useAuthStore()
   const { modelStatus, checkModelStatus, initAi, isAiReady } = useAiStore()
   const { startSync, stopSync, refreshEmails, setFolder, loading: emailsLoading, refreshing: isRefreshing } = useEmailStore()
+  const lockStatus = useLockStore((state) => state.status)
 
   useEffect(() => {
     checkAuth()
   }, [checkAuth])
 
+  // The app starts locked when the app lock is set up, and may lock itself when idle
+  useEffect(() => {
+    useLockStore.getState().fetchStatus()
+    const unlisten = useLockStore.getState().subscribe()
+    return () => {
+      unlisten.then((stop) => stop())
+    }
+  }, [])
+
+  // The backend loads the model and embedding engine at startup; pick them up when ready
+  useEffect(() => {
+    const unlisteners = [useAiStore.getState().subscribe(), useRagStore.getState().subscribe()]
+    return () => {
+      unlisteners.forEach((unlisten) => unlisten.then((stop) => stop()))
+    }
+  }, [])
+
   // Check model status after authentication
   useEffect(() => {
     if (authenticated) {
@@ -102,6 +122,10 @@ function App() {
     )
   }
 
+  if (lockStatus?.locked) {
+    return <LockScreen />
+  }
+
   // Login screen
   if (appState === 'login') {
     return <LoginScreen />
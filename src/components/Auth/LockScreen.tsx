@@ -0,0 +1,73 @@
+import { useState } from 'react'
+import { useLockStore } from '../../stores/lockStore'
+
+export default function LockScreen() {
+  const { status, unlock, error } = useLockStore()
+  const [passphrase, setPassphrase] = useState('')
+  const [unlocking, setUnlocking] = useState(false)
+
+  const handleUnlock = async (usePassphrase: boolean) => {
+    setUnlocking(true)
+    const unlocked = await unlock(usePassphrase ? passphrase : undefined)
+    setUnlocking(false)
+    if (unlocked) setPassphrase('')
+  }
+
+  return (
+    <div className="flex items-center justify-center h-screen bg-background">
+      <div className="w-full max-w-md px-8">
+        <div className="flex items-center gap-4 mb-12">
+          <div className="h-[2px] flex-1 bg-foreground" />
+          <div className="w-3 h-3 border-[2px] border-foreground" />
+        </div>
+
+        <h1 className="font-display text-6xl leading-none tracking-tighter mb-4">Locked</h1>
+        <p className="font-serif text-lg text-mutedForeground mb-8">
+          Enter your passphrase to open Inboxed.
+        </p>
+        {status?.damaged && (
+          <p className="font-mono text-xs text-red-500 mb-8">
+            The app lock file couldn't be read ({status.damaged}). Unlocking with your passphrase
+            repairs it.
+          </p>
+        )}
+
+        <form
+          onSubmit={(e) => {
+            e.preventDefault()
+            handleUnlock(true)
+          }}
+          className="space-y-4"
+        >
+          <input
+            type="password"
+            value={passphrase}
+            onChange={(e) => setPassphrase(e.target.value)}
+            placeholder="Passphrase"
+            autoFocus
+            className="w-full px-4 py-3 border-[2px] border-foreground bg-background font-mono text-sm"
+          />
+          <button
+            type="submit"
+            disabled={!passphrase || unlocking}
+            className="w-full px-4 py-3 bg-foreground text-background font-mono text-xs uppercase tracking-widest disabled:opacity-50"
+          >
+            {unlocking ? 'Unlocking' : 'Unlock'}
+          </button>
+          {status?.biometric && (
+            <button
+              type="button"
+              onClick={() => handleUnlock(false)}
+              disabled={unlocking}
+              className="w-full px-4 py-3 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50"
+            >
+              Use Biometrics
+            </button>
+          )}
+        </form>
+
+        {error && <p className="mt-4 font-mono text-xs text-red-500">{error}</p>}
+      </div>
+    </div>
+  )
+}
@@ -6,19 +6,23 @@ import { invoke } from '@tauri-apps/api/core'
 type AuthMode = 'select' | 'gmail' | 'outlook' | 'custom'
 
 export default function LoginScreen() {
-  const { signIn, loading, error } = useAuthStore()
+  const { signIn, signInWithCode, deviceCode, loading, error } = useAuthStore()
   const { addAccount } = useAccountStore()
   const [mode, setMode] = useState<AuthMode>('select')
   const [customForm, setCustomForm] = useState({
+    protocol: 'imap' as 'imap' | 'pop3',
+    preset: 'custom' as 'custom' | 'proton_bridge',
     email: '',
     password: '',
     imapHost: '',
     imapPort: '993',
     smtpHost: '',
     smtpPort: '465',
+    certFingerprint: '',
   })
   const [customError, setCustomError] = useState<string | null>(null)
   const [customLoading, setCustomLoading] = useState(false)
+  const [fingerprintLoading, setFingerprintLoading] = useState(false)
 
   const handleOAuthSignIn = async (_provider: 'gmail' | 'outlook') => {
     // Use existing OAuth flow for Gmail, will be extended for Outlook in Phase 3
@@ -26,7 +30,8 @@ export default function LoginScreen() {
   }
 
   const handleCustomSignIn = async () => {
-    if (!customForm.email || !customForm.password || !customForm.imapHost || !customForm.smtpHost) {
+    const isPop3 = customForm.protocol === 'pop3'
+    if (!customForm.email || !customForm.password || !customForm.imapHost || (!isPop3 && !customForm.smtpHost)) {
       setCustomError('Please fill in all required fields')
       return
     }
@@ -39,12 +44,13 @@ export default function LoginScreen() {
       const account = await addAccount({
         email: customForm.email,
         displayName: customForm.email.split('@')[0],
-        provider: 'custom',
+        provider: isPop3 ? 'pop3' : customForm.preset,
         authType: 'password',
         imapHost: customForm.imapHost,
         imapPort: parseInt(customForm.imapPort),
-        smtpHost: customForm.smtpHost,
-        smtpPort: parseInt(customForm.smtpPort),
+        smtpHost: isPop3 ? undefined : customForm.smtpHost,
+        smtpPort: isPop3 ? undefined : parseInt(customForm.smtpPort),
+        certFingerprint: customForm.certFingerprint || undefined,
       })
 
       // Store app password
@@ -68,6 +74,40 @@ export default function LoginScreen() {
     }
   }
 
+  // Show the server's certificate so a self-signed one (e.g. ProtonMail Bridge) can be pinned
+  const handleFetchFingerprint = async () => {
+    if (!customForm.imapHost) {
+      setCustomError('Enter the server host first')
+      return
+    }
+    setFingerprintLoading(true)
+    setCustomError(null)
+    try {
+      const fingerprint = await invoke<string>('get_server_certificate_fingerprint', {
+        host: customForm.imapHost,
+        port: parseInt(customForm.imapPort),
+      })
+      setCustomForm({ ...customForm, certFingerprint: fingerprint })
+    } catch (err) {
+      setCustomError((err as Error).toString())
+    } finally {
+      setFingerprintLoading(false)
+    }
+  }
+
+  const applyProtonBridgePreset = () => {
+    setCustomForm({
+      ...customForm,
+      protocol: 'imap',
+      preset: 'proton_bridge',
+      imapHost: '127.0.0.1',
+      imapPort: '1143',
+      smtpHost: '127.0.0.1',
+      smtpPort: '1025',
+    })
+    setMode('custom')
+  }
+
   return (
     <div className="flex items-center justify-center h-screen bg-background relative overflow-hidden">
       {/* Background texture */}
@@ -142,6 +182,25 @@ export default function LoginScreen() {
               </span>
             </button>
 
+            <button
+              onClick={() => signInWithCode('outlook')}
+              disabled={loading}
+              className="w-full font-mono text-xs uppercase tracking-widest text-mutedForeground hover:text-foreground disabled:opacity-50"
+            >
+              Outlook: sign in with a code instead
+            </button>
+
+            {deviceCode && (
+              <div className="border-[2px] border-foreground p-6 space-y-2">
+                <p className="font-mono text-sm uppercase tracking-wider">Enter this code</p>
+                <p className="font-mono text-3xl tracking-widest select-all">{deviceCode.user_code}</p>
+                <p className="text-mutedForeground">
+                  at {deviceCode.verification_uri} within {Math.round(deviceCode.expires_in_secs / 60)} minutes.
+                  Inboxed continues once you've signed in.
+                </p>
+              </div>
+            )}
+
             <button
               onClick={() => setMode('custom')}
               className="group relative w-full px-12 py-5 border-[2px] border-borderLight font-mono text-sm uppercase tracking-widest font-medium transition-all duration-100 hover:border-foreground text-mutedForeground hover:text-foreground"
@@ -154,6 +213,18 @@ export default function LoginScreen() {
               </span>
             </button>
 
+            <button
+              onClick={applyProtonBridgePreset}
+              className="group relative w-full px-12 py-5 border-[2px] border-borderLight font-mono text-sm uppercase tracking-widest font-medium transition-all duration-100 hover:border-foreground text-mutedForeground hover:text-foreground"
+            >
+              <span className="flex items-center justify-center gap-3">
+                ProtonMail Bridge
+                <span className="inline-block transition-transform group-hover:translate-x-1">
+                  →
+                </span>
+              </span>
+            </button>
+
             {error && (
               <div className="border-[2px] border-foreground p-6">
                 <p className="font-mono text-sm uppercase tracking-wider mb-2">
@@ -176,6 +247,34 @@ export default function LoginScreen() {
             </button>
 
             <div className="space-y-4">
+              <div className="flex gap-2">
+                {(['imap', 'pop3'] as const).map((protocol) => (
+                  <button
+                    key={protocol}
+                    onClick={() =>
+                      setCustomForm({
+                        ...customForm,
+                        protocol,
+                        preset: 'custom',
+                        imapPort: protocol === 'pop3' ? '995' : '993',
+                      })
+                    }
+                    className={`px-4 py-2 font-mono text-xs uppercase tracking-widest border-[2px] transition-all ${
+                      customForm.protocol === protocol
+                        ? 'bg-foreground text-background border-foreground'
+                        : 'border-borderLight hover:border-foreground'
+                    }`}
+                  >
+                    {protocol}
+                  </button>
+                ))}
+              </div>
+              {customForm.protocol === 'pop3' && (
+                <p className="font-mono text-xs text-mutedForeground">
+                  POP3 accounts download mail only; sending and server-side folders are unavailable
+                </p>
+              )}
+
               <div>
                 <label className="font-mono text-xs uppercase tracking-widest block mb-2">Email</label>
                 <input
@@ -200,17 +299,21 @@ export default function LoginScreen() {
 
               <div className="grid grid-cols-2 gap-4">
                 <div>
-                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">IMAP Host</label>
+                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">
+                    {customForm.protocol === 'pop3' ? 'POP3 Host' : 'IMAP Host'}
+                  </label>
                   <input
                     type="text"
                     value={customForm.imapHost}
                     onChange={(e) => setCustomForm({ ...customForm, imapHost: e.target.value })}
-                    placeholder="imap.example.com"
+                    placeholder={customForm.protocol === 'pop3' ? 'pop.example.com' : 'imap.example.com'}
                     className="w-full bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
                   />
                 </div>
                 <div>
-                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">IMAP Port</label>
+                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">
+                    {customForm.protocol === 'pop3' ? 'POP3 Port' : 'IMAP Port'}
+                  </label>
                   <input
                     type="number"
                     value={customForm.imapPort}
@@ -220,26 +323,56 @@ export default function LoginScreen() {
                 </div>
               </div>
 
-              <div className="grid grid-cols-2 gap-4">
-                <div>
-                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">SMTP Host</label>
-                  <input
-                    type="text"
-                    value={customForm.smtpHost}
-                    onChange={(e) => setCustomForm({ ...customForm, smtpHost: e.target.value })}
-                    placeholder="smtp.example.com"
-                    className="w-full bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
-                  />
+              {customForm.protocol === 'imap' && (
+                <div className="grid grid-cols-2 gap-4">
+                  <div>
+                    <label className="font-mono text-xs uppercase tracking-widest block mb-2">SMTP Host</label>
+                    <input
+                      type="text"
+                      value={customForm.smtpHost}
+                      onChange={(e) => setCustomForm({ ...customForm, smtpHost: e.target.value })}
+                      placeholder="smtp.example.com"
+                      className="w-full bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
+                    />
+                  </div>
+                  <div>
+                    <label className="font-mono text-xs uppercase tracking-widest block mb-2">SMTP Port</label>
+                    <input
+                      type="number"
+                      value={customForm.smtpPort}
+                      onChange={(e) => setCustomForm({ ...customForm, smtpPort: e.target.value })}
+                      className="w-full bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
+                    />
+                  </div>
                 </div>
-                <div>
-                  <label className="font-mono text-xs uppercase tracking-widest block mb-2">SMTP Port</label>
+              )}
+
+              <div>
+                <label className="font-mono text-xs uppercase tracking-widest block mb-2">
+                  Certificate Fingerprint (optional)
+                </label>
+                <div className="flex gap-4 items-end">
                   <input
-                    type="number"
-                    value={customForm.smtpPort}
-                    onChange={(e) => setCustomForm({ ...customForm, smtpPort: e.target.value })}
-                    className="w-full bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
+                    type="text"
+                    value={customForm.certFingerprint}
+                    onChange={(e) => setCustomForm({ ...customForm, certFingerprint: e.target.value })}
+                    placeholder="SHA-256, for self-signed certificates"
+                    className="flex-1 bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-mono text-xs outline-none transition-all"
                   />
+                  <button
+                    onClick={handleFetchFingerprint}
+                    disabled={fingerprintLoading}
+                    className="px-4 py-2 font-mono text-xs uppercase tracking-widest border-[2px] border-borderLight hover:border-foreground disabled:opacity-50"
+                  >
+                    {fingerprintLoading ? 'Fetching' : 'Fetch'}
+                  </button>
                 </div>
+                {customForm.certFingerprint && (
+                  <p className="font-mono text-xs text-mutedForeground mt-2">
+                    Only a server presenting this exact certificate will be trusted. Check it matches the one your
+                    server (or Bridge) shows.
+                  </p>
+                )}
               </div>
             </div>
 
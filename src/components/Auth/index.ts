@@ -1 +1,2 @@
 export { default as LoginScreen } from './LoginScreen'
+export { default as LockScreen } from './LockScreen'
@@ -1,5 +1,16 @@
-import { useState } from 'react'
+import { useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import type { FollowupDraft } from '../../stores/smartInboxStore'
+import type { Identity } from '../../stores/accountStore'
+import { useDictationStore } from '../../stores/dictationStore'
+
+// Address mail has bounced from, from `check_recipients`
+interface BouncedAddress {
+  address: string
+  reason: string | null
+  bounce_count: number
+  last_bounced_at: number
+}
 // Account store available for multi-account "From" dropdown
 // import { useAccountStore } from '../../stores/accountStore'
 
@@ -11,14 +22,17 @@ interface ComposeModalProps {
     subject: string
     messageId: string
   }
+  // Nudge for an unanswered sent email, from `draftFollowup`
+  followup?: FollowupDraft
 }
 
 export default function ComposeModal({
   isOpen,
   onClose,
   replyTo,
+  followup,
 }: ComposeModalProps) {
-  const [to, setTo] = useState(replyTo?.to || '')
+  const [to, setTo] = useState(replyTo?.to || followup?.to.join(', ') || '')
   const [cc, setCc] = useState('')
   const [bcc, setBcc] = useState('')
   const [subject, setSubject] = useState(
@@ -26,15 +40,62 @@ export default function ComposeModal({
       ? replyTo.subject
       : replyTo
       ? `Re: ${replyTo.subject}`
-      : ''
+      : followup?.subject || ''
   )
-  const [body, setBody] = useState('')
+  const [body, setBody] = useState(followup?.body || '')
   const [sending, setSending] = useState(false)
   const [error, setError] = useState<string | null>(null)
   const [showCc, setShowCc] = useState(false)
+  const [identities, setIdentities] = useState<Identity[]>([])
+  const [fromIdentity, setFromIdentity] = useState('')
+  const [requestReceipt, setRequestReceipt] = useState(false)
+  const [bounced, setBounced] = useState<BouncedAddress[]>([])
+  // Recipients the bounce warning was shown for; sending again to them goes ahead
+  const [warnedFor, setWarnedFor] = useState<string | null>(null)
+  const {
+    downloadedModels: speechModels,
+    isDownloading: speechDownloading,
+    downloadProgress: speechProgress,
+    isRecording,
+    isTranscribing,
+    error: dictationError,
+    loadModels: loadSpeechModels,
+    downloadModel: downloadSpeechModel,
+    startRecording,
+    stopRecording,
+    cancelRecording,
+  } = useDictationStore()
+
+  useEffect(() => {
+    if (!isOpen) return
+    invoke<Identity[]>('list_identities')
+      .then(setIdentities)
+      .catch(() => setIdentities([]))
+    loadSpeechModels()
+    // Closing the composer drops a dictation in progress
+    return () => {
+      cancelRecording()
+    }
+  }, [isOpen, loadSpeechModels, cancelRecording])
 
   if (!isOpen) return null
 
+  // Dictation appends what was said to the body; the first use downloads a speech model
+  const handleDictate = async () => {
+    if (speechModels.length === 0) {
+      await downloadSpeechModel()
+      return
+    }
+    if (!isRecording) {
+      await startRecording()
+      return
+    }
+    const transcript = await stopRecording()
+    if (transcript) {
+      setBody((current) => (current.trim() ? `${current.trimEnd()} ${transcript}` : transcript))
+    }
+  }
+
   const handleSend = async () => {
     if (!to || !subject) {
       setError('Please fill in recipient and subject')
@@ -49,12 +110,29 @@ export default function ComposeModal({
       const ccEmails = cc ? cc.split(',').map((e) => e.trim()) : undefined
       const bccEmails = bcc ? bcc.split(',').map((e) => e.trim()) : undefined
 
+      const recipients = [...toEmails, ...(ccEmails ?? []), ...(bccEmails ?? [])]
+      const recipientKey = recipients.join(',')
+      if (warnedFor !== recipientKey) {
+        const previouslyBounced = await invoke<BouncedAddress[]>('check_recipients', {
+          recipients,
+        })
+        if (previouslyBounced.length > 0) {
+          setBounced(previouslyBounced)
+          setWarnedFor(recipientKey)
+          return
+        }
+      }
+
       await invoke('send_email', {
         to: toEmails,
         subject,
         body: body.replace(/\n/g, '<br>'),
         cc: ccEmails,
         bcc: bccEmails,
+        inReplyTo: replyTo?.messageId,
+        followupOf: followup?.sent_id,
+        fromIdentity: fromIdentity || undefined,
+        requestReadReceipt: requestReceipt,
       })
 
       onClose()
@@ -71,7 +149,7 @@ export default function ComposeModal({
         {/* Header */}
         <div className="px-8 py-6 border-b-[2px] border-foreground flex items-center justify-between">
           <h2 className="font-display text-3xl tracking-tight">
-            {replyTo ? 'Reply' : 'New Message'}
+            {replyTo ? 'Reply' : followup ? 'Follow Up' : 'New Message'}
           </h2>
           <button
             onClick={onClose}
@@ -84,6 +162,29 @@ export default function ComposeModal({
         {/* Form */}
         <div className="flex-1 flex flex-col overflow-hidden">
           <div className="px-8 py-6 space-y-4 border-b-[2px] border-foreground">
+            {/* From: only shown when the account has send-as identities */}
+            {identities.length > 0 && (
+              <div className="flex items-center gap-4">
+                <label className="font-mono text-xs uppercase tracking-widest w-16">
+                  From
+                </label>
+                <select
+                  value={fromIdentity}
+                  onChange={(e) => setFromIdentity(e.target.value)}
+                  className="flex-1 bg-transparent border-b-[2px] border-borderLight focus:border-foreground py-2 font-serif outline-none transition-all"
+                >
+                  <option value="">Account address</option>
+                  {identities.map((identity) => (
+                    <option key={identity.id} value={identity.address}>
+                      {identity.display_name
+                        ? `${identity.display_name} <${identity.address}>`
+                        : identity.address}
+                    </option>
+                  ))}
+                </select>
+              </div>
+            )}
+
             {/* To */}
             <div className="flex items-center gap-4">
               <label className="font-mono text-xs uppercase tracking-widest w-16">
@@ -162,12 +263,55 @@ export default function ComposeModal({
           </div>
         </div>
 
+        {/* Bounce warning */}
+        {bounced.length > 0 && (
+          <div className="px-8 py-4 border-t-[2px] border-foreground bg-muted">
+            <p className="font-mono text-xs uppercase tracking-widest mb-2">
+              Mail to these addresses bounced before. Send anyway?
+            </p>
+            {bounced.map((b) => (
+              <p key={b.address} className="font-serif text-sm">
+                {b.address}
+                {b.reason && (
+                  <span className="text-mutedForeground"> — {b.reason}</span>
+                )}
+              </p>
+            ))}
+          </div>
+        )}
+
         {/* Footer */}
         <div className="px-8 py-6 border-t-[2px] border-foreground flex items-center justify-between">
-          {error && (
-            <p className="text-sm text-mutedForeground">{error}</p>
+          {(error || dictationError) && (
+            <p className="text-sm text-mutedForeground">{error || dictationError}</p>
           )}
           <div className="flex-1" />
+          <button
+            onClick={handleDictate}
+            disabled={speechDownloading || isTranscribing}
+            title="Dictation is transcribed on this device"
+            className={`mr-6 px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest transition-all duration-100 disabled:opacity-50 ${
+              isRecording ? 'bg-foreground text-background' : 'hover:bg-muted'
+            }`}
+          >
+            {speechDownloading
+              ? `Downloading ${Math.round(speechProgress)}%`
+              : isTranscribing
+              ? 'Transcribing...'
+              : isRecording
+              ? 'Stop Dictation'
+              : speechModels.length === 0
+              ? 'Set Up Dictation'
+              : 'Dictate'}
+          </button>
+          <label className="flex items-center gap-2 mr-6 font-mono text-xs uppercase tracking-widest text-mutedForeground">
+            <input
+              type="checkbox"
+              checked={requestReceipt}
+              onChange={(e) => setRequestReceipt(e.target.checked)}
+            />
+            Request read receipt
+          </label>
           <div className="flex gap-4">
             <button
               onClick={onClose}
@@ -1,4 +1,5 @@
 import { useEmailStore } from '../../stores/emailStore'
+import SenderAvatar from './SenderAvatar'
 
 export default function EmailList() {
   const { emails, selectedEmail, loading, refreshing, error, fetchEmails, selectEmail } =
@@ -96,6 +97,10 @@ export default function EmailList() {
               )}
             </div>
 
+            <div className="flex-shrink-0">
+              <SenderAvatar email={email.from_email} name={email.from.split('<')[0].trim()} />
+            </div>
+
             {/* Email content */}
             <div className="flex-1 min-w-0">
               {/* Header: From + Date */}
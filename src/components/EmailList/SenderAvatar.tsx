@@ -0,0 +1,51 @@
+import { useEffect, useState } from 'react'
+import { convertFileSrc, invoke } from '@tauri-apps/api/core'
+
+// Resolved avatar paths per sender, shared by every row showing the same sender
+const avatarCache = new Map<string, Promise<string | null>>()
+
+function resolveAvatar(email: string): Promise<string | null> {
+  const key = email.toLowerCase()
+  let pending = avatarCache.get(key)
+  if (!pending) {
+    pending = invoke<string | null>('get_sender_avatar', { email: key }).catch(() => null)
+    avatarCache.set(key, pending)
+  }
+  return pending
+}
+
+interface SenderAvatarProps {
+  email: string
+  name: string
+}
+
+export default function SenderAvatar({ email, name }: SenderAvatarProps) {
+  const [src, setSrc] = useState<string | null>(null)
+
+  useEffect(() => {
+    let cancelled = false
+    setSrc(null)
+    resolveAvatar(email).then((path) => {
+      if (!cancelled && path) setSrc(convertFileSrc(path))
+    })
+    return () => {
+      cancelled = true
+    }
+  }, [email])
+
+  if (src) {
+    return (
+      <img
+        src={src}
+        alt=""
+        className="w-8 h-8 object-cover border border-current"
+        onError={() => setSrc(null)}
+      />
+    )
+  }
+  return (
+    <div className="w-8 h-8 flex items-center justify-center border border-current font-mono text-xs uppercase">
+      {(name || email).charAt(0)}
+    </div>
+  )
+}
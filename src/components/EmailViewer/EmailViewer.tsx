@@ -4,6 +4,7 @@ import { listen, UnlistenFn } from '@tauri-apps/api/event'
 import DOMPurify from 'dompurify'
 import { useEmailStore } from '../../stores/emailStore'
 import { useAiStore } from '../../stores/aiStore'
+import { useSettingsStore } from '../../stores/settingsStore'
 import { ComposeModal } from '../Compose'
 
 interface EmailSummary {
@@ -12,9 +13,17 @@ interface EmailSummary {
   priority: string
 }
 
+interface EmailTranslation {
+  email_id: string
+  language: string
+  body: string
+  translated_at: number
+}
+
 export default function EmailViewer() {
   const { selectedEmail, fetchEmails } = useEmailStore()
   const { isModelLoaded, isAiReady, modelStatus, downloadProgress } = useAiStore()
+  const { settings } = useSettingsStore()
   const [showCompose, setShowCompose] = useState(false)
   const [actionLoading, setActionLoading] = useState<string | null>(null)
   const [summary, setSummary] = useState<EmailSummary | null>(null)
@@ -22,7 +31,13 @@ export default function EmailViewer() {
   const [loadingSummary, setLoadingSummary] = useState(false)
   const [showSummary, setShowSummary] = useState(false)
   const [isStreaming, setIsStreaming] = useState(false)
+  const [receiptAnswered, setReceiptAnswered] = useState(false)
+  // Translated body, streamed in while `translating`
+  const [translation, setTranslation] = useState<string | null>(null)
+  const [translating, setTranslating] = useState(false)
+  const [translationError, setTranslationError] = useState<string | null>(null)
   const unlistenRef = useRef<UnlistenFn | null>(null)
+  const translateUnlistenRef = useRef<UnlistenFn | null>(null)
 
 
   // Set up streaming listener
@@ -36,6 +51,11 @@ export default function EmailViewer() {
           setStreamingSummary(prev => prev + event.payload)
         }
       })
+      translateUnlistenRef.current = await listen<string>('translate:token', (event) => {
+        if (mounted) {
+          setTranslation(prev => (prev ?? '') + event.payload)
+        }
+      })
     }
 
     setupListener()
@@ -45,6 +65,9 @@ export default function EmailViewer() {
       if (unlistenRef.current) {
         unlistenRef.current()
       }
+      if (translateUnlistenRef.current) {
+        translateUnlistenRef.current()
+      }
     }
   }, [])
 
@@ -53,6 +76,9 @@ export default function EmailViewer() {
     setSummary(null)
     setStreamingSummary('')
     setIsStreaming(false)
+    setReceiptAnswered(false)
+    setTranslation(null)
+    setTranslationError(null)
   }, [selectedEmail?.id])
 
   // Load summary when showSummary is toggled on
@@ -76,7 +102,7 @@ export default function EmailViewer() {
       const result = await invoke<EmailSummary>(command, {
         subject: selectedEmail.subject,
         from: selectedEmail.from,
-        body: selectedEmail.body_html || selectedEmail.body_plain || selectedEmail.snippet,
+        body: selectedEmail.body_clean || selectedEmail.body_html || selectedEmail.snippet,
       })
 
       setSummary(result)
@@ -89,7 +115,7 @@ export default function EmailViewer() {
           const result = await invoke<EmailSummary>('summarize_email', {
             subject: selectedEmail.subject,
             from: selectedEmail.from,
-            body: selectedEmail.body_html || selectedEmail.body_plain || selectedEmail.snippet,
+            body: selectedEmail.body_clean || selectedEmail.body_html || selectedEmail.snippet,
           })
           setSummary(result)
         } catch (fallbackError) {
@@ -102,6 +128,31 @@ export default function EmailViewer() {
     }
   }
 
+  // Into the summary language from the settings, or English
+  const handleTranslate = async () => {
+    if (!selectedEmail) return
+    if (translation !== null) {
+      setTranslation(null)
+      return
+    }
+
+    setTranslating(true)
+    setTranslationError(null)
+    setTranslation('')
+    try {
+      const result = await invoke<EmailTranslation>('translate_email', {
+        emailId: selectedEmail.id,
+        targetLang: settings?.ai.summary_language ?? 'eng',
+      })
+      setTranslation(result.body)
+    } catch (error) {
+      setTranslation(null)
+      setTranslationError(String(error))
+    } finally {
+      setTranslating(false)
+    }
+  }
+
   const handleAction = async (action: string, actionFn: () => Promise<void>) => {
     setActionLoading(action)
     try {
@@ -152,6 +203,21 @@ export default function EmailViewer() {
     )
   }
 
+  const handleReadReceipt = async (send: boolean) => {
+    if (!selectedEmail) return
+    setActionLoading('receipt')
+    try {
+      await invoke(send ? 'send_read_receipt' : 'decline_read_receipt', {
+        emailId: selectedEmail.id,
+      })
+      setReceiptAnswered(true)
+    } catch (error) {
+      console.error('Read receipt failed:', error)
+    } finally {
+      setActionLoading(null)
+    }
+  }
+
   if (!selectedEmail) {
     return (
       <div className="flex-1 flex items-center justify-center p-12">
@@ -232,6 +298,15 @@ export default function EmailViewer() {
             {selectedEmail.is_read ? 'Unread' : 'Read'}
           </button>
           <div className="w-px h-6 bg-borderLight" />
+          {isModelLoaded && (
+            <button
+              onClick={handleTranslate}
+              disabled={translating}
+              className="px-6 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all duration-100 disabled:opacity-50 focus-visible:outline focus-visible:outline-3 focus-visible:outline-foreground focus-visible:outline-offset-3"
+            >
+              {translating ? 'Translating...' : translation !== null ? 'Original' : 'Translate'}
+            </button>
+          )}
           {/* AI Summary Button with Status */}
           <div className="relative group">
             <button
@@ -303,6 +378,31 @@ export default function EmailViewer() {
         </div>
       </div>
 
+      {/* Read receipt request */}
+      {selectedEmail.read_receipt_to && !receiptAnswered && (
+        <div className="border-b-[2px] border-foreground px-6 lg:px-12 py-4 flex items-center justify-between gap-4">
+          <p className="font-mono text-xs uppercase tracking-widest">
+            The sender asked for a read receipt
+          </p>
+          <div className="flex gap-2">
+            <button
+              onClick={() => handleReadReceipt(true)}
+              disabled={actionLoading === 'receipt'}
+              className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all duration-100 disabled:opacity-50"
+            >
+              Send
+            </button>
+            <button
+              onClick={() => handleReadReceipt(false)}
+              disabled={actionLoading === 'receipt'}
+              className="px-4 py-2 border-[2px] border-borderLight font-mono text-xs uppercase tracking-widest hover:border-foreground transition-all duration-100 disabled:opacity-50"
+            >
+              Decline
+            </button>
+          </div>
+        </div>
+      )}
+
       {/* AI Summary Panel */}
       {showSummary && (displaySummary || loadingSummary) && (
         <div className="border-b-[2px] border-foreground bg-muted">
@@ -376,7 +476,17 @@ export default function EmailViewer() {
       {/* Body */}
       <div className="flex-1 overflow-y-auto">
         <article className="max-w-3xl mx-auto px-6 lg:px-12 py-12">
-          {selectedEmail.body_html ? (
+          {translationError && (
+            <p className="mb-6 font-mono text-xs uppercase tracking-widest text-mutedForeground">
+              {translationError}
+            </p>
+          )}
+          {translation !== null ? (
+            <pre className="whitespace-pre-wrap font-serif text-lg leading-relaxed">
+              {translation}
+              {translating && <span className="animate-pulse">|</span>}
+            </pre>
+          ) : selectedEmail.body_html ? (
             <div
               className="font-serif text-lg leading-relaxed email-content"
               dangerouslySetInnerHTML={{
@@ -0,0 +1,140 @@
+import { useEffect, useState } from 'react'
+import { useLockStore } from '../../stores/lockStore'
+
+// Set up, change or turn off the passphrase lock, shown in Storage settings
+export default function AppLockSettings() {
+    const { status, fetchStatus, lock, setLock, disableLock } = useLockStore()
+    const [passphrase, setPassphrase] = useState('')
+    const [confirmPassphrase, setConfirmPassphrase] = useState('')
+    const [currentPassphrase, setCurrentPassphrase] = useState('')
+    const [biometric, setBiometric] = useState(false)
+    const [autoLockMinutes, setAutoLockMinutes] = useState(5)
+    const [error, setError] = useState<string | null>(null)
+
+    useEffect(() => {
+        fetchStatus()
+    }, [fetchStatus])
+
+    useEffect(() => {
+        if (status) {
+            setBiometric(status.biometric)
+            setAutoLockMinutes(status.auto_lock_minutes)
+        }
+    }, [status])
+
+    const reset = () => {
+        setPassphrase('')
+        setConfirmPassphrase('')
+        setCurrentPassphrase('')
+        setError(null)
+    }
+
+    const handleSave = async () => {
+        if (passphrase !== confirmPassphrase) {
+            setError('The passphrases do not match')
+            return
+        }
+        try {
+            await setLock(passphrase, {
+                currentPassphrase: status?.enabled ? currentPassphrase : undefined,
+                biometric,
+                autoLockMinutes,
+            })
+            reset()
+        } catch (err) {
+            setError((err as Error).toString())
+        }
+    }
+
+    const handleDisable = async () => {
+        try {
+            await disableLock(currentPassphrase)
+            reset()
+        } catch (err) {
+            setError((err as Error).toString())
+        }
+    }
+
+    const inputClass = 'px-3 py-2 border-[2px] border-foreground bg-background font-mono text-xs'
+    const buttonClass = 'px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50'
+
+    return (
+        <div className="border-[2px] border-foreground p-6 mb-8">
+            <div className="flex items-center justify-between mb-6">
+                <h2 className="font-mono text-xs uppercase tracking-widest">App Lock</h2>
+                {status?.enabled && (
+                    <button onClick={lock} className={buttonClass}>
+                        Lock Now
+                    </button>
+                )}
+            </div>
+
+            <p className="font-serif text-sm text-mutedForeground mb-4">
+                {status?.enabled
+                    ? 'Inboxed asks for your passphrase when it starts and after it has been idle. Enter the current passphrase to change or turn off the lock.'
+                    : 'Ask for a passphrase before showing any mail, when Inboxed starts and after it has been idle. Mail on disk is not encrypted.'}
+            </p>
+
+            <div className="flex flex-wrap gap-2 items-center">
+                {status?.enabled && (
+                    <input
+                        type="password"
+                        value={currentPassphrase}
+                        onChange={(e) => setCurrentPassphrase(e.target.value)}
+                        placeholder="Current passphrase"
+                        className={inputClass}
+                    />
+                )}
+                <input
+                    type="password"
+                    value={passphrase}
+                    onChange={(e) => setPassphrase(e.target.value)}
+                    placeholder={status?.enabled ? 'New passphrase' : 'Passphrase'}
+                    className={inputClass}
+                />
+                <input
+                    type="password"
+                    value={confirmPassphrase}
+                    onChange={(e) => setConfirmPassphrase(e.target.value)}
+                    placeholder="Repeat passphrase"
+                    className={inputClass}
+                />
+                <label className="flex items-center gap-2 font-mono text-xs">
+                    Lock after
+                    <input
+                        type="number"
+                        min={0}
+                        value={autoLockMinutes}
+                        onChange={(e) => setAutoLockMinutes(Math.max(0, parseInt(e.target.value) || 0))}
+                        className={`${inputClass} w-16`}
+                    />
+                    idle minutes
+                </label>
+                {status?.biometric_available && (
+                    <label className="flex items-center gap-2 font-mono text-xs">
+                        <input
+                            type="checkbox"
+                            checked={biometric}
+                            onChange={(e) => setBiometric(e.target.checked)}
+                            className="w-4 h-4 accent-foreground"
+                        />
+                        Unlock with biometrics
+                    </label>
+                )}
+            </div>
+
+            <div className="mt-4 flex gap-2">
+                <button onClick={handleSave} disabled={!passphrase} className={buttonClass}>
+                    {status?.enabled ? 'Update Lock' : 'Turn On Lock'}
+                </button>
+                {status?.enabled && (
+                    <button onClick={handleDisable} disabled={!currentPassphrase} className={buttonClass}>
+                        Turn Off Lock
+                    </button>
+                )}
+            </div>
+
+            {error && <p className="mt-2 font-mono text-xs text-red-500">{error}</p>}
+        </div>
+    )
+}
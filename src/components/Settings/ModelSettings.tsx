@@ -1,11 +1,33 @@
 import { useEffect, useState } from 'react'
-import { useAiStore, ModelOption } from '../../stores/aiStore'
+import { useAiStore, ModelOption, ModelBenchmark } from '../../stores/aiStore'
 import { useRagStore } from '../../stores/ragStore'
+import { useSettingsStore } from '../../stores/settingsStore'
+import { useDictationStore } from '../../stores/dictationStore'
 
 interface ModelSettingsProps {
   onClose: () => void
 }
 
+// ISO 639-3 codes, as detected for each email
+const SUMMARY_LANGUAGES: [string, string][] = [
+  ['eng', 'English'],
+  ['spa', 'Spanish'],
+  ['fra', 'French'],
+  ['deu', 'German'],
+  ['ita', 'Italian'],
+  ['por', 'Portuguese'],
+  ['nld', 'Dutch'],
+  ['pol', 'Polish'],
+  ['rus', 'Russian'],
+  ['ukr', 'Ukrainian'],
+  ['tur', 'Turkish'],
+  ['ara', 'Arabic'],
+  ['hin', 'Hindi'],
+  ['jpn', 'Japanese'],
+  ['kor', 'Korean'],
+  ['cmn', 'Chinese'],
+]
+
 export default function ModelSettings({ onClose }: ModelSettingsProps) {
   const {
     modelStatus,
@@ -15,6 +37,7 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
     downloadedModels,
     selectedModelId,
     activeModelId,
+    modelFallback,
     error,
     isDeleting,
     isActivating,
@@ -27,24 +50,46 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
     activateModel,
     initAi,
     checkModelStatus,
+    benchmarks,
+    benchmarkingModelId,
+    benchmarkModel,
+    getModelBenchmarks,
   } = useAiStore()
 
   const {
     isInitialized: ragInitialized,
     isModelDownloaded: embeddingModelDownloaded,
+    isRerankerDownloaded: rerankerDownloaded,
     isEmbedding,
     embeddingStatus,
     checkModelDownloaded: checkEmbeddingModel,
+    checkRerankerDownloaded,
+    downloadReranker,
     downloadAndInitRag,
     embedAllEmails,
     getEmbeddingStatus,
     getEmbeddedCount,
   } = useRagStore()
 
+  const { settings, loadSettings, updateSettings } = useSettingsStore()
+
+  const {
+    models: speechModels,
+    downloadedModels: downloadedSpeechModels,
+    isDownloading: speechDownloading,
+    downloadProgress: speechProgress,
+    error: speechError,
+    loadModels: loadSpeechModels,
+    downloadModel: downloadSpeechModel,
+    deleteModel: deleteSpeechModel,
+    clearError: clearSpeechError,
+  } = useDictationStore()
+
   const [confirmDelete, setConfirmDelete] = useState<string | null>(null)
   const [embeddingDownloading, setEmbeddingDownloading] = useState(false)
   const [embeddedCount, setEmbeddedCount] = useState(0)
   const [embeddingError, setEmbeddingError] = useState<string | null>(null)
+  const [rerankerDownloading, setRerankerDownloading] = useState(false)
 
   useEffect(() => {
     getAvailableModels()
@@ -52,7 +97,11 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
     getActiveModelId()
     checkModelStatus()
     checkEmbeddingModel()
-  }, [getAvailableModels, getDownloadedModels, getActiveModelId, checkModelStatus, checkEmbeddingModel])
+    checkRerankerDownloaded()
+    getModelBenchmarks()
+    loadSettings()
+    loadSpeechModels()
+  }, [getAvailableModels, getDownloadedModels, getActiveModelId, checkModelStatus, checkEmbeddingModel, checkRerankerDownloaded, getModelBenchmarks, loadSettings, loadSpeechModels])
 
   useEffect(() => {
     if (ragInitialized) {
@@ -77,6 +126,21 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
     }
   }
 
+  // The reranker model is downloaded the first time reranking is turned on
+  const handleRerankToggle = async (enabled: boolean) => {
+    setEmbeddingError(null)
+    if (enabled && !rerankerDownloaded) {
+      setRerankerDownloading(true)
+      const downloaded = await downloadReranker()
+      setRerankerDownloading(false)
+      if (!downloaded) {
+        setEmbeddingError('Reranker model download failed')
+        return
+      }
+    }
+    updateSettings({ ai: { rerank_results: enabled } })
+  }
+
   const handleEmbedEmails = async () => {
     setEmbeddingError(null)
     try {
@@ -257,6 +321,21 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
             </div>
           )}
 
+          {/* Chosen model was missing */}
+          {modelFallback && (
+            <div className="mt-4 p-4 bg-muted border border-borderLight">
+              <p className="font-mono text-xs uppercase tracking-widest mb-2">
+                Model Not Found
+              </p>
+              <p className="font-serif text-sm">
+                {availableModels.find((m) => m.id === modelFallback.requested)?.name ?? modelFallback.requested} is no longer downloaded.{' '}
+                {modelFallback.loaded
+                  ? `Loaded ${availableModels.find((m) => m.id === modelFallback.loaded)?.name ?? modelFallback.loaded} instead.`
+                  : 'Download a model to use AI features.'}
+              </p>
+            </div>
+          )}
+
           {/* Error */}
           {modelStatus.status === 'error' && error && (
             <div className="mt-4 p-4 bg-muted border border-borderLight">
@@ -266,6 +345,80 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
               <p className="font-serif text-sm">{error}</p>
             </div>
           )}
+
+          {/* Load on startup */}
+          <label className="mt-4 flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors">
+            <div>
+              <p className="font-mono text-sm font-medium">Load on Startup</p>
+              <p className="font-serif text-sm text-mutedForeground">
+                Load the downloaded model and semantic search in the background when the app opens
+              </p>
+            </div>
+            <input
+              type="checkbox"
+              checked={settings?.ai.load_on_startup ?? true}
+              onChange={(e) => updateSettings({ ai: { load_on_startup: e.target.checked } })}
+              className="w-5 h-5 accent-foreground"
+            />
+          </label>
+
+          {/* Summary language */}
+          <div className="mt-4 flex items-center justify-between p-4 border border-borderLight">
+            <div>
+              <p className="font-mono text-sm font-medium">Summary Language</p>
+              <p className="font-serif text-sm text-mutedForeground">
+                Language summaries are written in
+              </p>
+            </div>
+            <select
+              value={settings?.ai.summary_language ?? ''}
+              onChange={(e) => updateSettings({ ai: { summary_language: e.target.value || null } })}
+              className="px-4 py-2 border-[2px] border-foreground bg-background font-mono text-sm focus:outline-none"
+            >
+              <option value="">Same as the email</option>
+              {SUMMARY_LANGUAGES.map(([code, name]) => (
+                <option key={code} value={code}>{name}</option>
+              ))}
+            </select>
+          </div>
+
+          {/* Attachment OCR */}
+          <label className="mt-4 flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors">
+            <div>
+              <p className="font-mono text-sm font-medium">Read Attachments (OCR)</p>
+              <p className="font-serif text-sm text-mutedForeground">
+                Make images and scanned PDFs searchable and include them in summaries. Needs Tesseract
+                installed and uses a lot of CPU on large scans.
+              </p>
+            </div>
+            <input
+              type="checkbox"
+              checked={settings?.ai.ocr_enabled ?? false}
+              onChange={(e) => updateSettings({ ai: { ocr_enabled: e.target.checked } })}
+              className="w-5 h-5 accent-foreground"
+            />
+          </label>
+          {settings?.ai.ocr_enabled && (
+            <div className="mt-4 flex items-center justify-between p-4 border border-borderLight">
+              <div>
+                <p className="font-mono text-sm font-medium">OCR Languages</p>
+                <p className="font-serif text-sm text-mutedForeground">
+                  Tesseract language codes joined by +, such as eng+deu
+                </p>
+              </div>
+              <input
+                type="text"
+                defaultValue={settings.ai.ocr_languages}
+                onBlur={(e) => {
+                  const value = e.target.value.trim()
+                  if (value && value !== settings.ai.ocr_languages) {
+                    updateSettings({ ai: { ocr_languages: value } })
+                  }
+                }}
+                className="w-40 px-4 py-2 border-[2px] border-foreground bg-background font-mono text-sm focus:outline-none"
+              />
+            </div>
+          )}
         </div>
 
         {/* Semantic Search / Embedding Model Section */}
@@ -371,6 +524,118 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
               </button>
             )}
           </div>
+
+          {/* Cross-encoder reranking */}
+          <label className="mt-4 flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors">
+            <div>
+              <p className="font-mono text-sm font-medium">Rerank Chat Context</p>
+              <p className="font-serif text-sm text-mutedForeground">
+                {rerankerDownloading
+                  ? 'Downloading reranker model...'
+                  : 'Re-score the 50 best matches with a second model (~90 MB) so chat answers read the most relevant emails. Slower, notably better on large mailboxes.'}
+              </p>
+            </div>
+            <input
+              type="checkbox"
+              checked={settings?.ai.rerank_results ?? false}
+              disabled={rerankerDownloading}
+              onChange={(e) => handleRerankToggle(e.target.checked)}
+              className="w-5 h-5 accent-foreground"
+            />
+          </label>
+        </div>
+
+        {/* Dictation / Speech Model Section */}
+        <div className="border-[2px] border-foreground p-6 mb-8">
+          <h2 className="font-mono text-xs uppercase tracking-widest mb-4">
+            Dictation
+          </h2>
+          <p className="font-serif text-sm text-mutedForeground mb-4">
+            Speak instead of typing in the composer. Audio is transcribed on this device by a
+            Whisper model and never uploaded.
+          </p>
+
+          {speechDownloading && (
+            <div className="mb-4">
+              <div className="h-3 bg-muted border border-borderLight overflow-hidden">
+                <div
+                  className="h-full bg-foreground transition-all duration-300"
+                  style={{ width: `${speechProgress}%` }}
+                />
+              </div>
+              <p className="font-serif text-sm text-mutedForeground mt-2">
+                Downloading speech model... {Math.round(speechProgress)}%
+              </p>
+            </div>
+          )}
+
+          {speechError && (
+            <div className="mb-4 p-3 bg-red-50 border border-red-300 flex items-center justify-between">
+              <span className="text-red-600 text-sm font-mono">{speechError}</span>
+              <button
+                onClick={clearSpeechError}
+                className="text-red-600 hover:text-red-800 font-mono text-xs uppercase tracking-wider ml-4"
+              >
+                Dismiss
+              </button>
+            </div>
+          )}
+
+          <div className="space-y-3">
+            {speechModels.map((model) => {
+              const downloaded = downloadedSpeechModels.includes(model.id)
+              const active =
+                downloaded &&
+                (settings?.ai.speech_model_id === model.id ||
+                  (!downloadedSpeechModels.includes(settings?.ai.speech_model_id ?? '') &&
+                    downloadedSpeechModels[0] === model.id))
+              return (
+                <div
+                  key={model.id}
+                  className={`flex items-center justify-between p-4 border ${
+                    active ? 'border-foreground' : 'border-borderLight'
+                  }`}
+                >
+                  <div>
+                    <p className="font-mono text-sm font-medium">
+                      {model.name}
+                      {active && <span className="ml-2 text-xs uppercase tracking-widest">In use</span>}
+                    </p>
+                    <p className="font-serif text-sm text-mutedForeground">
+                      {model.description} ~{model.size_mb} MB
+                    </p>
+                  </div>
+                  <div className="flex gap-2">
+                    {!downloaded && (
+                      <button
+                        onClick={() => downloadSpeechModel(model.id)}
+                        disabled={speechDownloading}
+                        className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50 disabled:cursor-not-allowed"
+                      >
+                        Download
+                      </button>
+                    )}
+                    {downloaded && !active && (
+                      <button
+                        onClick={() => updateSettings({ ai: { speech_model_id: model.id } })}
+                        className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all"
+                      >
+                        Use
+                      </button>
+                    )}
+                    {downloaded && (
+                      <button
+                        onClick={() => deleteSpeechModel(model.id)}
+                        className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all"
+                      >
+                        Delete
+                      </button>
+                    )}
+                  </div>
+                </div>
+              )
+            })}
+          </div>
         </div>
 
         {/* Downloaded Models Section */}
@@ -387,12 +652,15 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
                   isActive={isModelActive(model.id)}
                   isActivating={isActivating && selectedModelId === model.id}
                   isDeleting={isDeleting}
+                  benchmark={benchmarks[model.id]}
+                  isBenchmarking={benchmarkingModelId === model.id}
+                  onBenchmark={() => benchmarkModel(model.id).catch(() => {})}
                   showDeleteConfirm={confirmDelete === model.id}
                   onActivate={() => handleActivate(model.id)}
                   onDelete={() => handleDelete(model.id)}
                   onShowDeleteConfirm={() => setConfirmDelete(model.id)}
                   onCancelDelete={() => setConfirmDelete(null)}
-                  disabled={isDownloading || isLoading || isActivating || isDeleting}
+                  disabled={isDownloading || isLoading || isActivating || isDeleting || benchmarkingModelId !== null}
                 />
               ))}
             </div>
@@ -412,6 +680,7 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
                 isDownloaded={isModelDownloaded(model.id)}
                 isActive={isModelActive(model.id)}
                 isDownloading={isDownloading && selectedModelId === model.id}
+                benchmark={benchmarks[model.id]}
                 onDownload={() => handleDownload(model.id)}
                 disabled={isDownloading || isLoading || isActivating || isDeleting}
               />
@@ -432,11 +701,19 @@ export default function ModelSettings({ onClose }: ModelSettingsProps) {
   )
 }
 
+// Measured speed when the model has been benchmarked, otherwise the catalog estimate
+function speedLabel(model: ModelOption, benchmark?: ModelBenchmark) {
+  return benchmark ? `${benchmark.tokens_per_sec.toFixed(1)} tok/s (measured)` : model.tokens_per_sec
+}
+
 interface DownloadedModelCardProps {
   model: ModelOption
   isActive: boolean
   isActivating: boolean
   isDeleting: boolean
+  benchmark?: ModelBenchmark
+  isBenchmarking: boolean
+  onBenchmark: () => void
   showDeleteConfirm: boolean
   onActivate: () => void
   onDelete: () => void
@@ -450,6 +727,9 @@ function DownloadedModelCard({
   isActive,
   isActivating,
   isDeleting,
+  benchmark,
+  isBenchmarking,
+  onBenchmark,
   showDeleteConfirm,
   onActivate,
   onDelete,
@@ -487,9 +767,20 @@ function DownloadedModelCard({
           </p>
           <div className="flex gap-4 text-xs text-mutedForeground">
             <span className="font-mono">{formatSize(model.size_mb)}</span>
-            <span className="font-mono">{model.tokens_per_sec}</span>
+            <span className="font-mono">{speedLabel(model, benchmark)}</span>
             <span className="font-mono">{model.min_ram_gb}GB+ RAM</span>
           </div>
+          {benchmark && (
+            <div className="flex gap-4 mt-1 text-xs text-mutedForeground">
+              <span className="font-mono">Loads in {(benchmark.load_time_ms / 1000).toFixed(1)}s</span>
+              <span className="font-mono">Prompt {benchmark.prompt_tokens_per_sec.toFixed(0)} tok/s</span>
+              {benchmark.peak_memory_bytes !== null && (
+                <span className="font-mono">
+                  +{(benchmark.peak_memory_bytes / 1024 ** 3).toFixed(1)} GB RAM
+                </span>
+              )}
+            </div>
+          )}
         </div>
         <div className="ml-4 flex gap-2">
           {showDeleteConfirm ? (
@@ -520,6 +811,14 @@ function DownloadedModelCard({
                   {isActivating ? 'Loading...' : 'Activate'}
                 </button>
               )}
+              <button
+                onClick={onBenchmark}
+                disabled={disabled}
+                className="px-3 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50 disabled:cursor-not-allowed"
+                title="Measure speed and memory on this device"
+              >
+                {isBenchmarking ? 'Testing...' : 'Benchmark'}
+              </button>
               <button
                 onClick={onShowDeleteConfirm}
                 disabled={disabled || isActive}
@@ -541,6 +840,7 @@ interface ModelCardProps {
   isDownloaded: boolean
   isActive: boolean
   isDownloading: boolean
+  benchmark?: ModelBenchmark
   onDownload: () => void
   disabled: boolean
 }
@@ -550,6 +850,7 @@ function ModelCard({
   isDownloaded,
   isActive,
   isDownloading,
+  benchmark,
   onDownload,
   disabled,
 }: ModelCardProps) {
@@ -589,7 +890,7 @@ function ModelCard({
           </p>
           <div className="flex gap-4 text-xs text-mutedForeground">
             <span className="font-mono">{formatSize(model.size_mb)}</span>
-            <span className="font-mono">{model.tokens_per_sec}</span>
+            <span className="font-mono">{speedLabel(model, benchmark)}</span>
             <span className="font-mono">{model.min_ram_gb}GB+ RAM</span>
           </div>
         </div>
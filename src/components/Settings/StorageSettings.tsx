@@ -1,19 +1,44 @@
 import { useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
+import { useSettingsStore } from '../../stores/settingsStore'
+import AppLockSettings from './AppLockSettings'
+import type { BackupProgress, BackupSummary, CacheSettings, ConfigImportSummary } from '../../stores/settingsStore'
 
 interface StorageInfo {
     database_size_bytes: number
     media_cache_size_bytes: number
+    attachments_size_bytes: number
+    attachments_downloaded: number
     total_emails_cached: number
     total_indexed_emails: number
     data_directory: string
+    free_space_bytes: number
+    last_maintenance: MaintenanceReport | null
 }
 
-interface CacheSettings {
-    cache_enabled: boolean
-    auto_sync_on_start: boolean
-    cache_media_assets: boolean
-    max_cache_age_days: number
+interface MaintenanceReport {
+    started_at: number
+    duration_ms: number
+    size_before: number
+    size_after: number
+    integrity_problems: string[]
+}
+
+interface DiagnosticCheck {
+    account_id: string | null
+    account_email: string | null
+    check: string
+    status: 'ok' | 'warning' | 'failed' | 'skipped'
+    detail: string
+    duration_ms: number
+}
+
+const CHECK_STATUS_COLORS: Record<DiagnosticCheck['status'], string> = {
+    ok: 'text-green-600',
+    warning: 'text-yellow-600',
+    failed: 'text-red-500',
+    skipped: 'text-mutedForeground',
 }
 
 interface StorageSettingsProps {
@@ -28,9 +53,30 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
     const [error, setError] = useState<string | null>(null)
     const [showConfirm, setShowConfirm] = useState<string | null>(null)
     const [savingSettings, setSavingSettings] = useState(false)
+    const [diagnosticsPath, setDiagnosticsPath] = useState<string | null>(null)
+    const [checks, setChecks] = useState<DiagnosticCheck[] | null>(null)
+    const [runningChecks, setRunningChecks] = useState(false)
+    const [configPath, setConfigPath] = useState<string | null>(null)
+    const [importPath, setImportPath] = useState('')
+    const [importSummary, setImportSummary] = useState<ConfigImportSummary | null>(null)
+    const [backup, setBackup] = useState<BackupSummary | null>(null)
+    const [restorePath, setRestorePath] = useState('')
+    const [restored, setRestored] = useState<BackupSummary | null>(null)
+    const [backupProgress, setBackupProgress] = useState<BackupProgress | null>(null)
+    const { settings, loadSettings, updateSettings, exportConfig, importConfig, createBackup, restoreBackup } = useSettingsStore()
 
     useEffect(() => {
         loadData()
+        loadSettings()
+    }, [])
+
+    useEffect(() => {
+        const unlisten = listen<BackupProgress>('backup:progress', (event) => {
+            setBackupProgress(event.payload)
+        })
+        return () => {
+            unlisten.then((fn) => fn())
+        }
     }, [])
 
     const loadData = async () => {
@@ -71,6 +117,79 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
         }
     }
 
+    const handleRunMaintenance = async () => {
+        try {
+            setClearing('maintenance')
+            await invoke<MaintenanceReport>('run_db_maintenance')
+            await loadData()
+        } catch (err) {
+            setError((err as Error).toString())
+        } finally {
+            setClearing(null)
+        }
+    }
+
+    const handleExportDiagnostics = async () => {
+        try {
+            const path = await invoke<string>('export_diagnostics')
+            setDiagnosticsPath(path)
+        } catch (err) {
+            setError((err as Error).toString())
+        }
+    }
+
+    const handleExportConfig = async () => {
+        try {
+            setConfigPath(await exportConfig())
+        } catch (err) {
+            setError((err as Error).toString())
+        }
+    }
+
+    const handleImportConfig = async () => {
+        try {
+            setImportSummary(await importConfig(importPath.trim()))
+            await loadData()
+        } catch (err) {
+            setError((err as Error).toString())
+        }
+    }
+
+    const handleCreateBackup = async () => {
+        try {
+            setBackup(null)
+            setBackup(await createBackup())
+        } catch (err) {
+            setError((err as Error).toString())
+        } finally {
+            setBackupProgress(null)
+        }
+    }
+
+    const handleRestoreBackup = async () => {
+        try {
+            setRestored(null)
+            setRestored(await restoreBackup(restorePath.trim()))
+            setShowConfirm(null)
+            await loadData()
+        } catch (err) {
+            setError((err as Error).toString())
+        } finally {
+            setBackupProgress(null)
+        }
+    }
+
+    const handleRunDiagnostics = async () => {
+        try {
+            setRunningChecks(true)
+            setChecks(await invoke<DiagnosticCheck[]>('run_diagnostics'))
+        } catch (err) {
+            setError((err as Error).toString())
+        } finally {
+            setRunningChecks(false)
+        }
+    }
+
     const handleClearMediaCache = async () => {
         try {
             setClearing('media')
@@ -266,6 +385,39 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
                             </div>
                         </div>
 
+                        {/* Database Maintenance */}
+                        <div className="flex items-center justify-between p-4 border border-borderLight">
+                            <div>
+                                <p className="font-mono text-sm font-medium">Database Maintenance</p>
+                                <p className="font-serif text-sm text-mutedForeground">
+                                    Reclaim space left by deleted emails and check the database for damage
+                                </p>
+                                {storageInfo?.last_maintenance && (
+                                    <p className="font-mono text-xs text-mutedForeground">
+                                        Last run {new Date(storageInfo.last_maintenance.started_at * 1000).toLocaleDateString()},{' '}
+                                        reclaimed {formatBytes(Math.max(0, storageInfo.last_maintenance.size_before - storageInfo.last_maintenance.size_after))}
+                                        {storageInfo.last_maintenance.integrity_problems.length > 0 && (
+                                            <span className="text-red-500">
+                                                {' '}· {storageInfo.last_maintenance.integrity_problems.length} integrity problems found
+                                            </span>
+                                        )}
+                                    </p>
+                                )}
+                            </div>
+                            <div className="flex items-center gap-4">
+                                <span className="font-mono text-sm text-mutedForeground">
+                                    {formatBytes(storageInfo?.free_space_bytes || 0)} free
+                                </span>
+                                <button
+                                    onClick={handleRunMaintenance}
+                                    disabled={clearing !== null}
+                                    className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50 disabled:cursor-not-allowed"
+                                >
+                                    {clearing === 'maintenance' ? 'Running...' : 'Run Now'}
+                                </button>
+                            </div>
+                        </div>
+
                         {/* Media Cache */}
                         <div className="flex items-center justify-between p-4 border border-borderLight">
                             <div>
@@ -273,6 +425,12 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
                                 <p className="font-serif text-sm text-mutedForeground">
                                     Cached images and attachments from emails
                                 </p>
+                                {(storageInfo?.attachments_downloaded || 0) > 0 && (
+                                    <p className="font-mono text-xs text-mutedForeground">
+                                        {storageInfo?.attachments_downloaded} attachments,{' '}
+                                        {formatBytes(storageInfo?.attachments_size_bytes || 0)}
+                                    </p>
+                                )}
                             </div>
                             <div className="flex items-center gap-4">
                                 <span className="font-mono text-lg">
@@ -369,6 +527,43 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
                             />
                         </label>
 
+                        {/* Collapse Duplicates */}
+                        <label className="flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors">
+                            <div>
+                                <p className="font-mono text-sm font-medium">Collapse Duplicates</p>
+                                <p className="font-serif text-sm text-mutedForeground">
+                                    Show a message that is in several folders or accounts only once
+                                </p>
+                            </div>
+                            <input
+                                type="checkbox"
+                                checked={cacheSettings?.collapse_duplicates ?? true}
+                                onChange={(e) => handleSettingChange('collapse_duplicates', e.target.checked)}
+                                className="w-5 h-5 accent-foreground"
+                            />
+                        </label>
+
+                        {/* Attachment Auto-download */}
+                        <div className="flex items-center justify-between p-4 border border-borderLight">
+                            <div>
+                                <p className="font-mono text-sm font-medium">Download Attachments</p>
+                                <p className="font-serif text-sm text-mutedForeground">
+                                    Save smaller attachments while syncing; larger ones download when opened
+                                </p>
+                            </div>
+                            <select
+                                value={cacheSettings?.auto_download_attachments_mb ?? 5}
+                                onChange={(e) => handleSettingChange('auto_download_attachments_mb', parseInt(e.target.value))}
+                                className="px-4 py-2 border-[2px] border-foreground bg-background font-mono text-sm focus:outline-none"
+                            >
+                                <option value={0}>Only when opened</option>
+                                <option value={1}>Under 1 MB</option>
+                                <option value={5}>Under 5 MB</option>
+                                <option value={10}>Under 10 MB</option>
+                                <option value={25}>Under 25 MB</option>
+                            </select>
+                        </div>
+
                         {/* Max Cache Age */}
                         <div className="flex items-center justify-between p-4 border border-borderLight">
                             <div>
@@ -390,9 +585,91 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
                                 <option value={365}>1 year</option>
                             </select>
                         </div>
+
+                        {/* Maintenance Schedule */}
+                        <div className="flex items-center justify-between p-4 border border-borderLight">
+                            <div>
+                                <p className="font-mono text-sm font-medium">Automatic Maintenance</p>
+                                <p className="font-serif text-sm text-mutedForeground">
+                                    How often to compact and check the email database
+                                </p>
+                            </div>
+                            <select
+                                value={cacheSettings?.maintenance_interval_days ?? 7}
+                                onChange={(e) => handleSettingChange('maintenance_interval_days', parseInt(e.target.value))}
+                                className="px-4 py-2 border-[2px] border-foreground bg-background font-mono text-sm focus:outline-none"
+                            >
+                                <option value={1}>Daily</option>
+                                <option value={7}>Weekly</option>
+                                <option value={30}>Monthly</option>
+                                <option value={0}>Never</option>
+                            </select>
+                        </div>
+
+                        {/* Polling Interval */}
+                        <div className="flex items-center justify-between p-4 border border-borderLight">
+                            <div>
+                                <p className="font-mono text-sm font-medium">Check for New Mail</p>
+                                <p className="font-serif text-sm text-mutedForeground">
+                                    How often to refresh in case push updates missed something
+                                </p>
+                            </div>
+                            <select
+                                value={settings?.sync.polling_interval_minutes ?? 10}
+                                onChange={(e) => updateSettings({ sync: { polling_interval_minutes: parseInt(e.target.value) } })}
+                                className="px-4 py-2 border-[2px] border-foreground bg-background font-mono text-sm focus:outline-none"
+                            >
+                                <option value={2}>Every 2 minutes</option>
+                                <option value={5}>Every 5 minutes</option>
+                                <option value={10}>Every 10 minutes</option>
+                                <option value={30}>Every 30 minutes</option>
+                                <option value={60}>Every hour</option>
+                            </select>
+                        </div>
                     </div>
                 </div>
 
+                <AppLockSettings />
+
+                {/* Privacy */}
+                <div className="border-[2px] border-foreground p-6 mb-8">
+                    <h2 className="font-mono text-xs uppercase tracking-widest mb-6">
+                        Privacy
+                    </h2>
+
+                    <label className="flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors">
+                        <div>
+                            <p className="font-mono text-sm font-medium">Check Package Status</p>
+                            <p className="font-serif text-sm text-mutedForeground">
+                                Look up tracked packages on the carriers' websites between shipping emails.
+                                This shares the tracking numbers and your IP address with the carriers.
+                            </p>
+                        </div>
+                        <input
+                            type="checkbox"
+                            checked={settings?.privacy.check_shipment_status ?? false}
+                            onChange={(e) => updateSettings({ privacy: { check_shipment_status: e.target.checked } })}
+                            className="w-5 h-5 accent-foreground"
+                        />
+                    </label>
+
+                    <label className="flex items-center justify-between p-4 border border-borderLight cursor-pointer hover:bg-muted transition-colors mt-4">
+                        <div>
+                            <p className="font-mono text-sm font-medium">Show Domain Logos</p>
+                            <p className="font-serif text-sm text-mutedForeground">
+                                Show the logo or favicon of a sender's domain when they have no Gravatar.
+                                The logos come from the sender's own servers, which can tell that you saw their email.
+                            </p>
+                        </div>
+                        <input
+                            type="checkbox"
+                            checked={settings?.privacy.fetch_domain_logos ?? false}
+                            onChange={(e) => updateSettings({ privacy: { fetch_domain_logos: e.target.checked } })}
+                            className="w-5 h-5 accent-foreground"
+                        />
+                    </label>
+                </div>
+
                 {/* Danger Zone */}
                 <div className="border-[2px] border-red-500 p-6">
                     <h2 className="font-mono text-xs uppercase tracking-widest mb-4 text-red-600">
@@ -515,6 +792,118 @@ export default function StorageSettings({ onClose }: StorageSettingsProps) {
                     <p className="font-serif text-sm text-mutedForeground">
                         Data stored in: <code className="font-mono text-xs bg-muted px-2 py-1">{storageInfo?.data_directory}</code>
                     </p>
+                    <div className="mt-4 flex justify-center gap-2">
+                        <button
+                            onClick={handleRunDiagnostics}
+                            disabled={runningChecks}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50"
+                        >
+                            {runningChecks ? 'Checking...' : 'Check Connections'}
+                        </button>
+                        <button
+                            onClick={handleExportDiagnostics}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all"
+                        >
+                            Export Diagnostics
+                        </button>
+                    </div>
+                    {checks && (
+                        <div className="mt-4 border-[2px] border-foreground text-left">
+                            {checks.map((check, i) => (
+                                <div
+                                    key={`${check.account_id ?? 'app'}-${check.check}`}
+                                    className={`flex items-start gap-4 px-4 py-2 ${i > 0 ? 'border-t border-foreground/20' : ''}`}
+                                >
+                                    <span className={`w-16 font-mono text-xs uppercase ${CHECK_STATUS_COLORS[check.status]}`}>
+                                        {check.status}
+                                    </span>
+                                    <div className="flex-1">
+                                        <p className="font-mono text-sm">
+                                            {check.account_email ? `${check.account_email} · ` : ''}{check.check.toUpperCase()}
+                                        </p>
+                                        <p className="font-serif text-sm text-mutedForeground">{check.detail}</p>
+                                    </div>
+                                    <span className="font-mono text-xs text-mutedForeground">{check.duration_ms} ms</span>
+                                </div>
+                            ))}
+                        </div>
+                    )}
+                    {diagnosticsPath && (
+                        <p className="mt-2 font-serif text-sm text-mutedForeground">
+                            Saved to <code className="font-mono text-xs bg-muted px-2 py-1">{diagnosticsPath}</code>; attach it to your bug report
+                        </p>
+                    )}
+                    <div className="mt-4 flex justify-center gap-2">
+                        <button
+                            onClick={handleExportConfig}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all"
+                        >
+                            Export Configuration
+                        </button>
+                        <input
+                            type="text"
+                            value={importPath}
+                            onChange={(e) => setImportPath(e.target.value)}
+                            placeholder="Path to a configuration file"
+                            className="px-3 py-2 border-[2px] border-foreground bg-background font-mono text-xs w-72"
+                        />
+                        <button
+                            onClick={handleImportConfig}
+                            disabled={!importPath.trim()}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50"
+                        >
+                            Import
+                        </button>
+                    </div>
+                    {configPath && (
+                        <p className="mt-2 font-serif text-sm text-mutedForeground">
+                            Configuration saved to <code className="font-mono text-xs bg-muted px-2 py-1">{configPath}</code>; it holds no passwords or sign-in tokens
+                        </p>
+                    )}
+                    {importSummary && (
+                        <p className="mt-2 font-serif text-sm text-mutedForeground">
+                            Imported {importSummary.accounts_added} account(s), {importSummary.categories_added} categories, {importSummary.labels_added} labels, {importSummary.sections_added} sections and {importSummary.saved_searches_imported} saved searches
+                            {importSummary.needs_sign_in.length > 0 && `. Sign in again to ${importSummary.needs_sign_in.join(', ')}`}
+                        </p>
+                    )}
+                    <div className="mt-4 flex justify-center gap-2">
+                        <button
+                            onClick={handleCreateBackup}
+                            disabled={backupProgress !== null}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50"
+                        >
+                            Back Up Mail
+                        </button>
+                        <input
+                            type="text"
+                            value={restorePath}
+                            onChange={(e) => setRestorePath(e.target.value)}
+                            placeholder="Path to a backup archive"
+                            className="px-3 py-2 border-[2px] border-foreground bg-background font-mono text-xs w-72"
+                        />
+                        <button
+                            onClick={() => showConfirm === 'restore' ? handleRestoreBackup() : setShowConfirm('restore')}
+                            disabled={!restorePath.trim() || backupProgress !== null}
+                            className="px-4 py-2 border-[2px] border-foreground font-mono text-xs uppercase tracking-widest hover:bg-foreground hover:text-background transition-all disabled:opacity-50"
+                        >
+                            {showConfirm === 'restore' ? 'Replace Local Mail?' : 'Restore'}
+                        </button>
+                    </div>
+                    {backupProgress && (
+                        <p className="mt-2 font-mono text-xs text-mutedForeground text-center uppercase tracking-widest">
+                            {backupProgress.stage} {backupProgress.total > 0 ? Math.round((backupProgress.done / backupProgress.total) * 100) : 0}%
+                        </p>
+                    )}
+                    {backup && (
+                        <p className="mt-2 font-serif text-sm text-mutedForeground">
+                            Backed up {backup.file_count} files ({formatBytes(backup.total_bytes)}) to <code className="font-mono text-xs bg-muted px-2 py-1">{backup.path}</code>
+                        </p>
+                    )}
+                    {restored && (
+                        <p className="mt-2 font-serif text-sm text-mutedForeground">
+                            Restored the backup from {new Date(restored.created_at * 1000).toLocaleString()}
+                        </p>
+                    )}
                 </div>
             </div>
         </div>
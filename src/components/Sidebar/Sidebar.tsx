@@ -114,7 +114,10 @@ export default function Sidebar({ onFolderSelect, onCompose, onOpenModelSettings
             className="w-full p-4 text-left hover:bg-muted transition-colors"
           >
             <div className="flex items-center gap-3">
-              <div className={`w-2 h-2 rounded-full ${activeAccount ? getProviderColor(activeAccount.provider) : 'bg-gray-400'}`} />
+              <div
+                className={`w-2 h-2 rounded-full ${activeAccount ? getProviderColor(activeAccount.provider) : 'bg-gray-400'}`}
+                style={activeAccount?.color ? { backgroundColor: activeAccount.color } : undefined}
+              />
               <div className="flex-1 min-w-0">
                 <p className="font-serif text-sm truncate">
                   {activeAccount?.display_name || 'No account'}
@@ -139,10 +142,18 @@ export default function Sidebar({ onFolderSelect, onCompose, onOpenModelSettings
                     account.id === activeAccountId ? 'bg-muted' : ''
                   }`}
                 >
-                  <div className={`w-2 h-2 rounded-full ${getProviderColor(account.provider)}`} />
+                  <div
+                    className={`w-2 h-2 rounded-full ${getProviderColor(account.provider)}`}
+                    style={account.color ? { backgroundColor: account.color } : undefined}
+                  />
                   <div className="flex-1 min-w-0">
                     <p className="font-serif text-xs truncate">{account.email}</p>
                   </div>
+                  {account.label && (
+                    <span className="font-mono text-xs uppercase tracking-widest text-mutedForeground">
+                      {account.label}
+                    </span>
+                  )}
                   {account.id === activeAccountId && (
                     <span className="font-mono text-xs">✓</span>
                   )}
@@ -260,9 +260,12 @@ export function SmartInbox({ onCompose }: SmartInboxProps) {
                 selectedAccountId === account.id
                   ? 'bg-foreground text-background'
                   : 'hover:bg-foreground hover:text-background'
-              }`}
+              } flex items-center gap-2`}
             >
-              {account.display_name || account.email}
+              {account.color && (
+                <span className="w-2 h-2 rounded-full" style={{ backgroundColor: account.color }} />
+              )}
+              {account.label || account.display_name || account.email}
             </button>
           ))}
         </div>
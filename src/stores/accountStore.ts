@@ -15,6 +15,62 @@ export interface Account {
   is_active: boolean
   created_at: number
   last_synced_at: number | null
+  cert_fingerprint: string | null
+  // #rrggbb shown next to the account
+  color: string | null
+  sort_order: number
+  // Short custom label, e.g. "Work"
+  label: string | null
+}
+
+// Changes to an account's display metadata; an empty color or label clears it
+export interface AccountPatch {
+  display_name?: string
+  color?: string
+  sort_order?: number
+  label?: string
+}
+
+// Address an account can send as besides its own
+export interface Identity {
+  id: string
+  account_id: string
+  address: string
+  display_name: string | null
+  signature: string | null
+  created_at: number
+}
+
+// Which new mail of an account raises a desktop notification
+export interface NotificationSettings {
+  enabled: boolean
+  min_priority: 'HIGH' | 'MEDIUM' | 'LOW'
+  muted_categories: string[]
+  // Minutes after local midnight; end before start runs past midnight
+  quiet_hours: { start_minute: number; end_minute: number } | null
+  vip_only: boolean
+  vip_senders: string[]
+}
+
+// Which folders of an account are synced and how far back; null means no limit
+export interface AccountSyncSettings {
+  // Folders kept in sync; the standard folders are watched when null
+  folders: string[] | null
+  days_back: number | null
+  max_messages: number | null
+  // Check the watched folders every this many seconds (at least 5) instead of using IDLE
+  fast_poll_secs: number | null
+}
+
+// Whether an account's credentials still work
+export interface AccountAuthStatus {
+  account_id: string
+  auth_type: string
+  // needs_reauth: the token expired and can't be refreshed; call reauthenticateAccount
+  state: 'valid' | 'needs_reauth' | 'missing'
+  expires_at: number | null
+  has_refresh_token: boolean
+  error: string | null
 }
 
 interface AccountStore {
@@ -33,10 +89,19 @@ interface AccountStore {
     imapPort?: number
     smtpHost?: string
     smtpPort?: number
+    certFingerprint?: string
   }) => Promise<Account>
   removeAccount: (accountId: string) => Promise<void>
+  updateAccount: (accountId: string, patch: AccountPatch) => Promise<void>
   setActiveAccount: (accountId: string) => Promise<void>
   connectAccount: (accountId: string) => Promise<void>
+  getAuthStatus: (accountId: string) => Promise<AccountAuthStatus>
+  // Opens the provider's sign-in page for the account and resolves once signed in
+  reauthenticateAccount: (accountId: string) => Promise<AccountAuthStatus>
+  getNotificationSettings: (accountId: string) => Promise<NotificationSettings>
+  setNotificationSettings: (accountId: string, settings: NotificationSettings) => Promise<void>
+  getSyncSettings: (accountId: string) => Promise<AccountSyncSettings>
+  setSyncSettings: (accountId: string, settings: AccountSyncSettings) => Promise<void>
 }
 
 export const useAccountStore = create<AccountStore>((set, get) => ({
@@ -72,6 +137,7 @@ export const useAccountStore = create<AccountStore>((set, get) => ({
         imapPort: params.imapPort,
         smtpHost: params.smtpHost,
         smtpPort: params.smtpPort,
+        certFingerprint: params.certFingerprint,
       })
 
       await get().fetchAccounts()
@@ -94,6 +160,17 @@ export const useAccountStore = create<AccountStore>((set, get) => ({
     }
   },
 
+  updateAccount: async (accountId: string, patch: AccountPatch) => {
+    try {
+      set({ error: null })
+      await invoke<Account>('update_account', { accountId, patch })
+      await get().fetchAccounts()
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
   setActiveAccount: async (accountId: string) => {
     try {
       await invoke('set_active_account', { accountId })
@@ -118,4 +195,34 @@ export const useAccountStore = create<AccountStore>((set, get) => ({
       throw error
     }
   },
+
+  getAuthStatus: async (accountId: string) => {
+    return await invoke<AccountAuthStatus>('get_account_auth_status', { accountId })
+  },
+
+  reauthenticateAccount: async (accountId: string) => {
+    try {
+      set({ error: null })
+      return await invoke<AccountAuthStatus>('reauthenticate_account', { accountId })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
+  getNotificationSettings: async (accountId: string) => {
+    return await invoke<NotificationSettings>('get_notification_settings', { accountId })
+  },
+
+  setNotificationSettings: async (accountId: string, settings: NotificationSettings) => {
+    await invoke('set_notification_settings', { accountId, settings })
+  },
+
+  getSyncSettings: async (accountId: string) => {
+    return await invoke<AccountSyncSettings>('get_sync_settings', { accountId })
+  },
+
+  setSyncSettings: async (accountId: string, settings: AccountSyncSettings) => {
+    await invoke('set_sync_settings', { accountId, settings })
+  },
 }))
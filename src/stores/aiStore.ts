@@ -10,6 +10,19 @@ export type ModelStatus =
   | { status: 'ready' }
   | { status: 'error'; message: string }
 
+// Sent when the model chosen last was no longer downloaded and another (or none) was loaded
+export interface ModelFallback {
+  requested: string
+  loaded: string | null
+}
+
+// Payload of `ai:warmup`, emitted once the startup load of the LLM or the embedding engine finishes
+export interface AiWarmupStatus {
+  component: 'llm' | 'rag'
+  ready: boolean
+  error: string | null
+}
+
 export interface ModelOption {
   id: string
   name: string
@@ -19,6 +32,76 @@ export interface ModelOption {
   description: string
   min_ram_gb: number
   tokens_per_sec: string
+  custom: boolean
+  prompt_format: PromptFormat | null
+}
+
+export type PromptFormat = 'lfm' | 'chat_ml' | 'llama3' | 'gemma' | 'mistral' | 'phi3'
+
+export interface ComputeCapabilities {
+  metal: boolean
+  cuda: boolean
+  llm_gpu_offload: boolean
+  cpu_cores: number
+  total_memory_bytes: number | null
+  os: string
+  arch: string
+}
+
+// Applied the next time a model is loaded
+export interface ComputeSettings {
+  device: 'auto' | 'cpu'
+  // Free models after this many idle minutes (reloaded on next use); null keeps them loaded
+  idle_unload_minutes: number | null
+}
+
+// Approximate; model sizes are their weight files, which are memory-mapped
+export interface MemoryUsage {
+  process_rss_bytes: number | null
+  llm_model: string | null
+  llm_model_bytes: number | null
+  embedding_model: string | null
+  embedding_model_bytes: number | null
+  idle_unload_minutes: number | null
+}
+
+// Placeholder strings; {content} stands for a turn's text. An empty system template
+// prepends the system prompt to the first user turn.
+export interface PromptTemplate {
+  prefix: string
+  system: string
+  user: string
+  assistant: string
+  generation_prompt: string
+  stop: string[]
+}
+
+export interface ModelPromptTemplate {
+  detected_format: PromptFormat
+  default_template: PromptTemplate
+  custom_template: PromptTemplate | null
+}
+
+// Measured on this machine by benchmark_model
+export interface ModelBenchmark {
+  model_id: string
+  load_time_ms: number
+  prompt_tokens: number
+  prompt_tokens_per_sec: number
+  generated_tokens: number
+  tokens_per_sec: number
+  peak_memory_bytes: number | null
+  measured_at: number
+}
+
+// Saving reloads the active model; null fields use llama.cpp defaults / full GPU offload
+export interface LlmSettings {
+  n_ctx: number
+  n_batch: number
+  n_threads: number | null
+  gpu_layers: number | null
+  temperature: number
+  top_p: number
 }
 
 export interface ModelInfo {
@@ -37,13 +120,20 @@ interface AiStore {
   downloadedModels: ModelOption[]
   selectedModelId: string | null
   activeModelId: string | null  // Currently loaded model
+  modelFallback: ModelFallback | null
   error: string | null
   isDeleting: boolean
   isActivating: boolean
+  computeCapabilities: ComputeCapabilities | null
+  computeSettings: ComputeSettings | null
+  llmSettings: LlmSettings | null
+  benchmarks: Record<string, ModelBenchmark>
+  benchmarkingModelId: string | null
 
   // Actions
   checkModelStatus: () => Promise<void>
   downloadModel: (modelId?: string) => Promise<void>
+  addCustomModel: (pathOrUrl: string, name?: string) => Promise<ModelOption>
   initAi: () => Promise<boolean>
   getModelInfo: () => Promise<void>
   getAvailableModels: () => Promise<void>
@@ -52,6 +142,18 @@ interface AiStore {
   deleteModel: (modelId: string) => Promise<void>
   activateModel: (modelId: string) => Promise<void>
   setSelectedModel: (modelId: string) => void
+  getComputeCapabilities: () => Promise<void>
+  getComputeSettings: () => Promise<void>
+  saveComputeSettings: (settings: ComputeSettings) => Promise<void>
+  getLlmSettings: () => Promise<void>
+  unloadModel: () => Promise<boolean>
+  getMemoryUsage: () => Promise<MemoryUsage | null>
+  benchmarkModel: (modelId: string) => Promise<ModelBenchmark>
+  getModelBenchmarks: () => Promise<void>
+  getPromptTemplate: (modelId: string) => Promise<ModelPromptTemplate | null>
+  setPromptTemplate: (modelId: string, template: PromptTemplate | null) => Promise<void>
+  setLlmSettings: (settings: LlmSettings) => Promise<void>
+  subscribe: () => Promise<UnlistenFn>
   reset: () => void
 }
 
@@ -65,9 +167,15 @@ export const useAiStore = create<AiStore>((set, get) => ({
   downloadedModels: [],
   selectedModelId: null,
   activeModelId: null,
+  modelFallback: null,
   error: null,
   isDeleting: false,
   isActivating: false,
+  computeCapabilities: null,
+  computeSettings: null,
+  llmSettings: null,
+  benchmarks: {},
+  benchmarkingModelId: null,
 
   checkModelStatus: async () => {
     try {
@@ -149,9 +257,39 @@ export const useAiStore = create<AiStore>((set, get) => ({
     }
   },
 
+  addCustomModel: async (pathOrUrl: string, name?: string) => {
+    let progressUnlisten: UnlistenFn | null = null
+
+    try {
+      set({ downloadProgress: 0, error: null })
+      progressUnlisten = await listen<number>('model:progress', (event) => {
+        set({ downloadProgress: event.payload })
+      })
+
+      const model = await invoke<ModelOption>('add_custom_model', {
+        pathOrUrl,
+        name: name || null,
+      })
+
+      await get().getAvailableModels()
+      await get().getDownloadedModels()
+      return model
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    } finally {
+      if (progressUnlisten) progressUnlisten()
+    }
+  },
+
   initAi: async () => {
+    let fallbackUnlisten: UnlistenFn | null = null
     try {
-      set({ modelStatus: { status: 'loading' }, error: null })
+      set({ modelStatus: { status: 'loading' }, error: null, modelFallback: null })
+
+      fallbackUnlisten = await listen<ModelFallback>('model:fallback', (event) => {
+        set({ modelFallback: event.payload })
+      })
 
       // Use fallback init which works with or without model
       const modelLoaded = await invoke<boolean>('init_ai_fallback')
@@ -168,6 +306,10 @@ export const useAiStore = create<AiStore>((set, get) => ({
       // Refresh active model ID
       await get().getActiveModelId()
 
+      // The model may have been loaded at startup, before we were listening
+      const fallback = await invoke<ModelFallback | null>('get_model_fallback')
+      if (fallback) set({ modelFallback: fallback })
+
       return modelLoaded
     } catch (error) {
       set({
@@ -176,6 +318,8 @@ export const useAiStore = create<AiStore>((set, get) => ({
         isAiReady: false,
       })
       return false
+    } finally {
+      if (fallbackUnlisten) fallbackUnlisten()
     }
   },
 
@@ -246,6 +390,7 @@ export const useAiStore = create<AiStore>((set, get) => ({
       set({
         activeModelId: modelId,
         selectedModelId: modelId,
+        modelFallback: null,
         isActivating: false,
         modelStatus: actualStatus,
         isModelLoaded: actualStatus.status === 'ready',
@@ -265,6 +410,134 @@ export const useAiStore = create<AiStore>((set, get) => ({
     set({ selectedModelId: modelId })
   },
 
+  getComputeCapabilities: async () => {
+    try {
+      const capabilities = await invoke<ComputeCapabilities>('get_compute_capabilities')
+      set({ computeCapabilities: capabilities })
+    } catch (error) {
+      console.error('Failed to get compute capabilities:', error)
+    }
+  },
+
+  getComputeSettings: async () => {
+    try {
+      const settings = await invoke<ComputeSettings>('get_compute_settings')
+      set({ computeSettings: settings })
+    } catch (error) {
+      console.error('Failed to get compute settings:', error)
+    }
+  },
+
+  saveComputeSettings: async (settings: ComputeSettings) => {
+    try {
+      await invoke('save_compute_settings', { settings })
+      set({ computeSettings: settings, error: null })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
+  getLlmSettings: async () => {
+    try {
+      const settings = await invoke<LlmSettings>('get_llm_settings')
+      set({ llmSettings: settings })
+    } catch (error) {
+      console.error('Failed to get LLM settings:', error)
+    }
+  },
+
+  setLlmSettings: async (settings: LlmSettings) => {
+    try {
+      await invoke('set_llm_settings', { settings })
+      set({ llmSettings: settings, error: null })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
+  unloadModel: async () => {
+    try {
+      const unloaded = await invoke<boolean>('unload_model')
+      if (unloaded) set({ isModelLoaded: false })
+      return unloaded
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
+  getMemoryUsage: async () => {
+    try {
+      return await invoke<MemoryUsage>('get_memory_usage')
+    } catch (error) {
+      console.error('Failed to get memory usage:', error)
+      return null
+    }
+  },
+
+  benchmarkModel: async (modelId: string) => {
+    set({ benchmarkingModelId: modelId, error: null })
+    try {
+      const benchmark = await invoke<ModelBenchmark>('benchmark_model', { modelId })
+      set({ benchmarks: { ...get().benchmarks, [modelId]: benchmark } })
+      return benchmark
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    } finally {
+      set({ benchmarkingModelId: null })
+    }
+  },
+
+  getModelBenchmarks: async () => {
+    try {
+      const list = await invoke<ModelBenchmark[]>('get_model_benchmarks')
+      set({ benchmarks: Object.fromEntries(list.map((b) => [b.model_id, b])) })
+    } catch (error) {
+      console.error('Failed to get model benchmarks:', error)
+    }
+  },
+
+  getPromptTemplate: async (modelId: string) => {
+    try {
+      return await invoke<ModelPromptTemplate>('get_prompt_template', { modelId })
+    } catch (error) {
+      console.error('Failed to get prompt template:', error)
+      return null
+    }
+  },
+
+  setPromptTemplate: async (modelId: string, template: PromptTemplate | null) => {
+    try {
+      await invoke('set_prompt_template', { modelId, template })
+      set({ error: null })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      throw error
+    }
+  },
+
+  // Pick up the model loaded in the background at startup; call the returned function to stop
+  subscribe: async () => {
+    const warmupUnlisten = await listen<AiWarmupStatus>('ai:warmup', (event) => {
+      if (event.payload.component !== 'llm') return
+      if (event.payload.ready) {
+        set({ isAiReady: true })
+        get().getActiveModelId()
+      }
+      get().checkModelStatus()
+    })
+    const fallbackUnlisten = await listen<ModelFallback>('model:fallback', (event) => {
+      set({ modelFallback: event.payload })
+    })
+    return () => {
+      warmupUnlisten()
+      fallbackUnlisten()
+    }
+  },
+
   reset: () => {
     set({
       modelStatus: { status: 'not_downloaded' },
@@ -7,13 +7,25 @@ interface AuthStatus {
   email: string | null
 }
 
+// Code the user enters on the provider's page when signing in without a browser redirect
+export interface DeviceCodePrompt {
+  user_code: string
+  verification_uri: string
+  verification_uri_complete: string | null
+  expires_in_secs: number
+}
+
 interface AuthStore {
   authenticated: boolean
   email: string | null
   loading: boolean
   error: string | null
+  // Shown while a code sign-in waits for the user
+  deviceCode: DeviceCodePrompt | null
   checkAuth: () => Promise<void>
   signIn: () => Promise<void>
+  // Sign in by entering a code on the provider's page; for when the local redirect is blocked
+  signInWithCode: (provider: string) => Promise<void>
   signOut: () => Promise<void>
 }
 
@@ -22,6 +34,7 @@ export const useAuthStore = create<AuthStore>((set) => ({
   email: null,
   loading: false,
   error: null,
+  deviceCode: null,
 
   checkAuth: async () => {
     try {
@@ -73,6 +86,34 @@ export const useAuthStore = create<AuthStore>((set) => ({
     }
   },
 
+  signInWithCode: async (provider: string) => {
+    try {
+      set({ loading: true, error: null })
+      const deviceCode = await invoke<DeviceCodePrompt>('start_device_auth', {
+        provider,
+        accountId: null,
+      })
+      set({ deviceCode })
+      await open(deviceCode.verification_uri_complete ?? deviceCode.verification_uri)
+
+      await invoke('complete_device_auth')
+      const status = await invoke<AuthStatus>('check_auth_status')
+      set({
+        authenticated: status.authenticated,
+        email: status.email,
+        loading: false,
+        deviceCode: null,
+      })
+    } catch (error) {
+      set({
+        error: (error as Error).toString(),
+        loading: false,
+        deviceCode: null,
+        authenticated: false,
+      })
+    }
+  },
+
   signOut: async () => {
     try {
       set({ loading: true, error: null })
@@ -0,0 +1,186 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/core'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+export interface SpeechModelOption {
+  id: string
+  name: string
+  repo: string
+  size_mb: number
+  description: string
+  multilingual: boolean
+}
+
+interface DictationStore {
+  models: SpeechModelOption[]
+  downloadedModels: string[]
+  isDownloading: boolean
+  downloadProgress: number
+  isRecording: boolean
+  isTranscribing: boolean
+  error: string | null
+
+  loadModels: () => Promise<void>
+  downloadModel: (modelId?: string) => Promise<void>
+  deleteModel: (modelId: string) => Promise<void>
+  startRecording: () => Promise<void>
+  // Stops recording and resolves with the transcript
+  stopRecording: () => Promise<string>
+  // Stops recording without transcribing
+  cancelRecording: () => Promise<void>
+  clearError: () => void
+}
+
+// Whisper's sample rate; asking the browser for it saves resampling on the backend
+const SAMPLE_RATE = 16000
+
+// The recording in progress; kept outside the store since none of it is rendered
+let recording: {
+  stream: MediaStream
+  context: AudioContext
+  source: MediaStreamAudioSourceNode
+  processor: ScriptProcessorNode
+  chunks: Float32Array[]
+} | null = null
+
+// Stop the recording in progress and return what it captured
+async function finishRecording(): Promise<{ samples: Float32Array; sampleRate: number } | null> {
+  const current = recording
+  if (!current) return null
+  recording = null
+
+  current.processor.disconnect()
+  current.source.disconnect()
+  current.stream.getTracks().forEach((track) => track.stop())
+  const sampleRate = current.context.sampleRate
+  await current.context.close()
+
+  const length = current.chunks.reduce((total, chunk) => total + chunk.length, 0)
+  const samples = new Float32Array(length)
+  let offset = 0
+  for (const chunk of current.chunks) {
+    samples.set(chunk, offset)
+    offset += chunk.length
+  }
+  return { samples, sampleRate }
+}
+
+// 16-bit mono PCM WAV of `samples`
+function encodeWav(samples: Float32Array, sampleRate: number): Uint8Array {
+  const buffer = new ArrayBuffer(44 + samples.length * 2)
+  const view = new DataView(buffer)
+  const writeString = (offset: number, value: string) => {
+    for (let i = 0; i < value.length; i++) view.setUint8(offset + i, value.charCodeAt(i))
+  }
+  writeString(0, 'RIFF')
+  view.setUint32(4, 36 + samples.length * 2, true)
+  writeString(8, 'WAVE')
+  writeString(12, 'fmt ')
+  view.setUint32(16, 16, true)
+  view.setUint16(20, 1, true) // PCM
+  view.setUint16(22, 1, true) // mono
+  view.setUint32(24, sampleRate, true)
+  view.setUint32(28, sampleRate * 2, true)
+  view.setUint16(32, 2, true)
+  view.setUint16(34, 16, true)
+  writeString(36, 'data')
+  view.setUint32(40, samples.length * 2, true)
+  samples.forEach((sample, i) => {
+    const clamped = Math.max(-1, Math.min(1, sample))
+    view.setInt16(44 + i * 2, clamped < 0 ? clamped * 0x8000 : clamped * 0x7fff, true)
+  })
+  return new Uint8Array(buffer)
+}
+
+export const useDictationStore = create<DictationStore>((set, get) => ({
+  models: [],
+  downloadedModels: [],
+  isDownloading: false,
+  downloadProgress: 0,
+  isRecording: false,
+  isTranscribing: false,
+  error: null,
+
+  loadModels: async () => {
+    try {
+      const [models, downloadedModels] = await Promise.all([
+        invoke<SpeechModelOption[]>('get_available_speech_models'),
+        invoke<string[]>('get_downloaded_speech_models'),
+      ])
+      set({ models, downloadedModels })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  downloadModel: async (modelId?: string) => {
+    let progressUnlisten: UnlistenFn | null = null
+    try {
+      set({ isDownloading: true, downloadProgress: 0, error: null })
+      progressUnlisten = await listen<number>('speech:progress', (event) => {
+        set({ downloadProgress: event.payload })
+      })
+      await invoke('download_speech_model', { modelId: modelId ?? null })
+      await get().loadModels()
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    } finally {
+      if (progressUnlisten) progressUnlisten()
+      set({ isDownloading: false })
+    }
+  },
+
+  deleteModel: async (modelId: string) => {
+    try {
+      await invoke('delete_speech_model', { modelId })
+      await get().loadModels()
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  startRecording: async () => {
+    if (recording) return
+    try {
+      set({ error: null })
+      const stream = await navigator.mediaDevices.getUserMedia({ audio: true })
+      const context = new AudioContext({ sampleRate: SAMPLE_RATE })
+      const source = context.createMediaStreamSource(stream)
+      const processor = context.createScriptProcessor(4096, 1, 1)
+      const chunks: Float32Array[] = []
+      processor.onaudioprocess = (event) => {
+        chunks.push(new Float32Array(event.inputBuffer.getChannelData(0)))
+      }
+      source.connect(processor)
+      processor.connect(context.destination)
+      recording = { stream, context, source, processor, chunks }
+      set({ isRecording: true })
+    } catch (error) {
+      set({ error: `Microphone unavailable: ${(error as Error).message ?? error}` })
+    }
+  },
+
+  stopRecording: async () => {
+    const captured = await finishRecording()
+    if (!captured) return ''
+
+    set({ isRecording: false, isTranscribing: true })
+    try {
+      return await invoke<string>('transcribe_audio', {
+        wavBytes: Array.from(encodeWav(captured.samples, captured.sampleRate)),
+      })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      return ''
+    } finally {
+      set({ isTranscribing: false })
+    }
+  },
+
+  cancelRecording: async () => {
+    await finishRecording()
+    set({ isRecording: false })
+  },
+
+  clearError: () => set({ error: null }),
+}))
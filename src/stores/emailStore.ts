@@ -3,6 +3,9 @@ import { invoke } from '@tauri-apps/api/core'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
 import { useRagStore } from './ragStore'
 import { useSmartInboxStore } from './smartInboxStore'
+import type { CacheChangeEvent, EmailWithInsight } from './smartInboxStore'
+import { useSettingsStore } from './settingsStore'
+import type { AppSettings } from './settingsStore'
 
 export interface EmailListItem {
   id: string
@@ -21,9 +24,34 @@ export interface Email extends EmailListItem {
   to: string[]
   body_html: string | null
   body_plain: string | null
+  // body_plain without quoted replies or signature
+  body_clean?: string | null
   labels: string[]
+  remote_images_blocked?: number
+  // Where the sender asked for a read receipt, until it is sent or declined
+  read_receipt_to?: string | null
 }
 
+// User label; emails list its `keyword` in `labels`
+export interface Label {
+  name: string
+  keyword: string
+  color: string | null
+  email_count: number
+}
+
+// Primary or a Gmail category tab
+export type InboxTabId = 'primary' | 'social' | 'promotions' | 'updates' | 'forums'
+
+export interface InboxTab {
+  id: InboxTabId
+  total: number
+  unread: number
+}
+
+// Special folder a folder can be made, overriding detection
+export type FolderRole = 'Sent' | 'Trash' | 'Drafts' | 'Spam' | 'Archive' | 'Starred'
+
 interface NewMailEvent {
   account_id: string
   folder: string
@@ -35,7 +63,52 @@ export interface FolderStats {
   unread_count: number
 }
 
-const POLLING_INTERVAL_MS = 10 * 60 * 1000 // 10 minutes
+interface FolderStatus {
+  folder: string
+  total_count: number
+  unread_count: number
+  recent_count: number
+}
+
+interface MailAlertEvent {
+  account_id: string
+  email_id: string
+  from: string
+  subject: string
+  snippet: string
+}
+
+async function showMailAlert(alert: MailAlertEvent) {
+  if (!('Notification' in window)) return
+  if (Notification.permission === 'default') {
+    await Notification.requestPermission()
+  }
+  if (Notification.permission !== 'granted') return
+  const notification = new Notification(alert.from || 'New email', {
+    body: alert.subject || alert.snippet,
+    tag: alert.email_id,
+  })
+  notification.onclick = () => {
+    window.focus()
+    useEmailStore.getState().selectEmail(alert.email_id)
+  }
+}
+
+const DEFAULT_POLLING_INTERVAL_MINUTES = 10
+
+// Refresh the current folder and stats every `minutes`, replacing any earlier timer
+function startPolling(minutes: number) {
+  const { pollingInterval } = useEmailStore.getState()
+  if (pollingInterval) {
+    clearInterval(pollingInterval)
+  }
+  const interval = setInterval(() => {
+    const { fetchEmails, fetchFolderStats } = useEmailStore.getState()
+    fetchEmails(50, undefined, true)
+    fetchFolderStats()
+  }, minutes * 60 * 1000)
+  useEmailStore.setState({ pollingInterval: interval })
+}
 
 interface EmailStore {
   emails: EmailListItem[]
@@ -47,6 +120,8 @@ interface EmailStore {
   error: string | null
   pollingInterval: ReturnType<typeof setInterval> | null
   unlistenNewMail: UnlistenFn | null
+  labels: Label[]
+  inboxTabs: InboxTab[]
   fetchEmails: (maxResults?: number, query?: string, forceRefresh?: boolean, folder?: string) => Promise<void>
   syncOtherFolders: () => Promise<void>
   fetchFolderStats: () => Promise<void>
@@ -57,6 +132,22 @@ interface EmailStore {
   startSync: () => Promise<void>
   stopSync: () => void
   refreshEmails: () => Promise<void>
+  fetchLabels: () => Promise<void>
+  createLabel: (name: string, color?: string) => Promise<Label>
+  deleteLabel: (name: string) => Promise<void>
+  applyLabel: (emailId: string, label: string) => Promise<void>
+  removeLabel: (emailId: string, label: string) => Promise<void>
+  getEmailsByLabel: (label: string, limit?: number, offset?: number) => Promise<EmailWithInsight[]>
+  fetchInboxTabs: (accountId?: string) => Promise<void>
+  getInboxTabEmails: (tab: InboxTabId, accountId?: string, limit?: number, offset?: number) => Promise<EmailWithInsight[]>
+  moveEmail: (emailId: string, targetFolder: string) => Promise<void>
+  createFolder: (name: string) => Promise<void>
+  renameFolder: (oldName: string, newName: string) => Promise<void>
+  deleteFolder: (name: string) => Promise<void>
+  // null role: the folder is not special
+  setFolderRole: (folder: string, role: FolderRole | null) => Promise<void>
+  resetFolderRole: (folder: string) => Promise<void>
+  searchServer: (query: string, folder?: string, maxResults?: number) => Promise<EmailListItem[]>
 }
 
 export const useEmailStore = create<EmailStore>((set, get) => ({
@@ -69,6 +160,8 @@ export const useEmailStore = create<EmailStore>((set, get) => ({
   error: null,
   pollingInterval: null,
   unlistenNewMail: null,
+  labels: [],
+  inboxTabs: [],
 
   fetchEmails: async (maxResults = 50, query, forceRefresh = false, folder) => {
     try {
@@ -156,8 +249,15 @@ export const useEmailStore = create<EmailStore>((set, get) => ({
 
   fetchFolderStats: async () => {
     try {
-      const stats = await invoke<FolderStats[]>('get_folder_stats')
-      set({ folderStats: stats })
+      // One STATUS pass over every folder, falling back to cached counts when offline
+      const statuses = await invoke<FolderStatus[]>('get_folder_status')
+      set({
+        folderStats: statuses.map((s) => ({
+          folder_name: s.folder,
+          total_count: s.total_count,
+          unread_count: s.unread_count,
+        })),
+      })
     } catch (error) {
       console.warn('[EmailStore] Failed to fetch folder stats:', error)
     }
@@ -171,7 +271,29 @@ export const useEmailStore = create<EmailStore>((set, get) => ({
       fetchEmails(50, undefined, true)
       fetchFolderStats()
     })
-    return unlisten
+    // New INBOX mail that passed the account's notification settings
+    const unlistenNotify = await listen<MailAlertEvent>('email:notify', (event) => {
+      showMailAlert(event.payload)
+    })
+    // Emails dropped from the cache, e.g. moved or deleted on another device
+    const unlistenRemoved = await listen<CacheChangeEvent>('emails:removed', (event) => {
+      const removed = new Set(event.payload.email_ids)
+      useEmailStore.setState((state) => ({
+        emails: state.emails.filter((email) => !removed.has(email.id)),
+      }))
+    })
+    // Follow changes to the polling interval while syncing
+    const unlistenSettings = await listen<AppSettings>('settings:changed', (event) => {
+      if (useEmailStore.getState().pollingInterval) {
+        startPolling(event.payload.sync.polling_interval_minutes)
+      }
+    })
+    return () => {
+      unlisten()
+      unlistenNotify()
+      unlistenRemoved()
+      unlistenSettings()
+    }
   },
 
   startSync: async () => {
@@ -203,13 +325,9 @@ export const useEmailStore = create<EmailStore>((set, get) => ({
       console.warn('[EmailStore] IDLE monitoring failed to start:', e)
     }
 
-    // 7. Start polling fallback (every 10 minutes)
-    const interval = setInterval(() => {
-      const { fetchEmails, fetchFolderStats } = useEmailStore.getState()
-      fetchEmails(50, undefined, true)
-      fetchFolderStats()
-    }, POLLING_INTERVAL_MS)
-    set({ pollingInterval: interval })
+    // 7. Start polling fallback (every 10 minutes unless the sync settings say otherwise)
+    const settings = await useSettingsStore.getState().loadSettings()
+    startPolling(settings?.sync.polling_interval_minutes ?? DEFAULT_POLLING_INTERVAL_MINUTES)
   },
 
   stopSync: () => {
@@ -236,4 +354,109 @@ export const useEmailStore = create<EmailStore>((set, get) => ({
   refreshEmails: async () => {
     await get().fetchEmails(50, undefined, true)
   },
+
+  fetchLabels: async () => {
+    try {
+      const labels = await invoke<Label[]>('list_labels')
+      set({ labels })
+    } catch (error) {
+      console.error('Failed to list labels:', error)
+    }
+  },
+
+  createLabel: async (name: string, color?: string) => {
+    const label = await invoke<Label>('create_label', { name, color })
+    await get().fetchLabels()
+    return label
+  },
+
+  deleteLabel: async (name: string) => {
+    const label = get().labels.find((l) => l.name === name)
+    await invoke('delete_label', { name })
+    const selected = get().selectedEmail
+    if (selected && label) {
+      set({ selectedEmail: { ...selected, labels: selected.labels.filter((k) => k !== label.keyword) } })
+    }
+    await get().fetchLabels()
+  },
+
+  applyLabel: async (emailId: string, label: string) => {
+    const applied = await invoke<Label>('apply_label', { emailId, label })
+    const selected = get().selectedEmail
+    if (selected?.id === emailId && !selected.labels.includes(applied.keyword)) {
+      set({ selectedEmail: { ...selected, labels: [...selected.labels, applied.keyword] } })
+    }
+    await get().fetchLabels()
+  },
+
+  removeLabel: async (emailId: string, label: string) => {
+    const keyword = get().labels.find((l) => l.name === label)?.keyword
+    await invoke('remove_label', { emailId, label })
+    const selected = get().selectedEmail
+    if (selected?.id === emailId && keyword) {
+      set({ selectedEmail: { ...selected, labels: selected.labels.filter((k) => k !== keyword) } })
+    }
+    await get().fetchLabels()
+  },
+
+  getEmailsByLabel: async (label: string, limit = 50, offset = 0) => {
+    return await invoke<EmailWithInsight[]>('get_emails_by_label', { label, limit, offset })
+  },
+
+  fetchInboxTabs: async (accountId?: string) => {
+    try {
+      const inboxTabs = await invoke<InboxTab[]>('get_inbox_tabs', { accountId })
+      set({ inboxTabs })
+    } catch (error) {
+      console.error('Failed to get inbox tabs:', error)
+    }
+  },
+
+  getInboxTabEmails: async (tab: InboxTabId, accountId?: string, limit = 50, offset = 0) => {
+    return await invoke<EmailWithInsight[]>('get_inbox_tab_emails', { tab, accountId, limit, offset })
+  },
+
+  // File an email into another folder (drag-and-drop, shortcuts)
+  moveEmail: async (emailId: string, targetFolder: string) => {
+    await invoke('move_email', { emailId, targetFolder })
+    set((state) => ({
+      emails: state.emails.filter((e) => e.id !== emailId),
+      selectedEmail: state.selectedEmail?.id === emailId ? null : state.selectedEmail,
+    }))
+    await get().fetchFolderStats()
+  },
+
+  createFolder: async (name: string) => {
+    await invoke('create_folder', { name })
+    await get().fetchFolderStats()
+  },
+
+  renameFolder: async (oldName: string, newName: string) => {
+    await invoke('rename_folder', { oldName, newName })
+    if (get().currentFolder === oldName) {
+      set({ currentFolder: newName, emails: [], selectedEmail: null })
+    }
+    await get().fetchFolderStats()
+  },
+
+  deleteFolder: async (name: string) => {
+    await invoke('delete_folder', { name })
+    if (get().currentFolder === name) {
+      set({ currentFolder: 'INBOX', emails: [], selectedEmail: null })
+    }
+    await get().fetchFolderStats()
+  },
+
+  setFolderRole: async (folder: string, role: FolderRole | null) => {
+    await invoke('set_folder_role', { folder, role })
+  },
+
+  resetFolderRole: async (folder: string) => {
+    await invoke('reset_folder_role', { folder })
+  },
+
+  // Search on the server; ids match cached emails, so results merge with local ones
+  searchServer: async (query: string, folder?: string, maxResults?: number) => {
+    return await invoke<EmailListItem[]>('search_server', { query, folder, maxResults })
+  },
 }))
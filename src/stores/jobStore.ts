@@ -0,0 +1,64 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/core'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+export type JobKind = 'indexing' | 'reindex' | 'reclassify' | 'embedding' | 'model_download' | 'sync'
+
+export type JobStatus = 'running' | 'paused' | 'completed' | 'failed' | 'cancelled'
+
+export interface JobInfo {
+    id: string
+    kind: JobKind
+    status: JobStatus
+    progress: number
+    processed: number | null
+    total: number | null
+    message: string | null
+    cancellable: boolean
+    started_at: number
+    updated_at: number
+}
+
+interface JobStore {
+    // State
+    jobs: JobInfo[]
+    error: string | null
+
+    // Actions
+    loadJobs: () => Promise<void>
+    cancelJob: (jobId: string) => Promise<void>
+    subscribe: () => Promise<UnlistenFn>
+}
+
+export const useJobStore = create<JobStore>((set) => ({
+    jobs: [],
+    error: null,
+
+    loadJobs: async () => {
+        try {
+            const jobs = await invoke<JobInfo[]>('list_jobs')
+            set({ jobs, error: null })
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+        }
+    },
+
+    cancelJob: async (jobId: string) => {
+        try {
+            await invoke('cancel_job', { jobId })
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+        }
+    },
+
+    // Keep `jobs` in sync with `job:update` events; call the returned function to stop
+    subscribe: async () => {
+        return listen<JobInfo>('job:update', (event) => {
+            const job = event.payload
+            set((state) => {
+                const others = state.jobs.filter((j) => j.id !== job.id)
+                return { jobs: [job, ...others].sort((a, b) => b.started_at - a.started_at) }
+            })
+        })
+    },
+}))
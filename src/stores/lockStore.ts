@@ -0,0 +1,91 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/core'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+export interface AppLockStatus {
+  enabled: boolean
+  locked: boolean
+  biometric: boolean
+  biometric_available: boolean
+  // 0 locks only on request
+  auto_lock_minutes: number
+  // Why the lock file couldn't be read; unlocking with the passphrase repairs it
+  damaged: string | null
+}
+
+interface LockStore {
+  status: AppLockStatus | null
+  error: string | null
+
+  fetchStatus: () => Promise<void>
+  // Follow `app:locked` / `app:unlocked`, including the idle auto-lock
+  subscribe: () => Promise<UnlistenFn>
+  lock: () => Promise<void>
+  // Without a passphrase the biometric prompt is used
+  unlock: (passphrase?: string) => Promise<boolean>
+  // Changing an existing lock takes the current passphrase
+  setLock: (
+    passphrase: string,
+    options: { currentPassphrase?: string; biometric?: boolean; autoLockMinutes?: number }
+  ) => Promise<void>
+  disableLock: (passphrase: string) => Promise<void>
+}
+
+export const useLockStore = create<LockStore>((set, get) => ({
+  status: null,
+  error: null,
+
+  fetchStatus: async () => {
+    try {
+      const status = await invoke<AppLockStatus>('get_app_lock_status')
+      set({ status })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  subscribe: async () => {
+    const unlistenLocked = await listen('app:locked', () => get().fetchStatus())
+    const unlistenUnlocked = await listen('app:unlocked', () => get().fetchStatus())
+    return () => {
+      unlistenLocked()
+      unlistenUnlocked()
+    }
+  },
+
+  lock: async () => {
+    try {
+      await invoke('lock_app')
+      await get().fetchStatus()
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  unlock: async (passphrase?: string) => {
+    try {
+      await invoke('unlock_app', { passphrase: passphrase ?? null })
+      set({ error: null })
+      await get().fetchStatus()
+      return true
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      return false
+    }
+  },
+
+  setLock: async (passphrase, options) => {
+    const status = await invoke<AppLockStatus>('set_app_lock', {
+      passphrase,
+      currentPassphrase: options.currentPassphrase ?? null,
+      biometric: options.biometric ?? false,
+      autoLockMinutes: options.autoLockMinutes ?? null,
+    })
+    set({ status, error: null })
+  },
+
+  disableLock: async (passphrase) => {
+    await invoke('disable_app_lock', { passphrase })
+    await get().fetchStatus()
+  },
+}))
@@ -1,14 +1,17 @@
 import { create } from 'zustand'
 import { invoke } from '@tauri-apps/api/core'
 import { listen, UnlistenFn } from '@tauri-apps/api/event'
+import type { AiWarmupStatus } from './aiStore'
 
 export interface EmbeddingStatus {
     is_embedding: boolean
+    is_paused: boolean
     total_emails: number
     embedded_emails: number
     current_model: string | null
     last_embedded_at: number | null
     error_message: string | null
+    last_processed_id: string | null
 }
 
 export interface SearchResult {
@@ -19,6 +22,45 @@ export interface SearchResult {
     snippet: string | null
 }
 
+// An email a chat answer was given as context, cited in the answer as [number]
+export interface ChatSource {
+    number: number
+    email_id: string
+    subject: string
+    from: string
+    relevance: number
+}
+
+export interface ChatAnswer {
+    answer: string
+    sources: ChatSource[]
+}
+
+export interface EmbeddingModelOption {
+    id: string
+    name: string
+    dimensions: number
+    size_mb: number
+    description: string
+    multilingual: boolean
+    pooling: 'mean' | 'cls'
+    query_prefix: string | null
+    passage_prefix: string | null
+}
+
+export interface EmbeddingModelInfo {
+    embedding_model: string
+    dimensions: number
+    count: number
+}
+
+// Payload of `embedding:model_mismatch`, emitted by init_rag when stored vectors are stale
+export interface EmbeddingModelMismatch {
+    current_model: string
+    dimensions: number
+    stale: EmbeddingModelInfo[]
+}
+
 export interface EmbeddingProgress {
     total: number
     embedded: number
@@ -29,6 +71,8 @@ interface RagStore {
     // State
     isInitialized: boolean
     isModelDownloaded: boolean
+    // Cross-encoder used to rerank chat context when `ai.rerank_results` is on
+    isRerankerDownloaded: boolean
     isEmbedding: boolean
     embeddingProgress: EmbeddingProgress | null
     embeddingStatus: EmbeddingStatus | null
@@ -41,20 +85,29 @@ interface RagStore {
     checkRagReady: () => Promise<boolean>
     checkModelDownloaded: () => Promise<boolean>
     downloadAndInitRag: () => Promise<boolean>
+    checkRerankerDownloaded: () => Promise<boolean>
+    downloadReranker: () => Promise<boolean>
     getEmbeddingStatus: () => Promise<void>
     embedAllEmails: () => Promise<number>
+    reembedAll: (modelId?: string) => Promise<number>
+    getEmbeddingModels: () => Promise<EmbeddingModelInfo[]>
+    getAvailableEmbeddingModels: () => Promise<EmbeddingModelOption[]>
+    setEmbeddingModel: (modelId: string) => Promise<number>
+    unloadEmbeddingModel: () => Promise<boolean>
     embedEmail: (emailId: string, subject: string, from: string, body: string) => Promise<void>
     searchSemantic: (query: string, limit?: number) => Promise<SearchResult[]>
     findSimilarEmails: (emailId: string, limit?: number) => Promise<SearchResult[]>
     getEmbeddedCount: () => Promise<number>
     clearEmbeddings: () => Promise<void>
-    chatWithContext: (query: string, limit?: number) => Promise<string>
+    chatWithContext: (query: string, limit?: number) => Promise<ChatAnswer>
+    subscribe: () => Promise<UnlistenFn>
     reset: () => void
 }
 
 export const useRagStore = create<RagStore>((set, get) => ({
     isInitialized: false,
     isModelDownloaded: false,
+    isRerankerDownloaded: false,
     isEmbedding: false,
     embeddingProgress: null,
     embeddingStatus: null,
@@ -91,6 +144,29 @@ export const useRagStore = create<RagStore>((set, get) => ({
         }
     },
 
+    checkRerankerDownloaded: async () => {
+        try {
+            const downloaded = await invoke<boolean>('is_reranker_downloaded')
+            set({ isRerankerDownloaded: downloaded })
+            return downloaded
+        } catch (error) {
+            console.error('Failed to check reranker model status:', error)
+            return false
+        }
+    },
+
+    downloadReranker: async () => {
+        try {
+            set({ error: null })
+            const success = await invoke<boolean>('download_reranker')
+            set({ isRerankerDownloaded: success })
+            return success
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+            return false
+        }
+    },
+
     initRag: async () => {
         try {
             set({ error: null })
@@ -170,6 +246,62 @@ export const useRagStore = create<RagStore>((set, get) => ({
         }
     },
 
+    reembedAll: async (modelId?: string) => {
+        try {
+            set({ isEmbedding: true, error: null })
+            const count = await invoke<number>('reembed_all', { modelId: modelId ?? null })
+            await get().getEmbeddingStatus()
+            return count
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+            throw error
+        } finally {
+            set({ isEmbedding: false, embeddingProgress: null })
+        }
+    },
+
+    unloadEmbeddingModel: async () => {
+        try {
+            return await invoke<boolean>('unload_embedding_model')
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+            throw error
+        }
+    },
+
+    getEmbeddingModels: async () => {
+        try {
+            return await invoke<EmbeddingModelInfo[]>('get_embedding_models')
+        } catch (error) {
+            console.error('Failed to get embedding models:', error)
+            return []
+        }
+    },
+
+    getAvailableEmbeddingModels: async () => {
+        try {
+            return await invoke<EmbeddingModelOption[]>('get_available_embedding_models')
+        } catch (error) {
+            console.error('Failed to get available embedding models:', error)
+            return []
+        }
+    },
+
+    // Switches models and re-embeds every email; search only sees the new model's vectors
+    setEmbeddingModel: async (modelId: string) => {
+        try {
+            set({ isEmbedding: true, error: null })
+            const count = await invoke<number>('set_embedding_model', { modelId })
+            await get().getEmbeddingStatus()
+            return count
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+            throw error
+        } finally {
+            set({ isEmbedding: false, embeddingProgress: null })
+        }
+    },
+
     embedEmail: async (emailId: string, subject: string, from: string, body: string) => {
         try {
             await invoke('embed_email', { emailId, subject, from, body })
@@ -224,13 +356,23 @@ export const useRagStore = create<RagStore>((set, get) => ({
     chatWithContext: async (query: string, limit = 5) => {
         try {
             set({ error: null })
-            return await invoke<string>('chat_with_context', { query, limit })
+            return await invoke<ChatAnswer>('chat_with_context', { query, limit })
         } catch (error) {
             set({ error: (error as Error).toString() })
-            return `Error: ${(error as Error).toString()}`
+            return { answer: `Error: ${(error as Error).toString()}`, sources: [] }
         }
     },
 
+    // Pick up the embedding engine loaded in the background at startup; call the returned function to stop
+    subscribe: async () => {
+        return listen<AiWarmupStatus>('ai:warmup', (event) => {
+            if (event.payload.component === 'rag' && event.payload.ready) {
+                set({ isInitialized: true, isModelDownloaded: true })
+                get().getEmbeddingStatus()
+            }
+        })
+    },
+
     reset: () => {
         set({
             isInitialized: false,
@@ -0,0 +1,157 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/core'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+import type { ComputeSettings, LlmSettings } from './aiStore'
+
+export interface CacheSettings {
+    cache_enabled: boolean
+    auto_sync_on_start: boolean
+    cache_media_assets: boolean
+    max_cache_age_days: number
+    auto_download_attachments_mb: number
+    collapse_duplicates: boolean
+    maintenance_interval_days: number
+}
+
+export interface SyncSettings {
+    polling_interval_minutes: number
+}
+
+export interface AiSettings {
+    model_id: string | null
+    load_on_startup: boolean
+    // ISO 639-3 code; summaries follow each email's language when null
+    summary_language: string | null
+    // Whisper model for dictation; the first downloaded one when null
+    speech_model_id: string | null
+    // Read text in image and scanned PDF attachments with Tesseract
+    ocr_enabled: boolean
+    // Tesseract language codes joined by '+', e.g. 'eng+deu'
+    ocr_languages: string
+    // Tesseract executable; looked up on the PATH when null
+    tesseract_path: string | null
+    // Reorder the best semantic matches for chat questions with a cross-encoder
+    rerank_results: boolean
+}
+
+export interface AuthSettings {
+    // Port the OAuth redirect is received on; 0 picks a free port
+    oauth_callback_port: number
+}
+
+export interface PrivacySettings {
+    // Look up tracked packages on the carriers' websites
+    check_shipment_status: boolean
+    // Show senders' domain logos, fetched from their servers, when they have no Gravatar
+    fetch_domain_logos: boolean
+}
+
+export interface AppSettings {
+    cache: CacheSettings
+    sync: SyncSettings
+    compute: ComputeSettings
+    llm: LlmSettings
+    ai: AiSettings
+    auth: AuthSettings
+    privacy: PrivacySettings
+}
+
+// What `import_app_config` changed
+export interface ConfigImportSummary {
+    accounts_added: number
+    // Addresses already set up here, left as they are
+    accounts_skipped: string[]
+    // Imported accounts that have to be signed in to again
+    needs_sign_in: string[]
+    categories_added: number
+    labels_added: number
+    sections_added: number
+    saved_searches_imported: number
+    remote_image_senders_added: number
+    settings_imported: boolean
+}
+
+// A backup archive written by `create_backup` or restored by `restore_backup`
+export interface BackupSummary {
+    path: string
+    created_at: number
+    app_version: string
+    file_count: number
+    total_bytes: number
+}
+
+// Payload of `backup:progress`
+export interface BackupProgress {
+    stage: 'emails' | 'vectors' | 'media' | 'verify' | 'extract'
+    done: number
+    total: number
+}
+
+type DeepPartial<T> = { [K in keyof T]?: T[K] extends object ? DeepPartial<T[K]> : T[K] }
+
+interface SettingsStore {
+    // State
+    settings: AppSettings | null
+    error: string | null
+
+    // Actions
+    loadSettings: () => Promise<AppSettings | null>
+    updateSettings: (patch: DeepPartial<AppSettings>) => Promise<void>
+    subscribe: () => Promise<UnlistenFn>
+    // Accounts (without credentials), categories, labels, sections, searches and settings
+    exportConfig: (path?: string) => Promise<string>
+    importConfig: (path: string) => Promise<ConfigImportSummary>
+    // Emails, embeddings and the media cache, as one zip
+    createBackup: (path?: string) => Promise<BackupSummary>
+    restoreBackup: (path: string) => Promise<BackupSummary>
+}
+
+export const useSettingsStore = create<SettingsStore>((set) => ({
+    settings: null,
+    error: null,
+
+    loadSettings: async () => {
+        try {
+            const settings = await invoke<AppSettings>('get_settings')
+            set({ settings, error: null })
+            return settings
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+            return null
+        }
+    },
+
+    // Only the given fields change; the rest are kept
+    updateSettings: async (patch) => {
+        try {
+            const settings = await invoke<AppSettings>('update_settings', { patch })
+            set({ settings, error: null })
+        } catch (error) {
+            set({ error: (error as Error).toString() })
+        }
+    },
+
+    // Keep `settings` in sync with `settings:changed` events; call the returned function to stop
+    subscribe: async () => {
+        return listen<AppSettings>('settings:changed', (event) => {
+            set({ settings: event.payload })
+        })
+    },
+
+    // Returns where the bundle was written; the data directory unless `path` is given
+    exportConfig: async (path) => {
+        return await invoke<string>('export_app_config', { path: path ?? null })
+    },
+
+    importConfig: async (path) => {
+        return await invoke<ConfigImportSummary>('import_app_config', { path })
+    },
+
+    createBackup: async (path) => {
+        return await invoke<BackupSummary>('create_backup', { path: path ?? null })
+    },
+
+    restoreBackup: async (path) => {
+        return await invoke<BackupSummary>('restore_backup', { path })
+    },
+}))
@@ -20,14 +20,242 @@ export interface EmailWithInsight {
   priority_score: number
   category: string | null
   summary: string | null
+  sentiment: string | null
+  // ISO 639-3 code, when it could be detected
+  language: string | null
+  // Pinned to the top of the smart inbox
+  pinned: boolean
+}
+
+// Saved filter behind a custom inbox section; unset fields match everything
+export interface InboxSectionFilter {
+  account_id?: string | null
+  folder?: string | null
+  category?: string | null
+  priority?: 'HIGH' | 'MEDIUM' | 'LOW' | null
+  // Part of the sender's address or name
+  from?: string | null
+  unread_only?: boolean
+  starred_only?: boolean
+  pinned_only?: boolean
+}
+
+export interface InboxSection {
+  id: number
+  name: string
+  filter: InboxSectionFilter
+  position: number
+}
+
+// Narrowing search operators can't express; unset fields match everything
+export interface SavedSearchFilters {
+  account_id?: string | null
+  category?: string | null
+  priority?: 'HIGH' | 'MEDIUM' | 'LOW' | null
+}
+
+export interface SavedSearch {
+  id: number
+  name: string
+  // Search text, with operators such as `from:` or `is:unread`
+  query: string
+  filters: SavedSearchFilters
+  created_at: number
+  // Set when listed with counts
+  unread_count: number | null
 }
 
 export interface IndexingStatus {
   is_indexing: boolean
+  is_paused: boolean
   total_emails: number
   processed_emails: number
   last_indexed_at: number | null
   error_message: string | null
+  last_processed_id: string | null
+}
+
+export interface AwaitingReplyEmail {
+  id: string
+  thread_id: string
+  subject: string
+  from_name: string
+  from_email: string
+  date: number
+  snippet: string
+  summary: string | null
+  reply_reminded_at: number | null
+}
+
+export interface PendingFollowup {
+  id: number
+  account_id: string
+  to_emails: string[]
+  subject: string
+  message_id: string
+  body_preview: string
+  expects_reply: boolean
+  sent_at: number
+  // Set when a delivery status notification reported the email as bounced
+  failed_recipients: string[]
+  delivery_error: string | null
+}
+
+export interface FollowupDraft {
+  sent_id: number
+  to: string[]
+  subject: string
+  body: string
+}
+
+// A meeting time proposed in an email (Unix timestamps)
+export interface MeetingSlot {
+  start: number
+  end: number | null
+}
+
+export interface MeetingProposals {
+  email_id: string
+  slots: MeetingSlot[]
+  duration_minutes: number | null
+}
+
+// A reply accepting a meeting slot, ready to open in the composer
+export interface SchedulingDraft {
+  email_id: string
+  to: string[]
+  subject: string
+  body: string
+}
+
+export interface ReplyReminderSettings {
+  enabled: boolean
+  after_days: number
+}
+
+export interface EmailLink {
+  url: string
+  text: string | null
+  domain: string
+  is_tracker: boolean
+}
+
+// A link someone sent, with the latest email it appeared in
+export interface SharedLink extends EmailLink {
+  email_id: string
+  subject: string
+  from_name: string
+  from_email: string
+  date: number
+}
+
+export interface LinkPreview {
+  title: string | null
+  description: string | null
+  favicon: string | null
+}
+
+export type AttachmentKind =
+  | 'image'
+  | 'document'
+  | 'spreadsheet'
+  | 'presentation'
+  | 'archive'
+  | 'audio'
+  | 'video'
+  | 'calendar'
+  | 'email'
+  | 'other'
+
+// Unset fields match everything; dates are Unix seconds, sizes bytes
+export interface AttachmentFilter {
+  account_id?: string | null
+  kind?: AttachmentKind | null
+  from?: string | null
+  filename?: string | null
+  after?: number | null
+  before?: number | null
+  min_size?: number | null
+  max_size?: number | null
+  limit?: number | null
+  offset?: number | null
+}
+
+export interface AttachmentItem {
+  email_id: string
+  position: number
+  filename: string
+  content_type: string
+  kind: AttachmentKind
+  size: number
+  // Set once the file is in the media cache
+  local_path: string | null
+  subject: string
+  from_name: string
+  from_email: string
+  date: number
+}
+
+// Payload of `attachment:progress`; byte counts are of the whole message
+export interface AttachmentProgress {
+  email_id: string
+  position: number
+  downloaded_bytes: number
+  total_bytes: number
+}
+
+// Total and unread emails in a priority, category or account
+export interface UnreadCount {
+  key: string
+  total: number
+  unread: number
+}
+
+export interface FolderCount {
+  account_id: string
+  folder: string
+  total: number
+  unread: number
+}
+
+export type ShipmentStatus = 'shipped' | 'in_transit' | 'out_for_delivery' | 'delivered' | 'exception'
+
+// A package found in a shipping notice
+export interface Shipment {
+  id: number
+  account_id: string
+  carrier: 'ups' | 'fedex' | 'usps' | 'dhl' | 'amazon' | 'royal_mail'
+  carrier_name: string
+  tracking_number: string
+  tracking_url: string
+  status: ShipmentStatus
+  status_at: number
+  // The most recent email about the package
+  email_id: string
+  subject: string
+  from_name: string
+  first_seen_at: number
+  // When the carrier's page was last checked, if the privacy settings allow it
+  checked_at: number | null
+}
+
+// Badge counts from `get_inbox_counts`
+export interface InboxCounts {
+  priorities: UnreadCount[]
+  categories: UnreadCount[]
+  accounts: UnreadCount[]
+  folders: FolderCount[]
+}
+
+// A page of a list with the size of the whole list
+export interface EmailPage {
+  emails: EmailWithInsight[]
+  total: number
+}
+
+// Payload of `emails:added`, `emails:updated`, `emails:removed` and `insights:updated`
+export interface CacheChangeEvent {
+  email_ids: string[]
 }
 
 export type CategoryBucket = 'important' | 'subscriptions' | 'newsletters' | 'promotions'
@@ -47,19 +275,110 @@ interface SmartInboxStore {
   indexingProgress: number
   selectedAccountId: string | null
   selectedCategory: CategoryBucket
+  awaitingReply: AwaitingReplyEmail[]
+  // Emails from the most recent `reply:reminder` event
+  replyReminders: AwaitingReplyEmail[]
+  // Sent emails nobody has answered yet
+  pendingFollowups: PendingFollowup[]
+  sharedLinks: SharedLink[]
+  attachments: AttachmentItem[]
+  shipments: Shipment[]
+  // Custom sections in display order
+  sections: InboxSection[]
+  savedSearches: SavedSearch[]
+  inboxCounts: InboxCounts | null
+  // Emails in the category last fetched with `getEmailsByCategory`, across all pages
+  categoryTotal: number | null
 
   // Actions
   fetchSmartInbox: (limit?: number, offset?: number) => Promise<void>
-  getEmailsByCategory: (category: string, limit?: number) => Promise<void>
+  getEmailsByCategory: (
+    category: string,
+    limit?: number,
+    language?: string,
+    offset?: number,
+    unreadOnly?: boolean
+  ) => Promise<void>
   searchEmails: (query: string, limit?: number) => Promise<void>
   getIndexingStatus: () => Promise<void>
   resetIndexingStatus: () => Promise<void>
+  forceResetAllStatuses: () => Promise<void>
   startIndexing: (maxEmails?: number) => Promise<void>
   initDatabase: () => Promise<void>
   setupIndexingListeners: () => Promise<() => void>
   setSelectedAccount: (accountId: string) => void
   setSelectedCategory: (category: CategoryBucket) => void
-  fetchEmailsByAccountAndCategory: (accountId: string, category: CategoryBucket, limit?: number) => Promise<void>
+  fetchEmailsByAccountAndCategory: (
+    accountId: string,
+    category: CategoryBucket,
+    limit?: number,
+    language?: string
+  ) => Promise<void>
+  fetchAwaitingReply: (minAgeDays?: number) => Promise<void>
+  dismissNeedsReply: (emailId: string) => Promise<void>
+  clearReplyReminders: () => void
+  getReplyReminderSettings: () => Promise<ReplyReminderSettings | null>
+  setReplyReminderSettings: (settings: ReplyReminderSettings) => Promise<void>
+  fetchPendingFollowups: (minAgeDays?: number, includeAll?: boolean) => Promise<void>
+  dismissFollowup: (sentId: number) => Promise<void>
+  draftFollowup: (sentId: number, taskId?: string) => Promise<FollowupDraft>
+  extractMeetingProposals: (emailId: string) => Promise<MeetingProposals>
+  draftSchedulingReply: (emailId: string, chosenSlot: MeetingSlot, taskId?: string) => Promise<SchedulingDraft>
+  fetchSharedLinks: (limit?: number) => Promise<void>
+  getEmailLinks: (emailId: string) => Promise<EmailLink[]>
+  getLinkPreview: (url: string) => Promise<LinkPreview | null>
+  fetchAttachments: (filter?: AttachmentFilter) => Promise<void>
+  downloadAttachment: (
+    emailId: string,
+    position: number,
+    onProgress?: (progress: AttachmentProgress) => void
+  ) => Promise<string | null>
+  fetchShipments: (includeDelivered?: boolean) => Promise<void>
+  pinEmail: (emailId: string, pinned: boolean) => Promise<void>
+  setPriorityOverride: (emailId: string, priority: 'HIGH' | 'MEDIUM' | 'LOW') => Promise<void>
+  fetchSections: () => Promise<void>
+  fetchInboxCounts: () => Promise<void>
+  createSection: (name: string, filter: InboxSectionFilter) => Promise<InboxSection>
+  updateSection: (id: number, name: string, filter: InboxSectionFilter) => Promise<void>
+  deleteSection: (id: number) => Promise<void>
+  reorderSections: (ids: number[]) => Promise<void>
+  getSectionEmails: (id: number, limit?: number, offset?: number) => Promise<EmailWithInsight[]>
+  saveSearch: (name: string, query: string, filters?: SavedSearchFilters) => Promise<SavedSearch>
+  fetchSavedSearches: (withCounts?: boolean) => Promise<void>
+  runSavedSearch: (id: number, limit?: number) => Promise<void>
+  deleteSavedSearch: (id: number) => Promise<void>
+}
+
+async function showDeliveryAlert(shipment: Shipment) {
+  if (!('Notification' in window)) return
+  if (Notification.permission === 'default') {
+    await Notification.requestPermission()
+  }
+  if (Notification.permission !== 'granted') return
+  const notification = new Notification('Out for delivery', {
+    body: `${shipment.carrier_name} ${shipment.tracking_number}: ${shipment.subject}`,
+    tag: `shipment-${shipment.id}`,
+  })
+  notification.onclick = () => {
+    window.focus()
+  }
+}
+
+let cacheRefreshTimer: ReturnType<typeof setTimeout> | null = null
+
+// Reload the current view at most once a second while the cache keeps changing
+function scheduleCacheRefresh() {
+  if (cacheRefreshTimer) return
+  cacheRefreshTimer = setTimeout(() => {
+    cacheRefreshTimer = null
+    const { selectedAccountId, selectedCategory, fetchEmailsByAccountAndCategory, fetchSmartInbox } =
+      useSmartInboxStore.getState()
+    const refresh = selectedAccountId
+      ? fetchEmailsByAccountAndCategory(selectedAccountId, selectedCategory)
+      : fetchSmartInbox()
+    refresh.catch((e) => console.error('[SmartInbox] Failed to refresh after cache change:', e))
+    useSmartInboxStore.getState().fetchInboxCounts()
+  }, 1000)
 }
 
 export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
@@ -70,6 +389,16 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
   indexingProgress: 0,
   selectedAccountId: null,
   selectedCategory: 'important',
+  awaitingReply: [],
+  replyReminders: [],
+  pendingFollowups: [],
+  sharedLinks: [],
+  attachments: [],
+  shipments: [],
+  sections: [],
+  savedSearches: [],
+  inboxCounts: null,
+  categoryTotal: null,
 
   initDatabase: async () => {
     try {
@@ -93,14 +422,17 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
     }
   },
 
-  getEmailsByCategory: async (category: string, limit = 500) => {
+  getEmailsByCategory: async (category: string, limit = 500, language?: string, offset = 0, unreadOnly = false) => {
     try {
       set({ loading: true, error: null })
-      const emails = await invoke<EmailWithInsight[]>('get_emails_by_category', {
+      const page = await invoke<EmailPage>('get_emails_by_category', {
         category,
+        language,
+        unreadOnly,
         limit,
+        offset,
       })
-      set({ emails, loading: false })
+      set({ emails: page.emails, categoryTotal: page.total, loading: false })
     } catch (error) {
       set({ error: (error as Error).toString(), loading: false })
     }
@@ -119,12 +451,18 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
     }
   },
 
-  fetchEmailsByAccountAndCategory: async (accountId: string, category: CategoryBucket, limit = 500) => {
+  fetchEmailsByAccountAndCategory: async (
+    accountId: string,
+    category: CategoryBucket,
+    limit = 500,
+    language?: string
+  ) => {
     try {
       set({ loading: true, error: null })
       const emails = await invoke<EmailWithInsight[]>('get_emails_by_account_and_category', {
         accountId,
         category,
+        language,
         limit,
       })
       set({ emails, loading: false })
@@ -165,6 +503,15 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
     }
   },
 
+  forceResetAllStatuses: async () => {
+    try {
+      await invoke('force_reset_all_statuses')
+      await get().getIndexingStatus()
+    } catch (error) {
+      console.error('Failed to reset job statuses:', error)
+    }
+  },
+
   startIndexing: async (maxEmails = 100) => {
     try {
       set({ error: null })
@@ -189,9 +536,97 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
     }
   },
 
+  fetchAwaitingReply: async (minAgeDays = 0) => {
+    try {
+      const emails = await invoke<AwaitingReplyEmail[]>('get_awaiting_reply', {
+        accountId: get().selectedAccountId,
+        minAgeDays,
+      })
+      set({ awaitingReply: emails })
+    } catch (error) {
+      console.error('Failed to get emails awaiting reply:', error)
+    }
+  },
+
+  dismissNeedsReply: async (emailId: string) => {
+    try {
+      await invoke('set_needs_reply', { emailId, needsReply: false })
+      set({
+        awaitingReply: get().awaitingReply.filter((e) => e.id !== emailId),
+        replyReminders: get().replyReminders.filter((e) => e.id !== emailId),
+      })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  clearReplyReminders: () => set({ replyReminders: [] }),
+
+  getReplyReminderSettings: async () => {
+    try {
+      return await invoke<ReplyReminderSettings>('get_reply_reminder_settings')
+    } catch (error) {
+      console.error('Failed to get reply reminder settings:', error)
+      return null
+    }
+  },
+
+  setReplyReminderSettings: async (settings: ReplyReminderSettings) => {
+    await invoke('set_reply_reminder_settings', { settings })
+  },
+
+  fetchPendingFollowups: async (minAgeDays = 3, includeAll = false) => {
+    try {
+      const followups = await invoke<PendingFollowup[]>('get_pending_followups', {
+        accountId: get().selectedAccountId,
+        minAgeDays,
+        includeAll,
+      })
+      set({ pendingFollowups: followups })
+    } catch (error) {
+      console.error('Failed to get pending follow-ups:', error)
+    }
+  },
+
+  dismissFollowup: async (sentId: number) => {
+    try {
+      await invoke('dismiss_followup', { sentId })
+      set({ pendingFollowups: get().pendingFollowups.filter((f) => f.id !== sentId) })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  // Streams `compose:token` while drafting; open the result in the composer with `followupOf`
+  draftFollowup: async (sentId: number, taskId?: string) => {
+    return await invoke<FollowupDraft>('draft_followup', { sentId, taskId })
+  },
+
+  extractMeetingProposals: async (emailId: string) => {
+    return await invoke<MeetingProposals>('extract_meeting_proposals', { emailId })
+  },
+
+  // Streams `compose:token` like `draftFollowup`
+  draftSchedulingReply: async (emailId: string, chosenSlot: MeetingSlot, taskId?: string) => {
+    return await invoke<SchedulingDraft>('draft_scheduling_reply', { emailId, chosenSlot, taskId })
+  },
+
   setupIndexingListeners: async () => {
     const unlisteners: UnlistenFn[] = []
 
+    // Follow-up reminders for emails still awaiting a reply
+    const reminderUnlisten = await listen<AwaitingReplyEmail[]>('reply:reminder', (event) => {
+      set({ replyReminders: event.payload })
+    })
+    unlisteners.push(reminderUnlisten)
+
+    // Packages that just went out for delivery
+    const deliveryUnlisten = await listen<Shipment[]>('shipment:out_for_delivery', (event) => {
+      event.payload.forEach(showDeliveryAlert)
+      get().fetchShipments()
+    })
+    unlisteners.push(deliveryUnlisten)
+
     // Listen for indexing started
     const startedUnlisten = await listen('indexing:started', () => {
       set({ indexingProgress: 0 })
@@ -249,9 +684,193 @@ export const useSmartInboxStore = create<SmartInboxStore>((set, get) => ({
     })
     unlisteners.push(errorUnlisten)
 
+    // Follow writes to the cache: removed emails leave the list at once, and the list
+    // reloads when new mail arrives or emails it shows change
+    const removedUnlisten = await listen<CacheChangeEvent>('emails:removed', (event) => {
+      const removed = new Set(event.payload.email_ids)
+      set({ emails: get().emails.filter((email) => !removed.has(email.id)) })
+    })
+    unlisteners.push(removedUnlisten)
+    const addedUnlisten = await listen<CacheChangeEvent>('emails:added', () => {
+      scheduleCacheRefresh()
+    })
+    unlisteners.push(addedUnlisten)
+    for (const name of ['emails:updated', 'insights:updated']) {
+      const changedUnlisten = await listen<CacheChangeEvent>(name, (event) => {
+        const shown = new Set(get().emails.map((email) => email.id))
+        if (event.payload.email_ids.some((id) => shown.has(id))) {
+          scheduleCacheRefresh()
+        }
+      })
+      unlisteners.push(changedUnlisten)
+    }
+
     // Return cleanup function
     return () => {
       unlisteners.forEach((fn) => fn())
     }
   },
+
+  fetchSharedLinks: async (limit = 100) => {
+    try {
+      const links = await invoke<SharedLink[]>('get_shared_links', {
+        accountId: get().selectedAccountId,
+        limit,
+      })
+      set({ sharedLinks: links })
+    } catch (error) {
+      console.error('Failed to fetch shared links:', error)
+    }
+  },
+
+  getEmailLinks: async (emailId: string) => {
+    return await invoke<EmailLink[]>('get_email_links', { emailId })
+  },
+
+  getLinkPreview: async (url: string) => {
+    try {
+      return await invoke<LinkPreview>('get_link_preview', { url })
+    } catch (error) {
+      console.warn('Failed to get link preview:', error)
+      return null
+    }
+  },
+
+  fetchAttachments: async (filter: AttachmentFilter = {}) => {
+    try {
+      const attachments = await invoke<AttachmentItem[]>('list_all_attachments', {
+        filter: { account_id: get().selectedAccountId, ...filter },
+      })
+      set({ attachments })
+    } catch (error) {
+      console.error('Failed to fetch attachments:', error)
+    }
+  },
+
+  downloadAttachment: async (emailId, position, onProgress) => {
+    const unlisten = await listen<AttachmentProgress>('attachment:progress', (event) => {
+      if (event.payload.email_id === emailId && event.payload.position === position) {
+        onProgress?.(event.payload)
+      }
+    })
+    try {
+      const path = await invoke<string>('download_attachment', { emailId, position })
+      set((state) => ({
+        attachments: state.attachments.map((a) =>
+          a.email_id === emailId && a.position === position ? { ...a, local_path: path } : a
+        ),
+      }))
+      return path
+    } catch (error) {
+      console.error('Failed to download attachment:', error)
+      return null
+    } finally {
+      unlisten()
+    }
+  },
+
+  fetchShipments: async (includeDelivered = false) => {
+    try {
+      const shipments = await invoke<Shipment[]>('get_shipments', {
+        accountId: get().selectedAccountId,
+        includeDelivered,
+      })
+      set({ shipments })
+    } catch (error) {
+      console.error('Failed to fetch shipments:', error)
+    }
+  },
+
+  pinEmail: async (emailId: string, pinned: boolean) => {
+    try {
+      await invoke('pin_email', { emailId, pinned })
+      set({ emails: get().emails.map((e) => (e.id === emailId ? { ...e, pinned } : e)) })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  setPriorityOverride: async (emailId: string, priority: 'HIGH' | 'MEDIUM' | 'LOW') => {
+    try {
+      await invoke('set_email_priority_override', { emailId, priority })
+      set({ emails: get().emails.map((e) => (e.id === emailId ? { ...e, priority } : e)) })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  fetchSections: async () => {
+    try {
+      const sections = await invoke<InboxSection[]>('get_inbox_sections')
+      set({ sections })
+    } catch (error) {
+      console.error('Failed to fetch inbox sections:', error)
+    }
+  },
+
+  fetchInboxCounts: async () => {
+    try {
+      const inboxCounts = await invoke<InboxCounts>('get_inbox_counts', {
+        accountId: get().selectedAccountId,
+      })
+      set({ inboxCounts })
+    } catch (error) {
+      console.error('Failed to fetch inbox counts:', error)
+    }
+  },
+
+  createSection: async (name: string, filter: InboxSectionFilter) => {
+    const section = await invoke<InboxSection>('create_inbox_section', { name, filter })
+    set({ sections: [...get().sections, section] })
+    return section
+  },
+
+  updateSection: async (id: number, name: string, filter: InboxSectionFilter) => {
+    await invoke('update_inbox_section', { id, name, filter })
+    await get().fetchSections()
+  },
+
+  deleteSection: async (id: number) => {
+    await invoke('delete_inbox_section', { id })
+    set({ sections: get().sections.filter((s) => s.id !== id) })
+  },
+
+  reorderSections: async (ids: number[]) => {
+    await invoke('reorder_inbox_sections', { ids })
+    await get().fetchSections()
+  },
+
+  getSectionEmails: async (id: number, limit = 50, offset = 0) => {
+    return await invoke<EmailWithInsight[]>('get_inbox_section_emails', { id, limit, offset })
+  },
+
+  saveSearch: async (name: string, query: string, filters?: SavedSearchFilters) => {
+    const search = await invoke<SavedSearch>('save_search', { name, query, filters: filters ?? null })
+    await get().fetchSavedSearches()
+    return search
+  },
+
+  fetchSavedSearches: async (withCounts = true) => {
+    try {
+      const savedSearches = await invoke<SavedSearch[]>('list_saved_searches', { withCounts })
+      set({ savedSearches })
+    } catch (error) {
+      console.error('Failed to fetch saved searches:', error)
+    }
+  },
+
+  runSavedSearch: async (id: number, limit = 500) => {
+    try {
+      set({ loading: true, error: null })
+      const emails = await invoke<EmailWithInsight[]>('run_saved_search', { id, limit })
+      set({ emails, loading: false })
+    } catch (error) {
+      set({ error: (error as Error).toString(), loading: false })
+    }
+  },
+
+  deleteSavedSearch: async (id: number) => {
+    await invoke('delete_saved_search', { id })
+    set({ savedSearches: get().savedSearches.filter((s) => s.id !== id) })
+  },
 }))
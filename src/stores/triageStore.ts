@@ -0,0 +1,110 @@
+import { create } from 'zustand'
+import { invoke } from '@tauri-apps/api/core'
+
+// Which emails a triage session goes through; INBOX of all accounts by default
+export interface TriageFilter {
+  account_id?: string | null
+  folder?: string | null
+  unread_only?: boolean
+  category?: string | null
+}
+
+export interface TriageItem {
+  id: string
+  account_id: string
+  subject: string
+  from_name: string
+  from_email: string
+  date: number
+  snippet: string
+  is_read: boolean
+  is_starred: boolean
+  has_attachments: boolean
+  priority: string | null
+  category: string | null
+}
+
+export interface TriageStats {
+  session_id: number
+  started_at: number
+  ended_at: number | null
+  queued: number
+  processed: number
+  archived: number
+  trashed: number
+  snoozed: number
+  kept: number
+  duration_secs: number
+}
+
+export type TriageAction = 'archive' | 'trash' | 'snooze' | 'keep'
+
+interface TriageStore {
+  sessionId: number | null
+  queue: TriageItem[]
+  // Index of the email being decided on
+  position: number
+  stats: TriageStats | null
+  error: string | null
+
+  start: (filter?: TriageFilter) => Promise<void>
+  // Apply an action to the current email and move to the next; snoozeUntil is Unix seconds
+  act: (action: TriageAction, snoozeUntil?: number) => Promise<void>
+  end: () => Promise<TriageStats | null>
+}
+
+export const useTriageStore = create<TriageStore>((set, get) => ({
+  sessionId: null,
+  queue: [],
+  position: 0,
+  stats: null,
+  error: null,
+
+  start: async (filter?: TriageFilter) => {
+    try {
+      const session = await invoke<{ session_id: number; items: TriageItem[] }>(
+        'start_triage_session',
+        { filter: filter ?? null }
+      )
+      set({
+        sessionId: session.session_id,
+        queue: session.items,
+        position: 0,
+        stats: null,
+        error: null,
+      })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  act: async (action: TriageAction, snoozeUntil?: number) => {
+    const { sessionId, queue, position } = get()
+    const item = queue[position]
+    if (sessionId === null || !item) return
+    try {
+      const stats = await invoke<TriageStats>('triage_action', {
+        sessionId,
+        emailId: item.id,
+        action,
+        snoozeUntil: snoozeUntil ?? null,
+      })
+      set({ stats, position: position + 1, error: null })
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+    }
+  },
+
+  end: async () => {
+    const { sessionId } = get()
+    if (sessionId === null) return null
+    try {
+      const stats = await invoke<TriageStats>('end_triage_session', { sessionId })
+      set({ sessionId: null, queue: [], position: 0, stats })
+      return stats
+    } catch (error) {
+      set({ error: (error as Error).toString() })
+      return null
+    }
+  },
+}))